@@ -0,0 +1,404 @@
+use std::convert::TryFrom;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use futures_channel::mpsc;
+use futures_util::{AsyncWriteExt as _, StreamExt as _};
+use ironrdp::codecs::rfx::image_processing::PixelFormat;
+use ironrdp::input::fast_path::{FastPathInput, FastPathInputEvent};
+use ironrdp::input::mouse::{ButtonEvents, MovementEvents, WheelEvents};
+use ironrdp::input::MousePdu;
+use ironrdp::{LimitsConfig, PduParsing, Rectangle};
+use ironrdp_session::image::DecodedImage;
+use ironrdp_session::{
+    process_connection_sequence, ActiveStageOutput, ActiveStageProcessor, ClientInfoConfig, CodecRegistry,
+    ConnectionSequenceResult, DiagnosticsRecorder, DomCodeMapper, ErasedWriter, FramedReader, InputConfig,
+    InputEventSender, KeyEvent, Modifiers, RdpError, ServerCertificate, UpgradedStream, GLOBAL_CHANNEL_NAME,
+    USER_CHANNEL_NAME,
+};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::{Clamped, JsCast};
+use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement, ImageData, KeyboardEvent};
+
+use crate::websocket::WebSocketStream;
+
+type TlsStream = futures_rustls::client::TlsStream<WebSocketStream>;
+
+enum SessionMessage {
+    Inputs(FastPathInput),
+    ResponseFrame(Vec<u8>),
+}
+
+/// The connection whose frames are processed once the canvas is given to [`Session::run`].
+struct Connection {
+    reader: FramedReader,
+    writer: ErasedWriter,
+    message_rx: mpsc::UnboundedReceiver<SessionMessage>,
+    processor: ActiveStageProcessor,
+    desktop_width: u16,
+    desktop_height: u16,
+}
+
+/// A session connected through a WebSocket proxy, whose desktop is drawn on a canvas.
+#[wasm_bindgen]
+pub struct Session {
+    message_tx: mpsc::UnboundedSender<SessionMessage>,
+    connection: Option<Connection>,
+    keyboard: InputEventSender<DomCodeMapper>,
+    left_button_down: bool,
+    desktop_width: u16,
+    desktop_height: u16,
+}
+
+/// Connects to the RDP server through the WebSocket proxy at `address`, e.g. `wss://proxy.example.com/rdp`,
+/// which relays the binary messages to the TCP connection of the server. The TLS upgrade and CredSSP
+/// are done by the client, the proxy never seeing the credentials.
+#[wasm_bindgen]
+pub async fn connect(
+    username: String,
+    password: String,
+    address: String,
+    width: u16,
+    height: u16,
+) -> Result<Session, String> {
+    let input_config = build_input_config(username, password, width, height);
+
+    let stream = WebSocketStream::connect(&address).await?;
+    // the address of the server is only known by the proxy
+    let routing_addr = SocketAddr::from(([0, 0, 0, 0], 0));
+
+    let (connection_sequence_result, reader, writer) =
+        process_connection_sequence(stream, &routing_addr, &input_config, upgrade_to_tls)
+            .await
+            .map_err(|e| e.to_string())?;
+
+    Ok(Session::new(reader, writer, input_config, connection_sequence_result))
+}
+
+#[wasm_bindgen]
+impl Session {
+    fn new(
+        reader: FramedReader,
+        writer: ErasedWriter,
+        input_config: InputConfig,
+        connection_sequence_result: ConnectionSequenceResult,
+    ) -> Self {
+        let (message_tx, message_rx) = mpsc::unbounded();
+        let desktop_width = connection_sequence_result.desktop_size.width;
+        let desktop_height = connection_sequence_result.desktop_size.height;
+
+        Self {
+            message_tx,
+            connection: Some(Connection {
+                reader,
+                writer,
+                message_rx,
+                processor: ActiveStageProcessor::new(input_config, connection_sequence_result),
+                desktop_width,
+                desktop_height,
+            }),
+            keyboard: InputEventSender::new(DomCodeMapper),
+            left_button_down: false,
+            desktop_width,
+            desktop_height,
+        }
+    }
+
+    /// The width of the desktop negotiated with the server.
+    pub fn desktop_width(&self) -> u16 {
+        self.desktop_width
+    }
+
+    /// The height of the desktop negotiated with the server.
+    pub fn desktop_height(&self) -> u16 {
+        self.desktop_height
+    }
+
+    /// Starts processing the frames of the server, the updated regions of the desktop being drawn on the canvas
+    /// in RGBA. The canvas is resized to the desktop.
+    pub fn run(&mut self, canvas: HtmlCanvasElement) -> Result<(), String> {
+        let connection = self.connection.take().ok_or("The session is already running")?;
+        let context = canvas
+            .get_context("2d")
+            .ok()
+            .flatten()
+            .and_then(|context| context.dyn_into::<CanvasRenderingContext2d>().ok())
+            .ok_or("Failed to get the 2D context of the canvas")?;
+
+        let message_tx = self.message_tx.clone();
+        wasm_bindgen_futures::spawn_local(async move {
+            let Connection {
+                reader,
+                writer,
+                message_rx,
+                processor,
+                desktop_width,
+                desktop_height,
+            } = connection;
+            let image = DecodedImage::new(PixelFormat::RgbA32, u32::from(desktop_width), u32::from(desktop_height));
+
+            wasm_bindgen_futures::spawn_local(async move {
+                if let Err(e) = write_messages(message_rx, writer).await {
+                    web_sys::console::error_1(&format!("Failed to send to the server: {}", e).into());
+                }
+            });
+
+            if let Err(e) = process_frames(reader, processor, image, &canvas, &context, message_tx).await {
+                web_sys::console::error_1(&format!("RDP session terminated: {}", e).into());
+            }
+        });
+
+        Ok(())
+    }
+
+    /// Moves the mouse, pressing or releasing the left button if its state has changed.
+    pub fn update_mouse(&mut self, mouse_x: u16, mouse_y: u16, left_click: bool) -> Result<(), String> {
+        let mouse_event = |movement_events, button_events| {
+            FastPathInputEvent::MouseEvent(MousePdu {
+                wheel_events: WheelEvents::empty(),
+                movement_events,
+                button_events,
+                number_of_wheel_rotations: 0,
+                x_position: mouse_x,
+                y_position: mouse_y,
+            })
+        };
+
+        let mut inputs = vec![mouse_event(MovementEvents::MOVE, ButtonEvents::empty())];
+        match (left_click, self.left_button_down) {
+            (true, false) => inputs.push(mouse_event(
+                MovementEvents::empty(),
+                ButtonEvents::DOWN | ButtonEvents::LEFT_BUTTON,
+            )),
+            (false, true) => inputs.push(mouse_event(MovementEvents::empty(), ButtonEvents::LEFT_BUTTON)),
+            _ => (),
+        }
+        self.left_button_down = left_click;
+
+        self.send(SessionMessage::Inputs(FastPathInput(inputs)))
+    }
+
+    /// Sends the key of a `keydown` or `keyup` event, identified by its physical code.
+    pub fn update_keyboard(&mut self, event: &KeyboardEvent, pressed: bool) -> Result<(), String> {
+        let mut modifiers = Modifiers::empty();
+        modifiers.set(Modifiers::SHIFT, event.shift_key());
+        modifiers.set(Modifiers::CONTROL, event.ctrl_key());
+        modifiers.set(Modifiers::ALT, event.alt_key());
+        modifiers.set(Modifiers::META, event.meta_key());
+
+        // the key is the produced character, if it is a single one, or the name of the key otherwise
+        let key = event.key();
+        let mut characters = key.chars();
+        let text = match (characters.next(), characters.next()) {
+            (Some(character), None) => Some(character),
+            _ => None,
+        };
+
+        let inputs = self.keyboard.key_event(&KeyEvent {
+            key: event.code(),
+            pressed,
+            repeat: event.repeat(),
+            modifiers,
+            text,
+        });
+        if inputs.is_empty() {
+            return Ok(());
+        }
+
+        self.send(SessionMessage::Inputs(FastPathInput(inputs)))
+    }
+
+    /// Closes the connection to the server.
+    pub fn shutdown(&self) {
+        self.message_tx.close_channel();
+    }
+
+    fn send(&self, message: SessionMessage) -> Result<(), String> {
+        self.message_tx
+            .unbounded_send(message)
+            .map_err(|_| String::from("The session is terminated"))
+    }
+}
+
+async fn process_frames(
+    mut reader: FramedReader,
+    mut processor: ActiveStageProcessor,
+    mut image: DecodedImage,
+    canvas: &HtmlCanvasElement,
+    context: &CanvasRenderingContext2d,
+    message_tx: mpsc::UnboundedSender<SessionMessage>,
+) -> Result<(), String> {
+    loop {
+        let frame = reader
+            .read_frame()
+            .await
+            .map_err(|e| e.to_string())?
+            .ok_or("The server has closed the connection")?;
+
+        for output in processor.process(&mut image, frame).await.map_err(|e| e.to_string())? {
+            match output {
+                ActiveStageOutput::ResponseFrame(frame) => {
+                    if message_tx
+                        .unbounded_send(SessionMessage::ResponseFrame(frame.to_vec()))
+                        .is_err()
+                    {
+                        return Ok(());
+                    }
+                }
+                ActiveStageOutput::GraphicsUpdate(region) => draw(canvas, context, &image, &region)?,
+                ActiveStageOutput::Terminate => return Ok(()),
+                // the state of the remote keyboard and session is not displayed
+                _ => (),
+            }
+        }
+    }
+}
+
+async fn write_messages(
+    mut message_rx: mpsc::UnboundedReceiver<SessionMessage>,
+    mut writer: ErasedWriter,
+) -> io::Result<()> {
+    while let Some(message) = message_rx.next().await {
+        let frame = match message {
+            SessionMessage::ResponseFrame(frame) => frame,
+            SessionMessage::Inputs(inputs) => {
+                let mut frame = Vec::with_capacity(inputs.buffer_length());
+                inputs
+                    .to_buffer(&mut frame)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+                frame
+            }
+        };
+
+        writer.write_all(&frame).await?;
+        writer.flush().await?;
+    }
+
+    writer.close().await
+}
+
+/// Copies the region of the image to the canvas, resized first if the desktop has been resized.
+fn draw(
+    canvas: &HtmlCanvasElement,
+    context: &CanvasRenderingContext2d,
+    image: &DecodedImage,
+    region: &Rectangle,
+) -> Result<(), String> {
+    if canvas.width() != image.width() || canvas.height() != image.height() {
+        canvas.set_width(image.width());
+        canvas.set_height(image.height());
+    }
+
+    let region = match region.intersect(&Rectangle {
+        left: 0,
+        top: 0,
+        right: image.width(),
+        bottom: image.height(),
+    }) {
+        Some(region) => region,
+        None => return Ok(()),
+    };
+
+    let pixel_size = usize::from(image.pixel_format().bytes_per_pixel());
+    let image_stride = usize::try_from(image.width()).unwrap() * pixel_size;
+    let region_stride = usize::try_from(region.width()).unwrap() * pixel_size;
+    let region_left = usize::try_from(region.left).unwrap() * pixel_size;
+
+    let mut pixels = Vec::with_capacity(region_stride * usize::try_from(region.height()).unwrap());
+    for row in region.top..region.bottom {
+        let start = usize::try_from(row).unwrap() * image_stride + region_left;
+        pixels.extend_from_slice(&image.data()[start..start + region_stride]);
+    }
+
+    let image_data = ImageData::new_with_u8_clamped_array_and_sh(Clamped(&pixels), region.width(), region.height())
+        .map_err(|e| format!("Failed to create the image data: {:?}", e))?;
+    context
+        .put_image_data(&image_data, f64::from(region.left), f64::from(region.top))
+        .map_err(|e| format!("Failed to draw on the canvas: {:?}", e))
+}
+
+async fn upgrade_to_tls(stream: WebSocketStream) -> Result<UpgradedStream<TlsStream>, RdpError> {
+    let client_config = rustls::ClientConfig::builder()
+        .with_safe_defaults()
+        .with_custom_certificate_verifier(Arc::new(danger::NoCertificateVerification))
+        .with_no_client_auth();
+    // the name of the server is not known, the certificate not being verified
+    let server_name = rustls::ServerName::try_from("stub_string").unwrap();
+
+    let mut tls_stream = futures_rustls::TlsConnector::from(Arc::new(client_config))
+        .connect(server_name, stream)
+        .await?;
+    tls_stream.flush().await?;
+
+    let server_certificate = tls_stream
+        .get_ref()
+        .1
+        .peer_certificates()
+        .and_then(|certificates| certificates.first())
+        .ok_or(RdpError::MissingPeerCertificate)?;
+    let server_certificate = ServerCertificate::from_der(server_certificate.as_ref())?;
+
+    Ok(UpgradedStream::new(tls_stream, server_certificate))
+}
+
+fn build_input_config(username: String, password: String, width: u16, height: u16) -> InputConfig {
+    InputConfig {
+        credentials: sspi::AuthIdentity {
+            username,
+            password,
+            domain: None,
+        },
+        security_protocol: ironrdp::nego::SecurityProtocol::HYBRID_EX,
+        encryption_methods: ironrdp::gcc::EncryptionMethod::empty(),
+        keyboard_type: ironrdp::gcc::KeyboardType::IbmEnhanced,
+        keyboard_subtype: 0,
+        keyboard_functional_keys_count: 12,
+        ime_file_name: String::new(),
+        client_name: None,
+        client_build: None,
+        dig_product_id: None,
+        width,
+        height,
+        monitors: Vec::new(),
+        global_channel_name: GLOBAL_CHANNEL_NAME,
+        user_channel_name: USER_CHANNEL_NAME,
+        graphics_config: None,
+        codecs: CodecRegistry::default(),
+        limits: LimitsConfig::default(),
+        audio_playback: false,
+        drive_redirection: false,
+        smart_card_redirection: false,
+        client_info: ClientInfoConfig::default(),
+        redirection_credentials: None,
+        routing_token: None,
+        redirected_session_id: None,
+        gateway: None,
+        output_pixel_format: PixelFormat::RgbA32,
+        diagnostics: DiagnosticsRecorder::default(),
+        license_store: None,
+    }
+}
+
+mod danger {
+    use std::time::SystemTime;
+
+    use rustls::client::{ServerCertVerified, ServerCertVerifier};
+    use rustls::{Certificate, Error, ServerName};
+
+    pub struct NoCertificateVerification;
+
+    impl ServerCertVerifier for NoCertificateVerification {
+        fn verify_server_cert(
+            &self,
+            _end_entity: &Certificate,
+            _intermediates: &[Certificate],
+            _server_name: &ServerName,
+            _scts: &mut dyn Iterator<Item = &[u8]>,
+            _ocsp_response: &[u8],
+            _now: SystemTime,
+        ) -> Result<ServerCertVerified, Error> {
+            Ok(ServerCertVerified::assertion())
+        }
+    }
+}
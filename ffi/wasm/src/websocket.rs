@@ -0,0 +1,146 @@
+use std::cell::RefCell;
+use std::io;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use futures_channel::{mpsc, oneshot};
+use futures_util::{AsyncRead, AsyncWrite, StreamExt as _};
+use js_sys::{ArrayBuffer, Uint8Array};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{BinaryType, Event, MessageEvent, WebSocket};
+
+/// Byte stream relayed over a WebSocket, whose binary messages are forwarded by the proxy
+/// to the TCP connection of the RDP server and back, as websockify does.
+///
+/// The WebSocket itself is driven by a local task, so that the stream can be sent to the session.
+pub struct WebSocketStream {
+    incoming: mpsc::UnboundedReceiver<io::Result<Vec<u8>>>,
+    outgoing: mpsc::UnboundedSender<Vec<u8>>,
+    received: Vec<u8>,
+    position: usize,
+}
+
+impl WebSocketStream {
+    /// Opens the WebSocket, failing if the proxy refuses it.
+    pub async fn connect(url: &str) -> Result<Self, String> {
+        let socket = WebSocket::new(url).map_err(|e| format!("Invalid WebSocket URL {}: {:?}", url, e))?;
+        socket.set_binary_type(BinaryType::Arraybuffer);
+
+        let (incoming_tx, incoming) = mpsc::unbounded();
+        // resolved with `false` if the WebSocket fails before being opened
+        let (opened_tx, opened_rx) = oneshot::channel();
+        let opened_tx = Rc::new(RefCell::new(Some(opened_tx)));
+        let notify_opened = move |opened: bool| {
+            if let Some(opened_tx) = opened_tx.borrow_mut().take() {
+                let _ = opened_tx.send(opened);
+            }
+        };
+
+        let on_message = {
+            let incoming_tx = incoming_tx.clone();
+            Closure::<dyn FnMut(MessageEvent)>::new(move |event: MessageEvent| {
+                if let Ok(data) = event.data().dyn_into::<ArrayBuffer>() {
+                    let _ = incoming_tx.unbounded_send(Ok(Uint8Array::new(&data).to_vec()));
+                }
+            })
+        };
+        let on_open = {
+            let notify_opened = notify_opened.clone();
+            Closure::<dyn FnMut(Event)>::new(move |_: Event| notify_opened(true))
+        };
+        let on_error = {
+            let incoming_tx = incoming_tx.clone();
+            let notify_opened = notify_opened.clone();
+            Closure::<dyn FnMut(Event)>::new(move |_: Event| {
+                notify_opened(false);
+                let _ =
+                    incoming_tx.unbounded_send(Err(io::Error::new(io::ErrorKind::ConnectionReset, "WebSocket error")));
+                incoming_tx.close_channel();
+            })
+        };
+        let on_close = Closure::<dyn FnMut(Event)>::new(move |_: Event| {
+            notify_opened(false);
+            incoming_tx.close_channel();
+        });
+
+        socket.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+        socket.set_onopen(Some(on_open.as_ref().unchecked_ref()));
+        socket.set_onerror(Some(on_error.as_ref().unchecked_ref()));
+        socket.set_onclose(Some(on_close.as_ref().unchecked_ref()));
+
+        if opened_rx.await != Ok(true) {
+            return Err(format!("Failed to open the WebSocket to {}", url));
+        }
+
+        let (outgoing, mut outgoing_rx) = mpsc::unbounded::<Vec<u8>>();
+        wasm_bindgen_futures::spawn_local(async move {
+            while let Some(data) = outgoing_rx.next().await {
+                if socket.send_with_u8_array(&data).is_err() {
+                    break;
+                }
+            }
+
+            socket.set_onmessage(None);
+            socket.set_onopen(None);
+            socket.set_onerror(None);
+            socket.set_onclose(None);
+            let _ = socket.close();
+
+            // the handlers live as long as the WebSocket is used
+            drop((on_message, on_open, on_error, on_close));
+        });
+
+        Ok(Self {
+            incoming,
+            outgoing,
+            received: Vec::new(),
+            position: 0,
+        })
+    }
+}
+
+impl AsyncRead for WebSocketStream {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        while self.position == self.received.len() {
+            match self.incoming.poll_next_unpin(cx) {
+                Poll::Ready(Some(Ok(data))) => {
+                    self.received = data;
+                    self.position = 0;
+                }
+                Poll::Ready(Some(Err(e))) => return Poll::Ready(Err(e)),
+                Poll::Ready(None) => return Poll::Ready(Ok(0)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+
+        let length = buf.len().min(self.received.len() - self.position);
+        buf[..length].copy_from_slice(&self.received[self.position..self.position + length]);
+        self.position += length;
+
+        Poll::Ready(Ok(length))
+    }
+}
+
+impl AsyncWrite for WebSocketStream {
+    fn poll_write(self: Pin<&mut Self>, _: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let result = self
+            .outgoing
+            .unbounded_send(buf.to_vec())
+            .map(|_| buf.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "WebSocket closed"));
+
+        Poll::Ready(result)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.outgoing.close_channel();
+
+        Poll::Ready(Ok(()))
+    }
+}
@@ -0,0 +1,18 @@
+#![no_main]
+
+use arbitrary::Arbitrary;
+use ironrdp::dvc::{ClientPdu, ServerPdu};
+use libfuzzer_sys::fuzz_target;
+
+#[derive(Debug, Arbitrary)]
+struct Input<'a> {
+    // The size announced by the virtual channel PDU, which does not have to match the data received
+    dvc_data_size: usize,
+    data: &'a [u8],
+}
+
+fuzz_target!(|input: Input<'_>| {
+    let _ = ServerPdu::from_buffer(input.data, input.dvc_data_size);
+    let _ = ServerPdu::from_buffer(input.data, input.data.len());
+    let _ = ClientPdu::from_buffer(input.data, input.dvc_data_size);
+});
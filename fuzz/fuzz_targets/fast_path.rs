@@ -0,0 +1,26 @@
+#![no_main]
+
+use arbitrary::Arbitrary;
+use ironrdp::fast_path::{FastPathHeader, FastPathUpdate, FastPathUpdatePdu, UpdateCode};
+use ironrdp::{PduBufferParsing, PduParsing};
+use libfuzzer_sys::fuzz_target;
+use num_traits::FromPrimitive;
+
+#[derive(Debug, Arbitrary)]
+struct Input<'a> {
+    update_code: u8,
+    data: &'a [u8],
+}
+
+fuzz_target!(|input: Input<'_>| {
+    let mut frame = input.data;
+    if FastPathHeader::from_buffer(&mut frame).is_ok() {
+        if let Ok(update_pdu) = FastPathUpdatePdu::from_buffer(frame) {
+            let _ = FastPathUpdate::from_buffer_with_code(update_pdu.data, update_pdu.update_code);
+        }
+    }
+
+    if let Some(update_code) = UpdateCode::from_u8(input.update_code) {
+        let _ = FastPathUpdate::from_buffer_with_code(input.data, update_code);
+    }
+});
@@ -0,0 +1,10 @@
+#![no_main]
+
+use ironrdp::gcc::{ConferenceCreateResponse, ServerGccBlocks};
+use ironrdp::PduParsing;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = ConferenceCreateResponse::from_buffer(data);
+    let _ = ServerGccBlocks::from_buffer(data);
+});
@@ -0,0 +1,17 @@
+#![no_main]
+
+use ironrdp::dvc::gfx::zgfx::Decompressor;
+use ironrdp::dvc::gfx::{Avc420BitmapStream, Avc444BitmapStream, ServerPdu};
+use ironrdp::{PduBufferParsing, PduParsing};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = ServerPdu::from_buffer(data);
+    let _ = Avc420BitmapStream::from_buffer(data);
+    let _ = Avc444BitmapStream::from_buffer(data);
+
+    let mut decompressed = Vec::new();
+    if Decompressor::new().decompress(data, &mut decompressed).is_ok() {
+        let _ = ServerPdu::from_buffer(decompressed.as_slice());
+    }
+});
@@ -0,0 +1,9 @@
+#![no_main]
+
+use ironrdp::{ConnectResponse, LimitsConfig, McsPdu, PduParsing};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = McsPdu::from_buffer(data);
+    let _ = ConnectResponse::from_buffer_with_limits(data, &LimitsConfig::default());
+});
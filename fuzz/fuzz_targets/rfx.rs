@@ -0,0 +1,38 @@
+#![no_main]
+
+use arbitrary::Arbitrary;
+use ironrdp::codecs::rfx::{self, EntropyAlgorithm, FrameBeginPdu, FrameEndPdu, Headers, RegionPdu, TileSetPdu};
+use ironrdp::PduBufferParsing;
+use libfuzzer_sys::fuzz_target;
+
+const TILE_COEFFICIENTS: usize = 64 * 64;
+
+#[derive(Debug, Arbitrary)]
+struct Input<'a> {
+    rlgr3: bool,
+    data: &'a [u8],
+}
+
+fuzz_target!(|input: Input<'_>| {
+    let entropy_algorithm = if input.rlgr3 {
+        EntropyAlgorithm::Rlgr3
+    } else {
+        EntropyAlgorithm::Rlgr1
+    };
+
+    let _ = Headers::from_buffer(input.data);
+    let _ = FrameBeginPdu::from_buffer(input.data);
+    let _ = RegionPdu::from_buffer(input.data);
+    let _ = FrameEndPdu::from_buffer(input.data);
+
+    let mut coefficients = vec![0; TILE_COEFFICIENTS];
+    if let Ok(tile_set) = TileSetPdu::from_buffer(input.data) {
+        for tile in &tile_set.tiles {
+            for component in [tile.y_data, tile.cb_data, tile.cr_data] {
+                let _ = rfx::rlgr::decode(tile_set.entropy_algorithm, component, &mut coefficients);
+            }
+        }
+    }
+
+    let _ = rfx::rlgr::decode(entropy_algorithm, input.data, &mut coefficients);
+});
@@ -5,33 +5,27 @@ use ironrdp_session::image::DecodedImage;
 use ironrdp::codecs::rfx::image_processing::PixelFormat;
 use bytes::BytesMut;
 use core::future::Future;
-use ironrdp::input::fast_path::FastPathInput;
+use ironrdp::input::fast_path::{FastPathInput, SynchronizeFlags};
 use ironrdp_session::{ErasedWriter, FramedReader};
 use ironrdp_session::ConnectionSequenceResult;
-use ironrdp_session::InputConfig;
-use ironrdp::Rectangle;
-use ironrdp_session::{process_connection_sequence, ActiveStageOutput, ActiveStageProcessor, RdpError, UpgradedStream};
+use ironrdp_session::{
+    ClientInfoConfig, CodecRegistry, DiagnosticsRecorder, InputConfig, GLOBAL_CHANNEL_NAME, USER_CHANNEL_NAME,
+};
+use ironrdp::{LimitsConfig, Rectangle};
+use ironrdp_session::connector::{self, ConnectTimeouts, TlsVerification};
+use ironrdp_session::{ActiveStageOutput, ActiveStageProcessor, RdpError};
+use ironrdp_session::{DomCodeMapper, InputEventSender, KeyEvent, Modifiers};
 use serde::Serialize;
 use sspi::AuthIdentity;
 use std::collections::HashMap;
-use std::io;
-use std::net::SocketAddr;
-use std::str::FromStr;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Mutex;
 use tauri::{Manager as _, State};
-use tokio::io::AsyncWriteExt as _;
-use tokio::net::{TcpListener, TcpStream};
+use tokio::net::TcpListener;
 use tokio::sync::mpsc;
-use tokio_util::compat::TokioAsyncReadCompatExt as _;
-use x509_parser::prelude::{FromDer as _, X509Certificate};
 
 const DEFAULT_WIDTH: u16 = 1280;
 const DEFAULT_HEIGHT: u16 = 720;
-const GLOBAL_CHANNEL_NAME: &str = "GLOBAL";
-const USER_CHANNEL_NAME: &str = "USER";
-
-type TlsStream = tokio_util::compat::Compat<tokio_rustls::client::TlsStream<TcpStream>>;
 
 fn main() {
     tauri::Builder::default()
@@ -39,7 +33,9 @@ fn main() {
             close_splashscreen,
             init,
             connect,
-            update_mouse
+            update_mouse,
+            update_keyboard,
+            update_focus
         ])
         .setup(|app| {
             if let Some(splashscreen) = app.get_window("splashscreen") {
@@ -115,6 +111,34 @@ impl SessionManager {
         }
     }
 
+    fn key_event(
+        &self,
+        session_id: usize,
+        event: &KeyEvent<String>,
+    ) -> anyhow::Result<Vec<ironrdp::input::fast_path::FastPathInputEvent>> {
+        let mut sessions = self.sessions.lock().unwrap();
+        let session = sessions.get_mut(&session_id).context("session not found")?;
+
+        Ok(session.keyboard.key_event(event))
+    }
+
+    fn focus_event(
+        &self,
+        session_id: usize,
+        focused: bool,
+        toggle_keys: SynchronizeFlags,
+        modifiers: Modifiers,
+    ) -> anyhow::Result<Vec<ironrdp::input::fast_path::FastPathInputEvent>> {
+        let mut sessions = self.sessions.lock().unwrap();
+        let session = sessions.get_mut(&session_id).context("session not found")?;
+
+        if focused {
+            Ok(session.keyboard.focus_in(toggle_keys, modifiers))
+        } else {
+            Ok(session.keyboard.focus_out())
+        }
+    }
+
     fn register_session(&self, session: Session) -> usize {
         let session_id = self.next_session_id.fetch_add(1, Ordering::SeqCst);
         self.sessions.lock().unwrap().insert(session_id, session);
@@ -129,6 +153,7 @@ type MessageReceiver = mpsc::UnboundedReceiver<SessionMessage>;
 struct Session {
     msg_tx: MessageSender,
     was_down: bool,
+    keyboard: InputEventSender<DomCodeMapper>,
 }
 
 impl Session {
@@ -137,6 +162,7 @@ impl Session {
         let session = Self {
             msg_tx: tx,
             was_down: false,
+            keyboard: InputEventSender::new(DomCodeMapper),
         };
         (session, rx)
     }
@@ -226,6 +252,85 @@ async fn update_mouse(
     Ok(())
 }
 
+#[tauri::command]
+#[allow(non_snake_case, clippy::too_many_arguments)]
+async fn update_keyboard(
+    sessionId: usize,
+    code: String,
+    pressed: bool,
+    repeat: bool,
+    shiftKey: bool,
+    ctrlKey: bool,
+    altKey: bool,
+    metaKey: bool,
+    text: Option<char>,
+    session_manager: State<'_, SessionManager>,
+) -> Result<(), String> {
+    let mut modifiers = Modifiers::empty();
+    modifiers.set(Modifiers::SHIFT, shiftKey);
+    modifiers.set(Modifiers::CONTROL, ctrlKey);
+    modifiers.set(Modifiers::ALT, altKey);
+    modifiers.set(Modifiers::META, metaKey);
+
+    let event = KeyEvent {
+        key: code,
+        pressed,
+        repeat,
+        modifiers,
+        text,
+    };
+    let inputs = session_manager
+        .key_event(sessionId, &event)
+        .map_err(|e| e.to_string())?;
+
+    if !inputs.is_empty() {
+        session_manager
+            .send_message(sessionId, SessionMessage::Inputs(FastPathInput(inputs)))
+            .map_err(|e| e.to_string())?;
+    }
+
+    Ok(())
+}
+
+/// Called when the window gains or loses the focus, for no key to stay stuck on the server after Alt+Tab.
+#[tauri::command]
+#[allow(non_snake_case, clippy::too_many_arguments)]
+async fn update_focus(
+    sessionId: usize,
+    focused: bool,
+    capsLock: bool,
+    numLock: bool,
+    scrollLock: bool,
+    shiftKey: bool,
+    ctrlKey: bool,
+    altKey: bool,
+    metaKey: bool,
+    session_manager: State<'_, SessionManager>,
+) -> Result<(), String> {
+    let mut toggle_keys = SynchronizeFlags::empty();
+    toggle_keys.set(SynchronizeFlags::FASTPATH_INPUT_SYNC_CAPS_LOCK, capsLock);
+    toggle_keys.set(SynchronizeFlags::FASTPATH_INPUT_SYNC_NUM_LOCK, numLock);
+    toggle_keys.set(SynchronizeFlags::FASTPATH_INPUT_SYNC_SCROLL_LOCK, scrollLock);
+
+    let mut modifiers = Modifiers::empty();
+    modifiers.set(Modifiers::SHIFT, shiftKey);
+    modifiers.set(Modifiers::CONTROL, ctrlKey);
+    modifiers.set(Modifiers::ALT, altKey);
+    modifiers.set(Modifiers::META, metaKey);
+
+    let inputs = session_manager
+        .focus_event(sessionId, focused, toggle_keys, modifiers)
+        .map_err(|e| e.to_string())?;
+
+    if !inputs.is_empty() {
+        session_manager
+            .send_message(sessionId, SessionMessage::Inputs(FastPathInput(inputs)))
+            .map_err(|e| e.to_string())?;
+    }
+
+    Ok(())
+}
+
 #[tauri::command]
 async fn connect(
     username: String,
@@ -233,19 +338,17 @@ async fn connect(
     address: String,
     session_manager: State<'_, SessionManager>,
 ) -> Result<NewSessionInfo, String> {
-    let input_config = build_input_config(username, password, None);
-
-    let address = SocketAddr::from_str(&address).unwrap();
+    let mut input_config = build_input_config(username, password, None);
 
     println!("Connect to RDP host");
 
-    let tcp_stream = TcpStream::connect(&address)
-        .await
-        .map_err(RdpError::ConnectionError)
-        .map_err(|e| e.to_string())?;
-
     let (connection_sequence_result, rdp_reader, rdp_writer) =
-        process_connection_sequence(tcp_stream.compat(), &address, &input_config, establish_tls)
+        connector::connect(
+            &address,
+            &mut input_config,
+            &TlsVerification::Skip,
+            ConnectTimeouts::default(),
+        )
             .await
             .map_err(|e| e.to_string())?;
 
@@ -290,16 +393,33 @@ fn build_input_config(username: String, password: String, domain: Option<String>
             domain,
         },
         security_protocol: ironrdp::nego::SecurityProtocol::HYBRID_EX,
+        encryption_methods: ironrdp::gcc::EncryptionMethod::empty(),
         keyboard_type: ironrdp::gcc::KeyboardType::IbmEnhanced,
         keyboard_subtype: 0,
         keyboard_functional_keys_count: 12,
         ime_file_name: String::new(),
-        dig_product_id: String::new(),
+        client_name: None,
+        client_build: None,
+        dig_product_id: None,
         width: DEFAULT_WIDTH,
         height: DEFAULT_HEIGHT,
-        global_channel_name: GLOBAL_CHANNEL_NAME.to_owned(),
-        user_channel_name: USER_CHANNEL_NAME.to_owned(),
+        monitors: Vec::new(),
+        global_channel_name: GLOBAL_CHANNEL_NAME,
+        user_channel_name: USER_CHANNEL_NAME,
         graphics_config: None,
+        codecs: CodecRegistry::default(),
+        limits: LimitsConfig::default(),
+        audio_playback: false,
+        drive_redirection: false,
+        smart_card_redirection: false,
+        client_info: ClientInfoConfig::default(),
+        redirection_credentials: None,
+        routing_token: None,
+        redirected_session_id: None,
+        gateway: None,
+        output_pixel_format: PixelFormat::RgbA32,
+        diagnostics: DiagnosticsRecorder::default(),
+        license_store: None,
     }
 }
 
@@ -393,6 +513,21 @@ async fn rdp_session_task(
 
                     frame_id += 1;
                 }
+                ActiveStageOutput::FrameMetadata(_)
+                | ActiveStageOutput::GraphicsFrame(_)
+                | ActiveStageOutput::Pointer(_) => (),
+                ActiveStageOutput::KeyboardIndicators(led_flags) => {
+                    println!("Remote keyboard indicators changed: {:?}", led_flags);
+                }
+                ActiveStageOutput::KeyboardImeStatus(ime_status) => {
+                    println!("Remote keyboard IME status changed: {:?}", ime_status);
+                }
+                ActiveStageOutput::SessionLockState(lock_state) => {
+                    println!("Remote session lock state changed: {:?}", lock_state);
+                }
+                ActiveStageOutput::Desynchronized { skipped_bytes } => {
+                    println!("Skipped {} bytes to resynchronize on the next frame", skipped_bytes);
+                }
                 ActiveStageOutput::Terminate => break 'outer,
             }
         }
@@ -408,10 +543,10 @@ fn extract_partial_image(image: &DecodedImage, region: &Rectangle) -> Vec<u8> {
 
     let image_width = usize::try_from(image.width()).unwrap();
 
-    let region_top = usize::from(region.top);
-    let region_left = usize::from(region.left);
-    let region_width = usize::from(region.width());
-    let region_height = usize::from(region.height());
+    let region_top = usize::try_from(region.top).unwrap();
+    let region_left = usize::try_from(region.left).unwrap();
+    let region_width = usize::try_from(region.width()).unwrap();
+    let region_height = usize::try_from(region.height()).unwrap();
 
     let dst_buf_size = region_width * region_height * pixel_size;
     let mut dst = vec![0; dst_buf_size];
@@ -462,72 +597,6 @@ async fn send_update_rectangle(
     Ok(())
 }
 
-// TODO: this can be refactored in a separate `ironrdp-tls` crate (all native clients will do the same TLS dance)
-async fn establish_tls(stream: tokio_util::compat::Compat<TcpStream>) -> Result<UpgradedStream<TlsStream>, RdpError> {
-    let stream = stream.into_inner();
-
-    let mut tls_stream = {
-        let mut client_config = rustls::client::ClientConfig::builder()
-            .with_safe_defaults()
-            .with_custom_certificate_verifier(std::sync::Arc::new(danger::NoCertificateVerification))
-            .with_no_client_auth();
-        // This adds support for the SSLKEYLOGFILE env variable (https://wiki.wireshark.org/TLS#using-the-pre-master-secret)
-        client_config.key_log = std::sync::Arc::new(rustls::KeyLogFile::new());
-        let rc_config = std::sync::Arc::new(client_config);
-        let example_com = "stub_string".try_into().unwrap();
-        let connector = tokio_rustls::TlsConnector::from(rc_config);
-        connector.connect(example_com, stream).await?
-    };
-
-    tls_stream.flush().await?;
-
-    let server_public_key = {
-        let cert = tls_stream
-            .get_ref()
-            .1
-            .peer_certificates()
-            .ok_or(RdpError::MissingPeerCertificate)?[0]
-            .as_ref();
-        get_tls_peer_pubkey(cert.to_vec())?
-    };
-
-    Ok(UpgradedStream {
-        stream: tls_stream.compat(),
-        server_public_key,
-    })
-}
-
-pub fn get_tls_peer_pubkey(cert: Vec<u8>) -> io::Result<Vec<u8>> {
-    let res = X509Certificate::from_der(&cert[..])
-        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Invalid der certificate."))?;
-    let public_key = res.1.tbs_certificate.subject_pki.subject_public_key;
-
-    Ok(public_key.data.to_vec())
-}
-
-mod danger {
-    use std::time::SystemTime;
-
-    use rustls::client::ServerCertVerified;
-    use rustls::{Certificate, Error, ServerName};
-
-    pub struct NoCertificateVerification;
-
-    impl rustls::client::ServerCertVerifier for NoCertificateVerification {
-        fn verify_server_cert(
-            &self,
-            _end_entity: &Certificate,
-            _intermediates: &[Certificate],
-            _server_name: &ServerName,
-            _scts: &mut dyn Iterator<Item = &[u8]>,
-            _ocsp_response: &[u8],
-            _now: SystemTime,
-        ) -> Result<ServerCertVerified, Error> {
-            Ok(rustls::client::ServerCertVerified::assertion())
-        }
-    }
-}
-
 fn spawn_task<F, T>(task: F)
 where
     F: Future<Output = anyhow::Result<T>> + Send + 'static,
@@ -27,15 +27,14 @@ export class LoginComponent {
   connect() {
     this.snackBar.open('Connection in progress...', '', {duration: 1000});
 
-    this.serverBridge.connect(this.form.value.username as string, this.form.value.password as string, this.form.value.host as string).subscribe(start_info => {
-      if (start_info.websocket_port && start_info.websocket_port > 0) {
+    this.serverBridge.connect(this.form.value.username as string, this.form.value.password as string, this.form.value.host as string).subscribe({
+      next: start_info => {
         this.snackBar.open('success', '', {duration: 1000});
         this.currentSession.sessionId = start_info.session_id;
         this.currentSession.desktopSize = start_info.initial_desktop_size;
         this.currentSession.active = true;
-      } else {
-        this.snackBar.open('failure', '', {duration: 1000});
-      }
+      },
+      error: () => this.snackBar.open('failure', '', {duration: 1000})
     })
   }
 }
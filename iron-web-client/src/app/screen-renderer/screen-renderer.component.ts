@@ -28,6 +28,7 @@ export class ScreenRendererComponent implements OnInit, AfterViewInit {
   ngAfterViewInit() {
     this.canvas = document.getElementById("renderer") as HTMLCanvasElement;
     this.canvasCtx = this.canvas?.getContext("2d", { alpha: false });
+    this.serverService.attachCanvas(this.canvas);
 
     this.sessionService.currentSession$.subscribe(session => {
       this.currentSession = session;
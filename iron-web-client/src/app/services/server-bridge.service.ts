@@ -39,5 +39,7 @@ export abstract class ServerBridgeService {
   abstract updateImage: Observable<any>;
 
   abstract updateMouse(mouse_x: number, mouse_y: number, click_state: number): void;
+
+  abstract attachCanvas(canvas: HTMLCanvasElement): void;
 }
 
@@ -41,6 +41,9 @@ export class TauriBridgeService implements ServerBridgeService {
     invoke("update_mouse", {sessionId: 0, mouseX, mouseY, leftClick});
   }
 
+  attachCanvas(canvas: HTMLCanvasElement) {
+  }
+
   async onSocketMessage(event: any) {
       if (typeof event.data === "string") {
         this.lastImageInformations = event.data;
@@ -1,7 +1,10 @@
 import {Injectable} from "@angular/core";
 import {NewSessionInfo, ServerBridgeService} from "./server-bridge.service";
 import * as IronWasm from "../../assets/pkg/ironrdp";
-import {Observable, of, Subject} from "rxjs";
+import {from, map, Observable, Subject} from "rxjs";
+
+const DEFAULT_WIDTH = 1280;
+const DEFAULT_HEIGHT = 720;
 
 @Injectable()
 export class WasmBridgeService implements ServerBridgeService {
@@ -10,6 +13,9 @@ export class WasmBridgeService implements ServerBridgeService {
   private _resize: Subject<any> = new Subject<any>();
   private _updateImage: Subject<any> = new Subject<any>();
 
+  private session?: IronWasm.Session;
+  private canvas?: HTMLCanvasElement;
+
   resize: Observable<any>;
   updateImage: Observable<any>;
 
@@ -22,24 +28,46 @@ export class WasmBridgeService implements ServerBridgeService {
     this.wasmBridge.init();
   }
 
-  // connect(username: string, password: string, address: string): Observable<number> {
-  //   this.wasmBridge.connect(username, password, address);
-  //   return of(0);
-  // }
+  // The address is the URL of the WebSocket proxy relaying the TCP connection to the server, e.g. wss://proxy/rdp
+  connect(username: string, password: string, address: string): Observable<NewSessionInfo> {
+    return from(this.wasmBridge.connect(username, password, address, DEFAULT_WIDTH, DEFAULT_HEIGHT)).pipe(map((session: IronWasm.Session) => {
+      this.session = session;
+      this.run();
 
-  updateMouse(mouse_x: number, mouse_y: number, click_state: number): void {
+      return {
+        session_id: 0,
+        initial_desktop_size: {
+          height: session.desktop_height(),
+          width: session.desktop_width()
+        },
+        websocket_port: 0
+      };
+    }));
   }
 
-  connect(username: string, password: string, address: string): Observable<NewSessionInfo> {
-    this.wasmBridge.connect(username, password, address);
-    return of({
-      session_id: 0,
-      initial_desktop_size: {
-        height: 0,
-        width: 0
-      },
-      websocket_port: 0
-    });
-    ;
+  attachCanvas(canvas: HTMLCanvasElement): void {
+    this.canvas = canvas;
+    this.canvas.tabIndex = 0;
+    this.canvas.addEventListener("keydown", (event: KeyboardEvent) => this.updateKeyboard(event, true));
+    this.canvas.addEventListener("keyup", (event: KeyboardEvent) => this.updateKeyboard(event, false));
+    this.run();
+  }
+
+  updateMouse(mouseX: number, mouseY: number, clickState: number): void {
+    this.session?.update_mouse(mouseX, mouseY, clickState !== 0);
+  }
+
+  private updateKeyboard(event: KeyboardEvent, pressed: boolean) {
+    if (this.session) {
+      event.preventDefault();
+      this.session.update_keyboard(event, pressed);
+    }
+  }
+
+  // The frames are drawn by the session once both the session and the canvas are available
+  private run() {
+    if (this.session && this.canvas) {
+      this.session.run(this.canvas);
+    }
   }
 }
@@ -1,33 +1,72 @@
-use std::{net::SocketAddr, num::ParseIntError};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::{num::ParseIntError, time::Duration};
 
 use clap::{clap_derive::ValueEnum, crate_name, Parser};
-use ironrdp_session::{GraphicsConfig, InputConfig};
+use ironrdp::codecs::rfx::image_processing::PixelFormat;
+use ironrdp::LimitsConfig;
+use ironrdp_session::connector::{ConnectTimeouts, TlsVerification};
+use ironrdp_session::transport::GatewayConfig;
+use ironrdp_session::{
+    ClientInfoConfig, CoalescingConfig, CodecRegistry, DiagnosticsRecorder, DirectoryLicenseStore, GraphicsConfig,
+    InputConfig, LicenseStore, MonitorConfig, RedirectionCredentials, DEFAULT_MAX_PDUS, GLOBAL_CHANNEL_NAME,
+    USER_CHANNEL_NAME,
+};
 use sspi::AuthIdentity;
 
+use crate::export::ImageFormat;
+use crate::screenshot::ScreenshotConfig;
+use crate::soak::SoakConfig;
+
 const DEFAULT_WIDTH: u16 = 1920;
 const DEFAULT_HEIGHT: u16 = 1080;
-const GLOBAL_CHANNEL_NAME: &str = "GLOBAL";
-const USER_CHANNEL_NAME: &str = "USER";
 
 pub struct Config {
     pub log_file: String,
-    pub routing_addr: SocketAddr,
+    pub server_addr: String,
+    pub connect_timeouts: ConnectTimeouts,
+    pub tls_verification: TlsVerification,
+    pub frame_interval: Duration,
+    pub ack_coalescing: Option<CoalescingConfig>,
+    /// Disconnect right after the MCS connect and report the duration of each phase.
+    pub probe: bool,
+    pub soak: Option<SoakConfig>,
+    pub screenshots: Option<ScreenshotConfig>,
+    /// The file the diagnostics of the connection are written to when it fails.
+    pub diagnostics_file: Option<PathBuf>,
     pub input: InputConfig,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
 enum SecurityProtocol {
+    /// Standard RDP Security, without TLS, for the servers not supporting it
+    Rdp,
     Ssl,
     Hybrid,
     HybridEx,
+    Rdstls,
 }
 
 impl SecurityProtocol {
     fn parse(security_protocol: SecurityProtocol) -> ironrdp::nego::SecurityProtocol {
         match security_protocol {
+            SecurityProtocol::Rdp => ironrdp::nego::SecurityProtocol::RDP,
             SecurityProtocol::Ssl => ironrdp::nego::SecurityProtocol::SSL,
             SecurityProtocol::Hybrid => ironrdp::nego::SecurityProtocol::HYBRID,
             SecurityProtocol::HybridEx => ironrdp::nego::SecurityProtocol::HYBRID_EX,
+            SecurityProtocol::Rdstls => ironrdp::nego::SecurityProtocol::RDSTLS,
+        }
+    }
+
+    /// The encryption methods of Standard RDP Security, the other protocols relying on TLS instead.
+    fn encryption_methods(security_protocol: SecurityProtocol) -> ironrdp::gcc::EncryptionMethod {
+        match security_protocol {
+            SecurityProtocol::Rdp => {
+                ironrdp::gcc::EncryptionMethod::BIT_40
+                    | ironrdp::gcc::EncryptionMethod::BIT_56
+                    | ironrdp::gcc::EncryptionMethod::BIT_128
+            }
+            _ => ironrdp::gcc::EncryptionMethod::empty(),
         }
     }
 }
@@ -57,6 +96,76 @@ impl KeyboardType {
     }
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+enum Compression {
+    Mppc8k,
+    Mppc64k,
+}
+
+impl Compression {
+    fn parse(compression: Compression) -> ironrdp::rdp::CompressionType {
+        match compression {
+            Compression::Mppc8k => ironrdp::rdp::CompressionType::K8,
+            Compression::Mppc64k => ironrdp::rdp::CompressionType::K64,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+enum ScreenshotFormat {
+    Png,
+    Jpeg,
+    RawBgra,
+}
+
+impl ScreenshotFormat {
+    fn parse(screenshot_format: ScreenshotFormat) -> ImageFormat {
+        match screenshot_format {
+            ScreenshotFormat::Png => ImageFormat::Png,
+            ScreenshotFormat::Jpeg => ImageFormat::Jpeg,
+            ScreenshotFormat::RawBgra => ImageFormat::RawBgra,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct RootCertificate(Vec<u8>);
+
+#[derive(Debug, Clone)]
+struct PublicKey(Vec<u8>);
+
+#[derive(Debug, Clone)]
+struct RedirectionBlob(Vec<u8>);
+
+fn read_root_certificate(path: &str) -> Result<RootCertificate, String> {
+    std::fs::read(path)
+        .map(RootCertificate)
+        .map_err(|e| format!("Failed to read the root certificate {}: {}", path, e))
+}
+
+fn parse_public_key(input: &str) -> Result<PublicKey, String> {
+    decode_hex(input)
+        .map(PublicKey)
+        .ok_or_else(|| String::from("The public key is not an even number of hexadecimal digits"))
+}
+
+fn parse_redirection_blob(input: &str) -> Result<RedirectionBlob, String> {
+    decode_hex(input)
+        .map(RedirectionBlob)
+        .ok_or_else(|| String::from("The value is not an even number of hexadecimal digits"))
+}
+
+fn decode_hex(input: &str) -> Option<Vec<u8>> {
+    if input.len() % 2 != 0 {
+        return None;
+    }
+
+    (0..input.len())
+        .step_by(2)
+        .map(|i| input.get(i..i + 2).and_then(|byte| u8::from_str_radix(byte, 16).ok()))
+        .collect()
+}
+
 fn parse_hex(input: &str) -> Result<u32, ParseIntError> {
     if input.starts_with("0x") {
         u32::from_str_radix(input.get(2..).unwrap_or(""), 16)
@@ -73,9 +182,26 @@ struct Args {
     #[clap(short, long, value_parser, default_value_t = format!("{}.log", crate_name!()))]
     log_file: String,
 
-    /// An address on which the client will connect. Format: <ip>:<port>
-    #[clap(value_parser = is_socket_address)]
-    addr: SocketAddr,
+    /// An address on which the client will connect. Format: <host>:<port>
+    #[clap(value_parser = is_server_address)]
+    addr: String,
+
+    /// The timeout in seconds of each connection step: DNS resolution, TCP connection and TLS handshake
+    #[clap(long, value_parser, default_value_t = 10)]
+    connect_timeout: u64,
+
+    /// Verify the certificate of the server against the root certificates of the system.
+    /// By default, any certificate is accepted
+    #[clap(long, group = "tls_verification")]
+    verify_certificate: bool,
+
+    /// Verify the certificate of the server against the DER-encoded root certificate file. Can be repeated.
+    #[clap(long = "root-certificate", value_parser = read_root_certificate, group = "tls_verification")]
+    root_certificates: Vec<RootCertificate>,
+
+    /// Accept the certificate of the server with the hex-encoded subject public key, as printed when it is rejected
+    #[clap(long, value_parser = parse_public_key, group = "tls_verification")]
+    pinned_public_key: Option<PublicKey>,
 
     /// A target RDP server user name
     #[clap(short, long, value_parser)]
@@ -93,6 +219,30 @@ struct Args {
     #[clap(long, value_enum, value_parser, default_value_t = SecurityProtocol::HybridEx)]
     security_protocol: SecurityProtocol,
 
+    /// The hex-encoded redirection GUID of the Server Redirection PDU, for the RDSTLS security protocol
+    #[clap(long, value_parser = parse_redirection_blob, requires = "redirection_password")]
+    redirection_guid: Option<RedirectionBlob>,
+
+    /// The hex-encoded encrypted password of the Server Redirection PDU, for the RDSTLS security protocol
+    #[clap(long, value_parser = parse_redirection_blob, requires = "redirection_guid")]
+    redirection_password: Option<RedirectionBlob>,
+
+    /// The address of the RD Gateway through which the connection is tunneled. Format: <host>:<port>
+    #[clap(long, value_parser = is_server_address, requires = "gateway_username", conflicts_with = "probe")]
+    gateway: Option<String>,
+
+    /// The RD Gateway user name, distinct from the target RDP server one
+    #[clap(long, value_parser, requires = "gateway_password")]
+    gateway_username: Option<String>,
+
+    /// An optional RD Gateway domain name
+    #[clap(long, value_parser, requires = "gateway")]
+    gateway_domain: Option<String>,
+
+    /// The RD Gateway user password
+    #[clap(long, value_parser, requires = "gateway")]
+    gateway_password: Option<String>,
+
     /// The keyboard type
     #[clap(long, value_enum, value_parser, default_value_t = KeyboardType::IbmEnhanced)]
     keyboard_type: KeyboardType,
@@ -109,9 +259,21 @@ struct Args {
     #[clap(long, value_parser, default_value_t = String::from(""))]
     ime_file_name: String,
 
-    /// Contains a value that uniquely identifies the client
-    #[clap(long, value_parser, default_value_t = String::from(""))]
-    dig_product_id: String,
+    /// The client name, defaults to the host name
+    #[clap(long, value_parser)]
+    client_name: Option<String>,
+
+    /// The client build number, defaults to the one derived from the version
+    #[clap(long, value_parser)]
+    client_build: Option<u32>,
+
+    /// Contains a value that uniquely identifies the client, defaults to the client name and version
+    #[clap(long, value_parser)]
+    dig_product_id: Option<String>,
+
+    /// The highest bulk compression of the data sent by the server, which is not compressed if not set
+    #[clap(long, value_enum, value_parser)]
+    compression: Option<Compression>,
 
     /// Enable AVC444
     #[clap(long, group = "avc")]
@@ -133,11 +295,118 @@ struct Args {
     /// starting from V8 to V10_7
     #[clap(long, value_parser = parse_hex, default_value_t = 0)]
     capabilities: u32,
+
+    /// The minimal interval in milliseconds between two renders of the graphics updates
+    #[clap(long, value_parser, default_value_t = 16)]
+    frame_interval: u64,
+
+    /// The minimal interval in milliseconds between two acknowledgements of the frames decoded,
+    /// to save the upstream bandwidth of high-latency links. Every frame is acknowledged if not set
+    #[clap(long, value_parser)]
+    ack_interval: Option<u64>,
+
+    /// The maximal number of frames acknowledged at once when --ack-interval is set
+    #[clap(long, value_parser, default_value_t = 8)]
+    ack_max_batch: usize,
+
+    /// Disconnect right after the MCS connect and print the duration of each phase, for health checks
+    #[clap(long)]
+    probe: bool,
+
+    /// Run a soak test of the session for the number of minutes: the mouse is moved continuously,
+    /// the decoded desktop is periodically compared with the one refreshed by the server, and the resident
+    /// memory of the client is sampled. The desktop is to be static, e.g. without a clock showing the seconds
+    #[clap(long, value_parser, conflicts_with = "probe")]
+    soak_duration: Option<u64>,
+
+    /// The interval in milliseconds between two moves of the mouse during the soak test
+    #[clap(long, value_parser, default_value_t = 50)]
+    soak_input_interval: u64,
+
+    /// The interval in seconds between two checks of the desktop and of the memory during the soak test
+    #[clap(long, value_parser, default_value_t = 60)]
+    soak_check_interval: u64,
+
+    /// The resident memory in MiB of the client beyond which the soak test fails
+    #[clap(long, value_parser)]
+    soak_memory_ceiling: Option<u64>,
+
+    /// The maximal number of reconnections to the session when the connection is lost during the soak test
+    #[clap(long, value_parser, default_value_t = 3)]
+    soak_max_reconnects: u32,
+
+    /// Save the number of first complete frames of the desktop as files named frame.<index>.<extension>,
+    /// then disconnect, e.g. for automated tests of the servers
+    #[clap(long, value_parser, conflicts_with_all = ["probe", "soak_duration"])]
+    screenshots: Option<usize>,
+
+    /// The format of the screenshots
+    #[clap(long, value_enum, value_parser, default_value_t = ScreenshotFormat::Png)]
+    screenshot_format: ScreenshotFormat,
+
+    /// The directory the screenshots are written to
+    #[clap(long, value_parser, default_value = ".")]
+    screenshot_dir: PathBuf,
+
+    /// The time in milliseconds without graphics updates after which a frame is considered complete,
+    /// for the servers which do not delimit the frames
+    #[clap(long, value_parser, default_value_t = 500)]
+    screenshot_settle_time: u64,
+
+    /// The timeout in seconds for the screenshots to be taken
+    #[clap(long, value_parser, default_value_t = 60)]
+    screenshot_timeout: u64,
+
+    /// Write the diagnostics of the connection to this file when it fails, for the problem to be reported
+    #[clap(long, value_parser)]
+    diagnostics_file: Option<PathBuf>,
+
+    /// Store the licenses issued by the server in this directory, for them to be reused by the next connections
+    #[clap(long, value_parser)]
+    license_directory: Option<PathBuf>,
+
+    /// A monitor of the client, the first one being the primary one. Can be repeated.
+    /// Format: <width>x<height>[+<left>+<top>], the offsets being signed, e.g. 1280x1024-1280+0
+    #[clap(long = "monitor", value_parser = parse_monitor)]
+    monitors: Vec<MonitorConfig>,
 }
 
-fn is_socket_address(s: &str) -> Result<SocketAddr, String> {
-    s.parse::<SocketAddr>()
-        .map_err(|_| String::from("The address does not match the format: <ip>:<port>"))
+fn parse_monitor(s: &str) -> Result<MonitorConfig, String> {
+    let error = || {
+        format!(
+            "The monitor {:?} does not match the format: <width>x<height>[+<left>+<top>]",
+            s
+        )
+    };
+
+    let (width, rest) = s.split_once('x').ok_or_else(error)?;
+    let (height, offsets) = match rest.find(|c| c == '+' || c == '-') {
+        Some(i) => rest.split_at(i),
+        None => (rest, ""),
+    };
+    let width = width.parse::<u32>().map_err(|_| error())?;
+    let height = height.parse::<u32>().map_err(|_| error())?;
+
+    let (left, top) = if offsets.is_empty() {
+        (0, 0)
+    } else {
+        let i = offsets[1..].find(|c| c == '+' || c == '-').ok_or_else(error)? + 1;
+        let (left, top) = offsets.split_at(i);
+
+        (
+            left.parse::<i32>().map_err(|_| error())?,
+            top.parse::<i32>().map_err(|_| error())?,
+        )
+    };
+
+    Ok(MonitorConfig::primary(width, height).with_position(left, top))
+}
+
+fn is_server_address(s: &str) -> Result<String, String> {
+    match s.rsplit_once(':') {
+        Some((host, port)) if !host.is_empty() && port.parse::<u16>().is_ok() => Ok(String::from(s)),
+        _ => Err(String::from("The address does not match the format: <host>:<port>")),
+    }
 }
 
 impl Config {
@@ -163,21 +432,111 @@ impl Config {
                 domain: args.domain,
             },
             security_protocol: SecurityProtocol::parse(args.security_protocol),
+            encryption_methods: SecurityProtocol::encryption_methods(args.security_protocol),
             keyboard_type: KeyboardType::parse(args.keyboard_type),
             keyboard_subtype: args.keyboard_subtype,
             keyboard_functional_keys_count: args.keyboard_functional_keys_count,
             ime_file_name: args.ime_file_name,
+            client_name: args.client_name,
+            client_build: args.client_build,
             dig_product_id: args.dig_product_id,
             width: DEFAULT_WIDTH,
             height: DEFAULT_HEIGHT,
-            global_channel_name: GLOBAL_CHANNEL_NAME.to_string(),
-            user_channel_name: USER_CHANNEL_NAME.to_string(),
+            monitors: args
+                .monitors
+                .into_iter()
+                .enumerate()
+                .map(|(i, monitor)| MonitorConfig {
+                    primary: i == 0,
+                    ..monitor
+                })
+                .collect(),
+            global_channel_name: GLOBAL_CHANNEL_NAME,
+            user_channel_name: USER_CHANNEL_NAME,
             graphics_config,
+            codecs: CodecRegistry::default(),
+            limits: LimitsConfig::default(),
+            audio_playback: false,
+            drive_redirection: false,
+            smart_card_redirection: false,
+            client_info: ClientInfoConfig {
+                compression_type: args.compression.map(Compression::parse),
+                ..ClientInfoConfig::default()
+            },
+            redirection_credentials: args.redirection_guid.zip(args.redirection_password).map(
+                |(redirection_guid, password)| RedirectionCredentials {
+                    redirection_guid: redirection_guid.0,
+                    password: password.0,
+                },
+            ),
+            routing_token: None,
+            redirected_session_id: None,
+            gateway: args.gateway.zip(args.gateway_username.zip(args.gateway_password)).map(
+                |(address, (username, password))| GatewayConfig {
+                    address,
+                    credentials: AuthIdentity {
+                        username,
+                        password,
+                        domain: args.gateway_domain,
+                    },
+                },
+            ),
+            output_pixel_format: PixelFormat::RgbA32,
+            diagnostics: if args.diagnostics_file.is_some() {
+                DiagnosticsRecorder::new(DEFAULT_MAX_PDUS)
+            } else {
+                DiagnosticsRecorder::default()
+            },
+            license_store: args
+                .license_directory
+                .map(|directory| Arc::new(DirectoryLicenseStore::new(directory)) as Arc<dyn LicenseStore>),
+        };
+
+        let tls_verification = if args.verify_certificate {
+            TlsVerification::SystemRoots
+        } else if !args.root_certificates.is_empty() {
+            TlsVerification::RootStore(
+                args.root_certificates
+                    .into_iter()
+                    .map(|root_certificate| root_certificate.0)
+                    .collect(),
+            )
+        } else if let Some(public_key) = args.pinned_public_key {
+            TlsVerification::PinnedPublicKey(public_key.0)
+        } else {
+            TlsVerification::Skip
         };
 
         Self {
             log_file: args.log_file,
-            routing_addr: args.addr,
+            server_addr: args.addr,
+            connect_timeouts: ConnectTimeouts {
+                dns_resolution: Duration::from_secs(args.connect_timeout),
+                tcp_connect: Duration::from_secs(args.connect_timeout),
+                tls_handshake: Duration::from_secs(args.connect_timeout),
+            },
+            tls_verification,
+            frame_interval: Duration::from_millis(args.frame_interval),
+            ack_coalescing: args.ack_interval.map(|ack_interval| CoalescingConfig {
+                min_interval: Duration::from_millis(ack_interval),
+                max_batch: args.ack_max_batch,
+            }),
+            probe: args.probe,
+            soak: args.soak_duration.map(|duration| SoakConfig {
+                duration: Duration::from_secs(duration * 60),
+                input_interval: Duration::from_millis(args.soak_input_interval),
+                check_interval: Duration::from_secs(args.soak_check_interval),
+                memory_ceiling: args.soak_memory_ceiling.map(|ceiling| ceiling * 1024 * 1024),
+                max_reconnects: args.soak_max_reconnects,
+            }),
+            screenshots: args.screenshots.map(|count| ScreenshotConfig {
+                count,
+                format: ScreenshotFormat::parse(args.screenshot_format),
+                directory: args.screenshot_dir,
+                settle_time: Duration::from_millis(args.screenshot_settle_time),
+                timeout: Duration::from_secs(args.screenshot_timeout),
+            }),
+            diagnostics_file: args.diagnostics_file,
             input,
         }
     }
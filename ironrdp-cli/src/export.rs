@@ -0,0 +1,37 @@
+//! Export of the decoded desktop to image files.
+
+use std::path::Path;
+
+use ironrdp::codecs::rfx::image_processing::PixelFormat;
+use ironrdp_session::image::{DecodedImage, DEFAULT_JPEG_QUALITY};
+use ironrdp_session::RdpError;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ImageFormat {
+    Png,
+    Jpeg,
+    /// The pixels without header, 4 bytes per pixel in the blue, green, red and alpha order, top-down.
+    RawBgra,
+}
+
+impl ImageFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::Jpeg => "jpg",
+            Self::RawBgra => "bgra",
+        }
+    }
+}
+
+pub fn save(image: &DecodedImage, format: ImageFormat, path: &Path) -> Result<(), RdpError> {
+    match format {
+        ImageFormat::Png => image.save_png(path)?,
+        ImageFormat::Jpeg => image.save_jpeg(path, DEFAULT_JPEG_QUALITY)?,
+        ImageFormat::RawBgra => {
+            std::fs::write(path, image.to_pixel_format(PixelFormat::BgrA32)?)?;
+        }
+    }
+
+    Ok(())
+}
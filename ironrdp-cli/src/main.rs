@@ -2,67 +2,87 @@
 extern crate log;
 
 mod config;
+mod export;
+mod screenshot;
+mod soak;
 
 use std::io;
+use std::time::Instant;
 
 use crate::config::Config;
 use futures_util::io::AsyncWriteExt as _;
 use ironrdp::codecs::rfx::image_processing::PixelFormat;
-use ironrdp_session::image::DecodedImage;
-use ironrdp_session::{process_connection_sequence, ActiveStageOutput, ActiveStageProcessor, RdpError, UpgradedStream};
-use tokio::io::AsyncWriteExt as _;
-use tokio::net::TcpStream;
-use tokio_util::compat::TokioAsyncReadCompatExt as _;
-use x509_parser::prelude::{FromDer as _, X509Certificate};
-
-#[cfg(feature = "rustls")]
-type TlsStream = tokio_util::compat::Compat<tokio_rustls::client::TlsStream<TcpStream>>;
-
-#[cfg(all(feature = "native-tls", not(feature = "rustls")))]
-type TlsStream = tokio_util::compat::Compat<async_native_tls::TlsStream<TcpStream>>;
-
-#[cfg(feature = "rustls")]
-mod danger {
-    use std::time::SystemTime;
-
-    use rustls::client::ServerCertVerified;
-    use rustls::{Certificate, Error, ServerName};
-
-    pub struct NoCertificateVerification;
-
-    impl rustls::client::ServerCertVerifier for NoCertificateVerification {
-        fn verify_server_cert(
-            &self,
-            _end_entity: &Certificate,
-            _intermediates: &[Certificate],
-            _server_name: &ServerName,
-            _scts: &mut dyn Iterator<Item = &[u8]>,
-            _ocsp_response: &[u8],
-            _now: SystemTime,
-        ) -> Result<ServerCertVerified, Error> {
-            Ok(rustls::client::ServerCertVerified::assertion())
-        }
-    }
-}
+use ironrdp_session::image::{DecodedImage, DEFAULT_JPEG_QUALITY};
+use ironrdp_session::{connector, ActiveStageOutput, ActiveStageProcessor, FrameScheduler, PduDirection, RdpError};
+
+#[cfg(feature = "alloc-audit")]
+#[global_allocator]
+static ALLOCATOR: ironrdp_session::alloc_audit::AuditAllocator = ironrdp_session::alloc_audit::AuditAllocator;
 
 #[tokio::main]
 async fn main() {
-    let config = Config::parse_args();
+    let mut config = Config::parse_args();
     setup_logging(config.log_file.as_str()).expect("failed to initialize logging");
 
-    let exit_code = match run(config).await {
-        Ok(_) => {
+    let diagnostics_file = config.diagnostics_file.take();
+    let diagnostics = config.input.diagnostics.clone();
+
+    let result = match config.soak.take() {
+        Some(soak_config) => soak::run(config, soak_config).await.map(|report| {
+            println!("{}", report);
+            report.passed()
+        }),
+        None => run(config).await.map(|()| true),
+    };
+
+    if let (Err(e), Some(diagnostics_file)) = (&result, diagnostics_file) {
+        diagnostics.record_error(e);
+        if let Some(snapshot) = diagnostics.snapshot() {
+            match std::fs::write(&diagnostics_file, snapshot.to_string()) {
+                Ok(()) => println!("The diagnostics have been written to {}", diagnostics_file.display()),
+                Err(e) => error!(
+                    "Failed to write the diagnostics to {}: {}",
+                    diagnostics_file.display(),
+                    e
+                ),
+            }
+        }
+    }
+
+    let exit_code = match result {
+        Ok(true) => {
             println!("RDP successfully finished");
             exitcode::OK
         }
+        Ok(false) => exitcode::SOFTWARE,
         Err(RdpError::IOError(e)) if e.kind() == io::ErrorKind::UnexpectedEof => {
             error!("{}", e);
             println!("The server has terminated the RDP session");
             exitcode::NOHOST
         }
+        Err(RdpError::UntrustedServerCertificate { certificate, reason }) => {
+            error!("Untrusted server certificate: {}", reason);
+            println!(
+                "The certificate of the server is not trusted: {}\n\
+                 Subject: {}\nIssuer: {}\nValid from {} to {}\nSHA-256 fingerprint: {}\n\
+                 It can be trusted with --pinned-public-key {}",
+                reason,
+                certificate.subject,
+                certificate.issuer,
+                certificate.not_before,
+                certificate.not_after,
+                certificate.sha256_fingerprint_string(),
+                certificate
+                    .public_key
+                    .iter()
+                    .map(|byte| format!("{:02x}", byte))
+                    .collect::<String>()
+            );
+            exitcode::NOPERM
+        }
         Err(ref e) => {
             error!("{}", e);
-            println!("RDP failed because of {}", e);
+            println!("RDP failed because of {} (error code {})", e, e.code().as_u32());
 
             match e {
                 RdpError::IOError(_) => exitcode::IOERR,
@@ -91,13 +111,37 @@ fn setup_logging(log_file: &str) -> Result<(), fern::InitError> {
     Ok(())
 }
 
-async fn run(config: Config) -> Result<(), RdpError> {
-    let stream = TcpStream::connect(config.routing_addr)
-        .await
-        .map_err(RdpError::ConnectionError)?;
+async fn run(mut config: Config) -> Result<(), RdpError> {
+    if config.probe {
+        return probe(config).await;
+    }
 
-    let (connection_sequence_result, mut reader, mut writer) =
-        process_connection_sequence(stream.compat(), &config.routing_addr, &config.input, establish_tls).await?;
+    if let Some(screenshots) = config.screenshots.take() {
+        for path in screenshot::run(config, screenshots).await? {
+            println!("Saved {}", path.display());
+        }
+
+        return Ok(());
+    }
+
+    let (connection_sequence_result, mut reader, mut writer) = connector::connect(
+        &config.server_addr,
+        &mut config.input,
+        &config.tls_verification,
+        config.connect_timeouts,
+    )
+    .await?;
+
+    if let Some(certificate) = connection_sequence_result.server_certificate.as_ref() {
+        info!(
+            "Server certificate: subject {}, issuer {}, valid from {} to {}, SHA-256 fingerprint {}",
+            certificate.subject,
+            certificate.issuer,
+            certificate.not_before,
+            certificate.not_after,
+            certificate.sha256_fingerprint_string()
+        );
+    }
 
     let mut image = DecodedImage::new(
         PixelFormat::RgbA32,
@@ -105,101 +149,118 @@ async fn run(config: Config) -> Result<(), RdpError> {
         u32::from(connection_sequence_result.desktop_size.height),
     );
 
+    let diagnostics = config.input.diagnostics.clone();
     let mut active_stage = ActiveStageProcessor::new(config.input, connection_sequence_result);
+    if diagnostics.is_enabled() {
+        let sent_diagnostics = diagnostics.clone();
+        active_stage.on_pdu_sent(move |pdu| sent_diagnostics.record_pdu(PduDirection::Sent, &pdu.name));
+        active_stage.on_pdu_received(move |pdu| diagnostics.record_pdu(PduDirection::Received, &pdu.name));
+    }
+    if let Some(ack_coalescing) = config.ack_coalescing {
+        active_stage.set_frame_acknowledge_coalescing(ack_coalescing);
+    }
+    let mut frame_scheduler = FrameScheduler::new(config.frame_interval);
     let mut frame_id = 0;
 
     'outer: loop {
-        let frame = reader.read_frame().await?.ok_or(RdpError::AccessDenied)?;
+        let deadline = [frame_scheduler.deadline(), active_stage.coalescing_deadline()]
+            .into_iter()
+            .flatten()
+            .min();
+        let frame = if let Some(deadline) = deadline {
+            tokio::select! {
+                frame = reader.read_frame() => frame?,
+                _ = tokio::time::sleep_until(deadline.into()) => {
+                    let now = Instant::now();
+                    if frame_scheduler.poll(now).is_some() {
+                        // TODO: control this with CLI argument
+                        dump_image(&image, frame_id);
+
+                        frame_id += 1;
+                    }
+
+                    let coalesced = active_stage.encode_coalesced_pdus(now)?;
+                    if !coalesced.is_empty() {
+                        writer.write_all(&coalesced).await?;
+                    }
+
+                    continue;
+                }
+            }
+        } else {
+            reader.read_frame().await?
+        };
+        let frame = frame.ok_or(RdpError::AccessDenied)?;
+
         let outputs = active_stage.process(&mut image, frame).await?;
         for out in outputs {
             match out {
                 ActiveStageOutput::ResponseFrame(frame) => writer.write_all(&frame).await?,
-                ActiveStageOutput::GraphicsUpdate(_region) => {
-                    // TODO: control this with CLI argument
-                    dump_image(&image, frame_id);
+                ActiveStageOutput::GraphicsUpdate(region) => {
+                    if frame_scheduler.update(region, Instant::now()).is_some() {
+                        // TODO: control this with CLI argument
+                        dump_image(&image, frame_id);
 
-                    frame_id += 1;
+                        frame_id += 1;
+                    }
+                }
+                ActiveStageOutput::FrameMetadata(frame_metadata) => {
+                    debug!("Frame metadata: {:?}", frame_metadata);
+                }
+                ActiveStageOutput::GraphicsFrame(frame_event) => {
+                    debug!("Graphics frame: {:?}", frame_event);
+                }
+                ActiveStageOutput::KeyboardIndicators(led_flags) => {
+                    info!("Remote keyboard indicators changed: {:?}", led_flags);
+                }
+                ActiveStageOutput::KeyboardImeStatus(ime_status) => {
+                    info!("Remote keyboard IME status changed: {:?}", ime_status);
+                }
+                ActiveStageOutput::SessionLockState(lock_state) => {
+                    info!("Remote session lock state changed: {:?}", lock_state);
+                }
+                ActiveStageOutput::Pointer(pointer_change) => {
+                    debug!("Remote pointer changed: {:?}", pointer_change);
+                }
+                ActiveStageOutput::Desynchronized { skipped_bytes } => {
+                    info!("Skipped {} bytes to resynchronize on the next frame", skipped_bytes);
                 }
                 ActiveStageOutput::Terminate => break 'outer,
             }
         }
     }
 
-    Ok(())
-}
-
-// TODO: this can be refactored into a separate `ironrdp-tls` crate (all native clients will do the same TLS dance)
-async fn establish_tls(stream: tokio_util::compat::Compat<TcpStream>) -> Result<UpgradedStream<TlsStream>, RdpError> {
-    let stream = stream.into_inner();
-
-    #[cfg(all(feature = "native-tls", not(feature = "rustls")))]
-    let mut tls_stream = {
-        let connector = async_native_tls::TlsConnector::new()
-            .danger_accept_invalid_certs(true)
-            .use_sni(false);
-
-        // domain is an empty string because client accepts IP address in the cli
-        match connector.connect("", stream).await {
-            Ok(tls) => tls,
-            Err(err) => return Err(RdpError::TlsHandshakeError(err)),
-        }
-    };
-
-    #[cfg(feature = "rustls")]
-    let mut tls_stream = {
-        let mut client_config = rustls::client::ClientConfig::builder()
-            .with_safe_defaults()
-            .with_custom_certificate_verifier(std::sync::Arc::new(danger::NoCertificateVerification))
-            .with_no_client_auth();
-        // This adds support for the SSLKEYLOGFILE env variable (https://wiki.wireshark.org/TLS#using-the-pre-master-secret)
-        client_config.key_log = std::sync::Arc::new(rustls::KeyLogFile::new());
-        let rc_config = std::sync::Arc::new(client_config);
-        let example_com = "stub_string".try_into().unwrap();
-        let connector = tokio_rustls::TlsConnector::from(rc_config);
-        connector.connect(example_com, stream).await?
-    };
-
-    tls_stream.flush().await?;
-
-    #[cfg(all(feature = "native-tls", not(feature = "rustls")))]
-    let server_public_key = {
-        let cert = tls_stream
-            .peer_certificate()
-            .map_err(RdpError::TlsConnectorError)?
-            .ok_or(RdpError::MissingPeerCertificate)?;
-        get_tls_peer_pubkey(cert.to_der().map_err(RdpError::DerEncode)?)?
-    };
-
-    #[cfg(feature = "rustls")]
-    let server_public_key = {
-        let cert = tls_stream
-            .get_ref()
-            .1
-            .peer_certificates()
-            .ok_or(RdpError::MissingPeerCertificate)?[0]
-            .as_ref();
-        get_tls_peer_pubkey(cert.to_vec())?
-    };
+    if frame_scheduler.flush(Instant::now()).is_some() {
+        dump_image(&image, frame_id);
+    }
 
-    Ok(UpgradedStream {
-        stream: tls_stream.compat(),
-        server_public_key,
-    })
+    Ok(())
 }
 
-pub fn get_tls_peer_pubkey(cert: Vec<u8>) -> io::Result<Vec<u8>> {
-    let res = X509Certificate::from_der(&cert[..])
-        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Invalid der certificate."))?;
-    let public_key = res.1.tbs_certificate.subject_pki.subject_public_key;
+async fn probe(config: Config) -> Result<(), RdpError> {
+    let probe_result = connector::probe(
+        &config.server_addr,
+        &config.input,
+        &config.tls_verification,
+        config.connect_timeouts,
+    )
+    .await?;
+    let timings = &probe_result.timings;
+
+    println!("Selected security protocol: {:?}", probe_result.selected_protocol);
+    println!("Negotiation: {:?}", timings.negotiation);
+    println!("Security upgrade: {:?}", timings.security_upgrade);
+    if let Some(cred_ssp) = timings.cred_ssp {
+        println!("CredSSP: {:?}", cred_ssp);
+    }
+    println!("MCS connect: {:?}", timings.mcs_connect);
+    println!("Total: {:?}", timings.total());
 
-    Ok(public_key.data.to_vec())
+    Ok(())
 }
 
 pub fn dump_image(image: &DecodedImage, frame_id: usize) {
-    debug_assert_eq!(image.pixel_format(), PixelFormat::RgbA32);
-
-    image::RgbaImage::from_raw(image.width(), image.height(), image.data().to_vec())
-        .unwrap()
-        .save(format!("frame.{frame_id}.jpg"))
+    image
+        .save_jpeg(format!("frame.{frame_id}.jpg"), DEFAULT_JPEG_QUALITY)
         .unwrap();
 }
@@ -0,0 +1,108 @@
+//! Headless screenshots: the client connects, saves the first complete frames of the desktop and disconnects,
+//! for the automated tests of the servers.
+
+use std::io;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use futures_util::io::AsyncWriteExt as _;
+use ironrdp::codecs::rfx::image_processing::PixelFormat;
+use ironrdp_session::image::DecodedImage;
+use ironrdp_session::{connector, ActiveStageOutput, ActiveStageProcessor, GraphicsFrameEvent, RdpError};
+
+use crate::config::Config;
+use crate::export::{self, ImageFormat};
+
+#[derive(Debug, Clone)]
+pub struct ScreenshotConfig {
+    pub count: usize,
+    pub format: ImageFormat,
+    pub directory: PathBuf,
+    /// The time without graphics updates after which a frame is considered complete,
+    /// for the servers which do not delimit the frames.
+    pub settle_time: Duration,
+    pub timeout: Duration,
+}
+
+/// Saves the first complete frames, returning the paths of the files written.
+///
+/// The frames are delimited by the Graphics Pipeline or by the frame markers of the surface commands.
+/// The servers sending bitmap updates do not delimit them, so a frame is considered complete once the desktop
+/// has not been updated for the settle time, until a delimited frame has been received.
+pub async fn run(mut config: Config, screenshots: ScreenshotConfig) -> Result<Vec<PathBuf>, RdpError> {
+    let deadline = Instant::now() + screenshots.timeout;
+
+    let (connection_sequence_result, mut reader, mut writer) = connector::connect(
+        &config.server_addr,
+        &mut config.input,
+        &config.tls_verification,
+        config.connect_timeouts,
+    )
+    .await?;
+
+    let mut image = DecodedImage::new(
+        PixelFormat::RgbA32,
+        u32::from(connection_sequence_result.desktop_size.width),
+        u32::from(connection_sequence_result.desktop_size.height),
+    );
+
+    let mut active_stage = ActiveStageProcessor::new(config.input, connection_sequence_result);
+    active_stage.set_frame_events_enabled(true);
+
+    let mut paths = Vec::with_capacity(screenshots.count);
+    let mut frames_delimited = false;
+    let mut last_update = None;
+
+    while paths.len() < screenshots.count {
+        let settle_deadline = last_update
+            .filter(|_| !frames_delimited)
+            .map(|last_update| last_update + screenshots.settle_time);
+
+        let frame = tokio::select! {
+            frame = reader.read_frame() => frame?,
+            _ = tokio::time::sleep_until(settle_deadline.unwrap_or(deadline).min(deadline).into()) => {
+                if Instant::now() >= deadline {
+                    return Err(RdpError::ReadTimedOut);
+                }
+
+                last_update = None;
+                paths.push(save_frame(&image, &screenshots, paths.len())?);
+
+                continue;
+            }
+        };
+        let frame = frame.ok_or_else(|| RdpError::IOError(io::Error::from(io::ErrorKind::UnexpectedEof)))?;
+
+        let outputs = active_stage.process(&mut image, frame).await?;
+        for out in outputs {
+            match out {
+                ActiveStageOutput::ResponseFrame(frame) => writer.write_all(&frame).await?,
+                ActiveStageOutput::GraphicsUpdate(_) => last_update = Some(Instant::now()),
+                ActiveStageOutput::GraphicsFrame(GraphicsFrameEvent::Ended { frame_id }) => {
+                    debug!("Frame {} has been completed", frame_id);
+                    frames_delimited = true;
+                    if paths.len() < screenshots.count {
+                        paths.push(save_frame(&image, &screenshots, paths.len())?);
+                    }
+                }
+                ActiveStageOutput::Terminate => {
+                    return Err(RdpError::IOError(io::Error::from(io::ErrorKind::UnexpectedEof)))
+                }
+                // the other events of the session do not change the frames
+                _ => {}
+            }
+        }
+    }
+
+    Ok(paths)
+}
+
+fn save_frame(image: &DecodedImage, screenshots: &ScreenshotConfig, index: usize) -> Result<PathBuf, RdpError> {
+    let path = screenshots
+        .directory
+        .join(format!("frame.{}.{}", index, screenshots.format.extension()));
+    export::save(image, screenshots.format, &path)?;
+    info!("Saved the frame {} to {}", index, path.display());
+
+    Ok(path)
+}
@@ -0,0 +1,465 @@
+//! Soak test of the session: the client stays connected for hours, moving the mouse continuously,
+//! comparing the decoded desktop with the one refreshed by the server and sampling its resident memory,
+//! to validate the buffer pools and the reconnection under a sustained load.
+
+use std::time::{Duration, Instant};
+use std::{fmt, io, mem};
+
+use futures_util::io::AsyncWriteExt as _;
+use ironrdp::codecs::rfx::image_processing::PixelFormat;
+use ironrdp::Rectangle;
+use ironrdp_session::image::DecodedImage;
+use ironrdp_session::{
+    connector, ActiveStageOutput, ActiveStageProcessor, ConnectionSequenceResult, DomCodeMapper, ErasedWriter,
+    FramedReader, InputConfig, InputEventSender, RdpError,
+};
+
+use crate::config::Config;
+
+/// The time without graphics updates after which the desktop is considered settled.
+const SETTLE_TIME: Duration = Duration::from_secs(2);
+/// The time after which a check is skipped if the desktop has not settled.
+const MAX_SETTLE_WAIT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone)]
+pub struct SoakConfig {
+    pub duration: Duration,
+    pub input_interval: Duration,
+    pub check_interval: Duration,
+    /// The resident memory in bytes beyond which the test fails.
+    pub memory_ceiling: Option<u64>,
+    pub max_reconnects: u32,
+}
+
+/// Summary of the soak test, printed once it has ended.
+#[derive(Debug, Default)]
+pub struct SoakReport {
+    pub elapsed: Duration,
+    pub frames: u64,
+    pub graphics_updates: u64,
+    pub mouse_moves: u64,
+    pub bytes_received: u64,
+    pub bytes_sent: u64,
+    pub checks: u32,
+    /// The checks whose desktop differed from the one refreshed by the server.
+    pub drifts: u32,
+    /// The checks skipped because the desktop did not settle, or the server did not refresh it.
+    pub skipped_checks: u32,
+    pub reconnects: u32,
+    /// The resident memory at the first check, once the buffers have been allocated.
+    pub baseline_memory: Option<u64>,
+    pub last_memory: Option<u64>,
+    pub peak_memory: Option<u64>,
+    pub failure: Option<String>,
+}
+
+impl SoakReport {
+    pub fn passed(&self) -> bool {
+        self.failure.is_none() && self.drifts == 0
+    }
+
+    /// The growth of the resident memory per hour since the first check, hinting at a leak if it stays positive.
+    pub fn memory_growth_per_hour(&self) -> Option<f64> {
+        let (baseline, last) = (self.baseline_memory?, self.last_memory?);
+        let hours = self.elapsed.as_secs_f64() / 3600.0;
+
+        (hours > 0.0).then(|| (last as f64 - baseline as f64) / hours)
+    }
+
+    fn sample_memory(&mut self, memory: u64) {
+        self.baseline_memory.get_or_insert(memory);
+        self.last_memory = Some(memory);
+        self.peak_memory = Some(self.peak_memory.map_or(memory, |peak| peak.max(memory)));
+    }
+}
+
+impl fmt::Display for SoakReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Soak test {}", if self.passed() { "passed" } else { "failed" })?;
+        if let Some(failure) = &self.failure {
+            writeln!(f, "Failure: {}", failure)?;
+        }
+        writeln!(f, "Elapsed: {:?}", self.elapsed)?;
+        writeln!(
+            f,
+            "Frames: {}, graphics updates: {}, mouse moves: {}",
+            self.frames, self.graphics_updates, self.mouse_moves
+        )?;
+        writeln!(
+            f,
+            "Received: {} bytes, sent: {} bytes",
+            self.bytes_received, self.bytes_sent
+        )?;
+        writeln!(
+            f,
+            "Checks: {}, drifts: {}, skipped: {}",
+            self.checks, self.drifts, self.skipped_checks
+        )?;
+        writeln!(f, "Reconnections: {}", self.reconnects)?;
+
+        match (self.baseline_memory, self.last_memory, self.peak_memory) {
+            (Some(baseline), Some(last), Some(peak)) => {
+                write!(
+                    f,
+                    "Resident memory: {} KiB at the first check, {} KiB at the last one, {} KiB at the peak",
+                    baseline / 1024,
+                    last / 1024,
+                    peak / 1024
+                )?;
+                if let Some(growth) = self.memory_growth_per_hour() {
+                    write!(f, ", growing by {:.0} KiB per hour", growth / 1024.0)?;
+                }
+                Ok(())
+            }
+            _ => write!(f, "Resident memory: not available"),
+        }
+    }
+}
+
+struct Session {
+    active_stage: ActiveStageProcessor,
+    reader: FramedReader,
+    writer: ErasedWriter,
+    image: DecodedImage,
+}
+
+impl Session {
+    fn new(
+        config: &Config,
+        input: InputConfig,
+        (connection_sequence_result, reader, writer): (ConnectionSequenceResult, FramedReader, ErasedWriter),
+    ) -> Self {
+        let image = DecodedImage::new(
+            PixelFormat::RgbA32,
+            u32::from(connection_sequence_result.desktop_size.width),
+            u32::from(connection_sequence_result.desktop_size.height),
+        );
+
+        let mut active_stage = ActiveStageProcessor::new(input, connection_sequence_result);
+        if let Some(ack_coalescing) = config.ack_coalescing {
+            active_stage.set_frame_acknowledge_coalescing(ack_coalescing);
+        }
+
+        Self {
+            active_stage,
+            reader,
+            writer,
+            image,
+        }
+    }
+
+    fn whole_desktop(&self) -> Rectangle {
+        Rectangle {
+            left: 0,
+            top: 0,
+            right: self.image.width(),
+            bottom: self.image.height(),
+        }
+    }
+}
+
+/// The step of the soak test, the input being paused while the desktop is checked.
+#[derive(Debug, Copy, Clone)]
+enum Phase {
+    Input {
+        next_move: Instant,
+        next_check: Instant,
+    },
+    /// Waiting for the desktop to stop changing before its checksum is taken.
+    Settling {
+        started: Instant,
+        last_update: Instant,
+    },
+    /// Waiting for the refresh of the whole desktop requested after its checksum has been taken.
+    Refreshing {
+        checksum: u64,
+        started: Instant,
+        last_update: Option<Instant>,
+    },
+}
+
+impl Phase {
+    fn input(now: Instant, soak: &SoakConfig) -> Self {
+        Self::Input {
+            next_move: now,
+            next_check: now + soak.check_interval,
+        }
+    }
+
+    fn deadline(&self) -> Instant {
+        match *self {
+            Self::Input { next_move, next_check } => next_move.min(next_check),
+            Self::Settling { started, last_update } => (last_update + SETTLE_TIME).min(started + MAX_SETTLE_WAIT),
+            Self::Refreshing {
+                started, last_update, ..
+            } => last_update
+                .map_or(started + MAX_SETTLE_WAIT, |last_update| last_update + SETTLE_TIME)
+                .min(started + MAX_SETTLE_WAIT),
+        }
+    }
+
+    fn graphics_updated(&mut self, now: Instant) {
+        match self {
+            Self::Input { .. } => {}
+            Self::Settling { last_update, .. } => *last_update = now,
+            Self::Refreshing { last_update, .. } => *last_update = Some(now),
+        }
+    }
+}
+
+/// Runs the soak test, reconnecting to the session with the auto-reconnect cookie when the connection is lost.
+pub async fn run(mut config: Config, soak: SoakConfig) -> Result<SoakReport, RdpError> {
+    let start = Instant::now();
+    let end = start + soak.duration;
+    let mut report = SoakReport::default();
+
+    let connection = connector::connect(
+        &config.server_addr,
+        &mut config.input,
+        &config.tls_verification,
+        config.connect_timeouts,
+    )
+    .await?;
+    // the configuration is consumed by the active stage, so it is parsed again from the arguments to reconnect
+    let input = mem::replace(&mut config.input, Config::parse_args().input);
+    let mut session = Session::new(&config, input, connection);
+
+    loop {
+        let error = match run_session(&mut session, &soak, end, &mut report).await {
+            Ok(()) => break,
+            Err(RdpError::IOError(error)) if report.reconnects < soak.max_reconnects => error,
+            Err(error) => return Err(error),
+        };
+
+        let auto_reconnect = match session.active_stage.auto_reconnect_cookie() {
+            Some(auto_reconnect) => auto_reconnect.clone(),
+            None => return Err(RdpError::IOError(error)),
+        };
+        warn!("The connection has been lost ({}), reconnecting to the session", error);
+        report.reconnects += 1;
+
+        let credentials = session.active_stage.take_session_credentials();
+        let channel_state = session.active_stage.into_channel_state();
+        let mut input = mem::replace(&mut config.input, Config::parse_args().input);
+        credentials.apply_to(&mut input);
+
+        let connection = connector::reconnect(
+            &config.server_addr,
+            &mut input,
+            auto_reconnect,
+            &config.tls_verification,
+            config.connect_timeouts,
+        )
+        .await?;
+
+        session = Session::new(&config, input, connection);
+        session.active_stage.restore_channel_state(channel_state);
+    }
+
+    check_memory(&soak, &mut report);
+    report.elapsed = start.elapsed();
+
+    Ok(report)
+}
+
+async fn run_session(
+    session: &mut Session,
+    soak: &SoakConfig,
+    end: Instant,
+    report: &mut SoakReport,
+) -> Result<(), RdpError> {
+    let mut input = InputEventSender::new(DomCodeMapper);
+    let mut phase = Phase::input(Instant::now(), soak);
+
+    loop {
+        let deadline = [Some(phase.deadline()), session.active_stage.coalescing_deadline()]
+            .into_iter()
+            .flatten()
+            .min()
+            .unwrap_or(end)
+            .min(end);
+
+        let frame = tokio::select! {
+            frame = session.reader.read_frame() => frame?,
+            _ = tokio::time::sleep_until(deadline.into()) => {
+                let now = Instant::now();
+                if now >= end {
+                    return Ok(());
+                }
+
+                let coalesced = session.active_stage.encode_coalesced_pdus(now)?;
+                if !coalesced.is_empty() {
+                    session.writer.write_all(&coalesced).await?;
+                }
+
+                if now >= phase.deadline() {
+                    phase = advance(phase, now, session, &mut input, soak, report).await?;
+                    if report.failure.is_some() {
+                        return Ok(());
+                    }
+                }
+
+                continue;
+            }
+        };
+        // the connection closed by the server is reconnected as the lost ones
+        let frame = frame.ok_or_else(|| RdpError::IOError(io::Error::from(io::ErrorKind::UnexpectedEof)))?;
+        report.frames += 1;
+
+        let outputs = session.active_stage.process(&mut session.image, frame).await?;
+        for out in outputs {
+            match out {
+                ActiveStageOutput::ResponseFrame(frame) => session.writer.write_all(&frame).await?,
+                ActiveStageOutput::GraphicsUpdate(_) => {
+                    report.graphics_updates += 1;
+                    phase.graphics_updated(Instant::now());
+                }
+                ActiveStageOutput::Desynchronized { skipped_bytes } => {
+                    warn!("Skipped {} bytes to resynchronize on the next frame", skipped_bytes);
+                }
+                ActiveStageOutput::Terminate => {
+                    report.failure = Some(String::from("the server has terminated the session"));
+                    return Ok(());
+                }
+                // the other events of the session are irrelevant to the test
+                _ => {}
+            }
+        }
+    }
+}
+
+async fn advance(
+    phase: Phase,
+    now: Instant,
+    session: &mut Session,
+    input: &mut InputEventSender<DomCodeMapper>,
+    soak: &SoakConfig,
+    report: &mut SoakReport,
+) -> Result<Phase, RdpError> {
+    match phase {
+        Phase::Input { next_check, .. } if now >= next_check => {
+            check_memory(soak, report);
+            let traffic = session.active_stage.take_traffic_snapshot().total();
+            report.bytes_received += traffic.received.bytes;
+            report.bytes_sent += traffic.sent.bytes;
+
+            Ok(Phase::Settling {
+                started: now,
+                last_update: now,
+            })
+        }
+        Phase::Input { next_check, .. } => {
+            let (x_position, y_position) = mouse_position(report.mouse_moves, &session.image);
+            let event = input.mouse_move(x_position, y_position);
+            let frame = session.active_stage.encode_fast_path_input(vec![event])?;
+            if !frame.is_empty() {
+                session.writer.write_all(&frame).await?;
+            }
+            report.mouse_moves += 1;
+
+            Ok(Phase::Input {
+                next_move: now + soak.input_interval,
+                next_check,
+            })
+        }
+        Phase::Settling { started, last_update } => {
+            if last_update + SETTLE_TIME > now {
+                warn!(
+                    "The desktop has not settled within {:?}, skipping the check",
+                    now - started
+                );
+                report.skipped_checks += 1;
+
+                return Ok(Phase::input(now, soak));
+            }
+
+            let checksum = checksum(session.image.data());
+            let refresh_request = session.active_stage.encode_refresh_request(session.whole_desktop())?;
+            session.writer.write_all(&refresh_request).await?;
+
+            Ok(Phase::Refreshing {
+                checksum,
+                started: now,
+                last_update: None,
+            })
+        }
+        Phase::Refreshing {
+            checksum: expected,
+            started,
+            last_update,
+        } => {
+            match last_update {
+                Some(last_update) if last_update + SETTLE_TIME <= now => {
+                    report.checks += 1;
+                    let actual = checksum(session.image.data());
+                    if actual == expected {
+                        info!("The desktop matches the one refreshed by the server");
+                    } else {
+                        warn!(
+                            "The desktop has drifted: checksum {:016x} before the refresh, {:016x} after",
+                            expected, actual
+                        );
+                        report.drifts += 1;
+                    }
+                }
+                Some(_) => {
+                    warn!(
+                        "The refresh has not settled within {:?}, skipping the check",
+                        now - started
+                    );
+                    report.skipped_checks += 1;
+                }
+                None => {
+                    warn!("The server has not refreshed the desktop within {:?}", now - started);
+                    report.skipped_checks += 1;
+                }
+            }
+
+            Ok(Phase::input(now, soak))
+        }
+    }
+}
+
+fn check_memory(soak: &SoakConfig, report: &mut SoakReport) {
+    let memory = match resident_memory() {
+        Some(memory) => memory,
+        None => return,
+    };
+    report.sample_memory(memory);
+    info!("Resident memory: {} KiB", memory / 1024);
+
+    if let Some(ceiling) = soak.memory_ceiling.filter(|&ceiling| memory > ceiling) {
+        report.failure = Some(format!(
+            "the resident memory of {} KiB exceeds the ceiling of {} KiB",
+            memory / 1024,
+            ceiling / 1024
+        ));
+    }
+}
+
+/// The resident memory of the process in bytes, only available on Linux.
+fn resident_memory() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let resident = status.lines().find_map(|line| line.strip_prefix("VmRSS:"))?;
+    let kib = resident.trim().strip_suffix("kB")?.trim().parse::<u64>().ok()?;
+
+    Some(kib * 1024)
+}
+
+/// Sweeps the mouse across the desktop along diagonals.
+fn mouse_position(step: u64, image: &DecodedImage) -> (u16, u16) {
+    let x = step * 7 % u64::from(image.width().max(1));
+    let y = step * 5 % u64::from(image.height().max(1));
+
+    (
+        u16::try_from(x).unwrap_or(u16::MAX),
+        u16::try_from(y).unwrap_or(u16::MAX),
+    )
+}
+
+/// 64-bit FNV-1a hash of the pixels.
+fn checksum(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
@@ -1,66 +1,776 @@
+mod audio;
+mod channels;
+mod coalescing;
 mod codecs;
+mod drive;
 mod fast_path;
+mod frame_metadata;
+mod frame_pacing;
+mod input;
+mod pdu_hooks;
+mod pointer;
+mod scard;
+mod traffic;
 mod x224;
 
+use std::time::Instant;
+use std::{io, mem};
+
 use bytes::{BufMut as _, BytesMut};
+use futures_util::{AsyncRead, AsyncWrite, AsyncWriteExt as _};
+use ironrdp::dvc::gfx::Timestamp;
 use ironrdp::fast_path::FastPathError;
-use ironrdp::{RdpPdu, Rectangle};
-use log::warn;
+use ironrdp::input::fast_path::{FastPathInput, FastPathInputEvent};
+use ironrdp::input::mouse::MovementEvents;
+use ironrdp::input::{InputEvent, InputEventPdu, MousePdu};
+use ironrdp::rdp::bulk::BulkDecompressor;
+use ironrdp::rdp::session_info::{LogonErrorsInfo, ServerAutoReconnect};
+use ironrdp::rdp::{LedFlags, RefreshRectanglePdu, SetKeyboardImeStatusPdu, ShareDataPdu, StatusCode};
+use ironrdp::{ChannelId, PduParsing, RdpPdu, Rectangle};
+use log::{debug, warn};
 
+use self::coalescing::Coalescer;
+use self::frame_metadata::FrameMetadataTracker;
+use self::input::PointerQuantizer;
+use crate::cancellation::ReadCancellation;
+use crate::codecs::{FramedReader, Synchronization};
 use crate::connection_sequence::ConnectionSequenceResult;
-use crate::image::DecodedImage;
-use crate::transport::{Decoder, RdpTransport};
-use crate::{utils, InputConfig, RdpError};
+use crate::image::ImageSink;
+use crate::transport::{
+    DataTransport, Decoder, Encoder, McsTransport, RdpTransport, SendDataContextTransport, ShareControlHeaderTransport,
+    ShareDataHeaderTransport,
+};
+use crate::{utils, InputConfig, MonitorConfig, RdpError, SessionCredentials};
+
+pub use self::audio::AudioSink;
+pub use self::channels::{ChannelEvent, ChannelRegistry};
+pub use self::coalescing::CoalescingConfig;
+#[cfg(feature = "h264")]
+pub use self::codecs::h264::{Avc420Decoder, YuvFrame};
+pub use self::drive::{DrivePolicy, FileHandle, FileOpenOptions, FileSystemBackend, LocalDirectory};
+pub use self::frame_metadata::FrameMetadata;
+pub use self::frame_pacing::{DecodeTiming, FrameAcknowledgePolicy, FramePacingConfig};
+pub use self::input::{
+    DomCodeMapper, FocusInConfig, InputBatch, InputEventSender, KeyEvent, LowRateInputConfig, Modifiers, Scancode,
+    ScancodeMapper,
+};
+pub use self::pdu_hooks::{PduChannel, PduSummary};
+pub(crate) use self::pointer::POINTER_CACHE_SIZE;
+pub use self::pointer::{DecodedPointer, PointerCache, PointerChange, PointerEvent};
+pub use self::scard::{CardStatus, ScardBackend, ScardResult};
+pub use self::traffic::{ChannelTraffic, TrafficCounters, TrafficSnapshot};
+pub use self::x224::ChannelState;
+
+/// The number of events of a Fast-Path Input Event PDU is encoded on a byte.
+const MAX_FAST_PATH_INPUT_EVENTS: usize = 255;
 
 pub struct ActiveStageProcessor {
     x224_processor: x224::Processor,
     fast_path_processor: fast_path::Processor,
+    pdu_hooks: pdu_hooks::PduHooks,
+    input_transport: ShareDataHeaderTransport,
+    /// The history of the Share Data PDUs and of the Fast-Path updates, which the server compresses together.
+    bulk_decompressor: BulkDecompressor,
+    global_channel_id: ChannelId,
+    received: BytesMut,
+    /// The number of bytes received since the start of the active stage, up to the frame being processed.
+    received_offset: u64,
+    max_frame_length: usize,
+    frame_metadata_enabled: bool,
+    frame_events_enabled: bool,
+    mouse_move: Coalescer<MousePdu>,
+    pointer_quantizer: Option<PointerQuantizer>,
+    credentials: SessionCredentials,
+    #[cfg(feature = "alloc-audit")]
+    last_frame_allocations: crate::alloc_audit::FrameAllocations,
 }
 
 impl ActiveStageProcessor {
-    pub fn new(config: InputConfig, connection_sequence_result: ConnectionSequenceResult) -> Self {
+    pub fn new(mut config: InputConfig, connection_sequence_result: ConnectionSequenceResult) -> Self {
+        let credentials = SessionCredentials::take_from(&mut config);
         let x224_processor = x224::Processor::new(
             utils::swap_hashmap_kv(connection_sequence_result.joined_static_channels),
             config.global_channel_name,
-            config.graphics_config,
+            config
+                .graphics_config
+                .and_then(|graphics_config| graphics_config.restricted_to(&config.codecs)),
+            config.output_pixel_format,
+            config.client_name.clone().unwrap_or_else(whoami::hostname),
+            config.limits,
         );
 
+        let input_transport = ShareDataHeaderTransport::new(ShareControlHeaderTransport::new(
+            SendDataContextTransport::new(
+                McsTransport::new(DataTransport::default()),
+                connection_sequence_result.initiator_id,
+                connection_sequence_result.global_channel_id,
+            ),
+            connection_sequence_result.initiator_id,
+            connection_sequence_result.global_channel_id,
+        ));
+        let global_channel_id = connection_sequence_result.global_channel_id;
+
         let fast_path_processor = fast_path::ProcessorBuilder {
             global_channel_id: connection_sequence_result.global_channel_id,
             initiator_id: connection_sequence_result.initiator_id,
+            pixel_format: config.output_pixel_format,
         }
         .build();
 
         Self {
             x224_processor,
             fast_path_processor,
+            pdu_hooks: pdu_hooks::PduHooks::default(),
+            input_transport,
+            bulk_decompressor: connection_sequence_result.bulk_decompressor,
+            global_channel_id,
+            received: BytesMut::new(),
+            received_offset: 0,
+            max_frame_length: config.limits.max_pdu_length,
+            frame_metadata_enabled: false,
+            frame_events_enabled: false,
+            mouse_move: Coalescer::new(None),
+            pointer_quantizer: None,
+            credentials,
+            #[cfg(feature = "alloc-audit")]
+            last_frame_allocations: crate::alloc_audit::FrameAllocations::default(),
+        }
+    }
+
+    /// Returns the allocations made while processing the last frame.
+    #[cfg(feature = "alloc-audit")]
+    pub fn last_frame_allocations(&self) -> crate::alloc_audit::FrameAllocations {
+        self.last_frame_allocations
+    }
+
+    /// Registers a hook called with the summary of every PDU decoded during the active stage.
+    pub fn on_pdu_received(&mut self, hook: impl FnMut(&PduSummary) + Send + 'static) {
+        self.pdu_hooks.add_received(Box::new(hook));
+    }
+
+    /// Registers a hook called with the summary of every PDU written into a response frame.
+    pub fn on_pdu_sent(&mut self, hook: impl FnMut(&PduSummary) + Send + 'static) {
+        self.pdu_hooks.add_sent(Box::new(hook));
+    }
+
+    /// The channels of the session, as joined during the connection and opened by the server since.
+    pub fn channels(&self) -> &ChannelRegistry {
+        self.x224_processor.channels()
+    }
+
+    /// Registers a hook called whenever the server requests, or closes, a dynamic channel.
+    pub fn on_channel_event(&mut self, hook: impl FnMut(&ChannelEvent) + Send + 'static) {
+        self.x224_processor.add_channel_hook(Box::new(hook));
+    }
+
+    /// Registers a hook called with every pointer update of the server: the moves of the pointer and the changes
+    /// of its shape, e.g. to the busy or text selection ones. The changes are also returned, decoded, as
+    /// [`ActiveStageOutput::Pointer`].
+    pub fn on_pointer_event(&mut self, hook: impl FnMut(&PointerEvent<'_>) + Send + 'static) {
+        self.fast_path_processor.add_pointer_hook(Box::new(hook));
+    }
+
+    /// Follows each [`ActiveStageOutput::GraphicsUpdate`] with the [`ActiveStageOutput::FrameMetadata`] of the update,
+    /// e.g. for recorders to correlate the frames with network captures. Disabled by default.
+    ///
+    /// The timestamps are taken with [`Instant::now`], which is not available on `wasm32-unknown-unknown`.
+    pub fn set_frame_metadata_enabled(&mut self, enabled: bool) {
+        self.frame_metadata_enabled = enabled;
+    }
+
+    /// Returns the starts and the ends of the frames of the Graphics Pipeline, and the ends of the frames
+    /// of the surface commands, as [`ActiveStageOutput::GraphicsFrame`]s, e.g. for renderers to present the frames
+    /// at the pace of the server or to synchronize them with the audio. Disabled by default.
+    pub fn set_frame_events_enabled(&mut self, enabled: bool) {
+        self.frame_events_enabled = enabled;
+    }
+
+    /// Holds back the acknowledgements of the frames decoded, of the surface commands and of the Graphics Pipeline,
+    /// sent before the interval has elapsed, only the last one being sent, e.g. to save the upstream bandwidth
+    /// of high-latency links. The acknowledgements are sent as decoded by default.
+    ///
+    /// The server limits the number of frames in flight until they are acknowledged, so an interval longer
+    /// than the time the server takes to send that many frames lowers the frame rate. It applies to
+    /// the Graphics Pipeline opened after the call, and [`Self::encode_coalesced_pdus`] is to be called
+    /// at the [deadline](Self::coalescing_deadline).
+    pub fn set_frame_acknowledge_coalescing(&mut self, config: CoalescingConfig) {
+        self.fast_path_processor.set_ack_coalescing(config);
+        self.x224_processor.set_ack_coalescing(config);
+    }
+
+    /// Sets the queue depth reported to the server when acknowledging the frames of the Graphics Pipeline,
+    /// trading the latency against the throughput, and whether their decode time is measured.
+    /// The acknowledgements are suspended by default, the server sending the frames without waiting for the client.
+    /// It applies to the Graphics Pipeline opened after the call.
+    pub fn set_frame_pacing(&mut self, config: FramePacingConfig) {
+        self.x224_processor.set_frame_pacing(config);
+    }
+
+    /// Returns the decode time of the frames of the Graphics Pipeline, if it is open and the time is measured
+    /// (see [`FramePacingConfig::decode_timing`]).
+    pub fn graphics_decode_timing(&self) -> Option<DecodeTiming> {
+        self.x224_processor.decode_timing()
+    }
+
+    /// Holds back the moves of the mouse encoded by [`Self::encode_fast_path_input`] before the interval has elapsed,
+    /// only the last position being sent. The other events are never held back, the pending move being sent
+    /// before them, and the slow-path input is not coalesced.
+    pub fn set_mouse_move_coalescing(&mut self, config: CoalescingConfig) {
+        self.mouse_move = Coalescer::new(Some(config));
+    }
+
+    /// Rounds the positions of the moves of the mouse encoded by [`Self::encode_fast_path_input`] down to a grid
+    /// and limits their rate, so that the embedders do not have to filter the input of the assistive technologies
+    /// or of the low-bandwidth links themselves. The moves within the cell of the last position sent are dropped,
+    /// the clicks and wheel rotations being sent at their exact position.
+    pub fn set_low_rate_input(&mut self, config: LowRateInputConfig) {
+        self.pointer_quantizer = Some(PointerQuantizer::new(config.step));
+        self.set_mouse_move_coalescing(CoalescingConfig {
+            min_interval: config.min_interval,
+            max_batch: usize::MAX,
+        });
+    }
+
+    /// Returns the instant at which the earliest PDU held back by the coalescing is due to be sent,
+    /// `None` if none is pending.
+    pub fn coalescing_deadline(&self) -> Option<Instant> {
+        [
+            self.mouse_move.deadline(),
+            self.fast_path_processor.coalescing_deadline(),
+            self.x224_processor.coalescing_deadline(),
+        ]
+        .into_iter()
+        .flatten()
+        .min()
+    }
+
+    /// Encodes the PDUs held back by the coalescing whose interval has elapsed at `now`.
+    /// The output is empty if none has.
+    pub fn encode_coalesced_pdus(&mut self, now: Instant) -> Result<BytesMut, RdpError> {
+        let mut output_writer = BytesMut::new().writer();
+        if let Some(mouse_move) = self.mouse_move.poll(now) {
+            let input = FastPathInput(vec![FastPathInputEvent::MouseEvent(mouse_move)]);
+            input.to_buffer(&mut output_writer)?;
+            self.pdu_hooks.sent(PduChannel::FastPath, "Fast-Path Input PDU");
+        }
+        self.fast_path_processor
+            .send_coalesced(&mut output_writer, now, &mut self.pdu_hooks)?;
+        self.x224_processor
+            .send_coalesced(&mut output_writer, now, &mut self.pdu_hooks)?;
+
+        let output = output_writer.into_inner();
+        if !output.is_empty() {
+            self.pdu_hooks.frame_sent(output.len());
         }
+
+        Ok(output)
+    }
+
+    /// Returns the PDUs and bytes received and sent on each channel since the previous snapshot,
+    /// e.g. for a gateway to attribute the bandwidth of the session to its channels periodically.
+    pub fn take_traffic_snapshot(&mut self) -> TrafficSnapshot {
+        self.pdu_hooks.take_traffic_snapshot()
     }
 
+    /// Returns the cookie sent by the server for the client to reconnect to the session without logging on again,
+    /// which is to be kept by the embedder and passed to [`ClientInfoConfig::auto_reconnect`](crate::ClientInfoConfig::auto_reconnect)
+    /// when the connection is lost.
+    pub fn auto_reconnect_cookie(&self) -> Option<&ServerAutoReconnect> {
+        self.x224_processor.auto_reconnect()
+    }
+
+    /// The credentials the session has been authenticated with, which are zeroized when the processor is dropped.
+    pub fn session_credentials(&self) -> &SessionCredentials {
+        &self.credentials
+    }
+
+    /// Takes the credentials the session has been authenticated with, once the connection has been lost,
+    /// for them to be applied to the configuration of [`connector::reconnect`](crate::connector::reconnect)
+    /// with [`SessionCredentials::apply_to`] instead of prompting the user again.
+    pub fn take_session_credentials(&mut self) -> SessionCredentials {
+        mem::replace(&mut self.credentials, SessionCredentials::cleared())
+    }
+
+    /// Zeroizes the credentials the session has been authenticated with, e.g. once the session has ended,
+    /// the session not being re-authenticated to after that.
+    pub fn clear_session_credentials(&mut self) {
+        self.credentials.clear();
+    }
+
+    /// Takes the configuration of the channels set by the embedder, and the last layout of the monitors,
+    /// once the connection has been lost, for it to be passed to [`Self::restore_channel_state`]
+    /// of the session reconnected with the [auto-reconnect cookie](Self::auto_reconnect_cookie).
+    pub fn into_channel_state(self) -> ChannelState {
+        self.x224_processor.into_channel_state()
+    }
+
+    /// Restores the configuration of the channels of the previous connection to the session,
+    /// before the first frame of the reconnected session is processed: the channels are configured again
+    /// as by [`Self::set_audio_sink`], [`Self::add_drive`] and [`Self::set_smart_card_backend`],
+    /// and the layout of the monitors is sent once the server has opened the Display Control channel.
+    pub fn restore_channel_state(&mut self, state: ChannelState) {
+        self.x224_processor.restore_channel_state(state);
+    }
+
+    /// Sets the destination of the audio output redirected by the server,
+    /// which requires [`InputConfig::audio_playback`] to be enabled.
+    pub fn set_audio_sink(&mut self, sink: impl AudioSink + 'static) {
+        self.x224_processor.set_audio_sink(Box::new(sink));
+    }
+
+    /// Redirects a drive to the remote session, where it is displayed with its name.
+    /// It requires [`InputConfig::drive_redirection`] to be enabled, and the drives to be added
+    /// before the server asks for them once the user has logged on.
+    pub fn add_drive(&mut self, name: impl Into<String>, backend: impl FileSystemBackend + 'static) {
+        self.add_drive_with_policy(name, backend, DrivePolicy::default());
+    }
+
+    /// Redirects a drive as [`Self::add_drive`] does, the requests of the remote session being restricted
+    /// by the policy, e.g. for a local directory to be shared read-only.
+    pub fn add_drive_with_policy(
+        &mut self,
+        name: impl Into<String>,
+        backend: impl FileSystemBackend + 'static,
+        policy: DrivePolicy,
+    ) {
+        self.x224_processor.add_drive(name.into(), Box::new(backend), policy);
+    }
+
+    /// Redirects the smart card readers of the backend to the remote session, e.g. for the user to log on
+    /// with a smart card. It requires [`InputConfig::smart_card_redirection`] to be enabled,
+    /// and the backend to be set before the server opens the `rdpdr` channel.
+    ///
+    /// As the backend is not blocked while the remote session waits for the readers to change,
+    /// [`Self::encode_smart_card_events`] is to be called periodically.
+    pub fn set_smart_card_backend(&mut self, backend: impl ScardBackend + 'static) {
+        self.x224_processor.set_smart_card_backend(Box::new(backend));
+    }
+
+    /// Sets the factory of the H.264 decoders of the AVC420 encoded surfaces, called for every
+    /// Graphics Pipeline opened by the server. AVC420 and AVC444 are advertised to the server
+    /// only once a factory is set.
+    #[cfg(feature = "h264")]
+    pub fn set_avc420_decoder_factory(&mut self, factory: impl FnMut() -> Box<dyn Avc420Decoder> + Send + 'static) {
+        self.x224_processor.set_avc420_decoder_factory(Box::new(factory));
+    }
+
+    /// Encodes the input events into a Fast-Path Input Event PDU, e.g. the events accumulated
+    /// in an [`InputBatch`](crate::InputBatch), the events beyond the 255 a PDU can hold being encoded
+    /// into the following PDUs. The output is empty if the events are moves of the mouse held back
+    /// by the [coalescing](Self::set_mouse_move_coalescing).
+    pub fn encode_fast_path_input(&mut self, events: Vec<FastPathInputEvent>) -> Result<BytesMut, RdpError> {
+        let events = self.coalesce_mouse_moves(events);
+        if events.is_empty() {
+            return Ok(BytesMut::new());
+        }
+
+        let mut output_writer = BytesMut::new().writer();
+        for events in events.chunks(MAX_FAST_PATH_INPUT_EVENTS) {
+            let input = FastPathInput(events.to_vec());
+            output_writer.get_mut().reserve(input.buffer_length());
+            input.to_buffer(&mut output_writer)?;
+            self.pdu_hooks.sent(PduChannel::FastPath, "Fast-Path Input PDU");
+        }
+
+        let output = output_writer.into_inner();
+        self.pdu_hooks.frame_sent(output.len());
+
+        Ok(output)
+    }
+
+    fn coalesce_mouse_moves(&mut self, events: Vec<FastPathInputEvent>) -> Vec<FastPathInputEvent> {
+        let mut coalesced = Vec::with_capacity(events.len());
+        for event in events {
+            match event {
+                FastPathInputEvent::MouseEvent(pdu) if is_mouse_move(&pdu) => {
+                    let pdu = match self.pointer_quantizer.as_mut() {
+                        Some(quantizer) => quantizer.quantize_move(pdu),
+                        None => Some(pdu),
+                    };
+                    if let Some(pdu) = pdu {
+                        coalesced.extend(self.mouse_move.push(pdu).map(FastPathInputEvent::MouseEvent));
+                    }
+                }
+                event => {
+                    if let (FastPathInputEvent::MouseEvent(pdu), Some(quantizer)) =
+                        (&event, self.pointer_quantizer.as_mut())
+                    {
+                        quantizer.track(pdu);
+                    }
+                    coalesced.extend(self.mouse_move.flush().map(FastPathInputEvent::MouseEvent));
+                    coalesced.push(event);
+                }
+            }
+        }
+
+        coalesced
+    }
+
+    /// Encodes the input events into a slow-path Input Event PDU,
+    /// for the servers which do not support Fast-Path input.
+    pub fn encode_slow_path_input(&mut self, events: Vec<InputEvent>) -> Result<BytesMut, RdpError> {
+        let input = ShareDataPdu::Input(InputEventPdu(events));
+        self.pdu_hooks
+            .sent(PduChannel::Static(self.global_channel_id), input.as_short_name());
+
+        let mut output_writer = BytesMut::new().writer();
+        self.input_transport.encode(input, &mut output_writer)?;
+
+        let output = output_writer.into_inner();
+        self.pdu_hooks.frame_sent(output.len());
+
+        Ok(output)
+    }
+
+    /// Encodes the request for the server to change the resolution of the desktop, typically to the size
+    /// of the resized window, `scale_factor` being in percent. The server applies it asynchronously,
+    /// the new size being passed to [`ImageSink::resize`], so the embedder should wait for the user
+    /// to stop resizing the window before requesting it.
+    ///
+    /// Fails if the server has not opened the Display Control channel.
+    pub fn encode_monitor_layout(&mut self, width: u32, height: u32, scale_factor: u32) -> Result<BytesMut, RdpError> {
+        self.encode_monitors_layout(&[MonitorConfig::primary(width, height).with_scale_factor(scale_factor)])
+    }
+
+    /// Encodes the request for the server to change the layout of the monitors spanned by the desktop,
+    /// as [`Self::encode_monitor_layout`] does for a single monitor.
+    pub fn encode_monitors_layout(&mut self, monitors: &[MonitorConfig]) -> Result<BytesMut, RdpError> {
+        let mut output_writer = BytesMut::new().writer();
+        self.x224_processor
+            .send_monitor_layout(&mut output_writer, monitors, &mut self.pdu_hooks)?;
+
+        let output = output_writer.into_inner();
+        self.pdu_hooks.frame_sent(output.len());
+
+        Ok(output)
+    }
+
+    /// Encodes the responses to the calls of the remote session waiting for the smart card readers to change,
+    /// which have changed or whose timeout has expired since the previous call. The output is empty if none has.
+    pub fn encode_smart_card_events(&mut self) -> Result<BytesMut, RdpError> {
+        let mut output_writer = BytesMut::new().writer();
+        self.x224_processor
+            .send_smart_card_events(&mut output_writer, &mut self.pdu_hooks)?;
+
+        let output = output_writer.into_inner();
+        if !output.is_empty() {
+            self.pdu_hooks.frame_sent(output.len());
+        }
+
+        Ok(output)
+    }
+
+    /// Encodes the request for the server to send again the graphics of the area of the desktop, e.g. for
+    /// the decoded image to be compared with the one of the server. The output is empty if the area is.
+    pub fn encode_refresh_request(&mut self, area: Rectangle) -> Result<BytesMut, RdpError> {
+        let mut output_writer = BytesMut::new().writer();
+        self.encode_refresh_rectangle(area, &mut output_writer)?;
+
+        let output = output_writer.into_inner();
+        if !output.is_empty() {
+            self.pdu_hooks.frame_sent(output.len());
+        }
+
+        Ok(output)
+    }
+
+    /// Requests the server to send again the graphics of the area of the desktop,
+    /// the area being clamped to the 16-bit coordinates and converted to the inclusive bounds of the Refresh Rect PDU.
+    fn encode_refresh_rectangle(&mut self, area: Rectangle, output: impl io::Write) -> Result<(), RdpError> {
+        let area = area.clamp_to_u16();
+        if area.width() == 0 || area.height() == 0 {
+            return Ok(());
+        }
+
+        let refresh_rectangle = ShareDataPdu::RefreshRectangle(RefreshRectanglePdu {
+            areas_to_refresh: vec![Rectangle {
+                right: area.right - 1,
+                bottom: area.bottom - 1,
+                ..area
+            }],
+        });
+        debug!("Requesting the refresh of {:?}", refresh_rectangle);
+        self.pdu_hooks.sent(
+            PduChannel::Static(self.global_channel_id),
+            refresh_rectangle.as_short_name(),
+        );
+
+        self.input_transport.encode(refresh_rectangle, output)
+    }
+
+    /// Processes a frame received from the server, passing the decoded graphics to the image sink.
     pub async fn process(
         &mut self,
-        image: &mut DecodedImage,
+        image: &mut impl ImageSink,
+        frame: BytesMut,
+    ) -> Result<Vec<ActiveStageOutput>, RdpError> {
+        self.process_audited(image, frame)
+    }
+
+    /// Reads the next frame from the server, processes it and writes the response frames, so that the session
+    /// can be driven end-to-end on any `AsyncRead + AsyncWrite` stream, e.g. from a tokio task.
+    /// Returns the outputs other than [`ActiveStageOutput::ResponseFrame`].
+    ///
+    /// Waiting for the frame can be cancelled, e.g. in a `select!` with the input of the embedder,
+    /// but writing the responses cannot: the frame would be lost.
+    pub async fn process_next_frame<R, W>(
+        &mut self,
+        reader: &mut FramedReader<R>,
+        writer: &mut W,
+        image: &mut impl ImageSink,
+    ) -> Result<Vec<ActiveStageOutput>, RdpError>
+    where
+        R: AsyncRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        self.process_next_frame_cancellable(reader, writer, image, ReadCancellation::new())
+            .await
+    }
+
+    /// Like [`Self::process_next_frame`], but gives up waiting for the frame with [`RdpError::ReadTimedOut`]
+    /// or [`RdpError::ReadCancelled`] when the deadline or the shutdown signal of `cancellation` fires,
+    /// so that a silent server cannot block the session forever. The session can keep going after a timeout.
+    pub async fn process_next_frame_cancellable<R, W>(
+        &mut self,
+        reader: &mut FramedReader<R>,
+        writer: &mut W,
+        image: &mut impl ImageSink,
+        cancellation: ReadCancellation,
+    ) -> Result<Vec<ActiveStageOutput>, RdpError>
+    where
+        R: AsyncRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        let frame = reader
+            .read_frame_cancellable(cancellation)
+            .await?
+            .ok_or(RdpError::UnexpectedStreamTermination)?;
+
+        let mut stage_outputs = Vec::new();
+        for stage_output in self.process_audited(image, frame)? {
+            match stage_output {
+                ActiveStageOutput::ResponseFrame(response) => writer.write_all(&response).await?,
+                stage_output => stage_outputs.push(stage_output),
+            }
+        }
+        writer.flush().await?;
+
+        Ok(stage_outputs)
+    }
+
+    /// Buffers the bytes received from the server, to be processed by [`Self::poll_once`]
+    /// or [`Self::poll_with_budget`]. The bytes may hold any part of a frame.
+    ///
+    /// When switching from a [`FramedReader`](crate::FramedReader), the bytes it has already
+    /// buffered (see [`FramedReader::into_inner`](crate::FramedReader::into_inner)) have to be fed first.
+    pub fn feed(&mut self, data: &[u8]) {
+        self.received.extend_from_slice(data);
+    }
+
+    /// Returns the number of fed bytes which have not been processed yet.
+    pub fn buffered_len(&self) -> usize {
+        self.received.len()
+    }
+
+    /// Processes at most one frame of the fed bytes and returns control, so that single-threaded
+    /// embedders (GUI main loops, WASM) can interleave the session with their own events
+    /// without spawning threads. Returns `None` if no complete frame has been fed yet.
+    ///
+    /// If the fed bytes do not start with a plausible frame header, the bytes preceding the next one
+    /// are skipped and [`ActiveStageOutput::Desynchronized`] is returned instead of a frame being processed.
+    pub fn poll_once(&mut self, image: &mut impl ImageSink) -> Result<Option<Vec<ActiveStageOutput>>, RdpError> {
+        match self.resynchronize()? {
+            Synchronization::Synchronized => (),
+            Synchronization::Pending => return Ok(None),
+            Synchronization::Resynchronized { skipped_bytes } => {
+                return Ok(Some(vec![ActiveStageOutput::Desynchronized { skipped_bytes }]));
+            }
+        }
+
+        match crate::codecs::decode_frame(&mut self.received, self.max_frame_length)? {
+            Some(frame) => self.process_audited(image, frame).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Processes the bytes received from the server, as many complete frames as they end being processed
+    /// and the rest being kept for the next call. It does no I/O: the embedder reads the bytes, writes the
+    /// [`ActiveStageOutput::ResponseFrame`]s, and handles the other outputs, so that the same session can be driven
+    /// from an async runtime, blocking sockets, the event loop of a browser or a test.
+    ///
+    /// It stops early on [`ActiveStageOutput::Terminate`], the remaining bytes being left unprocessed.
+    pub fn handle_incoming(
+        &mut self,
+        image: &mut impl ImageSink,
+        bytes: &[u8],
+    ) -> Result<Vec<ActiveStageOutput>, RdpError> {
+        self.feed(bytes);
+        self.poll_with_budget(image, usize::MAX)
+    }
+
+    /// Processes the complete frames of the fed bytes until `byte_budget` bytes have been processed,
+    /// the frame crossing the budget being processed entirely. Stops early on [`ActiveStageOutput::Terminate`].
+    pub fn poll_with_budget(
+        &mut self,
+        image: &mut impl ImageSink,
+        byte_budget: usize,
+    ) -> Result<Vec<ActiveStageOutput>, RdpError> {
+        let mut stage_outputs = Vec::new();
+        let mut processed_len = 0;
+
+        while processed_len < byte_budget {
+            match self.resynchronize()? {
+                Synchronization::Synchronized => (),
+                Synchronization::Pending => break,
+                Synchronization::Resynchronized { skipped_bytes } => {
+                    processed_len += skipped_bytes;
+                    stage_outputs.push(ActiveStageOutput::Desynchronized { skipped_bytes });
+
+                    continue;
+                }
+            }
+
+            let frame = match crate::codecs::decode_frame(&mut self.received, self.max_frame_length)? {
+                Some(frame) => frame,
+                None => break,
+            };
+            processed_len += frame.len();
+
+            let frame_outputs = self.process_audited(image, frame)?;
+            let terminated = frame_outputs
+                .iter()
+                .any(|output| matches!(output, ActiveStageOutput::Terminate));
+            stage_outputs.extend(frame_outputs);
+
+            if terminated {
+                break;
+            }
+        }
+
+        Ok(stage_outputs)
+    }
+
+    fn resynchronize(&mut self) -> Result<Synchronization, RdpError> {
+        let synchronization = crate::codecs::resynchronize(&mut self.received)?;
+        if let Synchronization::Resynchronized { skipped_bytes } = synchronization {
+            warn!("Skipped {} bytes to resynchronize on the next frame", skipped_bytes);
+            self.received_offset += skipped_bytes as u64;
+        }
+
+        Ok(synchronization)
+    }
+
+    fn process_audited(
+        &mut self,
+        image: &mut dyn ImageSink,
+        frame: BytesMut,
+    ) -> Result<Vec<ActiveStageOutput>, RdpError> {
+        #[cfg(feature = "alloc-audit")]
+        {
+            crate::alloc_audit::start_frame();
+            let stage_outputs = self.process_frame(image, frame);
+            self.last_frame_allocations = crate::alloc_audit::finish_frame();
+            debug!("Frame allocations: {}", self.last_frame_allocations);
+
+            stage_outputs
+        }
+
+        #[cfg(not(feature = "alloc-audit"))]
+        self.process_frame(image, frame)
+    }
+
+    fn process_frame(
+        &mut self,
+        image: &mut dyn ImageSink,
+        frame: BytesMut,
+    ) -> Result<Vec<ActiveStageOutput>, RdpError> {
+        if !self.frame_metadata_enabled {
+            let stage_outputs = self.decode_frame(image, frame);
+            self.take_frame_id();
+
+            return stage_outputs;
+        }
+
+        let received_at = Instant::now();
+        let mut tracker = FrameMetadataTracker::new(image);
+        let mut stage_outputs = self.decode_frame(&mut tracker, frame)?;
+        let frame_id = self.take_frame_id();
+
+        if let Some(frame_metadata) = tracker.finish(frame_id, received_at) {
+            let position = stage_outputs
+                .iter()
+                .position(|output| matches!(output, ActiveStageOutput::GraphicsUpdate(_)))
+                .map_or(stage_outputs.len(), |position| position + 1);
+            stage_outputs.insert(position, ActiveStageOutput::FrameMetadata(frame_metadata));
+        }
+
+        Ok(stage_outputs)
+    }
+
+    fn take_frame_id(&mut self) -> Option<u32> {
+        let fast_path_frame_id = self.fast_path_processor.take_frame_id();
+        let x224_frame_id = self.x224_processor.take_frame_id();
+
+        fast_path_frame_id.or(x224_frame_id)
+    }
+
+    fn decode_frame(&mut self, image: &mut dyn ImageSink, frame: BytesMut) -> Result<Vec<ActiveStageOutput>, RdpError> {
+        let offset = self.received_offset;
+        self.received_offset += frame.len() as u64;
+        self.pdu_hooks.start_frame();
+
+        self.decode_frame_pdus(image, frame).map_err(|source| RdpError::InPdu {
+            context: Box::new(self.pdu_hooks.frame_context(offset)),
+            source: Box::new(source),
+        })
+    }
+
+    fn decode_frame_pdus(
+        &mut self,
+        image: &mut dyn ImageSink,
         frame: BytesMut,
     ) -> Result<Vec<ActiveStageOutput>, RdpError> {
         let mut output_writer = BytesMut::new().writer();
         let mut frame_reader = frame.as_ref();
+        let frame_length = frame.len();
         let mut graphics_update_region = None;
+        let mut x224_output = None;
 
         match RdpTransport.decode(&mut frame_reader) {
             Ok(RdpPdu::X224(data)) => {
-                if let Err(error) = self.x224_processor.process(frame_reader, &mut output_writer, data) {
-                    match error {
-                        RdpError::UnexpectedDisconnection(message) => {
-                            warn!("User-Initiated disconnection on Server: {}", message);
-                            return Ok(vec![ActiveStageOutput::Terminate]);
-                        }
-                        RdpError::UnexpectedChannel(channel_id) => {
-                            warn!("Got message on a channel with {} ID", channel_id);
-                            return Ok(vec![ActiveStageOutput::Terminate]);
-                        }
-                        err => {
-                            return Err(err);
-                        }
+                #[cfg(feature = "alloc-audit")]
+                let _subsystem = crate::alloc_audit::enter(crate::alloc_audit::Subsystem::X224);
+
+                match self.x224_processor.process(
+                    frame_reader,
+                    &mut output_writer,
+                    data,
+                    image,
+                    &mut self.pdu_hooks,
+                    &mut self.bulk_decompressor,
+                ) {
+                    Ok(output) => x224_output = output,
+                    Err(RdpError::UnexpectedDisconnection(message)) => {
+                        warn!("User-Initiated disconnection on Server: {}", message);
+                        return Ok(vec![ActiveStageOutput::Terminate]);
+                    }
+                    Err(RdpError::UnexpectedChannel(channel_id)) => {
+                        warn!("Got message on a channel with {} ID", channel_id);
+                        return Ok(vec![ActiveStageOutput::Terminate]);
+                    }
+                    Err(err) => {
+                        return Err(err);
+                    }
+                }
+
+                if let Some(area) = self.x224_processor.take_refresh_request() {
+                    self.encode_refresh_rectangle(area, &mut output_writer)?;
+                }
+
+                for pointer in self.x224_processor.take_pointer_pdus() {
+                    match pointer.update() {
+                        Ok(update) => self.fast_path_processor.process_pointer_update(update),
+                        Err(error) => warn!("Received invalid pointer update: {:?}", error),
                     }
                 }
             }
@@ -69,9 +779,19 @@ impl ActiveStageProcessor {
                 // that data length was written in the not right way,
                 // so we should skip only what has been actually read
 
-                graphics_update_region =
-                    self.fast_path_processor
-                        .process(image, &header, frame_reader, &mut output_writer)?;
+                #[cfg(feature = "alloc-audit")]
+                let _subsystem = crate::alloc_audit::enter(crate::alloc_audit::Subsystem::FastPath);
+
+                self.pdu_hooks.receiving_on(PduChannel::FastPath);
+
+                graphics_update_region = self.fast_path_processor.process(
+                    image,
+                    &header,
+                    frame_reader,
+                    &mut output_writer,
+                    &mut self.pdu_hooks,
+                    &mut self.bulk_decompressor,
+                )?;
             }
             Err(RdpError::FastPathError(FastPathError::NullLength { bytes_read: _ })) => {
                 warn!("Received null-length Fast-Path packet, dropping it");
@@ -79,9 +799,12 @@ impl ActiveStageProcessor {
             Err(e) => return Err(e),
         }
 
+        self.pdu_hooks.frame_received(frame_length);
+
         let mut stage_outputs = Vec::new();
 
         let output_buffer = output_writer.into_inner();
+        self.pdu_hooks.frame_sent(output_buffer.len());
         if !output_buffer.is_empty() {
             stage_outputs.push(ActiveStageOutput::ResponseFrame(output_buffer));
         }
@@ -90,6 +813,17 @@ impl ActiveStageProcessor {
             stage_outputs.push(ActiveStageOutput::GraphicsUpdate(update_region));
         }
 
+        stage_outputs.extend(x224_output);
+
+        let pointer_changes = self.fast_path_processor.take_pointer_changes();
+        stage_outputs.extend(pointer_changes.into_iter().map(ActiveStageOutput::Pointer));
+
+        let mut frame_events = self.fast_path_processor.take_frame_events();
+        frame_events.extend(self.x224_processor.take_frame_events());
+        if self.frame_events_enabled {
+            stage_outputs.extend(frame_events.into_iter().map(ActiveStageOutput::GraphicsFrame));
+        }
+
         Ok(stage_outputs)
     }
 }
@@ -97,5 +831,62 @@ impl ActiveStageProcessor {
 pub enum ActiveStageOutput {
     ResponseFrame(BytesMut),
     GraphicsUpdate(Rectangle),
+    /// Follows the [`ActiveStageOutput::GraphicsUpdate`] it describes, if enabled with
+    /// [`ActiveStageProcessor::set_frame_metadata_enabled`].
+    FrameMetadata(FrameMetadata),
+    /// The server has synchronized the toggle keys state, typically after
+    /// the input language of the remote session has been switched.
+    KeyboardIndicators(LedFlags),
+    /// The IME state of the remote session has changed, the client should
+    /// update its local layout mapping to keep scancode translation correct.
+    KeyboardImeStatus(SetKeyboardImeStatusPdu),
+    /// The lock state of the remote session has changed, kiosk clients
+    /// may blank the local display while the session is locked.
+    SessionLockState(SessionLockState),
+    /// The pointer of the remote session has changed, for the local cursor to mirror it.
+    Pointer(PointerChange),
+    /// A frame of the Graphics Pipeline has been started or ended, or a frame of the surface commands has been ended,
+    /// if enabled with [`ActiveStageProcessor::set_frame_events_enabled`]. The events follow the
+    /// [`ActiveStageOutput::GraphicsUpdate`] of the frame they were received in.
+    GraphicsFrame(GraphicsFrameEvent),
+    /// The fed bytes did not start with a plausible frame header, e.g. after a corrupted packet,
+    /// and have been skipped up to the next one.
+    Desynchronized {
+        skipped_bytes: usize,
+    },
     Terminate,
 }
+
+/// Delimits the updates of a frame of the Graphics Pipeline, or of the surface commands delimited by frame markers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GraphicsFrameEvent {
+    /// The server has started the frame at the time of its clock, which it increases
+    /// at the pace it wants the frames to be presented.
+    Started { frame_id: u32, timestamp: Timestamp },
+    /// The updates of the frame have all been passed to the image sink, so the frame can be presented.
+    Ended { frame_id: u32 },
+}
+
+/// Lock state of the remote session, as far as it can be inferred from the logon
+/// notifications and the status info sent by the server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SessionLockState {
+    /// The user has logged on, the remote session is displayed normally.
+    Unlocked,
+    /// The user has to log on again, e.g. after the credentials were rejected
+    /// or when another connection is already attached to the session.
+    LogonRequired(LogonErrorsInfo),
+    /// The session is not online yet, e.g. while the virtual machine hosting it is resuming.
+    Pending(StatusCode),
+}
+
+impl SessionLockState {
+    pub fn is_locked(&self) -> bool {
+        !matches!(self, Self::Unlocked)
+    }
+}
+
+/// Whether the mouse event only moves the pointer, the position being the only state the server keeps of it.
+fn is_mouse_move(pdu: &MousePdu) -> bool {
+    pdu.movement_events.contains(MovementEvents::MOVE) && pdu.button_events.is_empty() && pdu.wheel_events.is_empty()
+}
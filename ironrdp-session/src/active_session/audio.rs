@@ -0,0 +1,34 @@
+use ironrdp::rdp::vc::rdpsnd::AudioFormat;
+
+use crate::RdpError;
+
+/// Destination of the audio output redirected by the server over the `rdpsnd` channel.
+///
+/// Only the formats accepted by [`AudioSink::supports`] are advertised to the server,
+/// so by default the sink receives uncompressed PCM samples.
+pub trait AudioSink: Send {
+    /// Plays an audio sample encoded in one of the formats accepted by the sink.
+    fn play(&mut self, format: &AudioFormat, data: &[u8]) -> Result<(), RdpError>;
+
+    /// Returns `true` if the sink can play the samples of the format offered by the server.
+    fn supports(&self, format: &AudioFormat) -> bool {
+        format.is_pcm()
+    }
+
+    /// Sets the volume of the left and right channels, `0xFFFF` being the full volume.
+    fn set_volume(&mut self, _left: u16, _right: u16) {}
+
+    /// Called when the server stops sending audio samples, e.g. before the session is disconnected.
+    fn close(&mut self) {}
+}
+
+impl<F> AudioSink for F
+where
+    F: FnMut(&AudioFormat, &[u8]) + Send,
+{
+    fn play(&mut self, format: &AudioFormat, data: &[u8]) -> Result<(), RdpError> {
+        self(format, data);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,130 @@
+#[cfg(test)]
+mod tests;
+
+use std::collections::{BTreeMap, HashMap};
+
+use ironrdp::rdp::vc::{DvcName, StaticChannelName};
+use ironrdp::ChannelId;
+
+/// A change of the lifecycle of a dynamic channel, passed to the channel hooks.
+///
+/// The static channels are all joined during the connection sequence, and stay open for the whole session.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChannelEvent {
+    /// The server has requested the creation of the channel, the client not having answered yet.
+    DynamicChannelRequested {
+        channel_id: u32,
+        name: String,
+    },
+    DynamicChannelOpened {
+        channel_id: u32,
+        name: DvcName,
+    },
+    /// The client has no handler for the channel, or its name is invalid.
+    DynamicChannelRejected {
+        channel_id: u32,
+        name: String,
+    },
+    DynamicChannelClosed {
+        channel_id: u32,
+        name: DvcName,
+    },
+}
+
+pub type ChannelHook = Box<dyn FnMut(&ChannelEvent) + Send>;
+
+/// The channels of the session: the joined static channels with their MCS IDs, the open dynamic channels,
+/// and the dynamic channels whose creation is pending.
+pub struct ChannelRegistry {
+    static_channels: BTreeMap<ChannelId, StaticChannelName>,
+    dynamic_channels: BTreeMap<u32, DvcName>,
+    pending_dynamic_channels: BTreeMap<u32, String>,
+    hooks: Vec<ChannelHook>,
+}
+
+impl ChannelRegistry {
+    pub(crate) fn new(static_channels: HashMap<ChannelId, StaticChannelName>) -> Self {
+        Self {
+            static_channels: static_channels.into_iter().collect(),
+            dynamic_channels: BTreeMap::new(),
+            pending_dynamic_channels: BTreeMap::new(),
+            hooks: Vec::new(),
+        }
+    }
+
+    /// The joined static channels by MCS channel ID, the I/O and user channels included.
+    pub fn static_channels(&self) -> impl Iterator<Item = (ChannelId, &StaticChannelName)> {
+        self.static_channels
+            .iter()
+            .map(|(channel_id, name)| (*channel_id, name))
+    }
+
+    pub fn static_channel_id(&self, name: &StaticChannelName) -> Option<ChannelId> {
+        self.static_channels()
+            .find_map(|(channel_id, channel_name)| (channel_name == name).then_some(channel_id))
+    }
+
+    pub fn static_channel_name(&self, channel_id: ChannelId) -> Option<&StaticChannelName> {
+        self.static_channels.get(&channel_id)
+    }
+
+    /// The open dynamic channels by channel ID.
+    pub fn dynamic_channels(&self) -> impl Iterator<Item = (u32, &DvcName)> {
+        self.dynamic_channels
+            .iter()
+            .map(|(channel_id, name)| (*channel_id, name))
+    }
+
+    pub fn dynamic_channel_id(&self, name: &DvcName) -> Option<u32> {
+        self.dynamic_channels()
+            .find_map(|(channel_id, channel_name)| (channel_name == name).then_some(channel_id))
+    }
+
+    pub fn dynamic_channel_name(&self, channel_id: u32) -> Option<&DvcName> {
+        self.dynamic_channels.get(&channel_id)
+    }
+
+    /// The dynamic channels requested by the server which the client has not answered yet, by channel ID.
+    pub fn pending_dynamic_channels(&self) -> impl Iterator<Item = (u32, &str)> {
+        self.pending_dynamic_channels
+            .iter()
+            .map(|(channel_id, name)| (*channel_id, name.as_str()))
+    }
+
+    pub(crate) fn add_hook(&mut self, hook: ChannelHook) {
+        self.hooks.push(hook);
+    }
+
+    pub(crate) fn dynamic_channel_requested(&mut self, channel_id: u32, name: &str) {
+        self.pending_dynamic_channels.insert(channel_id, String::from(name));
+        self.notify(ChannelEvent::DynamicChannelRequested {
+            channel_id,
+            name: String::from(name),
+        });
+    }
+
+    pub(crate) fn dynamic_channel_opened(&mut self, channel_id: u32, name: DvcName) {
+        self.pending_dynamic_channels.remove(&channel_id);
+        self.dynamic_channels.insert(channel_id, name.clone());
+        self.notify(ChannelEvent::DynamicChannelOpened { channel_id, name });
+    }
+
+    pub(crate) fn dynamic_channel_rejected(&mut self, channel_id: u32) {
+        if let Some(name) = self.pending_dynamic_channels.remove(&channel_id) {
+            self.notify(ChannelEvent::DynamicChannelRejected { channel_id, name });
+        }
+    }
+
+    /// Removes the channel, the close requests of the channels which have not been opened being ignored.
+    pub(crate) fn dynamic_channel_closed(&mut self, channel_id: u32) {
+        if let Some(name) = self.dynamic_channels.remove(&channel_id) {
+            self.notify(ChannelEvent::DynamicChannelClosed { channel_id, name });
+        }
+    }
+
+    fn notify(&mut self, event: ChannelEvent) {
+        for hook in self.hooks.iter_mut() {
+            hook(&event);
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use std::sync::{Arc, Mutex};
+
+use super::*;
+
+fn registry_with_events() -> (ChannelRegistry, Arc<Mutex<Vec<ChannelEvent>>>) {
+    let static_channels = [
+        (ChannelId(1003), StaticChannelName::from_static("GLOBAL")),
+        (ChannelId(1004), StaticChannelName::DRDYNVC),
+    ];
+    let mut registry = ChannelRegistry::new(static_channels.into_iter().collect());
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let hook_events = events.clone();
+    registry.add_hook(Box::new(move |event| hook_events.lock().unwrap().push(event.clone())));
+
+    (registry, events)
+}
+
+#[test]
+fn static_channels_are_found_by_name_and_id() {
+    let (registry, _) = registry_with_events();
+
+    assert_eq!(
+        Some(ChannelId(1004)),
+        registry.static_channel_id(&StaticChannelName::DRDYNVC)
+    );
+    assert_eq!(None, registry.static_channel_id(&StaticChannelName::RDPSND));
+    assert_eq!(
+        Some(&StaticChannelName::from_static("GLOBAL")),
+        registry.static_channel_name(ChannelId(1003))
+    );
+    assert_eq!(
+        vec![ChannelId(1003), ChannelId(1004)],
+        registry
+            .static_channels()
+            .map(|(channel_id, _)| channel_id)
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn dynamic_channel_goes_through_pending_open_and_closed() {
+    let (mut registry, events) = registry_with_events();
+
+    registry.dynamic_channel_requested(5, "Microsoft::Windows::RDS::DisplayControl");
+    assert_eq!(
+        vec![(5, "Microsoft::Windows::RDS::DisplayControl")],
+        registry.pending_dynamic_channels().collect::<Vec<_>>()
+    );
+    assert_eq!(None, registry.dynamic_channel_id(&DvcName::DISPLAY_CONTROL));
+
+    registry.dynamic_channel_opened(5, DvcName::DISPLAY_CONTROL);
+    assert_eq!(0, registry.pending_dynamic_channels().count());
+    assert_eq!(Some(5), registry.dynamic_channel_id(&DvcName::DISPLAY_CONTROL));
+
+    registry.dynamic_channel_closed(5);
+    registry.dynamic_channel_closed(5);
+    assert_eq!(0, registry.dynamic_channels().count());
+
+    assert_eq!(
+        vec![
+            ChannelEvent::DynamicChannelRequested {
+                channel_id: 5,
+                name: String::from("Microsoft::Windows::RDS::DisplayControl"),
+            },
+            ChannelEvent::DynamicChannelOpened {
+                channel_id: 5,
+                name: DvcName::DISPLAY_CONTROL,
+            },
+            ChannelEvent::DynamicChannelClosed {
+                channel_id: 5,
+                name: DvcName::DISPLAY_CONTROL,
+            },
+        ],
+        *events.lock().unwrap()
+    );
+}
+
+#[test]
+fn rejected_dynamic_channel_is_never_opened() {
+    let (mut registry, events) = registry_with_events();
+
+    registry.dynamic_channel_requested(7, "unknown");
+    registry.dynamic_channel_rejected(7);
+    registry.dynamic_channel_closed(7);
+
+    assert_eq!(0, registry.pending_dynamic_channels().count());
+    assert_eq!(0, registry.dynamic_channels().count());
+    assert_eq!(
+        ChannelEvent::DynamicChannelRejected {
+            channel_id: 7,
+            name: String::from("unknown"),
+        },
+        events.lock().unwrap()[1]
+    );
+    assert_eq!(2, events.lock().unwrap().len());
+}
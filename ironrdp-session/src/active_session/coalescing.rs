@@ -0,0 +1,89 @@
+#[cfg(test)]
+mod tests;
+
+use std::time::{Duration, Instant};
+
+/// Limits the rate of the PDUs of which only the last one matters, e.g. the acknowledgement of the latest frame
+/// or the latest position of the mouse, to save the upstream bandwidth of high-latency links.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CoalescingConfig {
+    /// The minimum interval between two PDUs sent, the PDUs in between replacing each other.
+    pub min_interval: Duration,
+    /// The maximum number of PDUs coalesced into the one sent, regardless of the interval.
+    pub max_batch: usize,
+}
+
+/// Holds back the PDUs sent before the interval has elapsed, the last one being sent once it has.
+///
+/// When a PDU is pending, it has to be polled at the [`Coalescer::deadline`] even if no new PDU has been pushed meanwhile.
+pub(crate) struct Coalescer<T> {
+    config: Option<CoalescingConfig>,
+    next_send: Option<Instant>,
+    pending: Option<T>,
+    pending_count: usize,
+}
+
+impl<T> Coalescer<T> {
+    /// Sends every PDU as it is pushed if `config` is `None`.
+    pub(crate) fn new(config: Option<CoalescingConfig>) -> Self {
+        Self {
+            config,
+            next_send: None,
+            pending: None,
+            pending_count: 0,
+        }
+    }
+
+    /// Returns the PDU to send now, if any, the pending one being replaced.
+    ///
+    /// The clock is only read if the coalescing is enabled, [`Instant::now`] not being available on `wasm32-unknown-unknown`.
+    pub(crate) fn push(&mut self, pdu: T) -> Option<T> {
+        if self.config.is_none() {
+            return Some(pdu);
+        }
+
+        self.push_at(pdu, Instant::now())
+    }
+
+    pub(crate) fn push_at(&mut self, pdu: T, now: Instant) -> Option<T> {
+        let Some(config) = self.config else {
+            return Some(pdu);
+        };
+
+        self.pending = Some(pdu);
+        self.pending_count += 1;
+
+        if self.pending_count >= config.max_batch {
+            self.send(now)
+        } else {
+            self.poll(now)
+        }
+    }
+
+    /// Returns the pending PDU to send if the interval has elapsed.
+    pub(crate) fn poll(&mut self, now: Instant) -> Option<T> {
+        if self.next_send.map_or(true, |next_send| next_send <= now) {
+            self.send(now)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the pending PDU regardless of the interval, for the PDUs following it to be sent in order.
+    pub(crate) fn flush(&mut self) -> Option<T> {
+        self.pending_count = 0;
+        self.pending.take()
+    }
+
+    /// Returns the instant at which the pending PDU, if any, is due to be sent.
+    pub(crate) fn deadline(&self) -> Option<Instant> {
+        self.pending.as_ref().and(self.next_send)
+    }
+
+    fn send(&mut self, now: Instant) -> Option<T> {
+        let pdu = self.flush()?;
+        self.next_send = self.config.map(|config| now + config.min_interval);
+
+        Some(pdu)
+    }
+}
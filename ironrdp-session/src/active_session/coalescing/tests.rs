@@ -0,0 +1,61 @@
+use super::*;
+
+const INTERVAL: Duration = Duration::from_millis(50);
+
+fn coalescer(max_batch: usize) -> Coalescer<u32> {
+    Coalescer::new(Some(CoalescingConfig {
+        min_interval: INTERVAL,
+        max_batch,
+    }))
+}
+
+#[test]
+fn pdus_are_sent_as_pushed_without_config() {
+    let mut coalescer = Coalescer::new(None);
+    let now = Instant::now();
+
+    assert_eq!(Some(1), coalescer.push_at(1, now));
+    assert_eq!(Some(2), coalescer.push_at(2, now));
+    assert_eq!(None, coalescer.deadline());
+}
+
+#[test]
+fn last_pdu_pushed_during_interval_is_sent_at_deadline() {
+    let mut coalescer = coalescer(10);
+    let now = Instant::now();
+
+    assert_eq!(Some(1), coalescer.push_at(1, now));
+    assert_eq!(None, coalescer.push_at(2, now + Duration::from_millis(10)));
+    assert_eq!(None, coalescer.push_at(3, now + Duration::from_millis(20)));
+    assert_eq!(Some(now + INTERVAL), coalescer.deadline());
+
+    assert_eq!(None, coalescer.poll(now + Duration::from_millis(49)));
+    assert_eq!(Some(3), coalescer.poll(now + INTERVAL));
+    assert_eq!(None, coalescer.deadline());
+}
+
+#[test]
+fn pending_pdu_is_sent_once_max_batch_is_reached() {
+    let mut coalescer = coalescer(3);
+    let now = Instant::now();
+
+    assert_eq!(Some(1), coalescer.push_at(1, now));
+    assert_eq!(None, coalescer.push_at(2, now));
+    assert_eq!(None, coalescer.push_at(3, now));
+    assert_eq!(Some(4), coalescer.push_at(4, now));
+    assert_eq!(None, coalescer.deadline());
+}
+
+#[test]
+fn flushed_pdu_does_not_restart_interval() {
+    let mut coalescer = coalescer(10);
+    let now = Instant::now();
+
+    assert_eq!(Some(1), coalescer.push_at(1, now));
+    assert_eq!(None, coalescer.push_at(2, now + Duration::from_millis(10)));
+    assert_eq!(Some(2), coalescer.flush());
+    assert_eq!(None, coalescer.flush());
+
+    assert_eq!(None, coalescer.push_at(3, now + Duration::from_millis(20)));
+    assert_eq!(Some(3), coalescer.poll(now + INTERVAL));
+}
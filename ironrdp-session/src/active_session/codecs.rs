@@ -1 +1,16 @@
+#[cfg(feature = "bitmap")]
+pub mod bitmap;
+#[cfg(feature = "zgfx")]
+pub mod clearcodec;
+#[cfg(feature = "h264")]
+pub mod h264;
+#[cfg(feature = "nscodec")]
+pub mod nscodec;
+#[cfg(feature = "zgfx")]
+pub mod planar;
+#[cfg(feature = "zgfx")]
+pub mod progressive;
+#[cfg(feature = "rfx")]
 pub mod rfx;
+#[cfg(feature = "zgfx")]
+mod surface;
@@ -0,0 +1,403 @@
+#[cfg(test)]
+mod tests;
+
+use std::collections::HashMap;
+use std::io;
+
+use ironrdp::bitmap::{rle, Bitmap, Compression};
+use ironrdp::codecs::planar;
+use ironrdp::codecs::rfx::image_processing::{PixelFormat, Rgba};
+use ironrdp::fast_path::FastPathError;
+use ironrdp::orders::{
+    Bounds, CacheBitmapRev2Flags, DrawingOrder, DrawingOrders, MemBlt, PrimaryOrderState,
+    BITMAP_CACHE_WAITING_LIST_INDEX, SRCCOPY_ROP,
+};
+use ironrdp::Rectangle;
+use log::{debug, warn};
+
+use crate::image::{ImageSink, ImageUpdate};
+use crate::{Codec, RdpError};
+
+const DEFAULT_PIXEL_FORMAT: PixelFormat = PixelFormat::BgrX32;
+
+/// The buffers the bitmaps are decoded in before being stored from the top to the bottom.
+#[derive(Default)]
+struct DecodingBuffers {
+    /// The bitmap decompressed with the interleaved RLE, in its color depth.
+    decompressed: Vec<u8>,
+    /// The bitmap being decoded, whose rows are stored from the bottom to the top.
+    bottom_up: Vec<u8>,
+}
+
+/// A bitmap decoded in the output pixel format, its rows going from the top to the bottom.
+struct DecodedBitmap {
+    width: u16,
+    height: u16,
+    data: Vec<u8>,
+}
+
+/// Draws the bitmap updates, and the bitmaps cached by the server through the Cache Bitmap (Revision 2) orders
+/// which the MemBlt orders refer to.
+pub struct DecodingContext {
+    pixel_format: PixelFormat,
+    /// The cached bitmaps by cache ID and cache index.
+    cache: HashMap<(u8, u16), DecodedBitmap>,
+    order_state: PrimaryOrderState,
+    buffers: DecodingBuffers,
+}
+
+impl Default for DecodingContext {
+    fn default() -> Self {
+        Self {
+            pixel_format: DEFAULT_PIXEL_FORMAT,
+            cache: HashMap::new(),
+            order_state: PrimaryOrderState::default(),
+            buffers: DecodingBuffers::default(),
+        }
+    }
+}
+
+impl DecodingContext {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the pixel format of the bitmaps passed to the image sink, `BgrX32` by default.
+    pub fn with_pixel_format(mut self, pixel_format: PixelFormat) -> Self {
+        self.pixel_format = pixel_format;
+
+        self
+    }
+
+    /// Draws the rectangles of the bitmap update, the bitmaps of unsupported formats being skipped.
+    /// Returns the updated region, if any.
+    pub fn process_bitmap(
+        &mut self,
+        image: &mut dyn ImageSink,
+        bitmap: &Bitmap<'_>,
+    ) -> Result<Option<Rectangle>, RdpError> {
+        let mut update_region: Option<Rectangle> = None;
+
+        for bitmap_data in &bitmap.rectangles {
+            let decoded = match decode_bitmap(
+                &mut self.buffers,
+                self.pixel_format,
+                bitmap_data.bitmap_data,
+                bitmap_data.width,
+                bitmap_data.height,
+                bitmap_data.bits_per_pixel,
+                bitmap_data.compression_flags.contains(Compression::COMPRESSED_HDR),
+            ) {
+                Err(error @ RdpError::UnsupportedBitmap { .. }) => {
+                    warn!("Skipping bitmap: {}", error);
+                    continue;
+                }
+                decoded => decoded?,
+            };
+
+            // the bounds of the destination are inclusive, and the bitmap may be larger than the destination
+            let destination = &bitmap_data.rectangle;
+            let rectangle = Rectangle {
+                left: destination.left,
+                top: destination.top,
+                right: (destination.right + 1).min(destination.left + u32::from(decoded.width)),
+                bottom: (destination.bottom + 1).min(destination.top + u32::from(decoded.height)),
+            };
+            if rectangle.width() == 0 || rectangle.height() == 0 {
+                continue;
+            }
+
+            draw(image, self.pixel_format, &decoded, 0, 0, &rectangle)?;
+            update_region = Some(union(update_region, rectangle));
+        }
+
+        Ok(update_region)
+    }
+
+    /// Caches the bitmaps of the Orders update and draws its MemBlt orders, the other orders being skipped.
+    /// Returns the updated region, if any.
+    pub fn process_orders(&mut self, image: &mut dyn ImageSink, input: &[u8]) -> Result<Option<Rectangle>, RdpError> {
+        let mut update_region: Option<Rectangle> = None;
+
+        let orders = DrawingOrders::from_buffer(input, &mut self.order_state).map_err(FastPathError::from)?;
+        for order in orders {
+            match order.map_err(FastPathError::from)? {
+                DrawingOrder::CacheBitmapRev2(order) => {
+                    if order.flags.contains(CacheBitmapRev2Flags::DO_NOT_CACHE)
+                        || order.cache_index == BITMAP_CACHE_WAITING_LIST_INDEX
+                    {
+                        debug!("Skipping bitmap not to be cached");
+                        continue;
+                    }
+
+                    match decode_bitmap(
+                        &mut self.buffers,
+                        self.pixel_format,
+                        order.bitmap_data,
+                        order.width,
+                        order.height,
+                        order.bits_per_pixel,
+                        order.compressed,
+                    ) {
+                        Ok(decoded) => {
+                            self.cache.insert((order.cache_id, order.cache_index), decoded);
+                        }
+                        Err(error @ RdpError::UnsupportedBitmap { .. }) => {
+                            warn!("Skipping cached bitmap: {}", error);
+                        }
+                        Err(error) => return Err(error),
+                    }
+                }
+                DrawingOrder::MemBlt { order, bounds } => {
+                    if let Some(rectangle) = mem_blt(image, self.pixel_format, &self.cache, &order, bounds)? {
+                        update_region = Some(union(update_region, rectangle));
+                    }
+                }
+                DrawingOrder::UnsupportedSecondary(order_type) => {
+                    debug!("Skipping unsupported secondary order: {:?}", order_type);
+                }
+            }
+        }
+
+        Ok(update_region)
+    }
+}
+
+fn union(update_region: Option<Rectangle>, rectangle: Rectangle) -> Rectangle {
+    match update_region {
+        Some(update_region) => update_region.union(&rectangle),
+        None => rectangle,
+    }
+}
+
+/// Draws the cached bitmap, clipped to the bounds of the order. Returns the drawn rectangle, if any.
+fn mem_blt(
+    image: &mut dyn ImageSink,
+    pixel_format: PixelFormat,
+    cache: &HashMap<(u8, u16), DecodedBitmap>,
+    order: &MemBlt,
+    bounds: Option<Bounds>,
+) -> Result<Option<Rectangle>, RdpError> {
+    if order.rop != SRCCOPY_ROP {
+        warn!(
+            "Skipping MemBlt order with unsupported raster operation: {:#x}",
+            order.rop
+        );
+        return Ok(None);
+    }
+
+    let bitmap = match cache.get(&(order.cache_id, order.cache_index)) {
+        Some(bitmap) => bitmap,
+        None => {
+            warn!(
+                "Skipping MemBlt order of missing cached bitmap {} in cache {}",
+                order.cache_index, order.cache_id
+            );
+            return Ok(None);
+        }
+    };
+
+    let mut left = i32::from(order.left);
+    let mut top = i32::from(order.top);
+    let mut right = left + i32::from(order.width);
+    let mut bottom = top + i32::from(order.height);
+    if let Some(bounds) = bounds {
+        left = left.max(i32::from(bounds.left));
+        top = top.max(i32::from(bounds.top));
+        right = right.min(i32::from(bounds.right) + 1);
+        bottom = bottom.min(i32::from(bounds.bottom) + 1);
+    }
+
+    // the source is clipped along with the destination, neither of them starting before the origin
+    let mut source_x = i32::from(order.source_x) + left - i32::from(order.left);
+    let mut source_y = i32::from(order.source_y) + top - i32::from(order.top);
+    let shift_x = 0.max(-left).max(-source_x);
+    let shift_y = 0.max(-top).max(-source_y);
+    left += shift_x;
+    source_x += shift_x;
+    top += shift_y;
+    source_y += shift_y;
+    right = right.min(left + i32::from(bitmap.width) - source_x);
+    bottom = bottom.min(top + i32::from(bitmap.height) - source_y);
+
+    if left >= right || top >= bottom {
+        return Ok(None);
+    }
+
+    let rectangle = Rectangle {
+        left: left as u32,
+        top: top as u32,
+        right: right as u32,
+        bottom: bottom as u32,
+    };
+    draw(
+        image,
+        pixel_format,
+        bitmap,
+        source_x as usize,
+        source_y as usize,
+        &rectangle,
+    )?;
+
+    Ok(Some(rectangle))
+}
+
+/// Passes the part of the bitmap starting at the source coordinates to the image sink.
+fn draw(
+    image: &mut dyn ImageSink,
+    pixel_format: PixelFormat,
+    bitmap: &DecodedBitmap,
+    source_x: usize,
+    source_y: usize,
+    destination: &Rectangle,
+) -> Result<(), RdpError> {
+    let bytes_per_pixel = usize::from(pixel_format.bytes_per_pixel());
+    let stride = usize::from(bitmap.width) * bytes_per_pixel;
+    let data = bitmap
+        .data
+        .get(source_y * stride + source_x * bytes_per_pixel..)
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("got source ({}, {}) outside of the bitmap", source_x, source_y),
+            )
+        })?;
+
+    image.update(&ImageUpdate {
+        rectangle: destination.clone(),
+        pixel_format,
+        stride,
+        data,
+        codec: Codec::Bitmap,
+    })
+}
+
+/// Decodes the bitmaps compressed with the interleaved RLE, the 32 bpp ones compressed with the planar codec,
+/// and the uncompressed ones. The bitmaps of 8 bpp, requiring a palette, are not supported.
+fn decode_bitmap(
+    buffers: &mut DecodingBuffers,
+    pixel_format: PixelFormat,
+    data: &[u8],
+    width: u16,
+    height: u16,
+    bits_per_pixel: u16,
+    compressed: bool,
+) -> Result<DecodedBitmap, RdpError> {
+    let pixel_width = usize::from(width);
+    let pixel_height = usize::from(height);
+
+    let bottom_up = &mut buffers.bottom_up;
+    let source_bytes_per_pixel = usize::from((bits_per_pixel + 7) / 8);
+
+    match (bits_per_pixel, compressed) {
+        (32, true) => planar::decode(data, pixel_width, pixel_height, pixel_format, bottom_up)?,
+        (15 | 16 | 24, true) => {
+            rle::decompress(
+                data,
+                pixel_width,
+                pixel_height,
+                bits_per_pixel,
+                &mut buffers.decompressed,
+            )?;
+
+            let source_stride = pixel_width * source_bytes_per_pixel;
+            decode_uncompressed(
+                &buffers.decompressed,
+                pixel_width,
+                pixel_height,
+                bits_per_pixel,
+                source_stride,
+                pixel_format,
+                bottom_up,
+            )?
+        }
+        (15 | 16 | 24 | 32, false) => {
+            // the rows of the uncompressed bitmaps are padded to a multiple of four bytes
+            let source_stride = (pixel_width * source_bytes_per_pixel + 3) & !3;
+            decode_uncompressed(
+                data,
+                pixel_width,
+                pixel_height,
+                bits_per_pixel,
+                source_stride,
+                pixel_format,
+                bottom_up,
+            )?
+        }
+        _ => {
+            return Err(RdpError::UnsupportedBitmap {
+                bits_per_pixel,
+                compressed,
+            })
+        }
+    }
+
+    let stride = pixel_width * usize::from(pixel_format.bytes_per_pixel());
+    let mut data = Vec::with_capacity(bottom_up.len());
+    if stride != 0 {
+        for row in bottom_up.chunks_exact(stride).rev() {
+            data.extend_from_slice(row);
+        }
+    }
+
+    Ok(DecodedBitmap { width, height, data })
+}
+
+/// Converts the pixels into the pixel format, the rows of the bitmap being `source_stride` bytes apart.
+fn decode_uncompressed(
+    data: &[u8],
+    width: usize,
+    height: usize,
+    bits_per_pixel: u16,
+    source_stride: usize,
+    pixel_format: PixelFormat,
+    output: &mut Vec<u8>,
+) -> Result<(), RdpError> {
+    let source_bytes_per_pixel = usize::from((bits_per_pixel + 7) / 8);
+    if data.len() < source_stride * height {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "the bitmap data is shorter than the bitmap").into());
+    }
+
+    let bytes_per_pixel = usize::from(pixel_format.bytes_per_pixel());
+    output.clear();
+    output.resize(width * height * bytes_per_pixel, 0);
+    if width == 0 {
+        return Ok(());
+    }
+
+    for (source_row, row) in data
+        .chunks_exact(source_stride)
+        .zip(output.chunks_exact_mut(width * bytes_per_pixel))
+    {
+        for (source, pixel) in source_row
+            .chunks_exact(source_bytes_per_pixel)
+            .zip(row.chunks_exact_mut(bytes_per_pixel))
+        {
+            let color = match bits_per_pixel {
+                15 => {
+                    let color = u16::from_le_bytes([source[0], source[1]]);
+                    let expand = |value: u16| ((value << 3) | (value >> 2)) as u8;
+
+                    Rgba {
+                        r: expand((color >> 10) & 0x1f),
+                        g: expand((color >> 5) & 0x1f),
+                        b: expand(color & 0x1f),
+                        a: 0xff,
+                    }
+                }
+                16 => PixelFormat::Rgb16.read_color(source)?,
+                24 => Rgba {
+                    b: source[0],
+                    g: source[1],
+                    r: source[2],
+                    a: 0xff,
+                },
+                _ => PixelFormat::BgrX32.read_color(source)?,
+            };
+
+            pixel_format.write_color(color, pixel)?;
+        }
+    }
+
+    Ok(())
+}
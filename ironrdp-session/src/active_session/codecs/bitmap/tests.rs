@@ -0,0 +1,138 @@
+use ironrdp::bitmap::BitmapData;
+
+use super::*;
+use crate::image::DecodedImage;
+
+const IMAGE_WIDTH: u32 = 4;
+const IMAGE_HEIGHT: u32 = 4;
+
+/// A 2x2 bitmap of 32 bpp, its rows going from the bottom to the top.
+#[rustfmt::skip]
+const BOTTOM_UP_BITMAP: [u8; 16] = [
+    1, 1, 1, 0, 2, 2, 2, 0,
+    3, 3, 3, 0, 4, 4, 4, 0,
+];
+
+#[rustfmt::skip]
+const CACHED_BITMAP_ORDERS: [u8; 58] = [
+    0x02, 0x00, // numberOrders
+    // Cache Bitmap (Revision 2) of 32 bpp in the cache 1, its height being the same as its width
+    0x03, 0x0d, 0x00, 0xb1, 0x00, 0x04,
+    0x02, // bitmapWidth
+    0x10, // bitmapLength
+    0x81, 0x2c, // cacheIndex
+    1, 1, 1, 0, 2, 2, 2, 0,
+    3, 3, 3, 0, 4, 4, 4, 0,
+    // MemBlt of the cached bitmap at (1, 1), its bounds only leaving the first column visible
+    0x0d, 0x0d, 0xff, 0x01,
+    0x0f, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x03, 0x00,
+    0x01, 0x00, 0x01, 0x00, 0x01, 0x00, 0x02, 0x00, 0x02, 0x00, 0xcc, 0x00, 0x00, 0x00, 0x00, 0x2c, 0x01,
+];
+
+fn pixel(image: &DecodedImage, x: u32, y: u32) -> [u8; 3] {
+    let index = ((y * IMAGE_WIDTH + x) * 4) as usize;
+    let data = image.data();
+
+    [data[index], data[index + 1], data[index + 2]]
+}
+
+fn bitmap_data(bits_per_pixel: u16, bitmap_data: &[u8]) -> BitmapData<'_> {
+    BitmapData {
+        rectangle: Rectangle {
+            left: 1,
+            top: 1,
+            right: 2,
+            bottom: 2,
+        },
+        width: 2,
+        height: 2,
+        bits_per_pixel,
+        compression_flags: Compression::empty(),
+        bitmap_data_length: bitmap_data.len(),
+        compressed_data_header: None,
+        bitmap_data,
+    }
+}
+
+#[test]
+fn uncompressed_bitmap_is_drawn_from_top_to_bottom() {
+    let mut image = DecodedImage::new(PixelFormat::BgrX32, IMAGE_WIDTH, IMAGE_HEIGHT);
+    let bitmap = Bitmap {
+        rectangles_number: 1,
+        rectangles: vec![bitmap_data(32, &BOTTOM_UP_BITMAP)],
+    };
+
+    let region = DecodingContext::new().process_bitmap(&mut image, &bitmap).unwrap();
+
+    assert_eq!(
+        Some(Rectangle {
+            left: 1,
+            top: 1,
+            right: 3,
+            bottom: 3,
+        }),
+        region
+    );
+    assert_eq!([3, 3, 3], pixel(&image, 1, 1));
+    assert_eq!([4, 4, 4], pixel(&image, 2, 1));
+    assert_eq!([1, 1, 1], pixel(&image, 1, 2));
+    assert_eq!([2, 2, 2], pixel(&image, 2, 2));
+    assert_eq!([0, 0, 0], pixel(&image, 3, 3));
+}
+
+#[test]
+fn interleaved_rle_bitmap_is_decompressed() {
+    let mut image = DecodedImage::new(PixelFormat::BgrX32, IMAGE_WIDTH, IMAGE_HEIGHT);
+    // white and black pixels, then a foreground run inverting them in the scanline above
+    let data = [0xfd, 0xfe, 0x22];
+    let bitmap = Bitmap {
+        rectangles_number: 1,
+        rectangles: vec![BitmapData {
+            compression_flags: Compression::COMPRESSED_HDR,
+            ..bitmap_data(16, &data)
+        }],
+    };
+
+    DecodingContext::new().process_bitmap(&mut image, &bitmap).unwrap();
+
+    assert_eq!([0, 0, 0], pixel(&image, 1, 1));
+    assert_eq!([0xff, 0xff, 0xff], pixel(&image, 2, 1));
+    assert_eq!([0xff, 0xff, 0xff], pixel(&image, 1, 2));
+    assert_eq!([0, 0, 0], pixel(&image, 2, 2));
+}
+
+#[test]
+fn cached_bitmap_is_drawn_within_bounds_of_mem_blt() {
+    let mut image = DecodedImage::new(PixelFormat::BgrX32, IMAGE_WIDTH, IMAGE_HEIGHT);
+
+    let region = DecodingContext::new()
+        .process_orders(&mut image, &CACHED_BITMAP_ORDERS)
+        .unwrap();
+
+    assert_eq!(
+        Some(Rectangle {
+            left: 1,
+            top: 1,
+            right: 2,
+            bottom: 3,
+        }),
+        region
+    );
+    assert_eq!([3, 3, 3], pixel(&image, 1, 1));
+    assert_eq!([1, 1, 1], pixel(&image, 1, 2));
+    assert_eq!([0, 0, 0], pixel(&image, 2, 1));
+}
+
+#[test]
+fn bitmap_requiring_palette_is_skipped() {
+    let mut image = DecodedImage::new(PixelFormat::BgrX32, IMAGE_WIDTH, IMAGE_HEIGHT);
+    let bitmap = Bitmap {
+        rectangles_number: 1,
+        rectangles: vec![bitmap_data(8, &[1, 2, 0, 0, 3, 4, 0, 0])],
+    };
+
+    let region = DecodingContext::new().process_bitmap(&mut image, &bitmap).unwrap();
+
+    assert_eq!(None, region);
+    assert!(image.data().iter().all(|&byte| byte == 0));
+}
@@ -0,0 +1,491 @@
+//! ClearCodec decoding of the Graphics Pipeline surfaces.
+//!
+//! A bitmap is made of up to three layers drawn one over the other: the residual layer of run-length encoded colors,
+//! the bands of vertical bars, which are cached across the bitmaps, and the rectangles of the subcodecs.
+//! The small bitmaps may also be cached as glyphs, to be drawn again from their index.
+
+#[cfg(test)]
+mod tests;
+
+use std::collections::HashMap;
+use std::io;
+
+use byteorder::{LittleEndian, ReadBytesExt};
+use ironrdp::codecs::nscodec;
+use ironrdp::codecs::rfx::image_processing::{PixelFormat, Rgba};
+use ironrdp::Rectangle;
+use log::warn;
+
+use super::surface::update_surface;
+use crate::image::ImageSink;
+use crate::RdpError;
+
+const DEFAULT_PIXEL_FORMAT: PixelFormat = PixelFormat::BgrX32;
+
+const FLAG_GLYPH_INDEX: u8 = 0x01;
+const FLAG_GLYPH_HIT: u8 = 0x02;
+const FLAG_CACHE_RESET: u8 = 0x04;
+
+const GLYPH_CACHE_SIZE: u16 = 4000;
+const MAX_GLYPH_PIXELS: usize = 1024;
+const VBAR_CACHE_SIZE: usize = 32_768;
+const SHORT_VBAR_CACHE_SIZE: usize = 16_384;
+const MAX_VBAR_HEIGHT: usize = 52;
+
+const VBAR_CACHE_HIT: u16 = 0x8000;
+const SHORT_VBAR_CACHE_HIT: u16 = 0x4000;
+
+const SUBCODEC_UNCOMPRESSED: u8 = 0;
+const SUBCODEC_NSCODEC: u8 = 1;
+const SUBCODEC_RLEX: u8 = 2;
+const MAX_PALETTE_COUNT: u8 = 127;
+
+/// A color of the stream, whose components are stored in the blue, green, red order.
+type Bgr = [u8; 3];
+
+/// Draws the ClearCodec bitmaps of the Graphics Pipeline surfaces, keeping the caches
+/// of the vertical bars and of the glyphs shared by all the surfaces.
+pub struct DecodingContext {
+    pixel_format: PixelFormat,
+    /// The sequence number expected for the next bitmap.
+    sequence_number: u8,
+    vbars: Vec<Vec<Bgr>>,
+    vbar_cursor: usize,
+    short_vbars: Vec<Vec<Bgr>>,
+    short_vbar_cursor: usize,
+    glyphs: HashMap<u16, Vec<u8>>,
+    /// The bitmap being decoded, in the pixel format passed to the image sink.
+    data: Vec<u8>,
+    /// The rectangle of a subcodec being decoded.
+    subcodec_data: Vec<u8>,
+}
+
+impl Default for DecodingContext {
+    fn default() -> Self {
+        Self {
+            pixel_format: DEFAULT_PIXEL_FORMAT,
+            sequence_number: 0,
+            vbars: Vec::new(),
+            vbar_cursor: 0,
+            short_vbars: Vec::new(),
+            short_vbar_cursor: 0,
+            glyphs: HashMap::new(),
+            data: Vec::new(),
+            subcodec_data: Vec::new(),
+        }
+    }
+}
+
+impl DecodingContext {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the pixel format of the bitmaps passed to the image sink, `BgrX32` by default.
+    pub fn with_pixel_format(mut self, pixel_format: PixelFormat) -> Self {
+        self.pixel_format = pixel_format;
+
+        self
+    }
+
+    /// Draws the bitmap at the destination of the surface, whose bounds are exclusive, the surface being
+    /// at the output origin. The surfaces not mapped to the output are decoded without being drawn,
+    /// for the caches to stay in sync with the server's. Returns the updated region of the output, if any.
+    pub fn decode(
+        &mut self,
+        image: &mut dyn ImageSink,
+        mut input: &[u8],
+        destination: &Rectangle,
+        output_origin: Option<(u32, u32)>,
+    ) -> Result<Option<Rectangle>, RdpError> {
+        let width = destination.width() as usize;
+        let height = destination.height() as usize;
+
+        let flags = input.read_u8()?;
+        let sequence_number = input.read_u8()?;
+        if sequence_number != self.sequence_number {
+            warn!(
+                "Expected ClearCodec bitmap {}, got {}: the cached vertical bars may be outdated",
+                self.sequence_number, sequence_number
+            );
+        }
+        self.sequence_number = sequence_number.wrapping_add(1);
+
+        if flags & FLAG_CACHE_RESET != 0 {
+            self.vbar_cursor = 0;
+            self.short_vbar_cursor = 0;
+        }
+
+        let glyph_index = if flags & FLAG_GLYPH_INDEX != 0 {
+            let glyph_index = input.read_u16::<LittleEndian>()?;
+            if glyph_index >= GLYPH_CACHE_SIZE || width * height > MAX_GLYPH_PIXELS {
+                return Err(invalid_data("invalid ClearCodec glyph"));
+            }
+
+            Some(glyph_index)
+        } else {
+            None
+        };
+
+        self.data.clear();
+        self.data
+            .resize(width * height * usize::from(self.pixel_format.bytes_per_pixel()), 0);
+
+        let mut regions = Vec::new();
+        if flags & FLAG_GLYPH_HIT != 0 {
+            let glyph = glyph_index
+                .and_then(|glyph_index| self.glyphs.get(&glyph_index))
+                .filter(|glyph| glyph.len() >= self.data.len())
+                .ok_or_else(|| invalid_data("the ClearCodec glyph is not cached"))?;
+            let length = self.data.len();
+            self.data.copy_from_slice(&glyph[..length]);
+            regions.extend(clipped_region(0, 0, width, height, width, height));
+        } else {
+            self.decode_composite(input, width, height, &mut regions)?;
+
+            if let Some(glyph_index) = glyph_index {
+                self.glyphs.insert(glyph_index, self.data.clone());
+            }
+        }
+
+        match output_origin {
+            Some(output_origin) => update_surface(
+                image,
+                &regions,
+                &self.data,
+                self.pixel_format,
+                destination,
+                output_origin,
+            ),
+            None => Ok(None),
+        }
+    }
+
+    /// Decodes the layers of the bitmap, the regions they cover being pushed in the order they are drawn.
+    fn decode_composite(
+        &mut self,
+        mut input: &[u8],
+        width: usize,
+        height: usize,
+        regions: &mut Vec<Rectangle>,
+    ) -> Result<(), RdpError> {
+        let residual_length = input.read_u32::<LittleEndian>()? as usize;
+        let bands_length = input.read_u32::<LittleEndian>()? as usize;
+        let subcodecs_length = input.read_u32::<LittleEndian>()? as usize;
+        let residual = split_to(&mut input, residual_length)?;
+        let bands = split_to(&mut input, bands_length)?;
+        let subcodecs = split_to(&mut input, subcodecs_length)?;
+
+        if !residual.is_empty() {
+            self.decode_residual(residual, width * height)?;
+            regions.extend(clipped_region(0, 0, width, height, width, height));
+        }
+        self.decode_bands(bands, width, height, regions)?;
+        self.decode_subcodecs(subcodecs, width, height, regions)?;
+
+        Ok(())
+    }
+
+    /// The residual layer, made of runs of colors, covers the whole bitmap.
+    fn decode_residual(&mut self, mut input: &[u8], pixel_count: usize) -> Result<(), RdpError> {
+        let mut position = 0;
+
+        while !input.is_empty() {
+            let color = read_color(&mut input)?;
+            let run_length = read_run_length(&mut input)?;
+            if position + run_length > pixel_count {
+                return Err(invalid_data("the ClearCodec residual layer exceeds the bitmap"));
+            }
+
+            for index in position..position + run_length {
+                write_pixel(&mut self.data, self.pixel_format, index, color)?;
+            }
+            position += run_length;
+        }
+
+        if position != pixel_count {
+            return Err(invalid_data("the ClearCodec residual layer does not cover the bitmap"));
+        }
+
+        Ok(())
+    }
+
+    /// Each column of a band is a vertical bar, either taken from the cache, or built from a short vertical bar
+    /// surrounded by the background color of the band and then cached. The short vertical bars are cached as well.
+    fn decode_bands(
+        &mut self,
+        mut input: &[u8],
+        width: usize,
+        height: usize,
+        regions: &mut Vec<Rectangle>,
+    ) -> Result<(), RdpError> {
+        while !input.is_empty() {
+            let x_start = usize::from(input.read_u16::<LittleEndian>()?);
+            let x_end = usize::from(input.read_u16::<LittleEndian>()?);
+            let y_start = usize::from(input.read_u16::<LittleEndian>()?);
+            let y_end = usize::from(input.read_u16::<LittleEndian>()?);
+            let background = read_color(&mut input)?;
+            if x_end < x_start || y_end < y_start || y_end - y_start >= MAX_VBAR_HEIGHT {
+                return Err(invalid_data("invalid ClearCodec band"));
+            }
+            let vbar_height = y_end - y_start + 1;
+
+            for x in x_start..=x_end {
+                let header = input.read_u16::<LittleEndian>()?;
+
+                let vbar_index = if header & VBAR_CACHE_HIT != 0 {
+                    usize::from(header & !VBAR_CACHE_HIT)
+                } else {
+                    let (short_vbar_index, y_on) = if header & SHORT_VBAR_CACHE_HIT != 0 {
+                        (usize::from(header & !SHORT_VBAR_CACHE_HIT), input.read_u8()?)
+                    } else {
+                        let y_on = (header & 0xff) as u8;
+                        let y_off = ((header >> 8) & 0x3f) as u8;
+                        if y_off < y_on {
+                            return Err(invalid_data("invalid ClearCodec short vertical bar"));
+                        }
+
+                        let short_vbar = (y_on..y_off)
+                            .map(|_| read_color(&mut input))
+                            .collect::<io::Result<Vec<_>>>()?;
+                        let index = self.short_vbar_cursor;
+                        store(&mut self.short_vbars, index, short_vbar);
+                        self.short_vbar_cursor = (index + 1) % SHORT_VBAR_CACHE_SIZE;
+
+                        (index, y_on)
+                    };
+
+                    let short_vbar = self
+                        .short_vbars
+                        .get(short_vbar_index)
+                        .ok_or_else(|| invalid_data("the ClearCodec short vertical bar is not cached"))?;
+                    let y_on = usize::from(y_on);
+                    let vbar = (0..vbar_height)
+                        .map(|y| {
+                            y.checked_sub(y_on)
+                                .and_then(|y| short_vbar.get(y).copied())
+                                .unwrap_or(background)
+                        })
+                        .collect();
+                    let index = self.vbar_cursor;
+                    store(&mut self.vbars, index, vbar);
+                    self.vbar_cursor = (index + 1) % VBAR_CACHE_SIZE;
+
+                    index
+                };
+
+                let vbar = self
+                    .vbars
+                    .get(vbar_index)
+                    .ok_or_else(|| invalid_data("the ClearCodec vertical bar is not cached"))?;
+                if x >= width {
+                    continue;
+                }
+                for (y, &color) in (y_start..height).zip(vbar.iter().take(vbar_height)) {
+                    write_pixel(&mut self.data, self.pixel_format, y * width + x, color)?;
+                }
+            }
+
+            regions.extend(clipped_region(x_start, y_start, x_end + 1, y_end + 1, width, height));
+        }
+
+        Ok(())
+    }
+
+    /// The rectangles of the subcodecs are uncompressed, or compressed with NSCodec or with RLEX,
+    /// which encodes runs of a color followed by suites of colors of a palette.
+    fn decode_subcodecs(
+        &mut self,
+        mut input: &[u8],
+        width: usize,
+        height: usize,
+        regions: &mut Vec<Rectangle>,
+    ) -> Result<(), RdpError> {
+        let bytes_per_pixel = usize::from(self.pixel_format.bytes_per_pixel());
+
+        while !input.is_empty() {
+            let x_start = usize::from(input.read_u16::<LittleEndian>()?);
+            let y_start = usize::from(input.read_u16::<LittleEndian>()?);
+            let subcodec_width = usize::from(input.read_u16::<LittleEndian>()?);
+            let subcodec_height = usize::from(input.read_u16::<LittleEndian>()?);
+            let length = input.read_u32::<LittleEndian>()? as usize;
+            let subcodec_id = input.read_u8()?;
+            let mut bitmap = split_to(&mut input, length)?;
+            let pixel_count = subcodec_width * subcodec_height;
+
+            self.subcodec_data.clear();
+            match subcodec_id {
+                SUBCODEC_UNCOMPRESSED => {
+                    if bitmap.len() != pixel_count * 3 {
+                        return Err(invalid_data("invalid length of the ClearCodec uncompressed rectangle"));
+                    }
+
+                    self.subcodec_data.resize(pixel_count * bytes_per_pixel, 0);
+                    for index in 0..pixel_count {
+                        let color = read_color(&mut bitmap)?;
+                        write_pixel(&mut self.subcodec_data, self.pixel_format, index, color)?;
+                    }
+                }
+                SUBCODEC_NSCODEC => nscodec::decode(
+                    bitmap,
+                    subcodec_width,
+                    subcodec_height,
+                    self.pixel_format,
+                    &mut self.subcodec_data,
+                )?,
+                SUBCODEC_RLEX => decode_rlex(bitmap, pixel_count, self.pixel_format, &mut self.subcodec_data)?,
+                _ => return Err(invalid_data("unknown ClearCodec subcodec")),
+            }
+
+            let region = match clipped_region(
+                x_start,
+                y_start,
+                x_start + subcodec_width,
+                y_start + subcodec_height,
+                width,
+                height,
+            ) {
+                Some(region) => region,
+                None => continue,
+            };
+
+            let row_length = region.width() as usize * bytes_per_pixel;
+            for row in 0..region.height() as usize {
+                let source = row * subcodec_width * bytes_per_pixel;
+                let destination = ((y_start + row) * width + x_start) * bytes_per_pixel;
+                self.data[destination..destination + row_length]
+                    .copy_from_slice(&self.subcodec_data[source..source + row_length]);
+            }
+
+            regions.push(region);
+        }
+
+        Ok(())
+    }
+}
+
+/// Each segment of the RLEX rectangle is a run of the color at the start index of the palette,
+/// followed by the colors from the start index to the stop index.
+fn decode_rlex(
+    mut input: &[u8],
+    pixel_count: usize,
+    pixel_format: PixelFormat,
+    output: &mut Vec<u8>,
+) -> Result<(), RdpError> {
+    let palette_count = input.read_u8()?;
+    if palette_count == 0 || palette_count > MAX_PALETTE_COUNT {
+        return Err(invalid_data("invalid size of the ClearCodec RLEX palette"));
+    }
+    let palette = (0..palette_count)
+        .map(|_| read_color(&mut input))
+        .collect::<io::Result<Vec<_>>>()?;
+    // the stop index takes the bits of the highest index of the palette, the suite depth the remaining ones
+    let stop_index_bits = (u8::BITS - (palette_count - 1).leading_zeros()).max(1);
+
+    output.resize(pixel_count * usize::from(pixel_format.bytes_per_pixel()), 0);
+    let mut position = 0;
+
+    while !input.is_empty() {
+        let indices = input.read_u8()?;
+        let run_length = read_run_length(&mut input)?;
+        let stop_index = usize::from(indices & ((1 << stop_index_bits) - 1));
+        let suite_depth = usize::from(indices >> stop_index_bits);
+        let start_index = stop_index
+            .checked_sub(suite_depth)
+            .filter(|_| stop_index < palette.len())
+            .ok_or_else(|| invalid_data("invalid ClearCodec RLEX palette index"))?;
+        if position + run_length + suite_depth + 1 > pixel_count {
+            return Err(invalid_data("the ClearCodec RLEX segment exceeds the rectangle"));
+        }
+
+        let run = (0..run_length).map(|_| palette[start_index]);
+        for color in run.chain(palette[start_index..=stop_index].iter().copied()) {
+            write_pixel(output, pixel_format, position, color)?;
+            position += 1;
+        }
+    }
+
+    if position != pixel_count {
+        return Err(invalid_data("the ClearCodec RLEX segments do not cover the rectangle"));
+    }
+
+    Ok(())
+}
+
+fn read_color(input: &mut &[u8]) -> io::Result<Bgr> {
+    let mut color = [0; 3];
+    io::Read::read_exact(input, &mut color)?;
+
+    Ok(color)
+}
+
+/// Reads the run length of 1 byte, or of 2 or 4 bytes after the maximum value of the shorter one.
+fn read_run_length(input: &mut &[u8]) -> io::Result<usize> {
+    let run_length = input.read_u8()?;
+    if run_length < u8::MAX {
+        return Ok(usize::from(run_length));
+    }
+
+    let run_length = input.read_u16::<LittleEndian>()?;
+    if run_length < u16::MAX {
+        return Ok(usize::from(run_length));
+    }
+
+    Ok(input.read_u32::<LittleEndian>()? as usize)
+}
+
+fn write_pixel(data: &mut [u8], pixel_format: PixelFormat, index: usize, [b, g, r]: Bgr) -> io::Result<()> {
+    let bytes_per_pixel = usize::from(pixel_format.bytes_per_pixel());
+
+    pixel_format.write_color(Rgba { r, g, b, a: 0xff }, &mut data[index * bytes_per_pixel..])
+}
+
+/// Stores the entry at the cursor of the cache, which is filled from its start.
+fn store(cache: &mut Vec<Vec<Bgr>>, index: usize, entry: Vec<Bgr>) {
+    if index < cache.len() {
+        cache[index] = entry;
+    } else {
+        cache.push(entry);
+    }
+}
+
+fn split_to<'a>(input: &mut &'a [u8], length: usize) -> io::Result<&'a [u8]> {
+    if input.len() < length {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "the ClearCodec layer is longer than the bitmap data",
+        ));
+    }
+
+    let (head, tail) = input.split_at(length);
+    *input = tail;
+
+    Ok(head)
+}
+
+/// The rectangle of exclusive bounds clipped to the bitmap, unless it is empty.
+fn clipped_region(
+    left: usize,
+    top: usize,
+    right: usize,
+    bottom: usize,
+    width: usize,
+    height: usize,
+) -> Option<Rectangle> {
+    let right = right.min(width);
+    let bottom = bottom.min(height);
+    if left >= right || top >= bottom {
+        return None;
+    }
+
+    Some(Rectangle {
+        left: left as u32,
+        top: top as u32,
+        right: right as u32,
+        bottom: bottom as u32,
+    })
+}
+
+fn invalid_data(message: &'static str) -> RdpError {
+    io::Error::new(io::ErrorKind::InvalidData, message).into()
+}
@@ -0,0 +1,144 @@
+use super::*;
+use crate::image::DecodedImage;
+
+const IMAGE_WIDTH: u32 = 4;
+const IMAGE_HEIGHT: u32 = 4;
+
+fn pixel(image: &DecodedImage, x: u32, y: u32) -> [u8; 3] {
+    let index = ((y * IMAGE_WIDTH + x) * 4) as usize;
+    let data = image.data();
+
+    [data[index], data[index + 1], data[index + 2]]
+}
+
+fn rectangle(left: u32, top: u32, right: u32, bottom: u32) -> Rectangle {
+    Rectangle {
+        left,
+        top,
+        right,
+        bottom,
+    }
+}
+
+#[test]
+fn residual_layer_is_drawn_at_output_origin() {
+    let mut image = DecodedImage::new(PixelFormat::BgrX32, IMAGE_WIDTH, IMAGE_HEIGHT);
+    #[rustfmt::skip]
+    let input = [
+        0x00, 0x00, // glyphFlags, seqNumber
+        0x08, 0x00, 0x00, 0x00, // residualByteCount
+        0x00, 0x00, 0x00, 0x00, // bandsByteCount
+        0x00, 0x00, 0x00, 0x00, // subcodecByteCount
+        1, 2, 3, 0x01,
+        4, 5, 6, 0x01,
+    ];
+
+    let update_region = DecodingContext::new()
+        .decode(&mut image, &input, &rectangle(0, 0, 2, 1), Some((1, 1)))
+        .unwrap();
+
+    assert_eq!(Some(rectangle(1, 1, 3, 2)), update_region);
+    assert_eq!([1, 2, 3], pixel(&image, 1, 1));
+    assert_eq!([4, 5, 6], pixel(&image, 2, 1));
+}
+
+#[test]
+fn vertical_bar_decoded_for_unmapped_surface_is_cached() {
+    let mut image = DecodedImage::new(PixelFormat::BgrX32, IMAGE_WIDTH, IMAGE_HEIGHT);
+    let mut context = DecodingContext::new();
+    #[rustfmt::skip]
+    let short_vbar_cache_miss = [
+        0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00,
+        0x10, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, // xStart, xEnd, yStart, yEnd
+        9, 9, 9, // background
+        0x01, 0x02, // the second pixel from the short vertical bar
+        7, 7, 7,
+    ];
+    #[rustfmt::skip]
+    let vbar_cache_hit = [
+        0x00, 0x01,
+        0x00, 0x00, 0x00, 0x00,
+        0x0d, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00,
+        0, 0, 0,
+        0x00, 0x80,
+    ];
+
+    let destination = rectangle(0, 0, 1, 2);
+    let update_region = context
+        .decode(&mut image, &short_vbar_cache_miss, &destination, None)
+        .unwrap();
+    assert_eq!(None, update_region);
+
+    let update_region = context
+        .decode(&mut image, &vbar_cache_hit, &destination, Some((0, 0)))
+        .unwrap();
+    assert_eq!(Some(destination), update_region);
+    assert_eq!([9, 9, 9], pixel(&image, 0, 0));
+    assert_eq!([7, 7, 7], pixel(&image, 0, 1));
+}
+
+#[test]
+fn rlex_subcodec_draws_run_and_suite_of_palette_colors() {
+    let mut image = DecodedImage::new(PixelFormat::BgrX32, IMAGE_WIDTH, IMAGE_HEIGHT);
+    #[rustfmt::skip]
+    let input = [
+        0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00,
+        0x18, 0x00, 0x00, 0x00,
+        0x01, 0x00, 0x00, 0x00, 0x03, 0x00, 0x01, 0x00, // xStart, yStart, width, height
+        0x0b, 0x00, 0x00, 0x00, 0x02, // bitmapDataByteCount, subCodecId
+        0x02, 1, 1, 1, 2, 2, 2, // palette
+        0x00, 0x01, // a run of 1 of the color 0, followed by the suite of the color 0
+        0x01, 0x00, // the suite of the color 1
+    ];
+
+    let update_region = DecodingContext::new()
+        .decode(&mut image, &input, &rectangle(0, 0, 4, 1), Some((0, 0)))
+        .unwrap();
+
+    assert_eq!(Some(rectangle(1, 0, 4, 1)), update_region);
+    assert_eq!([0, 0, 0], pixel(&image, 0, 0));
+    assert_eq!([1, 1, 1], pixel(&image, 1, 0));
+    assert_eq!([1, 1, 1], pixel(&image, 2, 0));
+    assert_eq!([2, 2, 2], pixel(&image, 3, 0));
+}
+
+#[test]
+fn cached_glyph_is_drawn_again_from_its_index() {
+    let mut image = DecodedImage::new(PixelFormat::BgrX32, IMAGE_WIDTH, IMAGE_HEIGHT);
+    let mut context = DecodingContext::new();
+    #[rustfmt::skip]
+    let glyph = [
+        0x01, 0x00, 0x05, 0x00, // glyphFlags, seqNumber, glyphIndex
+        0x04, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00,
+        3, 3, 3, 0x01,
+    ];
+    let glyph_hit = [0x03, 0x01, 0x05, 0x00];
+
+    context
+        .decode(&mut image, &glyph, &rectangle(0, 0, 1, 1), Some((0, 0)))
+        .unwrap();
+    let update_region = context
+        .decode(&mut image, &glyph_hit, &rectangle(2, 3, 3, 4), Some((0, 0)))
+        .unwrap();
+
+    assert_eq!(Some(rectangle(2, 3, 3, 4)), update_region);
+    assert_eq!([3, 3, 3], pixel(&image, 2, 3));
+}
+
+#[test]
+fn hit_of_uncached_glyph_is_rejected() {
+    let mut image = DecodedImage::new(PixelFormat::BgrX32, IMAGE_WIDTH, IMAGE_HEIGHT);
+
+    let result = DecodingContext::new().decode(&mut image, &[0x03, 0x00, 0x05, 0x00], &rectangle(0, 0, 1, 1), None);
+
+    assert!(result.is_err());
+}
@@ -0,0 +1,577 @@
+#[cfg(test)]
+mod tests;
+
+use ironrdp::codecs::rfx::image_processing::PixelFormat;
+use ironrdp::dvc::gfx::{Avc420BitmapStream, Avc444BitmapStream, Encoding};
+use ironrdp::Rectangle;
+use log::{debug, warn};
+
+use crate::image::{convert_bgrx_in_place, ImageSink, ImageUpdate};
+use crate::{Codec, RdpError};
+
+const SOURCE_PIXEL_FORMAT: PixelFormat = PixelFormat::BgrX32;
+const NAL_UNIT_TYPE_IDR_SLICE: u8 = 5;
+/// Number of pictures dropped while waiting for an IDR picture before the refresh is requested again,
+/// in case the server has not honored the previous request.
+const REFRESH_RETRY_DROPPED_PICTURES: u32 = 60;
+
+/// Picture decoded from an H.264 bitstream, in the planar YUV 4:2:0 format.
+///
+/// The chroma planes are subsampled by 2 in both directions, the row `n` of the luma plane
+/// starts at `y[n * y_stride]` and the row `n` of the chroma planes at `u[n * uv_stride]`.
+#[derive(Debug, Clone, Copy)]
+pub struct YuvFrame<'a> {
+    pub width: u16,
+    pub height: u16,
+    pub y: &'a [u8],
+    pub u: &'a [u8],
+    pub v: &'a [u8],
+    pub y_stride: usize,
+    pub uv_stride: usize,
+}
+
+impl YuvFrame<'_> {
+    fn validate(&self) -> Result<(), RdpError> {
+        let width = usize::from(self.width);
+        let height = usize::from(self.height);
+        let chroma_width = (width + 1) / 2;
+        let chroma_height = (height + 1) / 2;
+
+        let plane_fits = |plane: &[u8], stride: usize, width: usize, height: usize| {
+            height == 0 || (stride >= width && plane.len() >= (height - 1) * stride + width)
+        };
+
+        if plane_fits(self.y, self.y_stride, width, height)
+            && plane_fits(self.u, self.uv_stride, chroma_width, chroma_height)
+            && plane_fits(self.v, self.uv_stride, chroma_width, chroma_height)
+        {
+            Ok(())
+        } else {
+            Err(RdpError::H264DecodingError(format!(
+                "the planes of the decoded picture do not cover its {}x{} pixels",
+                self.width, self.height
+            )))
+        }
+    }
+}
+
+/// H.264 decoder for the AVC420 and AVC444 encoded surfaces of the Graphics Pipeline.
+///
+/// No decoder is bundled, embedders provide one (e.g. backed by openh264 or by the platform
+/// video APIs) with [`ActiveStageProcessor::set_avc420_decoder_factory`](crate::ActiveStageProcessor::set_avc420_decoder_factory).
+pub trait Avc420Decoder: Send {
+    /// Decodes the NAL units of an AVC420 bitmap stream. Returns `None` when the decoder
+    /// needs more data before outputting a picture.
+    fn decode(&mut self, bitstream: &[u8]) -> Result<Option<YuvFrame<'_>>, RdpError>;
+}
+
+/// Creates a decoder for each Graphics Pipeline opened by the server, so that
+/// a new pipeline never starts from the stream state of a previous one.
+pub type Avc420DecoderFactory = Box<dyn FnMut() -> Box<dyn Avc420Decoder> + Send>;
+
+pub struct DecodingContext {
+    decoder: Box<dyn Avc420Decoder>,
+    pixels: ConvertedPixels,
+    main_view: Option<Yuv420Planes>,
+    auxiliary_view: Option<Yuv420Planes>,
+    references: ReferencePictures,
+}
+
+impl DecodingContext {
+    pub fn new(decoder: Box<dyn Avc420Decoder>) -> Self {
+        Self {
+            decoder,
+            pixels: ConvertedPixels {
+                data: Vec::new(),
+                pixel_format: SOURCE_PIXEL_FORMAT,
+            },
+            main_view: None,
+            auxiliary_view: None,
+            references: ReferencePictures::Valid,
+        }
+    }
+
+    /// Sets the pixel format of the decoded regions passed to the image sink, `BgrX32` by default.
+    pub fn with_pixel_format(mut self, pixel_format: PixelFormat) -> Self {
+        self.pixels.pixel_format = pixel_format;
+
+        self
+    }
+
+    /// Marks the reference pictures as lost, e.g. when frames of the graphics pipeline are missing.
+    ///
+    /// The next pictures are predicted from pictures the decoder has not decoded, so they are dropped
+    /// instead of corrupting the image until an IDR picture, which the server sends when the client
+    /// requests a refresh (see [`Self::take_refresh_request`]), is received.
+    pub fn invalidate_references(&mut self) {
+        self.references.lose();
+    }
+
+    pub fn references_lost(&self) -> bool {
+        self.references != ReferencePictures::Valid
+    }
+
+    /// Returns `true` if the reference pictures have been lost and the client has to request
+    /// the server to refresh the surfaces, at most once per [`REFRESH_RETRY_DROPPED_PICTURES`] dropped pictures.
+    pub fn take_refresh_request(&mut self) -> bool {
+        match &mut self.references {
+            ReferencePictures::Valid => false,
+            ReferencePictures::Lost {
+                dropped_pictures,
+                refresh_requested,
+            } => {
+                if *refresh_requested && *dropped_pictures < REFRESH_RETRY_DROPPED_PICTURES {
+                    return false;
+                }
+
+                *dropped_pictures = 0;
+                *refresh_requested = true;
+
+                true
+            }
+        }
+    }
+
+    /// Decodes the bitmap stream sent for the destination rectangle of a surface whose top-left
+    /// corner is mapped at `output_origin`. Only the regions listed in the stream are copied
+    /// into the image, the region of the image which has been updated is returned.
+    ///
+    /// The reference pictures are marked as lost when the picture cannot be decoded.
+    pub fn decode(
+        &mut self,
+        image: &mut dyn ImageSink,
+        stream: &Avc420BitmapStream<'_>,
+        destination: &Rectangle,
+        output_origin: (u32, u32),
+    ) -> Result<Option<Rectangle>, RdpError> {
+        let frame = match decode_picture(self.decoder.as_mut(), &mut self.references, stream.data)? {
+            Some(frame) => frame,
+            None => return Ok(None),
+        };
+
+        update_image(
+            image,
+            &stream.rectangles,
+            destination,
+            &frame_rectangle(&frame),
+            output_origin,
+            &mut self.pixels,
+            |region, output| yuv420_to_bgrx(&frame, region, output),
+        )
+    }
+
+    /// Decodes the AVC444 bitmap stream, made of the main view carrying the luma with
+    /// subsampled chroma, and of the auxiliary view carrying the remaining chroma samples.
+    ///
+    /// Both views are H.264 pictures of the same stream. The server may send only one of them,
+    /// e.g. a luma update followed later by the chroma refinement, so the last picture of each
+    /// view is kept to be combined with the next picture of the other one.
+    pub fn decode_avc444(
+        &mut self,
+        image: &mut dyn ImageSink,
+        stream: &Avc444BitmapStream<'_>,
+        layout: AuxiliaryViewLayout,
+        destination: &Rectangle,
+        output_origin: (u32, u32),
+    ) -> Result<Option<Rectangle>, RdpError> {
+        let (main_stream, auxiliary_stream) = if stream.encoding == Encoding::LUMA_AND_CHROMA {
+            (Some(&stream.stream1), stream.stream2.as_ref())
+        } else if stream.encoding == Encoding::LUMA {
+            (Some(&stream.stream1), None)
+        } else {
+            (None, Some(&stream.stream1))
+        };
+
+        let mut regions = Vec::new();
+
+        if let Some(main_stream) = main_stream {
+            if let Some(frame) = decode_picture(self.decoder.as_mut(), &mut self.references, main_stream.data)? {
+                self.main_view
+                    .get_or_insert_with(Yuv420Planes::default)
+                    .copy_from(&frame);
+                regions.extend(main_stream.rectangles.iter().cloned());
+            }
+        }
+
+        if self.references_lost() {
+            // the chroma refinement of a lost picture would be combined with the luma of the next IDR picture
+            self.auxiliary_view = None;
+        }
+
+        if let Some(auxiliary_stream) = auxiliary_stream {
+            if let Some(frame) = decode_picture(self.decoder.as_mut(), &mut self.references, auxiliary_stream.data)? {
+                self.auxiliary_view
+                    .get_or_insert_with(Yuv420Planes::default)
+                    .copy_from(&frame);
+                regions.extend(auxiliary_stream.rectangles.iter().cloned());
+            }
+        }
+
+        // the chroma refinement is meaningless until the luma is received
+        let main = match self.main_view.as_ref() {
+            Some(main_view) => main_view.as_frame(),
+            None => return Ok(None),
+        };
+        let auxiliary = self.auxiliary_view.as_ref().map(Yuv420Planes::as_frame);
+
+        update_image(
+            image,
+            &regions,
+            destination,
+            &frame_rectangle(&main),
+            output_origin,
+            &mut self.pixels,
+            |region, output| yuv444_to_bgrx(&main, auxiliary.as_ref(), layout, region, output),
+        )
+    }
+}
+
+/// Validity of the pictures the decoder predicts the next pictures from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ReferencePictures {
+    Valid,
+    /// A picture has been lost or could not be decoded, the pictures are dropped until the next IDR picture.
+    Lost {
+        dropped_pictures: u32,
+        refresh_requested: bool,
+    },
+}
+
+impl ReferencePictures {
+    fn lose(&mut self) {
+        if *self == Self::Valid {
+            warn!("The H.264 reference pictures have been lost, dropping the pictures until the next IDR picture");
+            *self = Self::Lost {
+                dropped_pictures: 0,
+                refresh_requested: false,
+            };
+        }
+    }
+}
+
+/// Decodes the picture unless it is predicted from lost reference pictures,
+/// the reference pictures being valid again once an IDR picture is decoded.
+fn decode_picture<'a>(
+    decoder: &'a mut dyn Avc420Decoder,
+    references: &mut ReferencePictures,
+    bitstream: &[u8],
+) -> Result<Option<YuvFrame<'a>>, RdpError> {
+    let idr_picture = contains_idr_picture(bitstream);
+
+    if let ReferencePictures::Lost { dropped_pictures, .. } = references {
+        if !idr_picture {
+            debug!("Dropping H.264 picture predicted from lost reference pictures");
+            *dropped_pictures = dropped_pictures.saturating_add(1);
+
+            return Ok(None);
+        }
+    }
+
+    let frame = decoder
+        .decode(bitstream)
+        .and_then(|frame| frame.map(|frame| frame.validate().map(|_| frame)).transpose());
+
+    match frame {
+        Ok(frame) => {
+            if idr_picture && *references != ReferencePictures::Valid {
+                debug!("The H.264 reference pictures have been restored by an IDR picture");
+                *references = ReferencePictures::Valid;
+            }
+
+            Ok(frame)
+        }
+        Err(e) => {
+            references.lose();
+
+            Err(e)
+        }
+    }
+}
+
+/// Returns `true` if the Annex B bitstream holds a slice of an IDR picture, which is decoded
+/// without reference pictures. The emulation prevention bytes guarantee that the start code
+/// prefix is not found in the payload of the NAL units.
+fn contains_idr_picture(bitstream: &[u8]) -> bool {
+    bitstream
+        .windows(4)
+        .any(|window| window[..3] == [0x00, 0x00, 0x01] && window[3] & 0x1f == NAL_UNIT_TYPE_IDR_SLICE)
+}
+
+/// Arrangement of the chroma samples in the auxiliary view of an AVC444 stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuxiliaryViewLayout {
+    /// The odd rows of the chroma in the luma plane, the odd columns of the even rows
+    /// in the chroma planes (MS-RDPEGFX 3.3.8.3.2).
+    Avc444,
+    /// The odd columns of the chroma in the luma plane, the even columns of the odd rows
+    /// in the chroma planes (MS-RDPEGFX 3.3.8.3.3).
+    Avc444v2,
+}
+
+/// Owned copy of a decoded picture, the decoder reusing its buffers for the next one.
+#[derive(Debug, Default)]
+struct Yuv420Planes {
+    width: u16,
+    height: u16,
+    y: Vec<u8>,
+    u: Vec<u8>,
+    v: Vec<u8>,
+    y_stride: usize,
+    uv_stride: usize,
+}
+
+impl Yuv420Planes {
+    fn copy_from(&mut self, frame: &YuvFrame<'_>) {
+        self.width = frame.width;
+        self.height = frame.height;
+        self.y_stride = frame.y_stride;
+        self.uv_stride = frame.uv_stride;
+
+        for (plane, source) in [(&mut self.y, frame.y), (&mut self.u, frame.u), (&mut self.v, frame.v)] {
+            plane.clear();
+            plane.extend_from_slice(source);
+        }
+    }
+
+    fn as_frame(&self) -> YuvFrame<'_> {
+        YuvFrame {
+            width: self.width,
+            height: self.height,
+            y: &self.y,
+            u: &self.u,
+            v: &self.v,
+            y_stride: self.y_stride,
+            uv_stride: self.uv_stride,
+        }
+    }
+}
+
+fn frame_rectangle(frame: &YuvFrame<'_>) -> Rectangle {
+    Rectangle::from_u16(0, 0, frame.width, frame.height)
+}
+
+/// Pixels of a region converted from the picture, in the pixel format passed to the image sink.
+struct ConvertedPixels {
+    data: Vec<u8>,
+    pixel_format: PixelFormat,
+}
+
+/// Converts with `convert` each region clipped to the destination and to the picture,
+/// and copies it into the image at the output origin of the surface.
+fn update_image(
+    image: &mut dyn ImageSink,
+    regions: &[Rectangle],
+    destination: &Rectangle,
+    frame_rectangle: &Rectangle,
+    output_origin: (u32, u32),
+    pixels: &mut ConvertedPixels,
+    mut convert: impl FnMut(&Rectangle, &mut Vec<u8>),
+) -> Result<Option<Rectangle>, RdpError> {
+    let mut update_region: Option<Rectangle> = None;
+
+    for region in regions.iter() {
+        let region = match region
+            .intersect(destination)
+            .and_then(|region| region.intersect(frame_rectangle))
+        {
+            Some(region) => region,
+            None => continue,
+        };
+
+        convert(&region, &mut pixels.data);
+        convert_bgrx_in_place(&mut pixels.data, pixels.pixel_format);
+
+        let (left, top) = output_origin;
+        let rectangle = Rectangle {
+            left: left.saturating_add(region.left),
+            top: top.saturating_add(region.top),
+            right: left.saturating_add(region.right),
+            bottom: top.saturating_add(region.bottom),
+        };
+
+        image.update(&ImageUpdate {
+            rectangle: rectangle.clone(),
+            pixel_format: pixels.pixel_format,
+            stride: region.width() as usize * usize::from(pixels.pixel_format.bytes_per_pixel()),
+            data: &pixels.data,
+            codec: Codec::H264,
+        })?;
+
+        update_region = Some(match update_region {
+            Some(update_region) => update_region.union(&rectangle),
+            None => rectangle,
+        });
+    }
+
+    Ok(update_region)
+}
+
+/// Converts the region of the picture using the BT.709 coefficients, as the server encodes it.
+fn yuv420_to_bgrx(frame: &YuvFrame<'_>, region: &Rectangle, output: &mut Vec<u8>) {
+    output.clear();
+    output.reserve(region.width() as usize * region.height() as usize * 4);
+
+    for row in region.top as usize..region.bottom as usize {
+        let y_row = &frame.y[row * frame.y_stride..];
+        let u_row = &frame.u[row / 2 * frame.uv_stride..];
+        let v_row = &frame.v[row / 2 * frame.uv_stride..];
+
+        for column in region.left as usize..region.right as usize {
+            output.extend_from_slice(&yuv_to_bgrx(y_row[column], u_row[column / 2], v_row[column / 2]));
+        }
+    }
+}
+
+/// Converts the region of the main view, refining its chroma with the auxiliary view if any.
+fn yuv444_to_bgrx(
+    main: &YuvFrame<'_>,
+    auxiliary: Option<&YuvFrame<'_>>,
+    layout: AuxiliaryViewLayout,
+    region: &Rectangle,
+    output: &mut Vec<u8>,
+) {
+    let auxiliary = match auxiliary {
+        Some(auxiliary) => auxiliary,
+        None => return yuv420_to_bgrx(main, region, output),
+    };
+
+    output.clear();
+    output.reserve(region.width() as usize * region.height() as usize * 4);
+
+    for row in region.top as usize..region.bottom as usize {
+        for column in region.left as usize..region.right as usize {
+            let (u, v) = chroma444(main, auxiliary, layout, column, row);
+            output.extend_from_slice(&yuv_to_bgrx(main.y[row * main.y_stride + column], u, v));
+        }
+    }
+}
+
+/// Chroma of the pixel in the full resolution picture.
+///
+/// The main view carries the average of the chroma of each 2x2 block, at the top-left pixel
+/// of the block in the full resolution picture. Its value is restored from the average and from
+/// the three other pixels, which the auxiliary view carries. The chroma of the main view is kept
+/// when the auxiliary view does not cover the block.
+fn chroma444(
+    main: &YuvFrame<'_>,
+    auxiliary: &YuvFrame<'_>,
+    layout: AuxiliaryViewLayout,
+    x: usize,
+    y: usize,
+) -> (u8, u8) {
+    if let Some(chroma) = auxiliary_chroma(auxiliary, layout, x, y) {
+        return chroma;
+    }
+
+    let main_index = y / 2 * main.uv_stride + x / 2;
+    let (average_u, average_v) = (main.u[main_index], main.v[main_index]);
+
+    if x % 2 != 0 || y % 2 != 0 {
+        return (average_u, average_v);
+    }
+
+    let (mut sum_u, mut sum_v) = (0, 0);
+    for (x, y) in [(x + 1, y), (x, y + 1), (x + 1, y + 1)] {
+        match auxiliary_chroma(auxiliary, layout, x, y) {
+            Some((u, v)) => {
+                sum_u += i32::from(u);
+                sum_v += i32::from(v);
+            }
+            None => return (average_u, average_v),
+        }
+    }
+
+    (
+        conditional_clip(4 * i32::from(average_u) - sum_u, average_u),
+        conditional_clip(4 * i32::from(average_v) - sum_v, average_v),
+    )
+}
+
+/// Chroma sample of the full resolution picture carried by the auxiliary view,
+/// `None` for the top-left pixel of each 2x2 block and outside of the view.
+fn auxiliary_chroma(auxiliary: &YuvFrame<'_>, layout: AuxiliaryViewLayout, x: usize, y: usize) -> Option<(u8, u8)> {
+    let width = usize::from(auxiliary.width);
+    let height = usize::from(auxiliary.height);
+
+    if x >= width || y >= height {
+        return None;
+    }
+
+    match layout {
+        AuxiliaryViewLayout::Avc444 => {
+            if y % 2 == 1 {
+                // blocks of 16 rows: 8 odd rows of U followed by the same 8 odd rows of V
+                let odd_row = y / 2;
+                let u_row = odd_row / 8 * 16 + odd_row % 8;
+                let v_row = u_row + 8;
+                if v_row >= height {
+                    return None;
+                }
+
+                Some((
+                    auxiliary.y[u_row * auxiliary.y_stride + x],
+                    auxiliary.y[v_row * auxiliary.y_stride + x],
+                ))
+            } else if x % 2 == 1 {
+                let index = y / 2 * auxiliary.uv_stride + x / 2;
+
+                Some((auxiliary.u[index], auxiliary.v[index]))
+            } else {
+                None
+            }
+        }
+        AuxiliaryViewLayout::Avc444v2 => {
+            if x % 2 == 1 {
+                // left half of the luma plane for U, right half for V
+                let half_width = width / 2;
+                if x / 2 >= half_width {
+                    return None;
+                }
+
+                let row = &auxiliary.y[y * auxiliary.y_stride..];
+                Some((row[x / 2], row[half_width + x / 2]))
+            } else if y % 2 == 1 {
+                // columns 4n in the U plane and columns 4n + 2 in the V plane,
+                // each plane holding U in its left half and V in its right half
+                let quarter_width = width / 4;
+                if x / 4 >= quarter_width {
+                    return None;
+                }
+
+                let plane = if x % 4 == 0 { auxiliary.u } else { auxiliary.v };
+                let row = &plane[y / 2 * auxiliary.uv_stride..];
+                Some((row[x / 4], row[quarter_width + x / 4]))
+            } else {
+                None
+            }
+        }
+    }
+}
+
+/// Keeps the original value unless the restored one differs noticeably,
+/// which filters out the noise of the averaged chroma.
+fn conditional_clip(value: i32, original: u8) -> u8 {
+    let value = clip(value);
+
+    if value.abs_diff(original) < 30 {
+        original
+    } else {
+        value
+    }
+}
+
+fn yuv_to_bgrx(y: u8, u: u8, v: u8) -> [u8; 4] {
+    let y = 256 * i32::from(y);
+    let u = i32::from(u) - 128;
+    let v = i32::from(v) - 128;
+
+    let r = clip((y + 403 * v) >> 8);
+    let g = clip((y - 48 * u - 120 * v) >> 8);
+    let b = clip((y + 475 * u) >> 8);
+
+    [b, g, r, 0xff]
+}
+
+fn clip(value: i32) -> u8 {
+    value.clamp(0, 255) as u8
+}
@@ -0,0 +1,469 @@
+use ironrdp::dvc::gfx::{Encoding, QuantQuality};
+
+use super::*;
+use crate::image::DecodedImage;
+
+const IMAGE_WIDTH: u32 = 8;
+const IMAGE_HEIGHT: u32 = 8;
+const FORMAT_SIZE: usize = 4;
+
+struct ConstantDecoder {
+    width: u16,
+    height: u16,
+    y: Vec<u8>,
+    u: Vec<u8>,
+    v: Vec<u8>,
+}
+
+impl ConstantDecoder {
+    /// A picture whose luma is the index of the pixel, without chroma.
+    fn grayscale(width: u16, height: u16) -> Self {
+        let chroma_size = usize::from((width + 1) / 2) * usize::from((height + 1) / 2);
+
+        Self {
+            width,
+            height,
+            y: (0..usize::from(width) * usize::from(height)).map(|i| i as u8).collect(),
+            u: vec![128; chroma_size],
+            v: vec![128; chroma_size],
+        }
+    }
+}
+
+impl Avc420Decoder for ConstantDecoder {
+    fn decode(&mut self, _bitstream: &[u8]) -> Result<Option<YuvFrame<'_>>, RdpError> {
+        Ok(Some(YuvFrame {
+            width: self.width,
+            height: self.height,
+            y: &self.y,
+            u: &self.u,
+            v: &self.v,
+            y_stride: usize::from(self.width),
+            uv_stride: usize::from((self.width + 1) / 2),
+        }))
+    }
+}
+
+/// Outputs its pictures in turn, as the main and auxiliary views of an AVC444 stream.
+struct SequenceDecoder {
+    pictures: Vec<ConstantDecoder>,
+    next: usize,
+}
+
+impl Avc420Decoder for SequenceDecoder {
+    fn decode(&mut self, bitstream: &[u8]) -> Result<Option<YuvFrame<'_>>, RdpError> {
+        let index = self.next % self.pictures.len();
+        self.next += 1;
+
+        self.pictures[index].decode(bitstream)
+    }
+}
+
+/// Fails to decode the first pictures, e.g. after some NAL units have been lost.
+struct FailingDecoder {
+    decoder: ConstantDecoder,
+    failures: usize,
+}
+
+impl Avc420Decoder for FailingDecoder {
+    fn decode(&mut self, bitstream: &[u8]) -> Result<Option<YuvFrame<'_>>, RdpError> {
+        if self.failures > 0 {
+            self.failures -= 1;
+
+            return Err(RdpError::H264DecodingError(String::from("corrupted bitstream")));
+        }
+
+        self.decoder.decode(bitstream)
+    }
+}
+
+struct BufferingDecoder;
+
+impl Avc420Decoder for BufferingDecoder {
+    fn decode(&mut self, _bitstream: &[u8]) -> Result<Option<YuvFrame<'_>>, RdpError> {
+        Ok(None)
+    }
+}
+
+fn bitmap_stream(rectangles: Vec<Rectangle>) -> Avc420BitmapStream<'static> {
+    let quant_qual_vals = rectangles
+        .iter()
+        .map(|_| QuantQuality {
+            quantization_parameter: 22,
+            progressive: false,
+            quality: 100,
+        })
+        .collect();
+
+    Avc420BitmapStream {
+        rectangles,
+        quant_qual_vals,
+        data: &[0x00, 0x00, 0x00, 0x01],
+    }
+}
+
+/// A stream of an IDR picture, the NAL unit header following the start code.
+fn idr_bitmap_stream(rectangles: Vec<Rectangle>) -> Avc420BitmapStream<'static> {
+    Avc420BitmapStream {
+        data: &[0x00, 0x00, 0x00, 0x01, 0x65],
+        ..bitmap_stream(rectangles)
+    }
+}
+
+fn rectangle(left: u32, top: u32, right: u32, bottom: u32) -> Rectangle {
+    Rectangle {
+        left,
+        top,
+        right,
+        bottom,
+    }
+}
+
+#[test]
+fn decode_copies_only_stream_regions_at_surface_output_origin() {
+    let mut context = DecodingContext::new(Box::new(ConstantDecoder::grayscale(4, 4)));
+    let mut image = DecodedImage::new(SOURCE_PIXEL_FORMAT, IMAGE_WIDTH, IMAGE_HEIGHT);
+
+    let update_region = context
+        .decode(
+            &mut image,
+            &bitmap_stream(vec![rectangle(1, 1, 3, 3)]),
+            &rectangle(0, 0, 4, 4),
+            (2, 2),
+        )
+        .unwrap();
+
+    assert_eq!(Some(rectangle(3, 3, 5, 5)), update_region);
+
+    let stride = IMAGE_WIDTH as usize * FORMAT_SIZE;
+    for y in 0..IMAGE_HEIGHT as usize {
+        for x in 0..IMAGE_WIDTH as usize {
+            let pixel = &image.data()[y * stride + x * FORMAT_SIZE..][..FORMAT_SIZE];
+            let expected = if (3..5).contains(&x) && (3..5).contains(&y) {
+                let luma = ((y - 2) * 4 + (x - 2)) as u8;
+                [luma, luma, luma, 0xff]
+            } else {
+                [0; FORMAT_SIZE]
+            };
+
+            assert_eq!(expected, pixel, "pixel ({}, {})", x, y);
+        }
+    }
+}
+
+#[test]
+fn decode_clips_stream_regions_to_destination_and_picture() {
+    let mut context = DecodingContext::new(Box::new(ConstantDecoder::grayscale(4, 4)));
+    let mut updates = Vec::new();
+    let mut sink = |update: &ImageUpdate<'_>| updates.push(update.rectangle.clone());
+
+    let update_region = context
+        .decode(
+            &mut sink,
+            &bitmap_stream(vec![rectangle(2, 2, 8, 8), rectangle(0, 0, 1, 1)]),
+            &rectangle(1, 1, 4, 4),
+            (0, 0),
+        )
+        .unwrap();
+
+    assert_eq!(vec![rectangle(2, 2, 4, 4)], updates);
+    assert_eq!(Some(rectangle(2, 2, 4, 4)), update_region);
+}
+
+#[test]
+fn decode_does_not_update_image_without_decoded_picture() {
+    let mut context = DecodingContext::new(Box::new(BufferingDecoder));
+    let mut sink = |_: &ImageUpdate<'_>| panic!("the image must not be updated");
+
+    let update_region = context
+        .decode(
+            &mut sink,
+            &bitmap_stream(vec![rectangle(0, 0, 4, 4)]),
+            &rectangle(0, 0, 4, 4),
+            (0, 0),
+        )
+        .unwrap();
+
+    assert_eq!(None, update_region);
+}
+
+#[test]
+fn decode_fails_on_picture_smaller_than_its_dimensions() {
+    let mut decoder = ConstantDecoder::grayscale(4, 4);
+    decoder.y.truncate(8);
+    let mut context = DecodingContext::new(Box::new(decoder));
+    let mut sink = |_: &ImageUpdate<'_>| ();
+
+    assert!(matches!(
+        context.decode(
+            &mut sink,
+            &bitmap_stream(vec![rectangle(0, 0, 4, 4)]),
+            &rectangle(0, 0, 4, 4),
+            (0, 0),
+        ),
+        Err(RdpError::H264DecodingError(_))
+    ));
+}
+
+#[test]
+fn yuv420_to_bgrx_applies_chroma_of_subsampled_pixels() {
+    let frame = YuvFrame {
+        width: 2,
+        height: 2,
+        y: &[128, 128, 128, 128],
+        u: &[128],
+        v: &[255],
+        y_stride: 2,
+        uv_stride: 1,
+    };
+    let mut output = Vec::new();
+
+    yuv420_to_bgrx(&frame, &rectangle(1, 1, 2, 2), &mut output);
+
+    assert_eq!(vec![128, 68, 255, 0xff], output);
+}
+
+fn avc444_stream(encoding: Encoding, with_stream2: bool) -> Avc444BitmapStream<'static> {
+    Avc444BitmapStream {
+        encoding,
+        stream1: bitmap_stream(vec![rectangle(0, 0, 4, 2)]),
+        stream2: with_stream2.then(|| bitmap_stream(vec![rectangle(0, 0, 4, 2)])),
+    }
+}
+
+/// A 4x2 main view with the average chroma of each 2x2 block, and an auxiliary view carrying the
+/// other pixels of the blocks: U 100 and V 200 on the odd columns and on the bottom row.
+fn avc444_views(layout: AuxiliaryViewLayout) -> Vec<ConstantDecoder> {
+    let main = ConstantDecoder {
+        width: 4,
+        height: 2,
+        y: vec![128; 8],
+        u: vec![110; 2],
+        v: vec![180; 2],
+    };
+
+    let auxiliary = match layout {
+        // the odd row of U and V in the luma rows 0 and 8, the odd columns of the even row in the chroma
+        AuxiliaryViewLayout::Avc444 => {
+            let mut y = vec![0; 4 * 16];
+            y[..4].copy_from_slice(&[100; 4]);
+            y[4 * 8..4 * 9].copy_from_slice(&[200; 4]);
+
+            ConstantDecoder {
+                width: 4,
+                height: 16,
+                y,
+                u: vec![100; 2 * 8],
+                v: vec![200; 2 * 8],
+            }
+        }
+        // the odd columns of U and V in the left and right halves of the luma, the even columns
+        // of the odd row in the left and right halves of the chroma, U plane for the columns 4n
+        AuxiliaryViewLayout::Avc444v2 => ConstantDecoder {
+            width: 4,
+            height: 2,
+            y: vec![100, 100, 200, 200, 100, 100, 200, 200],
+            u: vec![100, 200],
+            v: vec![100, 200],
+        },
+    };
+
+    vec![main, auxiliary]
+}
+
+#[test]
+fn decode_avc444_restores_full_chroma_of_each_layout() {
+    for layout in [AuxiliaryViewLayout::Avc444, AuxiliaryViewLayout::Avc444v2] {
+        let mut context = DecodingContext::new(Box::new(SequenceDecoder {
+            pictures: avc444_views(layout),
+            next: 0,
+        }));
+        let mut image = DecodedImage::new(SOURCE_PIXEL_FORMAT, 4, 2);
+
+        let update_region = context
+            .decode_avc444(
+                &mut image,
+                &avc444_stream(Encoding::LUMA_AND_CHROMA, true),
+                layout,
+                &rectangle(0, 0, 4, 2),
+                (0, 0),
+            )
+            .unwrap();
+
+        assert_eq!(Some(rectangle(0, 0, 4, 2)), update_region, "{:?}", layout);
+
+        // the top-left chroma of each block is restored from the average: 4 * 110 - 3 * 100
+        // and 4 * 180 - 3 * 200
+        let restored = yuv_to_bgrx(128, 140, 120);
+        let auxiliary = yuv_to_bgrx(128, 100, 200);
+        let expected = [restored, auxiliary, restored, auxiliary]
+            .iter()
+            .chain([auxiliary; 4].iter())
+            .flatten()
+            .copied()
+            .collect::<Vec<_>>();
+        assert_eq!(expected, image.data(), "{:?}", layout);
+    }
+}
+
+#[test]
+fn decode_avc444_uses_subsampled_chroma_until_auxiliary_view_is_received() {
+    let mut context = DecodingContext::new(Box::new(SequenceDecoder {
+        pictures: avc444_views(AuxiliaryViewLayout::Avc444),
+        next: 0,
+    }));
+    let mut image = DecodedImage::new(SOURCE_PIXEL_FORMAT, 4, 2);
+
+    context
+        .decode_avc444(
+            &mut image,
+            &avc444_stream(Encoding::LUMA, false),
+            AuxiliaryViewLayout::Avc444,
+            &rectangle(0, 0, 4, 2),
+            (0, 0),
+        )
+        .unwrap();
+
+    assert_eq!(yuv_to_bgrx(128, 110, 180).repeat(8), image.data());
+
+    let update_region = context
+        .decode_avc444(
+            &mut image,
+            &avc444_stream(Encoding::CHROMA, false),
+            AuxiliaryViewLayout::Avc444,
+            &rectangle(0, 0, 4, 2),
+            (0, 0),
+        )
+        .unwrap();
+
+    assert_eq!(Some(rectangle(0, 0, 4, 2)), update_region);
+    assert_eq!(&yuv_to_bgrx(128, 100, 200), &image.data()[4..8]);
+}
+
+#[test]
+fn decode_avc444_does_not_update_image_with_chroma_only() {
+    let mut context = DecodingContext::new(Box::new(ConstantDecoder::grayscale(4, 2)));
+    let mut sink = |_: &ImageUpdate<'_>| panic!("the image must not be updated");
+
+    let update_region = context
+        .decode_avc444(
+            &mut sink,
+            &avc444_stream(Encoding::CHROMA, false),
+            AuxiliaryViewLayout::Avc444,
+            &rectangle(0, 0, 4, 2),
+            (0, 0),
+        )
+        .unwrap();
+
+    assert_eq!(None, update_region);
+}
+
+#[test]
+fn conditional_clip_keeps_original_value_on_small_difference() {
+    assert_eq!(110, conditional_clip(120, 110));
+    assert_eq!(140, conditional_clip(140, 110));
+    assert_eq!(255, conditional_clip(400, 110));
+}
+
+#[test]
+fn decode_converts_regions_to_requested_pixel_format() {
+    let mut decoder = ConstantDecoder::grayscale(2, 2);
+    decoder.v = vec![255];
+    let mut context = DecodingContext::new(Box::new(decoder)).with_pixel_format(PixelFormat::RgbA32);
+    let mut updates = Vec::new();
+    let mut sink = |update: &ImageUpdate<'_>| {
+        updates.push((update.pixel_format, update.stride, update.data[..FORMAT_SIZE].to_vec()));
+    };
+
+    context
+        .decode(
+            &mut sink,
+            &bitmap_stream(vec![rectangle(0, 0, 2, 2)]),
+            &rectangle(0, 0, 2, 2),
+            (0, 0),
+        )
+        .unwrap();
+
+    let [b, g, r, a] = yuv_to_bgrx(0, 128, 255);
+    assert_eq!(vec![(PixelFormat::RgbA32, 2 * FORMAT_SIZE, vec![r, g, b, a])], updates);
+}
+
+#[test]
+fn decode_drops_pictures_after_decoding_failure_until_idr_picture() {
+    let mut context = DecodingContext::new(Box::new(FailingDecoder {
+        decoder: ConstantDecoder::grayscale(4, 4),
+        failures: 1,
+    }));
+    let mut updates = 0;
+    let mut sink = |_: &ImageUpdate<'_>| updates += 1;
+    let destination = rectangle(0, 0, 4, 4);
+
+    assert!(context
+        .decode(
+            &mut sink,
+            &bitmap_stream(vec![destination.clone()]),
+            &destination,
+            (0, 0)
+        )
+        .is_err());
+    assert!(context.references_lost());
+    assert!(context.take_refresh_request());
+    assert!(!context.take_refresh_request());
+
+    let update_region = context
+        .decode(
+            &mut sink,
+            &bitmap_stream(vec![destination.clone()]),
+            &destination,
+            (0, 0),
+        )
+        .unwrap();
+    assert_eq!(None, update_region);
+
+    let update_region = context
+        .decode(
+            &mut sink,
+            &idr_bitmap_stream(vec![destination.clone()]),
+            &destination,
+            (0, 0),
+        )
+        .unwrap();
+    assert_eq!(Some(destination.clone()), update_region);
+    assert!(!context.references_lost());
+    assert!(!context.take_refresh_request());
+    assert_eq!(1, updates);
+}
+
+#[test]
+fn refresh_is_requested_again_while_no_idr_picture_is_received() {
+    let mut context = DecodingContext::new(Box::new(ConstantDecoder::grayscale(4, 4)));
+    let mut sink = |_: &ImageUpdate<'_>| panic!("the image must not be updated");
+    let destination = rectangle(0, 0, 4, 4);
+
+    context.invalidate_references();
+    assert!(context.take_refresh_request());
+
+    for _ in 0..REFRESH_RETRY_DROPPED_PICTURES {
+        assert!(!context.take_refresh_request());
+        context
+            .decode(
+                &mut sink,
+                &bitmap_stream(vec![destination.clone()]),
+                &destination,
+                (0, 0),
+            )
+            .unwrap();
+    }
+
+    assert!(context.take_refresh_request());
+}
+
+#[test]
+fn idr_picture_is_found_after_any_start_code() {
+    assert!(contains_idr_picture(&[0x00, 0x00, 0x01, 0x65]));
+    assert!(contains_idr_picture(&[
+        0x00, 0x00, 0x00, 0x01, 0x67, 0x42, 0x00, 0x00, 0x01, 0x65
+    ]));
+    assert!(!contains_idr_picture(&[0x00, 0x00, 0x00, 0x01, 0x41, 0x65]));
+    assert!(!contains_idr_picture(&[]));
+}
@@ -0,0 +1,87 @@
+#[cfg(test)]
+mod tests;
+
+use ironrdp::codecs::nscodec;
+use ironrdp::codecs::rfx::image_processing::PixelFormat;
+use ironrdp::surface_commands::ExtendedBitmapDataPdu;
+use ironrdp::Rectangle;
+
+use crate::image::{ImageSink, ImageUpdate};
+use crate::{Codec, RdpError};
+
+const DEFAULT_PIXEL_FORMAT: PixelFormat = PixelFormat::BgrX32;
+
+/// Draws the NSCodec bitmaps of the surface bits, whose scanlines are stored from the bottom to the top.
+pub struct DecodingContext {
+    pixel_format: PixelFormat,
+    /// The bitmap being decoded, whose rows are stored from the bottom to the top.
+    bottom_up: Vec<u8>,
+    /// The decoded bitmap, its rows going from the top to the bottom.
+    data: Vec<u8>,
+}
+
+impl Default for DecodingContext {
+    fn default() -> Self {
+        Self {
+            pixel_format: DEFAULT_PIXEL_FORMAT,
+            bottom_up: Vec::new(),
+            data: Vec::new(),
+        }
+    }
+}
+
+impl DecodingContext {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the pixel format of the bitmaps passed to the image sink, `BgrX32` by default.
+    pub fn with_pixel_format(mut self, pixel_format: PixelFormat) -> Self {
+        self.pixel_format = pixel_format;
+
+        self
+    }
+
+    /// Draws the bitmap at the destination of the surface bits, whose bounds are exclusive.
+    /// Returns the updated region, if any.
+    pub fn decode(
+        &mut self,
+        image: &mut dyn ImageSink,
+        destination: &Rectangle,
+        bitmap: &ExtendedBitmapDataPdu<'_>,
+    ) -> Result<Option<Rectangle>, RdpError> {
+        let width = usize::from(bitmap.width);
+        let height = usize::from(bitmap.height);
+
+        nscodec::decode(bitmap.data, width, height, self.pixel_format, &mut self.bottom_up)?;
+
+        let stride = width * usize::from(self.pixel_format.bytes_per_pixel());
+        self.data.clear();
+        if stride != 0 {
+            for row in self.bottom_up.chunks_exact(stride).rev() {
+                self.data.extend_from_slice(row);
+            }
+        }
+
+        // the bitmap may be larger than the destination
+        let rectangle = Rectangle {
+            left: destination.left,
+            top: destination.top,
+            right: destination.right.min(destination.left + u32::from(bitmap.width)),
+            bottom: destination.bottom.min(destination.top + u32::from(bitmap.height)),
+        };
+        if rectangle.width() == 0 || rectangle.height() == 0 {
+            return Ok(None);
+        }
+
+        image.update(&ImageUpdate {
+            rectangle: rectangle.clone(),
+            pixel_format: self.pixel_format,
+            stride,
+            data: &self.data,
+            codec: Codec::NsCodec,
+        })?;
+
+        Ok(Some(rectangle))
+    }
+}
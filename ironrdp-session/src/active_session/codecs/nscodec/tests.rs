@@ -0,0 +1,76 @@
+use super::*;
+use crate::image::DecodedImage;
+
+const IMAGE_WIDTH: u32 = 4;
+const IMAGE_HEIGHT: u32 = 4;
+
+/// A 2x2 grey bitmap of raw planes, its rows going from the bottom to the top.
+#[rustfmt::skip]
+const BOTTOM_UP_BITMAP: [u8; 32] = [
+    0x04, 0x00, 0x00, 0x00, // luma byte count
+    0x04, 0x00, 0x00, 0x00, // orange chroma byte count
+    0x04, 0x00, 0x00, 0x00, // green chroma byte count
+    0x00, 0x00, 0x00, 0x00, // alpha byte count, opaque
+    0x01, 0x00, 0x00, 0x00, // color loss level, chroma subsampling level, reserved
+    1, 2, 3, 4,
+    0, 0, 0, 0,
+    0, 0, 0, 0,
+];
+
+fn pixel(image: &DecodedImage, x: u32, y: u32) -> [u8; 3] {
+    let index = ((y * IMAGE_WIDTH + x) * 4) as usize;
+    let data = image.data();
+
+    [data[index], data[index + 1], data[index + 2]]
+}
+
+fn bitmap_data(data: &[u8]) -> ExtendedBitmapDataPdu<'_> {
+    ExtendedBitmapDataPdu {
+        bpp: 32,
+        codec_id: 0x01,
+        width: 2,
+        height: 2,
+        header: None,
+        data,
+    }
+}
+
+#[test]
+fn bitmap_is_drawn_from_top_to_bottom() {
+    let mut image = DecodedImage::new(PixelFormat::BgrX32, IMAGE_WIDTH, IMAGE_HEIGHT);
+    let destination = Rectangle {
+        left: 1,
+        top: 1,
+        right: 3,
+        bottom: 3,
+    };
+
+    let update_region = DecodingContext::new()
+        .decode(&mut image, &destination, &bitmap_data(&BOTTOM_UP_BITMAP))
+        .unwrap();
+
+    assert_eq!(Some(destination), update_region);
+    assert_eq!([3, 3, 3], pixel(&image, 1, 1));
+    assert_eq!([4, 4, 4], pixel(&image, 2, 1));
+    assert_eq!([1, 1, 1], pixel(&image, 1, 2));
+    assert_eq!([2, 2, 2], pixel(&image, 2, 2));
+}
+
+#[test]
+fn bitmap_is_clipped_to_destination() {
+    let mut image = DecodedImage::new(PixelFormat::BgrX32, IMAGE_WIDTH, IMAGE_HEIGHT);
+    let destination = Rectangle {
+        left: 1,
+        top: 1,
+        right: 2,
+        bottom: 2,
+    };
+
+    let update_region = DecodingContext::new()
+        .decode(&mut image, &destination, &bitmap_data(&BOTTOM_UP_BITMAP))
+        .unwrap();
+
+    assert_eq!(Some(destination), update_region);
+    assert_eq!([3, 3, 3], pixel(&image, 1, 1));
+    assert_eq!([0, 0, 0], pixel(&image, 2, 1));
+}
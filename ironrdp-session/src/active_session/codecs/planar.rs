@@ -0,0 +1,81 @@
+#[cfg(test)]
+mod tests;
+
+use ironrdp::codecs::planar;
+use ironrdp::codecs::rfx::image_processing::PixelFormat;
+use ironrdp::Rectangle;
+
+use super::surface::update_surface;
+use crate::image::ImageSink;
+use crate::RdpError;
+
+const DEFAULT_PIXEL_FORMAT: PixelFormat = PixelFormat::BgrX32;
+
+/// Draws the planar bitmaps of the Graphics Pipeline surfaces, whose scanlines are stored from the top to the bottom,
+/// unlike the ones of the bitmap updates.
+pub struct DecodingContext {
+    pixel_format: PixelFormat,
+    data: Vec<u8>,
+}
+
+impl Default for DecodingContext {
+    fn default() -> Self {
+        Self {
+            pixel_format: DEFAULT_PIXEL_FORMAT,
+            data: Vec::new(),
+        }
+    }
+}
+
+impl DecodingContext {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the pixel format of the bitmaps passed to the image sink, `BgrX32` by default.
+    pub fn with_pixel_format(mut self, pixel_format: PixelFormat) -> Self {
+        self.pixel_format = pixel_format;
+
+        self
+    }
+
+    /// Draws the bitmap filling the destination of the surface, whose bounds are exclusive,
+    /// the surface being at the output origin. Returns the updated region of the output, if any.
+    pub fn decode(
+        &mut self,
+        image: &mut dyn ImageSink,
+        input: &[u8],
+        destination: &Rectangle,
+        output_origin: (u32, u32),
+    ) -> Result<Option<Rectangle>, RdpError> {
+        let width = destination.width();
+        let height = destination.height();
+        if width == 0 || height == 0 {
+            return Ok(None);
+        }
+
+        planar::decode(
+            input,
+            width as usize,
+            height as usize,
+            self.pixel_format,
+            &mut self.data,
+        )?;
+
+        let region = Rectangle {
+            left: 0,
+            top: 0,
+            right: width,
+            bottom: height,
+        };
+
+        update_surface(
+            image,
+            &[region],
+            &self.data,
+            self.pixel_format,
+            destination,
+            output_origin,
+        )
+    }
+}
@@ -0,0 +1,42 @@
+use super::*;
+use crate::image::DecodedImage;
+
+const IMAGE_WIDTH: u32 = 4;
+const IMAGE_HEIGHT: u32 = 4;
+
+fn pixel(image: &DecodedImage, x: u32, y: u32) -> [u8; 3] {
+    let index = ((y * IMAGE_WIDTH + x) * 4) as usize;
+    let data = image.data();
+
+    [data[index], data[index + 1], data[index + 2]]
+}
+
+#[test]
+fn bitmap_is_drawn_from_top_to_bottom_at_output_origin() {
+    let mut image = DecodedImage::new(PixelFormat::BgrX32, IMAGE_WIDTH, IMAGE_HEIGHT);
+    let destination = Rectangle {
+        left: 1,
+        top: 0,
+        right: 2,
+        bottom: 2,
+    };
+    // raw red, green and blue planes of a 1x2 bitmap, without alpha
+    let input = [0x20, 1, 2, 3, 4, 5, 6, 0x00];
+
+    let update_region = DecodingContext::new()
+        .decode(&mut image, &input, &destination, (1, 1))
+        .unwrap();
+
+    assert_eq!(
+        Some(Rectangle {
+            left: 2,
+            top: 1,
+            right: 3,
+            bottom: 3,
+        }),
+        update_region
+    );
+    assert_eq!([5, 3, 1], pixel(&image, 2, 1));
+    assert_eq!([6, 4, 2], pixel(&image, 2, 2));
+    assert_eq!([0, 0, 0], pixel(&image, 1, 1));
+}
@@ -0,0 +1,359 @@
+//! Decoding of the RemoteFX Progressive surfaces of the Graphics Pipeline. The coefficients of the tiles
+//! are kept for each surface, to be upgraded by the next regions.
+
+#[cfg(test)]
+mod tests;
+
+use std::collections::HashMap;
+use std::io;
+
+use ironrdp::codecs::progressive::{
+    self, Block, ComponentQuant, FirstTile, ProgressiveQuant, Region, RegionFlags, Tile, TileFlags, UpgradeTile,
+    COEFFICIENTS_COUNT, FULL_QUALITY, SUBBANDS_COUNT,
+};
+use ironrdp::codecs::rfx::color_conversion::{self, YCbCrBuffer};
+use ironrdp::codecs::rfx::image_processing::PixelFormat;
+use ironrdp::codecs::rfx::RfxRectangle;
+use ironrdp::Rectangle;
+use log::debug;
+
+use super::surface::update_surface;
+use crate::image::{convert_bgrx_in_place, ImageSink};
+use crate::RdpError;
+
+const TILE_SIZE: u32 = 64;
+const SOURCE_PIXEL_FORMAT: PixelFormat = PixelFormat::BgrX32;
+
+type SurfaceTiles = HashMap<(u16, u16), TileState>;
+
+pub struct DecodingContext {
+    pixel_format: PixelFormat,
+    surfaces: HashMap<u16, SurfaceTiles>,
+    ycbcr_buffer: Vec<Vec<i16>>,
+    temp_buffer: Vec<i16>,
+    tile_output: Vec<u8>,
+}
+
+impl Default for DecodingContext {
+    fn default() -> Self {
+        Self {
+            pixel_format: SOURCE_PIXEL_FORMAT,
+            surfaces: HashMap::new(),
+            ycbcr_buffer: vec![vec![0; COEFFICIENTS_COUNT]; 3],
+            temp_buffer: vec![0; COEFFICIENTS_COUNT],
+            tile_output: vec![0; COEFFICIENTS_COUNT * usize::from(SOURCE_PIXEL_FORMAT.bytes_per_pixel())],
+        }
+    }
+}
+
+impl DecodingContext {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the pixel format of the decoded tiles passed to the image sink, `BgrX32` by default.
+    pub fn with_pixel_format(mut self, pixel_format: PixelFormat) -> Self {
+        self.pixel_format = pixel_format;
+
+        self
+    }
+
+    /// Decodes the blocks of a Wire To Surface 2 PDU. The updated tiles are passed to the image sink
+    /// if the surface is mapped to the output at `output_origin`. Returns the updated region of the output, if any.
+    pub fn decode(
+        &mut self,
+        image: &mut dyn ImageSink,
+        surface_id: u16,
+        mut input: &[u8],
+        output_origin: Option<(u32, u32)>,
+    ) -> Result<Option<Rectangle>, RdpError> {
+        let mut update_region: Option<Rectangle> = None;
+
+        while !input.is_empty() {
+            match Block::from_buffer_consume(&mut input)? {
+                Block::Region(region) => {
+                    if let Some(region_update) = self.decode_region(image, surface_id, &region, output_origin)? {
+                        update_region = Some(match update_region {
+                            Some(update_region) => update_region.union(&region_update),
+                            None => region_update,
+                        });
+                    }
+                }
+                block => debug!("Got progressive block: {:?}", block),
+            }
+        }
+
+        Ok(update_region)
+    }
+
+    /// Drops the tiles of the surface, when it is deleted or created again, or when its encoding context is deleted.
+    pub fn delete_surface(&mut self, surface_id: u16) {
+        self.surfaces.remove(&surface_id);
+    }
+
+    fn decode_region(
+        &mut self,
+        image: &mut dyn ImageSink,
+        surface_id: u16,
+        region: &Region<'_>,
+        output_origin: Option<(u32, u32)>,
+    ) -> Result<Option<Rectangle>, RdpError> {
+        let extrapolate = region.flags.contains(RegionFlags::REDUCE_EXTRAPOLATE);
+        let tiles = self.surfaces.entry(surface_id).or_default();
+        let mut update_region: Option<Rectangle> = None;
+
+        for tile in &region.tiles {
+            let position = tile.position();
+            let state = match tile {
+                Tile::First(tile) => {
+                    let state = tiles.entry(position).or_insert_with(TileState::new);
+                    state.decode_first(tile, region, extrapolate, &mut self.temp_buffer)?;
+
+                    state
+                }
+                Tile::Upgrade(tile) => {
+                    let state = tiles.get_mut(&position).ok_or_else(|| {
+                        io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            "got upgrade of a tile which has not been decoded",
+                        )
+                    })?;
+                    state.upgrade(tile, region, extrapolate)?;
+
+                    state
+                }
+            };
+
+            // the tiles of the surfaces which are not mapped are kept for their next upgrades
+            let output_origin = match output_origin {
+                Some(output_origin) => output_origin,
+                None => continue,
+            };
+
+            let destination = Rectangle {
+                left: u32::from(position.0) * TILE_SIZE,
+                top: u32::from(position.1) * TILE_SIZE,
+                right: (u32::from(position.0) + 1) * TILE_SIZE,
+                bottom: (u32::from(position.1) + 1) * TILE_SIZE,
+            };
+            let regions = region
+                .rectangles
+                .iter()
+                .filter_map(|rectangle| clip_to_tile(rectangle, &destination))
+                .collect::<Vec<_>>();
+            if regions.is_empty() {
+                continue;
+            }
+
+            state.write_pixels(
+                &mut self.ycbcr_buffer,
+                &mut self.temp_buffer,
+                &mut self.tile_output,
+                extrapolate,
+                self.pixel_format,
+            )?;
+
+            let tile_update = update_surface(
+                image,
+                &regions,
+                &self.tile_output,
+                self.pixel_format,
+                &destination,
+                output_origin,
+            )?;
+            if let Some(tile_update) = tile_update {
+                update_region = Some(match update_region {
+                    Some(update_region) => update_region.union(&tile_update),
+                    None => tile_update,
+                });
+            }
+        }
+
+        Ok(update_region)
+    }
+}
+
+/// Coefficients of a tile, as of its last upgrade.
+struct TileState {
+    components: [ComponentState; 3],
+}
+
+impl TileState {
+    fn new() -> Self {
+        Self {
+            components: [ComponentState::new(), ComponentState::new(), ComponentState::new()],
+        }
+    }
+
+    fn decode_first(
+        &mut self,
+        tile: &FirstTile<'_>,
+        region: &Region<'_>,
+        extrapolate: bool,
+        temp: &mut [i16],
+    ) -> Result<(), RdpError> {
+        let quants = tile_quants(region, tile.quant_indices)?;
+        let progressive_quant = progressive_quant(region, tile.quality)?;
+        let difference = tile.flags.contains(TileFlags::DIFFERENCE);
+
+        let components = self.components.iter_mut().zip(tile.data.iter());
+        for ((component, data), (quant, progressive_quant)) in
+            components.zip(quants.iter().zip(progressive_quant.components()))
+        {
+            component.bit_positions = bit_positions(quant, progressive_quant);
+
+            if difference {
+                progressive::decode_component(data, &component.bit_positions, extrapolate, temp, &mut component.sign)?;
+                for (coefficient, difference) in component.coefficients.iter_mut().zip(temp.iter()) {
+                    *coefficient = coefficient.wrapping_add(*difference);
+                }
+            } else {
+                progressive::decode_component(
+                    data,
+                    &component.bit_positions,
+                    extrapolate,
+                    &mut component.coefficients,
+                    &mut component.sign,
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn upgrade(&mut self, tile: &UpgradeTile<'_>, region: &Region<'_>, extrapolate: bool) -> Result<(), RdpError> {
+        let quants = tile_quants(region, tile.quant_indices)?;
+        let progressive_quant = progressive_quant(region, tile.quality)?;
+
+        let components = self
+            .components
+            .iter_mut()
+            .zip(tile.srl_data.iter().zip(tile.raw_data.iter()));
+        for ((component, (srl_data, raw_data)), (quant, progressive_quant)) in
+            components.zip(quants.iter().zip(progressive_quant.components()))
+        {
+            let bit_positions = bit_positions(quant, progressive_quant);
+            let mut bit_counts = [0; SUBBANDS_COUNT];
+            for (bit_count, (previous, next)) in bit_counts
+                .iter_mut()
+                .zip(component.bit_positions.iter().zip(bit_positions.iter()))
+            {
+                *bit_count = previous.saturating_sub(*next);
+            }
+
+            progressive::upgrade_component(
+                srl_data,
+                raw_data,
+                &bit_positions,
+                &bit_counts,
+                extrapolate,
+                &mut component.coefficients,
+                &mut component.sign,
+            );
+            component.bit_positions = bit_positions;
+        }
+
+        Ok(())
+    }
+
+    /// Writes the 64x64 pixels of the tile into `output` in the pixel format.
+    fn write_pixels(
+        &self,
+        ycbcr_buffer: &mut [Vec<i16>],
+        temp: &mut [i16],
+        output: &mut [u8],
+        extrapolate: bool,
+        pixel_format: PixelFormat,
+    ) -> Result<(), RdpError> {
+        for (component, buffer) in self.components.iter().zip(ycbcr_buffer.iter_mut()) {
+            buffer.copy_from_slice(&component.coefficients);
+            progressive::inverse_dwt(buffer, temp, extrapolate);
+        }
+
+        let ycbcr_buffer = YCbCrBuffer {
+            y: &ycbcr_buffer[0],
+            cb: &ycbcr_buffer[1],
+            cr: &ycbcr_buffer[2],
+        };
+        color_conversion::ycbcr_to_bgra(ycbcr_buffer, output)?;
+        convert_bgrx_in_place(output, pixel_format);
+
+        Ok(())
+    }
+}
+
+struct ComponentState {
+    coefficients: Vec<i16>,
+    /// The coefficients as first decoded, the upgrades refining the magnitude of the nonzero ones.
+    sign: Vec<i16>,
+    /// Bit position of the lowest bit decoded for each subband.
+    bit_positions: [u8; SUBBANDS_COUNT],
+}
+
+impl ComponentState {
+    fn new() -> Self {
+        Self {
+            coefficients: vec![0; COEFFICIENTS_COUNT],
+            sign: vec![0; COEFFICIENTS_COUNT],
+            bit_positions: [0; SUBBANDS_COUNT],
+        }
+    }
+}
+
+fn tile_quants<'a>(region: &'a Region<'_>, quant_indices: [u8; 3]) -> Result<[&'a ComponentQuant; 3], RdpError> {
+    let quant = |index: u8| {
+        region.quants.get(usize::from(index)).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("got tile with the quant {} of {}", index, region.quants.len()),
+            )
+        })
+    };
+
+    Ok([
+        quant(quant_indices[0])?,
+        quant(quant_indices[1])?,
+        quant(quant_indices[2])?,
+    ])
+}
+
+fn progressive_quant(region: &Region<'_>, quality: u8) -> Result<ProgressiveQuant, RdpError> {
+    if quality == FULL_QUALITY {
+        return Ok(ProgressiveQuant::default());
+    }
+
+    let progressive_quant = region.progressive_quants.get(usize::from(quality)).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("got tile of quality {} of {}", quality, region.progressive_quants.len()),
+        )
+    })?;
+
+    Ok(progressive_quant.clone())
+}
+
+/// The bit positions down to which the coefficients of the subbands are sent, the sums of the quantization
+/// factors of the tile and of its quality.
+fn bit_positions(quant: &ComponentQuant, progressive_quant: &ComponentQuant) -> [u8; SUBBANDS_COUNT] {
+    let mut bit_positions = quant.subbands();
+    for (bit_position, progressive) in bit_positions.iter_mut().zip(progressive_quant.subbands()) {
+        *bit_position += progressive;
+    }
+
+    bit_positions
+}
+
+/// The part of the tile within the rectangle of the region, relative to the tile.
+fn clip_to_tile(rectangle: &RfxRectangle, tile: &Rectangle) -> Option<Rectangle> {
+    let left = u32::from(rectangle.x).max(tile.left);
+    let top = u32::from(rectangle.y).max(tile.top);
+    let right = (u32::from(rectangle.x) + u32::from(rectangle.width)).min(tile.right);
+    let bottom = (u32::from(rectangle.y) + u32::from(rectangle.height)).min(tile.bottom);
+
+    (left < right && top < bottom).then(|| Rectangle {
+        left: left - tile.left,
+        top: top - tile.top,
+        right: right - tile.left,
+        bottom: bottom - tile.top,
+    })
+}
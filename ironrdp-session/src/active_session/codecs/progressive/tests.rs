@@ -0,0 +1,72 @@
+use super::*;
+use crate::image::DecodedImage;
+
+const IMAGE_WIDTH: u32 = 128;
+const IMAGE_HEIGHT: u32 = 32;
+
+#[rustfmt::skip]
+const SIMPLE_TILE_REGION: [u8; 53] = [
+    // region: tile size, 1 rectangle, 1 quant, no progressive quant, reduce-extrapolate, 1 tile of 22 bytes
+    0xc4, 0xcc, 0x35, 0x00, 0x00, 0x00, 0x40, 0x01, 0x00, 0x01, 0x00, 0x01, 0x01, 0x00, 0x16, 0x00, 0x00, 0x00,
+    // 32x16 rectangle at (64, 0)
+    0x40, 0x00, 0x00, 0x00, 0x20, 0x00, 0x10, 0x00,
+    0x66, 0x66, 0x66, 0x66, 0x66,
+    // simple tile (1, 0) without data, its coefficients being zeros
+    0xc5, 0xcc, 0x16, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+#[rustfmt::skip]
+const UPGRADE_TILE_REGION: [u8; 57] = [
+    0xc4, 0xcc, 0x39, 0x00, 0x00, 0x00, 0x40, 0x01, 0x00, 0x01, 0x00, 0x01, 0x01, 0x00, 0x1a, 0x00, 0x00, 0x00,
+    0x40, 0x00, 0x00, 0x00, 0x20, 0x00, 0x10, 0x00,
+    0x66, 0x66, 0x66, 0x66, 0x66,
+    // upgrade of tile (1, 0) to the full quality, without data
+    0xc7, 0xcc, 0x1a, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0xff,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+fn pixel(image: &DecodedImage, x: u32, y: u32) -> [u8; 3] {
+    let index = ((y * IMAGE_WIDTH + x) * 4) as usize;
+    let data = image.data();
+
+    [data[index], data[index + 1], data[index + 2]]
+}
+
+#[test]
+fn tile_is_clipped_to_region_at_output_origin() {
+    let mut image = DecodedImage::new(PixelFormat::BgrX32, IMAGE_WIDTH, IMAGE_HEIGHT);
+
+    let update_region = DecodingContext::new()
+        .decode(&mut image, 1, &SIMPLE_TILE_REGION, Some((0, 8)))
+        .unwrap();
+
+    assert_eq!(
+        Some(Rectangle {
+            left: 64,
+            top: 8,
+            right: 96,
+            bottom: 24,
+        }),
+        update_region
+    );
+    // the zero coefficients are the middle of the luma and chroma ranges
+    assert_eq!([128, 128, 128], pixel(&image, 64, 8));
+    assert_eq!([128, 128, 128], pixel(&image, 95, 23));
+    assert_eq!([0, 0, 0], pixel(&image, 96, 8));
+    assert_eq!([0, 0, 0], pixel(&image, 64, 7));
+}
+
+#[test]
+fn upgrade_of_tile_not_decoded_is_rejected() {
+    let mut image = DecodedImage::new(PixelFormat::BgrX32, IMAGE_WIDTH, IMAGE_HEIGHT);
+    let mut context = DecodingContext::new();
+
+    assert!(context.decode(&mut image, 1, &UPGRADE_TILE_REGION, None).is_err());
+
+    context.decode(&mut image, 1, &SIMPLE_TILE_REGION, None).unwrap();
+    assert!(context.decode(&mut image, 1, &UPGRADE_TILE_REGION, None).is_ok());
+
+    context.delete_surface(1);
+    assert!(context.decode(&mut image, 1, &UPGRADE_TILE_REGION, None).is_err());
+}
@@ -2,6 +2,7 @@
 mod tests;
 
 use std::cmp::min;
+use std::io;
 
 use ironrdp::codecs::rfx::color_conversion::YCbCrBuffer;
 use ironrdp::codecs::rfx::image_processing::PixelFormat;
@@ -14,8 +15,8 @@ use ironrdp::{PduBufferParsing, Rectangle};
 use lazy_static::lazy_static;
 use log::debug;
 
-use crate::image::DecodedImage;
-use crate::RdpError;
+use crate::image::{convert_bgrx_in_place, ImageSink, ImageUpdate};
+use crate::{Codec, RdpError};
 
 const TILE_SIZE: u16 = 64;
 const SOURCE_PIXEL_FORMAT: PixelFormat = PixelFormat::BgrX32;
@@ -31,6 +32,7 @@ pub struct DecodingContext {
     context: rfx::ContextPdu,
     channels: rfx::ChannelsPdu,
     decoding_tiles: DecodingTileContext,
+    pixel_format: PixelFormat,
 }
 
 impl Default for DecodingContext {
@@ -43,6 +45,7 @@ impl Default for DecodingContext {
             },
             channels: rfx::ChannelsPdu(vec![]),
             decoding_tiles: DecodingTileContext::new(),
+            pixel_format: SOURCE_PIXEL_FORMAT,
         }
     }
 }
@@ -52,9 +55,16 @@ impl DecodingContext {
         Self::default()
     }
 
+    /// Sets the pixel format of the decoded tiles passed to the image sink, `BgrX32` by default.
+    pub fn with_pixel_format(mut self, pixel_format: PixelFormat) -> Self {
+        self.pixel_format = pixel_format;
+
+        self
+    }
+
     pub fn decode(
         &mut self,
-        image: &mut DecodedImage,
+        image: &mut dyn ImageSink,
         destination: &Rectangle,
         input: &mut &[u8],
     ) -> Result<(FrameId, Rectangle), RdpError> {
@@ -100,13 +110,17 @@ impl DecodingContext {
 
     fn process_data_messages(
         &mut self,
-        image: &mut DecodedImage,
+        image: &mut dyn ImageSink,
         destination: &Rectangle,
         input: &mut &[u8],
     ) -> Result<(FrameId, Rectangle), RdpError> {
-        let width = self.channels.0.first().unwrap().width as u16;
-        let height = self.channels.0.first().unwrap().height as u16;
+        let channel = self.channels.0.first().ok_or(RdpError::NoRfxChannelsAnnounced)?;
+        let channel_width = channel.width as u16;
+        let channel_height = channel.height as u16;
+        let width = u32::from(channel_width);
+        let height = u32::from(channel_height);
         let entropy_algorithm = self.context.entropy_algorithm;
+        let pixel_format = self.pixel_format;
 
         let frame_begin = rfx::FrameBeginPdu::from_buffer_consume(input)?;
         let mut region = rfx::RegionPdu::from_buffer_consume(input)?;
@@ -114,12 +128,11 @@ impl DecodingContext {
         let _frame_end = rfx::FrameEndPdu::from_buffer_consume(input)?;
 
         if region.rectangles.is_empty() {
-            let channel = self.channels.0.first().unwrap();
             region.rectangles = vec![RfxRectangle {
                 x: 0,
                 y: 0,
-                width: channel.width as u16,
-                height: channel.height as u16,
+                width: channel_width,
+                height: channel_height,
             }];
         }
         let region = region;
@@ -133,23 +146,88 @@ impl DecodingContext {
         let clipping_rectangles = clipping_rectangles(region.rectangles.as_slice(), destination, width, height);
         debug!("Clipping rectangles: {:?}", clipping_rectangles);
 
-        for (update_rectangle, tile_data) in tiles_to_rectangles(tile_set.tiles.as_slice(), destination)
-            .zip(map_tiles_data(tile_set.tiles.as_slice(), tile_set.quants.as_slice()))
+        // the tiles index the quantization values of their tile set, which are not checked by the parsing
+        if let Some(quant_index) = tile_set
+            .tiles
+            .iter()
+            .flat_map(|t| [t.y_quant_index, t.cb_quant_index, t.cr_quant_index])
+            .find(|&quant_index| usize::from(quant_index) >= tile_set.quants.len())
         {
-            decode_tile(
-                &tile_data,
-                entropy_algorithm,
-                self.decoding_tiles.tile_output.as_mut(),
-                self.decoding_tiles.ycbcr_buffer.as_mut(),
-                self.decoding_tiles.ycbcr_temp_buffer.as_mut(),
+            return Err(RdpError::RfxError(rfx::RfxError::InvalidQuantIndex(quant_index)));
+        }
+
+        let tiles_data = map_tiles_data(tile_set.tiles.as_slice(), tile_set.quants.as_slice());
+
+        #[cfg(feature = "parallel")]
+        for (update_rectangle, tile_output) in tiles_to_rectangles(tile_set.tiles.as_slice(), destination).zip(
+            decode_tiles_parallel(&tiles_data.collect::<Vec<_>>(), entropy_algorithm, pixel_format)?,
+        ) {
+            let update_region = clipping_rectangles.intersect_rectangle(&update_rectangle);
+            let written_to_sink = write_tile_to_sink(
+                image,
+                pixel_format,
+                &update_region,
+                &update_rectangle,
+                |output, output_stride| {
+                    let row_length = usize::from(TILE_SIZE) * usize::from(pixel_format.bytes_per_pixel());
+                    for (source, destination) in tile_output
+                        .chunks(usize::from(*SOURCE_STRIDE))
+                        .zip(output.chunks_mut(output_stride))
+                    {
+                        destination[..row_length].copy_from_slice(&source[..row_length]);
+                    }
+
+                    Ok(())
+                },
             )?;
 
-            image.apply_tile(
-                &self.decoding_tiles.tile_output,
-                &clipping_rectangles,
+            if !written_to_sink {
+                apply_tile(image, pixel_format, &tile_output, &update_region, &update_rectangle)?;
+            }
+        }
+
+        #[cfg(not(feature = "parallel"))]
+        for (update_rectangle, tile_data) in tiles_to_rectangles(tile_set.tiles.as_slice(), destination).zip(tiles_data)
+        {
+            let decoding_tiles = &mut self.decoding_tiles;
+            let update_region = clipping_rectangles.intersect_rectangle(&update_rectangle);
+            let written_to_sink = write_tile_to_sink(
+                image,
+                pixel_format,
+                &update_region,
                 &update_rectangle,
-                width,
+                |output, output_stride| {
+                    decode_tile(
+                        &tile_data,
+                        entropy_algorithm,
+                        pixel_format,
+                        output,
+                        output_stride,
+                        decoding_tiles.ycbcr_buffer.as_mut(),
+                        decoding_tiles.ycbcr_temp_buffer.as_mut(),
+                    )
+                },
             )?;
+
+            if !written_to_sink {
+                decode_tile(
+                    &tile_data,
+                    entropy_algorithm,
+                    pixel_format,
+                    decoding_tiles.tile_output.as_mut(),
+                    usize::from(*SOURCE_STRIDE),
+                    decoding_tiles.ycbcr_buffer.as_mut(),
+                    decoding_tiles.ycbcr_temp_buffer.as_mut(),
+                )?;
+
+                apply_tile(
+                    image,
+                    pixel_format,
+                    &decoding_tiles.tile_output,
+                    &update_region,
+                    &update_rectangle,
+                )?;
+            }
         }
 
         if self.context.flags.contains(rfx::OperatingMode::IMAGE_MODE) {
@@ -177,10 +255,75 @@ impl DecodingTileContext {
     }
 }
 
+/// Passes the parts of the tile within the update region, the intersection of the tile with the clipping
+/// rectangles, to the image sink.
+fn apply_tile(
+    image: &mut dyn ImageSink,
+    pixel_format: PixelFormat,
+    tile_output: &[u8],
+    update_region: &Region,
+    update_rectangle: &Rectangle,
+) -> Result<(), RdpError> {
+    debug!("Tile: {:?}", update_rectangle);
+
+    for region_rectangle in &update_region.rectangles {
+        let source_x = (region_rectangle.left - update_rectangle.left) as usize;
+        let source_y = (region_rectangle.top - update_rectangle.top) as usize;
+        let stride = usize::from(*SOURCE_STRIDE);
+        let offset = source_y * stride + source_x * usize::from(pixel_format.bytes_per_pixel());
+
+        image.update(&ImageUpdate {
+            rectangle: region_rectangle.clone(),
+            pixel_format,
+            stride,
+            data: &tile_output[offset..],
+            codec: Codec::RemoteFx,
+        })?;
+    }
+
+    Ok(())
+}
+
+/// Decodes the tile into the buffer lent by the image sink, if the tile is entirely covered by the updated
+/// region. Returns `false` if no buffer has been lent, the tile having to be applied with [`apply_tile`].
+fn write_tile_to_sink(
+    image: &mut dyn ImageSink,
+    pixel_format: PixelFormat,
+    update_region: &Region,
+    update_rectangle: &Rectangle,
+    write: impl FnOnce(&mut [u8], usize) -> Result<(), RdpError>,
+) -> Result<bool, RdpError> {
+    if !matches!(update_region.rectangles.as_slice(), [rectangle] if rectangle == update_rectangle) {
+        return Ok(false);
+    }
+
+    let buffer = match image.tile_buffer(update_rectangle) {
+        Some(buffer) => buffer,
+        None => return Ok(false),
+    };
+
+    let row_length = usize::from(TILE_SIZE) * usize::from(pixel_format.bytes_per_pixel());
+    if buffer.stride < row_length || buffer.data.len() < (usize::from(TILE_SIZE) - 1) * buffer.stride + row_length {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "the tile buffer lent by the image sink is too small",
+        )
+        .into());
+    }
+
+    write(buffer.data, buffer.stride)?;
+    image.tile_decoded(update_rectangle)?;
+
+    Ok(true)
+}
+
+/// Decodes the tile in the pixel format, the row `n` of the tile being written at `output[n * output_stride]`.
 fn decode_tile(
     tile: &TileData<'_>,
     entropy_algorithm: EntropyAlgorithm,
+    pixel_format: PixelFormat,
     output: &mut [u8],
+    output_stride: usize,
     ycbcr_temp: &mut [Vec<i16>],
     temp: &mut [i16],
 ) -> Result<(), RdpError> {
@@ -188,17 +331,52 @@ fn decode_tile(
         decode_component(quant, entropy_algorithm, data, ycbcr_buffer.as_mut_slice(), temp)?;
     }
 
-    let ycbcr_buffer = YCbCrBuffer {
-        y: ycbcr_temp[0].as_slice(),
-        cb: ycbcr_temp[1].as_slice(),
-        cr: ycbcr_temp[2].as_slice(),
-    };
-
-    color_conversion::ycbcr_to_bgra(ycbcr_buffer, output)?;
+    let tile_width = usize::from(TILE_SIZE);
+    let row_length = tile_width * usize::from(pixel_format.bytes_per_pixel());
+    let mut source_row = [0; TILE_SIZE as usize * SOURCE_PIXEL_FORMAT.bytes_per_pixel() as usize];
+
+    for (row, output_row) in output.chunks_mut(output_stride).take(tile_width).enumerate() {
+        let pixels = row * tile_width..(row + 1) * tile_width;
+        let ycbcr_buffer = YCbCrBuffer {
+            y: &ycbcr_temp[0][pixels.clone()],
+            cb: &ycbcr_temp[1][pixels.clone()],
+            cr: &ycbcr_temp[2][pixels],
+        };
+
+        color_conversion::ycbcr_to_bgra(ycbcr_buffer, &mut source_row)?;
+        convert_bgrx_in_place(&mut source_row, pixel_format);
+        output_row[..row_length].copy_from_slice(&source_row[..row_length]);
+    }
 
     Ok(())
 }
 
+#[cfg(feature = "parallel")]
+fn decode_tiles_parallel(
+    tiles: &[TileData<'_>],
+    entropy_algorithm: EntropyAlgorithm,
+    pixel_format: PixelFormat,
+) -> Result<Vec<Vec<u8>>, RdpError> {
+    use rayon::prelude::*;
+
+    tiles
+        .par_iter()
+        .map_init(DecodingTileContext::new, |context, tile| {
+            decode_tile(
+                tile,
+                entropy_algorithm,
+                pixel_format,
+                context.tile_output.as_mut(),
+                usize::from(*SOURCE_STRIDE),
+                context.ycbcr_buffer.as_mut(),
+                context.ycbcr_temp_buffer.as_mut(),
+            )?;
+
+            Ok(context.tile_output.clone())
+        })
+        .collect()
+}
+
 fn decode_component(
     quant: &Quant,
     entropy_algorithm: EntropyAlgorithm,
@@ -214,16 +392,21 @@ fn decode_component(
     Ok(())
 }
 
-fn clipping_rectangles(rectangles: &[RfxRectangle], destination: &Rectangle, width: u16, height: u16) -> Region {
+fn clipping_rectangles(rectangles: &[RfxRectangle], destination: &Rectangle, width: u32, height: u32) -> Region {
     let mut clipping_rectangles = Region::new();
 
     rectangles
         .iter()
-        .map(|r| Rectangle {
-            left: min(destination.left + r.x, width),
-            top: min(destination.top + r.y, height),
-            right: min(destination.left + r.x + r.width, width),
-            bottom: min(destination.top + r.y + r.height, height),
+        .map(|r| {
+            let left = destination.left.saturating_add(u32::from(r.x));
+            let top = destination.top.saturating_add(u32::from(r.y));
+
+            Rectangle {
+                left: min(left, width),
+                top: min(top, height),
+                right: min(left.saturating_add(u32::from(r.width)), width),
+                bottom: min(top.saturating_add(u32::from(r.height)), height),
+            }
         })
         .for_each(|r| clipping_rectangles.union_rectangle(r));
 
@@ -231,26 +414,31 @@ fn clipping_rectangles(rectangles: &[RfxRectangle], destination: &Rectangle, wid
 }
 
 fn tiles_to_rectangles<'a>(tiles: &'a [Tile<'_>], destination: &'a Rectangle) -> impl Iterator<Item = Rectangle> + 'a {
-    tiles.iter().map(|t| Rectangle {
-        left: destination.left + t.x * TILE_SIZE,
-        top: destination.top + t.y * TILE_SIZE,
-        right: destination.left + t.x * TILE_SIZE + TILE_SIZE,
-        bottom: destination.top + t.y * TILE_SIZE + TILE_SIZE,
+    let tile_size = u32::from(TILE_SIZE);
+
+    tiles.iter().map(move |t| {
+        let left = destination.left.saturating_add(u32::from(t.x) * tile_size);
+        let top = destination.top.saturating_add(u32::from(t.y) * tile_size);
+
+        Rectangle {
+            left,
+            top,
+            right: left.saturating_add(tile_size),
+            bottom: top.saturating_add(tile_size),
+        }
     })
 }
 
-fn map_tiles_data<'a>(tiles: &'_ [Tile<'a>], quants: &'_ [Quant]) -> Vec<TileData<'a>> {
-    tiles
-        .iter()
-        .map(|t| TileData {
-            quants: [
-                quants[usize::from(t.y_quant_index)].clone(),
-                quants[usize::from(t.cb_quant_index)].clone(),
-                quants[usize::from(t.cr_quant_index)].clone(),
-            ],
-            data: [t.y_data, t.cb_data, t.cr_data],
-        })
-        .collect()
+/// Maps the tiles to their data as they are decoded, without collecting them unless they are decoded in parallel.
+fn map_tiles_data<'a, 'b>(tiles: &'b [Tile<'a>], quants: &'b [Quant]) -> impl Iterator<Item = TileData<'a>> + 'b {
+    tiles.iter().map(move |t| TileData {
+        quants: [
+            quants[usize::from(t.y_quant_index)].clone(),
+            quants[usize::from(t.cb_quant_index)].clone(),
+            quants[usize::from(t.cr_quant_index)].clone(),
+        ],
+        data: [t.y_data, t.cb_data, t.cr_data],
+    })
 }
 
 struct TileData<'a> {
@@ -1,5 +1,7 @@
+use ironrdp::codecs::rfx::image_processing::rgb16;
+
 use super::*;
-use crate::active_session::DecodedImage;
+use crate::image::{DecodedImage, TileBuffer};
 
 const IMAGE_WIDTH: usize = 64;
 const IMAGE_HEIGHT: usize = 64;
@@ -10,8 +12,8 @@ fn decode_decodes_valid_sequence_of_messages() {
     let destination = Rectangle {
         left: 0,
         top: 0,
-        right: IMAGE_WIDTH as u16,
-        bottom: IMAGE_HEIGHT as u16,
+        right: IMAGE_WIDTH as u32,
+        bottom: IMAGE_HEIGHT as u32,
     };
     let mut data = ENCODED_MESSAGES.as_ref();
     let expected = DECODED_IMAGE.as_ref();
@@ -25,6 +27,145 @@ fn decode_decodes_valid_sequence_of_messages() {
     assert_eq!(expected, image.data());
 }
 
+#[test]
+fn decode_passes_decoded_rectangles_to_closure_sink() {
+    let destination = Rectangle {
+        left: 0,
+        top: 0,
+        right: IMAGE_WIDTH as u32,
+        bottom: IMAGE_HEIGHT as u32,
+    };
+    let mut data = ENCODED_MESSAGES.as_ref();
+    let expected = DECODED_IMAGE.as_ref();
+
+    let mut framebuffer = vec![0; IMAGE_WIDTH * IMAGE_HEIGHT * FORMAT_SIZE];
+    let mut sink = |update: &ImageUpdate<'_>| {
+        assert_eq!(PixelFormat::BgrX32, update.pixel_format);
+
+        let row_length = update.rectangle.width() as usize * FORMAT_SIZE;
+        for row in 0..update.rectangle.height() as usize {
+            let source = &update.data[row * update.stride..][..row_length];
+            let destination = (update.rectangle.top as usize + row) * IMAGE_WIDTH * FORMAT_SIZE
+                + update.rectangle.left as usize * FORMAT_SIZE;
+            framebuffer[destination..][..row_length].copy_from_slice(source);
+        }
+    };
+
+    let mut handler = DecodingContext::default();
+
+    handler.decode(&mut sink, &destination, &mut data).unwrap();
+
+    assert_eq!(expected, framebuffer.as_slice());
+}
+
+#[test]
+fn decode_converts_tiles_to_requested_pixel_format() {
+    let destination = Rectangle {
+        left: 0,
+        top: 0,
+        right: IMAGE_WIDTH as u32,
+        bottom: IMAGE_HEIGHT as u32,
+    };
+    let mut data = ENCODED_MESSAGES.as_ref();
+    let expected = DECODED_IMAGE
+        .chunks_exact(FORMAT_SIZE)
+        .flat_map(|pixel| rgb16(pixel[2], pixel[1], pixel[0]).to_le_bytes())
+        .collect::<Vec<_>>();
+
+    let mut framebuffer = vec![0; IMAGE_WIDTH * IMAGE_HEIGHT * 2];
+    let mut sink = |update: &ImageUpdate<'_>| {
+        assert_eq!(PixelFormat::Rgb16, update.pixel_format);
+
+        let row_length = update.rectangle.width() as usize * 2;
+        for row in 0..update.rectangle.height() as usize {
+            let source = &update.data[row * update.stride..][..row_length];
+            let destination =
+                (update.rectangle.top as usize + row) * IMAGE_WIDTH * 2 + update.rectangle.left as usize * 2;
+            framebuffer[destination..][..row_length].copy_from_slice(source);
+        }
+    };
+
+    let mut handler = DecodingContext::default().with_pixel_format(PixelFormat::Rgb16);
+
+    handler.decode(&mut sink, &destination, &mut data).unwrap();
+
+    assert_eq!(expected, framebuffer);
+}
+
+/// Lends a buffer wider than the tiles, as a mapped texture of the whole desktop would be.
+struct LendingSink {
+    buffer: Vec<u8>,
+    stride: usize,
+    decoded_tiles: Vec<Rectangle>,
+}
+
+impl ImageSink for LendingSink {
+    fn update(&mut self, _update: &ImageUpdate<'_>) -> Result<(), RdpError> {
+        panic!("the tiles must be decoded into the lent buffer");
+    }
+
+    fn tile_buffer(&mut self, _tile: &Rectangle) -> Option<TileBuffer<'_>> {
+        Some(TileBuffer {
+            data: self.buffer.as_mut_slice(),
+            stride: self.stride,
+        })
+    }
+
+    fn tile_decoded(&mut self, tile: &Rectangle) -> Result<(), RdpError> {
+        self.decoded_tiles.push(tile.clone());
+
+        Ok(())
+    }
+}
+
+#[test]
+fn decode_writes_covered_tiles_into_buffer_lent_by_sink() {
+    let destination = Rectangle {
+        left: 0,
+        top: 0,
+        right: IMAGE_WIDTH as u32,
+        bottom: IMAGE_HEIGHT as u32,
+    };
+    let mut data = ENCODED_MESSAGES.as_ref();
+    let row_length = IMAGE_WIDTH * FORMAT_SIZE;
+    let stride = row_length + 16 * FORMAT_SIZE;
+    let mut sink = LendingSink {
+        buffer: vec![0; IMAGE_HEIGHT * stride],
+        stride,
+        decoded_tiles: Vec::new(),
+    };
+
+    let mut handler = DecodingContext::default();
+
+    handler.decode(&mut sink, &destination, &mut data).unwrap();
+
+    assert_eq!(vec![destination], sink.decoded_tiles);
+    for (expected, row) in DECODED_IMAGE.chunks(row_length).zip(sink.buffer.chunks(stride)) {
+        assert_eq!(expected, &row[..row_length]);
+    }
+}
+
+#[test]
+fn decode_fails_on_too_small_buffer_lent_by_sink() {
+    let destination = Rectangle {
+        left: 0,
+        top: 0,
+        right: IMAGE_WIDTH as u32,
+        bottom: IMAGE_HEIGHT as u32,
+    };
+    let mut data = ENCODED_MESSAGES.as_ref();
+    let stride = IMAGE_WIDTH * FORMAT_SIZE;
+    let mut sink = LendingSink {
+        buffer: vec![0; (IMAGE_HEIGHT - 1) * stride],
+        stride,
+        decoded_tiles: Vec::new(),
+    };
+
+    let mut handler = DecodingContext::default();
+
+    assert!(handler.decode(&mut sink, &destination, &mut data).is_err());
+}
+
 const ENCODED_MESSAGES: [u8; 2970] = [
     /* HEADERS as in 4.2.2 */
     0xc0, 0xcc, 0x0c, 0x00, 0x00, 0x00, 0xca, 0xac, 0xcc, 0xca, 0x00, 0x01, 0xc3, 0xcc, 0x0d, 0x00, 0x00, 0x00, 0x01,
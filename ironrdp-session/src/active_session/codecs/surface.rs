@@ -0,0 +1,59 @@
+//! Drawing of the bitmaps decoded for the Graphics Pipeline surfaces.
+
+use std::io;
+
+use ironrdp::codecs::rfx::image_processing::PixelFormat;
+use ironrdp::Rectangle;
+
+use crate::image::{ImageSink, ImageUpdate};
+use crate::{Codec, RdpError};
+
+/// Passes the regions of the bitmap decoded for the destination of a Graphics Pipeline surface, relative to it,
+/// to the image sink at the output origin of the surface. Returns the updated region of the output, if any.
+pub(crate) fn update_surface(
+    image: &mut dyn ImageSink,
+    regions: &[Rectangle],
+    data: &[u8],
+    pixel_format: PixelFormat,
+    destination: &Rectangle,
+    output_origin: (u32, u32),
+) -> Result<Option<Rectangle>, RdpError> {
+    let bytes_per_pixel = usize::from(pixel_format.bytes_per_pixel());
+    let stride = destination.width() as usize * bytes_per_pixel;
+    let (left, top) = output_origin;
+    let left = left.saturating_add(destination.left);
+    let top = top.saturating_add(destination.top);
+    let mut update_region: Option<Rectangle> = None;
+
+    for region in regions {
+        let rectangle = Rectangle {
+            left: left.saturating_add(region.left),
+            top: top.saturating_add(region.top),
+            right: left.saturating_add(region.right),
+            bottom: top.saturating_add(region.bottom),
+        };
+
+        let offset = region.top as usize * stride + region.left as usize * bytes_per_pixel;
+        let data = data.get(offset..).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("got region {:?} outside of the decoded bitmap", region),
+            )
+        })?;
+
+        image.update(&ImageUpdate {
+            rectangle: rectangle.clone(),
+            pixel_format,
+            stride,
+            data,
+            codec: Codec::Zgfx,
+        })?;
+
+        update_region = Some(match update_region {
+            Some(update_region) => update_region.union(&rectangle),
+            None => rectangle,
+        });
+    }
+
+    Ok(update_region)
+}
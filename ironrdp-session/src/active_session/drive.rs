@@ -0,0 +1,293 @@
+use std::fs;
+use std::io::{self, Read as _, Seek as _, SeekFrom, Write as _};
+use std::path::{Component, Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ironrdp::rdp::vc::rdpdr::{
+    CreateDisposition, CreateInformation, DirectoryEntry, FileAttributes, FileBasicInformation, FileInformation,
+    VolumeInformation,
+};
+
+/// Number of 100-nanosecond intervals between January 1, 1601 and the Unix epoch.
+const FILETIME_UNIX_EPOCH: u64 = 116_444_736_000_000_000;
+const ALLOCATION_UNIT_SIZE: u64 = 4096;
+const SECTORS_PER_ALLOCATION_UNIT: u32 = 8;
+const BYTES_PER_SECTOR: u32 = 512;
+
+/// File system of a drive redirected to the remote session over the `rdpdr` channel,
+/// see [`ActiveStageProcessor::add_drive`](crate::ActiveStageProcessor::add_drive).
+///
+/// The paths are relative to the root of the drive, their components being separated by `/`,
+/// the root itself being the empty path. They never hold `.` nor `..` components.
+pub trait FileSystemBackend: Send {
+    /// Opens or creates the file or directory, returning whether it has been created, opened or overwritten.
+    fn open(&mut self, path: &str, options: &FileOpenOptions) -> io::Result<(Box<dyn FileHandle>, CreateInformation)>;
+
+    /// Removes the file or the empty directory, which the remote session has marked for deletion and then closed.
+    fn remove(&mut self, path: &str) -> io::Result<()>;
+
+    fn rename(&mut self, from: &str, to: &str, replace_if_exists: bool) -> io::Result<()>;
+
+    fn volume_information(&mut self) -> io::Result<VolumeInformation>;
+}
+
+/// A file or directory opened by the remote session.
+pub trait FileHandle: Send {
+    fn information(&mut self) -> io::Result<FileInformation>;
+
+    /// Reads at most `length` bytes at the offset, less only at the end of the file.
+    fn read(&mut self, offset: u64, length: u32) -> io::Result<Vec<u8>>;
+
+    /// Writes the data at the offset, returning the number of bytes written.
+    fn write(&mut self, offset: u64, data: &[u8]) -> io::Result<u32>;
+
+    fn set_len(&mut self, length: u64) -> io::Result<()>;
+
+    /// Returns all the entries of the directory, which the remote session filters.
+    fn read_directory(&mut self) -> io::Result<Vec<DirectoryEntry>>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileOpenOptions {
+    pub disposition: CreateDisposition,
+    /// `Some(true)` if the file must be a directory, `Some(false)` if it must not.
+    pub directory: Option<bool>,
+    /// The remote session requests to modify the file.
+    pub write: bool,
+}
+
+/// Restrictions on what the remote session can do with a redirected drive, enforced whatever its backend,
+/// see [`ActiveStageProcessor::add_drive_with_policy`](crate::ActiveStageProcessor::add_drive_with_policy).
+///
+/// Whatever the policy, the paths holding `.` or `..` components, or the `/` and `:` characters,
+/// are rejected before being passed to the backend.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DrivePolicy {
+    /// Denies the creation, the modification, the renaming and the deletion of the files and directories.
+    pub read_only: bool,
+    /// Hides the files and directories whose name starts with a `.`, which cannot be opened either.
+    pub hide_dotfiles: bool,
+    /// The maximum size of the files, which cannot be written or extended beyond it.
+    pub max_file_size: Option<u64>,
+    /// The maximum number of bytes the remote session can write to the drive during the connection.
+    pub write_quota: Option<u64>,
+}
+
+/// Exposes a local directory, and all its subdirectories, as a drive of the remote session.
+///
+/// As the standard library does not provide the free space of a volume, a nominal size is reported.
+#[derive(Debug, Clone)]
+pub struct LocalDirectory {
+    root: PathBuf,
+}
+
+impl LocalDirectory {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    fn resolve(&self, path: &str) -> io::Result<PathBuf> {
+        let mut resolved = self.root.clone();
+
+        for component in path.split('/').filter(|component| !component.is_empty()) {
+            // only normal components, so that the remote session cannot escape the root
+            match Path::new(component).components().collect::<Vec<_>>().as_slice() {
+                [Component::Normal(_)] => resolved.push(component),
+                _ => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("invalid path component: {:?}", component),
+                    ))
+                }
+            }
+        }
+
+        Ok(resolved)
+    }
+}
+
+impl FileSystemBackend for LocalDirectory {
+    fn open(&mut self, path: &str, options: &FileOpenOptions) -> io::Result<(Box<dyn FileHandle>, CreateInformation)> {
+        let path = self.resolve(path)?;
+        let metadata = fs::metadata(&path).ok();
+        let exists = metadata.is_some();
+
+        match options.disposition {
+            CreateDisposition::Open | CreateDisposition::Overwrite if !exists => {
+                return Err(io::Error::from(io::ErrorKind::NotFound))
+            }
+            CreateDisposition::Create if exists => return Err(io::Error::from(io::ErrorKind::AlreadyExists)),
+            _ => (),
+        }
+
+        let is_directory = metadata.map_or(options.directory == Some(true), |metadata| metadata.is_dir());
+        match (options.directory, is_directory) {
+            (Some(false), true) => return Err(io::Error::new(io::ErrorKind::Other, "the file is a directory")),
+            (Some(true), false) if exists => {
+                return Err(io::Error::new(io::ErrorKind::Other, "the file is not a directory"))
+            }
+            _ => (),
+        }
+
+        if is_directory {
+            if !exists {
+                fs::create_dir(&path)?;
+            }
+
+            let information = if exists {
+                CreateInformation::Opened
+            } else {
+                CreateInformation::Superseded
+            };
+
+            return Ok((Box::new(LocalFile { path, file: None }), information));
+        }
+
+        let truncate = matches!(
+            options.disposition,
+            CreateDisposition::Supersede | CreateDisposition::Overwrite | CreateDisposition::OverwriteIf
+        );
+        let file = fs::OpenOptions::new()
+            .read(true)
+            .write(options.write || truncate || !exists)
+            .create(!exists)
+            .truncate(truncate && exists)
+            .open(&path)?;
+
+        let information = match (exists, truncate) {
+            (false, _) => CreateInformation::Superseded,
+            (true, true) => CreateInformation::Overwritten,
+            (true, false) => CreateInformation::Opened,
+        };
+
+        Ok((Box::new(LocalFile { path, file: Some(file) }), information))
+    }
+
+    fn remove(&mut self, path: &str) -> io::Result<()> {
+        let path = self.resolve(path)?;
+
+        if fs::metadata(&path)?.is_dir() {
+            fs::remove_dir(path)
+        } else {
+            fs::remove_file(path)
+        }
+    }
+
+    fn rename(&mut self, from: &str, to: &str, replace_if_exists: bool) -> io::Result<()> {
+        let from = self.resolve(from)?;
+        let to = self.resolve(to)?;
+
+        if !replace_if_exists && fs::metadata(&to).is_ok() {
+            return Err(io::Error::from(io::ErrorKind::AlreadyExists));
+        }
+
+        fs::rename(from, to)
+    }
+
+    fn volume_information(&mut self) -> io::Result<VolumeInformation> {
+        let metadata = fs::metadata(&self.root)?;
+        let units = u64::from(u32::MAX);
+
+        Ok(VolumeInformation {
+            label: self
+                .root
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            serial_number: 0,
+            creation_time: metadata.created().map_or(0, to_filetime),
+            file_system_name: String::from("NTFS"),
+            total_allocation_units: units,
+            available_allocation_units: units,
+            sectors_per_allocation_unit: SECTORS_PER_ALLOCATION_UNIT,
+            bytes_per_sector: BYTES_PER_SECTOR,
+        })
+    }
+}
+
+struct LocalFile {
+    path: PathBuf,
+    /// `None` for the directories.
+    file: Option<fs::File>,
+}
+
+impl LocalFile {
+    fn file(&mut self) -> io::Result<&mut fs::File> {
+        self.file
+            .as_mut()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "the file is a directory"))
+    }
+}
+
+impl FileHandle for LocalFile {
+    fn information(&mut self) -> io::Result<FileInformation> {
+        Ok(file_information(&fs::metadata(&self.path)?))
+    }
+
+    fn read(&mut self, offset: u64, length: u32) -> io::Result<Vec<u8>> {
+        let file = self.file()?;
+        file.seek(SeekFrom::Start(offset))?;
+
+        let mut data = Vec::new();
+        file.take(u64::from(length)).read_to_end(&mut data)?;
+
+        Ok(data)
+    }
+
+    fn write(&mut self, offset: u64, data: &[u8]) -> io::Result<u32> {
+        let file = self.file()?;
+        file.seek(SeekFrom::Start(offset))?;
+        file.write_all(data)?;
+
+        Ok(data.len() as u32)
+    }
+
+    fn set_len(&mut self, length: u64) -> io::Result<()> {
+        self.file()?.set_len(length)
+    }
+
+    fn read_directory(&mut self) -> io::Result<Vec<DirectoryEntry>> {
+        fs::read_dir(&self.path)?
+            .map(|entry| {
+                let entry = entry?;
+
+                Ok(DirectoryEntry {
+                    file_name: entry.file_name().to_string_lossy().into_owned(),
+                    information: file_information(&entry.metadata()?),
+                })
+            })
+            .collect()
+    }
+}
+
+fn file_information(metadata: &fs::Metadata) -> FileInformation {
+    let mut file_attributes = if metadata.is_dir() {
+        FileAttributes::DIRECTORY
+    } else {
+        FileAttributes::ARCHIVE
+    };
+    if metadata.permissions().readonly() {
+        file_attributes |= FileAttributes::READONLY;
+    }
+
+    let last_write_time = metadata.modified().map_or(0, to_filetime);
+    let end_of_file = if metadata.is_dir() { 0 } else { metadata.len() };
+
+    FileInformation {
+        basic: FileBasicInformation {
+            creation_time: metadata.created().map_or(last_write_time, to_filetime),
+            last_access_time: metadata.accessed().map_or(last_write_time, to_filetime),
+            last_write_time,
+            change_time: last_write_time,
+            file_attributes,
+        },
+        end_of_file,
+        allocation_size: (end_of_file + ALLOCATION_UNIT_SIZE - 1) / ALLOCATION_UNIT_SIZE * ALLOCATION_UNIT_SIZE,
+    }
+}
+
+fn to_filetime(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map_or(0, |duration| {
+        FILETIME_UNIX_EPOCH + duration.as_secs() * 10_000_000 + u64::from(duration.subsec_nanos()) / 100
+    })
+}
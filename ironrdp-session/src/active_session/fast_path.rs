@@ -1,65 +1,193 @@
-use std::io;
+use std::time::Instant;
+use std::{io, mem};
 
+use ironrdp::bitmap::Bitmap;
+use ironrdp::codecs::rfx::image_processing::PixelFormat;
 use ironrdp::codecs::rfx::FrameAcknowledgePdu;
-use ironrdp::fast_path::{FastPathError, FastPathHeader, FastPathUpdate, FastPathUpdatePdu, Fragmentation, UpdateCode};
+use ironrdp::fast_path::{
+    FastPathError, FastPathHeader, FastPathUpdate, FastPathUpdatePdu, Fragmentation, SurfaceCommands, UpdateCode,
+};
+use ironrdp::pointer::PointerUpdate;
+use ironrdp::rdp::bulk::BulkDecompressor;
+use ironrdp::rdp::capability_sets::CodecGuid;
 use ironrdp::surface_commands::{FrameAction, FrameMarkerPdu, SurfaceCommand};
-use ironrdp::{PduBufferParsing, Rectangle, ShareDataPdu};
+use ironrdp::{ChannelId, PduBufferParsing, Rectangle, ShareDataPdu, UserId};
 use log::{debug, info, warn};
-use num_traits::FromPrimitive;
 
+use super::coalescing::{Coalescer, CoalescingConfig};
+#[cfg(feature = "bitmap")]
+use super::codecs::bitmap;
+#[cfg(feature = "nscodec")]
+use super::codecs::nscodec;
+#[cfg(feature = "rfx")]
 use super::codecs::rfx;
-use crate::image::DecodedImage;
+use super::pdu_hooks::{PduChannel, PduHooks};
+use super::pointer::{PointerCache, PointerChange, PointerHook, PointerHooks};
+use super::GraphicsFrameEvent;
+use crate::image::ImageSink;
 use crate::transport::{
     DataTransport, Encoder, McsTransport, SendDataContextTransport, ShareControlHeaderTransport,
     ShareDataHeaderTransport,
 };
-use crate::utils::CodecId;
 use crate::RdpError;
 
 pub struct Processor {
     complete_data: CompleteData,
+    #[cfg(feature = "rfx")]
     rfx_handler: rfx::DecodingContext,
+    #[cfg(feature = "bitmap")]
+    bitmap_handler: bitmap::DecodingContext,
+    #[cfg(feature = "nscodec")]
+    nscodec_handler: nscodec::DecodingContext,
     frame: Frame,
+    frame_id: Option<u32>,
+    pointer_hooks: PointerHooks,
+    pointer_cache: PointerCache,
+    pointer_changes: Vec<PointerChange>,
+    frame_events: Vec<GraphicsFrameEvent>,
 }
 
 impl Processor {
+    /// Returns the ID of the last frame the server has sent surface commands in since the previous call.
+    pub fn take_frame_id(&mut self) -> Option<u32> {
+        self.frame_id.take()
+    }
+
+    /// Holds back the Frame Acknowledge PDUs sent before the interval has elapsed, only the last one being sent.
+    pub fn set_ack_coalescing(&mut self, config: CoalescingConfig) {
+        self.frame.ack = Coalescer::new(Some(config));
+    }
+
+    /// Sends the acknowledgement held back if the interval has elapsed.
+    pub fn send_coalesced(
+        &mut self,
+        output: impl io::Write,
+        now: Instant,
+        hooks: &mut PduHooks,
+    ) -> Result<(), RdpError> {
+        match self.frame.ack.poll(now) {
+            Some(frame_id) => self.frame.send_ack(frame_id, output, hooks),
+            None => Ok(()),
+        }
+    }
+
+    pub fn coalescing_deadline(&self) -> Option<Instant> {
+        self.frame.ack.deadline()
+    }
+
+    pub fn add_pointer_hook(&mut self, hook: PointerHook) {
+        self.pointer_hooks.add(hook);
+    }
+
+    /// Applies the pointer update to the pointer cache and passes it to the pointer hooks,
+    /// the slow-path updates received on the I/O channel being passed here as well.
+    pub fn process_pointer_update(&mut self, update: PointerUpdate<'_>) {
+        self.pointer_changes.extend(self.pointer_cache.process(&update));
+        self.pointer_hooks.notify(update);
+    }
+
+    /// Returns the changes of the pointer since the previous call.
+    pub fn take_pointer_changes(&mut self) -> Vec<PointerChange> {
+        mem::take(&mut self.pointer_changes)
+    }
+
+    /// Returns the ends of the frames delimited by the frame markers of the surface commands since the previous call.
+    pub fn take_frame_events(&mut self) -> Vec<GraphicsFrameEvent> {
+        mem::take(&mut self.frame_events)
+    }
+
     // Returns true if image buffer was updated, false otherwise
     pub fn process(
         &mut self,
-        image: &mut DecodedImage,
+        image: &mut dyn ImageSink,
         header: &FastPathHeader,
         input: &[u8],
-        mut output: impl io::Write,
+        output: impl io::Write,
+        hooks: &mut PduHooks,
+        decompressor: &mut BulkDecompressor,
     ) -> Result<Option<Rectangle>, RdpError> {
         debug!("Got Fast-Path Header: {:?}", header);
 
         let update_pdu = FastPathUpdatePdu::from_buffer(input)?;
         debug!("Fast-Path Update fragmentation: {:?}", update_pdu.fragmentation);
 
-        let processed_complete_data = self
-            .complete_data
-            .process_data(update_pdu.data, update_pdu.fragmentation);
-        let update_code = update_pdu.update_code;
+        // each fragment is compressed on its own, with the history of the Share Data PDUs
+        let data = if update_pdu.compression_flags.is_empty() {
+            update_pdu.data
+        } else {
+            decompressor.decompress(
+                update_pdu.compression_type,
+                update_pdu.compression_flags,
+                update_pdu.data,
+            )?
+        };
 
-        let Some(data) = processed_complete_data else {
-            return Ok(None);
+        // the reassembly buffer is put back once the update has been processed, to be reused by the next one
+        let mut complete_data = mem::take(&mut self.complete_data);
+        let update_region = match complete_data.process_data(data, update_pdu.fragmentation) {
+            Some(data) => self.process_update(image, data, update_pdu.update_code, output, hooks),
+            None => Ok(None),
         };
+        self.complete_data = complete_data;
+
+        update_region
+    }
+
+    /// Processes the complete update, borrowed from the received frame or from the reassembly buffer.
+    fn process_update(
+        &mut self,
+        image: &mut dyn ImageSink,
+        data: &[u8],
+        update_code: UpdateCode,
+        mut output: impl io::Write,
+        hooks: &mut PduHooks,
+    ) -> Result<Option<Rectangle>, RdpError> {
+        // the surface commands are decoded as they are parsed, without being collected first
+        if update_code == UpdateCode::SurfaceCommands {
+            info!("Received Surface Commands");
+            hooks.received(PduChannel::FastPath, "Surface Commands");
+            let update_region = self.process_surface_commands(image, &mut output, SurfaceCommands::new(data), hooks)?;
+            return Ok(Some(update_region));
+        }
+
+        // the primary orders are encoded relative to the previous ones, which the bitmap handler keeps track of
+        if update_code == UpdateCode::Orders {
+            info!("Received Orders");
+            hooks.received(PduChannel::FastPath, "Orders");
+            return self.process_orders(image, data);
+        }
+
+        // the pointer is not drawn by the session, its changes are returned for the embedder to draw it
+        match PointerUpdate::from_buffer_with_code(data, update_code) {
+            Ok(Some(pointer_update)) => {
+                debug!("Received pointer update: {:?}", pointer_update);
+                hooks.received(PduChannel::FastPath, pointer_update.as_short_name());
+                self.process_pointer_update(pointer_update);
+                return Ok(None);
+            }
+            Ok(None) => (),
+            Err(error) => {
+                warn!("Received invalid pointer update: {:?}", error);
+                return Ok(None);
+            }
+        }
 
-        let update = FastPathUpdate::from_buffer_with_code(data.as_slice(), update_code);
+        let update = FastPathUpdate::from_buffer_with_code(data, update_code);
+        if let Ok(update) = update.as_ref() {
+            hooks.received(PduChannel::FastPath, update.as_short_name());
+        }
 
         match update {
             Ok(FastPathUpdate::SurfaceCommands(surface_commands)) => {
-                info!("Received Surface Commands: {} pieces", surface_commands.len());
-                let update_region = self.process_surface_commands(image, &mut output, surface_commands)?;
+                let update_region =
+                    self.process_surface_commands(image, &mut output, surface_commands.into_iter().map(Ok), hooks)?;
                 Ok(Some(update_region))
             }
             Ok(FastPathUpdate::Bitmap(bitmap)) => {
                 info!("Received Bitmap: {:?}", bitmap);
-                Ok(None)
+                self.process_bitmap(image, &bitmap)
             }
-            Err(FastPathError::UnsupportedFastPathUpdate(code))
-                if code == UpdateCode::Orders || code == UpdateCode::Palette =>
-            {
+            Err(FastPathError::UnsupportedFastPathUpdate(code)) if code == UpdateCode::Palette => {
                 Err(RdpError::UnexpectedFastPathUpdate(code))
             }
             Err(FastPathError::UnsupportedFastPathUpdate(update_code)) => {
@@ -74,30 +202,80 @@ impl Processor {
         }
     }
 
-    fn process_surface_commands(
+    #[cfg_attr(not(feature = "bitmap"), allow(unused_variables))]
+    fn process_bitmap(
+        &mut self,
+        image: &mut dyn ImageSink,
+        bitmap: &Bitmap<'_>,
+    ) -> Result<Option<Rectangle>, RdpError> {
+        #[cfg(feature = "bitmap")]
+        return self.bitmap_handler.process_bitmap(image, bitmap);
+
+        #[cfg(not(feature = "bitmap"))]
+        {
+            warn!("Skipping bitmap: the bitmap codec is not compiled in");
+            Ok(None)
+        }
+    }
+
+    /// The orders are only advertised along with the bitmap codec.
+    #[cfg_attr(not(feature = "bitmap"), allow(unused_variables))]
+    fn process_orders(&mut self, image: &mut dyn ImageSink, data: &[u8]) -> Result<Option<Rectangle>, RdpError> {
+        #[cfg(feature = "bitmap")]
+        return self.bitmap_handler.process_orders(image, data);
+
+        #[cfg(not(feature = "bitmap"))]
+        Err(RdpError::UnexpectedFastPathUpdate(UpdateCode::Orders))
+    }
+
+    #[cfg_attr(not(any(feature = "rfx", feature = "nscodec")), allow(unused_variables, unused_mut))]
+    fn process_surface_commands<'a>(
         &mut self,
-        image: &mut DecodedImage,
+        image: &mut dyn ImageSink,
         mut output: impl io::Write,
-        surface_commands: Vec<SurfaceCommand<'_>>,
+        surface_commands: impl Iterator<Item = Result<SurfaceCommand<'a>, FastPathError>>,
+        hooks: &mut PduHooks,
     ) -> Result<Rectangle, RdpError> {
         let mut update_rectangle = Rectangle::empty();
 
         for command in surface_commands {
-            match command {
+            match command? {
                 SurfaceCommand::SetSurfaceBits(bits) | SurfaceCommand::StreamSurfaceBits(bits) => {
                     info!("Surface bits");
-                    let codec_id = CodecId::from_u8(bits.extended_bitmap_data.codec_id)
-                        .ok_or(RdpError::UnexpectedCodecId(bits.extended_bitmap_data.codec_id))?;
-                    match codec_id {
-                        CodecId::RemoteFx => {
+                    let codec_id = bits.extended_bitmap_data.codec_id;
+                    match CodecGuid::from_id(codec_id).ok_or(RdpError::UnexpectedCodecId(codec_id))? {
+                        #[cfg(feature = "rfx")]
+                        CodecGuid::RemoteFx => {
                             let destination = bits.destination;
                             let mut data = bits.extended_bitmap_data.data;
 
+                            #[cfg(feature = "alloc-audit")]
+                            let _subsystem = crate::alloc_audit::enter(crate::alloc_audit::Subsystem::RemoteFx);
+
                             while !data.is_empty() {
-                                let (_frame_id, rectangle) = self.rfx_handler.decode(image, &destination, &mut data)?;
+                                let (frame_id, rectangle) = self.rfx_handler.decode(image, &destination, &mut data)?;
+                                update_rectangle = update_rectangle.union(&rectangle);
+                                // the frame markers take precedence over the frame index of RemoteFX
+                                self.frame_id.get_or_insert(frame_id);
+                            }
+                        }
+                        #[cfg(not(feature = "rfx"))]
+                        CodecGuid::RemoteFx => return Err(RdpError::CodecNotCompiledIn(crate::Codec::RemoteFx)),
+                        #[cfg(feature = "nscodec")]
+                        CodecGuid::NsCodec => {
+                            let rectangle =
+                                self.nscodec_handler
+                                    .decode(image, &bits.destination, &bits.extended_bitmap_data)?;
+                            if let Some(rectangle) = rectangle {
                                 update_rectangle = update_rectangle.union(&rectangle);
                             }
                         }
+                        #[cfg(not(feature = "nscodec"))]
+                        CodecGuid::NsCodec => return Err(RdpError::CodecNotCompiledIn(crate::Codec::NsCodec)),
+                        // not advertised to the server
+                        CodecGuid::ImageRemoteFx | CodecGuid::Ignore => {
+                            return Err(RdpError::UnexpectedCodecId(codec_id))
+                        }
                     }
                 }
                 SurfaceCommand::FrameMarker(marker) => {
@@ -106,7 +284,14 @@ impl Processor {
                         marker.frame_action,
                         marker.frame_id.unwrap_or(0)
                     );
-                    self.frame.process_marker(&marker, &mut output)?;
+                    match marker.frame_action {
+                        FrameAction::Begin => self.frame_id = marker.frame_id,
+                        // the surface commands carry no timestamp, so only the ends of the frames are reported
+                        FrameAction::End => self.frame_events.push(GraphicsFrameEvent::Ended {
+                            frame_id: marker.frame_id.unwrap_or(0),
+                        }),
+                    }
+                    self.frame.process_marker(&marker, &mut output, hooks)?;
                 }
             }
         }
@@ -116,41 +301,58 @@ impl Processor {
 }
 
 pub struct ProcessorBuilder {
-    pub global_channel_id: u16,
-    pub initiator_id: u16,
+    pub global_channel_id: ChannelId,
+    pub initiator_id: UserId,
+    pub pixel_format: PixelFormat,
 }
 
 impl ProcessorBuilder {
     pub fn build(self) -> Processor {
         Processor {
             complete_data: CompleteData::new(),
-            rfx_handler: rfx::DecodingContext::new(),
+            #[cfg(feature = "rfx")]
+            rfx_handler: rfx::DecodingContext::new().with_pixel_format(self.pixel_format),
+            #[cfg(feature = "bitmap")]
+            bitmap_handler: bitmap::DecodingContext::new().with_pixel_format(self.pixel_format),
+            #[cfg(feature = "nscodec")]
+            nscodec_handler: nscodec::DecodingContext::new().with_pixel_format(self.pixel_format),
             frame: Frame::new(self.initiator_id, self.global_channel_id),
+            frame_id: None,
+            pointer_hooks: PointerHooks::default(),
+            pointer_cache: PointerCache::default(),
+            pointer_changes: Vec::new(),
+            frame_events: Vec::new(),
         }
     }
 }
 
-#[derive(Debug, PartialEq)]
+/// Reassembles the fragmented updates in a buffer whose capacity is kept from one update to the next,
+/// the unfragmented updates being borrowed from the received frame.
+#[derive(Debug, Default, PartialEq)]
 struct CompleteData {
-    fragmented_data: Option<Vec<u8>>,
+    fragmented_data: Vec<u8>,
+    /// Whether the First fragment of an update has been received, and not its Last one yet.
+    fragmented: bool,
 }
 
 impl CompleteData {
     fn new() -> Self {
-        Self { fragmented_data: None }
+        Self::default()
     }
 
-    fn process_data(&mut self, data: &[u8], fragmentation: Fragmentation) -> Option<Vec<u8>> {
+    fn process_data<'a>(&'a mut self, data: &'a [u8], fragmentation: Fragmentation) -> Option<&'a [u8]> {
         match fragmentation {
             Fragmentation::Single => {
                 self.check_data_is_empty();
 
-                Some(data.to_vec())
+                Some(data)
             }
             Fragmentation::First => {
                 self.check_data_is_empty();
 
-                self.fragmented_data = Some(data.to_vec());
+                self.fragmented_data.clear();
+                self.fragmented_data.extend_from_slice(data);
+                self.fragmented = true;
 
                 None
             }
@@ -160,37 +362,43 @@ impl CompleteData {
                 None
             }
             Fragmentation::Last => {
-                self.append_data(data);
-
-                let complete_data = self.fragmented_data.take().unwrap();
+                if !self.append_data(data) {
+                    return None;
+                }
+                self.fragmented = false;
 
-                Some(complete_data)
+                Some(self.fragmented_data.as_slice())
             }
         }
     }
 
     fn check_data_is_empty(&mut self) {
-        if self.fragmented_data.is_some() {
+        if self.fragmented {
             warn!("Skipping pending Fast-Path Update internal multiple elements data");
-            self.fragmented_data = None;
+            self.fragmented = false;
         }
     }
 
-    fn append_data(&mut self, data: &[u8]) {
-        if let Some(fragmented_data) = self.fragmented_data.as_mut() {
-            fragmented_data.extend_from_slice(data);
+    /// Returns `false` if no First fragment has been received, the data being skipped.
+    fn append_data(&mut self, data: &[u8]) -> bool {
+        if self.fragmented {
+            self.fragmented_data.extend_from_slice(data);
         } else {
             warn!("Got unexpected Next fragmentation PDU without prior First fragmentation PDU");
         }
+
+        self.fragmented
     }
 }
 
 struct Frame {
     transport: ShareDataHeaderTransport,
+    global_channel_id: ChannelId,
+    ack: Coalescer<u32>,
 }
 
 impl Frame {
-    fn new(initiator_id: u16, global_channel_id: u16) -> Self {
+    fn new(initiator_id: UserId, global_channel_id: ChannelId) -> Self {
         Self {
             transport: ShareDataHeaderTransport::new(ShareControlHeaderTransport::new(
                 SendDataContextTransport::new(
@@ -201,18 +409,33 @@ impl Frame {
                 initiator_id,
                 global_channel_id,
             )),
+            global_channel_id,
+            ack: Coalescer::new(None),
         }
     }
 
-    fn process_marker(&mut self, marker: &FrameMarkerPdu, mut output: impl io::Write) -> Result<(), RdpError> {
+    fn process_marker(
+        &mut self,
+        marker: &FrameMarkerPdu,
+        output: impl io::Write,
+        hooks: &mut PduHooks,
+    ) -> Result<(), RdpError> {
         match marker.frame_action {
             FrameAction::Begin => Ok(()),
-            FrameAction::End => self.transport.encode(
-                ShareDataPdu::FrameAcknowledge(FrameAcknowledgePdu {
-                    frame_id: marker.frame_id.unwrap_or(0),
-                }),
-                &mut output,
-            ),
+            FrameAction::End => match self.ack.push(marker.frame_id.unwrap_or(0)) {
+                Some(frame_id) => self.send_ack(frame_id, output, hooks),
+                None => Ok(()),
+            },
         }
     }
+
+    fn send_ack(&mut self, frame_id: u32, mut output: impl io::Write, hooks: &mut PduHooks) -> Result<(), RdpError> {
+        let frame_acknowledge = ShareDataPdu::FrameAcknowledge(FrameAcknowledgePdu { frame_id });
+        hooks.sent(
+            PduChannel::Static(self.global_channel_id),
+            frame_acknowledge.as_short_name(),
+        );
+
+        self.transport.encode(frame_acknowledge, &mut output)
+    }
 }
@@ -0,0 +1,89 @@
+#[cfg(test)]
+mod tests;
+
+use std::time::Instant;
+
+use ironrdp::Rectangle;
+
+use crate::image::{ImageSink, ImageUpdate, TileBuffer};
+use crate::{Codec, RdpError};
+
+/// Metadata of the graphics update it follows, see
+/// [`ActiveStageProcessor::set_frame_metadata_enabled`](crate::ActiveStageProcessor::set_frame_metadata_enabled).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameMetadata {
+    /// The ID of the frame the server has sent the update in, `None` if it is not delimited by frame markers.
+    pub frame_id: Option<u32>,
+    /// When the frame has been given to the processor.
+    pub received_at: Instant,
+    /// When the frame has been decoded into the image.
+    pub decoded_at: Instant,
+    /// The number of rectangles written to the image, the RemoteFX tiles being counted separately.
+    pub rectangle_count: usize,
+    /// The codecs the rectangles have been decoded with, in the order they were first used.
+    pub codecs: Vec<Codec>,
+}
+
+/// Counts the rectangles written to the image and the codecs they have been decoded with.
+pub(crate) struct FrameMetadataTracker<'a> {
+    image: &'a mut dyn ImageSink,
+    rectangle_count: usize,
+    codecs: Vec<Codec>,
+}
+
+impl<'a> FrameMetadataTracker<'a> {
+    pub(crate) fn new(image: &'a mut dyn ImageSink) -> Self {
+        Self {
+            image,
+            rectangle_count: 0,
+            codecs: Vec::new(),
+        }
+    }
+
+    /// Returns `None` if the image has not been updated.
+    pub(crate) fn finish(self, frame_id: Option<u32>, received_at: Instant) -> Option<FrameMetadata> {
+        if self.rectangle_count == 0 {
+            return None;
+        }
+
+        Some(FrameMetadata {
+            frame_id,
+            received_at,
+            decoded_at: Instant::now(),
+            rectangle_count: self.rectangle_count,
+            codecs: self.codecs,
+        })
+    }
+
+    fn track(&mut self, codec: Codec) {
+        self.rectangle_count += 1;
+        if !self.codecs.contains(&codec) {
+            self.codecs.push(codec);
+        }
+    }
+}
+
+impl ImageSink for FrameMetadataTracker<'_> {
+    fn update(&mut self, update: &ImageUpdate<'_>) -> Result<(), RdpError> {
+        self.image.update(update)?;
+        self.track(update.codec);
+
+        Ok(())
+    }
+
+    fn tile_buffer(&mut self, tile: &Rectangle) -> Option<TileBuffer<'_>> {
+        self.image.tile_buffer(tile)
+    }
+
+    fn tile_decoded(&mut self, tile: &Rectangle) -> Result<(), RdpError> {
+        self.image.tile_decoded(tile)?;
+        // only the RemoteFX tiles are decoded into the buffers lent by the image
+        self.track(Codec::RemoteFx);
+
+        Ok(())
+    }
+
+    fn resize(&mut self, width: u32, height: u32) -> Result<(), RdpError> {
+        self.image.resize(width, height)
+    }
+}
@@ -0,0 +1,41 @@
+use ironrdp::codecs::rfx::image_processing::PixelFormat;
+
+use super::*;
+
+fn update(codec: Codec) -> ImageUpdate<'static> {
+    ImageUpdate {
+        rectangle: Rectangle::from_u16(0, 0, 1, 1),
+        pixel_format: PixelFormat::RgbA32,
+        stride: 4,
+        data: &[0; 4],
+        codec,
+    }
+}
+
+#[test]
+fn rectangles_and_codecs_written_to_image_are_tracked() {
+    let mut updates = 0;
+    let mut image = |_: &ImageUpdate<'_>| updates += 1;
+    let mut tracker = FrameMetadataTracker::new(&mut image);
+
+    tracker.update(&update(Codec::H264)).unwrap();
+    tracker.tile_decoded(&Rectangle::from_u16(0, 0, 63, 63)).unwrap();
+    tracker.update(&update(Codec::H264)).unwrap();
+
+    let received_at = Instant::now();
+    let frame_metadata = tracker.finish(Some(7), received_at).unwrap();
+    assert_eq!(Some(7), frame_metadata.frame_id);
+    assert_eq!(3, frame_metadata.rectangle_count);
+    assert_eq!(vec![Codec::H264, Codec::RemoteFx], frame_metadata.codecs);
+    assert!(frame_metadata.decoded_at >= received_at);
+    assert_eq!(2, updates);
+}
+
+#[test]
+fn no_metadata_is_returned_if_image_is_not_updated() {
+    let mut image = |_: &ImageUpdate<'_>| ();
+    let mut tracker = FrameMetadataTracker::new(&mut image);
+    tracker.resize(800, 600).unwrap();
+
+    assert_eq!(None, tracker.finish(Some(7), Instant::now()));
+}
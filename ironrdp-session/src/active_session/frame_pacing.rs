@@ -0,0 +1,120 @@
+#[cfg(test)]
+mod tests;
+
+use std::time::Duration;
+
+use ironrdp::dvc::gfx::QueueDepth;
+
+/// Queue depth reported by the [`FrameAcknowledgePolicy::Adaptive`] policy when the frames are decoded
+/// much slower than the target, for the server not to stall the session altogether.
+const MAX_ADAPTIVE_QUEUE_DEPTH: u32 = 16;
+
+/// The queue depth reported to the server in the Frame Acknowledge PDUs of the Graphics Pipeline,
+/// which the server uses to limit the number of frames in flight.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FrameAcknowledgePolicy {
+    /// Suspends the acknowledgements, the server sending the frames without waiting for the client,
+    /// for the highest throughput.
+    Unlimited,
+    /// Reports a fixed number of frames waiting to be decoded, 0 telling the server the depth is unavailable.
+    QueueDepth(u32),
+    /// Reports the number of frames the client lags behind, the smoothed decode time of the frames divided by
+    /// the target, for the server to send the frames at the pace the client decodes them.
+    Adaptive { target_decode_time: Duration },
+}
+
+impl Default for FrameAcknowledgePolicy {
+    fn default() -> Self {
+        Self::Unlimited
+    }
+}
+
+/// The pacing of the frames of the Graphics Pipeline, trading the latency against the throughput.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct FramePacingConfig {
+    pub ack_policy: FrameAcknowledgePolicy,
+    /// Measures the decode time of the frames, as returned by
+    /// [`ActiveStageProcessor::graphics_decode_timing`](crate::ActiveStageProcessor::graphics_decode_timing).
+    /// Always measured by the [`FrameAcknowledgePolicy::Adaptive`] policy.
+    ///
+    /// The time is taken with [`Instant::now`](std::time::Instant::now), which is not available
+    /// on `wasm32-unknown-unknown`.
+    pub decode_timing: bool,
+}
+
+/// The time taken to decode the frames of the Graphics Pipeline, from the decompression of their PDUs
+/// to the updates of the image sink.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct DecodeTiming {
+    pub frames: u64,
+    pub last: Duration,
+    /// Exponentially weighted moving average of the decode times, each frame weighing 1/8.
+    pub average: Duration,
+    pub max: Duration,
+}
+
+impl DecodeTiming {
+    fn record_frame(&mut self, decode_time: Duration) {
+        self.average = if self.frames == 0 {
+            decode_time
+        } else {
+            self.average - self.average / 8 + decode_time / 8
+        };
+        self.frames += 1;
+        self.last = decode_time;
+        self.max = self.max.max(decode_time);
+    }
+}
+
+/// Measures the decode time of the frames and derives the queue depth of their acknowledgements from it.
+#[cfg_attr(not(feature = "zgfx"), allow(dead_code))]
+pub(crate) struct FramePacer {
+    config: FramePacingConfig,
+    frame_decode_time: Duration,
+    timing: DecodeTiming,
+}
+
+#[cfg_attr(not(feature = "zgfx"), allow(dead_code))]
+impl FramePacer {
+    pub(crate) fn new(config: FramePacingConfig) -> Self {
+        Self {
+            config,
+            frame_decode_time: Duration::ZERO,
+            timing: DecodeTiming::default(),
+        }
+    }
+
+    /// Returns `true` if the decode time is to be measured, the clock being read only then.
+    pub(crate) fn is_timing_enabled(&self) -> bool {
+        self.config.decode_timing || matches!(self.config.ack_policy, FrameAcknowledgePolicy::Adaptive { .. })
+    }
+
+    /// Adds the time spent decoding a part of the current frame.
+    pub(crate) fn record(&mut self, decode_time: Duration) {
+        self.frame_decode_time += decode_time;
+    }
+
+    /// Ends the current frame, returning the queue depth to acknowledge it with.
+    pub(crate) fn end_frame(&mut self) -> QueueDepth {
+        if self.is_timing_enabled() {
+            let decode_time = std::mem::take(&mut self.frame_decode_time);
+            self.timing.record_frame(decode_time);
+        }
+
+        match self.config.ack_policy {
+            FrameAcknowledgePolicy::Unlimited => QueueDepth::Suspend,
+            FrameAcknowledgePolicy::QueueDepth(queue_depth) => QueueDepth::from_u32(queue_depth),
+            FrameAcknowledgePolicy::Adaptive { target_decode_time } => {
+                let target = target_decode_time.as_nanos().max(1);
+                let lag = (self.timing.average.as_nanos() + target - 1) / target;
+                let queue_depth = u32::try_from(lag).unwrap_or(u32::MAX);
+
+                QueueDepth::AvailableBytes(queue_depth.clamp(1, MAX_ADAPTIVE_QUEUE_DEPTH))
+            }
+        }
+    }
+
+    pub(crate) fn timing(&self) -> Option<DecodeTiming> {
+        self.is_timing_enabled().then_some(self.timing)
+    }
+}
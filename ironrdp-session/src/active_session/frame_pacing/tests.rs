@@ -0,0 +1,67 @@
+use super::*;
+
+const TARGET_DECODE_TIME: Duration = Duration::from_millis(10);
+
+fn adaptive_pacer() -> FramePacer {
+    FramePacer::new(FramePacingConfig {
+        ack_policy: FrameAcknowledgePolicy::Adaptive {
+            target_decode_time: TARGET_DECODE_TIME,
+        },
+        decode_timing: false,
+    })
+}
+
+#[test]
+fn acknowledgements_are_suspended_by_default() {
+    let mut pacer = FramePacer::new(FramePacingConfig::default());
+
+    assert!(!pacer.is_timing_enabled());
+    assert_eq!(QueueDepth::Suspend, pacer.end_frame());
+    assert_eq!(None, pacer.timing());
+}
+
+#[test]
+fn fixed_queue_depth_is_reported() {
+    let mut pacer = FramePacer::new(FramePacingConfig {
+        ack_policy: FrameAcknowledgePolicy::QueueDepth(3),
+        decode_timing: false,
+    });
+
+    assert_eq!(QueueDepth::AvailableBytes(3), pacer.end_frame());
+}
+
+#[test]
+fn adaptive_queue_depth_follows_decode_time() {
+    let mut pacer = adaptive_pacer();
+
+    pacer.record(Duration::from_millis(4));
+    assert_eq!(QueueDepth::AvailableBytes(1), pacer.end_frame());
+
+    for _ in 0..32 {
+        pacer.record(Duration::from_millis(25));
+        pacer.end_frame();
+    }
+    pacer.record(Duration::from_millis(25));
+    assert_eq!(QueueDepth::AvailableBytes(3), pacer.end_frame());
+}
+
+#[test]
+fn decode_timing_accumulates_parts_of_frame() {
+    let mut pacer = adaptive_pacer();
+
+    pacer.record(Duration::from_millis(8));
+    pacer.end_frame();
+    pacer.record(Duration::from_millis(12));
+    pacer.record(Duration::from_millis(4));
+    pacer.end_frame();
+
+    assert_eq!(
+        Some(DecodeTiming {
+            frames: 2,
+            last: Duration::from_millis(16),
+            average: Duration::from_millis(9),
+            max: Duration::from_millis(16),
+        }),
+        pacer.timing()
+    );
+}
@@ -0,0 +1,527 @@
+#[cfg(test)]
+mod tests;
+
+use std::collections::HashSet;
+use std::time::Duration;
+
+use bitflags::bitflags;
+use ironrdp::input::fast_path::{FastPathInputEvent, KeyboardFlags, SynchronizeFlags};
+use ironrdp::input::mouse::{ButtonEvents, MouseButton, MovementEvents, WheelEvents, WheelOrientation};
+use ironrdp::input::MousePdu;
+
+/// Scan code of a key in the PC/AT Set 1, `extended` standing for the `E0` prefix.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Scancode {
+    pub code: u8,
+    pub extended: bool,
+}
+
+impl Scancode {
+    pub const fn new(code: u8) -> Self {
+        Self { code, extended: false }
+    }
+
+    pub const fn extended(code: u8) -> Self {
+        Self { code, extended: true }
+    }
+
+    fn keyboard_event(self, flags: KeyboardFlags) -> FastPathInputEvent {
+        let flags = if self.extended {
+            flags | KeyboardFlags::FASTPATH_INPUT_KBDFLAGS_EXTENDED
+        } else {
+            flags
+        };
+
+        FastPathInputEvent::KeyboardEvent(flags, self.code)
+    }
+}
+
+bitflags! {
+    /// Modifiers held down when a key event has been generated, as reported by the windowing system.
+    pub struct Modifiers: u8 {
+        const SHIFT = 0x01;
+        const CONTROL = 0x02;
+        const ALT = 0x04;
+        const META = 0x08;
+    }
+}
+
+const MODIFIER_SCANCODES: [(Scancode, Modifiers); 8] = [
+    (Scancode::new(0x2a), Modifiers::SHIFT),
+    (Scancode::new(0x36), Modifiers::SHIFT),
+    (Scancode::new(0x1d), Modifiers::CONTROL),
+    (Scancode::extended(0x1d), Modifiers::CONTROL),
+    (Scancode::new(0x38), Modifiers::ALT),
+    (Scancode::extended(0x38), Modifiers::ALT),
+    (Scancode::extended(0x5b), Modifiers::META),
+    (Scancode::extended(0x5c), Modifiers::META),
+];
+
+const TAB_SCANCODE: Scancode = Scancode::new(0x0f);
+
+/// The input events sent when the window regains the focus, in addition to the Synchronize event,
+/// see [`InputEventSender::focus_in`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct FocusInConfig {
+    /// Releases the Tab key after the Synchronize event, as the Microsoft client does, for the server not to
+    /// keep the task switcher open when the focus has been regained with Alt+Tab.
+    pub release_tab: bool,
+    /// Presses the modifiers held down locally, e.g. Ctrl held down while clicking back into the window,
+    /// the presses having happened while the window was not focused.
+    pub press_held_modifiers: bool,
+}
+
+impl Default for FocusInConfig {
+    fn default() -> Self {
+        Self {
+            release_tab: true,
+            press_held_modifiers: true,
+        }
+    }
+}
+
+/// A key pressed or released locally, `K` identifying the key in the terms of the windowing system.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyEvent<K> {
+    pub key: K,
+    pub pressed: bool,
+    /// The key press has been generated by the local key repeat.
+    pub repeat: bool,
+    pub modifiers: Modifiers,
+    /// The text produced by the key with the local layout, sent as Unicode if the key has no scancode.
+    pub text: Option<char>,
+}
+
+/// Maps the keys of the windowing system to scancodes. Layout-aware mappers may return `None`
+/// for the keys producing characters missing from the layout of the remote session,
+/// which are then sent as Unicode.
+pub trait ScancodeMapper {
+    type Key;
+
+    fn scancode(&self, key: &Self::Key) -> Option<Scancode>;
+}
+
+/// Maps the physical key codes of the W3C UI Events specification (`KeyboardEvent.code`),
+/// which are also the names of the winit key codes, to the scancodes of the same key positions.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct DomCodeMapper;
+
+impl ScancodeMapper for DomCodeMapper {
+    type Key = String;
+
+    fn scancode(&self, key: &String) -> Option<Scancode> {
+        dom_code_scancode(key)
+    }
+}
+
+/// Converts the key and mouse events of the windowing system into Fast-Path input events,
+/// keeping track of the pressed keys and buttons so that none stays pressed on the server
+/// when a release has been missed, e.g. while the window was not focused.
+pub struct InputEventSender<M> {
+    mapper: M,
+    pressed: HashSet<Scancode>,
+    pressed_buttons: HashSet<MouseButton>,
+    mouse_position: (u16, u16),
+    focus_in_config: FocusInConfig,
+}
+
+impl<M: ScancodeMapper> InputEventSender<M> {
+    pub fn new(mapper: M) -> Self {
+        Self {
+            mapper,
+            pressed: HashSet::new(),
+            pressed_buttons: HashSet::new(),
+            mouse_position: (0, 0),
+            focus_in_config: FocusInConfig::default(),
+        }
+    }
+
+    pub fn with_focus_in_config(mut self, focus_in_config: FocusInConfig) -> Self {
+        self.focus_in_config = focus_in_config;
+        self
+    }
+
+    pub fn is_pressed(&self, scancode: Scancode) -> bool {
+        self.pressed.contains(&scancode)
+    }
+
+    /// Returns the input events to send for the key event. The repeated presses of a key are sent
+    /// as presses without release, as the server does not repeat the keys on its own. The keys without
+    /// scancode are sent as a press and a release of their text, and their releases are ignored.
+    pub fn key_event(&mut self, event: &KeyEvent<M::Key>) -> Vec<FastPathInputEvent> {
+        let scancode = self.mapper.scancode(&event.key);
+        let mut events = self.release_missed_modifiers(event.modifiers, scancode);
+
+        match scancode {
+            Some(scancode) if event.pressed => {
+                // pressed while the window was not focused, the release is likely to be missed as well
+                if event.repeat && !self.pressed.contains(&scancode) {
+                    return events;
+                }
+                self.pressed.insert(scancode);
+                events.push(scancode.keyboard_event(KeyboardFlags::empty()));
+            }
+            Some(scancode) => {
+                if self.pressed.remove(&scancode) {
+                    events.push(scancode.keyboard_event(KeyboardFlags::FASTPATH_INPUT_KBDFLAGS_RELEASE));
+                }
+            }
+            None => match event.text {
+                Some(text) if event.pressed => {
+                    let mut code_units = [0; 2];
+                    for &code_unit in text.encode_utf16(&mut code_units).iter() {
+                        events.push(FastPathInputEvent::UnicodeKeyboardEvent(
+                            KeyboardFlags::empty(),
+                            code_unit,
+                        ));
+                        events.push(FastPathInputEvent::UnicodeKeyboardEvent(
+                            KeyboardFlags::FASTPATH_INPUT_KBDFLAGS_RELEASE,
+                            code_unit,
+                        ));
+                    }
+                }
+                _ => debug!("Ignored key event without scancode nor text"),
+            },
+        }
+
+        events
+    }
+
+    pub fn mouse_move(&mut self, x_position: u16, y_position: u16) -> FastPathInputEvent {
+        self.mouse_position = (x_position, y_position);
+
+        FastPathInputEvent::MouseEvent(MousePdu {
+            wheel_events: WheelEvents::empty(),
+            movement_events: MovementEvents::MOVE,
+            button_events: ButtonEvents::empty(),
+            number_of_wheel_rotations: 0,
+            x_position,
+            y_position,
+        })
+    }
+
+    /// Returns the event of the button pressed or released at the last position of the mouse,
+    /// or `None` for the release of a button which has not been pressed in the window.
+    pub fn mouse_button(&mut self, button: MouseButton, pressed: bool) -> Option<FastPathInputEvent> {
+        let changed = if pressed {
+            self.pressed_buttons.insert(button)
+        } else {
+            self.pressed_buttons.remove(&button)
+        };
+
+        if changed || pressed {
+            let (x_position, y_position) = self.mouse_position;
+            Some(FastPathInputEvent::mouse_button(
+                button, pressed, x_position, y_position,
+            ))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the events of a wheel rotation at the last position of the mouse, in units of
+    /// [`WHEEL_DELTA`](ironrdp::input::mouse::WHEEL_DELTA) for a detent of a standard wheel,
+    /// positive scrolling up or right.
+    pub fn wheel_rotation(&mut self, wheel: WheelOrientation, rotation_units: i32) -> Vec<FastPathInputEvent> {
+        let (x_position, y_position) = self.mouse_position;
+
+        MousePdu::wheel_rotation(wheel, rotation_units, x_position, y_position)
+            .into_iter()
+            .map(FastPathInputEvent::MouseEvent)
+            .collect()
+    }
+
+    /// Releases all the pressed keys and mouse buttons, to be called when the window loses the focus.
+    pub fn release_all(&mut self) -> Vec<FastPathInputEvent> {
+        let (x_position, y_position) = self.mouse_position;
+        let button_releases = self
+            .pressed_buttons
+            .drain()
+            .map(|button| FastPathInputEvent::mouse_button(button, false, x_position, y_position));
+
+        self.pressed
+            .drain()
+            .map(|scancode| scancode.keyboard_event(KeyboardFlags::FASTPATH_INPUT_KBDFLAGS_RELEASE))
+            .chain(button_releases)
+            .collect()
+    }
+
+    /// Releases all the pressed keys and mouse buttons, and synchronizes the toggle keys with their local state,
+    /// to be called when the window gains the focus.
+    pub fn synchronize(&mut self, toggle_keys: SynchronizeFlags) -> Vec<FastPathInputEvent> {
+        let mut events = self.release_all();
+        events.push(FastPathInputEvent::SyncEvent(toggle_keys));
+
+        events
+    }
+
+    /// Releases all the pressed keys and mouse buttons, to be called when the window loses the focus,
+    /// e.g. with Alt+Tab, whose releases happen outside of the window.
+    pub fn focus_out(&mut self) -> Vec<FastPathInputEvent> {
+        self.release_all()
+    }
+
+    /// Returns the sequence recommended when the window regains the focus, for no key to stay stuck on the server:
+    /// the pressed keys are released, the toggle keys are synchronized with their local state,
+    /// then the modifiers held down locally are refreshed as configured by the [`FocusInConfig`].
+    pub fn focus_in(&mut self, toggle_keys: SynchronizeFlags, modifiers: Modifiers) -> Vec<FastPathInputEvent> {
+        let mut events = self.synchronize(toggle_keys);
+
+        if self.focus_in_config.release_tab {
+            events.push(TAB_SCANCODE.keyboard_event(KeyboardFlags::FASTPATH_INPUT_KBDFLAGS_RELEASE));
+        }
+
+        if self.focus_in_config.press_held_modifiers {
+            let mut pressed_modifiers = Modifiers::empty();
+            for (scancode, modifier) in MODIFIER_SCANCODES.iter() {
+                // the left key of each modifier, the side of the key held down being unknown
+                if modifiers.contains(*modifier) && !pressed_modifiers.contains(*modifier) {
+                    pressed_modifiers.insert(*modifier);
+                    self.pressed.insert(*scancode);
+                    events.push(scancode.keyboard_event(KeyboardFlags::empty()));
+                }
+            }
+        }
+
+        events
+    }
+
+    fn release_missed_modifiers(
+        &mut self,
+        modifiers: Modifiers,
+        scancode: Option<Scancode>,
+    ) -> Vec<FastPathInputEvent> {
+        MODIFIER_SCANCODES
+            .iter()
+            .filter(|(modifier_scancode, modifier)| {
+                Some(*modifier_scancode) != scancode && !modifiers.contains(*modifier)
+            })
+            .filter(|(modifier_scancode, _)| self.pressed.remove(modifier_scancode))
+            .map(|(modifier_scancode, _)| {
+                modifier_scancode.keyboard_event(KeyboardFlags::FASTPATH_INPUT_KBDFLAGS_RELEASE)
+            })
+            .collect()
+    }
+}
+
+/// Accumulates the input events of the windowing system until they are sent, e.g. once per frame rendered,
+/// for them to be packed into a single Fast-Path Input PDU by
+/// [`ActiveStageProcessor::encode_fast_path_input`](crate::ActiveStageProcessor::encode_fast_path_input)
+/// instead of a PDU being sent for each event of a high-frequency mouse.
+///
+/// The consecutive moves of the mouse are coalesced into the last one, the moves preceding the other events
+/// being kept for the clicks and wheel rotations to happen at their position.
+#[derive(Debug, Clone, Default)]
+pub struct InputBatch {
+    events: Vec<FastPathInputEvent>,
+}
+
+impl InputBatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, event: FastPathInputEvent) {
+        if let (Some(FastPathInputEvent::MouseEvent(last)), FastPathInputEvent::MouseEvent(pdu)) =
+            (self.events.last_mut(), &event)
+        {
+            if super::is_mouse_move(last) && super::is_mouse_move(pdu) {
+                *last = pdu.clone();
+                return;
+            }
+        }
+
+        self.events.push(event);
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Returns the accumulated events, the batch being empty afterwards.
+    pub fn take(&mut self) -> Vec<FastPathInputEvent> {
+        std::mem::take(&mut self.events)
+    }
+}
+
+impl Extend<FastPathInputEvent> for InputBatch {
+    fn extend<T: IntoIterator<Item = FastPathInputEvent>>(&mut self, events: T) {
+        for event in events {
+            self.push(event);
+        }
+    }
+}
+
+/// Reduces the pointer movement sent to the server, for the assistive technologies moving the pointer
+/// in steps and for the links too slow for every move, see
+/// [`ActiveStageProcessor::set_low_rate_input`](crate::ActiveStageProcessor::set_low_rate_input).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct LowRateInputConfig {
+    /// The size in pixels of the grid the positions of the moves are rounded down to,
+    /// a move being only sent once the pointer enters another cell.
+    pub step: u16,
+    /// The minimum interval between two moves sent, the moves in between replacing each other.
+    pub min_interval: Duration,
+}
+
+/// Rounds the positions of the pointer moves down to a grid, dropping the moves which stay in the cell
+/// of the last position sent.
+pub(crate) struct PointerQuantizer {
+    step: u16,
+    last_position: Option<(u16, u16)>,
+}
+
+impl PointerQuantizer {
+    pub(crate) fn new(step: u16) -> Self {
+        Self {
+            step: step.max(1),
+            last_position: None,
+        }
+    }
+
+    /// Returns the move to the rounded position, or `None` if the pointer has not left the cell.
+    pub(crate) fn quantize_move(&mut self, pdu: MousePdu) -> Option<MousePdu> {
+        let position = self.cell(pdu.x_position, pdu.y_position);
+        if self.last_position == Some(position) {
+            return None;
+        }
+        self.last_position = Some(position);
+
+        Some(MousePdu {
+            x_position: position.0,
+            y_position: position.1,
+            ..pdu
+        })
+    }
+
+    /// Records the position of the clicks and wheel rotations, which are sent as they are.
+    pub(crate) fn track(&mut self, pdu: &MousePdu) {
+        self.last_position = Some(self.cell(pdu.x_position, pdu.y_position));
+    }
+
+    fn cell(&self, x_position: u16, y_position: u16) -> (u16, u16) {
+        (x_position - x_position % self.step, y_position - y_position % self.step)
+    }
+}
+
+fn dom_code_scancode(code: &str) -> Option<Scancode> {
+    let scancode = match code {
+        "Escape" => Scancode::new(0x01),
+        "Digit1" => Scancode::new(0x02),
+        "Digit2" => Scancode::new(0x03),
+        "Digit3" => Scancode::new(0x04),
+        "Digit4" => Scancode::new(0x05),
+        "Digit5" => Scancode::new(0x06),
+        "Digit6" => Scancode::new(0x07),
+        "Digit7" => Scancode::new(0x08),
+        "Digit8" => Scancode::new(0x09),
+        "Digit9" => Scancode::new(0x0a),
+        "Digit0" => Scancode::new(0x0b),
+        "Minus" => Scancode::new(0x0c),
+        "Equal" => Scancode::new(0x0d),
+        "Backspace" => Scancode::new(0x0e),
+        "Tab" => Scancode::new(0x0f),
+        "KeyQ" => Scancode::new(0x10),
+        "KeyW" => Scancode::new(0x11),
+        "KeyE" => Scancode::new(0x12),
+        "KeyR" => Scancode::new(0x13),
+        "KeyT" => Scancode::new(0x14),
+        "KeyY" => Scancode::new(0x15),
+        "KeyU" => Scancode::new(0x16),
+        "KeyI" => Scancode::new(0x17),
+        "KeyO" => Scancode::new(0x18),
+        "KeyP" => Scancode::new(0x19),
+        "BracketLeft" => Scancode::new(0x1a),
+        "BracketRight" => Scancode::new(0x1b),
+        "Enter" => Scancode::new(0x1c),
+        "ControlLeft" => Scancode::new(0x1d),
+        "KeyA" => Scancode::new(0x1e),
+        "KeyS" => Scancode::new(0x1f),
+        "KeyD" => Scancode::new(0x20),
+        "KeyF" => Scancode::new(0x21),
+        "KeyG" => Scancode::new(0x22),
+        "KeyH" => Scancode::new(0x23),
+        "KeyJ" => Scancode::new(0x24),
+        "KeyK" => Scancode::new(0x25),
+        "KeyL" => Scancode::new(0x26),
+        "Semicolon" => Scancode::new(0x27),
+        "Quote" => Scancode::new(0x28),
+        "Backquote" => Scancode::new(0x29),
+        "ShiftLeft" => Scancode::new(0x2a),
+        "Backslash" => Scancode::new(0x2b),
+        "KeyZ" => Scancode::new(0x2c),
+        "KeyX" => Scancode::new(0x2d),
+        "KeyC" => Scancode::new(0x2e),
+        "KeyV" => Scancode::new(0x2f),
+        "KeyB" => Scancode::new(0x30),
+        "KeyN" => Scancode::new(0x31),
+        "KeyM" => Scancode::new(0x32),
+        "Comma" => Scancode::new(0x33),
+        "Period" => Scancode::new(0x34),
+        "Slash" => Scancode::new(0x35),
+        "ShiftRight" => Scancode::new(0x36),
+        "NumpadMultiply" => Scancode::new(0x37),
+        "AltLeft" => Scancode::new(0x38),
+        "Space" => Scancode::new(0x39),
+        "CapsLock" => Scancode::new(0x3a),
+        "F1" => Scancode::new(0x3b),
+        "F2" => Scancode::new(0x3c),
+        "F3" => Scancode::new(0x3d),
+        "F4" => Scancode::new(0x3e),
+        "F5" => Scancode::new(0x3f),
+        "F6" => Scancode::new(0x40),
+        "F7" => Scancode::new(0x41),
+        "F8" => Scancode::new(0x42),
+        "F9" => Scancode::new(0x43),
+        "F10" => Scancode::new(0x44),
+        "NumLock" => Scancode::new(0x45),
+        "ScrollLock" => Scancode::new(0x46),
+        "Numpad7" => Scancode::new(0x47),
+        "Numpad8" => Scancode::new(0x48),
+        "Numpad9" => Scancode::new(0x49),
+        "NumpadSubtract" => Scancode::new(0x4a),
+        "Numpad4" => Scancode::new(0x4b),
+        "Numpad5" => Scancode::new(0x4c),
+        "Numpad6" => Scancode::new(0x4d),
+        "NumpadAdd" => Scancode::new(0x4e),
+        "Numpad1" => Scancode::new(0x4f),
+        "Numpad2" => Scancode::new(0x50),
+        "Numpad3" => Scancode::new(0x51),
+        "Numpad0" => Scancode::new(0x52),
+        "NumpadDecimal" => Scancode::new(0x53),
+        "IntlBackslash" => Scancode::new(0x56),
+        "F11" => Scancode::new(0x57),
+        "F12" => Scancode::new(0x58),
+        "KanaMode" => Scancode::new(0x70),
+        "IntlRo" => Scancode::new(0x73),
+        "Convert" => Scancode::new(0x79),
+        "NonConvert" => Scancode::new(0x7b),
+        "IntlYen" => Scancode::new(0x7d),
+        "NumpadEnter" => Scancode::extended(0x1c),
+        "ControlRight" => Scancode::extended(0x1d),
+        "NumpadDivide" => Scancode::extended(0x35),
+        "PrintScreen" => Scancode::extended(0x37),
+        "AltRight" => Scancode::extended(0x38),
+        "Home" => Scancode::extended(0x47),
+        "ArrowUp" => Scancode::extended(0x48),
+        "PageUp" => Scancode::extended(0x49),
+        "ArrowLeft" => Scancode::extended(0x4b),
+        "ArrowRight" => Scancode::extended(0x4d),
+        "End" => Scancode::extended(0x4f),
+        "ArrowDown" => Scancode::extended(0x50),
+        "PageDown" => Scancode::extended(0x51),
+        "Insert" => Scancode::extended(0x52),
+        "Delete" => Scancode::extended(0x53),
+        // winit names the logo keys after the Super modifier, older browsers after the OS
+        "MetaLeft" | "SuperLeft" | "OSLeft" => Scancode::extended(0x5b),
+        "MetaRight" | "SuperRight" | "OSRight" => Scancode::extended(0x5c),
+        "ContextMenu" => Scancode::extended(0x5d),
+        _ => return None,
+    };
+
+    Some(scancode)
+}
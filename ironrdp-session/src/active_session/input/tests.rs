@@ -0,0 +1,260 @@
+use super::*;
+
+fn key_event(code: &str, pressed: bool, modifiers: Modifiers) -> KeyEvent<String> {
+    KeyEvent {
+        key: code.to_string(),
+        pressed,
+        repeat: false,
+        modifiers,
+        text: None,
+    }
+}
+
+#[test]
+fn extended_keys_are_sent_with_extended_flag() {
+    let mut sender = InputEventSender::new(DomCodeMapper);
+
+    assert_eq!(
+        vec![FastPathInputEvent::KeyboardEvent(
+            KeyboardFlags::FASTPATH_INPUT_KBDFLAGS_EXTENDED,
+            0x4b
+        )],
+        sender.key_event(&key_event("ArrowLeft", true, Modifiers::empty()))
+    );
+    assert_eq!(
+        vec![FastPathInputEvent::KeyboardEvent(
+            KeyboardFlags::FASTPATH_INPUT_KBDFLAGS_EXTENDED | KeyboardFlags::FASTPATH_INPUT_KBDFLAGS_RELEASE,
+            0x4b
+        )],
+        sender.key_event(&key_event("ArrowLeft", false, Modifiers::empty()))
+    );
+}
+
+#[test]
+fn repeated_key_is_sent_as_presses_without_release() {
+    let mut sender = InputEventSender::new(DomCodeMapper);
+    let press = FastPathInputEvent::KeyboardEvent(KeyboardFlags::empty(), 0x1e);
+
+    assert_eq!(
+        vec![press.clone()],
+        sender.key_event(&key_event("KeyA", true, Modifiers::empty()))
+    );
+    let repeat = KeyEvent {
+        repeat: true,
+        ..key_event("KeyA", true, Modifiers::empty())
+    };
+    assert_eq!(vec![press.clone()], sender.key_event(&repeat));
+    assert_eq!(vec![press], sender.key_event(&repeat));
+    assert!(sender.is_pressed(Scancode::new(0x1e)));
+}
+
+#[test]
+fn repeat_of_key_pressed_while_unfocused_is_ignored() {
+    let mut sender = InputEventSender::new(DomCodeMapper);
+    let repeat = KeyEvent {
+        repeat: true,
+        ..key_event("Enter", true, Modifiers::empty())
+    };
+
+    assert!(sender.key_event(&repeat).is_empty());
+    assert!(sender
+        .key_event(&key_event("Enter", false, Modifiers::empty()))
+        .is_empty());
+}
+
+#[test]
+fn modifier_released_while_unfocused_is_released_on_next_key_event() {
+    let mut sender = InputEventSender::new(DomCodeMapper);
+
+    sender.key_event(&key_event("ShiftLeft", true, Modifiers::empty()));
+    sender.key_event(&key_event("ControlRight", true, Modifiers::SHIFT));
+
+    assert_eq!(
+        vec![
+            FastPathInputEvent::KeyboardEvent(KeyboardFlags::FASTPATH_INPUT_KBDFLAGS_RELEASE, 0x2a),
+            FastPathInputEvent::KeyboardEvent(KeyboardFlags::empty(), 0x2c),
+        ],
+        sender.key_event(&key_event("KeyZ", true, Modifiers::CONTROL))
+    );
+    assert!(sender.is_pressed(Scancode::extended(0x1d)));
+}
+
+#[test]
+fn key_without_scancode_is_sent_as_unicode() {
+    let mut sender = InputEventSender::new(DomCodeMapper);
+    let event = KeyEvent {
+        text: Some('€'),
+        ..key_event("Unidentified", true, Modifiers::empty())
+    };
+
+    assert_eq!(
+        vec![
+            FastPathInputEvent::UnicodeKeyboardEvent(KeyboardFlags::empty(), 0x20ac),
+            FastPathInputEvent::UnicodeKeyboardEvent(KeyboardFlags::FASTPATH_INPUT_KBDFLAGS_RELEASE, 0x20ac),
+        ],
+        sender.key_event(&event)
+    );
+    assert!(sender
+        .key_event(&KeyEvent {
+            pressed: false,
+            ..event
+        })
+        .is_empty());
+}
+
+#[test]
+fn synchronize_releases_pressed_keys_before_sync_event() {
+    let mut sender = InputEventSender::new(DomCodeMapper);
+    sender.key_event(&key_event("KeyQ", true, Modifiers::empty()));
+
+    assert_eq!(
+        vec![
+            FastPathInputEvent::KeyboardEvent(KeyboardFlags::FASTPATH_INPUT_KBDFLAGS_RELEASE, 0x10),
+            FastPathInputEvent::SyncEvent(SynchronizeFlags::FASTPATH_INPUT_SYNC_NUM_LOCK),
+        ],
+        sender.synchronize(SynchronizeFlags::FASTPATH_INPUT_SYNC_NUM_LOCK)
+    );
+    assert!(!sender.is_pressed(Scancode::new(0x10)));
+}
+
+#[test]
+fn focus_in_synchronizes_then_releases_tab_and_presses_held_modifiers() {
+    let mut sender = InputEventSender::new(DomCodeMapper);
+    sender.key_event(&key_event("AltLeft", true, Modifiers::ALT));
+
+    assert_eq!(
+        vec![
+            FastPathInputEvent::KeyboardEvent(KeyboardFlags::FASTPATH_INPUT_KBDFLAGS_RELEASE, 0x38),
+            FastPathInputEvent::SyncEvent(SynchronizeFlags::FASTPATH_INPUT_SYNC_CAPS_LOCK),
+            FastPathInputEvent::KeyboardEvent(KeyboardFlags::FASTPATH_INPUT_KBDFLAGS_RELEASE, 0x0f),
+            FastPathInputEvent::KeyboardEvent(KeyboardFlags::empty(), 0x2a),
+        ],
+        sender.focus_in(SynchronizeFlags::FASTPATH_INPUT_SYNC_CAPS_LOCK, Modifiers::SHIFT)
+    );
+    assert!(sender.is_pressed(Scancode::new(0x2a)));
+    assert!(!sender.is_pressed(Scancode::new(0x38)));
+
+    // the modifier released while the window was not focused again
+    assert_eq!(
+        vec![
+            FastPathInputEvent::KeyboardEvent(KeyboardFlags::FASTPATH_INPUT_KBDFLAGS_RELEASE, 0x2a),
+            FastPathInputEvent::KeyboardEvent(KeyboardFlags::empty(), 0x1e),
+        ],
+        sender.key_event(&key_event("KeyA", true, Modifiers::empty()))
+    );
+}
+
+#[test]
+fn focus_in_only_synchronizes_when_refresh_is_disabled() {
+    let mut sender = InputEventSender::new(DomCodeMapper).with_focus_in_config(FocusInConfig {
+        release_tab: false,
+        press_held_modifiers: false,
+    });
+
+    assert_eq!(
+        vec![FastPathInputEvent::SyncEvent(SynchronizeFlags::empty())],
+        sender.focus_in(SynchronizeFlags::empty(), Modifiers::CONTROL)
+    );
+}
+
+#[test]
+fn mouse_buttons_are_sent_at_last_mouse_position() {
+    let mut sender = InputEventSender::new(DomCodeMapper);
+    sender.mouse_move(16, 32);
+
+    assert_eq!(
+        Some(FastPathInputEvent::mouse_button(MouseButton::X1, true, 16, 32)),
+        sender.mouse_button(MouseButton::X1, true)
+    );
+    assert_eq!(
+        vec![FastPathInputEvent::MouseEvent(MousePdu {
+            wheel_events: WheelEvents::HORIZONTAL_WHEEL | WheelEvents::WHEEL_NEGATIVE,
+            movement_events: MovementEvents::empty(),
+            button_events: ButtonEvents::empty(),
+            number_of_wheel_rotations: -120,
+            x_position: 16,
+            y_position: 32,
+        })],
+        sender.wheel_rotation(WheelOrientation::Horizontal, -120)
+    );
+}
+
+#[test]
+fn release_all_releases_pressed_mouse_buttons() {
+    let mut sender = InputEventSender::new(DomCodeMapper);
+    sender.mouse_button(MouseButton::Left, true);
+
+    assert_eq!(
+        vec![FastPathInputEvent::mouse_button(MouseButton::Left, false, 0, 0)],
+        sender.release_all()
+    );
+    assert_eq!(None, sender.mouse_button(MouseButton::Left, false));
+}
+
+fn mouse_move(x_position: u16, y_position: u16) -> MousePdu {
+    MousePdu {
+        wheel_events: WheelEvents::empty(),
+        movement_events: MovementEvents::MOVE,
+        button_events: ButtonEvents::empty(),
+        number_of_wheel_rotations: 0,
+        x_position,
+        y_position,
+    }
+}
+
+#[test]
+fn quantized_moves_are_rounded_down_and_sent_once_per_cell() {
+    let mut quantizer = PointerQuantizer::new(8);
+
+    assert_eq!(Some(mouse_move(8, 16)), quantizer.quantize_move(mouse_move(13, 17)));
+    assert_eq!(None, quantizer.quantize_move(mouse_move(15, 23)));
+    assert_eq!(Some(mouse_move(16, 16)), quantizer.quantize_move(mouse_move(16, 23)));
+    assert_eq!(Some(mouse_move(8, 16)), quantizer.quantize_move(mouse_move(9, 20)));
+}
+
+#[test]
+fn quantized_move_within_cell_of_click_is_dropped() {
+    let mut quantizer = PointerQuantizer::new(10);
+    quantizer.quantize_move(mouse_move(0, 0));
+
+    let FastPathInputEvent::MouseEvent(click) = FastPathInputEvent::mouse_button(MouseButton::Left, true, 42, 57)
+    else {
+        unreachable!();
+    };
+    quantizer.track(&click);
+
+    assert_eq!(None, quantizer.quantize_move(mouse_move(49, 50)));
+    assert_eq!(Some(mouse_move(50, 50)), quantizer.quantize_move(mouse_move(50, 50)));
+}
+
+#[test]
+fn zero_quantization_step_sends_every_position() {
+    let mut quantizer = PointerQuantizer::new(0);
+
+    assert_eq!(Some(mouse_move(3, 5)), quantizer.quantize_move(mouse_move(3, 5)));
+    assert_eq!(None, quantizer.quantize_move(mouse_move(3, 5)));
+    assert_eq!(Some(mouse_move(4, 5)), quantizer.quantize_move(mouse_move(4, 5)));
+}
+
+#[test]
+fn consecutive_moves_in_batch_are_coalesced_into_last_one() {
+    let mut sender = InputEventSender::new(DomCodeMapper);
+    let mut batch = InputBatch::new();
+
+    batch.push(sender.mouse_move(1, 1));
+    batch.push(sender.mouse_move(2, 2));
+    batch.extend(sender.mouse_button(MouseButton::Left, true));
+    batch.push(sender.mouse_move(3, 3));
+    batch.push(sender.mouse_move(4, 4));
+
+    assert_eq!(3, batch.len());
+    assert_eq!(
+        vec![
+            sender.mouse_move(2, 2),
+            FastPathInputEvent::mouse_button(MouseButton::Left, true, 2, 2),
+            sender.mouse_move(4, 4),
+        ],
+        batch.take()
+    );
+    assert!(batch.is_empty());
+}
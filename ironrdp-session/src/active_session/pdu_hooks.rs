@@ -0,0 +1,106 @@
+use ironrdp::ChannelId;
+
+use super::traffic::{TrafficAccounting, TrafficSnapshot};
+use crate::PduContext;
+
+/// Identifies where a PDU has been carried during the active stage.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum PduChannel {
+    FastPath,
+    Static(ChannelId),
+    Dynamic(u32),
+}
+
+/// A short description of a decoded or encoded PDU, passed to the PDU hooks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PduSummary {
+    pub channel: PduChannel,
+    pub name: String,
+}
+
+impl PduSummary {
+    pub fn new(channel: PduChannel, name: &str) -> Self {
+        Self {
+            channel,
+            name: String::from(name),
+        }
+    }
+}
+
+pub type PduHook = Box<dyn FnMut(&PduSummary) + Send>;
+
+#[derive(Default)]
+pub struct PduHooks {
+    received: Vec<PduHook>,
+    sent: Vec<PduHook>,
+    traffic: TrafficAccounting,
+    frame_channel: Option<PduChannel>,
+    /// The short name of the last PDU received in the frame being processed, empty if none has been.
+    frame_pdu: String,
+}
+
+impl PduHooks {
+    pub fn add_received(&mut self, hook: PduHook) {
+        self.received.push(hook);
+    }
+
+    pub fn add_sent(&mut self, hook: PduHook) {
+        self.sent.push(hook);
+    }
+
+    pub fn received(&mut self, channel: PduChannel, name: &str) {
+        self.frame_pdu.clear();
+        self.frame_pdu.push_str(name);
+        self.traffic.pdu_received(channel);
+        notify(&mut self.received, channel, name);
+    }
+
+    pub fn sent(&mut self, channel: PduChannel, name: &str) {
+        self.traffic.pdu_sent(channel);
+        notify(&mut self.sent, channel, name);
+    }
+
+    /// Sets the channel to which the bytes of the frame being processed are attributed.
+    pub fn receiving_on(&mut self, channel: PduChannel) {
+        self.frame_channel = Some(channel);
+        self.traffic.receiving_on(channel);
+    }
+
+    /// Forgets the channel and the PDU of the previous frame, before the next one is processed.
+    pub fn start_frame(&mut self) {
+        self.frame_channel = None;
+        self.frame_pdu.clear();
+    }
+
+    pub fn frame_context(&self, offset: u64) -> PduContext {
+        PduContext {
+            offset,
+            channel: self.frame_channel,
+            pdu: Some(self.frame_pdu.clone()).filter(|pdu| !pdu.is_empty()),
+        }
+    }
+
+    pub fn frame_received(&mut self, length: usize) {
+        self.traffic.frame_received(length);
+    }
+
+    /// Attributes the bytes of the frame to the channel of the last PDU sent.
+    pub fn frame_sent(&mut self, length: usize) {
+        self.traffic.frame_sent(length);
+    }
+
+    pub fn take_traffic_snapshot(&mut self) -> TrafficSnapshot {
+        self.traffic.take_snapshot()
+    }
+}
+
+fn notify(hooks: &mut [PduHook], channel: PduChannel, name: &str) {
+    if hooks.is_empty() {
+        return;
+    }
+
+    let summary = PduSummary::new(channel, name);
+    for hook in hooks.iter_mut() {
+        hook(&summary);
+    }
+}
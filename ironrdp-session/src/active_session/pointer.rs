@@ -0,0 +1,216 @@
+#[cfg(test)]
+mod tests;
+
+use std::io;
+use std::sync::Arc;
+use std::time::Instant;
+
+use ironrdp::pointer::{PointerShape, PointerUpdate};
+use log::warn;
+
+/// The number of pointer shapes the client advertises it caches, for both the color and the new pointer updates.
+pub(crate) const POINTER_CACHE_SIZE: u16 = 25;
+
+/// A pointer update sent by the server, passed to the pointer hooks, e.g. for automation to infer the state
+/// of the remote session from the busy or text selection shapes of the pointer.
+///
+/// A shape is only sent once, the following updates referring to it by its cache index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PointerEvent<'a> {
+    /// When the update has been processed, taken with [`Instant::now`].
+    pub received_at: Instant,
+    pub update: PointerUpdate<'a>,
+}
+
+pub type PointerHook = Box<dyn FnMut(&PointerEvent<'_>) + Send>;
+
+#[derive(Default)]
+pub struct PointerHooks {
+    hooks: Vec<PointerHook>,
+}
+
+impl PointerHooks {
+    pub fn add(&mut self, hook: PointerHook) {
+        self.hooks.push(hook);
+    }
+
+    /// The timestamp is only taken if a hook is registered, as it is not available on `wasm32-unknown-unknown`.
+    pub fn notify(&mut self, update: PointerUpdate<'_>) {
+        if self.hooks.is_empty() {
+            return;
+        }
+
+        let event = PointerEvent {
+            received_at: Instant::now(),
+            update,
+        };
+        for hook in self.hooks.iter_mut() {
+            hook(&event);
+        }
+    }
+}
+
+/// A change of the pointer of the remote session, for the local cursor to mirror it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PointerChange {
+    /// The pointer is hidden.
+    Hidden,
+    /// The pointer takes the default shape of the local operating system.
+    Default,
+    /// The server has moved the pointer, e.g. to the default button of a dialog.
+    Position { x: u16, y: u16 },
+    /// The pointer takes the shape, either newly sent by the server or taken from the [`PointerCache`].
+    Bitmap(Arc<DecodedPointer>),
+}
+
+/// A pointer shape decoded to RGBA pixels, 4 bytes each, whose rows are stored from the top to the bottom.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedPointer {
+    pub width: u16,
+    pub height: u16,
+    pub hot_spot_x: u16,
+    pub hot_spot_y: u16,
+    pub data: Vec<u8>,
+}
+
+impl DecodedPointer {
+    /// Combines the masks of the shape, the pixels of the AND mask making the black pixels of the XOR mask
+    /// transparent. The other pixels of the AND mask invert the screen, which RGBA cannot express,
+    /// so they are drawn in black.
+    pub fn from_shape(shape: &PointerShape<'_>) -> io::Result<Self> {
+        let width = usize::from(shape.width);
+        let height = usize::from(shape.height);
+        let xor_stride = mask_stride(width, usize::from(shape.xor_bpp));
+        let and_stride = mask_stride(width, 1);
+
+        if shape.xor_mask.len() < xor_stride * height {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "XOR mask of the pointer is too short: {} < {}",
+                    shape.xor_mask.len(),
+                    xor_stride * height
+                ),
+            ));
+        }
+        // the AND mask may be omitted, e.g. by the shapes of 32 bpp carrying their own alpha
+        let and_mask = (shape.and_mask.len() >= and_stride * height).then_some(shape.and_mask);
+
+        let mut data = Vec::with_capacity(width * height * 4);
+        for y in 0..height {
+            // the rows of the masks are stored from the bottom to the top
+            let xor_row = &shape.xor_mask[(height - 1 - y) * xor_stride..][..xor_stride];
+            let and_row = and_mask.map(|and_mask| &and_mask[(height - 1 - y) * and_stride..][..and_stride]);
+
+            for x in 0..width {
+                let [r, g, b, a] = xor_pixel(xor_row, x, shape.xor_bpp)?;
+                let transparent = and_row.map_or(false, |and_row| bit(and_row, x));
+
+                let pixel = match (transparent, [r, g, b]) {
+                    (false, _) => [r, g, b, a],
+                    (true, [0, 0, 0]) => [0, 0, 0, 0],
+                    (true, _) => [0, 0, 0, 0xff],
+                };
+                data.extend_from_slice(&pixel);
+            }
+        }
+
+        Ok(Self {
+            width: shape.width,
+            height: shape.height,
+            hot_spot_x: shape.hot_spot_x,
+            hot_spot_y: shape.hot_spot_y,
+            data,
+        })
+    }
+}
+
+/// The rows of the masks are padded to 2 bytes.
+fn mask_stride(width: usize, bpp: usize) -> usize {
+    (width * bpp + 15) / 16 * 2
+}
+
+fn bit(row: &[u8], x: usize) -> bool {
+    row[x / 8] & (0x80 >> (x % 8)) != 0
+}
+
+fn xor_pixel(row: &[u8], x: usize, bpp: u16) -> io::Result<[u8; 4]> {
+    let pixel = match bpp {
+        1 if bit(row, x) => [0xff, 0xff, 0xff, 0xff],
+        1 => [0, 0, 0, 0xff],
+        16 => {
+            let pixel = u16::from_le_bytes([row[x * 2], row[x * 2 + 1]]);
+            let r = ((pixel >> 11) & 0x1f) as u8;
+            let g = ((pixel >> 5) & 0x3f) as u8;
+            let b = (pixel & 0x1f) as u8;
+
+            [r << 3 | r >> 2, g << 2 | g >> 4, b << 3 | b >> 2, 0xff]
+        }
+        24 => [row[x * 3 + 2], row[x * 3 + 1], row[x * 3], 0xff],
+        32 => [row[x * 4 + 2], row[x * 4 + 1], row[x * 4], row[x * 4 + 3]],
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Unsupported color depth of the pointer: {} bpp", bpp),
+            ))
+        }
+    };
+
+    Ok(pixel)
+}
+
+/// The pointer shapes the server refers to by their cache index, decoded once when they are received.
+pub struct PointerCache {
+    pointers: Vec<Option<Arc<DecodedPointer>>>,
+}
+
+impl Default for PointerCache {
+    fn default() -> Self {
+        Self {
+            pointers: vec![None; usize::from(POINTER_CACHE_SIZE)],
+        }
+    }
+}
+
+impl PointerCache {
+    pub fn get(&self, cache_index: u16) -> Option<Arc<DecodedPointer>> {
+        self.pointers.get(usize::from(cache_index)).cloned().flatten()
+    }
+
+    /// Returns the change of the pointer made by the update, the invalid shapes and the unknown cache indices
+    /// being ignored with a warning.
+    pub fn process(&mut self, update: &PointerUpdate<'_>) -> Option<PointerChange> {
+        match update {
+            PointerUpdate::Hidden => Some(PointerChange::Hidden),
+            PointerUpdate::Default => Some(PointerChange::Default),
+            PointerUpdate::Position { x, y } => Some(PointerChange::Position { x: *x, y: *y }),
+            PointerUpdate::Cached { cache_index } => {
+                let pointer = self.get(*cache_index);
+                if pointer.is_none() {
+                    warn!("Received the pointer of the empty cache index {}", cache_index);
+                }
+
+                pointer.map(PointerChange::Bitmap)
+            }
+            PointerUpdate::Shape(shape) => {
+                let pointer = match DecodedPointer::from_shape(shape) {
+                    Ok(pointer) => Arc::new(pointer),
+                    Err(error) => {
+                        warn!("Failed to decode the pointer shape: {}", error);
+                        return None;
+                    }
+                };
+
+                match self.pointers.get_mut(usize::from(shape.cache_index)) {
+                    Some(entry) => *entry = Some(Arc::clone(&pointer)),
+                    None => warn!(
+                        "Received the pointer of the out of range cache index {}",
+                        shape.cache_index
+                    ),
+                }
+
+                Some(PointerChange::Bitmap(pointer))
+            }
+        }
+    }
+}
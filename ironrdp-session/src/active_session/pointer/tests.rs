@@ -0,0 +1,75 @@
+use std::sync::{Arc, Mutex};
+
+use super::*;
+
+#[test]
+fn every_hook_receives_the_update() {
+    let updates = Arc::new(Mutex::new(Vec::new()));
+    let mut hooks = PointerHooks::default();
+    for hook_id in 0..2 {
+        let updates = Arc::clone(&updates);
+        hooks.add(Box::new(move |event: &PointerEvent<'_>| {
+            if let PointerUpdate::Position { x, y } = event.update {
+                updates.lock().unwrap().push((hook_id, x, y));
+            }
+        }));
+    }
+
+    hooks.notify(PointerUpdate::Position { x: 10, y: 20 });
+
+    assert_eq!(vec![(0, 10, 20), (1, 10, 20)], *updates.lock().unwrap());
+}
+
+/// A 2x2 shape of 24 bpp, whose top-left pixel is transparent, the rows being stored from the bottom.
+#[rustfmt::skip]
+const XOR_MASK: [u8; 12] = [
+    0x00, 0x00, 0xff, 0xff, 0x00, 0x00, // red, blue
+    0x00, 0x00, 0x00, 0x00, 0xff, 0x00, // black, green
+];
+#[rustfmt::skip]
+const AND_MASK: [u8; 4] = [
+    0x00, 0x00,
+    0x80, 0x00,
+];
+
+fn shape(cache_index: u16) -> PointerShape<'static> {
+    PointerShape {
+        xor_bpp: 24,
+        cache_index,
+        hot_spot_x: 1,
+        hot_spot_y: 0,
+        width: 2,
+        height: 2,
+        xor_mask: &XOR_MASK,
+        and_mask: &AND_MASK,
+    }
+}
+
+#[test]
+fn shape_is_decoded_to_top_down_rgba() {
+    let pointer = DecodedPointer::from_shape(&shape(0)).unwrap();
+
+    #[rustfmt::skip]
+    let expected = [
+        0x00, 0x00, 0x00, 0x00, 0x00, 0xff, 0x00, 0xff,
+        0xff, 0x00, 0x00, 0xff, 0x00, 0x00, 0xff, 0xff,
+    ];
+    assert_eq!(&expected, pointer.data.as_slice());
+    assert_eq!((1, 0), (pointer.hot_spot_x, pointer.hot_spot_y));
+}
+
+#[test]
+fn cached_pointer_is_taken_from_cache() {
+    let mut cache = PointerCache::default();
+
+    let new_pointer = cache.process(&PointerUpdate::Shape(shape(3)));
+    let cached_pointer = cache.process(&PointerUpdate::Cached { cache_index: 3 });
+
+    match (new_pointer, cached_pointer) {
+        (Some(PointerChange::Bitmap(new_pointer)), Some(PointerChange::Bitmap(cached_pointer))) => {
+            assert!(Arc::ptr_eq(&new_pointer, &cached_pointer))
+        }
+        changes => panic!("Unexpected pointer changes: {:?}", changes),
+    }
+    assert_eq!(None, cache.process(&PointerUpdate::Cached { cache_index: 4 }));
+}
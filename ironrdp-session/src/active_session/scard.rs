@@ -0,0 +1,64 @@
+use ironrdp::rdp::vc::rdpdr::scard::{ReaderState, ScardIoRequest, ScardReturnCode};
+
+/// The result of a smart card call, failing with one of the `SCARD_E_*` codes.
+pub type ScardResult<T> = Result<T, ScardReturnCode>;
+
+/// Smart card API of the client, typically PC/SC, whose readers are redirected to the remote session
+/// over the `rdpdr` channel, see [`ActiveStageProcessor::set_smart_card_backend`](crate::ActiveStageProcessor::set_smart_card_backend).
+///
+/// The contexts and the cards are identified by values chosen by the backend,
+/// the parameters and results of the calls being those of the `SCard*` functions of the API.
+pub trait ScardBackend: Send {
+    fn establish_context(&mut self, scope: u32) -> ScardResult<u64>;
+
+    fn release_context(&mut self, context: u64) -> ScardResult<()>;
+
+    fn list_readers(&mut self, context: u64) -> ScardResult<Vec<String>>;
+
+    /// Updates the event state, and the ATR, of the readers whose state differs from their current state.
+    ///
+    /// Must not block: if none of the states has changed, it fails with [`ScardReturnCode::TIMEOUT`]
+    /// and is called again until the timeout requested by the remote session expires.
+    fn get_status_change(&mut self, context: u64, reader_states: &mut [ReaderState]) -> ScardResult<()>;
+
+    /// Connects to the card in the reader, returning the card and the active protocol.
+    fn connect(
+        &mut self,
+        context: u64,
+        reader: &str,
+        share_mode: u32,
+        preferred_protocols: u32,
+    ) -> ScardResult<(u64, u32)>;
+
+    /// Reconnects to the card, returning the active protocol.
+    fn reconnect(
+        &mut self,
+        card: u64,
+        share_mode: u32,
+        preferred_protocols: u32,
+        initialization: u32,
+    ) -> ScardResult<u32>;
+
+    fn disconnect(&mut self, card: u64, disposition: u32) -> ScardResult<()>;
+
+    fn begin_transaction(&mut self, card: u64) -> ScardResult<()>;
+
+    fn end_transaction(&mut self, card: u64, disposition: u32) -> ScardResult<()>;
+
+    fn status(&mut self, card: u64) -> ScardResult<CardStatus>;
+
+    /// Sends the APDU to the card, returning its response of at most `max_length` bytes.
+    fn transmit(&mut self, card: u64, send_pci: &ScardIoRequest, data: &[u8], max_length: u32) -> ScardResult<Vec<u8>>;
+
+    fn control(&mut self, card: u64, control_code: u32, data: &[u8], max_length: u32) -> ScardResult<Vec<u8>>;
+
+    fn get_attrib(&mut self, card: u64, attribute_id: u32) -> ScardResult<Vec<u8>>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CardStatus {
+    pub reader_names: Vec<String>,
+    pub state: u32,
+    pub protocol: u32,
+    pub atr: Vec<u8>,
+}
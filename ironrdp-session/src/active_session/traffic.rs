@@ -0,0 +1,94 @@
+#[cfg(test)]
+mod tests;
+
+use std::collections::HashMap;
+
+use super::pdu_hooks::PduChannel;
+
+/// Number of PDUs and of bytes carried in one direction.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct TrafficCounters {
+    pub pdus: u64,
+    /// The bytes of the frames carrying the PDUs, headers included.
+    pub bytes: u64,
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct ChannelTraffic {
+    pub received: TrafficCounters,
+    pub sent: TrafficCounters,
+}
+
+/// Traffic of each channel since the previous snapshot, see
+/// [`ActiveStageProcessor::take_traffic_snapshot`](crate::ActiveStageProcessor::take_traffic_snapshot).
+///
+/// The dynamic virtual channels are accounted separately from the static channel multiplexing them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TrafficSnapshot {
+    pub channels: HashMap<PduChannel, ChannelTraffic>,
+}
+
+impl TrafficSnapshot {
+    pub fn channel(&self, channel: PduChannel) -> ChannelTraffic {
+        self.channels.get(&channel).copied().unwrap_or_default()
+    }
+
+    pub fn total(&self) -> ChannelTraffic {
+        self.channels
+            .values()
+            .fold(ChannelTraffic::default(), |mut total, traffic| {
+                total.received.pdus += traffic.received.pdus;
+                total.received.bytes += traffic.received.bytes;
+                total.sent.pdus += traffic.sent.pdus;
+                total.sent.bytes += traffic.sent.bytes;
+
+                total
+            })
+    }
+}
+
+/// Counts the PDUs as they are decoded and encoded, and attributes the bytes of each frame
+/// to the channel it has been received on, or to the last channel a PDU has been sent on.
+#[derive(Debug, Default)]
+pub struct TrafficAccounting {
+    snapshot: TrafficSnapshot,
+    receiving_channel: Option<PduChannel>,
+    sending_channel: Option<PduChannel>,
+}
+
+impl TrafficAccounting {
+    pub fn pdu_received(&mut self, channel: PduChannel) {
+        self.channel_mut(channel).received.pdus += 1;
+    }
+
+    pub fn pdu_sent(&mut self, channel: PduChannel) {
+        self.channel_mut(channel).sent.pdus += 1;
+        self.sending_channel = Some(channel);
+    }
+
+    /// Sets the channel the frame being processed has been received on,
+    /// a dynamic channel overriding the static channel carrying it.
+    pub fn receiving_on(&mut self, channel: PduChannel) {
+        self.receiving_channel = Some(channel);
+    }
+
+    pub fn frame_received(&mut self, length: usize) {
+        if let Some(channel) = self.receiving_channel.take() {
+            self.channel_mut(channel).received.bytes += length as u64;
+        }
+    }
+
+    pub fn frame_sent(&mut self, length: usize) {
+        if let Some(channel) = self.sending_channel.take() {
+            self.channel_mut(channel).sent.bytes += length as u64;
+        }
+    }
+
+    pub fn take_snapshot(&mut self) -> TrafficSnapshot {
+        std::mem::take(&mut self.snapshot)
+    }
+
+    fn channel_mut(&mut self, channel: PduChannel) -> &mut ChannelTraffic {
+        self.snapshot.channels.entry(channel).or_default()
+    }
+}
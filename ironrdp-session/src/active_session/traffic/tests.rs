@@ -0,0 +1,55 @@
+use ironrdp::ChannelId;
+
+use super::*;
+
+const DRDYNVC: PduChannel = PduChannel::Static(ChannelId(1004));
+const GRAPHICS: PduChannel = PduChannel::Dynamic(3);
+
+#[test]
+fn frame_bytes_are_attributed_to_dynamic_channel_over_static_one() {
+    let mut traffic = TrafficAccounting::default();
+
+    traffic.receiving_on(DRDYNVC);
+    traffic.receiving_on(GRAPHICS);
+    traffic.pdu_received(GRAPHICS);
+    traffic.frame_received(1500);
+
+    let snapshot = traffic.take_snapshot();
+    assert_eq!(
+        TrafficCounters { pdus: 1, bytes: 1500 },
+        snapshot.channel(GRAPHICS).received
+    );
+    assert_eq!(ChannelTraffic::default(), snapshot.channel(DRDYNVC));
+}
+
+#[test]
+fn sent_frame_is_attributed_to_channel_of_last_pdu_sent() {
+    let mut traffic = TrafficAccounting::default();
+
+    traffic.pdu_sent(GRAPHICS);
+    traffic.frame_sent(42);
+    // nothing has been sent since the previous frame
+    traffic.frame_sent(0);
+    traffic.pdu_sent(PduChannel::FastPath);
+    traffic.frame_sent(12);
+
+    let snapshot = traffic.take_snapshot();
+    assert_eq!(TrafficCounters { pdus: 1, bytes: 42 }, snapshot.channel(GRAPHICS).sent);
+    assert_eq!(
+        TrafficCounters { pdus: 1, bytes: 12 },
+        snapshot.channel(PduChannel::FastPath).sent
+    );
+    assert_eq!(TrafficCounters { pdus: 2, bytes: 54 }, snapshot.total().sent);
+}
+
+#[test]
+fn snapshot_resets_counters() {
+    let mut traffic = TrafficAccounting::default();
+
+    traffic.receiving_on(PduChannel::FastPath);
+    traffic.pdu_received(PduChannel::FastPath);
+    traffic.frame_received(100);
+    traffic.take_snapshot();
+
+    assert_eq!(TrafficSnapshot::default(), traffic.take_snapshot());
+}
@@ -1,428 +1,622 @@
 mod display;
+mod drdynvc;
+#[cfg(feature = "zgfx")]
 mod gfx;
-
-use std::collections::HashMap;
-use std::{cmp, io};
-
-use ironrdp::dvc::FieldType;
-use ironrdp::rdp::vc::{self, dvc};
-use ironrdp::rdp::{ErrorInfo, ProtocolIndependentCode, ServerSetErrorInfoPdu};
-use ironrdp::{Data, ShareDataPdu};
-use log::{debug, error};
-
+mod rdpdr;
+mod rdpsnd;
+
+use std::any::Any;
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::time::Instant;
+
+use ironrdp::codecs::rfx::image_processing::PixelFormat;
+use ironrdp::pointer::PointerPdu;
+use ironrdp::rdp::bulk::BulkDecompressor;
+use ironrdp::rdp::session_info::{InfoData, LogonInfoExtended, SaveSessionInfoPdu, ServerAutoReconnect};
+use ironrdp::rdp::vc::{ChannelControlFlags, ChannelPduHeader, ChannelReassembler, StaticChannelName};
+use ironrdp::rdp::{ErrorInfo, ProtocolIndependentCode, ServerSetErrorInfoPdu, ServerStatusInfoPdu};
+use ironrdp::{ChannelId, Data, LimitsConfig, PduParsing, Rectangle, ShareControlHeader, ShareDataPdu};
+use log::{debug, warn};
+
+use super::audio::AudioSink;
+use super::channels::{ChannelHook, ChannelRegistry};
+use super::coalescing::CoalescingConfig;
+#[cfg(feature = "h264")]
+use super::codecs::h264::Avc420DecoderFactory;
+use super::drive::{DrivePolicy, FileSystemBackend};
+use super::frame_pacing::{DecodeTiming, FramePacingConfig};
+use super::pdu_hooks::{PduChannel, PduHooks};
+use super::scard::ScardBackend;
+use super::{ActiveStageOutput, GraphicsFrameEvent, SessionLockState};
+use crate::image::ImageSink;
 use crate::transport::{
-    Decoder, DynamicVirtualChannelTransport, Encoder, SendDataContextTransport, ShareControlHeaderTransport,
-    ShareDataHeaderTransport, StaticVirtualChannelTransport,
+    ChannelIdentificators, Decoder, Encoder, SendDataContextTransport, ShareControlHeaderTransport,
+    ShareDataHeaderTransport,
 };
-use crate::{GraphicsConfig, RdpError};
-
-const RDP8_GRAPHICS_PIPELINE_NAME: &str = "Microsoft::Windows::RDS::Graphics";
-const RDP8_DISPLAY_PIPELINE_NAME: &str = "Microsoft::Windows::RDS::DisplayControl";
+use crate::{GraphicsConfig, MonitorConfig, RdpError};
 
+/// Demultiplexes the X.224 data frames to the handlers of the joined static channels.
 pub struct Processor {
-    static_channels: HashMap<u16, String>,
-    channel_map: HashMap<String, u32>,
-    dynamic_channels: HashMap<u32, DynamicChannel>,
-    global_channel_name: String,
-    drdynvc_transport: Option<DynamicVirtualChannelTransport>,
-    static_transport: Option<ShareDataHeaderTransport>,
-    graphics_config: Option<GraphicsConfig>,
+    static_channels: StaticChannelSet,
+    channels: ChannelRegistry,
+    /// The I/O channel, whose Share Data PDUs are not sent in chunks unlike the messages of the other channels.
+    global_channel_id: Option<ChannelId>,
+    reassembler: ChannelReassembler,
 }
 
 impl Processor {
     pub fn new(
-        static_channels: HashMap<u16, String>,
-        global_channel_name: String,
+        static_channels: HashMap<ChannelId, StaticChannelName>,
+        global_channel_name: StaticChannelName,
         graphics_config: Option<GraphicsConfig>,
+        pixel_format: PixelFormat,
+        client_name: String,
+        limits: LimitsConfig,
     ) -> Self {
+        let mut channel_set = StaticChannelSet::default();
+        let global_channel_id = static_channels
+            .iter()
+            .find(|(_, name)| **name == global_channel_name)
+            .map(|(channel_id, _)| *channel_id);
+
+        for (channel_id, name) in static_channels.iter() {
+            let handler: Option<Box<dyn StaticChannelHandler>> = if *name == global_channel_name {
+                Some(Box::new(GlobalChannelHandler::default()))
+            } else if *name == StaticChannelName::DRDYNVC {
+                Some(Box::new(drdynvc::Handler::new(
+                    graphics_config.clone(),
+                    pixel_format,
+                    limits,
+                )))
+            } else if *name == StaticChannelName::RDPSND {
+                Some(Box::new(rdpsnd::Handler::default()))
+            } else if *name == StaticChannelName::RDPDR {
+                Some(Box::new(rdpdr::Handler::new(client_name.clone())))
+            } else {
+                None
+            };
+
+            channel_set.insert(*channel_id, handler);
+        }
+
         Self {
-            static_channels,
-            dynamic_channels: HashMap::new(),
-            channel_map: HashMap::new(),
-            global_channel_name,
-            drdynvc_transport: None,
-            static_transport: None,
-            graphics_config,
+            static_channels: channel_set,
+            channels: ChannelRegistry::new(static_channels),
+            global_channel_id,
+            reassembler: ChannelReassembler::new(limits.max_svc_reassembly_size),
         }
     }
 
-    pub fn process(
-        &mut self,
-        mut stream: impl io::Read,
-        mut output: impl io::Write,
-        data: Data,
-    ) -> Result<(), RdpError> {
-        let mut transport = SendDataContextTransport::default();
-        transport.mcs_transport.0.set_decoded_context(data.data_length);
+    pub fn channels(&self) -> &ChannelRegistry {
+        &self.channels
+    }
 
-        let channel_ids = transport.decode(&mut stream)?;
-        transport.set_decoded_context(channel_ids);
+    pub fn add_channel_hook(&mut self, hook: ChannelHook) {
+        self.channels.add_hook(hook);
+    }
 
-        let channel_id = channel_ids.channel_id;
-        let initiator_id = channel_ids.initiator_id;
-        match self.static_channels.get(&channel_id).map(String::as_str) {
-            Some(vc::DRDYNVC_CHANNEL_NAME) => self.process_dvc_message(&mut stream, &mut output, transport, channel_id),
-            Some(name) if name == self.global_channel_name => {
-                if self.static_transport.is_none() {
-                    self.static_transport = Some(ShareDataHeaderTransport::new(ShareControlHeaderTransport::new(
-                        transport,
-                        initiator_id,
-                        channel_id,
-                    )));
-                }
-                let transport = self.static_transport.as_mut().unwrap();
+    /// The last auto-reconnect cookie sent by the server.
+    pub fn auto_reconnect(&self) -> Option<&ServerAutoReconnect> {
+        self.static_channels
+            .get::<GlobalChannelHandler>()
+            .and_then(|(_, handler)| handler.auto_reconnect.as_ref())
+    }
 
-                process_global_channel_pdu(&mut stream, transport)
-            }
-            Some(_) => Err(RdpError::UnexpectedChannel(channel_id)),
-            None => panic!("Channel with {} ID must be added", channel_id),
+    /// Returns the area of the output to be refreshed by the server, which the dynamic channel handlers
+    /// have requested since the previous call.
+    pub fn take_refresh_request(&mut self) -> Option<Rectangle> {
+        self.static_channels
+            .get_mut::<drdynvc::Handler>()
+            .and_then(|(_, handler)| handler.take_refresh_request())
+    }
+
+    pub fn take_frame_id(&mut self) -> Option<u32> {
+        self.static_channels
+            .get_mut::<drdynvc::Handler>()
+            .and_then(|(_, handler)| handler.take_frame_id())
+    }
+
+    /// Returns the slow-path pointer updates received on the I/O channel since the previous call.
+    pub fn take_pointer_pdus(&mut self) -> Vec<PointerPdu> {
+        self.static_channels
+            .get_mut::<GlobalChannelHandler>()
+            .map(|(_, handler)| std::mem::take(&mut handler.pointer_pdus))
+            .unwrap_or_default()
+    }
+
+    pub fn take_frame_events(&mut self) -> Vec<GraphicsFrameEvent> {
+        self.static_channels
+            .get_mut::<drdynvc::Handler>()
+            .map(|(_, handler)| handler.take_frame_events())
+            .unwrap_or_default()
+    }
+
+    pub fn set_ack_coalescing(&mut self, config: CoalescingConfig) {
+        if let Some((_, handler)) = self.static_channels.get_mut::<drdynvc::Handler>() {
+            handler.set_ack_coalescing(config);
         }
     }
 
-    /// Sends a PDU on the dynamic channel. The upper layers are responsible for encoding the PDU and converting them to message
-    #[allow(dead_code)]
-    pub fn send_dynamic(
+    pub fn set_frame_pacing(&mut self, config: FramePacingConfig) {
+        if let Some((_, handler)) = self.static_channels.get_mut::<drdynvc::Handler>() {
+            handler.set_frame_pacing(config);
+        }
+    }
+
+    pub fn decode_timing(&self) -> Option<DecodeTiming> {
+        self.static_channels
+            .get::<drdynvc::Handler>()
+            .and_then(|(_, handler)| handler.decode_timing())
+    }
+
+    /// Sends the PDUs held back by the channel handlers whose interval has elapsed.
+    pub fn send_coalesced(
         &mut self,
-        mut stream: impl io::Write,
-        channel_name: &str,
-        message: Vec<u8>,
+        output: impl io::Write,
+        now: Instant,
+        hooks: &mut PduHooks,
     ) -> Result<(), RdpError> {
-        if let Some(transport) = self.drdynvc_transport.as_mut() {
-            let channel_id = self
-                .channel_map
-                .get(channel_name)
-                .ok_or_else(|| RdpError::AccessToNonExistingChannelName(channel_name.to_string()))?;
-            let channel = self
-                .dynamic_channels
-                .get_mut(channel_id)
-                .ok_or(RdpError::AccessToNonExistingChannel(*channel_id))?;
-            let client_data = dvc::ClientPdu::Data(dvc::DataPdu {
-                channel_id_type: channel.channel_id_type,
-                channel_id: channel.channel_id,
-                data_size: message.len(),
-            });
-
-            transport.encode(
-                DynamicVirtualChannelTransport::prepare_data_to_encode(client_data, Some(message))?,
-                &mut stream,
-            )?;
-        } else {
-            return Err(RdpError::DynamicVirtualChannelNotConnected);
-        };
+        match self.static_channels.get_mut::<drdynvc::Handler>() {
+            Some((_, handler)) => handler.send_coalesced(output, now, hooks),
+            None => Ok(()),
+        }
+    }
 
-        Ok(())
+    pub fn coalescing_deadline(&self) -> Option<Instant> {
+        self.static_channels
+            .get::<drdynvc::Handler>()
+            .and_then(|(_, handler)| handler.coalescing_deadline())
     }
 
-    /// Send a pdu on the static global channel. Typically used to send input events
-    #[allow(dead_code)]
-    pub fn send_static(&mut self, mut stream: impl io::Write, message: ShareDataPdu) -> Result<(), RdpError> {
-        if let Some(transport) = self.static_transport.as_mut() {
-            transport.encode(message, &mut stream)?;
-        } else {
-            return Err(RdpError::StaticChannelNotConnected);
-        };
+    pub fn set_audio_sink(&mut self, sink: Box<dyn AudioSink>) {
+        if let Some((_, handler)) = self.static_channels.get_mut::<rdpsnd::Handler>() {
+            handler.set_sink(sink);
+        }
+    }
 
-        Ok(())
+    pub fn add_drive(&mut self, name: String, backend: Box<dyn FileSystemBackend>, policy: DrivePolicy) {
+        if let Some((_, handler)) = self.static_channels.get_mut::<rdpdr::Handler>() {
+            handler.add_drive(name, backend, policy);
+        }
     }
 
-    fn process_dvc_message(
-        &mut self,
-        mut stream: impl io::Read,
-        mut output: impl io::Write,
-        transport: SendDataContextTransport,
-        channel_id: u16,
-    ) -> Result<(), RdpError> {
-        if self.drdynvc_transport.is_none() {
-            self.drdynvc_transport = Some(DynamicVirtualChannelTransport::new(
-                StaticVirtualChannelTransport::new(transport),
-                channel_id,
-            ));
+    pub fn set_smart_card_backend(&mut self, backend: Box<dyn ScardBackend>) {
+        if let Some((_, handler)) = self.static_channels.get_mut::<rdpdr::Handler>() {
+            handler.set_smart_card_backend(backend);
         }
+    }
 
-        let transport = self.drdynvc_transport.as_mut().unwrap();
+    #[cfg(feature = "h264")]
+    pub fn set_avc420_decoder_factory(&mut self, factory: Avc420DecoderFactory) {
+        if let Some((_, handler)) = self.static_channels.get_mut::<drdynvc::Handler>() {
+            handler.set_avc420_decoder_factory(factory);
+        }
+    }
 
-        match transport.decode(&mut stream)? {
-            dvc::ServerPdu::CapabilitiesRequest(caps_request) => {
-                debug!("Got DVC Capabilities Request PDU: {:?}", caps_request);
-                let caps_response = dvc::ClientPdu::CapabilitiesResponse(dvc::CapabilitiesResponsePdu {
-                    version: dvc::CapsVersion::V1,
-                });
+    /// Takes the configuration of the channels, for it to be restored on the processor of the reconnected session.
+    pub fn into_channel_state(mut self) -> ChannelState {
+        let audio_sink = self
+            .static_channels
+            .get_mut::<rdpsnd::Handler>()
+            .and_then(|(_, handler)| handler.take_sink());
+        let (drives, smart_card_backend) = match self.static_channels.get_mut::<rdpdr::Handler>() {
+            Some((_, handler)) => (handler.take_drives(), handler.take_smart_card_backend()),
+            None => (Vec::new(), None),
+        };
+        let mut state = ChannelState {
+            audio_sink,
+            drives,
+            smart_card_backend,
+            #[cfg(feature = "h264")]
+            avc420_decoder_factory: None,
+            monitor_layout: None,
+        };
 
-                debug!("Send DVC Capabilities Response PDU: {:?}", caps_response);
-                transport.encode(
-                    DynamicVirtualChannelTransport::prepare_data_to_encode(caps_response, None)?,
-                    &mut output,
-                )?;
+        if let Some((_, handler)) = self.static_channels.get_mut::<drdynvc::Handler>() {
+            #[cfg(feature = "h264")]
+            {
+                state.avc420_decoder_factory = handler.take_avc420_decoder_factory();
             }
-            dvc::ServerPdu::CreateRequest(create_request) => {
-                debug!("Got DVC Create Request PDU: {:?}", create_request);
-
-                let creation_status = if let Some(dyncamic_channel) = create_dvc(
-                    create_request.channel_name.as_str(),
-                    create_request.channel_id,
-                    create_request.channel_id_type,
-                ) {
-                    self.dynamic_channels
-                        .insert(create_request.channel_id, dyncamic_channel);
-                    self.channel_map
-                        .insert(create_request.channel_name.clone(), create_request.channel_id);
-
-                    dvc::DVC_CREATION_STATUS_OK
-                } else {
-                    dvc::DVC_CREATION_STATUS_NO_LISTENER
-                };
-
-                let create_response = dvc::ClientPdu::CreateResponse(dvc::CreateResponsePdu {
-                    channel_id_type: create_request.channel_id_type,
-                    channel_id: create_request.channel_id,
-                    creation_status,
-                });
-
-                debug!("Send DVC Create Response PDU: {:?}", create_response);
-                transport.encode(
-                    DynamicVirtualChannelTransport::prepare_data_to_encode(create_response, None)?,
-                    &mut output,
-                )?;
-
-                negotiate_dvc(&create_request, transport, &mut output, &self.graphics_config)?;
-            }
-            dvc::ServerPdu::CloseRequest(close_request) => {
-                debug!("Got DVC Close Request PDU: {:?}", close_request);
-
-                let close_response = dvc::ClientPdu::CloseResponse(dvc::ClosePdu {
-                    channel_id_type: close_request.channel_id_type,
-                    channel_id: close_request.channel_id,
-                });
+            state.monitor_layout = handler.take_monitor_layout();
+        }
 
-                debug!("Send DVC Close Response PDU: {:?}", close_response);
-                transport.encode(
-                    DynamicVirtualChannelTransport::prepare_data_to_encode(close_response, None)?,
-                    &mut output,
-                )?;
+        state
+    }
 
-                self.dynamic_channels.remove(&close_request.channel_id);
+    /// Restores the configuration of the channels of the previous session, the part of it whose channel
+    /// has not been joined again being dropped.
+    pub fn restore_channel_state(&mut self, state: ChannelState) {
+        if let Some(sink) = state.audio_sink {
+            match self.static_channels.get_mut::<rdpsnd::Handler>() {
+                Some((_, handler)) => handler.set_sink(sink),
+                None => warn!("The audio output is not restored as the rdpsnd channel has not been joined"),
             }
-            dvc::ServerPdu::DataFirst(data) => {
-                let channel_id_type = data.channel_id_type;
-                let channel_id = data.channel_id;
-                let mut data_buff = vec![0; data.data_size];
-                stream.read_exact(&mut data_buff)?;
-
-                if let Some(dvc_data) = self
-                    .dynamic_channels
-                    .get_mut(&data.channel_id)
-                    .ok_or(RdpError::AccessToNonExistingChannel(data.channel_id))?
-                    .process_data_first_pdu(data.total_data_size as usize, data_buff)?
-                {
-                    let client_data = dvc::ClientPdu::Data(dvc::DataPdu {
-                        channel_id_type,
-                        channel_id,
-                        data_size: dvc_data.len(),
-                    });
-
-                    transport.encode(
-                        DynamicVirtualChannelTransport::prepare_data_to_encode(client_data, Some(dvc_data))?,
-                        &mut output,
-                    )?;
+        }
+
+        if !state.drives.is_empty() || state.smart_card_backend.is_some() {
+            match self.static_channels.get_mut::<rdpdr::Handler>() {
+                Some((_, handler)) => {
+                    for (name, backend, policy) in state.drives {
+                        handler.add_drive(name, backend, policy);
+                    }
+                    if let Some(backend) = state.smart_card_backend {
+                        handler.set_smart_card_backend(backend);
+                    }
                 }
+                None => warn!("The redirected devices are not restored as the rdpdr channel has not been joined"),
             }
-            dvc::ServerPdu::Data(data) => {
-                let channel_id_type = data.channel_id_type;
-                let channel_id = data.channel_id;
-                let mut data_buff = vec![0; data.data_size];
-                stream.read_exact(&mut data_buff)?;
-
-                if let Some(dvc_data) = self
-                    .dynamic_channels
-                    .get_mut(&data.channel_id)
-                    .ok_or(RdpError::AccessToNonExistingChannel(data.channel_id))?
-                    .process_data_pdu(data_buff)?
-                {
-                    let client_data = dvc::ClientPdu::Data(dvc::DataPdu {
-                        channel_id_type,
-                        channel_id,
-                        data_size: dvc_data.len(),
-                    });
-
-                    transport.encode(
-                        DynamicVirtualChannelTransport::prepare_data_to_encode(client_data, Some(dvc_data))?,
-                        &mut output,
-                    )?;
-                }
+        }
+
+        if let Some((_, handler)) = self.static_channels.get_mut::<drdynvc::Handler>() {
+            #[cfg(feature = "h264")]
+            if let Some(factory) = state.avc420_decoder_factory {
+                handler.set_avc420_decoder_factory(factory);
             }
+            if let Some(monitors) = state.monitor_layout {
+                handler.set_monitor_layout(monitors);
+            }
+        } else if state.monitor_layout.is_some() {
+            warn!("The layout of the monitors is not restored as the drdynvc channel has not been joined");
         }
+    }
 
-        Ok(())
+    /// Routes the message to the mailbox of its channel, whose handler then processes it.
+    pub fn process(
+        &mut self,
+        mut stream: impl io::Read,
+        mut output: impl io::Write,
+        data: Data,
+        image: &mut dyn ImageSink,
+        hooks: &mut PduHooks,
+        decompressor: &mut BulkDecompressor,
+    ) -> Result<Option<ActiveStageOutput>, RdpError> {
+        let mut transport = SendDataContextTransport::default();
+        transport.mcs_transport.0.set_decoded_context(data.data_length);
+
+        let channel_ids = transport.decode(&mut stream)?;
+        transport.set_decoded_context(channel_ids);
+        hooks.receiving_on(PduChannel::Static(channel_ids.channel_id));
+
+        let mut message_data = Vec::new();
+        stream.read_to_end(&mut message_data)?;
+        if Some(channel_ids.channel_id) == self.global_channel_id {
+            // the Share Data PDUs are compressed with the history of the Fast-Path updates
+            if let Some(pdu) = ShareControlHeader::decompress(&message_data, decompressor)
+                .map_err(RdpError::ShareControlHeaderError)?
+            {
+                message_data = pdu;
+            }
+        } else {
+            match self.reassemble(channel_ids.channel_id, &message_data)? {
+                Some(message) => message_data = message,
+                None => return Ok(None),
+            }
+        }
+
+        self.static_channels.post(ChannelMessage {
+            channel_ids,
+            transport,
+            data: message_data,
+        })?;
+
+        self.static_channels
+            .process_mailbox(channel_ids.channel_id, &mut output, image, hooks, &mut self.channels)
     }
-}
 
-fn process_global_channel_pdu(
-    mut stream: impl io::Read,
-    transport: &mut ShareDataHeaderTransport,
-) -> Result<(), RdpError> {
-    let share_data_pdu = transport.decode(&mut stream)?;
+    /// Reassembles the message of a static channel from its chunks, decompressing them, and returns it once
+    /// complete as a single chunk, as the channel handlers decode it.
+    fn reassemble(&mut self, channel_id: ChannelId, chunk: &[u8]) -> Result<Option<Vec<u8>>, RdpError> {
+        let header = ChannelPduHeader::from_buffer(chunk)?;
+        let Some(message) = self
+            .reassembler
+            .process(channel_id, &header, &chunk[header.buffer_length()..])?
+        else {
+            return Ok(None);
+        };
 
-    match share_data_pdu {
-        ShareDataPdu::SaveSessionInfo(session_info) => {
-            debug!("Got Session Save Info PDU: {:?}", session_info);
+        let header = ChannelPduHeader {
+            total_length: message.len() as u32,
+            flags: ChannelControlFlags::FLAG_FIRST | ChannelControlFlags::FLAG_LAST,
+        };
+        let mut message_data = Vec::with_capacity(header.buffer_length() + message.len());
+        header.to_buffer(&mut message_data)?;
+        message_data.extend_from_slice(&message);
 
-            Ok(())
-        }
-        ShareDataPdu::ServerSetErrorInfo(ServerSetErrorInfoPdu(ErrorInfo::ProtocolIndependentCode(
-            ProtocolIndependentCode::None,
-        ))) => {
-            debug!("Received None server error");
+        Ok(Some(message_data))
+    }
 
-            Ok(())
-        }
-        ShareDataPdu::ServerSetErrorInfo(ServerSetErrorInfoPdu(e)) => Err(RdpError::ServerError(e.description())),
-        _ => Err(RdpError::UnexpectedPdu(format!(
-            "Expected Session Save Info PDU, got: {:?}",
-            share_data_pdu.as_short_name()
-        ))),
+    /// Sends the layout of the monitors on the Display Control channel,
+    /// for the server to change the resolution of the desktop.
+    pub fn send_monitor_layout(
+        &mut self,
+        stream: impl io::Write,
+        monitors: &[MonitorConfig],
+        hooks: &mut PduHooks,
+    ) -> Result<(), RdpError> {
+        self.static_channels
+            .get_mut::<drdynvc::Handler>()
+            .ok_or(RdpError::DynamicVirtualChannelNotConnected)?
+            .1
+            .send_monitor_layout(stream, monitors, hooks, &self.channels)
     }
-}
 
-fn create_dvc(channel_name: &str, channel_id: u32, channel_id_type: FieldType) -> Option<DynamicChannel> {
-    match channel_name {
-        RDP8_GRAPHICS_PIPELINE_NAME => Some(DynamicChannel::new(
-            Box::new(gfx::Handler::new()),
-            channel_id,
-            channel_id_type,
-        )),
-        RDP8_DISPLAY_PIPELINE_NAME => Some(DynamicChannel::new(
-            Box::new(display::Handler::new()),
-            channel_id,
-            channel_id_type,
-        )),
-        _ => {
-            error!("Unknown channel name: {}", channel_name);
-            None
+    /// Send a pdu on the static global channel. Typically used to send input events
+    #[allow(dead_code)]
+    pub fn send_static(&mut self, mut stream: impl io::Write, message: ShareDataPdu) -> Result<(), RdpError> {
+        let transport = self
+            .static_channels
+            .get_mut::<GlobalChannelHandler>()
+            .and_then(|(_, handler)| handler.transport.as_mut())
+            .ok_or(RdpError::StaticChannelNotConnected)?;
+
+        transport.encode(message, &mut stream)
+    }
+
+    /// Sends the completions of the pending smart card calls on the `rdpdr` channel,
+    /// once the server has opened it.
+    pub fn send_smart_card_events(&mut self, output: impl io::Write, hooks: &mut PduHooks) -> Result<(), RdpError> {
+        match self.static_channels.get_mut::<rdpdr::Handler>() {
+            Some((channel_id, handler)) => handler.send_smart_card_events(channel_id, output, hooks),
+            None => Ok(()),
         }
     }
 }
 
-fn negotiate_dvc(
-    create_request: &dvc::CreateRequestPdu,
-    transport: &mut DynamicVirtualChannelTransport,
-    mut stream: impl io::Write,
-    graphics_config: &Option<GraphicsConfig>,
-) -> Result<(), RdpError> {
-    if create_request.channel_name == RDP8_GRAPHICS_PIPELINE_NAME {
-        let dvc_data = gfx::create_capabilities_advertise(graphics_config)?;
-        let client_data = dvc::ClientPdu::Data(dvc::DataPdu {
-            channel_id_type: create_request.channel_id_type,
-            channel_id: create_request.channel_id,
-            data_size: dvc_data.len(),
-        });
+/// The configuration of the channels of a session, taken from its processor once the connection has been lost,
+/// so that the channels of the session reconnected with the auto-reconnect cookie work as they did before.
+///
+/// The audio sink, the redirected drives and smart card backend, the H.264 decoder factory and the last layout
+/// of the monitors are kept. The audio formats and the dynamic channels are negotiated again by the server.
+pub struct ChannelState {
+    audio_sink: Option<Box<dyn AudioSink>>,
+    drives: Vec<(String, Box<dyn FileSystemBackend>, DrivePolicy)>,
+    smart_card_backend: Option<Box<dyn ScardBackend>>,
+    #[cfg(feature = "h264")]
+    avc420_decoder_factory: Option<Avc420DecoderFactory>,
+    monitor_layout: Option<Vec<MonitorConfig>>,
+}
 
-        debug!("Send GFX Capabilities Advertise PDU");
-        transport.encode(
-            DynamicVirtualChannelTransport::prepare_data_to_encode(client_data, Some(dvc_data))?,
-            &mut stream,
-        )?;
+impl ChannelState {
+    /// The layout of the monitors sent on the Display Control channel of the reconnected session.
+    pub fn monitor_layout(&self) -> Option<&[MonitorConfig]> {
+        self.monitor_layout.as_deref()
     }
 
-    Ok(())
+    /// Replaces the layout of the monitors, e.g. when the window has been resized while the connection was lost.
+    pub fn with_monitor_layout(mut self, monitors: Vec<MonitorConfig>) -> Self {
+        self.monitor_layout = Some(monitors);
+        self
+    }
+
+    /// Does not send any layout of the monitors, the reconnected session keeping the resolution it was created with.
+    pub fn without_monitor_layout(mut self) -> Self {
+        self.monitor_layout = None;
+        self
+    }
+}
+
+/// A message received on a static channel, whose MCS and Send Data headers have been decoded.
+struct ChannelMessage {
+    channel_ids: ChannelIdentificators,
+    /// The transport holding the decoded context of the message, from which the handlers create theirs.
+    transport: SendDataContextTransport,
+    data: Vec<u8>,
+}
+
+/// Handles the messages of a joined static channel, owning its state and its transport.
+trait StaticChannelHandler: Send {
+    /// Processes a message of the channel, the responses being written into the output shared by all the channels.
+    fn process_message(
+        &mut self,
+        message: ChannelMessage,
+        output: &mut dyn io::Write,
+        image: &mut dyn ImageSink,
+        hooks: &mut PduHooks,
+        channels: &mut ChannelRegistry,
+    ) -> Result<Option<ActiveStageOutput>, RdpError>;
+
+    /// Allows the processor to reach the handler of a channel by its type, e.g. to configure it.
+    fn as_any(&self) -> &dyn Any;
+
+    fn as_any_mut(&mut self) -> &mut dyn Any;
 }
 
-trait DynamicChannelDataHandler {
-    fn process_complete_data(&mut self, complete_data: Vec<u8>) -> Result<Option<Vec<u8>>, RdpError>;
+struct StaticChannel {
+    /// `None` for the joined channels the client does not handle.
+    handler: Option<Box<dyn StaticChannelHandler>>,
+    /// The messages routed to the channel and not yet processed by its handler.
+    mailbox: VecDeque<ChannelMessage>,
 }
 
-pub struct DynamicChannel {
-    data: CompleteData,
-    channel_id_type: FieldType,
-    channel_id: u32,
-    handler: Box<dyn DynamicChannelDataHandler + Send>,
+/// The joined static channels, by channel ID.
+#[derive(Default)]
+struct StaticChannelSet {
+    channels: HashMap<ChannelId, StaticChannel>,
 }
 
-impl DynamicChannel {
-    fn new(handler: Box<dyn DynamicChannelDataHandler + Send>, channel_id: u32, channel_id_type: FieldType) -> Self {
-        Self {
-            data: CompleteData::new(),
-            handler,
-            channel_id_type,
+impl StaticChannelSet {
+    fn insert(&mut self, channel_id: ChannelId, handler: Option<Box<dyn StaticChannelHandler>>) {
+        self.channels.insert(
             channel_id,
-        }
+            StaticChannel {
+                handler,
+                mailbox: VecDeque::new(),
+            },
+        );
     }
 
-    fn process_data_first_pdu(&mut self, total_data_size: usize, data: Vec<u8>) -> Result<Option<Vec<u8>>, RdpError> {
-        if let Some(complete_data) = self.data.process_data_first_pdu(total_data_size, data) {
-            self.handler.process_complete_data(complete_data)
-        } else {
-            Ok(None)
+    /// Returns the handler of the type, and the ID of its channel.
+    fn get<T: Any>(&self) -> Option<(ChannelId, &T)> {
+        self.channels.iter().find_map(|(channel_id, channel)| {
+            channel
+                .handler
+                .as_ref()
+                .and_then(|handler| handler.as_any().downcast_ref::<T>())
+                .map(|handler| (*channel_id, handler))
+        })
+    }
+
+    fn get_mut<T: Any>(&mut self) -> Option<(ChannelId, &mut T)> {
+        self.channels.iter_mut().find_map(|(channel_id, channel)| {
+            channel
+                .handler
+                .as_mut()
+                .and_then(|handler| handler.as_any_mut().downcast_mut::<T>())
+                .map(|handler| (*channel_id, handler))
+        })
+    }
+
+    fn post(&mut self, message: ChannelMessage) -> Result<(), RdpError> {
+        let channel_id = message.channel_ids.channel_id;
+        let channel = self
+            .channels
+            .get_mut(&channel_id)
+            .ok_or(RdpError::UnexpectedChannel(channel_id))?;
+        if channel.handler.is_none() {
+            return Err(RdpError::UnexpectedChannel(channel_id));
         }
+
+        channel.mailbox.push_back(message);
+
+        Ok(())
     }
 
-    fn process_data_pdu(&mut self, data: Vec<u8>) -> Result<Option<Vec<u8>>, RdpError> {
-        if let Some(complete_data) = self.data.process_data_pdu(data) {
-            self.handler.process_complete_data(complete_data)
-        } else {
-            Ok(None)
+    /// Processes the oldest message of the mailbox of the channel.
+    fn process_mailbox(
+        &mut self,
+        channel_id: ChannelId,
+        output: &mut dyn io::Write,
+        image: &mut dyn ImageSink,
+        hooks: &mut PduHooks,
+        channels: &mut ChannelRegistry,
+    ) -> Result<Option<ActiveStageOutput>, RdpError> {
+        let channel = self
+            .channels
+            .get_mut(&channel_id)
+            .ok_or(RdpError::UnexpectedChannel(channel_id))?;
+
+        match (channel.handler.as_mut(), channel.mailbox.pop_front()) {
+            (Some(handler), Some(message)) => handler.process_message(message, output, image, hooks, channels),
+            _ => Ok(None),
         }
     }
 }
 
-#[derive(Debug, PartialEq)]
-struct CompleteData {
-    total_size: usize,
-    data: Vec<u8>,
+/// Handles the I/O channel, which carries the Share Data PDUs of the session other than the graphics and input.
+#[derive(Default)]
+struct GlobalChannelHandler {
+    transport: Option<ShareDataHeaderTransport>,
+    auto_reconnect: Option<ServerAutoReconnect>,
+    pointer_pdus: Vec<PointerPdu>,
 }
 
-impl CompleteData {
-    fn new() -> Self {
-        Self {
-            total_size: 0,
-            data: Vec::new(),
-        }
+impl StaticChannelHandler for GlobalChannelHandler {
+    fn process_message(
+        &mut self,
+        message: ChannelMessage,
+        _output: &mut dyn io::Write,
+        _image: &mut dyn ImageSink,
+        hooks: &mut PduHooks,
+        _channels: &mut ChannelRegistry,
+    ) -> Result<Option<ActiveStageOutput>, RdpError> {
+        let ChannelIdentificators {
+            initiator_id,
+            channel_id,
+        } = message.channel_ids;
+        let transport = self.transport.get_or_insert_with(|| {
+            ShareDataHeaderTransport::new(ShareControlHeaderTransport::new(
+                message.transport,
+                initiator_id,
+                channel_id,
+            ))
+        });
+
+        process_global_channel_pdu(
+            message.data.as_slice(),
+            transport,
+            channel_id,
+            &mut self.auto_reconnect,
+            &mut self.pointer_pdus,
+            hooks,
+        )
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
     }
 
-    fn process_data_first_pdu(&mut self, total_data_size: usize, data: Vec<u8>) -> Option<Vec<u8>> {
-        if self.total_size != 0 || !self.data.is_empty() {
-            error!("Incomplete DVC message, it will be skipped");
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
 
-            self.data.clear();
+fn process_global_channel_pdu(
+    mut stream: impl io::Read,
+    transport: &mut ShareDataHeaderTransport,
+    channel_id: ChannelId,
+    auto_reconnect: &mut Option<ServerAutoReconnect>,
+    pointer_pdus: &mut Vec<PointerPdu>,
+    hooks: &mut PduHooks,
+) -> Result<Option<ActiveStageOutput>, RdpError> {
+    let share_data_pdu = transport.decode(&mut stream)?;
+    hooks.received(PduChannel::Static(channel_id), share_data_pdu.as_short_name());
+
+    match share_data_pdu {
+        ShareDataPdu::SaveSessionInfo(session_info) => {
+            debug!("Got Session Save Info PDU: {:?}", session_info);
+
+            if let InfoData::LogonExtended(LogonInfoExtended {
+                auto_reconnect: Some(cookie),
+                ..
+            }) = &session_info.info_data
+            {
+                *auto_reconnect = Some(cookie.clone());
+            }
+
+            Ok(session_lock_state(&session_info).map(ActiveStageOutput::SessionLockState))
         }
+        ShareDataPdu::ServerStatusInfo(ServerStatusInfoPdu(status_code)) => {
+            debug!("Got Server Status Info PDU: {}", status_code.description());
 
-        if total_data_size == data.len() {
-            Some(data)
-        } else {
-            self.total_size = total_data_size;
-            self.data = data;
+            Ok(Some(ActiveStageOutput::SessionLockState(SessionLockState::Pending(
+                status_code,
+            ))))
+        }
+        ShareDataPdu::ServerSetErrorInfo(ServerSetErrorInfoPdu(ErrorInfo::ProtocolIndependentCode(
+            ProtocolIndependentCode::None,
+        ))) => {
+            debug!("Received None server error");
 
-            None
+            Ok(None)
         }
-    }
+        ShareDataPdu::SetKeyboardIndicators(indicators) => {
+            debug!("Got Set Keyboard Indicators PDU: {:?}", indicators);
 
-    fn process_data_pdu(&mut self, mut data: Vec<u8>) -> Option<Vec<u8>> {
-        if self.total_size == 0 && self.data.is_empty() {
-            // message is not fragmented
-            Some(data)
-        } else {
-            // message is fragmented so need to reassemble it
-            let actual_data_length = self.data.len() + data.len();
-
-            match actual_data_length.cmp(&(self.total_size)) {
-                cmp::Ordering::Less => {
-                    // this is one of the fragmented messages, just append it
-                    self.data.append(&mut data);
-                    None
-                }
-                cmp::Ordering::Equal => {
-                    // this is the last fragmented message, need to return the whole reassembled message
-                    self.total_size = 0;
-                    self.data.append(&mut data);
-                    Some(self.data.drain(..).collect())
-                }
-                cmp::Ordering::Greater => {
-                    error!("Actual DVC message size is grater than expected total DVC message size");
-                    self.total_size = 0;
-                    self.data.clear();
+            Ok(Some(ActiveStageOutput::KeyboardIndicators(indicators.led_flags)))
+        }
+        ShareDataPdu::SetKeyboardImeStatus(ime_status) => {
+            debug!("Got Set Keyboard IME Status PDU: {:?}", ime_status);
 
-                    None
-                }
-            }
+            Ok(Some(ActiveStageOutput::KeyboardImeStatus(ime_status)))
+        }
+        ShareDataPdu::Pointer(pointer) => {
+            debug!("Got Pointer Update PDU: {:?}", pointer.message_type);
+            pointer_pdus.push(pointer);
+
+            Ok(None)
         }
+        ShareDataPdu::ServerSetErrorInfo(ServerSetErrorInfoPdu(e)) => Err(RdpError::ServerError(e.description())),
+        _ => Err(RdpError::UnexpectedPdu(format!(
+            "Expected Session Save Info PDU, got: {:?}",
+            share_data_pdu.as_short_name()
+        ))),
+    }
+}
+
+fn session_lock_state(session_info: &SaveSessionInfoPdu) -> Option<SessionLockState> {
+    match &session_info.info_data {
+        InfoData::LogonInfoV1(_) | InfoData::LogonInfoV2(_) | InfoData::PlainNotify => Some(SessionLockState::Unlocked),
+        InfoData::LogonExtended(extended) => extended.errors_info.clone().map(SessionLockState::LogonRequired),
     }
 }
@@ -1,21 +1,100 @@
-use ironrdp::{dvc::display::ServerPdu, PduParsing};
+#[cfg(test)]
+mod tests;
+
+use ironrdp::dvc::display::{ClientPdu, Monitor, MonitorFlags, MonitorLayoutPdu, Orientation, ServerPdu};
+use ironrdp::gcc::MonitorOrientation;
+use ironrdp::PduParsing;
 use log::debug;
 
-use super::DynamicChannelDataHandler;
-use crate::RdpError;
+use super::drdynvc::DynamicChannelDataHandler;
+use crate::image::ImageSink;
+use crate::{MonitorConfig, RdpError};
 
-pub struct Handler {}
+/// Handles the Display Control channel, on which the layout of the monitors of the previous connection
+/// to the session is sent again once the server has sent its capabilities.
+pub struct Handler {
+    monitor_layout: Option<Vec<MonitorConfig>>,
+}
 
 impl Handler {
-    pub fn new() -> Self {
-        Self {}
+    pub fn new(monitor_layout: Option<Vec<MonitorConfig>>) -> Self {
+        Self { monitor_layout }
     }
 }
 
 impl DynamicChannelDataHandler for Handler {
-    fn process_complete_data(&mut self, complete_data: Vec<u8>) -> Result<Option<Vec<u8>>, RdpError> {
+    fn process_complete_data(
+        &mut self,
+        complete_data: Vec<u8>,
+        _image: &mut dyn ImageSink,
+    ) -> Result<Option<Vec<u8>>, RdpError> {
         let gfx_pdu = ServerPdu::from_buffer(&mut complete_data.as_slice())?;
         debug!("Got Display PDU: {:?}", gfx_pdu);
-        Ok(None)
+
+        match (gfx_pdu, self.monitor_layout.take()) {
+            (ServerPdu::DisplayControlCaps(_), Some(monitors)) => {
+                let pdu = monitor_layout(&monitors);
+                debug!("Send Display PDU: {:?}", pdu);
+                let mut message = Vec::with_capacity(pdu.buffer_length());
+                pdu.to_buffer(&mut message)?;
+
+                Ok(Some(message))
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+/// Size range of a monitor accepted by the server, the width having to be even as well.
+const MIN_MONITOR_SIZE: u32 = 200;
+const MAX_MONITOR_SIZE: u32 = 8192;
+const MIN_DESKTOP_SCALE_FACTOR: u32 = 100;
+const MAX_DESKTOP_SCALE_FACTOR: u32 = 500;
+
+/// Creates the layout of the monitors, the sizes and the scale factors being clamped to the ranges accepted
+/// by the server. The first monitor is the primary one if none is marked as such.
+pub fn monitor_layout(monitors: &[MonitorConfig]) -> ClientPdu {
+    let has_primary = monitors.iter().any(|monitor| monitor.primary);
+
+    ClientPdu::DisplayControlMonitorLayout(MonitorLayoutPdu {
+        monitors: monitors
+            .iter()
+            .enumerate()
+            .map(|(i, monitor)| {
+                let monitor = MonitorConfig {
+                    scale_factor: monitor
+                        .scale_factor
+                        .clamp(MIN_DESKTOP_SCALE_FACTOR, MAX_DESKTOP_SCALE_FACTOR),
+                    ..monitor.clone()
+                };
+
+                Monitor {
+                    flags: if monitor.primary || (!has_primary && i == 0) {
+                        MonitorFlags::PRIMARY
+                    } else {
+                        MonitorFlags::empty()
+                    },
+                    // the coordinates are signed, although declared as unsigned by the PDU
+                    left: monitor.left as u32,
+                    top: monitor.top as u32,
+                    width: monitor.width.clamp(MIN_MONITOR_SIZE, MAX_MONITOR_SIZE) & !1,
+                    height: monitor.height.clamp(MIN_MONITOR_SIZE, MAX_MONITOR_SIZE),
+                    physical_width: monitor.physical_width,
+                    physical_height: monitor.physical_height,
+                    orientation: orientation(monitor.orientation),
+                    desktop_scale_factor: monitor.scale_factor,
+                    device_scale_factor: monitor.device_scale_factor(),
+                }
+            })
+            .collect(),
+    })
+}
+
+fn orientation(orientation: MonitorOrientation) -> Orientation {
+    match orientation {
+        MonitorOrientation::Landscape => Orientation::Landscape,
+        MonitorOrientation::Portrait => Orientation::Portrait,
+        MonitorOrientation::LandscapeFlipped => Orientation::LandscapeFlipped,
+        MonitorOrientation::PortraitFlipped => Orientation::PortraitFlipped,
     }
 }
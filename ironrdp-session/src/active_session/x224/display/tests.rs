@@ -0,0 +1,49 @@
+use super::*;
+
+fn monitor(pdu: ClientPdu) -> Monitor {
+    let ClientPdu::DisplayControlMonitorLayout(MonitorLayoutPdu { mut monitors }) = pdu;
+    assert_eq!(1, monitors.len());
+
+    monitors.remove(0)
+}
+
+#[test]
+fn monitor_layout_has_even_width() {
+    let monitor = monitor(monitor_layout(&[MonitorConfig::primary(1025, 769)]));
+
+    assert_eq!((1024, 769), (monitor.width, monitor.height));
+    assert_eq!(MonitorFlags::PRIMARY, monitor.flags);
+}
+
+#[test]
+fn monitor_layout_is_clamped_to_size_accepted_by_server() {
+    let monitor = monitor(monitor_layout(&[MonitorConfig::primary(100, 10_000)]));
+
+    assert_eq!((200, 8192), (monitor.width, monitor.height));
+}
+
+#[test]
+fn device_scale_factor_is_closest_accepted_by_server() {
+    let monitor = monitor(monitor_layout(&[
+        MonitorConfig::primary(1024, 768).with_scale_factor(150)
+    ]));
+
+    assert_eq!(150, monitor.desktop_scale_factor);
+    assert_eq!(140, monitor.device_scale_factor);
+}
+
+#[test]
+fn first_monitor_is_primary_if_none_is_marked() {
+    let secondary = MonitorConfig {
+        primary: false,
+        ..MonitorConfig::primary(1280, 1024)
+    };
+    let ClientPdu::DisplayControlMonitorLayout(MonitorLayoutPdu { monitors }) =
+        monitor_layout(&[secondary.clone(), secondary.with_position(-1280, 0)]);
+
+    assert_eq!(
+        vec![MonitorFlags::PRIMARY, MonitorFlags::empty()],
+        monitors.iter().map(|monitor| monitor.flags).collect::<Vec<_>>()
+    );
+    assert_eq!(-1280, monitors[1].left as i32);
+}
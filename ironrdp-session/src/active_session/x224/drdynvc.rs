@@ -0,0 +1,707 @@
+use std::any::Any;
+use std::collections::HashMap;
+use std::time::Instant;
+use std::{cmp, io};
+
+use ironrdp::codecs::rfx::image_processing::PixelFormat;
+use ironrdp::dvc::FieldType;
+use ironrdp::rdp::vc::{dvc, DvcName};
+use ironrdp::{ChannelId, LimitsConfig, PduParsing, Rectangle};
+use log::{debug, error, warn};
+
+use super::super::channels::ChannelRegistry;
+use super::super::coalescing::CoalescingConfig;
+#[cfg(feature = "h264")]
+use super::super::codecs::h264::Avc420DecoderFactory;
+use super::super::frame_pacing::{DecodeTiming, FramePacingConfig};
+use super::super::pdu_hooks::{PduChannel, PduHooks};
+use super::super::{ActiveStageOutput, GraphicsFrameEvent};
+#[cfg(feature = "zgfx")]
+use super::gfx;
+use super::{display, ChannelMessage, StaticChannelHandler};
+use crate::image::{ImageSink, UpdateTracker};
+use crate::transport::{
+    Decoder, DynamicVirtualChannelTransport, Encoder, SendDataContextTransport, StaticVirtualChannelTransport,
+};
+use crate::{GraphicsConfig, MonitorConfig, RdpError};
+
+/// Handles the `drdynvc` static channel, which multiplexes the dynamic channels opened by the server.
+pub struct Handler {
+    transport: Option<DynamicVirtualChannelTransport>,
+    dynamic_channels: HashMap<u32, DynamicChannel>,
+    graphics_config: Option<GraphicsConfig>,
+    decoder_factories: DecoderFactories,
+    pixel_format: PixelFormat,
+    refresh_request: Option<Rectangle>,
+    frame_id: Option<u32>,
+    frame_events: Vec<GraphicsFrameEvent>,
+    ack_coalescing: Option<CoalescingConfig>,
+    frame_pacing: FramePacingConfig,
+    /// The last layout of the monitors sent, or restored from the previous connection to the session.
+    monitor_layout: Option<Vec<MonitorConfig>>,
+    limits: LimitsConfig,
+}
+
+impl Handler {
+    pub fn new(graphics_config: Option<GraphicsConfig>, pixel_format: PixelFormat, limits: LimitsConfig) -> Self {
+        Self {
+            transport: None,
+            dynamic_channels: HashMap::new(),
+            graphics_config,
+            decoder_factories: DecoderFactories::default(),
+            pixel_format,
+            refresh_request: None,
+            frame_id: None,
+            frame_events: Vec::new(),
+            ack_coalescing: None,
+            frame_pacing: FramePacingConfig::default(),
+            monitor_layout: None,
+            limits,
+        }
+    }
+
+    #[cfg(feature = "h264")]
+    pub fn set_avc420_decoder_factory(&mut self, factory: Avc420DecoderFactory) {
+        self.decoder_factories.avc420 = Some(factory);
+    }
+
+    #[cfg(feature = "h264")]
+    pub fn take_avc420_decoder_factory(&mut self) -> Option<Avc420DecoderFactory> {
+        self.decoder_factories.avc420.take()
+    }
+
+    /// Sets the layout of the monitors to be sent once the server has opened the Display Control channel.
+    pub fn set_monitor_layout(&mut self, monitors: Vec<MonitorConfig>) {
+        self.monitor_layout = Some(monitors);
+    }
+
+    pub fn take_monitor_layout(&mut self) -> Option<Vec<MonitorConfig>> {
+        self.monitor_layout.take()
+    }
+
+    /// Returns the area of the output to be refreshed by the server, which the dynamic channel handlers
+    /// have requested since the previous call.
+    pub fn take_refresh_request(&mut self) -> Option<Rectangle> {
+        self.refresh_request.take()
+    }
+
+    /// Returns the ID of the last frame started by the server on the dynamic channels since the previous call.
+    pub fn take_frame_id(&mut self) -> Option<u32> {
+        self.frame_id.take()
+    }
+
+    /// Returns the starts and the ends of the frames received on the dynamic channels since the previous call.
+    pub fn take_frame_events(&mut self) -> Vec<GraphicsFrameEvent> {
+        std::mem::take(&mut self.frame_events)
+    }
+
+    /// Sets the coalescing of the acknowledgements of the Graphics Pipeline opened after the call.
+    pub fn set_ack_coalescing(&mut self, config: CoalescingConfig) {
+        self.ack_coalescing = Some(config);
+    }
+
+    /// Sets the pacing of the frames of the Graphics Pipeline opened after the call.
+    pub fn set_frame_pacing(&mut self, config: FramePacingConfig) {
+        self.frame_pacing = config;
+    }
+
+    /// Returns the decode time of the frames received on the dynamic channels, if it is measured.
+    pub fn decode_timing(&self) -> Option<DecodeTiming> {
+        self.dynamic_channels
+            .values()
+            .find_map(|channel| channel.handler.decode_timing())
+    }
+
+    /// Sends the PDUs held back by the dynamic channel handlers whose interval has elapsed.
+    pub fn send_coalesced(
+        &mut self,
+        mut stream: impl io::Write,
+        now: Instant,
+        hooks: &mut PduHooks,
+    ) -> Result<(), RdpError> {
+        let Some(transport) = self.transport.as_mut() else {
+            return Ok(());
+        };
+
+        for channel in self.dynamic_channels.values_mut() {
+            if let Some(dvc_data) = channel.handler.poll_coalesced(now)? {
+                let client_data = dvc::ClientPdu::Data(dvc::DataPdu {
+                    channel_id_type: channel.channel_id_type,
+                    channel_id: channel.channel_id,
+                    data_size: dvc_data.len(),
+                });
+
+                hooks.sent(PduChannel::Dynamic(channel.channel_id), client_data.as_short_name());
+                transport.encode(
+                    DynamicVirtualChannelTransport::prepare_data_to_encode(client_data, Some(dvc_data))?,
+                    &mut stream,
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn coalescing_deadline(&self) -> Option<Instant> {
+        self.dynamic_channels
+            .values()
+            .filter_map(|channel| channel.handler.coalescing_deadline())
+            .min()
+    }
+
+    /// Sends the layout of the monitors on the Display Control channel,
+    /// for the server to change the resolution of the desktop.
+    pub fn send_monitor_layout(
+        &mut self,
+        stream: impl io::Write,
+        monitors: &[MonitorConfig],
+        hooks: &mut PduHooks,
+        channels: &ChannelRegistry,
+    ) -> Result<(), RdpError> {
+        let pdu = display::monitor_layout(monitors);
+        debug!("Send Display PDU: {:?}", pdu);
+        let mut message = Vec::with_capacity(pdu.buffer_length());
+        pdu.to_buffer(&mut message)?;
+
+        let channel_id = self.send_dynamic(stream, &DvcName::DISPLAY_CONTROL, message, channels)?;
+        hooks.sent(PduChannel::Dynamic(channel_id), "Display Control Monitor Layout PDU");
+        self.monitor_layout = Some(monitors.to_vec());
+
+        Ok(())
+    }
+
+    /// Sends a PDU on the dynamic channel, returning the ID of the channel.
+    /// The upper layers are responsible for encoding the PDU and converting them to message
+    fn send_dynamic(
+        &mut self,
+        mut stream: impl io::Write,
+        channel_name: &DvcName,
+        message: Vec<u8>,
+        channels: &ChannelRegistry,
+    ) -> Result<u32, RdpError> {
+        if let Some(transport) = self.transport.as_mut() {
+            let channel_id = channels
+                .dynamic_channel_id(channel_name)
+                .ok_or_else(|| RdpError::AccessToNonExistingChannelName(channel_name.clone()))?;
+            let channel = self
+                .dynamic_channels
+                .get_mut(&channel_id)
+                .ok_or(RdpError::AccessToNonExistingChannel(channel_id))?;
+            let client_data = dvc::ClientPdu::Data(dvc::DataPdu {
+                channel_id_type: channel.channel_id_type,
+                channel_id: channel.channel_id,
+                data_size: message.len(),
+            });
+
+            transport.encode(
+                DynamicVirtualChannelTransport::prepare_data_to_encode(client_data, Some(message))?,
+                &mut stream,
+            )?;
+
+            Ok(channel_id)
+        } else {
+            Err(RdpError::DynamicVirtualChannelNotConnected)
+        }
+    }
+
+    fn process_dvc_message(
+        &mut self,
+        mut stream: impl io::Read,
+        mut output: impl io::Write,
+        transport: SendDataContextTransport,
+        channel_id: ChannelId,
+        image: &mut dyn ImageSink,
+        hooks: &mut PduHooks,
+        channels: &mut ChannelRegistry,
+    ) -> Result<(), RdpError> {
+        let transport = self.transport.get_or_insert_with(|| {
+            DynamicVirtualChannelTransport::new(StaticVirtualChannelTransport::new(transport), channel_id)
+        });
+
+        let server_pdu = transport.decode(&mut stream)?;
+        let server_pdu_channel = server_pdu_channel(&server_pdu, channel_id);
+        hooks.receiving_on(server_pdu_channel);
+        hooks.received(server_pdu_channel, server_pdu.as_short_name());
+
+        match server_pdu {
+            dvc::ServerPdu::CapabilitiesRequest(caps_request) => {
+                debug!("Got DVC Capabilities Request PDU: {:?}", caps_request);
+                let caps_response = dvc::ClientPdu::CapabilitiesResponse(dvc::CapabilitiesResponsePdu {
+                    version: dvc::CapsVersion::V1,
+                });
+
+                debug!("Send DVC Capabilities Response PDU: {:?}", caps_response);
+                hooks.sent(PduChannel::Static(channel_id), caps_response.as_short_name());
+                transport.encode(
+                    DynamicVirtualChannelTransport::prepare_data_to_encode(caps_response, None)?,
+                    &mut output,
+                )?;
+            }
+            dvc::ServerPdu::CreateRequest(create_request) => {
+                debug!("Got DVC Create Request PDU: {:?}", create_request);
+                channels.dynamic_channel_requested(create_request.channel_id, &create_request.channel_name);
+
+                let is_limit_reached = !self.dynamic_channels.contains_key(&create_request.channel_id)
+                    && self.dynamic_channels.len() >= self.limits.max_channel_count;
+
+                let dynamic_channel = match DvcName::new(create_request.channel_name.as_str()) {
+                    Ok(_) if is_limit_reached => {
+                        warn!(
+                            "Rejecting the {} DVC: {} dynamic channels are already opened",
+                            create_request.channel_name,
+                            self.dynamic_channels.len()
+                        );
+                        None
+                    }
+                    Ok(channel_name) => create_dvc(
+                        &channel_name,
+                        create_request.channel_id,
+                        create_request.channel_id_type,
+                        &mut self.decoder_factories,
+                        self.pixel_format,
+                        self.ack_coalescing,
+                        self.frame_pacing,
+                        self.monitor_layout.as_deref(),
+                    )
+                    .map(|dynamic_channel| (channel_name, dynamic_channel)),
+                    Err(e) => {
+                        error!("Invalid DVC name: {}", e);
+                        None
+                    }
+                };
+
+                let creation_status = if let Some((channel_name, dynamic_channel)) = dynamic_channel {
+                    self.dynamic_channels.insert(create_request.channel_id, dynamic_channel);
+                    channels.dynamic_channel_opened(create_request.channel_id, channel_name);
+
+                    dvc::DVC_CREATION_STATUS_OK
+                } else {
+                    channels.dynamic_channel_rejected(create_request.channel_id);
+
+                    dvc::DVC_CREATION_STATUS_NO_LISTENER
+                };
+
+                let create_response = dvc::ClientPdu::CreateResponse(dvc::CreateResponsePdu {
+                    channel_id_type: create_request.channel_id_type,
+                    channel_id: create_request.channel_id,
+                    creation_status,
+                });
+
+                debug!("Send DVC Create Response PDU: {:?}", create_response);
+                hooks.sent(
+                    PduChannel::Dynamic(create_request.channel_id),
+                    create_response.as_short_name(),
+                );
+                transport.encode(
+                    DynamicVirtualChannelTransport::prepare_data_to_encode(create_response, None)?,
+                    &mut output,
+                )?;
+
+                negotiate_dvc(
+                    &create_request,
+                    transport,
+                    &mut output,
+                    &self.graphics_config,
+                    &self.decoder_factories,
+                    hooks,
+                )?;
+            }
+            dvc::ServerPdu::CloseRequest(close_request) => {
+                debug!("Got DVC Close Request PDU: {:?}", close_request);
+
+                let close_response = dvc::ClientPdu::CloseResponse(dvc::ClosePdu {
+                    channel_id_type: close_request.channel_id_type,
+                    channel_id: close_request.channel_id,
+                });
+
+                debug!("Send DVC Close Response PDU: {:?}", close_response);
+                hooks.sent(
+                    PduChannel::Dynamic(close_request.channel_id),
+                    close_response.as_short_name(),
+                );
+                transport.encode(
+                    DynamicVirtualChannelTransport::prepare_data_to_encode(close_response, None)?,
+                    &mut output,
+                )?;
+
+                self.dynamic_channels.remove(&close_request.channel_id);
+                channels.dynamic_channel_closed(close_request.channel_id);
+            }
+            dvc::ServerPdu::DataFirst(data) => {
+                if data.total_data_size as usize > self.limits.max_dvc_reassembly_size {
+                    return Err(RdpError::LimitExceeded(format!(
+                        "DVC message of {} bytes is larger than {}",
+                        data.total_data_size, self.limits.max_dvc_reassembly_size
+                    )));
+                }
+
+                let channel_id_type = data.channel_id_type;
+                let channel_id = data.channel_id;
+                let mut data_buff = vec![0; data.data_size];
+                stream.read_exact(&mut data_buff)?;
+
+                if let Some(dvc_data) = self
+                    .dynamic_channels
+                    .get_mut(&data.channel_id)
+                    .ok_or(RdpError::AccessToNonExistingChannel(data.channel_id))?
+                    .process_data_first_pdu(data.total_data_size as usize, data_buff, image)?
+                {
+                    let client_data = dvc::ClientPdu::Data(dvc::DataPdu {
+                        channel_id_type,
+                        channel_id,
+                        data_size: dvc_data.len(),
+                    });
+
+                    hooks.sent(PduChannel::Dynamic(channel_id), client_data.as_short_name());
+                    transport.encode(
+                        DynamicVirtualChannelTransport::prepare_data_to_encode(client_data, Some(dvc_data))?,
+                        &mut output,
+                    )?;
+                }
+            }
+            dvc::ServerPdu::Data(data) => {
+                let channel_id_type = data.channel_id_type;
+                let channel_id = data.channel_id;
+                let mut data_buff = vec![0; data.data_size];
+                stream.read_exact(&mut data_buff)?;
+
+                if let Some(dvc_data) = self
+                    .dynamic_channels
+                    .get_mut(&data.channel_id)
+                    .ok_or(RdpError::AccessToNonExistingChannel(data.channel_id))?
+                    .process_data_pdu(data_buff, image)?
+                {
+                    let client_data = dvc::ClientPdu::Data(dvc::DataPdu {
+                        channel_id_type,
+                        channel_id,
+                        data_size: dvc_data.len(),
+                    });
+
+                    hooks.sent(PduChannel::Dynamic(channel_id), client_data.as_short_name());
+                    transport.encode(
+                        DynamicVirtualChannelTransport::prepare_data_to_encode(client_data, Some(dvc_data))?,
+                        &mut output,
+                    )?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl StaticChannelHandler for Handler {
+    fn process_message(
+        &mut self,
+        message: ChannelMessage,
+        output: &mut dyn io::Write,
+        image: &mut dyn ImageSink,
+        hooks: &mut PduHooks,
+        channels: &mut ChannelRegistry,
+    ) -> Result<Option<ActiveStageOutput>, RdpError> {
+        let mut image = UpdateTracker::new(image);
+        self.process_dvc_message(
+            message.data.as_slice(),
+            output,
+            message.transport,
+            message.channel_ids.channel_id,
+            &mut image,
+            hooks,
+            channels,
+        )?;
+
+        for channel in self.dynamic_channels.values_mut() {
+            if let Some(area) = channel.handler.take_refresh_request() {
+                self.refresh_request = Some(match self.refresh_request.take() {
+                    Some(refresh_request) => refresh_request.union(&area),
+                    None => area,
+                });
+            }
+
+            if let Some(frame_id) = channel.handler.take_frame_id() {
+                self.frame_id = Some(frame_id);
+            }
+
+            self.frame_events.extend(channel.handler.take_frame_events());
+        }
+
+        Ok(image.update_region().map(ActiveStageOutput::GraphicsUpdate))
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+fn server_pdu_channel(server_pdu: &dvc::ServerPdu, drdynvc_channel_id: ChannelId) -> PduChannel {
+    match server_pdu {
+        dvc::ServerPdu::CapabilitiesRequest(_) => PduChannel::Static(drdynvc_channel_id),
+        dvc::ServerPdu::CreateRequest(create_request) => PduChannel::Dynamic(create_request.channel_id),
+        dvc::ServerPdu::DataFirst(data) => PduChannel::Dynamic(data.channel_id),
+        dvc::ServerPdu::Data(data) => PduChannel::Dynamic(data.channel_id),
+        dvc::ServerPdu::CloseRequest(close_request) => PduChannel::Dynamic(close_request.channel_id),
+    }
+}
+
+fn create_dvc(
+    channel_name: &DvcName,
+    channel_id: u32,
+    channel_id_type: FieldType,
+    decoder_factories: &mut DecoderFactories,
+    pixel_format: PixelFormat,
+    ack_coalescing: Option<CoalescingConfig>,
+    frame_pacing: FramePacingConfig,
+    monitor_layout: Option<&[MonitorConfig]>,
+) -> Option<DynamicChannel> {
+    let handler: Box<dyn DynamicChannelDataHandler> = if *channel_name == DvcName::GRAPHICS_PIPELINE {
+        create_graphics_pipeline_handler(decoder_factories, pixel_format, ack_coalescing, frame_pacing)?
+    } else if *channel_name == DvcName::DISPLAY_CONTROL {
+        Box::new(display::Handler::new(monitor_layout.map(<[MonitorConfig]>::to_vec)))
+    } else {
+        error!("Unknown channel name: {}", channel_name);
+        return None;
+    };
+
+    Some(DynamicChannel::new(handler, channel_id, channel_id_type))
+}
+
+#[cfg(feature = "zgfx")]
+#[cfg_attr(not(feature = "h264"), allow(unused_variables))]
+fn create_graphics_pipeline_handler(
+    decoder_factories: &mut DecoderFactories,
+    pixel_format: PixelFormat,
+    ack_coalescing: Option<CoalescingConfig>,
+    frame_pacing: FramePacingConfig,
+) -> Option<Box<dyn DynamicChannelDataHandler>> {
+    let handler = gfx::Handler::new()
+        .with_ack_coalescing(ack_coalescing)
+        .with_frame_pacing(frame_pacing)
+        .with_pixel_format(pixel_format);
+
+    #[cfg(feature = "h264")]
+    let handler = match decoder_factories.avc420.as_mut() {
+        Some(factory) => handler.with_avc420_decoder(factory(), pixel_format),
+        None => handler,
+    };
+
+    Some(Box::new(handler))
+}
+
+#[cfg(not(feature = "zgfx"))]
+fn create_graphics_pipeline_handler(
+    _decoder_factories: &mut DecoderFactories,
+    _pixel_format: PixelFormat,
+    _ack_coalescing: Option<CoalescingConfig>,
+    _frame_pacing: FramePacingConfig,
+) -> Option<Box<dyn DynamicChannelDataHandler>> {
+    error!("The Graphics Pipeline requires the zgfx feature");
+    None
+}
+
+#[cfg_attr(not(feature = "zgfx"), allow(unused_variables, unused_mut))]
+fn negotiate_dvc(
+    create_request: &dvc::CreateRequestPdu,
+    transport: &mut DynamicVirtualChannelTransport,
+    mut stream: impl io::Write,
+    graphics_config: &Option<GraphicsConfig>,
+    decoder_factories: &DecoderFactories,
+    hooks: &mut PduHooks,
+) -> Result<(), RdpError> {
+    #[cfg(feature = "zgfx")]
+    if DvcName::GRAPHICS_PIPELINE == create_request.channel_name.as_str() {
+        let dvc_data = gfx::create_capabilities_advertise(&decoder_factories.restrict(graphics_config))?;
+        let client_data = dvc::ClientPdu::Data(dvc::DataPdu {
+            channel_id_type: create_request.channel_id_type,
+            channel_id: create_request.channel_id,
+            data_size: dvc_data.len(),
+        });
+
+        debug!("Send GFX Capabilities Advertise PDU");
+        hooks.sent(
+            PduChannel::Dynamic(create_request.channel_id),
+            client_data.as_short_name(),
+        );
+        transport.encode(
+            DynamicVirtualChannelTransport::prepare_data_to_encode(client_data, Some(dvc_data))?,
+            &mut stream,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Creates the decoders of the Graphics Pipeline codecs which are provided by the embedder.
+#[derive(Default)]
+struct DecoderFactories {
+    #[cfg(feature = "h264")]
+    avc420: Option<Avc420DecoderFactory>,
+}
+
+impl DecoderFactories {
+    /// Disables the codecs which cannot be decoded for lack of decoder.
+    #[cfg_attr(not(feature = "zgfx"), allow(dead_code))]
+    fn restrict(&self, graphics_config: &Option<GraphicsConfig>) -> Option<GraphicsConfig> {
+        #[cfg_attr(not(feature = "h264"), allow(unused_mut))]
+        let mut graphics_config = graphics_config.clone();
+
+        #[cfg(feature = "h264")]
+        if let Some(config) = graphics_config.as_mut().filter(|_| self.avc420.is_none()) {
+            if config.h264 || config.avc444 {
+                info!("No H.264 decoder has been provided, AVC420 and AVC444 are not advertised");
+            }
+
+            config.h264 = false;
+            config.avc444 = false;
+        }
+
+        graphics_config
+    }
+}
+
+pub trait DynamicChannelDataHandler: Send {
+    fn process_complete_data(
+        &mut self,
+        complete_data: Vec<u8>,
+        image: &mut dyn ImageSink,
+    ) -> Result<Option<Vec<u8>>, RdpError>;
+
+    /// Returns the area of the output the handler needs the server to send again, e.g. after a decoder
+    /// has lost the pictures it predicts the next ones from.
+    fn take_refresh_request(&mut self) -> Option<Rectangle> {
+        None
+    }
+
+    /// Returns the ID of the last frame started by the server since the previous call,
+    /// for the channels whose updates are delimited by frames.
+    fn take_frame_id(&mut self) -> Option<u32> {
+        None
+    }
+
+    /// Returns the starts and the ends of the frames since the previous call, for the channels
+    /// whose updates are delimited by frames.
+    fn take_frame_events(&mut self) -> Vec<GraphicsFrameEvent> {
+        Vec::new()
+    }
+
+    /// Returns the data held back by the handler to be sent once the interval has elapsed, if it has.
+    fn poll_coalesced(&mut self, _now: Instant) -> Result<Option<Vec<u8>>, RdpError> {
+        Ok(None)
+    }
+
+    /// Returns the instant at which the data held back by the handler, if any, is due to be sent.
+    fn coalescing_deadline(&self) -> Option<Instant> {
+        None
+    }
+
+    /// Returns the decode time of the frames, if it is measured, for the channels whose updates
+    /// are delimited by frames.
+    fn decode_timing(&self) -> Option<DecodeTiming> {
+        None
+    }
+}
+
+pub struct DynamicChannel {
+    data: CompleteData,
+    channel_id_type: FieldType,
+    channel_id: u32,
+    handler: Box<dyn DynamicChannelDataHandler>,
+}
+
+impl DynamicChannel {
+    fn new(handler: Box<dyn DynamicChannelDataHandler>, channel_id: u32, channel_id_type: FieldType) -> Self {
+        Self {
+            data: CompleteData::new(),
+            handler,
+            channel_id_type,
+            channel_id,
+        }
+    }
+
+    fn process_data_first_pdu(
+        &mut self,
+        total_data_size: usize,
+        data: Vec<u8>,
+        image: &mut dyn ImageSink,
+    ) -> Result<Option<Vec<u8>>, RdpError> {
+        if let Some(complete_data) = self.data.process_data_first_pdu(total_data_size, data) {
+            self.handler.process_complete_data(complete_data, image)
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn process_data_pdu(&mut self, data: Vec<u8>, image: &mut dyn ImageSink) -> Result<Option<Vec<u8>>, RdpError> {
+        if let Some(complete_data) = self.data.process_data_pdu(data) {
+            self.handler.process_complete_data(complete_data, image)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct CompleteData {
+    total_size: usize,
+    data: Vec<u8>,
+}
+
+impl CompleteData {
+    fn new() -> Self {
+        Self {
+            total_size: 0,
+            data: Vec::new(),
+        }
+    }
+
+    fn process_data_first_pdu(&mut self, total_data_size: usize, data: Vec<u8>) -> Option<Vec<u8>> {
+        if self.total_size != 0 || !self.data.is_empty() {
+            error!("Incomplete DVC message, it will be skipped");
+
+            self.data.clear();
+        }
+
+        if total_data_size == data.len() {
+            Some(data)
+        } else {
+            self.total_size = total_data_size;
+            self.data = data;
+
+            None
+        }
+    }
+
+    fn process_data_pdu(&mut self, mut data: Vec<u8>) -> Option<Vec<u8>> {
+        if self.total_size == 0 && self.data.is_empty() {
+            // message is not fragmented
+            Some(data)
+        } else {
+            // message is fragmented so need to reassemble it
+            let actual_data_length = self.data.len() + data.len();
+
+            match actual_data_length.cmp(&(self.total_size)) {
+                cmp::Ordering::Less => {
+                    // this is one of the fragmented messages, just append it
+                    self.data.append(&mut data);
+                    None
+                }
+                cmp::Ordering::Equal => {
+                    // this is the last fragmented message, need to return the whole reassembled message
+                    self.total_size = 0;
+                    self.data.append(&mut data);
+                    Some(self.data.drain(..).collect())
+                }
+                cmp::Ordering::Greater => {
+                    error!("Actual DVC message size is grater than expected total DVC message size");
+                    self.total_size = 0;
+                    self.data.clear();
+
+                    None
+                }
+            }
+        }
+    }
+}
@@ -1,21 +1,45 @@
+#[cfg(test)]
+mod tests;
+
+use std::collections::HashMap;
+use std::time::Instant;
+
 use bitflags::bitflags;
+use ironrdp::codecs::rfx::image_processing::PixelFormat;
+#[cfg(feature = "h264")]
+use ironrdp::dvc::gfx::{Avc420BitmapStream, Avc444BitmapStream, GraphicsPipelineError};
+#[cfg(feature = "h264")]
+use ironrdp::PduBufferParsing;
 use ironrdp::{
     dvc::gfx::{
         zgfx, CapabilitiesAdvertisePdu, CapabilitiesV103Flags, CapabilitiesV104Flags, CapabilitiesV107Flags,
-        CapabilitiesV10Flags, CapabilitiesV81Flags, CapabilitiesV8Flags, CapabilitySet, ClientPdu, FrameAcknowledgePdu,
-        QueueDepth, ServerPdu,
+        CapabilitiesV10Flags, CapabilitiesV81Flags, CapabilitiesV8Flags, CapabilitySet, ClientPdu, Codec1Type,
+        Codec2Type, CreateSurfacePdu, FrameAcknowledgePdu, MapSurfaceToOutputPdu, QueueDepth, ServerPdu, StartFramePdu,
+        WireToSurface1Pdu, WireToSurface2Pdu,
     },
-    PduParsing,
+    PduParsing, Rectangle,
 };
-use log::debug;
+use log::{debug, warn};
 
-use super::DynamicChannelDataHandler;
+use super::drdynvc::DynamicChannelDataHandler;
+use crate::active_session::coalescing::{Coalescer, CoalescingConfig};
+#[cfg(feature = "h264")]
+use crate::active_session::codecs::h264::{self, Avc420Decoder};
+use crate::active_session::codecs::{clearcodec, planar, progressive};
+use crate::active_session::frame_pacing::{DecodeTiming, FramePacer, FramePacingConfig};
+use crate::active_session::GraphicsFrameEvent;
+use crate::image::ImageSink;
 use crate::{GraphicsConfig, RdpError};
 
 pub struct Handler {
     decompressor: zgfx::Decompressor,
     decompressed_buffer: Vec<u8>,
-    frames_decoded: u32,
+    pipeline: PipelineState,
+    decoders: SurfaceDecoders,
+    refresh_request: Option<Rectangle>,
+    frame_id: Option<u32>,
+    frame_acknowledge: Coalescer<FrameAcknowledgePdu>,
+    frame_pacer: FramePacer,
 }
 
 impl Handler {
@@ -23,14 +47,58 @@ impl Handler {
         Self {
             decompressor: zgfx::Decompressor::new(),
             decompressed_buffer: Vec::with_capacity(1024 * 16),
-            frames_decoded: 0,
+            pipeline: PipelineState::default(),
+            decoders: SurfaceDecoders::default(),
+            refresh_request: None,
+            frame_id: None,
+            frame_acknowledge: Coalescer::new(None),
+            frame_pacer: FramePacer::new(FramePacingConfig::default()),
         }
     }
+
+    /// Holds back the Frame Acknowledge PDUs sent before the interval has elapsed, only the last one,
+    /// which carries the total of the frames decoded, being sent.
+    pub fn with_ack_coalescing(mut self, config: Option<CoalescingConfig>) -> Self {
+        self.frame_acknowledge = Coalescer::new(config);
+
+        self
+    }
+
+    /// Sets the queue depth reported in the Frame Acknowledge PDUs and whether the decode time of the frames
+    /// is measured. The acknowledgements are suspended by default.
+    pub fn with_frame_pacing(mut self, config: FramePacingConfig) -> Self {
+        self.frame_pacer = FramePacer::new(config);
+
+        self
+    }
+
+    /// Sets the pixel format of the ClearCodec, planar and RemoteFX Progressive surfaces passed to the image sink,
+    /// `BgrX32` by default.
+    pub fn with_pixel_format(mut self, pixel_format: PixelFormat) -> Self {
+        self.decoders.clearcodec = clearcodec::DecodingContext::new().with_pixel_format(pixel_format);
+        self.decoders.planar = planar::DecodingContext::new().with_pixel_format(pixel_format);
+        self.decoders.progressive = progressive::DecodingContext::new().with_pixel_format(pixel_format);
+
+        self
+    }
+
+    #[cfg(feature = "h264")]
+    pub fn with_avc420_decoder(mut self, decoder: Box<dyn Avc420Decoder>, pixel_format: PixelFormat) -> Self {
+        self.decoders.avc420 = Some(h264::DecodingContext::new(decoder).with_pixel_format(pixel_format));
+
+        self
+    }
 }
 
 impl DynamicChannelDataHandler for Handler {
-    fn process_complete_data(&mut self, complete_data: Vec<u8>) -> Result<Option<Vec<u8>>, RdpError> {
+    fn process_complete_data(
+        &mut self,
+        complete_data: Vec<u8>,
+        image: &mut dyn ImageSink,
+    ) -> Result<Option<Vec<u8>>, RdpError> {
         let mut client_pdu_buffer: Vec<u8> = vec![];
+        // the clock is only read if the decode time is measured, as it is not available on `wasm32-unknown-unknown`
+        let mut decode_started = self.frame_pacer.is_timing_enabled().then(Instant::now);
         self.decompressed_buffer.clear();
         self.decompressor
             .decompress(complete_data.as_slice(), &mut self.decompressed_buffer)?;
@@ -39,20 +107,51 @@ impl DynamicChannelDataHandler for Handler {
             let gfx_pdu = ServerPdu::from_buffer(&mut slice)?;
             debug!("Got GFX PDU: {:?}", gfx_pdu);
 
-            if let ServerPdu::EndFrame(end_frame_pdu) = gfx_pdu {
-                self.frames_decoded += 1;
-                // Enqueue an acknowledge for every end frame
-                let client_pdu = ClientPdu::FrameAcknowledge(FrameAcknowledgePdu {
-                    queue_depth: QueueDepth::Suspend,
-                    frame_id: end_frame_pdu.frame_id,
-                    total_frames_decoded: self.frames_decoded,
-                });
-                debug!("Sending GFX PDU: {:?}", client_pdu);
-                client_pdu_buffer.reserve(client_pdu_buffer.len() + client_pdu.buffer_length());
-                client_pdu.to_buffer(&mut client_pdu_buffer)?;
-            } else {
-                // Handle the normal PDU
+            let frame_acknowledge = self.pipeline.process_pdu(&gfx_pdu);
+            self.decoders.forget_surface(&gfx_pdu);
+            if let ServerPdu::StartFrame(pdu) = &gfx_pdu {
+                self.frame_id = Some(pdu.frame_id);
             }
+            if self.pipeline.take_frames_lost() {
+                self.decoders.invalidate_references();
+            }
+
+            if let ServerPdu::ResetGraphics(pdu) = &gfx_pdu {
+                image.resize(pdu.width, pdu.height)?;
+            }
+
+            if let ServerPdu::WireToSurface1(pdu) = &gfx_pdu {
+                self.decoders.decode_wire_to_surface_1(&self.pipeline, image, pdu)?;
+            }
+
+            if let ServerPdu::WireToSurface2(pdu) = &gfx_pdu {
+                self.decoders.decode_wire_to_surface_2(&self.pipeline, image, pdu)?;
+            }
+
+            // Enqueue an acknowledge for every end frame, unless it is held back
+            if let Some(frame_acknowledge) = frame_acknowledge {
+                if let Some(started) = decode_started.as_mut() {
+                    let now = Instant::now();
+                    self.frame_pacer.record(now - *started);
+                    *started = now;
+                }
+
+                let frame_acknowledge = FrameAcknowledgePdu {
+                    queue_depth: self.frame_pacer.end_frame(),
+                    ..frame_acknowledge
+                };
+                if let Some(frame_acknowledge) = self.frame_acknowledge.push(frame_acknowledge) {
+                    encode_frame_acknowledge(frame_acknowledge, &mut client_pdu_buffer)?;
+                }
+            }
+        }
+
+        if let Some(started) = decode_started {
+            self.frame_pacer.record(started.elapsed());
+        }
+
+        if self.decoders.take_refresh_request() {
+            self.refresh_request = self.pipeline.output_rectangle();
         }
 
         if !client_pdu_buffer.is_empty() {
@@ -61,6 +160,373 @@ impl DynamicChannelDataHandler for Handler {
 
         Ok(None)
     }
+
+    fn take_refresh_request(&mut self) -> Option<Rectangle> {
+        self.refresh_request.take()
+    }
+
+    fn take_frame_id(&mut self) -> Option<u32> {
+        self.frame_id.take()
+    }
+
+    fn take_frame_events(&mut self) -> Vec<GraphicsFrameEvent> {
+        self.pipeline.take_frame_events()
+    }
+
+    fn poll_coalesced(&mut self, now: Instant) -> Result<Option<Vec<u8>>, RdpError> {
+        match self.frame_acknowledge.poll(now) {
+            Some(frame_acknowledge) => {
+                let mut client_pdu_buffer = Vec::new();
+                encode_frame_acknowledge(frame_acknowledge, &mut client_pdu_buffer)?;
+
+                Ok(Some(client_pdu_buffer))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn coalescing_deadline(&self) -> Option<Instant> {
+        self.frame_acknowledge.deadline()
+    }
+
+    fn decode_timing(&self) -> Option<DecodeTiming> {
+        self.frame_pacer.timing()
+    }
+}
+
+fn encode_frame_acknowledge(
+    frame_acknowledge: FrameAcknowledgePdu,
+    client_pdu_buffer: &mut Vec<u8>,
+) -> Result<(), RdpError> {
+    let client_pdu = ClientPdu::FrameAcknowledge(frame_acknowledge);
+    debug!("Sending GFX PDU: {:?}", client_pdu);
+    client_pdu_buffer.reserve(client_pdu.buffer_length());
+    client_pdu.to_buffer(client_pdu_buffer)?;
+
+    Ok(())
+}
+
+/// Decoders of the surface updates, some of them being provided by the embedder.
+#[derive(Default)]
+struct SurfaceDecoders {
+    clearcodec: clearcodec::DecodingContext,
+    planar: planar::DecodingContext,
+    progressive: progressive::DecodingContext,
+    #[cfg(feature = "h264")]
+    avc420: Option<h264::DecodingContext>,
+}
+
+impl SurfaceDecoders {
+    fn invalidate_references(&mut self) {
+        #[cfg(feature = "h264")]
+        {
+            if let Some(context) = self.avc420.as_mut() {
+                context.invalidate_references();
+            }
+        }
+    }
+
+    /// Drops the state kept by the decoders for the surface which is created again or deleted,
+    /// or whose encoding context is deleted.
+    fn forget_surface(&mut self, pdu: &ServerPdu) {
+        match pdu {
+            ServerPdu::CreateSurface(pdu) => self.progressive.delete_surface(pdu.surface_id),
+            ServerPdu::DeleteSurface(pdu) => self.progressive.delete_surface(pdu.surface_id),
+            ServerPdu::DeleteEncodingContext(pdu) => self.progressive.delete_surface(pdu.surface_id),
+            _ => (),
+        }
+    }
+
+    /// Returns `true` if a decoder has lost the pictures it predicts the next ones from,
+    /// and needs the server to send the whole output again.
+    fn take_refresh_request(&mut self) -> bool {
+        #[cfg(feature = "h264")]
+        {
+            if let Some(context) = self.avc420.as_mut() {
+                return context.take_refresh_request();
+            }
+        }
+
+        false
+    }
+
+    fn decode_wire_to_surface_1(
+        &mut self,
+        pipeline: &PipelineState,
+        image: &mut dyn ImageSink,
+        pdu: &WireToSurface1Pdu,
+    ) -> Result<(), RdpError> {
+        match pdu.codec_id {
+            // decoded even if the surface is not mapped to the output, for the caches to be kept up to date
+            Codec1Type::ClearCodec => {
+                let output_origin = pipeline.output_origin(pdu.surface_id);
+                self.clearcodec
+                    .decode(image, &pdu.bitmap_data, &pdu.destination_rectangle, output_origin)?;
+            }
+            Codec1Type::Planar => {
+                let output_origin = match pipeline.output_origin(pdu.surface_id) {
+                    Some(output_origin) => output_origin,
+                    None => {
+                        debug!(
+                            "Surface {} is not mapped to the output, dropping its {:?} update",
+                            pdu.surface_id, pdu.codec_id
+                        );
+                        return Ok(());
+                    }
+                };
+
+                self.planar
+                    .decode(image, &pdu.bitmap_data, &pdu.destination_rectangle, output_origin)?;
+            }
+            #[cfg(feature = "h264")]
+            Codec1Type::Avc420 | Codec1Type::Avc444 | Codec1Type::Avc444v2 => {
+                let context = match self.avc420.as_mut() {
+                    Some(context) => context,
+                    None => {
+                        warn!("Got {:?} update without H.264 decoder, dropping it", pdu.codec_id);
+                        return Ok(());
+                    }
+                };
+
+                let output_origin = match pipeline.output_origin(pdu.surface_id) {
+                    Some(output_origin) => output_origin,
+                    None => {
+                        debug!(
+                            "Surface {} is not mapped to the output, dropping its {:?} update",
+                            pdu.surface_id, pdu.codec_id
+                        );
+                        return Ok(());
+                    }
+                };
+
+                if pdu.codec_id == Codec1Type::Avc420 {
+                    let stream = Avc420BitmapStream::from_buffer_consume(&mut pdu.bitmap_data.as_slice())
+                        .map_err(GraphicsPipelineError::from)?;
+                    let decoded = context.decode(image, &stream, &pdu.destination_rectangle, output_origin);
+                    recover_from_lost_references(context, decoded)?;
+                } else {
+                    let layout = if pdu.codec_id == Codec1Type::Avc444 {
+                        h264::AuxiliaryViewLayout::Avc444
+                    } else {
+                        h264::AuxiliaryViewLayout::Avc444v2
+                    };
+                    let stream = Avc444BitmapStream::from_buffer_consume(&mut pdu.bitmap_data.as_slice())
+                        .map_err(GraphicsPipelineError::from)?;
+                    let decoded =
+                        context.decode_avc444(image, &stream, layout, &pdu.destination_rectangle, output_origin);
+                    recover_from_lost_references(context, decoded)?;
+                }
+            }
+            #[cfg(not(feature = "h264"))]
+            Codec1Type::Avc420 | Codec1Type::Avc444 | Codec1Type::Avc444v2 => {
+                return Err(RdpError::CodecNotCompiledIn(crate::Codec::H264))
+            }
+            codec_id => debug!(
+                "Dropping {:?} update of surface {}, the codec is not supported",
+                codec_id, pdu.surface_id
+            ),
+        }
+
+        Ok(())
+    }
+
+    fn decode_wire_to_surface_2(
+        &mut self,
+        pipeline: &PipelineState,
+        image: &mut dyn ImageSink,
+        pdu: &WireToSurface2Pdu,
+    ) -> Result<(), RdpError> {
+        match pdu.codec_id {
+            // decoded even if the surface is not mapped to the output, for its tiles to be upgraded later
+            Codec2Type::RemoteFxProgressive => {
+                let output_origin = pipeline.output_origin(pdu.surface_id);
+                self.progressive
+                    .decode(image, pdu.surface_id, &pdu.bitmap_data, output_origin)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Keeps the session alive when the picture could not be decoded, the decoder dropping the next pictures
+/// until the refresh requested to the server is received. The other errors, e.g. of the image sink, are returned.
+#[cfg(feature = "h264")]
+fn recover_from_lost_references(
+    context: &h264::DecodingContext,
+    decoded: Result<Option<Rectangle>, RdpError>,
+) -> Result<(), RdpError> {
+    match decoded {
+        Ok(_) => Ok(()),
+        Err(e) if context.references_lost() => {
+            warn!("Failed to decode H.264 picture: {}", e);
+
+            Ok(())
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Lifetime of the surfaces and frames of the graphics pipeline.
+///
+/// The server reuses the id of a deleted surface and the frame ids wrap around after `u32::MAX`,
+/// so neither is assumed to be unique over a session nor to be increasing. Each created surface
+/// is given a serial number which is never reused.
+#[derive(Debug, Default)]
+struct PipelineState {
+    surface_serials: HashMap<u16, u64>,
+    output_origins: HashMap<u16, (u32, u32)>,
+    output_size: Option<(u32, u32)>,
+    next_surface_serial: u64,
+    current_frame_id: Option<u32>,
+    last_frame_id: Option<u32>,
+    frames_decoded: u32,
+    frames_lost: bool,
+    frame_events: Vec<GraphicsFrameEvent>,
+}
+
+impl PipelineState {
+    /// Returns the acknowledge to send when the PDU ends a frame.
+    fn process_pdu(&mut self, pdu: &ServerPdu) -> Option<FrameAcknowledgePdu> {
+        match pdu {
+            ServerPdu::CreateSurface(pdu) => self.create_surface(pdu),
+            ServerPdu::DeleteSurface(pdu) => {
+                if self.surface_serials.remove(&pdu.surface_id).is_none() {
+                    warn!("Got Delete Surface PDU for unknown surface {}", pdu.surface_id);
+                }
+                self.output_origins.remove(&pdu.surface_id);
+            }
+            ServerPdu::MapSurfaceToOutput(pdu) => self.map_surface_to_output(pdu),
+            ServerPdu::ResetGraphics(pdu) => {
+                self.output_size = Some((pdu.width, pdu.height));
+            }
+            ServerPdu::StartFrame(pdu) => self.start_frame(pdu),
+            ServerPdu::EndFrame(pdu) => return Some(self.end_frame(pdu.frame_id)),
+            pdu => {
+                for surface_id in targeted_surfaces(pdu) {
+                    if !self.surface_serials.contains_key(&surface_id) {
+                        warn!("Got GFX PDU for unknown surface {}: {:?}", surface_id, pdu);
+                    }
+                }
+            }
+        }
+
+        None
+    }
+
+    fn create_surface(&mut self, pdu: &CreateSurfacePdu) {
+        let serial = self.next_surface_serial;
+        self.next_surface_serial += 1;
+
+        // A surface is not displayed until it is mapped to the output
+        self.output_origins.remove(&pdu.surface_id);
+
+        if let Some(previous_serial) = self.surface_serials.insert(pdu.surface_id, serial) {
+            warn!(
+                "Surface {} has been created again without being deleted, discarding surface #{}",
+                pdu.surface_id, previous_serial
+            );
+        }
+    }
+
+    fn map_surface_to_output(&mut self, pdu: &MapSurfaceToOutputPdu) {
+        if !self.surface_serials.contains_key(&pdu.surface_id) {
+            warn!("Got Map Surface To Output PDU for unknown surface {}", pdu.surface_id);
+            return;
+        }
+
+        self.output_origins
+            .insert(pdu.surface_id, (pdu.output_origin_x, pdu.output_origin_y));
+    }
+
+    /// Position of the top-left corner of the surface in the output, if it is mapped.
+    fn output_origin(&self, surface_id: u16) -> Option<(u32, u32)> {
+        self.output_origins.get(&surface_id).copied()
+    }
+
+    /// The whole output, as set by the last Reset Graphics PDU.
+    fn output_rectangle(&self) -> Option<Rectangle> {
+        self.output_size.map(|(width, height)| Rectangle {
+            left: 0,
+            top: 0,
+            right: width,
+            bottom: height,
+        })
+    }
+
+    /// Returns `true` if frames have been skipped since the previous call, e.g. after a packet loss
+    /// on a lossy transport, the surface updates they held being lost.
+    fn take_frames_lost(&mut self) -> bool {
+        std::mem::take(&mut self.frames_lost)
+    }
+
+    /// Returns the starts and the ends of the frames since the previous call, in the order they were received.
+    fn take_frame_events(&mut self) -> Vec<GraphicsFrameEvent> {
+        std::mem::take(&mut self.frame_events)
+    }
+
+    fn start_frame(&mut self, pdu: &StartFramePdu) {
+        let frame_id = pdu.frame_id;
+        if let Some(current_frame_id) = self.current_frame_id {
+            warn!(
+                "Frame {} has been started before the end of frame {}",
+                frame_id, current_frame_id
+            );
+        }
+
+        match self.last_frame_id {
+            Some(last_frame_id) if last_frame_id.wrapping_add(1) != frame_id => {
+                warn!(
+                    "Frame {} follows frame {}, the frames in between have been lost",
+                    frame_id, last_frame_id
+                );
+                self.frames_lost = true;
+            }
+            _ => (),
+        }
+
+        self.current_frame_id = Some(frame_id);
+        self.frame_events.push(GraphicsFrameEvent::Started {
+            frame_id,
+            timestamp: pdu.timestamp,
+        });
+    }
+
+    fn end_frame(&mut self, frame_id: u32) -> FrameAcknowledgePdu {
+        match self.current_frame_id.take() {
+            Some(current_frame_id) if current_frame_id == frame_id => (),
+            Some(current_frame_id) => warn!("Frame {} has been ended during frame {}", frame_id, current_frame_id),
+            None => warn!("Frame {} has been ended without being started", frame_id),
+        }
+
+        // The server waits for the acknowledge of every frame it sent, even the unexpected ones
+        self.last_frame_id = Some(frame_id);
+        self.frames_decoded = self.frames_decoded.wrapping_add(1);
+        self.frame_events.push(GraphicsFrameEvent::Ended { frame_id });
+
+        // the queue depth is set by the frame pacer of the handler
+        FrameAcknowledgePdu {
+            queue_depth: QueueDepth::Suspend,
+            frame_id,
+            total_frames_decoded: self.frames_decoded,
+        }
+    }
+}
+
+fn targeted_surfaces(pdu: &ServerPdu) -> Vec<u16> {
+    match pdu {
+        ServerPdu::WireToSurface1(pdu) => vec![pdu.surface_id],
+        ServerPdu::WireToSurface2(pdu) => vec![pdu.surface_id],
+        ServerPdu::DeleteEncodingContext(pdu) => vec![pdu.surface_id],
+        ServerPdu::SolidFill(pdu) => vec![pdu.surface_id],
+        ServerPdu::SurfaceToSurface(pdu) => vec![pdu.source_surface_id, pdu.destination_surface_id],
+        ServerPdu::SurfaceToCache(pdu) => vec![pdu.surface_id],
+        ServerPdu::CacheToSurface(pdu) => vec![pdu.surface_id],
+        ServerPdu::MapSurfaceToOutput(pdu) => vec![pdu.surface_id],
+        ServerPdu::MapSurfaceToScaledOutput(pdu) => vec![pdu.surface_id],
+        ServerPdu::MapSurfaceToScaledWindow(pdu) => vec![pdu.surface_id],
+        _ => Vec::new(),
+    }
 }
 
 bitflags! {
@@ -171,7 +637,7 @@ pub fn create_capabilities_advertise(graphics_config: &Option<GraphicsConfig>) -
 
             if capability_version.contains(CapabilityVersion::V10_7) {
                 capabilities.push(CapabilitySet::V10_7 {
-                    flags: CapabilitiesV107Flags::from_bits(flags.bits()).unwrap(),
+                    flags: CapabilitiesV107Flags::from_bits_truncate(flags.bits()),
                 });
             }
         }
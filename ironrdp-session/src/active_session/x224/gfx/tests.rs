@@ -0,0 +1,194 @@
+use ironrdp::dvc::gfx::{DeleteSurfacePdu, EndFramePdu, MapSurfaceToOutputPdu, PixelFormat, StartFramePdu, Timestamp};
+
+use super::*;
+
+const TIMESTAMP: Timestamp = Timestamp {
+    milliseconds: 0,
+    seconds: 0,
+    minutes: 0,
+    hours: 0,
+};
+
+fn create_surface(surface_id: u16) -> ServerPdu {
+    ServerPdu::CreateSurface(CreateSurfacePdu {
+        surface_id,
+        width: 64,
+        height: 64,
+        pixel_format: PixelFormat::XRgb,
+    })
+}
+
+fn delete_surface(surface_id: u16) -> ServerPdu {
+    ServerPdu::DeleteSurface(DeleteSurfacePdu { surface_id })
+}
+
+fn start_frame(frame_id: u32) -> ServerPdu {
+    ServerPdu::StartFrame(StartFramePdu {
+        timestamp: TIMESTAMP,
+        frame_id,
+    })
+}
+
+fn end_frame(frame_id: u32) -> ServerPdu {
+    ServerPdu::EndFrame(EndFramePdu { frame_id })
+}
+
+fn map_surface_to_output(surface_id: u16, output_origin_x: u32, output_origin_y: u32) -> ServerPdu {
+    ServerPdu::MapSurfaceToOutput(MapSurfaceToOutputPdu {
+        surface_id,
+        output_origin_x,
+        output_origin_y,
+    })
+}
+
+fn acknowledge(frame_id: u32, total_frames_decoded: u32) -> Option<FrameAcknowledgePdu> {
+    Some(FrameAcknowledgePdu {
+        queue_depth: QueueDepth::Suspend,
+        frame_id,
+        total_frames_decoded,
+    })
+}
+
+#[test]
+fn surface_id_reused_after_deletion_gets_new_serial() {
+    let mut pipeline = PipelineState::default();
+
+    pipeline.process_pdu(&create_surface(1));
+    let first_serial = pipeline.surface_serials[&1];
+    pipeline.process_pdu(&delete_surface(1));
+    assert!(pipeline.surface_serials.is_empty());
+
+    pipeline.process_pdu(&create_surface(1));
+    assert_ne!(first_serial, pipeline.surface_serials[&1]);
+}
+
+#[test]
+fn surface_created_again_without_deletion_replaces_previous_surface() {
+    let mut pipeline = PipelineState::default();
+
+    pipeline.process_pdu(&create_surface(1));
+    let first_serial = pipeline.surface_serials[&1];
+    pipeline.process_pdu(&create_surface(1));
+
+    assert_eq!(1, pipeline.surface_serials.len());
+    assert_ne!(first_serial, pipeline.surface_serials[&1]);
+}
+
+#[test]
+fn deletion_of_unknown_surface_is_ignored() {
+    let mut pipeline = PipelineState::default();
+    pipeline.process_pdu(&create_surface(1));
+
+    assert_eq!(None, pipeline.process_pdu(&delete_surface(2)));
+    assert!(pipeline.surface_serials.contains_key(&1));
+}
+
+#[test]
+fn commands_for_unknown_surface_are_ignored() {
+    let mut pipeline = PipelineState::default();
+
+    assert_eq!(None, pipeline.process_pdu(&map_surface_to_output(7, 0, 0)));
+    assert!(pipeline.surface_serials.is_empty());
+    assert_eq!(None, pipeline.output_origin(7));
+}
+
+#[test]
+fn surface_mapped_to_output_is_unmapped_on_deletion() {
+    let mut pipeline = PipelineState::default();
+
+    pipeline.process_pdu(&create_surface(1));
+    assert_eq!(None, pipeline.output_origin(1));
+
+    pipeline.process_pdu(&map_surface_to_output(1, 640, 0));
+    assert_eq!(Some((640, 0)), pipeline.output_origin(1));
+
+    pipeline.process_pdu(&delete_surface(1));
+    pipeline.process_pdu(&create_surface(1));
+    assert_eq!(None, pipeline.output_origin(1));
+}
+
+#[test]
+fn surface_mapped_beyond_16_bit_coordinates_is_mapped() {
+    let mut pipeline = PipelineState::default();
+    let output_origin_x = u32::from(u16::MAX) + 1;
+
+    pipeline.process_pdu(&create_surface(1));
+    pipeline.process_pdu(&map_surface_to_output(1, output_origin_x, 0));
+
+    assert_eq!(Some((output_origin_x, 0)), pipeline.output_origin(1));
+}
+
+#[test]
+fn frame_ids_wrap_around() {
+    let mut pipeline = PipelineState::default();
+
+    for (frame_id, total_frames_decoded) in [(u32::MAX - 1, 1), (u32::MAX, 2), (0, 3), (1, 4)] {
+        assert_eq!(None, pipeline.process_pdu(&start_frame(frame_id)));
+        assert_eq!(
+            acknowledge(frame_id, total_frames_decoded),
+            pipeline.process_pdu(&end_frame(frame_id))
+        );
+    }
+    assert_eq!(None, pipeline.current_frame_id);
+    assert!(!pipeline.take_frames_lost());
+}
+
+#[test]
+fn skipped_frame_ids_are_reported_once_as_lost() {
+    let mut pipeline = PipelineState::default();
+
+    for frame_id in [1, 2, 4] {
+        pipeline.process_pdu(&start_frame(frame_id));
+        pipeline.process_pdu(&end_frame(frame_id));
+    }
+
+    assert!(pipeline.take_frames_lost());
+    assert!(!pipeline.take_frames_lost());
+}
+
+#[test]
+fn total_frames_decoded_wraps_around() {
+    let mut pipeline = PipelineState {
+        frames_decoded: u32::MAX,
+        ..PipelineState::default()
+    };
+
+    pipeline.process_pdu(&start_frame(5));
+    assert_eq!(acknowledge(5, 0), pipeline.process_pdu(&end_frame(5)));
+}
+
+#[test]
+fn unexpected_end_frame_is_still_acknowledged() {
+    let mut pipeline = PipelineState::default();
+
+    assert_eq!(acknowledge(3, 1), pipeline.process_pdu(&end_frame(3)));
+
+    pipeline.process_pdu(&start_frame(4));
+    assert_eq!(acknowledge(5, 2), pipeline.process_pdu(&end_frame(5)));
+    assert_eq!(None, pipeline.current_frame_id);
+}
+
+#[test]
+fn frame_events_are_taken_in_order_with_server_timestamp() {
+    let mut pipeline = PipelineState::default();
+    let timestamp = Timestamp {
+        milliseconds: 250,
+        seconds: 1,
+        minutes: 0,
+        hours: 0,
+    };
+
+    pipeline.process_pdu(&ServerPdu::StartFrame(StartFramePdu { timestamp, frame_id: 7 }));
+    pipeline.process_pdu(&end_frame(7));
+    pipeline.process_pdu(&end_frame(8));
+
+    assert_eq!(
+        vec![
+            GraphicsFrameEvent::Started { frame_id: 7, timestamp },
+            GraphicsFrameEvent::Ended { frame_id: 7 },
+            GraphicsFrameEvent::Ended { frame_id: 8 },
+        ],
+        pipeline.take_frame_events()
+    );
+    assert!(pipeline.take_frame_events().is_empty());
+}
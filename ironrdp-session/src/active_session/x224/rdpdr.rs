@@ -0,0 +1,610 @@
+mod scard;
+#[cfg(test)]
+mod tests;
+
+use std::any::Any;
+use std::collections::{HashMap, VecDeque};
+use std::io;
+
+use ironrdp::consts::{
+    RDPDR_DRIVE_CAPABILITY_VERSION_02, RDPDR_GENERAL_CAPABILITY_VERSION_02, RDPDR_SMARTCARD_CAPABILITY_VERSION_01,
+    RDPDR_VERSION_MAJOR, RDPDR_VERSION_MINOR_12,
+};
+use ironrdp::rdp::vc::rdpdr::{
+    CapabilitySet, ClientNamePdu, ClientPdu, CoreCapabilityPdu, CreateDisposition, CreateInformation, CreateOptions,
+    DeviceAnnounce, DeviceIoCompletionPdu, DeviceIoRequestPdu, DeviceListAnnouncePdu, DirectoryEntry, ExtendedPdu,
+    ExtraFlags1, GeneralCapabilitySet, IoCode1, IoRequest, IoResponse, MajorFunction, NtStatus, ServerPdu,
+    SetInformation, VersionAndIdPdu,
+};
+use ironrdp::{ChannelId, PduParsing};
+use log::{debug, warn};
+
+use super::super::channels::ChannelRegistry;
+use super::super::drive::{DrivePolicy, FileHandle, FileOpenOptions, FileSystemBackend};
+use super::super::pdu_hooks::{PduChannel, PduHooks};
+use super::super::scard::ScardBackend;
+use super::super::ActiveStageOutput;
+use super::{ChannelMessage, StaticChannelHandler};
+use crate::image::ImageSink;
+use crate::transport::{Decoder, Encoder, StaticVirtualChannelTransport};
+use crate::RdpError;
+
+/// FILE_WRITE_DATA | FILE_APPEND_DATA | GENERIC_ALL | GENERIC_WRITE
+const WRITE_ACCESS_MASK: u32 = 0x5000_0006;
+
+struct Drive {
+    name: String,
+    backend: Box<dyn FileSystemBackend>,
+    policy: DrivePolicy,
+    /// The number of bytes written by the remote session, counted against the write quota.
+    written: u64,
+}
+
+impl Drive {
+    fn check_writable(&self) -> Result<(), NtStatus> {
+        if self.policy.read_only {
+            Err(NtStatus::ACCESS_DENIED)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Checks that a file can be extended to `size` bytes, of which `written` bytes are written.
+    fn check_quotas(&self, size: u64, written: u64) -> Result<(), NtStatus> {
+        let exceeds_file_size = self.policy.max_file_size.map_or(false, |max_size| size > max_size);
+        let exceeds_quota = self
+            .policy
+            .write_quota
+            .map_or(false, |quota| self.written.saturating_add(written) > quota);
+
+        if exceeds_file_size || exceeds_quota {
+            Err(NtStatus::DISK_FULL)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn is_hidden(&self, path: &str) -> bool {
+        self.policy.hide_dotfiles && path.split('/').any(|component| component.starts_with('.'))
+    }
+}
+
+struct OpenFile {
+    device_id: u32,
+    path: String,
+    handle: Box<dyn FileHandle>,
+    delete_on_close: bool,
+    /// The entries matching the pattern of the directory query, which are returned one at a time.
+    directory_entries: VecDeque<DirectoryEntry>,
+}
+
+pub struct Handler {
+    transport: Option<StaticVirtualChannelTransport>,
+    computer_name: String,
+    drives: Vec<Drive>,
+    files: HashMap<u32, OpenFile>,
+    next_file_id: u32,
+    smart_card: Option<scard::SmartCard>,
+}
+
+impl Handler {
+    pub fn new(computer_name: String) -> Self {
+        Self {
+            transport: None,
+            computer_name,
+            drives: Vec::new(),
+            files: HashMap::new(),
+            next_file_id: 1,
+            smart_card: None,
+        }
+    }
+
+    pub fn add_drive(&mut self, name: String, backend: Box<dyn FileSystemBackend>, policy: DrivePolicy) {
+        self.drives.push(Drive {
+            name,
+            backend,
+            policy,
+            written: 0,
+        });
+    }
+
+    pub fn set_smart_card_backend(&mut self, backend: Box<dyn ScardBackend>) {
+        self.smart_card = Some(scard::SmartCard::new(backend));
+    }
+
+    /// Takes the redirected drives, whose open files are closed.
+    pub fn take_drives(&mut self) -> Vec<(String, Box<dyn FileSystemBackend>, DrivePolicy)> {
+        self.files.clear();
+
+        self.drives
+            .drain(..)
+            .map(|drive| (drive.name, drive.backend, drive.policy))
+            .collect()
+    }
+
+    pub fn take_smart_card_backend(&mut self) -> Option<Box<dyn ScardBackend>> {
+        self.smart_card.take().map(scard::SmartCard::into_backend)
+    }
+
+    fn process(
+        &mut self,
+        mut stream: impl io::Read,
+        mut output: impl io::Write,
+        transport: &mut StaticVirtualChannelTransport,
+        hooks: &mut PduHooks,
+    ) -> Result<(), RdpError> {
+        // the message has been reassembled from its chunks by the processor
+        let (channel_id, _) = transport.decode(&mut stream)?;
+        let mut data = Vec::new();
+        stream.read_to_end(&mut data)?;
+
+        let server_pdu = ServerPdu::from_buffer(data.as_slice())?;
+        hooks.received(PduChannel::Static(channel_id), server_pdu.as_short_name());
+
+        match server_pdu {
+            ServerPdu::ServerAnnounce(announce) => {
+                debug!("Got Server Announce Request PDU: {:?}", announce);
+
+                let announce_reply = ClientPdu::ClientAnnounceReply(VersionAndIdPdu {
+                    version_major: RDPDR_VERSION_MAJOR,
+                    version_minor: RDPDR_VERSION_MINOR_12,
+                    client_id: announce.client_id,
+                });
+                send(announce_reply, channel_id, transport, &mut output, hooks)?;
+
+                let client_name = ClientPdu::ClientName(ClientNamePdu {
+                    computer_name: self.computer_name.clone(),
+                });
+                send(client_name, channel_id, transport, &mut output, hooks)?;
+            }
+            ServerPdu::ServerCapability(capabilities) => {
+                debug!("Got Server Core Capability Request PDU: {:?}", capabilities);
+
+                send(
+                    client_capabilities(self.smart_card.is_some()),
+                    channel_id,
+                    transport,
+                    &mut output,
+                    hooks,
+                )?;
+            }
+            ServerPdu::ClientIdConfirm(confirm) => {
+                debug!("Got Server Client ID Confirm PDU: {:?}", confirm);
+
+                // the smart card is announced before the user logs on, so that it can be used to log on
+                if self.smart_card.is_some() {
+                    let device_list = ClientPdu::DeviceListAnnounce(DeviceListAnnouncePdu {
+                        devices: vec![DeviceAnnounce::smartcard(scard::DEVICE_ID)],
+                    });
+                    send(device_list, channel_id, transport, &mut output, hooks)?;
+                }
+            }
+            ServerPdu::UserLoggedOn => {
+                debug!("Got Server User Logged On PDU");
+
+                if !self.drives.is_empty() {
+                    let devices = self
+                        .drives
+                        .iter()
+                        .enumerate()
+                        .map(|(index, drive)| DeviceAnnounce::drive(device_id(index), &drive.name))
+                        .collect();
+                    let device_list = ClientPdu::DeviceListAnnounce(DeviceListAnnouncePdu { devices });
+                    send(device_list, channel_id, transport, &mut output, hooks)?;
+                }
+            }
+            ServerPdu::DeviceReply(reply) => {
+                if reply.result_code.is_success() {
+                    debug!("Device {} has been accepted by the server", reply.device_id);
+                } else {
+                    warn!(
+                        "Device {} has been rejected by the server: {:#010x}",
+                        reply.device_id, reply.result_code.0
+                    );
+                }
+            }
+            ServerPdu::DeviceIoRequest(request)
+                if request.device_id == scard::DEVICE_ID && self.smart_card.is_some() =>
+            {
+                let completions = self.smart_card.as_mut().unwrap().process(request);
+                for completion in completions {
+                    send(
+                        ClientPdu::DeviceIoCompletion(completion),
+                        channel_id,
+                        transport,
+                        &mut output,
+                        hooks,
+                    )?;
+                }
+            }
+            ServerPdu::DeviceIoRequest(request) => {
+                if let IoRequest::NotifyChangeDirectory { .. } = request.request {
+                    // the changes are not watched, the request stays pending until the file is closed
+                    return Ok(());
+                }
+
+                let completion = match self.complete(&request) {
+                    Ok(response) => DeviceIoCompletionPdu::new(&request, NtStatus::SUCCESS, &response),
+                    Err(io_status) => {
+                        debug!("I/O request {} failed: {:#010x}", request.completion_id, io_status.0);
+
+                        DeviceIoCompletionPdu::new(&request, io_status, &failure_response(&request.request))
+                    }
+                };
+                send(
+                    ClientPdu::DeviceIoCompletion(completion),
+                    channel_id,
+                    transport,
+                    &mut output,
+                    hooks,
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Completes the pending smart card calls whose state has changed or whose timeout has expired.
+    pub fn send_smart_card_events(
+        &mut self,
+        channel_id: ChannelId,
+        mut output: impl io::Write,
+        hooks: &mut PduHooks,
+    ) -> Result<(), RdpError> {
+        let (smart_card, transport) = match (self.smart_card.as_mut(), self.transport.as_mut()) {
+            (Some(smart_card), Some(transport)) => (smart_card, transport),
+            _ => return Ok(()),
+        };
+        let completions = smart_card.poll_status_changes();
+
+        for completion in completions {
+            send(
+                ClientPdu::DeviceIoCompletion(completion),
+                channel_id,
+                transport,
+                &mut output,
+                hooks,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn complete(&mut self, request: &DeviceIoRequestPdu) -> Result<IoResponse, NtStatus> {
+        let drive = request
+            .device_id
+            .checked_sub(1)
+            .and_then(|index| self.drives.get_mut(index as usize))
+            .ok_or(NtStatus::INVALID_HANDLE)?;
+
+        if let IoRequest::Create(create) = &request.request {
+            let path = backend_path(&create.path)?;
+            if drive.is_hidden(&path) {
+                return Err(NtStatus::OBJECT_NAME_NOT_FOUND);
+            }
+
+            let delete_on_close = create.create_options.contains(CreateOptions::DELETE_ON_CLOSE);
+            let mut options = FileOpenOptions {
+                disposition: create.create_disposition,
+                directory: if create.create_options.contains(CreateOptions::DIRECTORY_FILE) {
+                    Some(true)
+                } else if create.create_options.contains(CreateOptions::NON_DIRECTORY_FILE) {
+                    Some(false)
+                } else {
+                    None
+                },
+                write: create.desired_access & WRITE_ACCESS_MASK != 0,
+            };
+            if drive.policy.read_only {
+                match options.disposition {
+                    CreateDisposition::Open => (),
+                    // the file is opened if it exists, but never created
+                    CreateDisposition::OpenIf => options.disposition = CreateDisposition::Open,
+                    _ => return Err(NtStatus::ACCESS_DENIED),
+                }
+                if options.write || delete_on_close {
+                    return Err(NtStatus::ACCESS_DENIED);
+                }
+            }
+            let (handle, information) = drive.backend.open(&path, &options).map_err(|e| io_status(&e))?;
+
+            let file_id = self.next_file_id;
+            self.next_file_id = self.next_file_id.checked_add(1).unwrap_or(1);
+            self.files.insert(
+                file_id,
+                OpenFile {
+                    device_id: request.device_id,
+                    path,
+                    handle,
+                    delete_on_close,
+                    directory_entries: VecDeque::new(),
+                },
+            );
+
+            return Ok(IoResponse::Create { file_id, information });
+        }
+
+        if let IoRequest::QueryVolumeInformation { information_class } = request.request {
+            let volume_information = drive.backend.volume_information().map_err(|e| io_status(&e))?;
+
+            return volume_information
+                .encode(information_class)
+                .map(|buffer| IoResponse::Information { buffer })
+                .ok_or(NtStatus::NOT_SUPPORTED);
+        }
+
+        let file = match self.files.get_mut(&request.file_id) {
+            Some(file) if file.device_id == request.device_id => file,
+            _ => return Err(NtStatus::INVALID_HANDLE),
+        };
+
+        match &request.request {
+            IoRequest::Close => {
+                let file = self.files.remove(&request.file_id).unwrap();
+                let OpenFile {
+                    path,
+                    handle,
+                    delete_on_close,
+                    ..
+                } = file;
+                drop(handle);
+
+                if delete_on_close {
+                    drive.backend.remove(&path).map_err(|e| io_status(&e))?;
+                }
+
+                Ok(IoResponse::Close)
+            }
+            IoRequest::Read { length, offset } => {
+                let data = file.handle.read(*offset, *length).map_err(|e| io_status(&e))?;
+
+                Ok(IoResponse::Read { data })
+            }
+            IoRequest::Write { offset, data } => {
+                drive.check_writable()?;
+                let data_length = data.len() as u64;
+                drive.check_quotas(offset.saturating_add(data_length), data_length)?;
+
+                let length = file.handle.write(*offset, data).map_err(|e| io_status(&e))?;
+                drive.written += u64::from(length);
+
+                Ok(IoResponse::Write { length })
+            }
+            IoRequest::QueryInformation { information_class } => {
+                let information = file.handle.information().map_err(|e| io_status(&e))?;
+
+                information
+                    .encode(*information_class)
+                    .map(|buffer| IoResponse::Information { buffer })
+                    .ok_or(NtStatus::NOT_SUPPORTED)
+            }
+            IoRequest::SetInformation(set_information) => {
+                match set_information {
+                    // the times and attributes are kept as they are
+                    SetInformation::Basic(_) | SetInformation::Allocation(_) => (),
+                    SetInformation::EndOfFile(length) => {
+                        drive.check_writable()?;
+                        // the bytes the file is extended with are counted as written, unlike those truncated
+                        let end_of_file = file.handle.information().map_err(|e| io_status(&e))?.end_of_file;
+                        let growth = length.saturating_sub(end_of_file);
+                        drive.check_quotas(*length, growth)?;
+
+                        file.handle.set_len(*length).map_err(|e| io_status(&e))?;
+                        drive.written += growth;
+                    }
+                    SetInformation::Disposition { delete_pending } => {
+                        if *delete_pending {
+                            drive.check_writable()?;
+                        }
+
+                        file.delete_on_close = *delete_pending;
+                    }
+                    SetInformation::Rename {
+                        replace_if_exists,
+                        file_name,
+                    } => {
+                        drive.check_writable()?;
+                        let new_path = backend_path(file_name)?;
+                        if drive.is_hidden(&new_path) {
+                            return Err(NtStatus::ACCESS_DENIED);
+                        }
+
+                        drive
+                            .backend
+                            .rename(&file.path, &new_path, *replace_if_exists)
+                            .map_err(|e| io_status(&e))?;
+                        file.path = new_path;
+                    }
+                    SetInformation::Other { .. } => return Err(NtStatus::NOT_SUPPORTED),
+                }
+
+                Ok(IoResponse::SetInformation {
+                    length: set_information.buffer_length() as u32,
+                })
+            }
+            IoRequest::QueryDirectory {
+                information_class,
+                initial_query,
+                pattern,
+            } => {
+                if *initial_query {
+                    let pattern = pattern.rsplit('\\').next().unwrap_or_default();
+                    file.directory_entries = file
+                        .handle
+                        .read_directory()
+                        .map_err(|e| io_status(&e))?
+                        .into_iter()
+                        .filter(|entry| !drive.is_hidden(&entry.file_name))
+                        .filter(|entry| matches_pattern(&entry.file_name, pattern))
+                        .collect();
+
+                    if file.directory_entries.is_empty() {
+                        return Err(NtStatus::NO_SUCH_FILE);
+                    }
+                }
+
+                let entry = file.directory_entries.pop_front().ok_or(NtStatus::NO_MORE_FILES)?;
+
+                entry
+                    .encode(*information_class)
+                    .map(|buffer| IoResponse::Information { buffer })
+                    .ok_or(NtStatus::NOT_SUPPORTED)
+            }
+            // the drives do not support any I/O control code, which the servers do not expect to fail
+            IoRequest::DeviceControl { .. } => Ok(IoResponse::DeviceControl {
+                output_buffer: Vec::new(),
+            }),
+            IoRequest::Other { major_function, .. } if *major_function == MajorFunction::LockControl as u32 => {
+                Ok(IoResponse::LockControl)
+            }
+            IoRequest::Create(_)
+            | IoRequest::QueryVolumeInformation { .. }
+            | IoRequest::NotifyChangeDirectory { .. }
+            | IoRequest::Other { .. } => Err(NtStatus::NOT_SUPPORTED),
+        }
+    }
+}
+
+impl StaticChannelHandler for Handler {
+    fn process_message(
+        &mut self,
+        message: ChannelMessage,
+        output: &mut dyn io::Write,
+        _image: &mut dyn ImageSink,
+        hooks: &mut PduHooks,
+        _channels: &mut ChannelRegistry,
+    ) -> Result<Option<ActiveStageOutput>, RdpError> {
+        let mut transport = self
+            .transport
+            .take()
+            .unwrap_or_else(|| StaticVirtualChannelTransport::new(message.transport));
+        let result = self.process(message.data.as_slice(), output, &mut transport, hooks);
+        self.transport = Some(transport);
+
+        result.map(|_| None)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+fn client_capabilities(smart_card: bool) -> ClientPdu {
+    let mut capabilities = vec![
+        CapabilitySet::General(GeneralCapabilitySet {
+            version: RDPDR_GENERAL_CAPABILITY_VERSION_02,
+            os_type: 0,
+            os_version: 0,
+            protocol_major_version: RDPDR_VERSION_MAJOR,
+            protocol_minor_version: RDPDR_VERSION_MINOR_12,
+            io_code1: IoCode1::all() - IoCode1::QUERY_SECURITY - IoCode1::SET_SECURITY,
+            extended_pdu: ExtendedPdu::DEVICE_REMOVE_PDUS | ExtendedPdu::USER_LOGGEDON_PDU,
+            extra_flags1: ExtraFlags1::empty(),
+            special_type_device_cap: u32::from(smart_card),
+        }),
+        CapabilitySet::Drive {
+            version: RDPDR_DRIVE_CAPABILITY_VERSION_02,
+        },
+    ];
+    if smart_card {
+        capabilities.push(CapabilitySet::Smartcard {
+            version: RDPDR_SMARTCARD_CAPABILITY_VERSION_01,
+        });
+    }
+
+    ClientPdu::ClientCapability(CoreCapabilityPdu { capabilities })
+}
+
+/// The output of the response to a failed request, which is to be sent even if empty.
+fn failure_response(request: &IoRequest) -> IoResponse {
+    match request {
+        IoRequest::Create(_) => IoResponse::Create {
+            file_id: 0,
+            information: CreateInformation::Superseded,
+        },
+        IoRequest::Close => IoResponse::Close,
+        IoRequest::Read { .. } => IoResponse::Read { data: Vec::new() },
+        IoRequest::Write { .. } => IoResponse::Write { length: 0 },
+        IoRequest::QueryInformation { .. }
+        | IoRequest::QueryVolumeInformation { .. }
+        | IoRequest::QueryDirectory { .. } => IoResponse::Information { buffer: Vec::new() },
+        IoRequest::SetInformation(_) => IoResponse::SetInformation { length: 0 },
+        IoRequest::DeviceControl { .. } => IoResponse::DeviceControl {
+            output_buffer: Vec::new(),
+        },
+        IoRequest::NotifyChangeDirectory { .. } | IoRequest::Other { .. } => IoResponse::Empty,
+    }
+}
+
+fn device_id(drive_index: usize) -> u32 {
+    drive_index as u32 + 1
+}
+
+/// Converts the `\` separated path sent by the server to the path of the backend,
+/// rejecting the components which could escape the root of the drive.
+fn backend_path(path: &str) -> Result<String, NtStatus> {
+    let components = path
+        .split('\\')
+        .filter(|component| !component.is_empty())
+        .collect::<Vec<_>>();
+
+    let is_invalid = |component: &&str| {
+        *component == "." || *component == ".." || component.contains(|c| matches!(c, '/' | ':' | '\0'))
+    };
+    if components.iter().any(is_invalid) {
+        return Err(NtStatus::OBJECT_NAME_INVALID);
+    }
+
+    Ok(components.join("/"))
+}
+
+/// Matches the file name against the `*` and `?` wildcards of the pattern, ignoring the case.
+fn matches_pattern(file_name: &str, pattern: &str) -> bool {
+    fn matches(name: &[char], pattern: &[char]) -> bool {
+        match pattern.split_first() {
+            None => name.is_empty(),
+            Some(('*', rest)) => (0..=name.len()).any(|skipped| matches(&name[skipped..], rest)),
+            Some(('?', rest)) => !name.is_empty() && matches(&name[1..], rest),
+            Some((c, rest)) => name.first() == Some(c) && matches(&name[1..], rest),
+        }
+    }
+
+    if pattern.is_empty() || pattern == "*.*" {
+        return true;
+    }
+
+    let file_name = file_name.to_lowercase().chars().collect::<Vec<_>>();
+    let pattern = pattern.to_lowercase().chars().collect::<Vec<_>>();
+
+    matches(&file_name, &pattern)
+}
+
+fn io_status(error: &io::Error) -> NtStatus {
+    match error.kind() {
+        io::ErrorKind::NotFound => NtStatus::OBJECT_NAME_NOT_FOUND,
+        io::ErrorKind::PermissionDenied => NtStatus::ACCESS_DENIED,
+        io::ErrorKind::AlreadyExists => NtStatus::OBJECT_NAME_COLLISION,
+        io::ErrorKind::InvalidInput => NtStatus::INVALID_PARAMETER,
+        io::ErrorKind::UnexpectedEof => NtStatus::END_OF_FILE,
+        io::ErrorKind::Unsupported => NtStatus::NOT_SUPPORTED,
+        _ => NtStatus::UNSUCCESSFUL,
+    }
+}
+
+fn send(
+    client_pdu: ClientPdu,
+    channel_id: ChannelId,
+    transport: &mut StaticVirtualChannelTransport,
+    output: impl io::Write,
+    hooks: &mut PduHooks,
+) -> Result<(), RdpError> {
+    let mut buffer = Vec::with_capacity(client_pdu.buffer_length());
+    client_pdu.to_buffer(&mut buffer)?;
+
+    hooks.sent(PduChannel::Static(channel_id), client_pdu.as_short_name());
+    transport.encode(buffer, output)
+}
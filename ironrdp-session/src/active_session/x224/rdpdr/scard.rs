@@ -0,0 +1,386 @@
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+use ironrdp::rdp::vc::rdpdr::scard::{
+    self, ReaderState, ScardCall, ScardContext, ScardHandle, ScardIoRequest, ScardReturn, ScardReturnCode,
+    INFINITE_TIMEOUT,
+};
+use ironrdp::rdp::vc::rdpdr::{DeviceIoCompletionPdu, DeviceIoRequestPdu, IoRequest, IoResponse, NtStatus};
+use log::debug;
+
+use super::super::super::scard::{ScardBackend, ScardResult};
+
+/// The device ID of the smart card, distinct from the ones of the drives.
+pub const DEVICE_ID: u32 = 0x8000_0000;
+
+/// SCARD_READER_TYPE_USB
+const READER_TYPE_USB: u32 = 0x0000_0020;
+
+struct PendingStatusChange {
+    request: DeviceIoRequestPdu,
+    context: u64,
+    reader_states: Vec<ReaderState>,
+    /// `None` if the call waits for a change with no time limit.
+    deadline: Option<Instant>,
+}
+
+/// Executes the calls of the remote session on the smart card backend, the status change calls
+/// staying pending until a state changes, their timeout expires or they are cancelled.
+pub struct SmartCard {
+    backend: Box<dyn ScardBackend>,
+    contexts: HashSet<u64>,
+    pending_status_changes: Vec<PendingStatusChange>,
+}
+
+impl SmartCard {
+    pub fn new(backend: Box<dyn ScardBackend>) -> Self {
+        Self {
+            backend,
+            contexts: HashSet::new(),
+            pending_status_changes: Vec::new(),
+        }
+    }
+
+    /// Returns the backend, once the contexts established by the remote session have been released.
+    pub fn into_backend(mut self) -> Box<dyn ScardBackend> {
+        for context in self.contexts.drain() {
+            if let Err(return_code) = self.backend.release_context(context) {
+                debug!("Failed to release smart card context: {:#010x}", return_code.0);
+            }
+        }
+
+        self.backend
+    }
+
+    /// Returns the completions of the request, and of the pending requests it has cancelled,
+    /// none if the request is pending.
+    pub fn process(&mut self, request: DeviceIoRequestPdu) -> Vec<DeviceIoCompletionPdu> {
+        let (io_control_code, input_buffer) = match &request.request {
+            IoRequest::DeviceControl {
+                io_control_code,
+                input_buffer,
+                ..
+            } => (*io_control_code, input_buffer),
+            _ => {
+                return vec![DeviceIoCompletionPdu::new(
+                    &request,
+                    NtStatus::NOT_SUPPORTED,
+                    &IoResponse::Empty,
+                )]
+            }
+        };
+
+        let call = match ScardCall::decode(io_control_code, input_buffer) {
+            Ok(call) => call,
+            Err(e) => {
+                debug!("Invalid smart card call {:#010x}: {}", io_control_code, e);
+
+                return vec![DeviceIoCompletionPdu::new(
+                    &request,
+                    NtStatus::INVALID_PARAMETER,
+                    &IoResponse::DeviceControl {
+                        output_buffer: Vec::new(),
+                    },
+                )];
+            }
+        };
+
+        let mut completions = Vec::new();
+        match &call {
+            ScardCall::GetStatusChange {
+                context,
+                timeout,
+                reader_states,
+                ..
+            } => {
+                let pending = match context_id(context) {
+                    Ok(context) => PendingStatusChange {
+                        request,
+                        context,
+                        reader_states: reader_states.clone(),
+                        deadline: if *timeout == INFINITE_TIMEOUT {
+                            None
+                        } else {
+                            Some(Instant::now() + Duration::from_millis(u64::from(*timeout)))
+                        },
+                    },
+                    Err(return_code) => return vec![completion(&request, &call.failure(return_code))],
+                };
+
+                self.pending_status_changes.push(pending);
+                completions.extend(self.poll_status_changes());
+            }
+            ScardCall::Cancel { context } | ScardCall::ReleaseContext { context } => {
+                if let Ok(context) = context_id(context) {
+                    completions.extend(self.cancel_status_changes(context));
+                }
+
+                completions.push(completion(&request, &self.call(&call)));
+            }
+            _ => completions.push(completion(&request, &self.call(&call))),
+        }
+
+        completions
+    }
+
+    /// Returns the completions of the pending status change calls whose state has changed or whose timeout has expired.
+    pub fn poll_status_changes(&mut self) -> Vec<DeviceIoCompletionPdu> {
+        let now = Instant::now();
+        let mut completions = Vec::new();
+
+        let backend = &mut self.backend;
+        self.pending_status_changes.retain_mut(|pending| {
+            let return_code = match backend.get_status_change(pending.context, &mut pending.reader_states) {
+                Ok(()) => ScardReturnCode::SUCCESS,
+                Err(ScardReturnCode::TIMEOUT) if pending.deadline.map_or(true, |deadline| now < deadline) => {
+                    return true;
+                }
+                Err(return_code) => return_code,
+            };
+
+            let reader_states = if return_code == ScardReturnCode::SUCCESS {
+                std::mem::take(&mut pending.reader_states)
+            } else {
+                Vec::new()
+            };
+            completions.push(completion(
+                &pending.request,
+                &ScardReturn::GetStatusChange {
+                    return_code,
+                    reader_states,
+                },
+            ));
+
+            false
+        });
+
+        completions
+    }
+
+    fn cancel_status_changes(&mut self, context: u64) -> Vec<DeviceIoCompletionPdu> {
+        let (cancelled, pending) = std::mem::take(&mut self.pending_status_changes)
+            .into_iter()
+            .partition::<Vec<_>, _>(|pending| pending.context == context);
+        self.pending_status_changes = pending;
+
+        cancelled
+            .iter()
+            .map(|pending| {
+                completion(
+                    &pending.request,
+                    &ScardReturn::GetStatusChange {
+                        return_code: ScardReturnCode::CANCELLED,
+                        reader_states: Vec::new(),
+                    },
+                )
+            })
+            .collect()
+    }
+
+    fn call(&mut self, call: &ScardCall) -> ScardReturn {
+        // the calls are not logged as they hold the data sent to the cards, e.g. their PIN
+        self.try_call(call).unwrap_or_else(|return_code| {
+            debug!("Smart card call failed: {:#010x}", return_code.0);
+
+            call.failure(return_code)
+        })
+    }
+
+    fn try_call(&mut self, call: &ScardCall) -> ScardResult<ScardReturn> {
+        let scard_return = match call {
+            ScardCall::EstablishContext { scope } => {
+                let context = self.backend.establish_context(*scope)?;
+                self.contexts.insert(context);
+
+                ScardReturn::EstablishContext {
+                    return_code: ScardReturnCode::SUCCESS,
+                    context: ScardContext(context.to_le_bytes().to_vec()),
+                }
+            }
+            ScardCall::ReleaseContext { context } => {
+                let context = context_id(context)?;
+                self.contexts.remove(&context);
+                self.backend.release_context(context)?;
+
+                success()
+            }
+            ScardCall::IsValidContext { context } => {
+                if !self.contexts.contains(&context_id(context)?) {
+                    return Err(ScardReturnCode::INVALID_HANDLE);
+                }
+
+                success()
+            }
+            // the pending calls of the context have been cancelled
+            ScardCall::Cancel { .. }
+            | ScardCall::AccessStartedEvent
+            | ScardCall::ReleaseStartedEvent
+            | ScardCall::WriteCache => success(),
+            ScardCall::ListReaders { context, character_set } => {
+                let readers = self.backend.list_readers(context_id(context)?)?;
+                if readers.is_empty() {
+                    return Err(ScardReturnCode::NO_READERS_AVAILABLE);
+                }
+
+                ScardReturn::ListReaders {
+                    return_code: ScardReturnCode::SUCCESS,
+                    readers: scard::encode_multi_string(&readers, *character_set),
+                }
+            }
+            ScardCall::Connect {
+                context,
+                reader,
+                share_mode,
+                preferred_protocols,
+                ..
+            } => {
+                let (card, active_protocol) =
+                    self.backend
+                        .connect(context_id(context)?, reader, *share_mode, *preferred_protocols)?;
+
+                ScardReturn::Connect {
+                    return_code: ScardReturnCode::SUCCESS,
+                    handle: ScardHandle {
+                        context: context.clone(),
+                        handle: card.to_le_bytes().to_vec(),
+                    },
+                    active_protocol,
+                }
+            }
+            ScardCall::Reconnect {
+                handle,
+                share_mode,
+                preferred_protocols,
+                initialization,
+            } => {
+                let active_protocol =
+                    self.backend
+                        .reconnect(card_id(handle)?, *share_mode, *preferred_protocols, *initialization)?;
+
+                ScardReturn::Reconnect {
+                    return_code: ScardReturnCode::SUCCESS,
+                    active_protocol,
+                }
+            }
+            ScardCall::Disconnect { handle, disposition } => {
+                self.backend.disconnect(card_id(handle)?, *disposition)?;
+
+                success()
+            }
+            ScardCall::BeginTransaction { handle } => {
+                self.backend.begin_transaction(card_id(handle)?)?;
+
+                success()
+            }
+            ScardCall::EndTransaction { handle, disposition } => {
+                self.backend.end_transaction(card_id(handle)?, *disposition)?;
+
+                success()
+            }
+            ScardCall::Status { handle, character_set } => {
+                let status = self.backend.status(card_id(handle)?)?;
+
+                ScardReturn::Status {
+                    return_code: ScardReturnCode::SUCCESS,
+                    reader_names: scard::encode_multi_string(&status.reader_names, *character_set),
+                    state: status.state,
+                    protocol: status.protocol,
+                    atr: status.atr,
+                }
+            }
+            ScardCall::Transmit {
+                handle,
+                send_pci,
+                send_buffer,
+                receive_pci,
+                receive_length,
+            } => {
+                let receive_buffer = self
+                    .backend
+                    .transmit(card_id(handle)?, send_pci, send_buffer, *receive_length)?;
+
+                ScardReturn::Transmit {
+                    return_code: ScardReturnCode::SUCCESS,
+                    receive_pci: if *receive_pci {
+                        Some(ScardIoRequest {
+                            protocol: send_pci.protocol,
+                            extra_bytes: Vec::new(),
+                        })
+                    } else {
+                        None
+                    },
+                    receive_buffer,
+                }
+            }
+            ScardCall::Control {
+                handle,
+                control_code,
+                in_buffer,
+                out_buffer_size,
+            } => {
+                let out_buffer = self
+                    .backend
+                    .control(card_id(handle)?, *control_code, in_buffer, *out_buffer_size)?;
+
+                ScardReturn::Control {
+                    return_code: ScardReturnCode::SUCCESS,
+                    out_buffer,
+                }
+            }
+            ScardCall::GetAttrib { handle, attribute_id } => {
+                let attribute = self.backend.get_attrib(card_id(handle)?, *attribute_id)?;
+
+                ScardReturn::GetAttrib {
+                    return_code: ScardReturnCode::SUCCESS,
+                    attribute,
+                }
+            }
+            ScardCall::GetDeviceTypeId { context, .. } => {
+                context_id(context)?;
+
+                ScardReturn::GetDeviceTypeId {
+                    return_code: ScardReturnCode::SUCCESS,
+                    device_type_id: READER_TYPE_USB,
+                }
+            }
+            // the data read from the cards is not cached
+            ScardCall::ReadCache => return Err(ScardReturnCode::CACHE_ITEM_NOT_FOUND),
+            ScardCall::GetStatusChange { .. } | ScardCall::Unsupported { .. } => {
+                return Err(ScardReturnCode::UNSUPPORTED_FEATURE)
+            }
+        };
+
+        Ok(scard_return)
+    }
+}
+
+fn success() -> ScardReturn {
+    ScardReturn::Long {
+        return_code: ScardReturnCode::SUCCESS,
+    }
+}
+
+fn completion(request: &DeviceIoRequestPdu, scard_return: &ScardReturn) -> DeviceIoCompletionPdu {
+    DeviceIoCompletionPdu::new(
+        request,
+        NtStatus::SUCCESS,
+        &IoResponse::DeviceControl {
+            output_buffer: scard_return.encode(),
+        },
+    )
+}
+
+fn context_id(context: &ScardContext) -> ScardResult<u64> {
+    to_id(&context.0)
+}
+
+fn card_id(handle: &ScardHandle) -> ScardResult<u64> {
+    to_id(&handle.handle)
+}
+
+fn to_id(bytes: &[u8]) -> ScardResult<u64> {
+    bytes
+        .try_into()
+        .map(u64::from_le_bytes)
+        .map_err(|_| ScardReturnCode::INVALID_HANDLE)
+}
@@ -0,0 +1,307 @@
+use std::sync::{Arc, Mutex};
+
+use ironrdp::rdp::vc::rdpdr::{
+    CreateRequest, FileAttributes, FileBasicInformation, FileInformation, FileInformationClass,
+};
+
+use super::*;
+
+const GENERIC_READ: u32 = 0x8000_0000;
+const GENERIC_WRITE: u32 = 0x4000_0000;
+
+type Files = Arc<Mutex<HashMap<String, Vec<u8>>>>;
+
+/// The files of the root directory, the empty path.
+struct MemoryBackend(Files);
+
+struct MemoryFile {
+    files: Files,
+    path: String,
+}
+
+impl FileSystemBackend for MemoryBackend {
+    fn open(&mut self, path: &str, options: &FileOpenOptions) -> io::Result<(Box<dyn FileHandle>, CreateInformation)> {
+        let mut files = self.0.lock().unwrap();
+        let information = match (files.contains_key(path), options.disposition) {
+            (false, CreateDisposition::Open) => return Err(io::Error::from(io::ErrorKind::NotFound)),
+            (false, _) => {
+                files.insert(path.to_owned(), Vec::new());
+                CreateInformation::Superseded
+            }
+            (true, _) => CreateInformation::Opened,
+        };
+        let file = MemoryFile {
+            files: self.0.clone(),
+            path: path.to_owned(),
+        };
+
+        Ok((Box::new(file), information))
+    }
+
+    fn remove(&mut self, path: &str) -> io::Result<()> {
+        self.0.lock().unwrap().remove(path);
+
+        Ok(())
+    }
+
+    fn rename(&mut self, from: &str, to: &str, _replace_if_exists: bool) -> io::Result<()> {
+        let mut files = self.0.lock().unwrap();
+        let data = files
+            .remove(from)
+            .ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))?;
+        files.insert(to.to_owned(), data);
+
+        Ok(())
+    }
+
+    fn volume_information(&mut self) -> io::Result<ironrdp::rdp::vc::rdpdr::VolumeInformation> {
+        Err(io::Error::from(io::ErrorKind::Unsupported))
+    }
+}
+
+impl FileHandle for MemoryFile {
+    fn information(&mut self) -> io::Result<FileInformation> {
+        let end_of_file = self.files.lock().unwrap()[&self.path].len() as u64;
+
+        Ok(information(end_of_file))
+    }
+
+    fn read(&mut self, offset: u64, length: u32) -> io::Result<Vec<u8>> {
+        let files = self.files.lock().unwrap();
+        let data = files[&self.path].iter().skip(offset as usize).take(length as usize);
+
+        Ok(data.copied().collect())
+    }
+
+    fn write(&mut self, offset: u64, data: &[u8]) -> io::Result<u32> {
+        let mut files = self.files.lock().unwrap();
+        let file = files.get_mut(&self.path).unwrap();
+        let end = offset as usize + data.len();
+        if file.len() < end {
+            file.resize(end, 0);
+        }
+        file[offset as usize..end].copy_from_slice(data);
+
+        Ok(data.len() as u32)
+    }
+
+    fn set_len(&mut self, length: u64) -> io::Result<()> {
+        self.files
+            .lock()
+            .unwrap()
+            .get_mut(&self.path)
+            .unwrap()
+            .resize(length as usize, 0);
+
+        Ok(())
+    }
+
+    fn read_directory(&mut self) -> io::Result<Vec<DirectoryEntry>> {
+        let files = self.files.lock().unwrap();
+
+        Ok(files
+            .iter()
+            .filter(|(path, _)| !path.is_empty())
+            .map(|(path, data)| DirectoryEntry {
+                file_name: path.clone(),
+                information: information(data.len() as u64),
+            })
+            .collect())
+    }
+}
+
+fn information(end_of_file: u64) -> FileInformation {
+    FileInformation {
+        basic: FileBasicInformation {
+            creation_time: 0,
+            last_access_time: 0,
+            last_write_time: 0,
+            change_time: 0,
+            file_attributes: FileAttributes::ARCHIVE,
+        },
+        end_of_file,
+        allocation_size: end_of_file,
+    }
+}
+
+fn handler_with_drive(policy: DrivePolicy, paths: &[&str]) -> (Handler, Files) {
+    let files = paths
+        .iter()
+        .chain([""].iter())
+        .map(|path| (path.to_string(), b"data".to_vec()))
+        .collect::<HashMap<_, _>>();
+    let files = Arc::new(Mutex::new(files));
+
+    let mut handler = Handler::new(String::from("client"));
+    handler.add_drive(String::from("drive"), Box::new(MemoryBackend(files.clone())), policy);
+
+    (handler, files)
+}
+
+fn complete(handler: &mut Handler, file_id: u32, request: IoRequest) -> Result<IoResponse, NtStatus> {
+    handler.complete(&DeviceIoRequestPdu {
+        device_id: 1,
+        file_id,
+        completion_id: 0,
+        request,
+    })
+}
+
+fn create(
+    handler: &mut Handler,
+    path: &str,
+    disposition: CreateDisposition,
+    desired_access: u32,
+) -> Result<u32, NtStatus> {
+    let request = IoRequest::Create(CreateRequest {
+        desired_access,
+        allocation_size: 0,
+        file_attributes: FileAttributes::empty(),
+        shared_access: 0,
+        create_disposition: disposition,
+        create_options: CreateOptions::empty(),
+        path: path.to_owned(),
+    });
+
+    match complete(handler, 0, request)? {
+        IoResponse::Create { file_id, .. } => Ok(file_id),
+        response => panic!("unexpected response: {:?}", response),
+    }
+}
+
+fn write(handler: &mut Handler, file_id: u32, offset: u64, length: usize) -> Result<IoResponse, NtStatus> {
+    let data = vec![0xAB; length];
+
+    complete(handler, file_id, IoRequest::Write { offset, data })
+}
+
+#[test]
+fn read_only_drive_denies_modifications() {
+    let policy = DrivePolicy {
+        read_only: true,
+        ..DrivePolicy::default()
+    };
+    let (mut handler, files) = handler_with_drive(policy, &["a.txt"]);
+
+    let file_id = create(&mut handler, "\\a.txt", CreateDisposition::Open, GENERIC_READ).unwrap();
+    assert!(complete(&mut handler, file_id, IoRequest::Read { length: 4, offset: 0 }).is_ok());
+    assert_eq!(Err(NtStatus::ACCESS_DENIED), write(&mut handler, file_id, 0, 1));
+    assert_eq!(
+        Err(NtStatus::ACCESS_DENIED),
+        complete(
+            &mut handler,
+            file_id,
+            IoRequest::SetInformation(SetInformation::Disposition { delete_pending: true })
+        )
+    );
+
+    assert_eq!(
+        Err(NtStatus::ACCESS_DENIED),
+        create(&mut handler, "\\a.txt", CreateDisposition::Open, GENERIC_WRITE)
+    );
+    assert_eq!(
+        Err(NtStatus::ACCESS_DENIED),
+        create(&mut handler, "\\b.txt", CreateDisposition::Create, GENERIC_READ)
+    );
+    assert_eq!(
+        Err(NtStatus::OBJECT_NAME_NOT_FOUND),
+        create(&mut handler, "\\b.txt", CreateDisposition::OpenIf, GENERIC_READ)
+    );
+    assert_eq!(2, files.lock().unwrap().len());
+}
+
+#[test]
+fn paths_escaping_the_root_are_rejected() {
+    let (mut handler, files) = handler_with_drive(DrivePolicy::default(), &[]);
+
+    for path in ["\\..\\secret", "\\a\\.\\b", "\\a/../../b", "\\a.txt:stream"] {
+        assert_eq!(
+            Err(NtStatus::OBJECT_NAME_INVALID),
+            create(&mut handler, path, CreateDisposition::OpenIf, GENERIC_READ),
+            "{}",
+            path
+        );
+    }
+    assert_eq!(1, files.lock().unwrap().len());
+}
+
+#[test]
+fn dotfiles_are_hidden() {
+    let policy = DrivePolicy {
+        hide_dotfiles: true,
+        ..DrivePolicy::default()
+    };
+    let (mut handler, _) = handler_with_drive(policy, &[".ssh", "a.txt"]);
+
+    assert_eq!(
+        Err(NtStatus::OBJECT_NAME_NOT_FOUND),
+        create(&mut handler, "\\.ssh", CreateDisposition::Open, GENERIC_READ)
+    );
+
+    let root = create(&mut handler, "", CreateDisposition::Open, GENERIC_READ).unwrap();
+    let query = |initial_query| IoRequest::QueryDirectory {
+        information_class: FileInformationClass::NAMES,
+        initial_query,
+        pattern: String::from("\\*"),
+    };
+    let expected = DirectoryEntry {
+        file_name: String::from("a.txt"),
+        information: information(4),
+    };
+    assert_eq!(
+        Ok(IoResponse::Information {
+            buffer: expected.encode(FileInformationClass::NAMES).unwrap()
+        }),
+        complete(&mut handler, root, query(true))
+    );
+    assert_eq!(Err(NtStatus::NO_MORE_FILES), complete(&mut handler, root, query(false)));
+}
+
+#[test]
+fn writes_beyond_the_quotas_fail_with_disk_full() {
+    let policy = DrivePolicy {
+        max_file_size: Some(8),
+        write_quota: Some(12),
+        ..DrivePolicy::default()
+    };
+    let (mut handler, _) = handler_with_drive(policy, &["a.txt"]);
+    let file_id = create(&mut handler, "\\a.txt", CreateDisposition::Open, GENERIC_WRITE).unwrap();
+
+    assert_eq!(Ok(IoResponse::Write { length: 6 }), write(&mut handler, file_id, 0, 6));
+    assert_eq!(Err(NtStatus::DISK_FULL), write(&mut handler, file_id, 6, 4));
+    assert_eq!(
+        Err(NtStatus::DISK_FULL),
+        complete(
+            &mut handler,
+            file_id,
+            IoRequest::SetInformation(SetInformation::EndOfFile(9))
+        )
+    );
+    assert_eq!(Ok(IoResponse::Write { length: 6 }), write(&mut handler, file_id, 2, 6));
+    assert_eq!(Err(NtStatus::DISK_FULL), write(&mut handler, file_id, 0, 1));
+}
+
+#[test]
+fn extending_files_counts_against_the_write_quota() {
+    let policy = DrivePolicy {
+        write_quota: Some(10),
+        ..DrivePolicy::default()
+    };
+    let (mut handler, files) = handler_with_drive(policy, &["a.txt"]);
+    let file_id = create(&mut handler, "\\a.txt", CreateDisposition::Open, GENERIC_WRITE).unwrap();
+    let set_end_of_file = |handler: &mut Handler, length| {
+        complete(
+            handler,
+            file_id,
+            IoRequest::SetInformation(SetInformation::EndOfFile(length)),
+        )
+    };
+
+    assert_eq!(Err(NtStatus::DISK_FULL), set_end_of_file(&mut handler, 20));
+    assert!(set_end_of_file(&mut handler, 10).is_ok());
+    assert_eq!(10, files.lock().unwrap()["a.txt"].len());
+    assert!(set_end_of_file(&mut handler, 2).is_ok());
+    assert_eq!(Err(NtStatus::DISK_FULL), set_end_of_file(&mut handler, 7));
+    assert_eq!(Ok(IoResponse::Write { length: 4 }), write(&mut handler, file_id, 0, 4));
+    assert_eq!(Err(NtStatus::DISK_FULL), write(&mut handler, file_id, 4, 1));
+}
@@ -0,0 +1,227 @@
+use std::any::Any;
+use std::io;
+
+use ironrdp::rdp::vc::rdpsnd::{
+    AudioFormat, AudioFormatsFlags, AudioFormatsPdu, ClientPdu, QualityMode, QualityModePdu, ServerPdu,
+    TrainingConfirmPdu, WaveConfirmPdu, WaveInfoPdu, WavePdu,
+};
+use ironrdp::{ChannelId, PduParsing};
+use log::{debug, warn};
+
+use super::super::audio::AudioSink;
+use super::super::channels::ChannelRegistry;
+use super::super::pdu_hooks::{PduChannel, PduHooks};
+use super::super::ActiveStageOutput;
+use super::{ChannelMessage, StaticChannelHandler};
+use crate::image::ImageSink;
+use crate::transport::{Decoder, Encoder, StaticVirtualChannelTransport};
+use crate::RdpError;
+
+const CLIENT_VERSION: u16 = 6;
+const QUALITY_MODE_MIN_VERSION: u16 = 6;
+const FULL_VOLUME: u32 = 0xFFFF_FFFF;
+const NORMAL_PITCH: u32 = 0x0001_0000;
+
+#[derive(Default)]
+pub struct Handler {
+    transport: Option<StaticVirtualChannelTransport>,
+    sink: Option<Box<dyn AudioSink>>,
+    client_formats: Vec<AudioFormat>,
+    wave_info: Option<WaveInfoPdu>,
+}
+
+impl Handler {
+    pub fn set_sink(&mut self, sink: Box<dyn AudioSink>) {
+        self.sink = Some(sink);
+    }
+
+    /// Takes the sink, closed as the playback of the session is interrupted.
+    pub fn take_sink(&mut self) -> Option<Box<dyn AudioSink>> {
+        let mut sink = self.sink.take();
+        if let Some(sink) = sink.as_mut() {
+            sink.close();
+        }
+
+        sink
+    }
+
+    fn process(
+        &mut self,
+        mut stream: impl io::Read,
+        mut output: impl io::Write,
+        transport: &mut StaticVirtualChannelTransport,
+        hooks: &mut PduHooks,
+    ) -> Result<(), RdpError> {
+        // the message has been reassembled from its chunks by the processor
+        let (channel_id, _) = transport.decode(&mut stream)?;
+        let mut data = Vec::new();
+        stream.read_to_end(&mut data)?;
+
+        if let Some(wave_info) = self.wave_info.take() {
+            let wave = WavePdu::from_buffer(data.as_slice(), &wave_info)?;
+            hooks.received(PduChannel::Static(channel_id), "Wave PDU");
+
+            self.play(wave_info.format_no, &wave.data)?;
+
+            return confirm_wave(
+                wave_info.time_stamp,
+                wave_info.block_no,
+                channel_id,
+                transport,
+                output,
+                hooks,
+            );
+        }
+
+        let server_pdu = ServerPdu::from_buffer(data.as_slice())?;
+        hooks.received(PduChannel::Static(channel_id), server_pdu.as_short_name());
+
+        match server_pdu {
+            ServerPdu::AudioFormats(server_formats) => {
+                debug!("Got Server Audio Formats PDU: {:?}", server_formats);
+
+                let sink = &self.sink;
+                self.client_formats = server_formats
+                    .formats
+                    .into_iter()
+                    .filter(|format| sink.as_ref().map_or(false, |sink| sink.supports(format)))
+                    .collect();
+                if self.client_formats.is_empty() {
+                    warn!("None of the audio formats offered by the server is supported");
+                }
+
+                let client_formats = ClientPdu::AudioFormats(AudioFormatsPdu {
+                    flags: AudioFormatsFlags::ALIVE | AudioFormatsFlags::VOLUME,
+                    volume: FULL_VOLUME,
+                    pitch: NORMAL_PITCH,
+                    dgram_port: 0,
+                    last_block_confirmed: 0,
+                    version: CLIENT_VERSION,
+                    formats: self.client_formats.clone(),
+                });
+                debug!("Send Client Audio Formats PDU: {:?}", client_formats);
+                send(client_formats, channel_id, transport, &mut output, hooks)?;
+
+                if server_formats.version >= QUALITY_MODE_MIN_VERSION {
+                    let quality_mode = ClientPdu::QualityMode(QualityModePdu {
+                        quality_mode: QualityMode::High,
+                    });
+                    send(quality_mode, channel_id, transport, &mut output, hooks)?;
+                }
+            }
+            ServerPdu::Training(training) => {
+                debug!("Got Training PDU: {:?}", training.time_stamp);
+
+                let training_confirm = ClientPdu::TrainingConfirm(TrainingConfirmPdu {
+                    time_stamp: training.time_stamp,
+                    pack_size: training.pack_size,
+                });
+                send(training_confirm, channel_id, transport, &mut output, hooks)?;
+            }
+            ServerPdu::WaveInfo(wave_info) => {
+                self.wave_info = Some(wave_info);
+            }
+            ServerPdu::Wave2(wave) => {
+                self.play(wave.format_no, &wave.data)?;
+
+                confirm_wave(
+                    wave.time_stamp,
+                    wave.block_no,
+                    channel_id,
+                    transport,
+                    &mut output,
+                    hooks,
+                )?;
+            }
+            ServerPdu::Volume(volume) => {
+                debug!("Got Volume PDU: {:?}", volume);
+
+                if let Some(sink) = self.sink.as_mut() {
+                    sink.set_volume(volume.left, volume.right);
+                }
+            }
+            ServerPdu::Pitch(pitch) => {
+                debug!("Got Pitch PDU: {:?}, ignoring it", pitch);
+            }
+            ServerPdu::Close => {
+                debug!("Got Close PDU");
+
+                if let Some(sink) = self.sink.as_mut() {
+                    sink.close();
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn play(&mut self, format_no: u16, data: &[u8]) -> Result<(), RdpError> {
+        match (self.client_formats.get(usize::from(format_no)), self.sink.as_mut()) {
+            (Some(format), Some(sink)) => sink.play(format, data),
+            (None, _) => {
+                warn!("Got audio sample in the unknown format {}, dropping it", format_no);
+
+                Ok(())
+            }
+            (Some(_), None) => Ok(()),
+        }
+    }
+}
+
+impl StaticChannelHandler for Handler {
+    fn process_message(
+        &mut self,
+        message: ChannelMessage,
+        output: &mut dyn io::Write,
+        _image: &mut dyn ImageSink,
+        hooks: &mut PduHooks,
+        _channels: &mut ChannelRegistry,
+    ) -> Result<Option<ActiveStageOutput>, RdpError> {
+        let mut transport = self
+            .transport
+            .take()
+            .unwrap_or_else(|| StaticVirtualChannelTransport::new(message.transport));
+        let result = self.process(message.data.as_slice(), output, &mut transport, hooks);
+        self.transport = Some(transport);
+
+        result.map(|_| None)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+fn confirm_wave(
+    time_stamp: u16,
+    block_no: u8,
+    channel_id: ChannelId,
+    transport: &mut StaticVirtualChannelTransport,
+    output: impl io::Write,
+    hooks: &mut PduHooks,
+) -> Result<(), RdpError> {
+    let wave_confirm = ClientPdu::WaveConfirm(WaveConfirmPdu {
+        time_stamp,
+        confirmed_block_no: block_no,
+    });
+
+    send(wave_confirm, channel_id, transport, output, hooks)
+}
+
+fn send(
+    client_pdu: ClientPdu,
+    channel_id: ChannelId,
+    transport: &mut StaticVirtualChannelTransport,
+    output: impl io::Write,
+    hooks: &mut PduHooks,
+) -> Result<(), RdpError> {
+    let mut buffer = Vec::with_capacity(client_pdu.buffer_length());
+    client_pdu.to_buffer(&mut buffer)?;
+
+    hooks.sent(PduChannel::Static(channel_id), client_pdu.as_short_name());
+    transport.encode(buffer, output)
+}
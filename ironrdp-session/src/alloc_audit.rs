@@ -0,0 +1,137 @@
+//! Allocation counting for the active stage hot path.
+//!
+//! The embedder has to install [`AuditAllocator`] as the global allocator:
+//!
+//! ```ignore
+//! #[global_allocator]
+//! static ALLOCATOR: ironrdp_session::alloc_audit::AuditAllocator = ironrdp_session::alloc_audit::AuditAllocator;
+//! ```
+//!
+//! Only the allocations made on the thread processing a frame while a subsystem is active are counted.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::fmt;
+
+const SUBSYSTEMS_COUNT: usize = 3;
+
+thread_local! {
+    static CURRENT_SUBSYSTEM: Cell<Option<Subsystem>> = const { Cell::new(None) };
+    static FRAME_ALLOCATIONS: Cell<[AllocationStats; SUBSYSTEMS_COUNT]> =
+        const { Cell::new([AllocationStats::EMPTY; SUBSYSTEMS_COUNT]) };
+}
+
+pub struct AuditAllocator;
+
+unsafe impl GlobalAlloc for AuditAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        record_allocation(layout.size());
+        System.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        record_allocation(layout.size());
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        record_allocation(new_size);
+        System.realloc(ptr, layout, new_size)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Subsystem {
+    X224,
+    FastPath,
+    RemoteFx,
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct AllocationStats {
+    pub allocations: u64,
+    pub bytes: u64,
+}
+
+impl AllocationStats {
+    const EMPTY: Self = Self {
+        allocations: 0,
+        bytes: 0,
+    };
+}
+
+/// Allocations made while processing a single frame, by subsystem.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct FrameAllocations {
+    pub x224: AllocationStats,
+    pub fast_path: AllocationStats,
+    pub remote_fx: AllocationStats,
+}
+
+impl fmt::Display for FrameAllocations {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "X.224: {} ({} bytes), Fast-Path: {} ({} bytes), RemoteFX: {} ({} bytes)",
+            self.x224.allocations,
+            self.x224.bytes,
+            self.fast_path.allocations,
+            self.fast_path.bytes,
+            self.remote_fx.allocations,
+            self.remote_fx.bytes,
+        )
+    }
+}
+
+/// Attributes the allocations to the subsystem until dropped, then restores the previous one.
+pub(crate) struct SubsystemGuard {
+    previous: Option<Subsystem>,
+}
+
+impl Drop for SubsystemGuard {
+    fn drop(&mut self) {
+        let _ = CURRENT_SUBSYSTEM.try_with(|current| current.set(self.previous));
+    }
+}
+
+pub(crate) fn enter(subsystem: Subsystem) -> SubsystemGuard {
+    let previous = CURRENT_SUBSYSTEM
+        .try_with(|current| current.replace(Some(subsystem)))
+        .unwrap_or(None);
+
+    SubsystemGuard { previous }
+}
+
+pub(crate) fn start_frame() {
+    let _ = FRAME_ALLOCATIONS.try_with(|stats| stats.set([AllocationStats::EMPTY; SUBSYSTEMS_COUNT]));
+}
+
+pub(crate) fn finish_frame() -> FrameAllocations {
+    let [x224, fast_path, remote_fx] = FRAME_ALLOCATIONS
+        .try_with(Cell::get)
+        .unwrap_or([AllocationStats::EMPTY; SUBSYSTEMS_COUNT]);
+
+    FrameAllocations {
+        x224,
+        fast_path,
+        remote_fx,
+    }
+}
+
+fn record_allocation(size: usize) {
+    let Ok(Some(subsystem)) = CURRENT_SUBSYSTEM.try_with(Cell::get) else {
+        return;
+    };
+
+    let _ = FRAME_ALLOCATIONS.try_with(|stats| {
+        let mut frame_stats = stats.get();
+        let subsystem_stats = &mut frame_stats[subsystem as usize];
+        subsystem_stats.allocations += 1;
+        subsystem_stats.bytes += size as u64;
+        stats.set(frame_stats);
+    });
+}
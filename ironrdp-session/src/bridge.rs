@@ -0,0 +1,555 @@
+#[cfg(test)]
+mod tests;
+
+mod policy;
+
+use std::cmp;
+use std::collections::HashMap;
+
+use ironrdp::consts::CHANNEL_CHUNK_LENGTH;
+use ironrdp::input::fast_path::FastPathInputEvent;
+use ironrdp::rdp::vc::dvc::{self, DataFirstPdu, DataPdu, FieldType};
+use ironrdp::rdp::vc::{ChannelControlFlags, ChannelError, ChannelPduHeader, DvcName, StaticChannelName};
+use ironrdp::{ChannelId, PduParsing};
+
+use crate::connection_sequence::StaticChannels;
+use crate::{RdpError, GLOBAL_CHANNEL_NAME, USER_CHANNEL_NAME};
+
+pub use self::policy::{
+    ClipboardPolicy, ClipboardTransfer, ClipboardTransferKind, InputEventKind, InputPolicy, PolicyDecision,
+    PolicyEvent, PolicyEventHook, PolicySubject,
+};
+
+/// The side of a proxy a message has been received from.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum BridgeSide {
+    /// The client connected to the proxy.
+    Downstream,
+    /// The server the proxy is connected to.
+    Upstream,
+}
+
+impl BridgeSide {
+    pub fn opposite(self) -> Self {
+        match self {
+            BridgeSide::Downstream => BridgeSide::Upstream,
+            BridgeSide::Upstream => BridgeSide::Downstream,
+        }
+    }
+}
+
+/// Transforms a complete message of a bridged channel received from the side before it is forwarded,
+/// the message being dropped when `None` is returned.
+pub type TransformHook = Box<dyn FnMut(BridgeSide, Vec<u8>) -> Option<Vec<u8>> + Send>;
+
+/// The chunks to be sent to the other side of the proxy, each in its own MCS Send Data PDU.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BridgedChunks {
+    pub to: BridgeSide,
+    /// The ID of the static channel on the side the chunks are sent to.
+    pub channel_id: ChannelId,
+    /// The chunks starting with their Channel PDU Header, none being sent until a message is complete.
+    pub chunks: Vec<Vec<u8>>,
+}
+
+/// Pairs the static channels joined by the client of a proxy with those joined by the proxy on the server,
+/// and forwards the messages between them.
+///
+/// The static channel messages are reassembled and split again according to the chunk length of the side
+/// they are sent to. The messages of the dynamic channels multiplexed by `drdynvc` are forwarded as they are,
+/// unless a transform is registered for the channel, in which case they are reassembled from their Data First
+/// and Data PDUs, transformed, and split again. The IDs of the dynamic channels being assigned by the server,
+/// they are the same on both sides.
+///
+/// The clipboard transfers of the `cliprdr` channel are submitted to the clipboard policy before being
+/// transformed, and the input events relayed by the proxy to the input policy with [`Self::filter_input`].
+pub struct ChannelBridge {
+    static_channels: Vec<BridgedStaticChannel>,
+    downstream_chunk_length: usize,
+    upstream_chunk_length: usize,
+    dynamic_channels: HashMap<u32, DvcName>,
+    dynamic_messages: HashMap<(BridgeSide, u32), PartialMessage>,
+    static_transforms: HashMap<StaticChannelName, TransformHook>,
+    dynamic_transforms: HashMap<DvcName, TransformHook>,
+    clipboard_policy: Option<ClipboardPolicy>,
+    input_policy: Option<InputPolicy>,
+    policy_hooks: Vec<PolicyEventHook>,
+}
+
+impl ChannelBridge {
+    /// Pairs the static channels joined on both sides by name, the I/O and user channels being left
+    /// to the proxy.
+    pub fn new(downstream: &StaticChannels, upstream: &StaticChannels) -> Self {
+        let static_channels = downstream
+            .iter()
+            .filter(|(name, _)| **name != GLOBAL_CHANNEL_NAME && **name != USER_CHANNEL_NAME)
+            .filter_map(|(name, downstream_id)| {
+                upstream.get(name).map(|upstream_id| BridgedStaticChannel {
+                    name: name.clone(),
+                    downstream_id: *downstream_id,
+                    upstream_id: *upstream_id,
+                    downstream_message: None,
+                    upstream_message: None,
+                })
+            })
+            .collect();
+
+        Self {
+            static_channels,
+            downstream_chunk_length: CHANNEL_CHUNK_LENGTH,
+            upstream_chunk_length: CHANNEL_CHUNK_LENGTH,
+            dynamic_channels: HashMap::new(),
+            dynamic_messages: HashMap::new(),
+            static_transforms: HashMap::new(),
+            dynamic_transforms: HashMap::new(),
+            clipboard_policy: None,
+            input_policy: None,
+            policy_hooks: Vec::new(),
+        }
+    }
+
+    /// Sets the length of the chunks sent to the side, as advertised in its Virtual Channel Capability Set.
+    /// Defaults to the length every peer accepts.
+    pub fn with_chunk_length(mut self, side: BridgeSide, chunk_length: usize) -> Self {
+        match side {
+            BridgeSide::Downstream => self.downstream_chunk_length = chunk_length,
+            BridgeSide::Upstream => self.upstream_chunk_length = chunk_length,
+        }
+        self
+    }
+
+    /// Transforms the messages of the static channel, `drdynvc` excepted.
+    pub fn with_static_transform(
+        mut self,
+        name: StaticChannelName,
+        hook: impl FnMut(BridgeSide, Vec<u8>) -> Option<Vec<u8>> + Send + 'static,
+    ) -> Self {
+        self.static_transforms.insert(name, Box::new(hook));
+        self
+    }
+
+    pub fn with_dynamic_transform(
+        mut self,
+        name: DvcName,
+        hook: impl FnMut(BridgeSide, Vec<u8>) -> Option<Vec<u8>> + Send + 'static,
+    ) -> Self {
+        self.dynamic_transforms.insert(name, Box::new(hook));
+        self
+    }
+
+    /// Decides on the clipboard transfers of both directions, all being allowed by default.
+    pub fn with_clipboard_policy(
+        mut self,
+        policy: impl FnMut(&ClipboardTransfer<'_>) -> PolicyDecision + Send + 'static,
+    ) -> Self {
+        self.clipboard_policy = Some(Box::new(policy));
+        self
+    }
+
+    /// Decides on the input events passed to [`Self::filter_input`], all being allowed by default.
+    pub fn with_input_policy(
+        mut self,
+        policy: impl FnMut(&FastPathInputEvent) -> PolicyDecision + Send + 'static,
+    ) -> Self {
+        self.input_policy = Some(Box::new(policy));
+        self
+    }
+
+    /// Reports the decisions of the policies, in addition to the log.
+    pub fn with_policy_event_hook(mut self, hook: impl FnMut(&PolicyEvent) + Send + 'static) -> Self {
+        self.policy_hooks.push(Box::new(hook));
+        self
+    }
+
+    /// Returns the ID of the channel paired on the other side with the channel of the side, if it is bridged.
+    pub fn bridged_channel_id(&self, from: BridgeSide, channel_id: ChannelId) -> Option<ChannelId> {
+        self.static_channels
+            .iter()
+            .find(|channel| channel.id(from) == channel_id)
+            .map(|channel| channel.id(from.opposite()))
+    }
+
+    /// The open dynamic channels, as created by the server.
+    pub fn dynamic_channels(&self) -> impl Iterator<Item = (u32, &DvcName)> {
+        self.dynamic_channels
+            .iter()
+            .map(|(channel_id, name)| (*channel_id, name))
+    }
+
+    /// Processes a chunk received from the side on a static channel, returning the chunks to be sent
+    /// to the other side once the message is complete.
+    pub fn pump(&mut self, from: BridgeSide, channel_id: ChannelId, chunk: &[u8]) -> Result<BridgedChunks, RdpError> {
+        let channel = self
+            .static_channels
+            .iter_mut()
+            .find(|channel| channel.id(from) == channel_id)
+            .ok_or(RdpError::UnexpectedChannel(channel_id))?;
+        let to = from.opposite();
+        let mut bridged = BridgedChunks {
+            to,
+            channel_id: channel.id(to),
+            chunks: Vec::new(),
+        };
+
+        let header = ChannelPduHeader::from_buffer(chunk)?;
+        if header.flags.contains(ChannelControlFlags::PACKET_COMPRESSED) {
+            return Err(RdpError::CompressedChannelData(channel_id));
+        }
+
+        let data = &chunk[header.buffer_length()..];
+        let Some(message) = channel.reassemble(from, header, data)? else {
+            return Ok(bridged);
+        };
+        let name = channel.name.clone();
+
+        let messages = if name == StaticChannelName::DRDYNVC {
+            self.bridge_dynamic(from, message)?
+        } else {
+            let message = if name == StaticChannelName::CLIPRDR {
+                self.apply_clipboard_policy(from, message)
+            } else {
+                message
+            };
+
+            match self.static_transforms.get_mut(&name) {
+                Some(transform) => transform(from, message).into_iter().collect(),
+                None => vec![message],
+            }
+        };
+
+        let chunk_length = match to {
+            BridgeSide::Downstream => self.downstream_chunk_length,
+            BridgeSide::Upstream => self.upstream_chunk_length,
+        };
+        for message in messages {
+            split_into_chunks(&message, chunk_length, &mut bridged.chunks)?;
+        }
+
+        Ok(bridged)
+    }
+
+    /// Applies the input policy to the events received from the client, returning the events to be relayed
+    /// to the server.
+    pub fn filter_input(&mut self, events: Vec<FastPathInputEvent>) -> Vec<FastPathInputEvent> {
+        let Some(policy) = self.input_policy.as_mut() else {
+            return events;
+        };
+
+        let mut decisions = Vec::with_capacity(events.len());
+        let events = events
+            .into_iter()
+            .filter(|event| {
+                let decision = policy(event);
+                decisions.push(PolicyEvent {
+                    subject: PolicySubject::Input {
+                        kind: InputEventKind::from(event),
+                    },
+                    decision,
+                });
+
+                decision == PolicyDecision::Allow
+            })
+            .collect();
+
+        for decision in decisions {
+            self.notify_policy_event(decision);
+        }
+
+        events
+    }
+
+    /// Returns the message of the `cliprdr` channel to be forwarded, replaced if the transfer is denied.
+    fn apply_clipboard_policy(&mut self, from: BridgeSide, message: Vec<u8>) -> Vec<u8> {
+        let Some(policy) = self.clipboard_policy.as_mut() else {
+            return message;
+        };
+        let Some(transfer) = policy::clipboard_transfer(from, &message) else {
+            return message;
+        };
+
+        let decision = policy(&transfer);
+        let kind = transfer.kind;
+        let event = PolicyEvent {
+            subject: PolicySubject::Clipboard {
+                from,
+                kind,
+                length: transfer.data.len(),
+            },
+            decision,
+        };
+        self.notify_policy_event(event);
+
+        match decision {
+            PolicyDecision::Allow => message,
+            PolicyDecision::Deny => policy::denied_clipboard_message(&message, kind),
+        }
+    }
+
+    fn notify_policy_event(&mut self, event: PolicyEvent) {
+        match event.decision {
+            PolicyDecision::Allow => debug!("Policy decision: {:?}", event),
+            PolicyDecision::Deny => info!("Policy decision: {:?}", event),
+        }
+
+        for hook in self.policy_hooks.iter_mut() {
+            hook(&event);
+        }
+    }
+
+    /// Forwards the `drdynvc` PDU, returning the PDUs to be sent in its place.
+    fn bridge_dynamic(&mut self, from: BridgeSide, pdu: Vec<u8>) -> Result<Vec<Vec<u8>>, RdpError> {
+        let (channel_id, message) = match decode_dvc_pdu(from, &pdu)? {
+            DvcPdu::CreateRequest(create_request) => {
+                match DvcName::new(create_request.channel_name.as_str()) {
+                    Ok(name) => {
+                        self.dynamic_channels.insert(create_request.channel_id, name);
+                    }
+                    Err(e) => warn!("Invalid DVC name: {}", e),
+                }
+
+                return Ok(vec![pdu]);
+            }
+            DvcPdu::Close(channel_id) => {
+                self.dynamic_channels.remove(&channel_id);
+                self.dynamic_messages.remove(&(BridgeSide::Downstream, channel_id));
+                self.dynamic_messages.remove(&(BridgeSide::Upstream, channel_id));
+
+                return Ok(vec![pdu]);
+            }
+            DvcPdu::DataFirst(data_first) if self.is_transformed(data_first.channel_id) => {
+                let data = pdu[data_first.buffer_length()..].to_vec();
+                let message = PartialMessage::new(data_first.total_data_size as usize, data)?;
+                if message.is_complete() {
+                    (data_first.channel_id, message.data)
+                } else {
+                    self.dynamic_messages.insert((from, data_first.channel_id), message);
+
+                    return Ok(Vec::new());
+                }
+            }
+            DvcPdu::Data(data) if self.is_transformed(data.channel_id) => {
+                let payload = &pdu[data.buffer_length()..];
+                match self.dynamic_messages.get_mut(&(from, data.channel_id)) {
+                    Some(message) => {
+                        message.extend(payload)?;
+                        if !message.is_complete() {
+                            return Ok(Vec::new());
+                        }
+
+                        let message = self.dynamic_messages.remove(&(from, data.channel_id)).unwrap();
+                        (data.channel_id, message.data)
+                    }
+                    None => (data.channel_id, payload.to_vec()),
+                }
+            }
+            _ => return Ok(vec![pdu]),
+        };
+
+        let transform = self
+            .dynamic_channels
+            .get(&channel_id)
+            .and_then(|name| self.dynamic_transforms.get_mut(name))
+            .expect("transformed channel");
+
+        match transform(from, message) {
+            Some(message) => encode_dvc_data(channel_id, &message),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    fn is_transformed(&self, channel_id: u32) -> bool {
+        self.dynamic_channels
+            .get(&channel_id)
+            .map_or(false, |name| self.dynamic_transforms.contains_key(name))
+    }
+}
+
+struct BridgedStaticChannel {
+    name: StaticChannelName,
+    downstream_id: ChannelId,
+    upstream_id: ChannelId,
+    /// The chunks received from each side which do not complete a message yet.
+    downstream_message: Option<PartialMessage>,
+    upstream_message: Option<PartialMessage>,
+}
+
+impl BridgedStaticChannel {
+    fn id(&self, side: BridgeSide) -> ChannelId {
+        match side {
+            BridgeSide::Downstream => self.downstream_id,
+            BridgeSide::Upstream => self.upstream_id,
+        }
+    }
+
+    /// Returns the message once its last chunk has been received.
+    fn reassemble(
+        &mut self,
+        from: BridgeSide,
+        header: ChannelPduHeader,
+        data: &[u8],
+    ) -> Result<Option<Vec<u8>>, RdpError> {
+        let partial = match from {
+            BridgeSide::Downstream => &mut self.downstream_message,
+            BridgeSide::Upstream => &mut self.upstream_message,
+        };
+
+        if header.flags.contains(ChannelControlFlags::FLAG_FIRST) {
+            if partial.is_some() {
+                warn!("Incomplete message on the {} channel, it will be skipped", self.name);
+            }
+            *partial = Some(PartialMessage::new(header.total_length as usize, data.to_vec())?);
+        } else {
+            partial
+                .as_mut()
+                .ok_or(ChannelError::InvalidChannelPduHeader)?
+                .extend(data)?;
+        }
+
+        if !header.flags.contains(ChannelControlFlags::FLAG_LAST) {
+            return Ok(None);
+        }
+
+        let message = partial.take().unwrap();
+        if message.is_complete() {
+            Ok(Some(message.data))
+        } else {
+            Err(ChannelError::InvalidChannelTotalDataLength.into())
+        }
+    }
+}
+
+struct PartialMessage {
+    total_length: usize,
+    data: Vec<u8>,
+}
+
+impl PartialMessage {
+    fn new(total_length: usize, data: Vec<u8>) -> Result<Self, ChannelError> {
+        if data.len() > total_length {
+            return Err(ChannelError::InvalidChannelTotalDataLength);
+        }
+
+        Ok(Self { total_length, data })
+    }
+
+    fn extend(&mut self, data: &[u8]) -> Result<(), ChannelError> {
+        if self.data.len() + data.len() > self.total_length {
+            return Err(ChannelError::InvalidChannelTotalDataLength);
+        }
+        self.data.extend_from_slice(data);
+
+        Ok(())
+    }
+
+    fn is_complete(&self) -> bool {
+        self.data.len() == self.total_length
+    }
+}
+
+/// The `drdynvc` PDUs of both directions, as far as the bridge is concerned.
+enum DvcPdu {
+    CreateRequest(dvc::CreateRequestPdu),
+    DataFirst(DataFirstPdu),
+    Data(DataPdu),
+    Close(u32),
+    Other,
+}
+
+fn decode_dvc_pdu(from: BridgeSide, pdu: &[u8]) -> Result<DvcPdu, RdpError> {
+    let pdu = match from {
+        BridgeSide::Upstream => match dvc::ServerPdu::from_buffer(pdu, pdu.len())? {
+            dvc::ServerPdu::CreateRequest(create_request) => DvcPdu::CreateRequest(create_request),
+            dvc::ServerPdu::DataFirst(data_first) => DvcPdu::DataFirst(data_first),
+            dvc::ServerPdu::Data(data) => DvcPdu::Data(data),
+            dvc::ServerPdu::CloseRequest(close_request) => DvcPdu::Close(close_request.channel_id),
+            dvc::ServerPdu::CapabilitiesRequest(_) => DvcPdu::Other,
+        },
+        BridgeSide::Downstream => match dvc::ClientPdu::from_buffer(pdu, pdu.len())? {
+            dvc::ClientPdu::DataFirst(data_first) => DvcPdu::DataFirst(data_first),
+            dvc::ClientPdu::Data(data) => DvcPdu::Data(data),
+            dvc::ClientPdu::CloseResponse(close_response) => DvcPdu::Close(close_response.channel_id),
+            dvc::ClientPdu::CapabilitiesResponse(_) | dvc::ClientPdu::CreateResponse(_) => DvcPdu::Other,
+        },
+    };
+
+    Ok(pdu)
+}
+
+/// Splits the message of the dynamic channel into a Data PDU if it fits in one,
+/// or into a Data First PDU followed by Data PDUs.
+fn encode_dvc_data(channel_id: u32, message: &[u8]) -> Result<Vec<Vec<u8>>, RdpError> {
+    let channel_id_type = field_type(channel_id);
+    let data = DataPdu {
+        channel_id_type,
+        channel_id,
+        data_size: 0,
+    };
+    let data_first = DataFirstPdu {
+        channel_id_type,
+        channel_id,
+        total_data_size_type: field_type(message.len() as u32),
+        total_data_size: message.len() as u32,
+        data_size: 0,
+    };
+
+    let mut pdus = Vec::new();
+    let mut offset = 0;
+    if data.buffer_length() + message.len() > CHANNEL_CHUNK_LENGTH {
+        offset = CHANNEL_CHUNK_LENGTH - data_first.buffer_length();
+        let mut pdu = Vec::with_capacity(CHANNEL_CHUNK_LENGTH);
+        data_first.to_buffer(&mut pdu)?;
+        pdu.extend_from_slice(&message[..offset]);
+        pdus.push(pdu);
+    }
+
+    while offset < message.len() || pdus.is_empty() {
+        let end = cmp::min(offset + CHANNEL_CHUNK_LENGTH - data.buffer_length(), message.len());
+        let mut pdu = Vec::with_capacity(data.buffer_length() + end - offset);
+        data.to_buffer(&mut pdu)?;
+        pdu.extend_from_slice(&message[offset..end]);
+        pdus.push(pdu);
+        offset = end;
+    }
+
+    Ok(pdus)
+}
+
+fn field_type(value: u32) -> FieldType {
+    if value <= u32::from(u8::MAX) {
+        FieldType::U8
+    } else if value <= u32::from(u16::MAX) {
+        FieldType::U16
+    } else {
+        FieldType::U32
+    }
+}
+
+fn split_into_chunks(message: &[u8], chunk_length: usize, chunks: &mut Vec<Vec<u8>>) -> Result<(), RdpError> {
+    let total_length = message.len();
+    let mut offset = 0;
+
+    loop {
+        let end = cmp::min(offset + chunk_length, total_length);
+        let mut flags = ChannelControlFlags::empty();
+        if offset == 0 {
+            flags |= ChannelControlFlags::FLAG_FIRST;
+        }
+        if end == total_length {
+            flags |= ChannelControlFlags::FLAG_LAST;
+        }
+        let header = ChannelPduHeader {
+            total_length: total_length as u32,
+            flags,
+        };
+
+        let mut chunk = Vec::with_capacity(header.buffer_length() + end - offset);
+        header.to_buffer(&mut chunk)?;
+        chunk.extend_from_slice(&message[offset..end]);
+        chunks.push(chunk);
+
+        if end == total_length {
+            return Ok(());
+        }
+        offset = end;
+    }
+}
@@ -0,0 +1,142 @@
+//! Policies of the proxy on the clipboard transfers and the input events crossing it, e.g. for data loss
+//! prevention, each decision being reported to the policy event hooks of the [`ChannelBridge`](super::ChannelBridge).
+
+#[cfg(test)]
+mod tests;
+
+use ironrdp::input::fast_path::FastPathInputEvent;
+
+use super::BridgeSide;
+
+const CLIPRDR_HEADER_SIZE: usize = 8;
+const STREAM_ID_SIZE: usize = 4;
+
+const CB_FORMAT_LIST: u16 = 0x0002;
+const CB_FORMAT_DATA_RESPONSE: u16 = 0x0005;
+const CB_FILECONTENTS_RESPONSE: u16 = 0x0009;
+
+const CB_RESPONSE_FAIL: u16 = 0x0002;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PolicyDecision {
+    Allow,
+    Deny,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ClipboardTransferKind {
+    /// The side announces the formats of its clipboard, an empty list being forwarded instead if denied.
+    FormatList,
+    /// The data of a clipboard format, a failure response being forwarded instead if denied.
+    FormatData,
+    /// A range of the contents of a copied file, a failure response being forwarded instead if denied.
+    FileContents,
+}
+
+/// A message of the `cliprdr` channel announcing or carrying the clipboard of a side, passed to the clipboard policy.
+///
+/// The requests are not passed, as they carry no data and are answered by the responses the policy decides on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClipboardTransfer<'a> {
+    pub from: BridgeSide,
+    pub kind: ClipboardTransferKind,
+    /// The body of the message following its header, the stream ID of the file contents excluded.
+    pub data: &'a [u8],
+}
+
+pub type ClipboardPolicy = Box<dyn FnMut(&ClipboardTransfer<'_>) -> PolicyDecision + Send>;
+
+/// Decides on the input events sent by the client to the server.
+///
+/// A policy denying the key presses is to deny the matching releases as well, for the keys not to be held
+/// on the server.
+pub type InputPolicy = Box<dyn FnMut(&FastPathInputEvent) -> PolicyDecision + Send>;
+
+/// The kind of an input event, the key codes being left out of the policy events.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum InputEventKind {
+    Keyboard,
+    Unicode,
+    Mouse,
+    Synchronize,
+    QualityOfExperience,
+}
+
+impl From<&FastPathInputEvent> for InputEventKind {
+    fn from(event: &FastPathInputEvent) -> Self {
+        match event {
+            FastPathInputEvent::KeyboardEvent(..) => Self::Keyboard,
+            FastPathInputEvent::UnicodeKeyboardEvent(..) => Self::Unicode,
+            FastPathInputEvent::MouseEvent(_) | FastPathInputEvent::MouseEventEx(_) => Self::Mouse,
+            FastPathInputEvent::SyncEvent(_) => Self::Synchronize,
+            FastPathInputEvent::QoeEvent(_) => Self::QualityOfExperience,
+        }
+    }
+}
+
+/// What a policy has decided on, without the data of the clipboard or the key codes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PolicySubject {
+    Clipboard {
+        from: BridgeSide,
+        kind: ClipboardTransferKind,
+        length: usize,
+    },
+    Input {
+        kind: InputEventKind,
+    },
+}
+
+/// A decision of a policy, passed to the policy event hooks, e.g. for the audit trail of the proxy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PolicyEvent {
+    pub subject: PolicySubject,
+    pub decision: PolicyDecision,
+}
+
+pub type PolicyEventHook = Box<dyn FnMut(&PolicyEvent) + Send>;
+
+/// Parses the message of the `cliprdr` channel if it is a transfer the clipboard policy decides on.
+pub(super) fn clipboard_transfer(from: BridgeSide, message: &[u8]) -> Option<ClipboardTransfer<'_>> {
+    if message.len() < CLIPRDR_HEADER_SIZE {
+        return None;
+    }
+
+    let msg_type = u16::from_le_bytes([message[0], message[1]]);
+    let msg_flags = u16::from_le_bytes([message[2], message[3]]);
+    let body = &message[CLIPRDR_HEADER_SIZE..];
+
+    let (kind, data) = match msg_type {
+        CB_FORMAT_LIST => (ClipboardTransferKind::FormatList, body),
+        // the failure responses carry no data
+        _ if msg_flags & CB_RESPONSE_FAIL != 0 => return None,
+        CB_FORMAT_DATA_RESPONSE => (ClipboardTransferKind::FormatData, body),
+        CB_FILECONTENTS_RESPONSE if body.len() >= STREAM_ID_SIZE => {
+            (ClipboardTransferKind::FileContents, &body[STREAM_ID_SIZE..])
+        }
+        _ => return None,
+    };
+
+    Some(ClipboardTransfer { from, kind, data })
+}
+
+/// The message forwarded in place of the denied transfer, for the other side not to wait for a response.
+pub(super) fn denied_clipboard_message(message: &[u8], kind: ClipboardTransferKind) -> Vec<u8> {
+    let (msg_type, msg_flags, body) = match kind {
+        ClipboardTransferKind::FormatList => (CB_FORMAT_LIST, u16::from_le_bytes([message[2], message[3]]), &[][..]),
+        ClipboardTransferKind::FormatData => (CB_FORMAT_DATA_RESPONSE, CB_RESPONSE_FAIL, &[][..]),
+        ClipboardTransferKind::FileContents => (
+            CB_FILECONTENTS_RESPONSE,
+            CB_RESPONSE_FAIL,
+            &message[CLIPRDR_HEADER_SIZE..CLIPRDR_HEADER_SIZE + STREAM_ID_SIZE],
+        ),
+    };
+
+    let mut denied = Vec::with_capacity(CLIPRDR_HEADER_SIZE + body.len());
+    denied.extend_from_slice(&msg_type.to_le_bytes());
+    denied.extend_from_slice(&msg_flags.to_le_bytes());
+    denied.extend_from_slice(&(body.len() as u32).to_le_bytes());
+    denied.extend_from_slice(body);
+
+    denied
+}
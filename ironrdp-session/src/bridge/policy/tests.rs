@@ -0,0 +1,65 @@
+use super::*;
+
+fn cliprdr_message(msg_type: u16, msg_flags: u16, body: &[u8]) -> Vec<u8> {
+    [
+        msg_type.to_le_bytes().as_ref(),
+        msg_flags.to_le_bytes().as_ref(),
+        (body.len() as u32).to_le_bytes().as_ref(),
+        body,
+    ]
+    .concat()
+}
+
+#[test]
+fn clipboard_transfers_carry_the_data_of_the_clipboard() {
+    let format_list = cliprdr_message(CB_FORMAT_LIST, 0, &[0x0d, 0x00, 0x00, 0x00, 0x00, 0x00]);
+    assert_eq!(
+        Some(ClipboardTransfer {
+            from: BridgeSide::Downstream,
+            kind: ClipboardTransferKind::FormatList,
+            data: &[0x0d, 0x00, 0x00, 0x00, 0x00, 0x00],
+        }),
+        clipboard_transfer(BridgeSide::Downstream, &format_list)
+    );
+
+    let file_contents = cliprdr_message(CB_FILECONTENTS_RESPONSE, 0x0001, &[7, 0, 0, 0, b'a', b'b']);
+    assert_eq!(
+        Some(ClipboardTransfer {
+            from: BridgeSide::Upstream,
+            kind: ClipboardTransferKind::FileContents,
+            data: b"ab",
+        }),
+        clipboard_transfer(BridgeSide::Upstream, &file_contents)
+    );
+}
+
+#[test]
+fn requests_and_failed_responses_are_not_transfers() {
+    let format_data_request = cliprdr_message(0x0004, 0, &[0x0d, 0x00, 0x00, 0x00]);
+    let failed_response = cliprdr_message(CB_FORMAT_DATA_RESPONSE, CB_RESPONSE_FAIL, &[]);
+
+    assert_eq!(None, clipboard_transfer(BridgeSide::Upstream, &format_data_request));
+    assert_eq!(None, clipboard_transfer(BridgeSide::Upstream, &failed_response));
+    assert_eq!(None, clipboard_transfer(BridgeSide::Upstream, &[0x05, 0x00]));
+}
+
+#[test]
+fn denied_transfers_are_replaced_by_empty_or_failed_messages() {
+    let format_list = cliprdr_message(CB_FORMAT_LIST, 0x0004, &[0x0d, 0x00, 0x00, 0x00, 0x00, 0x00]);
+    assert_eq!(
+        cliprdr_message(CB_FORMAT_LIST, 0x0004, &[]),
+        denied_clipboard_message(&format_list, ClipboardTransferKind::FormatList)
+    );
+
+    let format_data = cliprdr_message(CB_FORMAT_DATA_RESPONSE, 0x0001, b"secret");
+    assert_eq!(
+        cliprdr_message(CB_FORMAT_DATA_RESPONSE, CB_RESPONSE_FAIL, &[]),
+        denied_clipboard_message(&format_data, ClipboardTransferKind::FormatData)
+    );
+
+    let file_contents = cliprdr_message(CB_FILECONTENTS_RESPONSE, 0x0001, &[7, 0, 0, 0, b'a', b'b']);
+    assert_eq!(
+        cliprdr_message(CB_FILECONTENTS_RESPONSE, CB_RESPONSE_FAIL, &[7, 0, 0, 0]),
+        denied_clipboard_message(&file_contents, ClipboardTransferKind::FileContents)
+    );
+}
@@ -0,0 +1,291 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use ironrdp::input::fast_path::{KeyboardFlags, SynchronizeFlags};
+use ironrdp::rdp::vc::dvc::CreateRequestPdu;
+
+use super::*;
+
+const DOWNSTREAM_CLIPRDR_ID: ChannelId = ChannelId(1004);
+const DOWNSTREAM_DRDYNVC_ID: ChannelId = ChannelId(1005);
+const UPSTREAM_CLIPRDR_ID: ChannelId = ChannelId(1006);
+const UPSTREAM_DRDYNVC_ID: ChannelId = ChannelId(1007);
+const ECHO_CHANNEL_ID: u32 = 3;
+
+fn bridge() -> ChannelBridge {
+    let downstream = HashMap::from([
+        (StaticChannelName::CLIPRDR, DOWNSTREAM_CLIPRDR_ID),
+        (StaticChannelName::DRDYNVC, DOWNSTREAM_DRDYNVC_ID),
+        (StaticChannelName::RDPSND, ChannelId(1008)),
+    ]);
+    let upstream = HashMap::from([
+        (GLOBAL_CHANNEL_NAME, ChannelId(1003)),
+        (USER_CHANNEL_NAME, ChannelId(1009)),
+        (StaticChannelName::CLIPRDR, UPSTREAM_CLIPRDR_ID),
+        (StaticChannelName::DRDYNVC, UPSTREAM_DRDYNVC_ID),
+    ]);
+
+    ChannelBridge::new(&downstream, &upstream)
+}
+
+fn chunk(flags: ChannelControlFlags, total_length: usize, data: &[u8]) -> Vec<u8> {
+    let mut chunk = Vec::new();
+    ChannelPduHeader {
+        total_length: total_length as u32,
+        flags,
+    }
+    .to_buffer(&mut chunk)
+    .unwrap();
+    chunk.extend_from_slice(data);
+
+    chunk
+}
+
+fn single_chunk(data: &[u8]) -> Vec<u8> {
+    chunk(
+        ChannelControlFlags::FLAG_FIRST | ChannelControlFlags::FLAG_LAST,
+        data.len(),
+        data,
+    )
+}
+
+fn create_request(channel_name: &str) -> Vec<u8> {
+    let mut pdu = Vec::new();
+    dvc::ServerPdu::CreateRequest(CreateRequestPdu {
+        channel_id_type: FieldType::U8,
+        channel_id: ECHO_CHANNEL_ID,
+        channel_name: String::from(channel_name),
+    })
+    .to_buffer(&mut pdu)
+    .unwrap();
+
+    pdu
+}
+
+#[test]
+fn only_channels_joined_on_both_sides_are_bridged() {
+    let bridge = bridge();
+
+    assert_eq!(
+        Some(UPSTREAM_CLIPRDR_ID),
+        bridge.bridged_channel_id(BridgeSide::Downstream, DOWNSTREAM_CLIPRDR_ID)
+    );
+    assert_eq!(
+        Some(DOWNSTREAM_DRDYNVC_ID),
+        bridge.bridged_channel_id(BridgeSide::Upstream, UPSTREAM_DRDYNVC_ID)
+    );
+    assert_eq!(None, bridge.bridged_channel_id(BridgeSide::Downstream, ChannelId(1008)));
+    assert_eq!(None, bridge.bridged_channel_id(BridgeSide::Upstream, ChannelId(1003)));
+}
+
+#[test]
+fn static_message_is_split_according_to_the_chunk_length_of_the_receiving_side() {
+    let mut bridge = bridge().with_chunk_length(BridgeSide::Upstream, 4);
+
+    let first = bridge
+        .pump(
+            BridgeSide::Downstream,
+            DOWNSTREAM_CLIPRDR_ID,
+            &chunk(ChannelControlFlags::FLAG_FIRST, 6, b"abc"),
+        )
+        .unwrap();
+    assert!(first.chunks.is_empty());
+
+    let last = bridge
+        .pump(
+            BridgeSide::Downstream,
+            DOWNSTREAM_CLIPRDR_ID,
+            &chunk(ChannelControlFlags::FLAG_LAST, 6, b"def"),
+        )
+        .unwrap();
+    assert_eq!(
+        BridgedChunks {
+            to: BridgeSide::Upstream,
+            channel_id: UPSTREAM_CLIPRDR_ID,
+            chunks: vec![
+                chunk(ChannelControlFlags::FLAG_FIRST, 6, b"abcd"),
+                chunk(ChannelControlFlags::FLAG_LAST, 6, b"ef"),
+            ],
+        },
+        last
+    );
+}
+
+#[test]
+fn static_transform_replaces_or_drops_the_message() {
+    let mut bridge = bridge().with_static_transform(StaticChannelName::CLIPRDR, |from, message| {
+        (from == BridgeSide::Upstream).then(|| message.to_ascii_uppercase())
+    });
+
+    let bridged = bridge
+        .pump(BridgeSide::Upstream, UPSTREAM_CLIPRDR_ID, &single_chunk(b"text"))
+        .unwrap();
+    assert_eq!(vec![single_chunk(b"TEXT")], bridged.chunks);
+
+    let bridged = bridge
+        .pump(BridgeSide::Downstream, DOWNSTREAM_CLIPRDR_ID, &single_chunk(b"text"))
+        .unwrap();
+    assert!(bridged.chunks.is_empty());
+}
+
+#[test]
+fn dynamic_message_is_reassembled_for_its_transform() {
+    let name = "ECHO";
+    let mut bridge = bridge().with_dynamic_transform(DvcName::new(name).unwrap(), |_, mut message| {
+        message.reverse();
+        Some(message)
+    });
+
+    let create_request = create_request(name);
+    let bridged = bridge
+        .pump(
+            BridgeSide::Upstream,
+            UPSTREAM_DRDYNVC_ID,
+            &single_chunk(&create_request),
+        )
+        .unwrap();
+    assert_eq!(vec![single_chunk(&create_request)], bridged.chunks);
+    assert_eq!(
+        vec![(ECHO_CHANNEL_ID, &DvcName::new(name).unwrap())],
+        bridge.dynamic_channels().collect::<Vec<_>>()
+    );
+
+    // Data First PDU with a total length of 4, then Data PDU.
+    let data_first = [0x20, ECHO_CHANNEL_ID as u8, 4, b'a', b'b'];
+    let data = [0x30, ECHO_CHANNEL_ID as u8, b'c', b'd'];
+    let bridged = bridge
+        .pump(
+            BridgeSide::Downstream,
+            DOWNSTREAM_DRDYNVC_ID,
+            &single_chunk(&data_first),
+        )
+        .unwrap();
+    assert!(bridged.chunks.is_empty());
+    let bridged = bridge
+        .pump(BridgeSide::Downstream, DOWNSTREAM_DRDYNVC_ID, &single_chunk(&data))
+        .unwrap();
+    assert_eq!(
+        vec![single_chunk(&[0x30, ECHO_CHANNEL_ID as u8, b'd', b'c', b'b', b'a'])],
+        bridged.chunks
+    );
+}
+
+#[test]
+fn large_dynamic_message_is_split_into_data_first_and_data_pdus() {
+    let message = vec![0xAB; 2 * CHANNEL_CHUNK_LENGTH];
+    let pdus = encode_dvc_data(ECHO_CHANNEL_ID, &message).unwrap();
+
+    assert_eq!(3, pdus.len());
+    assert_eq!([0x24, ECHO_CHANNEL_ID as u8, 0x80, 0x0C], pdus[0][..4]);
+    assert!(pdus.iter().all(|pdu| pdu.len() <= CHANNEL_CHUNK_LENGTH));
+    assert!(pdus[1..].iter().all(|pdu| pdu[0] == 0x30));
+    assert_eq!(
+        message.len(),
+        pdus[0].len() - 4 + pdus[1..].iter().map(|pdu| pdu.len() - 2).sum::<usize>()
+    );
+}
+
+#[test]
+fn compressed_chunk_is_rejected() {
+    let mut bridge = bridge();
+    let chunk = chunk(
+        ChannelControlFlags::FLAG_FIRST | ChannelControlFlags::FLAG_LAST | ChannelControlFlags::PACKET_COMPRESSED,
+        4,
+        b"data",
+    );
+
+    assert!(matches!(
+        bridge.pump(BridgeSide::Downstream, DOWNSTREAM_CLIPRDR_ID, &chunk),
+        Err(RdpError::CompressedChannelData(DOWNSTREAM_CLIPRDR_ID))
+    ));
+}
+
+#[test]
+fn denied_clipboard_data_is_replaced_by_failure_response() {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let recorded_events = Arc::clone(&events);
+    let mut bridge = bridge()
+        .with_clipboard_policy(|transfer| {
+            if transfer.from == BridgeSide::Upstream && transfer.kind == ClipboardTransferKind::FormatData {
+                PolicyDecision::Deny
+            } else {
+                PolicyDecision::Allow
+            }
+        })
+        .with_policy_event_hook(move |event| recorded_events.lock().unwrap().push(event.clone()));
+
+    // Format Data Response carrying "secret"
+    let response = [[0x05, 0x00, 0x01, 0x00, 0x06, 0x00, 0x00, 0x00].as_ref(), b"secret"].concat();
+    let bridged = bridge
+        .pump(BridgeSide::Upstream, UPSTREAM_CLIPRDR_ID, &single_chunk(&response))
+        .unwrap();
+    assert_eq!(
+        vec![single_chunk(&[0x05, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00])],
+        bridged.chunks
+    );
+
+    let bridged = bridge
+        .pump(BridgeSide::Downstream, DOWNSTREAM_CLIPRDR_ID, &single_chunk(&response))
+        .unwrap();
+    assert_eq!(vec![single_chunk(&response)], bridged.chunks);
+
+    assert_eq!(
+        vec![
+            PolicyEvent {
+                subject: PolicySubject::Clipboard {
+                    from: BridgeSide::Upstream,
+                    kind: ClipboardTransferKind::FormatData,
+                    length: 6,
+                },
+                decision: PolicyDecision::Deny,
+            },
+            PolicyEvent {
+                subject: PolicySubject::Clipboard {
+                    from: BridgeSide::Downstream,
+                    kind: ClipboardTransferKind::FormatData,
+                    length: 6,
+                },
+                decision: PolicyDecision::Allow,
+            },
+        ],
+        *events.lock().unwrap()
+    );
+}
+
+#[test]
+fn input_policy_filters_relayed_events() {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let recorded_events = Arc::clone(&events);
+    let mut bridge = bridge()
+        .with_input_policy(|event| match event {
+            FastPathInputEvent::KeyboardEvent(..) | FastPathInputEvent::UnicodeKeyboardEvent(..) => {
+                PolicyDecision::Deny
+            }
+            _ => PolicyDecision::Allow,
+        })
+        .with_policy_event_hook(move |event| recorded_events.lock().unwrap().push(event.clone()));
+
+    let relayed = bridge.filter_input(vec![
+        FastPathInputEvent::SyncEvent(SynchronizeFlags::empty()),
+        FastPathInputEvent::KeyboardEvent(KeyboardFlags::empty(), 0x1e),
+        FastPathInputEvent::KeyboardEvent(KeyboardFlags::FASTPATH_INPUT_KBDFLAGS_RELEASE, 0x1e),
+    ]);
+
+    assert_eq!(vec![FastPathInputEvent::SyncEvent(SynchronizeFlags::empty())], relayed);
+    assert_eq!(
+        vec![
+            (InputEventKind::Synchronize, PolicyDecision::Allow),
+            (InputEventKind::Keyboard, PolicyDecision::Deny),
+            (InputEventKind::Keyboard, PolicyDecision::Deny),
+        ],
+        events
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|event| match event.subject {
+                PolicySubject::Input { kind } => (kind, event.decision),
+                PolicySubject::Clipboard { .. } => unreachable!(),
+            })
+            .collect::<Vec<_>>()
+    );
+}
@@ -0,0 +1,128 @@
+//! Bounds of the reads waiting for the server, which would otherwise block forever if it stays silent.
+
+#[cfg(test)]
+mod tests;
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+use futures_util::future::poll_fn;
+
+use crate::RdpError;
+
+type Sleep = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// Signal stopping the reads of the session it is passed to, e.g. from another task when the user
+/// closes the session. The clones of the signal share its state.
+#[derive(Clone, Default)]
+pub struct ShutdownSignal {
+    state: Arc<ShutdownState>,
+}
+
+#[derive(Default)]
+struct ShutdownState {
+    triggered: AtomicBool,
+    wakers: Mutex<Vec<Waker>>,
+}
+
+impl ShutdownSignal {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stops the pending reads and the next ones with [`RdpError::ReadCancelled`].
+    pub fn trigger(&self) {
+        self.state.triggered.store(true, Ordering::SeqCst);
+
+        let wakers = std::mem::take(&mut *self.state.wakers.lock().unwrap());
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+
+    pub fn is_triggered(&self) -> bool {
+        self.state.triggered.load(Ordering::SeqCst)
+    }
+
+    fn poll_triggered(&self, cx: &mut Context<'_>) -> Poll<()> {
+        if self.is_triggered() {
+            return Poll::Ready(());
+        }
+
+        {
+            let mut wakers = self.state.wakers.lock().unwrap();
+            if !wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+                wakers.push(cx.waker().clone());
+            }
+        }
+
+        // the signal may have been triggered before the waker was registered
+        if self.is_triggered() {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+/// Deadline and shutdown signal of a read, for the reads of the active stage to be stopped the same way
+/// however they are driven. Without either, the read waits until the server sends a frame or closes the stream.
+///
+/// The session does not depend on an async runtime, so the deadline is a future provided by the embedder,
+/// e.g. `tokio::time::sleep(idle_timeout)`.
+#[derive(Default)]
+pub struct ReadCancellation {
+    deadline: Option<Sleep>,
+    shutdown: Option<ShutdownSignal>,
+}
+
+impl ReadCancellation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Gives up the read with [`RdpError::ReadTimedOut`] when `sleep` completes.
+    pub fn with_deadline(mut self, sleep: impl Future<Output = ()> + Send + 'static) -> Self {
+        self.deadline = Some(Box::pin(sleep));
+
+        self
+    }
+
+    /// Gives up the read with [`RdpError::ReadCancelled`] when the signal is triggered.
+    pub fn with_shutdown(mut self, shutdown: ShutdownSignal) -> Self {
+        self.shutdown = Some(shutdown);
+
+        self
+    }
+
+    /// Runs the read until it completes, the deadline elapses or the shutdown signal is triggered.
+    /// The shutdown takes precedence over the read, which takes precedence over the deadline.
+    pub(crate) async fn run<T>(self, read: impl Future<Output = Result<T, RdpError>>) -> Result<T, RdpError> {
+        let Self { mut deadline, shutdown } = self;
+        futures_util::pin_mut!(read);
+
+        poll_fn(|cx| {
+            if let Some(shutdown) = shutdown.as_ref() {
+                if shutdown.poll_triggered(cx).is_ready() {
+                    return Poll::Ready(Err(RdpError::ReadCancelled));
+                }
+            }
+
+            if let Poll::Ready(result) = read.as_mut().poll(cx) {
+                return Poll::Ready(result);
+            }
+
+            if let Some(deadline) = deadline.as_mut() {
+                if deadline.as_mut().poll(cx).is_ready() {
+                    return Poll::Ready(Err(RdpError::ReadTimedOut));
+                }
+            }
+
+            Poll::Pending
+        })
+        .await
+    }
+}
@@ -0,0 +1,50 @@
+use std::io;
+
+use futures_util::future;
+use futures_util::AsyncRead;
+
+use super::*;
+use crate::FramedReader;
+
+/// A server which never sends anything.
+struct SilentServer;
+
+impl AsyncRead for SilentServer {
+    fn poll_read(self: Pin<&mut Self>, _: &mut Context<'_>, _: &mut [u8]) -> Poll<io::Result<usize>> {
+        Poll::Pending
+    }
+}
+
+#[tokio::test]
+async fn read_of_silent_server_times_out_at_deadline() {
+    let mut reader = FramedReader::new(SilentServer);
+
+    let result = reader
+        .read_frame_cancellable(ReadCancellation::new().with_deadline(future::ready(())))
+        .await;
+
+    assert!(matches!(result, Err(RdpError::ReadTimedOut)));
+}
+
+#[tokio::test]
+async fn read_of_silent_server_is_cancelled_by_shutdown_signal() {
+    let mut reader = FramedReader::new(SilentServer);
+    let shutdown = ShutdownSignal::new();
+    let trigger = shutdown.clone();
+
+    tokio::spawn(async move { trigger.trigger() });
+    let result = reader
+        .read_frame_cancellable(ReadCancellation::new().with_shutdown(shutdown))
+        .await;
+
+    assert!(matches!(result, Err(RdpError::ReadCancelled)));
+}
+
+#[tokio::test]
+async fn completed_read_takes_precedence_over_deadline() {
+    let cancellation = ReadCancellation::new().with_deadline(future::ready(()));
+
+    let result = cancellation.run(future::ready(Ok::<_, RdpError>(1))).await;
+
+    assert_eq!(1, result.unwrap());
+}
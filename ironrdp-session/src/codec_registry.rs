@@ -0,0 +1,152 @@
+//! The graphics codecs usable during the session.
+//!
+//! Each codec is gated behind its own cargo feature (`rfx`, `zgfx`, `h264`, `bitmap`, `nscodec`), so the binary size and
+//! the compile time can be reduced by disabling the unneeded ones (e.g. for WASM builds). The
+//! registry starts from the compiled-in codecs, some of them can additionally be disabled at runtime.
+//! The capabilities advertised to the server are derived from the registry.
+
+#[cfg(test)]
+mod tests;
+
+use std::fmt;
+
+use ironrdp::rdp::capability_sets::{
+    BitmapCodecs, CaptureFlags, CodecGuid, EntropyBits, NsCodec, RfxCaps, RfxCapset, RfxClientCapsContainer, RfxICap,
+    RfxICapFlags,
+};
+
+/// The highest color loss level the server may reduce the chroma of the NSCodec bitmaps with.
+const NSCODEC_COLOR_LOSS_LEVEL: u8 = 3;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Codec {
+    /// RemoteFX, sent through the surface commands.
+    RemoteFx,
+    /// RDP 8.0 bulk compression, required by the Graphics Pipeline, with the ClearCodec, planar
+    /// and RemoteFX Progressive surfaces.
+    Zgfx,
+    /// AVC420 and AVC444 Graphics Pipeline codecs.
+    H264,
+    /// Uncompressed and RDP 6.0 planar bitmaps, sent through the bitmap updates and the bitmap cache orders.
+    Bitmap,
+    /// NSCodec, sent through the surface commands.
+    NsCodec,
+}
+
+impl Codec {
+    pub const ALL: [Codec; 5] = [Codec::RemoteFx, Codec::Zgfx, Codec::H264, Codec::Bitmap, Codec::NsCodec];
+
+    /// The cargo feature the codec is gated behind.
+    pub fn feature_name(self) -> &'static str {
+        match self {
+            Codec::RemoteFx => "rfx",
+            Codec::Zgfx => "zgfx",
+            Codec::H264 => "h264",
+            Codec::Bitmap => "bitmap",
+            Codec::NsCodec => "nscodec",
+        }
+    }
+
+    /// The codec of the Bitmap Codecs Capability Set, `None` being returned for the codecs
+    /// which are not sent through the surface commands.
+    pub fn bitmap_codec(self) -> Option<CodecGuid> {
+        match self {
+            Codec::RemoteFx => Some(CodecGuid::RemoteFx),
+            Codec::NsCodec => Some(CodecGuid::NsCodec),
+            Codec::Zgfx | Codec::H264 | Codec::Bitmap => None,
+        }
+    }
+
+    pub fn is_compiled_in(self) -> bool {
+        match self {
+            Codec::RemoteFx => cfg!(feature = "rfx"),
+            Codec::Zgfx => cfg!(feature = "zgfx"),
+            Codec::H264 => cfg!(feature = "h264"),
+            Codec::Bitmap => cfg!(feature = "bitmap"),
+            Codec::NsCodec => cfg!(feature = "nscodec"),
+        }
+    }
+}
+
+impl fmt::Display for Codec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Codec::RemoteFx => "RemoteFX",
+            Codec::Zgfx => "ZGFX",
+            Codec::H264 => "H.264",
+            Codec::Bitmap => "Bitmap",
+            Codec::NsCodec => "NSCodec",
+        };
+
+        f.write_str(name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CodecRegistry {
+    codecs: Vec<Codec>,
+}
+
+impl CodecRegistry {
+    /// All the codecs compiled into this build.
+    pub fn compiled_in() -> Self {
+        Self {
+            codecs: Codec::ALL
+                .iter()
+                .copied()
+                .filter(|codec| codec.is_compiled_in())
+                .collect(),
+        }
+    }
+
+    /// Prevents the codec from being advertised to the server.
+    pub fn disable(&mut self, codec: Codec) {
+        self.codecs.retain(|&registered| registered != codec);
+    }
+
+    pub fn without(mut self, codec: Codec) -> Self {
+        self.disable(codec);
+
+        self
+    }
+
+    pub fn contains(&self, codec: Codec) -> bool {
+        self.codecs.contains(&codec)
+    }
+
+    pub fn codecs(&self) -> &[Codec] {
+        &self.codecs
+    }
+
+    /// The Bitmap Codecs Capability Set advertising the codecs of the surface commands in the registry,
+    /// the server picking one of them.
+    pub fn bitmap_codecs(&self) -> BitmapCodecs {
+        let mut builder = BitmapCodecs::builder();
+
+        for codec in self.codecs.iter().filter_map(|codec| codec.bitmap_codec()) {
+            builder = match codec {
+                CodecGuid::RemoteFx => builder.with_remote_fx(RfxClientCapsContainer {
+                    capture_flags: CaptureFlags::empty(),
+                    caps_data: RfxCaps(RfxCapset(vec![RfxICap {
+                        flags: RfxICapFlags::empty(),
+                        entropy_bits: EntropyBits::Rlgr3,
+                    }])),
+                }),
+                CodecGuid::NsCodec => builder.with_ns_codec(NsCodec {
+                    is_dynamic_fidelity_allowed: true,
+                    is_subsampling_allowed: true,
+                    color_loss_level: NSCODEC_COLOR_LOSS_LEVEL,
+                }),
+                CodecGuid::ImageRemoteFx | CodecGuid::Ignore => builder,
+            };
+        }
+
+        builder.build()
+    }
+}
+
+impl Default for CodecRegistry {
+    fn default() -> Self {
+        Self::compiled_in()
+    }
+}
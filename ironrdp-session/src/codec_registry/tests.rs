@@ -0,0 +1,36 @@
+use super::*;
+
+#[test]
+fn compiled_in_registry_contains_codecs_enabled_by_features() {
+    let registry = CodecRegistry::compiled_in();
+
+    assert_eq!(cfg!(feature = "rfx"), registry.contains(Codec::RemoteFx));
+    assert_eq!(cfg!(feature = "zgfx"), registry.contains(Codec::Zgfx));
+    assert_eq!(cfg!(feature = "h264"), registry.contains(Codec::H264));
+    assert_eq!(cfg!(feature = "bitmap"), registry.contains(Codec::Bitmap));
+    assert_eq!(cfg!(feature = "nscodec"), registry.contains(Codec::NsCodec));
+}
+
+#[test]
+fn disabled_codec_is_removed_from_registry() {
+    let registry = CodecRegistry::compiled_in().without(Codec::RemoteFx);
+
+    assert!(!registry.contains(Codec::RemoteFx));
+    assert!(registry.codecs().iter().all(|codec| codec.is_compiled_in()));
+}
+
+#[test]
+fn bitmap_codecs_advertise_enabled_surface_codecs() {
+    let registry = CodecRegistry::compiled_in().without(Codec::RemoteFx);
+
+    let codecs = registry.bitmap_codecs();
+
+    let ids = codecs.0.iter().map(|codec| codec.id).collect::<Vec<_>>();
+    let expected = if cfg!(feature = "nscodec") {
+        vec![CodecGuid::NsCodec.id()]
+    } else {
+        vec![]
+    };
+    assert_eq!(expected, ids);
+    assert_eq!(None, codecs.find(CodecGuid::RemoteFx));
+}
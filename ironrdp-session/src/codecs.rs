@@ -1,14 +1,16 @@
 use std::io;
 use std::pin::Pin;
+use std::task::{Context, Poll};
 
 use bit_field::BitField;
 use byteorder::{BigEndian, ReadBytesExt};
-use bytes::{BufMut, BytesMut};
-use futures_util::{AsyncRead, AsyncReadExt as _, AsyncWrite, AsyncWriteExt as _};
-use ironrdp::Action;
+use bytes::{Buf as _, BytesMut};
+use futures_util::{future, ready, AsyncRead, AsyncWrite};
+use ironrdp::{Action, LimitsConfig};
 use num_traits::FromPrimitive;
 
-use crate::transport::{Decoder as TransportDecoder, Encoder as TransportEncoder};
+use crate::cancellation::ReadCancellation;
+use crate::transport::{AsyncDecoder, AsyncEncoder, Decoder as TransportDecoder, Encoder as TransportEncoder};
 
 pub type ErasedWriter = Pin<Box<dyn AsyncWrite + Send>>;
 
@@ -17,6 +19,7 @@ pub type ErasedReader = Pin<Box<dyn AsyncRead + Send>>;
 pub struct FramedReader<R = ErasedReader> {
     reader: R,
     buf: BytesMut,
+    max_frame_length: usize,
 }
 
 impl<R> FramedReader<R>
@@ -27,9 +30,16 @@ where
         Self {
             reader,
             buf: BytesMut::new(),
+            max_frame_length: LimitsConfig::DEFAULT_MAX_PDU_LENGTH,
         }
     }
 
+    /// Fails reading the frames longer than `max_frame_length`, see [`LimitsConfig::max_pdu_length`].
+    pub fn with_max_frame_length(mut self, max_frame_length: usize) -> Self {
+        self.max_frame_length = max_frame_length;
+        self
+    }
+
     pub fn into_erased(self) -> FramedReader<ErasedReader>
     where
         R: Send + 'static,
@@ -37,6 +47,7 @@ where
         FramedReader {
             reader: Box::pin(self.reader),
             buf: self.buf,
+            max_frame_length: self.max_frame_length,
         }
     }
 
@@ -45,42 +56,56 @@ where
     }
 
     pub async fn read_frame(&mut self) -> Result<Option<BytesMut>, ironrdp::RdpError>
+    where
+        R: Unpin,
+    {
+        future::poll_fn(|cx| self.poll_read_frame(cx)).await
+    }
+
+    /// Polls the reading of the next frame, for the futures reading it such as [`AsyncDecoder::decode_async`].
+    pub fn poll_read_frame(&mut self, cx: &mut Context<'_>) -> Poll<Result<Option<BytesMut>, ironrdp::RdpError>>
     where
         R: Unpin,
     {
         loop {
             // Try decoding and see if a frame has been received already
-            if let Some(frame) = decode_frame(&mut self.buf)? {
-                return Ok(Some(frame));
+            if let Some(frame) = decode_frame(&mut self.buf, self.max_frame_length)? {
+                return Poll::Ready(Ok(Some(frame)));
             }
 
             // NOTE: tokio ecosystem has a nice API for this with `AsyncReadExt::read_buf`
             let mut read_bytes = [0u8; 1024];
-            let len = self.reader.read(&mut read_bytes[..]).await?;
+            let len = ready!(Pin::new(&mut self.reader).poll_read(cx, &mut read_bytes[..]))?;
             self.buf.extend_from_slice(&read_bytes[..len]);
 
             // Handle EOF
             if len == 0 {
-                let frame = decode_frame_eof(&mut self.buf)?;
-                return Ok(frame);
+                return Poll::Ready(decode_frame_eof(&mut self.buf, self.max_frame_length));
             }
         }
     }
 
+    /// Reads the next frame like [`Self::read_frame`], unless the deadline elapses or the shutdown signal
+    /// is triggered first. The bytes read so far are kept, for the next read to complete the frame.
+    pub async fn read_frame_cancellable(
+        &mut self,
+        cancellation: ReadCancellation,
+    ) -> Result<Option<BytesMut>, crate::RdpError>
+    where
+        R: Unpin,
+    {
+        cancellation
+            .run(async { self.read_frame().await.map_err(crate::RdpError::from) })
+            .await
+    }
+
     pub async fn decode_next_frame<D>(&mut self, decoder: &mut D) -> Result<D::Item, crate::RdpError>
     where
         D: TransportDecoder,
         D::Error: Into<crate::RdpError>,
         R: Unpin,
     {
-        let frame = self
-            .read_frame()
-            .await?
-            .ok_or(crate::RdpError::UnexpectedStreamTermination)?;
-
-        let item = decoder.decode(&frame[..]).map_err(Into::into)?;
-
-        Ok(item)
+        decoder.decode_async(self).await
     }
 }
 
@@ -90,16 +115,12 @@ where
     E: TransportEncoder,
     E::Error: Into<crate::RdpError>,
 {
-    let mut buf = BytesMut::new();
-    let buf_writer = (&mut buf).writer();
-    encoder.encode(item, buf_writer).map_err(Into::into)?;
-    writer.write_all(&buf).await?;
-    Ok(())
+    encoder.encode_async(item, writer).await
 }
 
 /// Function to call when there are no more bytes available to be read from the underlying I/O.
-fn decode_frame_eof(buf: &mut BytesMut) -> Result<Option<BytesMut>, ironrdp::RdpError> {
-    match decode_frame(buf)? {
+fn decode_frame_eof(buf: &mut BytesMut, max_length: usize) -> Result<Option<BytesMut>, ironrdp::RdpError> {
+    match decode_frame(buf, max_length)? {
         Some(frame) => Ok(Some(frame)),
         None => {
             if buf.is_empty() {
@@ -111,9 +132,30 @@ fn decode_frame_eof(buf: &mut BytesMut) -> Result<Option<BytesMut>, ironrdp::Rdp
     }
 }
 
-/// Attempts to decode a frame from the provided buffer of bytes.
-fn decode_frame(buf: &mut BytesMut) -> Result<Option<BytesMut>, ironrdp::RdpError> {
-    let mut stream = buf.as_ref();
+/// Attempts to decode a frame from the provided buffer of bytes, failing if it is longer than `max_length`.
+pub(crate) fn decode_frame(buf: &mut BytesMut, max_length: usize) -> Result<Option<BytesMut>, ironrdp::RdpError> {
+    let length = match frame_length(buf)? {
+        Some(length) => length,
+        None => return Ok(None),
+    };
+
+    if length > max_length {
+        return Err(ironrdp::RdpError::FrameTooLong {
+            length,
+            max: max_length,
+        });
+    }
+
+    if buf.len() >= length {
+        Ok(Some(buf.split_to(length)))
+    } else {
+        Ok(None)
+    }
+}
+
+/// The length of the TPKT or Fast-Path frame starting the buffer, `None` if its header is incomplete.
+pub(crate) fn frame_length(buf: &[u8]) -> Result<Option<usize>, ironrdp::RdpError> {
+    let mut stream = buf;
     if stream.is_empty() {
         return Ok(None);
     }
@@ -144,9 +186,76 @@ fn decode_frame(buf: &mut BytesMut) -> Result<Option<BytesMut>, ironrdp::RdpErro
         }
     };
 
-    if buf.len() >= length as usize {
-        Ok(Some(buf.split_to(length as usize)))
+    Ok(Some(usize::from(length)))
+}
+
+/// Maximum number of bytes skipped while looking for the next plausible frame header.
+const MAX_RESYNCHRONIZATION_LENGTH: usize = 0x10000;
+
+pub(crate) enum Synchronization {
+    /// The buffer starts with a plausible frame header.
+    Synchronized,
+    /// More bytes are needed to tell whether the buffer starts with a plausible frame header.
+    Pending,
+    /// The bytes preceding the next plausible frame header have been skipped.
+    Resynchronized { skipped_bytes: usize },
+}
+
+/// Skips the bytes preceding the next plausible TPKT or Fast-Path header of the buffer,
+/// so that a corrupted frame does not abort the session. Fails if no plausible header is found
+/// within [`MAX_RESYNCHRONIZATION_LENGTH`] bytes.
+pub(crate) fn resynchronize(buf: &mut BytesMut) -> Result<Synchronization, ironrdp::RdpError> {
+    for skipped_bytes in 0..buf.len().min(MAX_RESYNCHRONIZATION_LENGTH + 1) {
+        match is_plausible_frame_header(&buf[skipped_bytes..]) {
+            Some(true) if skipped_bytes == 0 => return Ok(Synchronization::Synchronized),
+            Some(true) => {
+                buf.advance(skipped_bytes);
+
+                return Ok(Synchronization::Resynchronized { skipped_bytes });
+            }
+            Some(false) => (),
+            None => return Ok(Synchronization::Pending),
+        }
+    }
+
+    if buf.len() > MAX_RESYNCHRONIZATION_LENGTH {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "no frame header found to resynchronize the stream",
+        )
+        .into())
     } else {
-        Ok(None)
+        Ok(Synchronization::Pending)
+    }
+}
+
+/// Returns `None` if the buffer is too short to tell.
+fn is_plausible_frame_header(buf: &[u8]) -> Option<bool> {
+    let header = *buf.first()?;
+
+    match Action::from_u8(header.get_bits(0..2)) {
+        Some(Action::X224) => {
+            // TPKT version 3, reserved byte, length, and the X.224 length indicator
+            let tpkt = buf.get(..5)?;
+            let length = usize::from(u16::from_be_bytes([tpkt[2], tpkt[3]]));
+            let length_indicator = usize::from(tpkt[4]);
+
+            Some(header == 3 && tpkt[1] == 0 && length_indicator >= 2 && 5 + length_indicator <= length)
+        }
+        // the bits 2 to 5 of the Fast-Path output header are reserved
+        Some(Action::FastPath) if header.get_bits(2..6) != 0 => Some(false),
+        Some(Action::FastPath) => {
+            let a = *buf.get(1)?;
+            let (length, sizeof_length) = if a & 0x80 != 0 {
+                let b = *buf.get(2)?;
+
+                ((usize::from(a & !0x80) << 8) + usize::from(b), 2)
+            } else {
+                (usize::from(a), 1)
+            };
+
+            Some(length > sizeof_length + 1)
+        }
+        None => Some(false),
     }
 }
@@ -5,52 +5,135 @@ use std::future::Future;
 use std::io;
 use std::iter;
 use std::net::SocketAddr;
+use std::time::{Duration, Instant};
 
 use dns_lookup::lookup_addr;
 use futures_util::AsyncRead;
 use futures_util::AsyncReadExt as _;
 use futures_util::AsyncWrite;
+use futures_util::AsyncWriteExt as _;
+use ironrdp::gcc::{ClientSecurityData, EncryptionLevel, EncryptionMethod, ServerSecurityData};
+use ironrdp::mcs::DisconnectUltimatumReason;
+use ironrdp::rdp::bulk::BulkDecompressor;
 use ironrdp::rdp::capability_sets::CapabilitySet;
 use ironrdp::rdp::server_license::{
-    ClientNewLicenseRequest, ClientPlatformChallengeResponse, InitialMessageType, InitialServerLicenseMessage,
-    ServerPlatformChallenge, ServerUpgradeLicense, PREMASTER_SECRET_SIZE, RANDOM_NUMBER_SIZE,
+    ClientLicenseInformation, ClientNewLicenseRequest, ClientPlatformChallengeResponse, InitialMessageType,
+    InitialServerLicenseMessage, NewLicenseInformation, ServerLicenseRequest, ServerPlatformChallenge,
+    ServerUpgradeLicense, PREMASTER_SECRET_SIZE, RANDOM_NUMBER_SIZE,
 };
-use ironrdp::rdp::{ErrorInfo, ProtocolIndependentCode, ServerSetErrorInfoPdu, SERVER_CHANNEL_ID};
-use ironrdp::{nego, rdp, PduParsing};
+use ironrdp::rdp::standard_security::{
+    ClientSecurityExchangePdu, SessionCipher, SessionKeys, NON_FIPS_SECURITY_HEADER_SIZE, RANDOM_SIZE,
+};
+use ironrdp::rdp::vc::StaticChannelName;
+use ironrdp::rdp::{
+    BasicSecurityHeader, BasicSecurityHeaderFlags, ErrorInfo, ProtocolIndependentCode, ServerSetErrorInfoPdu,
+    BASIC_SECURITY_HEADER_SIZE, SERVER_CHANNEL_ID,
+};
+use ironrdp::rdstls::{
+    RdstlsAuthenticationRequestPdu, RdstlsAuthenticationResponsePdu, RdstlsCapabilitiesPdu, RdstlsResultCode,
+    RDSTLS_VERSION_1,
+};
+use ironrdp::{nego, rdp, ChannelId, PduParsing, UserId};
 use ring::rand::SecureRandom as _;
-use sspi::internal::credssp;
-use sspi::NegotiateConfig;
+use zeroize::{Zeroize, Zeroizing};
 
 use crate::codecs::encode_next_frame;
 use crate::codecs::ErasedWriter;
 use crate::codecs::FramedReader;
+use crate::credssp::{CredsspClient, CredsspOutput, TS_REQUEST_LENGTH_PREFIX_SIZE};
+use crate::diagnostics::PduDirection;
 use crate::transport::ChannelIdentificators;
 use crate::transport::SendPduDataContextTransport;
 use crate::transport::ShareControlHeaderTransport;
-use crate::transport::TsRequestTransport;
 use crate::transport::{
-    connect, DataTransport, EarlyUserAuthResult, McsTransport, SendDataContextTransport, ShareDataHeaderTransport,
-    X224DataTransport,
+    connect, DataTransport, McsTransport, SendDataContextTransport, ShareDataHeaderTransport, X224DataTransport,
 };
-use crate::{InputConfig, RdpError};
+use crate::transport::{StandardSecurityReader, StandardSecurityWriter};
+use crate::{InputConfig, LicenseKey, RdpError, RedirectionCredentials, ServerCertificate};
 
-pub type StaticChannels = HashMap<String, u16>;
+pub type StaticChannels = HashMap<StaticChannelName, ChannelId>;
 
 pub struct DesktopSize {
     pub width: u16,
     pub height: u16,
 }
 
+#[derive(Debug)]
+pub struct NegotiatedEncryption {
+    pub client_methods: EncryptionMethod,
+    pub server_method: EncryptionMethod,
+    pub server_level: EncryptionLevel,
+    /// `Some` if the server requires Standard RDP Security instead of relying on the security protocol.
+    pub standard_security: Option<StandardSecurityData>,
+}
+
+/// The random and the certificate of the Server Security Data, from which the session keys
+/// of Standard RDP Security are derived.
+#[derive(Debug, Clone)]
+pub struct StandardSecurityData {
+    pub server_random: [u8; RANDOM_SIZE],
+    pub server_certificate: Vec<u8>,
+}
+
 pub struct ConnectionSequenceResult {
     pub desktop_size: DesktopSize,
+    pub encryption: NegotiatedEncryption,
     pub joined_static_channels: StaticChannels,
-    pub global_channel_id: u16,
-    pub initiator_id: u16,
+    pub global_channel_id: ChannelId,
+    pub initiator_id: UserId,
+    /// The certificate presented by the server, if the stream upgrade has provided it.
+    pub server_certificate: Option<ServerCertificate>,
+    /// The history of the Share Data PDUs the server has compressed during the finalization,
+    /// which the data of the active stage refers to.
+    pub bulk_decompressor: BulkDecompressor,
 }
 
 pub struct UpgradedStream<S> {
     pub stream: S,
     pub server_public_key: Vec<u8>,
+    pub server_certificate: Option<ServerCertificate>,
+}
+
+impl<S> UpgradedStream<S> {
+    /// Binds the CredSSP exchange to the public key of the certificate presented by the server.
+    pub fn new(stream: S, server_certificate: ServerCertificate) -> Self {
+        Self {
+            stream,
+            server_public_key: server_certificate.public_key.clone(),
+            server_certificate: Some(server_certificate),
+        }
+    }
+}
+
+/// A stream on which the negotiation, the security upgrade and CredSSP have already been done,
+/// e.g. by a gateway terminating NLA on behalf of the client.
+pub struct EstablishedStream<S> {
+    pub stream: S,
+    /// The protocol selected by the server in the X.224 Connection Confirm.
+    pub selected_protocol: nego::SecurityProtocol,
+    pub server_certificate: Option<ServerCertificate>,
+}
+
+/// Durations of the phases of the connection sequence gone through by [`probe_session`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PhaseTimings {
+    pub negotiation: Duration,
+    pub security_upgrade: Duration,
+    /// `None` if the selected security protocol does not require CredSSP.
+    pub cred_ssp: Option<Duration>,
+    pub mcs_connect: Duration,
+}
+
+impl PhaseTimings {
+    pub fn total(&self) -> Duration {
+        self.negotiation + self.security_upgrade + self.cred_ssp.unwrap_or_default() + self.mcs_connect
+    }
+}
+
+pub struct ProbeResult {
+    pub selected_protocol: nego::SecurityProtocol,
+    pub server_certificate: Option<ServerCertificate>,
+    pub timings: PhaseTimings,
 }
 
 pub async fn process_connection_sequence<S, UpgradeFn, FnRes, UpgradedS>(
@@ -65,17 +148,104 @@ where
     FnRes: Future<Output = Result<UpgradedStream<UpgradedS>, RdpError>>,
     UpgradedS: AsyncRead + AsyncWrite + Unpin + Send + 'static,
 {
-    let (reader, mut writer) = stream.split();
+    let (established_stream, _) = establish_stream(stream, routing_addr, config, upgrade_stream).await?;
 
-    let mut reader = FramedReader::new(reader);
+    continue_connection_sequence(established_stream, routing_addr, config).await
+}
 
-    let selected_protocol = connect(
-        &mut reader,
+/// Goes through the negotiation, the security upgrade and the MCS connect, then disconnects.
+///
+/// Meant for the health checks of RDP servers, e.g. by load balancers,
+/// which do not need a user session to be created.
+pub async fn probe_session<S, UpgradeFn, FnRes, UpgradedS>(
+    stream: S,
+    routing_addr: &SocketAddr,
+    config: &InputConfig,
+    upgrade_stream: UpgradeFn,
+) -> Result<ProbeResult, RdpError>
+where
+    S: AsyncRead + AsyncWrite + Unpin + 'static,
+    UpgradeFn: FnOnce(S) -> FnRes,
+    FnRes: Future<Output = Result<UpgradedStream<UpgradedS>, RdpError>>,
+    UpgradedS: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let (
+        EstablishedStream {
+            stream,
+            selected_protocol,
+            server_certificate,
+        },
+        mut timings,
+    ) = establish_stream(stream, routing_addr, config, upgrade_stream).await?;
+
+    let (reader, writer) = stream.split();
+    let mut reader = FramedReader::new(reader)
+        .with_max_frame_length(config.limits.max_pdu_length)
+        .into_erased();
+    let mut writer = Box::pin(writer) as ErasedWriter;
+
+    let start = Instant::now();
+    process_mcs_connect(&mut reader, &mut writer, config, selected_protocol).await?;
+    timings.mcs_connect = start.elapsed();
+
+    let disconnect = ironrdp::McsPdu::DisconnectProviderUltimatum(DisconnectUltimatumReason::UserRequested);
+    debug!("Send MCS Disconnect Provider Ultimatum PDU: {:?}", disconnect);
+    encode_next_frame(
         &mut writer,
-        config.security_protocol,
-        config.credentials.username.clone(),
+        &mut X224DataTransport::<ironrdp::McsPdu>::default(),
+        disconnect,
     )
     .await?;
+    writer.close().await?;
+
+    debug!("Probe session timings: {:?}", timings);
+
+    Ok(ProbeResult {
+        selected_protocol,
+        server_certificate,
+        timings,
+    })
+}
+
+/// Goes through the negotiation, the security upgrade and CredSSP if required by the selected protocol.
+async fn establish_stream<S, UpgradeFn, FnRes, UpgradedS>(
+    stream: S,
+    routing_addr: &SocketAddr,
+    config: &InputConfig,
+    upgrade_stream: UpgradeFn,
+) -> Result<(EstablishedStream<UpgradedS>, PhaseTimings), RdpError>
+where
+    S: AsyncRead + AsyncWrite + Unpin + 'static,
+    UpgradeFn: FnOnce(S) -> FnRes,
+    FnRes: Future<Output = Result<UpgradedStream<UpgradedS>, RdpError>>,
+    UpgradedS: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let mut timings = PhaseTimings::default();
+
+    let (reader, mut writer) = stream.split();
+
+    let mut reader = FramedReader::new(reader).with_max_frame_length(config.limits.max_pdu_length);
+
+    let diagnostics = &config.diagnostics;
+    let nego_data = match config.routing_token.clone() {
+        Some(routing_token) => {
+            diagnostics.record_event("Routing token sent in the X.224 Connection Request");
+            nego::NegoData::RoutingToken(routing_token)
+        }
+        None => nego::NegoData::Cookie(config.credentials.username.clone()),
+    };
+    diagnostics.record_event(format_args!(
+        "Requested security protocols: {:?}",
+        config.security_protocol
+    ));
+
+    let start = Instant::now();
+    diagnostics.record_pdu(PduDirection::Sent, "X.224 Connection Request");
+    let selected_protocol = connect(&mut reader, &mut writer, config.security_protocol, nego_data).await?;
+    timings.negotiation = start.elapsed();
+    diagnostics.record_pdu(PduDirection::Received, "X.224 Connection Confirm");
+    diagnostics.record_event(format_args!("Selected security protocol: {:?}", selected_protocol));
+    diagnostics.record_phase("negotiation", timings.negotiation);
 
     let (reader, leftover) = reader.into_inner();
 
@@ -83,135 +253,311 @@ where
 
     debug_assert_eq!(leftover.len(), 0, "no leftover is expected after initial negotiation");
 
+    let start = Instant::now();
     let UpgradedStream {
         mut stream,
         server_public_key,
+        server_certificate,
     } = upgrade_stream(stream).await?;
+    timings.security_upgrade = start.elapsed();
+    diagnostics.record_phase("security upgrade", timings.security_upgrade);
+    if let Some(certificate) = server_certificate.as_ref() {
+        diagnostics.record_event(format_args!(
+            "Server certificate: subject {}, issuer {}, valid from {} to {}, SHA-256 fingerprint {}",
+            certificate.subject,
+            certificate.issuer,
+            certificate.not_before,
+            certificate.not_after,
+            certificate.sha256_fingerprint_string()
+        ));
+    }
+
+    if selected_protocol.contains(nego::SecurityProtocol::RDSTLS) {
+        let redirection_credentials = config
+            .redirection_credentials
+            .as_ref()
+            .ok_or(RdpError::MissingRedirectionCredentials)?;
 
-    if selected_protocol.contains(nego::SecurityProtocol::HYBRID)
+        let start = Instant::now();
+        process_rdstls(&mut stream, &config.credentials, redirection_credentials).await?;
+        diagnostics.record_phase("RDSTLS", start.elapsed());
+    } else if selected_protocol.contains(nego::SecurityProtocol::HYBRID)
         || selected_protocol.contains(nego::SecurityProtocol::HYBRID_EX)
     {
-        process_cred_ssp(&mut stream, config.credentials.clone(), server_public_key, routing_addr).await?;
-
-        if selected_protocol.contains(nego::SecurityProtocol::HYBRID_EX) {
-            let data = EarlyUserAuthResult::read(&mut stream).await?;
-            if let credssp::EarlyUserAuthResult::AccessDenied = data {
-                return Err(RdpError::AccessDenied);
-            }
-        }
+        let start = Instant::now();
+        process_cred_ssp(
+            &mut stream,
+            config.credentials.clone(),
+            server_public_key,
+            routing_addr,
+            selected_protocol,
+        )
+        .await?;
+        timings.cred_ssp = Some(start.elapsed());
+        diagnostics.record_phase("CredSSP", start.elapsed());
     }
 
+    Ok((
+        EstablishedStream {
+            stream,
+            selected_protocol,
+            server_certificate,
+        },
+        timings,
+    ))
+}
+
+/// Goes through the connection sequence from the MCS connect, on a stream established by the caller.
+pub async fn continue_connection_sequence<S>(
+    established_stream: EstablishedStream<S>,
+    routing_addr: &SocketAddr,
+    config: &InputConfig,
+) -> Result<(ConnectionSequenceResult, FramedReader, ErasedWriter), RdpError>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let EstablishedStream {
+        stream,
+        selected_protocol,
+        server_certificate,
+    } = established_stream;
+
     let (reader, writer) = stream.split();
-    let mut reader = FramedReader::new(reader).into_erased();
+    let mut reader = FramedReader::new(reader)
+        .with_max_frame_length(config.limits.max_pdu_length)
+        .into_erased();
     let mut writer = Box::pin(writer) as ErasedWriter;
 
-    let static_channels = process_mcs_connect(&mut reader, &mut writer, config, selected_protocol).await?;
+    let diagnostics = &config.diagnostics;
+
+    let start = Instant::now();
+    let (static_channels, encryption) =
+        process_mcs_connect(&mut reader, &mut writer, config, selected_protocol).await?;
+    diagnostics.record_phase("MCS connect", start.elapsed());
+    diagnostics.record_event(format_args!("Negotiated encryption: {:?}", encryption));
+
+    let start = Instant::now();
     let joined_static_channels = process_mcs(&mut reader, &mut writer, static_channels, config).await?;
     debug!("Joined static active_session: {:?}", joined_static_channels);
+    diagnostics.record_phase("MCS channel join", start.elapsed());
+    diagnostics.record_event(format_args!("Joined static channels: {:?}", joined_static_channels));
 
     let global_channel_id = *joined_static_channels
-        .get(config.global_channel_name.as_str())
+        .get(&config.global_channel_name)
         .expect("global channel must be added");
-    let initiator_id = *joined_static_channels
-        .get(config.user_channel_name.as_str())
-        .expect("user channel must be added");
+    // the user channel has the ID of the user
+    let initiator_id = UserId(
+        joined_static_channels
+            .get(&config.user_channel_name)
+            .expect("user channel must be added")
+            .0,
+    );
+
+    let mut encrypt_cipher = match encryption.standard_security.as_ref() {
+        Some(standard_security) => {
+            let transport =
+                SendDataContextTransport::new(McsTransport::new(DataTransport::new()), initiator_id, global_channel_id);
+            let SessionKeys { encrypt, decrypt } = process_security_exchange(
+                &mut writer,
+                transport,
+                encryption.server_method,
+                standard_security,
+                config,
+            )
+            .await?;
+
+            // the server encrypts what it sends from the Client Info PDU on
+            let (inner_reader, leftover) = reader.into_inner();
+            reader = FramedReader::new(StandardSecurityReader::new(inner_reader, decrypt, leftover))
+                .with_max_frame_length(config.limits.max_pdu_length)
+                .into_erased();
+
+            Some(encrypt)
+        }
+        None => None,
+    };
 
     let transport =
         SendDataContextTransport::new(McsTransport::new(DataTransport::new()), initiator_id, global_channel_id);
-    send_client_info(&mut writer, transport, config, routing_addr).await?;
+    send_client_info(&mut writer, transport, config, routing_addr, encrypt_cipher.as_mut()).await?;
 
+    let start = Instant::now();
     process_server_license_exchange(&mut reader, &mut writer, config, global_channel_id).await?;
+    diagnostics.record_phase("licensing", start.elapsed());
+
+    // the licensing PDUs of the client are not encrypted, unlike the following ones
+    if let Some(encrypt_cipher) = encrypt_cipher {
+        writer = Box::pin(StandardSecurityWriter::new(writer, encrypt_cipher));
+    }
 
     let transport =
         SendDataContextTransport::new(McsTransport::new(DataTransport::new()), initiator_id, global_channel_id);
     let transport = ShareControlHeaderTransport::new(transport, initiator_id, global_channel_id);
+    let start = Instant::now();
     let desktop_size = process_capability_sets(&mut reader, &mut writer, transport, config).await?;
+    diagnostics.record_phase("capability exchange", start.elapsed());
+    diagnostics.record_event(format_args!(
+        "Desktop size: {}x{}",
+        desktop_size.width, desktop_size.height
+    ));
 
     let transport =
         SendDataContextTransport::new(McsTransport::new(DataTransport::new()), initiator_id, global_channel_id);
     let transport = ShareControlHeaderTransport::new(transport, initiator_id, global_channel_id);
-    let transport = ShareDataHeaderTransport::new(transport);
-    process_finalization(&mut reader, &mut writer, transport, initiator_id).await?;
+    let mut transport = ShareDataHeaderTransport::new(transport);
+    let start = Instant::now();
+    process_finalization(&mut reader, &mut writer, &mut transport, initiator_id).await?;
+    diagnostics.record_phase("finalization", start.elapsed());
 
     Ok((
         ConnectionSequenceResult {
             desktop_size,
+            encryption,
             joined_static_channels,
             global_channel_id,
             initiator_id,
+            server_certificate,
+            bulk_decompressor: transport.into_decompressor(),
         },
         reader,
         writer,
     ))
 }
 
+/// Goes through the CredSSP exchange of HYBRID and HYBRID_EX with [`CredsspClient`],
+/// up to the Early User Authorization Result of HYBRID_EX.
 pub async fn process_cred_ssp(
     mut stream: impl AsyncRead + AsyncWrite + Unpin,
     credentials: sspi::AuthIdentity,
     server_public_key: Vec<u8>,
     routing_addr: &SocketAddr,
+    selected_protocol: nego::SecurityProtocol,
 ) -> Result<(), RdpError> {
-    let mut transport = TsRequestTransport::default();
-
     let destination_host = lookup_addr(&routing_addr.ip())
         .map_err(|err| RdpError::UserInfoError(format!("unable to query destination host name: {:?}", err)))?;
     let service_principal_name = format!("TERMSRV/{}", destination_host);
 
-    let mut cred_ssp_client = credssp::CredSspClient::new(
+    let mut cred_ssp_client = CredsspClient::new(
         server_public_key,
         credentials,
-        credssp::CredSspMode::WithCredentials,
-        credssp::ClientMode::Negotiate(NegotiateConfig::default()),
         service_principal_name,
-    )
-    .map_err(RdpError::CredSspError)?;
-    let mut next_ts_request = credssp::TsRequest::default();
-
-    loop {
-        let result = cred_ssp_client
-            .process(next_ts_request)
-            .map_err(RdpError::CredSspError)?;
-        debug!("Got CredSSP TSRequest: {:x?}", result);
-
-        match result {
-            credssp::ClientState::ReplyNeeded(ts_request) => {
-                debug!("Send CredSSP TSRequest (reply needed): {:x?}", ts_request);
-                transport.encode(ts_request, &mut stream).await?;
-                next_ts_request = transport.decode(&mut stream).await?;
-            }
-            credssp::ClientState::FinalMessage(ts_request) => {
-                debug!("Send CredSSP TSRequest (final): {:x?}", ts_request);
-                transport.encode(ts_request, &mut stream).await?;
-                break;
+        selected_protocol,
+    )?;
+    let mut input = Vec::new();
+
+    while !cred_ssp_client.is_finished() {
+        match cred_ssp_client.step(&input)? {
+            CredsspOutput::ReplyNeeded(message) | CredsspOutput::FinalMessage(message) => {
+                stream.write_all(&message).await?;
+                stream.flush().await?;
             }
+            CredsspOutput::Finished => break,
         }
+
+        input = read_cred_ssp_message(&mut stream, &cred_ssp_client).await?;
     }
 
     Ok(())
 }
 
+/// Goes through the RDSTLS authentication, in which the client presents the credentials it has been
+/// redirected with to the server, once the TLS handshake is complete.
+pub async fn process_rdstls(
+    mut stream: impl AsyncRead + AsyncWrite + Unpin,
+    credentials: &sspi::AuthIdentity,
+    redirection_credentials: &RedirectionCredentials,
+) -> Result<(), RdpError> {
+    let mut buffer = vec![0; RdstlsCapabilitiesPdu::SIZE];
+    stream.read_exact(&mut buffer).await?;
+    let capabilities = RdstlsCapabilitiesPdu::from_buffer(buffer.as_slice())?;
+    debug!("Got RDSTLS Capabilities PDU: {:?}", capabilities);
+
+    if capabilities.supported_versions & RDSTLS_VERSION_1 == 0 {
+        return Err(RdpError::InvalidResponse(format!(
+            "the server does not support RDSTLS version 1: {:#x}",
+            capabilities.supported_versions
+        )));
+    }
+
+    let mut authentication_request = RdstlsAuthenticationRequestPdu::PasswordCredentials {
+        redirection_guid: redirection_credentials.redirection_guid.clone(),
+        username: credentials.username.clone(),
+        domain: credentials.domain.clone().unwrap_or_default(),
+        password: redirection_credentials.password.clone(),
+    };
+    debug!("Send RDSTLS Authentication Request PDU");
+    let mut buffer = Zeroizing::new(Vec::with_capacity(authentication_request.buffer_length()));
+    authentication_request.to_buffer(&mut *buffer)?;
+    if let RdstlsAuthenticationRequestPdu::PasswordCredentials { password, .. } = &mut authentication_request {
+        password.zeroize();
+    }
+    stream.write_all(&buffer).await?;
+    stream.flush().await?;
+
+    let mut buffer = vec![0; RdstlsAuthenticationResponsePdu::SIZE];
+    stream.read_exact(&mut buffer).await?;
+    let authentication_response = RdstlsAuthenticationResponsePdu::from_buffer(buffer.as_slice())?;
+    debug!("Got RDSTLS Authentication Response PDU: {:?}", authentication_response);
+
+    if authentication_response.result_code != RdstlsResultCode::SUCCESS {
+        return Err(RdpError::RdstlsAuthenticationFailed(
+            authentication_response.result_code.0,
+        ));
+    }
+
+    Ok(())
+}
+
+async fn read_cred_ssp_message(
+    mut stream: impl AsyncRead + Unpin,
+    cred_ssp_client: &CredsspClient,
+) -> Result<Vec<u8>, RdpError> {
+    let mut message = Vec::new();
+
+    loop {
+        let length = cred_ssp_client
+            .next_message_length(&message)?
+            .unwrap_or(TS_REQUEST_LENGTH_PREFIX_SIZE);
+        if message.len() >= length {
+            return Ok(message);
+        }
+
+        let read_length = message.len();
+        message.resize(length, 0x00);
+        stream.read_exact(&mut message[read_length..]).await?;
+    }
+}
+
 pub async fn process_mcs_connect(
     reader: &mut FramedReader,
     writer: &mut ErasedWriter,
     config: &InputConfig,
     selected_protocol: nego::SecurityProtocol,
-) -> Result<StaticChannels, RdpError> {
+) -> Result<(StaticChannels, NegotiatedEncryption), RdpError> {
     let connect_initial =
         ironrdp::ConnectInitial::with_gcc_blocks(user_info::create_gcc_blocks(config, selected_protocol)?);
     debug!("Send MCS Connect Initial PDU: {:?}", connect_initial);
-    let mut codec = X224DataTransport::default();
+    config.diagnostics.record_pdu(PduDirection::Sent, "MCS Connect Initial");
+    let mut codec = X224DataTransport::<ironrdp::ConnectInitial>::default();
     encode_next_frame(writer, &mut codec, connect_initial.clone()).await?;
-    let connect_response: ironrdp::ConnectResponse = reader.decode_next_frame(&mut codec).await?;
+
+    let frame = reader
+        .read_frame()
+        .await?
+        .ok_or(RdpError::UnexpectedStreamTermination)?;
+    let mut frame = frame.as_ref();
+    ironrdp::Data::from_buffer(&mut frame).map_err(ironrdp::RdpError::X224Error)?;
+    let connect_response = ironrdp::ConnectResponse::from_buffer_with_limits(frame, &config.limits)?;
     debug!("Got MCS Connect Response PDU: {:?}", connect_response);
+    config
+        .diagnostics
+        .record_pdu(PduDirection::Received, "MCS Connect Response");
 
     let gcc_blocks = connect_response.conference_create_response.gcc_blocks;
-    if connect_initial.conference_create_request.gcc_blocks.security == ironrdp::gcc::ClientSecurityData::no_security()
-        && gcc_blocks.security != ironrdp::gcc::ServerSecurityData::no_security()
-    {
-        return Err(RdpError::InvalidResponse(String::from(
-            "The server demands a security, while the client requested 'no security'",
-        )));
-    }
+    let encryption = negotiate_encryption(
+        &connect_initial.conference_create_request.gcc_blocks.security,
+        &gcc_blocks.security,
+    )?;
 
     if gcc_blocks.message_channel.is_some() || gcc_blocks.multi_transport_channel.is_some() {
         return Err(RdpError::InvalidResponse(String::from(
@@ -219,19 +565,113 @@ pub async fn process_mcs_connect(
         )));
     }
 
-    let static_channel_ids = gcc_blocks.network.channel_ids;
-    let global_channel_id = gcc_blocks.network.io_channel;
+    let static_channel_ids = gcc_blocks.network.channel_ids.into_iter().map(ChannelId);
+    let global_channel_id = ChannelId(gcc_blocks.network.io_channel);
 
     let static_channels = connect_initial
         .channel_names()
         .unwrap_or_default()
         .into_iter()
-        .map(|channel| channel.name)
-        .zip(static_channel_ids.into_iter())
-        .chain(iter::once((config.global_channel_name.clone(), global_channel_id)))
-        .collect::<StaticChannels>();
+        .map(|channel| StaticChannelName::new(channel.name))
+        .zip(static_channel_ids)
+        .map(|(name, id)| name.map(|name| (name, id)))
+        .chain(iter::once(Ok((config.global_channel_name.clone(), global_channel_id))))
+        .collect::<Result<StaticChannels, _>>()?;
 
-    Ok(static_channels)
+    Ok((static_channels, encryption))
+}
+
+fn negotiate_encryption(
+    client_security: &ClientSecurityData,
+    server_security: &ServerSecurityData,
+) -> Result<NegotiatedEncryption, RdpError> {
+    debug!(
+        "Server selected encryption method ({:?}) and encryption level ({:?})",
+        server_security.encryption_method, server_security.encryption_level
+    );
+
+    if client_security.encryption_methods.is_empty() && server_security.is_encryption_required() {
+        return Err(RdpError::InvalidResponse(String::from(
+            "The server demands a security, while the client requested 'no security'",
+        )));
+    }
+
+    if !client_security
+        .encryption_methods
+        .contains(server_security.encryption_method)
+    {
+        return Err(RdpError::InvalidResponse(format!(
+            "The server selected the {:?} encryption method, while the client requested one of {:?}",
+            server_security.encryption_method, client_security.encryption_methods
+        )));
+    }
+
+    // unless the server relies on the security protocol negotiated previously (TLS or CredSSP),
+    // the PDUs are encrypted with Standard RDP Security, for which FIPS is not implemented
+    let standard_security = if server_security.is_encryption_required() {
+        let is_supported_method = [
+            EncryptionMethod::BIT_40,
+            EncryptionMethod::BIT_56,
+            EncryptionMethod::BIT_128,
+        ]
+        .contains(&server_security.encryption_method);
+
+        match server_security.server_random {
+            Some(server_random) if is_supported_method && !server_security.server_cert.is_empty() => {
+                Some(StandardSecurityData {
+                    server_random,
+                    server_certificate: server_security.server_cert.clone(),
+                })
+            }
+            _ => {
+                return Err(RdpError::EncryptionNotSupported(
+                    server_security.encryption_level,
+                    server_security.encryption_method,
+                ));
+            }
+        }
+    } else {
+        None
+    };
+
+    Ok(NegotiatedEncryption {
+        client_methods: client_security.encryption_methods,
+        server_method: server_security.encryption_method,
+        server_level: server_security.encryption_level,
+        standard_security,
+    })
+}
+
+/// Sends the client random encrypted with the public key of the server in the Client Security Exchange PDU,
+/// and derives the session keys of Standard RDP Security from the client and server randoms.
+pub async fn process_security_exchange(
+    writer: &mut ErasedWriter,
+    mut codec: SendDataContextTransport,
+    encryption_method: EncryptionMethod,
+    standard_security: &StandardSecurityData,
+    config: &InputConfig,
+) -> Result<SessionKeys, RdpError> {
+    let mut client_random = Zeroizing::new([0u8; RANDOM_SIZE]);
+    ring::rand::SystemRandom::new()
+        .fill(&mut client_random[..])
+        .map_err(|err| RdpError::IOError(io::Error::new(io::ErrorKind::InvalidData, format!("{}", err))))?;
+
+    let security_exchange = ClientSecurityExchangePdu::new(&standard_security.server_certificate, &client_random[..])?;
+    debug!("Send Client Security Exchange PDU");
+    config
+        .diagnostics
+        .record_pdu(PduDirection::Sent, "Client Security Exchange");
+    let mut pdu = Vec::with_capacity(security_exchange.buffer_length());
+    security_exchange.to_buffer(&mut pdu)?;
+    encode_next_frame(writer, &mut codec, pdu).await?;
+
+    let session_keys = SessionKeys::derive(encryption_method, &client_random[..], &standard_security.server_random)?;
+    config.diagnostics.record_event(format_args!(
+        "Standard RDP Security with {:?} encryption",
+        encryption_method
+    ));
+
+    Ok(session_keys)
 }
 
 pub async fn process_mcs(
@@ -245,9 +685,12 @@ pub async fn process_mcs(
         sub_interval: 0,
     };
 
-    let mut codec = X224DataTransport::default();
+    let mut codec = X224DataTransport::<ironrdp::McsPdu>::default();
 
     debug!("Send MCS Erect Domain Request PDU: {:?}", erect_domain_request);
+    config
+        .diagnostics
+        .record_pdu(PduDirection::Sent, "MCS Erect Domain Request");
     encode_next_frame(
         writer,
         &mut codec,
@@ -256,13 +699,22 @@ pub async fn process_mcs(
     .await?;
 
     debug!("Send MCS Attach User Request PDU");
+    config
+        .diagnostics
+        .record_pdu(PduDirection::Sent, "MCS Attach User Request");
     encode_next_frame(writer, &mut codec, ironrdp::McsPdu::AttachUserRequest).await?;
 
     let mcs_pdu = stream.decode_next_frame(&mut codec).await?;
+    config
+        .diagnostics
+        .record_pdu(PduDirection::Received, mcs_pdu.as_short_name());
     let initiator_id = if let ironrdp::McsPdu::AttachUserConfirm(attach_user_confirm) = mcs_pdu {
         debug!("Got MCS Attach User Confirm PDU: {:?}", attach_user_confirm);
 
-        static_channels.insert(config.user_channel_name.clone(), attach_user_confirm.initiator_id);
+        static_channels.insert(
+            config.user_channel_name.clone(),
+            attach_user_confirm.initiator_id.channel_id(),
+        );
 
         attach_user_confirm.initiator_id
     } else {
@@ -278,6 +730,9 @@ pub async fn process_mcs(
             channel_id: *id,
         };
         debug!("Send MCS Channel Join Request PDU: {:?}", channel_join_request);
+        config
+            .diagnostics
+            .record_pdu(PduDirection::Sent, "MCS Channel Join Request");
         encode_next_frame(
             writer,
             &mut codec,
@@ -286,6 +741,9 @@ pub async fn process_mcs(
         .await?;
 
         let mcs_pdu = stream.decode_next_frame(&mut codec).await?;
+        config
+            .diagnostics
+            .record_pdu(PduDirection::Received, mcs_pdu.as_short_name());
         if let ironrdp::McsPdu::ChannelJoinConfirm(channel_join_confirm) = mcs_pdu {
             debug!("Got MCS Channel Join Confirm PDU: {:?}", channel_join_confirm);
 
@@ -313,14 +771,33 @@ pub async fn send_client_info(
     mut codec: SendDataContextTransport,
     config: &InputConfig,
     routing_addr: &SocketAddr,
+    encrypt_cipher: Option<&mut SessionCipher>,
 ) -> Result<(), RdpError> {
     let client_info_pdu = user_info::create_client_info_pdu(config, routing_addr)?;
     debug!("Send Client Info PDU: {:?}", client_info_pdu);
-    let mut pdu = Vec::with_capacity(client_info_pdu.buffer_length());
+    config.diagnostics.record_pdu(PduDirection::Sent, "Client Info");
+    // the PDU holds the password, so its buffers are overwritten with zeros once it has been sent
+    let mut pdu = Zeroizing::new(Vec::with_capacity(client_info_pdu.buffer_length()));
     client_info_pdu
-        .to_buffer(&mut pdu)
+        .to_buffer(&mut *pdu)
         .map_err(RdpError::ServerLicenseError)?;
-    encode_next_frame(writer, &mut codec, pdu).await?;
+    if let Some(cipher) = encrypt_cipher {
+        let (signature, encrypted) = cipher.encrypt(&pdu[BASIC_SECURITY_HEADER_SIZE..]);
+        let encrypted = Zeroizing::new(encrypted);
+
+        let mut encrypted_pdu = Zeroizing::new(Vec::with_capacity(NON_FIPS_SECURITY_HEADER_SIZE + encrypted.len()));
+        BasicSecurityHeader {
+            flags: BasicSecurityHeaderFlags::INFO_PKT | BasicSecurityHeaderFlags::ENCRYPT,
+        }
+        .to_buffer(&mut *encrypted_pdu)
+        .map_err(RdpError::ClientInfoError)?;
+        encrypted_pdu.extend_from_slice(&signature);
+        encrypted_pdu.extend_from_slice(&encrypted);
+        pdu = encrypted_pdu;
+    }
+    let mut frame = Zeroizing::new(Vec::with_capacity(codec.sensitive_frame_length(pdu.len())));
+    codec.encode_sensitive(&pdu, &mut *frame)?;
+    writer.write_all(&frame).await?;
     Ok(())
 }
 
@@ -328,7 +805,7 @@ pub async fn process_server_license_exchange(
     reader: &mut FramedReader,
     writer: &mut ErasedWriter,
     config: &InputConfig,
-    global_channel_id: u16,
+    global_channel_id: ChannelId,
 ) -> Result<(), RdpError> {
     let mut codec = SendPduDataContextTransport::<ClientNewLicenseRequest, InitialServerLicenseMessage>::default();
     let (channel_ids, initial_license_message) = reader.decode_next_frame(&mut codec).await?;
@@ -336,26 +813,71 @@ pub async fn process_server_license_exchange(
     check_global_id(channel_ids, global_channel_id)?;
 
     debug!("Received Initial License Message PDU");
+    config
+        .diagnostics
+        .record_pdu(PduDirection::Received, "Initial License Message");
     trace!("{:?}", initial_license_message);
 
-    let (new_license_request, encryption_data) = match initial_license_message.message_type {
-        InitialMessageType::LicenseRequest(license_request) => {
-            let mut client_random = vec![0u8; RANDOM_NUMBER_SIZE];
+    let license_request = match initial_license_message.message_type {
+        InitialMessageType::LicenseRequest(license_request) => license_request,
+        InitialMessageType::StatusValidClient(_) => {
+            info!("The server has not initiated license exchange");
+
+            return Ok(());
+        }
+    };
+
+    let mut client_random = vec![0u8; RANDOM_NUMBER_SIZE];
+
+    let rand = ring::rand::SystemRandom::new();
+    rand.fill(&mut client_random)
+        .map_err(|err| RdpError::IOError(io::Error::new(io::ErrorKind::InvalidData, format!("{}", err))))?;
+
+    let mut premaster_secret = vec![0u8; PREMASTER_SECRET_SIZE];
+    rand.fill(&mut premaster_secret)
+        .map_err(|err| RdpError::IOError(io::Error::new(io::ErrorKind::InvalidData, format!("{}", err))))?;
+
+    // the hardware ID the licenses are bound to is derived from the client name, which is stable across the connections
+    let client_name = config.client_name.clone().unwrap_or_else(whoami::hostname);
+
+    let encryption_data = match load_license(config, &license_request) {
+        Some(license_info) => {
+            let (license_information, encryption_data) = ClientLicenseInformation::from_server_license_request(
+                &license_request,
+                client_random.as_slice(),
+                premaster_secret.as_slice(),
+                license_info.as_slice(),
+                &client_name,
+            )
+            .map_err(|err| {
+                RdpError::IOError(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "Unable to generate Client License Information from Server License Request: {}",
+                        err
+                    ),
+                ))
+            })?;
 
-            let rand = ring::rand::SystemRandom::new();
-            rand.fill(&mut client_random)
-                .map_err(|err| RdpError::IOError(io::Error::new(io::ErrorKind::InvalidData, format!("{}", err))))?;
+            debug!("Successfully generated Client License Information");
+            trace!("{:?}", license_information);
+            trace!("{:?}", encryption_data);
 
-            let mut premaster_secret = vec![0u8; PREMASTER_SECRET_SIZE];
-            rand.fill(&mut premaster_secret)
-                .map_err(|err| RdpError::IOError(io::Error::new(io::ErrorKind::InvalidData, format!("{}", err))))?;
+            let mut codec = codec.map_context::<ClientLicenseInformation, InitialServerLicenseMessage>();
+            encode_next_frame(writer, &mut codec, license_information).await?;
+            config
+                .diagnostics
+                .record_pdu(PduDirection::Sent, "Client License Information");
 
-            ClientNewLicenseRequest::from_server_license_request(
+            encryption_data
+        }
+        None => {
+            let (new_license_request, encryption_data) = ClientNewLicenseRequest::from_server_license_request(
                 &license_request,
                 client_random.as_slice(),
                 premaster_secret.as_slice(),
                 &config.credentials.username,
-                config.credentials.domain.as_deref().unwrap_or(""),
+                &client_name,
             )
             .map_err(|err| {
                 RdpError::IOError(io::Error::new(
@@ -365,40 +887,55 @@ pub async fn process_server_license_exchange(
                         err
                     ),
                 ))
-            })?
-        }
-        InitialMessageType::StatusValidClient(_) => {
-            info!("The server has not initiated license exchange");
+            })?;
 
-            return Ok(());
-        }
-    };
+            debug!("Successfully generated Client New License Request");
+            trace!("{:?}", new_license_request);
+            trace!("{:?}", encryption_data);
 
-    debug!("Successfully generated Client New License Request");
-    trace!("{:?}", new_license_request);
-    trace!("{:?}", encryption_data);
+            encode_next_frame(writer, &mut codec, new_license_request).await?;
+            config
+                .diagnostics
+                .record_pdu(PduDirection::Sent, "Client New License Request");
 
-    encode_next_frame(writer, &mut codec, new_license_request).await?;
+            encryption_data
+        }
+    };
 
     let mut codec = SendPduDataContextTransport::<ClientPlatformChallengeResponse, ServerPlatformChallenge>::default();
-    let (channel_ids, challenge) = reader.decode_next_frame(&mut codec).await?;
+    let (channel_ids, challenge) = match reader.decode_next_frame(&mut codec).await {
+        // the license presented in the Client License Information may be accepted without a challenge
+        Err(RdpError::ServerLicenseError(rdp::RdpError::ServerLicenseError(
+            rdp::server_license::ServerLicenseError::UnexpectedValidClientError(_),
+        ))) => {
+            info!("The server has accepted the license");
+            return Ok(());
+        }
+        Ok(data) => data,
+        Err(err) => {
+            return Err(err);
+        }
+    };
+    config
+        .diagnostics
+        .record_pdu(PduDirection::Received, "Server Platform Challenge");
     check_global_id(channel_ids, global_channel_id)?;
 
-    let challenge_response = ClientPlatformChallengeResponse::from_server_platform_challenge(
-        &challenge,
-        config.credentials.domain.as_deref().unwrap_or(""),
-        &encryption_data,
-    )
-    .map_err(|err| {
-        RdpError::ServerLicenseError(rdp::RdpError::IOError(io::Error::new(
-            io::ErrorKind::InvalidData,
-            format!("Unable to generate Client Platform Challenge Response {}", err),
-        )))
-    })?;
+    let challenge_response =
+        ClientPlatformChallengeResponse::from_server_platform_challenge(&challenge, &client_name, &encryption_data)
+            .map_err(|err| {
+                RdpError::ServerLicenseError(rdp::RdpError::IOError(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Unable to generate Client Platform Challenge Response {}", err),
+                )))
+            })?;
 
     debug!("Successfully generated Client Platform Challenge Response");
     trace!("{:?}", challenge_response);
     encode_next_frame(writer, &mut codec, challenge_response).await?;
+    config
+        .diagnostics
+        .record_pdu(PduDirection::Sent, "Client Platform Challenge Response");
 
     let mut codec = SendPduDataContextTransport::<ServerUpgradeLicense>::default();
     let (channel_ids, upgrade_license) = match reader.decode_next_frame(&mut codec).await {
@@ -416,9 +953,12 @@ pub async fn process_server_license_exchange(
     check_global_id(channel_ids, global_channel_id)?;
 
     debug!("Received Server Upgrade License PDU");
+    config
+        .diagnostics
+        .record_pdu(PduDirection::Received, "Server Upgrade License");
     trace!("{:?}", upgrade_license);
 
-    upgrade_license.verify_server_license(&encryption_data).map_err(|err| {
+    let license_information = upgrade_license.decrypt_license_info(&encryption_data).map_err(|err| {
         RdpError::ServerLicenseError(rdp::RdpError::IOError(io::Error::new(
             io::ErrorKind::InvalidData,
             format!("License verification failed: {:?}", err),
@@ -426,10 +966,34 @@ pub async fn process_server_license_exchange(
     })?;
 
     debug!("Successfully verified the license");
+    store_license(config, &license_information);
 
     Ok(())
 }
 
+/// Loads the license stored for one of the scopes of the license request, the errors of the store being ignored.
+fn load_license(config: &InputConfig, license_request: &ServerLicenseRequest) -> Option<Vec<u8>> {
+    let store = config.license_store.as_ref()?;
+
+    LicenseKey::from_license_request(license_request).find_map(|key| match store.load(&key) {
+        Ok(license) => license,
+        Err(err) => {
+            warn!("Unable to load the license of {:?}: {}", key, err);
+            None
+        }
+    })
+}
+
+fn store_license(config: &InputConfig, license_information: &NewLicenseInformation) {
+    if let Some(store) = config.license_store.as_ref() {
+        let key = LicenseKey::from_license_information(license_information);
+        match store.store(&key, &license_information.license_info) {
+            Ok(()) => debug!("Stored the license of {:?}", key),
+            Err(err) => warn!("Unable to store the license of {:?}: {}", key, err),
+        }
+    }
+}
+
 pub async fn process_capability_sets(
     reader: &mut FramedReader,
     writer: &mut ErasedWriter,
@@ -437,17 +1001,31 @@ pub async fn process_capability_sets(
     config: &InputConfig,
 ) -> Result<DesktopSize, RdpError> {
     let share_control_pdu = reader.decode_next_frame(&mut codec).await?;
-    let capability_sets = if let ironrdp::ShareControlPdu::ServerDemandActive(server_demand_active) = share_control_pdu
-    {
-        debug!("Got Server Demand Active PDU: {:?}", server_demand_active.pdu);
-        server_demand_active.pdu.capability_sets
-    } else {
-        return Err(RdpError::UnexpectedPdu(format!(
-            "Expected Server Demand Active PDU, got: {:?}",
-            share_control_pdu.as_short_name()
-        )));
+    config
+        .diagnostics
+        .record_pdu(PduDirection::Received, share_control_pdu.as_short_name());
+    let server_demand_active = match share_control_pdu {
+        ironrdp::ShareControlPdu::ServerDemandActive(server_demand_active) => {
+            debug!("Got Server Demand Active PDU: {:?}", server_demand_active.pdu);
+            config
+                .diagnostics
+                .record_capability_sets(PduDirection::Received, &server_demand_active.pdu.capability_sets);
+            server_demand_active
+        }
+        ironrdp::ShareControlPdu::ServerRedirection(server_redirection) => {
+            debug!("Got Server Redirection PDU: {:?}", server_redirection);
+            return Err(RdpError::ServerRedirection(Box::new(server_redirection)));
+        }
+        share_control_pdu => {
+            return Err(RdpError::UnexpectedPdu(format!(
+                "Expected Server Demand Active PDU, got: {:?}",
+                share_control_pdu.as_short_name()
+            )));
+        }
     };
-    let desktop_size = capability_sets
+    let desktop_size = server_demand_active
+        .pdu
+        .capability_sets
         .iter()
         .find(|c| matches!(c, CapabilitySet::Bitmap(_)))
         .map(|c| match c {
@@ -462,11 +1040,15 @@ pub async fn process_capability_sets(
             height: config.height,
         });
 
-    let client_confirm_active = ironrdp::ShareControlPdu::ClientConfirmActive(user_info::create_client_confirm_active(
-        config,
-        capability_sets,
-    )?);
+    let client_confirm_active = user_info::create_client_confirm_active(config, &server_demand_active)?;
+    config
+        .diagnostics
+        .record_capability_sets(PduDirection::Sent, &client_confirm_active.pdu.capability_sets);
+    let client_confirm_active = ironrdp::ShareControlPdu::ClientConfirmActive(client_confirm_active);
     debug!("Send Client Confirm Active PDU: {:?}", client_confirm_active);
+    config
+        .diagnostics
+        .record_pdu(PduDirection::Sent, client_confirm_active.as_short_name());
     encode_next_frame(writer, &mut codec, client_confirm_active).await?;
     Ok(desktop_size)
 }
@@ -474,8 +1056,8 @@ pub async fn process_capability_sets(
 pub async fn process_finalization(
     reader: &mut FramedReader,
     writer: &mut ErasedWriter,
-    mut codec: ShareDataHeaderTransport,
-    initiator_id: u16,
+    codec: &mut ShareDataHeaderTransport,
+    initiator_id: UserId,
 ) -> Result<(), RdpError> {
     use ironrdp::rdp::{ControlAction, ControlPdu, FontPdu, SequenceFlags, ShareDataPdu, SynchronizePdu};
 
@@ -492,7 +1074,7 @@ pub async fn process_finalization(
     while finalization_order != FinalizationOrder::Finished {
         let share_data_pdu = match finalization_order {
             FinalizationOrder::Synchronize => ShareDataPdu::Synchronize(SynchronizePdu {
-                target_user_id: initiator_id,
+                target_user_id: initiator_id.0,
             }),
             FinalizationOrder::ControlCooperate => ShareDataPdu::Control(ControlPdu {
                 action: ControlAction::Cooperate,
@@ -513,8 +1095,8 @@ pub async fn process_finalization(
             FinalizationOrder::Finished => unreachable!(),
         };
         debug!("Send Finalization PDU: {:?}", share_data_pdu);
-        encode_next_frame(writer, &mut codec, share_data_pdu).await?;
-        let share_data_pdu = reader.decode_next_frame(&mut codec).await?;
+        encode_next_frame(writer, codec, share_data_pdu).await?;
+        let share_data_pdu = reader.decode_next_frame(codec).await?;
         debug!("Got Finalization PDU: {:?}", share_data_pdu);
 
         finalization_order = match (finalization_order, share_data_pdu) {
@@ -534,7 +1116,7 @@ pub async fn process_finalization(
                     grant_id,
                     control_id,
                 }),
-            ) if grant_id == initiator_id && control_id == u32::from(SERVER_CHANNEL_ID) => FinalizationOrder::Font,
+            ) if grant_id == initiator_id.0 && control_id == u32::from(SERVER_CHANNEL_ID) => FinalizationOrder::Font,
             (FinalizationOrder::Font, ShareDataPdu::FontMap(_)) => FinalizationOrder::Finished,
             (
                 order,
@@ -558,7 +1140,7 @@ pub async fn process_finalization(
     Ok(())
 }
 
-fn check_global_id(channel_ids: ChannelIdentificators, id: u16) -> Result<(), RdpError> {
+fn check_global_id(channel_ids: ChannelIdentificators, id: ChannelId) -> Result<(), RdpError> {
     if channel_ids.channel_id != id {
         Err(RdpError::InvalidResponse(format!(
             "Unexpected Send Data Context channel ID ({})",
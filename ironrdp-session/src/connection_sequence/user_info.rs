@@ -1,47 +1,159 @@
-use std::{env, net, str::FromStr};
+#[cfg(test)]
+mod tests;
+
+use std::{env, net};
 
 use ironrdp::gcc::{
-    Channel, ChannelOptions, ClientCoreData, ClientCoreOptionalData, ClientEarlyCapabilityFlags, ClientGccBlocks,
-    ClientNetworkData, ClientSecurityData, ColorDepth, ConnectionType, HighColorDepth, RdpVersion,
-    SecureAccessSequence, SupportedColorDepths,
+    Channel, ChannelOptions, ClientClusterData, ClientCoreData, ClientCoreOptionalData, ClientEarlyCapabilityFlags,
+    ClientGccBlocks, ClientMonitorData, ClientMonitorExtendedData, ClientNetworkData, ClientSecurityData, ColorDepth,
+    ConnectionType, ExtendedMonitorInfo, HighColorDepth, Monitor, MonitorFlags, RdpVersion, RedirectionFlags,
+    RedirectionVersion, SecureAccessSequence, SupportedColorDepths, CLIENT_NAME_SIZE, DIG_PRODUCT_ID_SIZE,
+    IME_FILE_NAME_SIZE,
 };
 use ironrdp::nego::SecurityProtocol;
 use ironrdp::rdp::capability_sets::{
-    Bitmap, BitmapCache, BitmapCodecs, BitmapDrawingFlags, Brush, CacheDefinition, CacheEntry, CaptureFlags, CmdFlags,
-    Codec, CodecProperty, EntropyBits, FrameAcknowledge, General, GeneralExtraFlags, GlyphCache, GlyphSupportLevel,
-    Input, InputFlags, LargePointer, LargePointerSupportFlags, MajorPlatformType, MinorPlatformType,
-    MultifragmentUpdate, OffscreenBitmapCache, Order, OrderFlags, OrderSupportExFlags, Pointer, RemoteFxContainer,
-    RfxCaps, RfxCapset, RfxClientCapsContainer, RfxICap, RfxICapFlags, Sound, SoundFlags, SupportLevel,
-    SurfaceCommands, VirtualChannel, VirtualChannelFlags, BITMAP_CACHE_ENTRIES_NUM, GLYPH_CACHE_NUM,
+    Bitmap, BitmapCache, BitmapCacheRev2, BitmapDrawingFlags, Brush, CacheDefinition, CacheEntry, CacheFlags, CellInfo,
+    CmdFlags, FrameAcknowledge, General, GeneralExtraFlags, GlyphCache, GlyphSupportLevel, Input, InputFlags,
+    LargePointer, LargePointerSupportFlags, MajorPlatformType, MinorPlatformType, MultifragmentUpdate,
+    OffscreenBitmapCache, Order, OrderFlags, OrderSupportExFlags, OrderSupportIndex, Pointer, Sound, SoundFlags,
+    SupportLevel, SurfaceCommands, VirtualChannel, VirtualChannelFlags, BITMAP_CACHE_ENTRIES_NUM,
+    BITMAP_CACHE_REV2_CELL_INFO_NUM, GLYPH_CACHE_NUM,
 };
+use ironrdp::rdp::vc::StaticChannelName;
 use ironrdp::rdp::{
     AddressFamily, BasicSecurityHeader, BasicSecurityHeaderFlags, ClientInfo, ClientInfoFlags, ClientInfoPdu,
-    CompressionType, Credentials, ExtendedClientInfo, ExtendedClientOptionalInfo, SERVER_CHANNEL_ID,
+    CompressionType, Credentials, ExtendedClientInfo, ExtendedClientOptionalInfo, PerformanceFlags, TimezoneInfo,
 };
-use ironrdp::{CapabilitySet, ClientConfirmActive};
-use num_traits::ToPrimitive;
+use ironrdp::{CapabilitySet, ClientConfirmActive, ServerDemandActive};
 
-use crate::utils::CodecId;
-use crate::{InputConfig, RdpError};
+use crate::active_session::POINTER_CACHE_SIZE;
+use crate::codec_registry;
+use crate::{InputConfig, MonitorConfig, RdpError};
 
 const SOURCE_DESCRIPTOR: &str = "IRONRDP";
+const MAX_MONITOR_COUNT: usize = 16;
+/// The client random of the auto-reconnect cookie, all zeros with Enhanced RDP Security (TLS and CredSSP).
+const ENHANCED_SECURITY_CLIENT_RANDOM: [u8; 32] = [0; 32];
+/// The number of entries of the bitmap caches, holding bitmaps of up to 256, 1024 and 4096 pixels.
+const BITMAP_CACHE_CELLS: [u32; 3] = [600, 600, 2048];
 
 pub fn create_gcc_blocks(
     config: &InputConfig,
     selected_protocol: SecurityProtocol,
 ) -> Result<ClientGccBlocks, RdpError> {
+    let (monitor, monitor_extended) = match create_monitor_data(&config.monitors)? {
+        Some((monitor, monitor_extended)) => (Some(monitor), Some(monitor_extended)),
+        None => (None, None),
+    };
+
     Ok(ClientGccBlocks {
         core: create_core_data(config, selected_protocol)?,
-        security: create_security_data(),
+        security: create_security_data(config),
         network: Some(create_network_data(config)),
-        cluster: None,
-        monitor: None,
+        cluster: Some(create_cluster_data(config)),
+        monitor,
         message_channel: None,
         multi_transport_channel: None,
-        monitor_extended: None,
+        monitor_extended,
     })
 }
 
+/// Advertises the support of the Server Redirection PDU, which the connection brokers
+/// send instead of the Server Demand Active PDU, and the session the client has been redirected to.
+fn create_cluster_data(config: &InputConfig) -> ClientClusterData {
+    let mut flags = RedirectionFlags::REDIRECTION_SUPPORTED;
+    flags.set(
+        RedirectionFlags::REDIRECTED_SESSION_FIELD_VALID,
+        config.redirected_session_id.is_some(),
+    );
+
+    ClientClusterData {
+        flags,
+        redirection_version: RedirectionVersion::V4,
+        redirected_session_id: config.redirected_session_id.unwrap_or(0),
+    }
+}
+
+/// Creates the Client Monitor Data and Client Monitor Extended Data blocks, the first monitor
+/// being the primary one if none is marked as such. No block is sent for an empty layout.
+fn create_monitor_data(
+    monitors: &[MonitorConfig],
+) -> Result<Option<(ClientMonitorData, ClientMonitorExtendedData)>, RdpError> {
+    if monitors.is_empty() {
+        return Ok(None);
+    }
+
+    if monitors.len() > MAX_MONITOR_COUNT {
+        return Err(RdpError::InvalidMonitorLayout(format!(
+            "{} monitors, at most {} are supported",
+            monitors.len(),
+            MAX_MONITOR_COUNT
+        )));
+    }
+
+    let primary_index = match monitors.iter().filter(|monitor| monitor.primary).count() {
+        0 => 0,
+        1 => monitors.iter().position(|monitor| monitor.primary).unwrap(),
+        count => {
+            return Err(RdpError::InvalidMonitorLayout(format!(
+                "{} monitors are marked as primary",
+                count
+            )))
+        }
+    };
+
+    let primary = &monitors[primary_index];
+    if (primary.left, primary.top) != (0, 0) {
+        return Err(RdpError::InvalidMonitorLayout(format!(
+            "the primary monitor is at ({}, {}) instead of the origin",
+            primary.left, primary.top
+        )));
+    }
+
+    if let Some(monitor) = monitors
+        .iter()
+        .find(|monitor| monitor.width == 0 || monitor.height == 0)
+    {
+        return Err(RdpError::InvalidMonitorLayout(format!(
+            "the monitor at ({}, {}) is empty",
+            monitor.left, monitor.top
+        )));
+    }
+
+    let monitor_data = ClientMonitorData {
+        monitors: monitors
+            .iter()
+            .enumerate()
+            .map(|(i, monitor)| Monitor {
+                left: monitor.left,
+                top: monitor.top,
+                // the bounds are inclusive
+                right: monitor.left + monitor.width as i32 - 1,
+                bottom: monitor.top + monitor.height as i32 - 1,
+                flags: if i == primary_index {
+                    MonitorFlags::PRIMARY
+                } else {
+                    MonitorFlags::empty()
+                },
+            })
+            .collect(),
+    };
+    let monitor_extended_data = ClientMonitorExtendedData {
+        extended_monitors_info: monitors
+            .iter()
+            .map(|monitor| ExtendedMonitorInfo {
+                physical_width: monitor.physical_width,
+                physical_height: monitor.physical_height,
+                orientation: monitor.orientation,
+                desktop_scale_factor: monitor.scale_factor,
+                device_scale_factor: monitor.device_scale_factor(),
+            })
+            .collect(),
+    };
+
+    Ok(Some((monitor_data, monitor_extended_data)))
+}
+
 pub fn create_client_info_pdu(config: &InputConfig, routing_addr: &net::SocketAddr) -> Result<ClientInfoPdu, RdpError> {
     let security_header = BasicSecurityHeader {
         flags: BasicSecurityHeaderFlags::INFO_PKT,
@@ -49,27 +161,15 @@ pub fn create_client_info_pdu(config: &InputConfig, routing_addr: &net::SocketAd
     let client_info = ClientInfo {
         credentials: auth_identity_to_credentials(config.credentials.clone()),
         code_page: 0, // ignored if the keyboardLayout field of the Client Core Data is set to zero
-        flags: ClientInfoFlags::UNICODE
-            | ClientInfoFlags::DISABLE_CTRL_ALT_DEL
-            | ClientInfoFlags::LOGON_NOTIFY
-            | ClientInfoFlags::LOGON_ERRORS
-            | ClientInfoFlags::NO_AUDIO_PLAYBACK
-            | ClientInfoFlags::VIDEO_DISABLE,
-        compression_type: CompressionType::K8, // ignored if ClientInfoFlags::COMPRESSION is not set
-        alternate_shell: String::new(),
-        work_dir: String::new(),
-        extra_info: ExtendedClientInfo {
-            address_family: match routing_addr {
-                net::SocketAddr::V4(_) => AddressFamily::INet,
-                net::SocketAddr::V6(_) => AddressFamily::INet6,
-            },
-            address: routing_addr.ip().to_string(),
-            dir: env::current_dir()
-                .map_err(|e| RdpError::UserInfoError(format!("Failed to get current directory path: {:?}", e)))?
-                .to_string_lossy()
-                .to_string(),
-            optional_data: ExtendedClientOptionalInfo::default(),
-        },
+        flags: create_client_info_flags(config),
+        // ignored if ClientInfoFlags::COMPRESSION is not set
+        compression_type: config
+            .client_info
+            .compression_type
+            .map_or(CompressionType::K8, advertised_compression_type),
+        alternate_shell: config.client_info.alternate_shell.clone(),
+        work_dir: config.client_info.work_dir.clone(),
+        extra_info: create_extended_client_info(config, routing_addr)?,
     };
 
     Ok(ClientInfoPdu {
@@ -78,16 +178,105 @@ pub fn create_client_info_pdu(config: &InputConfig, routing_addr: &net::SocketAd
     })
 }
 
+fn create_extended_client_info(
+    config: &InputConfig,
+    routing_addr: &net::SocketAddr,
+) -> Result<ExtendedClientInfo, RdpError> {
+    let address = config.client_info.address.unwrap_or_else(|| routing_addr.ip());
+    let dir = match &config.client_info.dir {
+        Some(dir) => dir.clone(),
+        None => env::current_dir()
+            .map_err(|e| RdpError::UserInfoError(format!("Failed to get current directory path: {:?}", e)))?
+            .to_string_lossy()
+            .to_string(),
+    };
+
+    let client_info = &config.client_info;
+    let reconnect_cookie = client_info
+        .auto_reconnect
+        .as_ref()
+        .map(|auto_reconnect| auto_reconnect.client_cookie(&ENHANCED_SECURITY_CLIENT_RANDOM));
+
+    // the optional fields are written in order up to the first missing one
+    let optional_data =
+        if client_info.timezone.is_some() || client_info.performance_flags.is_some() || reconnect_cookie.is_some() {
+            ExtendedClientOptionalInfo {
+                timezone: Some(client_info.timezone.clone().unwrap_or_else(utc_timezone)),
+                session_id: Some(0),
+                // the performance flags precede the cookie
+                performance_flags: client_info
+                    .performance_flags
+                    .or_else(|| reconnect_cookie.map(|_| PerformanceFlags::empty())),
+                reconnect_cookie,
+            }
+        } else {
+            ExtendedClientOptionalInfo::default()
+        };
+
+    Ok(ExtendedClientInfo {
+        address_family: match address {
+            net::IpAddr::V4(_) => AddressFamily::INet,
+            net::IpAddr::V6(_) => AddressFamily::INet6,
+        },
+        address: address.to_string(),
+        dir,
+        optional_data,
+    })
+}
+
+fn utc_timezone() -> TimezoneInfo {
+    TimezoneInfo {
+        bias: 0,
+        standard_name: String::new(),
+        standard_date: None,
+        standard_bias: 0,
+        daylight_name: String::new(),
+        daylight_date: None,
+        daylight_bias: 0,
+    }
+}
+
+fn create_client_info_flags(config: &InputConfig) -> ClientInfoFlags {
+    let mut flags = ClientInfoFlags::UNICODE
+        | ClientInfoFlags::DISABLE_CTRL_ALT_DEL
+        | ClientInfoFlags::LOGON_NOTIFY
+        | ClientInfoFlags::LOGON_ERRORS
+        | ClientInfoFlags::VIDEO_DISABLE;
+
+    if !config.audio_playback {
+        flags |= ClientInfoFlags::NO_AUDIO_PLAYBACK;
+    }
+    if config.client_info.compression_type.is_some() {
+        flags |= ClientInfoFlags::COMPRESSION;
+    }
+
+    flags
+}
+
+/// The decompression of RDP 6.0 and RDP 6.1 is not supported, so MPPC 64K is advertised instead.
+fn advertised_compression_type(compression_type: CompressionType) -> CompressionType {
+    match compression_type {
+        CompressionType::K8 | CompressionType::K64 => compression_type,
+        CompressionType::Rdp6 | CompressionType::Rdp61 => {
+            warn!(
+                "The {:?} compression is not supported, MPPC 64K is advertised instead",
+                compression_type
+            );
+
+            CompressionType::K64
+        }
+    }
+}
+
 pub fn create_client_confirm_active(
     config: &InputConfig,
-    mut server_capability_sets: Vec<CapabilitySet>,
+    server_demand_active: &ServerDemandActive,
 ) -> Result<ClientConfirmActive, RdpError> {
-    server_capability_sets.retain(|capability_set| matches!(capability_set, CapabilitySet::MultiFragmentUpdate(_)));
-    server_capability_sets.extend_from_slice(&[
+    let mut capability_sets = vec![
         create_general_capability_set(),
         create_bitmap_capability_set(config),
-        create_orders_capability_set(),
-        create_bitmap_cache_capability_set(),
+        create_orders_capability_set(config),
+        create_bitmap_cache_capability_set(config),
         create_input_capability_set(config),
         create_pointer_capability_set(),
         create_brush_capability_set(),
@@ -96,27 +285,25 @@ pub fn create_client_confirm_active(
         create_virtual_channel_capability_set(),
         create_sound_capability_set(),
         create_large_pointer_capability_set(),
-        create_surface_commands_capability_set(),
-        create_bitmap_codes_capability_set(),
         CapabilitySet::FrameAcknowledge(FrameAcknowledge {
             max_unacknowledged_frame_count: 2,
         }),
-    ]);
+        create_multi_fragment_update_capability_set(),
+    ];
 
-    if !server_capability_sets
-        .iter()
-        .any(|c| matches!(&c, CapabilitySet::MultiFragmentUpdate(_)))
-    {
-        server_capability_sets.push(create_multi_fragment_update_capability_set());
+    let bitmap_codecs = config.codecs.bitmap_codecs();
+    if !bitmap_codecs.0.is_empty() {
+        capability_sets.extend_from_slice(&[
+            create_surface_commands_capability_set(),
+            CapabilitySet::BitmapCodecs(bitmap_codecs),
+        ]);
     }
 
-    Ok(ClientConfirmActive {
-        originator_id: SERVER_CHANNEL_ID,
-        pdu: ironrdp::DemandActive {
-            source_descriptor: SOURCE_DESCRIPTOR.to_string(),
-            capability_sets: server_capability_sets,
-        },
-    })
+    Ok(ClientConfirmActive::mirrored(
+        server_demand_active,
+        SOURCE_DESCRIPTOR.to_string(),
+        capability_sets,
+    ))
 }
 
 fn create_core_data(config: &InputConfig, selected_protocol: SecurityProtocol) -> Result<ClientCoreData, RdpError> {
@@ -127,14 +314,16 @@ fn create_core_data(config: &InputConfig, selected_protocol: SecurityProtocol) -
         color_depth: ColorDepth::Bpp4, // ignored
         sec_access_sequence: SecureAccessSequence::Del,
         keyboard_layout: 0, // the server SHOULD use the default active input locale identifier
-        client_build: semver::Version::parse(env!("CARGO_PKG_VERSION"))
-            .map(|version| version.major * 100 + version.minor * 10 + version.patch)
-            .unwrap_or(0) as u32,
-        client_name: whoami::hostname(),
+        client_build: config.client_build.unwrap_or_else(default_client_build),
+        client_name: validate_client_field(
+            "client name",
+            config.client_name.clone().unwrap_or_else(whoami::hostname),
+            CLIENT_NAME_SIZE,
+        )?,
         keyboard_type: config.keyboard_type,
         keyboard_subtype: config.keyboard_subtype,
         keyboard_functional_keys_count: config.keyboard_functional_keys_count,
-        ime_file_name: config.ime_file_name.clone(),
+        ime_file_name: validate_client_field("IME file name", config.ime_file_name.clone(), IME_FILE_NAME_SIZE)?,
         optional_data: create_optional_core_data(config, selected_protocol)?,
     })
 }
@@ -146,7 +335,7 @@ fn create_optional_core_data(
     let mut early_capability_flags =
         ClientEarlyCapabilityFlags::VALID_CONNECTION_TYPE | ClientEarlyCapabilityFlags::SUPPORT_ERR_INFO_PDU;
 
-    if config.graphics_config.is_some() {
+    if config.is_graphics_pipeline_enabled() {
         early_capability_flags |= ClientEarlyCapabilityFlags::SUPPORT_DYN_VC_GFX_PROTOCOL;
     }
 
@@ -157,7 +346,11 @@ fn create_optional_core_data(
         high_color_depth: Some(HighColorDepth::Bpp24),
         supported_color_depths: Some(SupportedColorDepths::all()),
         early_capability_flags: Some(early_capability_flags),
-        dig_product_id: Some(config.dig_product_id.clone()),
+        dig_product_id: Some(validate_client_field(
+            "dig product ID",
+            config.dig_product_id.clone().unwrap_or_else(default_dig_product_id),
+            DIG_PRODUCT_ID_SIZE,
+        )?),
         connection_type: Some(ConnectionType::Lan),
         server_selected_protocol: Some(selected_protocol),
         desktop_physical_width: None,
@@ -168,21 +361,69 @@ fn create_optional_core_data(
     })
 }
 
-fn create_security_data() -> ClientSecurityData {
-    ClientSecurityData::no_security()
+fn default_client_build() -> u32 {
+    semver::Version::parse(env!("CARGO_PKG_VERSION"))
+        .map(|version| version.major * 100 + version.minor * 10 + version.patch)
+        .unwrap_or(0) as u32
+}
+
+fn default_dig_product_id() -> String {
+    format!("{}/{}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"))
+}
+
+/// Checks that the value can be written into a fixed-size null-terminated UTF-16 field.
+/// Values that are too long are truncated at a character boundary when encoded.
+fn validate_client_field(field_name: &str, value: String, field_size: usize) -> Result<String, RdpError> {
+    if value.contains('\0') {
+        return Err(RdpError::InvalidClientMetadata(format!(
+            "{} contains a null character",
+            field_name
+        )));
+    }
+
+    let max_length = field_size / 2 - 1;
+    if value.encode_utf16().count() > max_length {
+        warn!(
+            "The {} {:?} is longer than {} UTF-16 characters and will be truncated",
+            field_name, value, max_length
+        );
+    }
+
+    Ok(value)
+}
+
+fn create_security_data(config: &InputConfig) -> ClientSecurityData {
+    ClientSecurityData {
+        encryption_methods: config.encryption_methods,
+        ext_encryption_methods: 0,
+    }
 }
 
 fn create_network_data(config: &InputConfig) -> ClientNetworkData {
-    if config.graphics_config.is_some() {
-        ClientNetworkData {
-            channels: vec![Channel {
-                name: String::from_str("drdynvc").unwrap(),
-                options: ChannelOptions::COMPRESS_RDP,
-            }],
-        }
-    } else {
-        ClientNetworkData { channels: Vec::new() }
+    let mut channels = Vec::new();
+
+    if config.is_graphics_pipeline_enabled() {
+        channels.push(Channel {
+            name: String::from(StaticChannelName::DRDYNVC),
+            options: ChannelOptions::COMPRESS_RDP,
+        });
     }
+
+    if config.audio_playback {
+        channels.push(Channel {
+            name: String::from(StaticChannelName::RDPSND),
+            options: ChannelOptions::INITIALIZED,
+        });
+    }
+
+    if config.drive_redirection || config.smart_card_redirection {
+        channels.push(Channel {
+            name: String::from(StaticChannelName::RDPDR),
+            options: ChannelOptions::INITIALIZED,
+        });
+    }
+
+    ClientNetworkData { channels }
 }
 
 fn create_general_capability_set() -> CapabilitySet {
@@ -212,34 +453,54 @@ fn create_bitmap_capability_set(config: &InputConfig) -> CapabilitySet {
     })
 }
 
-fn create_orders_capability_set() -> CapabilitySet {
-    CapabilitySet::Order(Order::new(
+fn create_orders_capability_set(config: &InputConfig) -> CapabilitySet {
+    let mut order = Order::new(
         OrderFlags::NEGOTIATE_ORDER_SUPPORT | OrderFlags::ZERO_BOUNDS_DELTAS_SUPPORT,
         OrderSupportExFlags::empty(),
         0,
         0,
-    ))
+    );
+    // the cached bitmaps are drawn with the MemBlt order, the only one supported
+    order.set_support_flag(
+        OrderSupportIndex::MemBlt,
+        config.codecs.contains(codec_registry::Codec::Bitmap),
+    );
+
+    CapabilitySet::Order(order)
 }
 
-fn create_bitmap_cache_capability_set() -> CapabilitySet {
-    CapabilitySet::BitmapCache(BitmapCache {
-        caches: [CacheEntry {
-            entries: 0,
-            max_cell_size: 0,
-        }; BITMAP_CACHE_ENTRIES_NUM],
+fn create_bitmap_cache_capability_set(config: &InputConfig) -> CapabilitySet {
+    if !config.codecs.contains(codec_registry::Codec::Bitmap) {
+        return CapabilitySet::BitmapCache(BitmapCache {
+            caches: [CacheEntry {
+                entries: 0,
+                max_cell_size: 0,
+            }; BITMAP_CACHE_ENTRIES_NUM],
+        });
+    }
+
+    let mut cache_cell_info = [CellInfo::default(); BITMAP_CACHE_REV2_CELL_INFO_NUM];
+    for (cell_info, &num_entries) in cache_cell_info.iter_mut().zip(BITMAP_CACHE_CELLS.iter()) {
+        cell_info.num_entries = num_entries;
+    }
+
+    CapabilitySet::BitmapCacheRev2(BitmapCacheRev2 {
+        cache_flags: CacheFlags::empty(),
+        num_cell_caches: BITMAP_CACHE_CELLS.len() as u8,
+        cache_cell_info,
     })
 }
 
 fn create_pointer_capability_set() -> CapabilitySet {
     CapabilitySet::Pointer(Pointer {
-        color_pointer_cache_size: 0,
-        pointer_cache_size: 0,
+        color_pointer_cache_size: POINTER_CACHE_SIZE,
+        pointer_cache_size: POINTER_CACHE_SIZE,
     })
 }
 
 fn create_input_capability_set(config: &InputConfig) -> CapabilitySet {
     CapabilitySet::Input(Input {
-        input_flags: InputFlags::SCANCODES,
+        input_flags: InputFlags::SCANCODES | InputFlags::MOUSEX | InputFlags::UNICODE | InputFlags::TS_MOUSE_HWHEEL,
         keyboard_layout: 0,
         keyboard_type: Some(config.keyboard_type),
         keyboard_subtype: config.keyboard_subtype,
@@ -305,19 +566,6 @@ fn create_surface_commands_capability_set() -> CapabilitySet {
     })
 }
 
-fn create_bitmap_codes_capability_set() -> CapabilitySet {
-    CapabilitySet::BitmapCodecs(BitmapCodecs(vec![Codec {
-        id: CodecId::RemoteFx.to_u8().unwrap(),
-        property: CodecProperty::RemoteFx(RemoteFxContainer::ClientContainer(RfxClientCapsContainer {
-            capture_flags: CaptureFlags::empty(),
-            caps_data: RfxCaps(RfxCapset(vec![RfxICap {
-                flags: RfxICapFlags::empty(),
-                entropy_bits: EntropyBits::Rlgr3,
-            }])),
-        })),
-    }]))
-}
-
 fn auth_identity_to_credentials(auth_identity: sspi::AuthIdentity) -> Credentials {
     Credentials {
         username: auth_identity.username,
@@ -0,0 +1,77 @@
+use super::*;
+
+#[test]
+fn monitor_data_is_not_sent_for_empty_layout() {
+    assert!(create_monitor_data(&[]).unwrap().is_none());
+}
+
+#[test]
+fn monitor_data_has_inclusive_bounds() {
+    let monitors = [
+        MonitorConfig::primary(1920, 1080).with_scale_factor(150),
+        MonitorConfig {
+            primary: false,
+            ..MonitorConfig::primary(1280, 1024).with_position(-1280, 0)
+        },
+    ];
+
+    let (monitor_data, monitor_extended_data) = create_monitor_data(&monitors).unwrap().unwrap();
+
+    assert_eq!(
+        vec![
+            Monitor {
+                left: 0,
+                top: 0,
+                right: 1919,
+                bottom: 1079,
+                flags: MonitorFlags::PRIMARY,
+            },
+            Monitor {
+                left: -1280,
+                top: 0,
+                right: -1,
+                bottom: 1023,
+                flags: MonitorFlags::empty(),
+            },
+        ],
+        monitor_data.monitors
+    );
+    assert_eq!(
+        (150, 140),
+        (
+            monitor_extended_data.extended_monitors_info[0].desktop_scale_factor,
+            monitor_extended_data.extended_monitors_info[0].device_scale_factor
+        )
+    );
+}
+
+#[test]
+fn primary_monitor_has_to_be_at_origin() {
+    let monitors = [MonitorConfig::primary(1920, 1080).with_position(1920, 0)];
+
+    assert!(matches!(
+        create_monitor_data(&monitors),
+        Err(RdpError::InvalidMonitorLayout(_))
+    ));
+}
+
+#[test]
+fn several_primary_monitors_are_rejected() {
+    let monitors = [MonitorConfig::primary(800, 600), MonitorConfig::primary(800, 600)];
+
+    assert!(matches!(
+        create_monitor_data(&monitors),
+        Err(RdpError::InvalidMonitorLayout(_))
+    ));
+}
+
+#[test]
+fn rdp6_compression_types_are_advertised_as_mppc_64k() {
+    assert_eq!(CompressionType::K8, advertised_compression_type(CompressionType::K8));
+    assert_eq!(CompressionType::K64, advertised_compression_type(CompressionType::K64));
+    assert_eq!(CompressionType::K64, advertised_compression_type(CompressionType::Rdp6));
+    assert_eq!(
+        CompressionType::K64,
+        advertised_compression_type(CompressionType::Rdp61)
+    );
+}
@@ -0,0 +1,570 @@
+//! TCP connection and TLS upgrade shared by the native clients.
+//!
+//! The futures returned by this module can be cancelled at any time by dropping them.
+
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use ironrdp::nego;
+use ironrdp::rdp::session_info::ServerAutoReconnect;
+use ironrdp::rdp::{ServerRedirectionFlags, ServerRedirectionPdu};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpStream;
+use tokio_util::compat::{Compat, FuturesAsyncReadCompatExt, TokioAsyncReadCompatExt as _};
+
+use crate::transport::{connect_gateway, GatewayConfig};
+use crate::{
+    probe_session, process_connection_sequence, AuthenticationFailure, ConnectionSequenceResult, ErasedWriter,
+    FramedReader, InputConfig, ProbeResult, RdpError, ServerCertificate, UpgradedStream,
+};
+
+#[cfg(feature = "rustls")]
+pub type TlsStream<S = TcpStream> = Compat<tokio_rustls::client::TlsStream<S>>;
+
+#[cfg(all(feature = "native-tls", not(feature = "rustls")))]
+pub type TlsStream<S = TcpStream> = Compat<async_native_tls::TlsStream<S>>;
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+/// The connection brokers redirect the client once, to the server hosting the session,
+/// so more redirections are likely to be a loop.
+const MAX_REDIRECTIONS: usize = 3;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ConnectTimeouts {
+    pub dns_resolution: Duration,
+    pub tcp_connect: Duration,
+    pub tls_handshake: Duration,
+}
+
+impl Default for ConnectTimeouts {
+    fn default() -> Self {
+        Self {
+            dns_resolution: DEFAULT_TIMEOUT,
+            tcp_connect: DEFAULT_TIMEOUT,
+            tls_handshake: DEFAULT_TIMEOUT,
+        }
+    }
+}
+
+/// How the certificate presented by the server during the TLS handshake is verified.
+///
+/// RDP servers usually present a self-signed certificate. The user can then be asked whether to trust
+/// the certificate carried by [`RdpError::UntrustedServerCertificate`], whose public key is pinned
+/// for the next connections. In any case, the credentials are only sent once the certificate has been verified.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TlsVerification {
+    /// Verifies the certificate chain against the root certificates of the system, and the host name of the server,
+    /// which has to be reached by its DNS name.
+    SystemRoots,
+    /// Verifies the certificate chain against the DER-encoded root certificates, and the host name of the server.
+    /// With `native-tls`, the root certificates of the system are trusted as well.
+    RootStore(Vec<Vec<u8>>),
+    /// Accepts the certificate whose subject public key is the given one, see [`ServerCertificate::public_key`].
+    PinnedPublicKey(Vec<u8>),
+    /// Accepts any certificate, leaving the connection open to man-in-the-middle attacks.
+    Skip,
+}
+
+/// Resolves the `<host>:<port>` server address, connects to it and goes through the connection sequence,
+/// upgrading the stream to TLS with [`establish_tls`], unless only Standard RDP Security is requested
+/// by [`InputConfig::security_protocol`], in which case the stream is left as is.
+///
+/// When the server redirects the client, e.g. a connection broker to the server hosting the session of the user,
+/// the connection sequence is gone through again with the target, on the same port, `config` being updated
+/// with [`InputConfig::apply_redirection`].
+///
+/// If [`InputConfig::gateway`] is set, the connection is tunneled through the gateway, see [`connect_through_gateway`].
+pub async fn connect(
+    server_addr: &str,
+    config: &mut InputConfig,
+    tls_verification: &TlsVerification,
+    timeouts: ConnectTimeouts,
+) -> Result<(ConnectionSequenceResult, FramedReader, ErasedWriter), RdpError> {
+    let mut server_addr = server_addr.to_owned();
+
+    for _ in 0..=MAX_REDIRECTIONS {
+        let result = match &config.gateway {
+            Some(gateway) => connect_through_gateway(gateway, &server_addr, config, tls_verification, timeouts).await,
+            None if config.security_protocol == nego::SecurityProtocol::RDP => {
+                let (stream, routing_addr) = connect_tcp(&server_addr, timeouts).await?;
+
+                process_connection_sequence(stream.compat(), &routing_addr, config, keep_stream).await
+            }
+            None => {
+                let (stream, routing_addr) = connect_tcp(&server_addr, timeouts).await?;
+
+                process_connection_sequence(stream.compat(), &routing_addr, config, |stream| {
+                    establish_tls(
+                        stream.into_inner(),
+                        host_name(&server_addr),
+                        tls_verification,
+                        timeouts.tls_handshake,
+                    )
+                })
+                .await
+            }
+        };
+
+        match result {
+            Err(RdpError::ServerRedirection(redirection)) => {
+                config.apply_redirection(&redirection);
+                if let Some(target) = redirection_target(&redirection) {
+                    server_addr = format_server_addr(target, server_port(&server_addr)?);
+                }
+                info!("Redirected to {}", server_addr);
+            }
+            result => return result,
+        }
+    }
+
+    Err(RdpError::ConnectionError(io::Error::new(
+        io::ErrorKind::Other,
+        format!("redirected more than {} times", MAX_REDIRECTIONS),
+    )))
+}
+
+/// Connects to the `<host>:<port>` address of the gateway, upgrading the stream to TLS, and goes through the
+/// connection sequence over the tunnel to the server, whose host name is resolved by the gateway.
+///
+/// The gateways being reached by their DNS name, their certificate is verified against the root certificates
+/// of the system or of `tls_verification`, unless it skips the verification altogether.
+/// The address of the gateway is the routing address of the connection sequence.
+pub async fn connect_through_gateway(
+    gateway: &GatewayConfig,
+    server_addr: &str,
+    config: &InputConfig,
+    tls_verification: &TlsVerification,
+    timeouts: ConnectTimeouts,
+) -> Result<(ConnectionSequenceResult, FramedReader, ErasedWriter), RdpError> {
+    let gateway_verification = match tls_verification {
+        TlsVerification::SystemRoots | TlsVerification::PinnedPublicKey(_) => TlsVerification::SystemRoots,
+        verification => verification.clone(),
+    };
+    let (stream, gateway_addr) = connect_tcp(&gateway.address, timeouts).await?;
+    let stream = establish_tls(
+        stream,
+        host_name(&gateway.address),
+        &gateway_verification,
+        timeouts.tls_handshake,
+    )
+    .await?
+    .stream;
+
+    let client_name = config.client_name.clone().unwrap_or_else(whoami::hostname);
+    let tunnel = connect_gateway(
+        stream,
+        gateway,
+        host_name(server_addr),
+        server_port(server_addr)?,
+        &client_name,
+    )
+    .await?;
+
+    process_connection_sequence(tunnel, &gateway_addr, config, |stream| {
+        establish_tls(
+            FuturesAsyncReadCompatExt::compat(stream),
+            host_name(server_addr),
+            tls_verification,
+            timeouts.tls_handshake,
+        )
+    })
+    .await
+}
+
+/// Reconnects to the session identified by the auto-reconnect cookie, returned by
+/// [`ActiveStageProcessor::auto_reconnect_cookie`](crate::ActiveStageProcessor::auto_reconnect_cookie)
+/// before the connection has been lost, so that a transient network failure does not require the user to log on again.
+/// The credentials of `config` are still needed for the Network Level Authentication, which can be the ones
+/// of the lost connection, see [`ActiveStageProcessor::take_session_credentials`](crate::ActiveStageProcessor::take_session_credentials).
+///
+/// The channels of the new session are configured as the ones of the lost connection by passing
+/// [`ActiveStageProcessor::into_channel_state`](crate::ActiveStageProcessor::into_channel_state) to
+/// [`ActiveStageProcessor::restore_channel_state`](crate::ActiveStageProcessor::restore_channel_state).
+pub async fn reconnect(
+    server_addr: &str,
+    config: &mut InputConfig,
+    auto_reconnect: ServerAutoReconnect,
+    tls_verification: &TlsVerification,
+    timeouts: ConnectTimeouts,
+) -> Result<(ConnectionSequenceResult, FramedReader, ErasedWriter), RdpError> {
+    config.client_info.auto_reconnect = Some(auto_reconnect);
+
+    connect(server_addr, config, tls_verification, timeouts).await
+}
+
+/// Connects as [`connect`] does, calling `prompt` with the reason and the number of the failed attempt
+/// when the server rejects the credentials, for the user to be asked for other ones, such as a new password.
+/// The authentication is retried with the returned credentials, which are kept in `config`,
+/// until `prompt` gives up by returning `None`.
+///
+/// The server closes the connection once the authentication has failed, so each retry opens a new connection
+/// to the address resolved for the first attempt, without resolving the server address again.
+pub async fn connect_with_credentials_prompt<PromptFn, PromptRes>(
+    server_addr: &str,
+    config: &mut InputConfig,
+    tls_verification: &TlsVerification,
+    timeouts: ConnectTimeouts,
+    mut prompt: PromptFn,
+) -> Result<(ConnectionSequenceResult, FramedReader, ErasedWriter), RdpError>
+where
+    PromptFn: FnMut(AuthenticationFailure, u32) -> PromptRes,
+    PromptRes: Future<Output = Option<sspi::AuthIdentity>>,
+{
+    let (mut stream, routing_addr) = connect_tcp(server_addr, timeouts).await?;
+    let mut attempt = 0;
+
+    loop {
+        attempt += 1;
+        let result = process_connection_sequence(stream.compat(), &routing_addr, config, |stream| {
+            establish_tls(
+                stream.into_inner(),
+                host_name(server_addr),
+                tls_verification,
+                timeouts.tls_handshake,
+            )
+        })
+        .await;
+
+        match result {
+            Err(RdpError::AuthenticationFailed(failure)) => {
+                warn!("Authentication attempt {} failed: {}", attempt, failure);
+
+                config.credentials = prompt(failure, attempt)
+                    .await
+                    .ok_or(RdpError::AuthenticationFailed(failure))?;
+            }
+            result => return result,
+        }
+
+        stream = with_timeout(TcpStream::connect(routing_addr), timeouts.tcp_connect, "TCP connection")
+            .await
+            .map_err(RdpError::ConnectionError)?;
+    }
+}
+
+/// Resolves the `<host>:<port>` server address, connects to it and disconnects right after the MCS connect,
+/// see [`probe_session`].
+pub async fn probe(
+    server_addr: &str,
+    config: &InputConfig,
+    tls_verification: &TlsVerification,
+    timeouts: ConnectTimeouts,
+) -> Result<ProbeResult, RdpError> {
+    let (stream, routing_addr) = connect_tcp(server_addr, timeouts).await?;
+
+    probe_session(stream.compat(), &routing_addr, config, |stream| {
+        establish_tls(
+            stream.into_inner(),
+            host_name(server_addr),
+            tls_verification,
+            timeouts.tls_handshake,
+        )
+    })
+    .await
+}
+
+/// Tries the resolved addresses in order and returns the first established connection.
+pub async fn connect_tcp(server_addr: &str, timeouts: ConnectTimeouts) -> Result<(TcpStream, SocketAddr), RdpError> {
+    let addrs = with_timeout(
+        tokio::net::lookup_host(server_addr),
+        timeouts.dns_resolution,
+        "DNS resolution",
+    )
+    .await
+    .map_err(RdpError::ConnectionError)?;
+
+    let mut last_error = io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("{} did not resolve to any address", server_addr),
+    );
+    for addr in addrs {
+        match with_timeout(TcpStream::connect(addr), timeouts.tcp_connect, "TCP connection").await {
+            Ok(stream) => return Ok((stream, addr)),
+            Err(e) => {
+                warn!("Failed to connect to {}: {}", addr, e);
+                last_error = e;
+            }
+        }
+    }
+
+    Err(RdpError::ConnectionError(last_error))
+}
+
+/// Leaves the stream as is, the server selecting Standard RDP Security encrypting the PDUs
+/// from the Client Info PDU on.
+async fn keep_stream<S>(stream: S) -> Result<UpgradedStream<S>, RdpError> {
+    Ok(UpgradedStream {
+        stream,
+        server_public_key: Vec::new(),
+        server_certificate: None,
+    })
+}
+
+/// Upgrades the stream to TLS, the certificate of the server being verified as set by `verification`,
+/// against the `server_name` host name.
+pub async fn establish_tls<S>(
+    stream: S,
+    server_name: &str,
+    verification: &TlsVerification,
+    handshake_timeout: Duration,
+) -> Result<UpgradedStream<TlsStream<S>>, RdpError>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    match tokio::time::timeout(handshake_timeout, upgrade_to_tls(stream, server_name, verification)).await {
+        Ok(result) => result,
+        Err(_) => Err(RdpError::ConnectionError(timed_out("TLS handshake"))),
+    }
+}
+
+async fn upgrade_to_tls<S>(
+    stream: S,
+    server_name: &str,
+    verification: &TlsVerification,
+) -> Result<UpgradedStream<TlsStream<S>>, RdpError>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    use tokio::io::AsyncWriteExt as _;
+
+    #[cfg(all(feature = "native-tls", not(feature = "rustls")))]
+    let mut tls_stream = {
+        let connector = async_native_tls::TlsConnector::new();
+        let (connector, domain) = match verification {
+            TlsVerification::SystemRoots => (connector, server_name),
+            TlsVerification::RootStore(root_certificates) => {
+                let connector = root_certificates
+                    .iter()
+                    .try_fold(connector, |connector, root_certificate| {
+                        native_tls::Certificate::from_der(root_certificate)
+                            .map(|root_certificate| connector.add_root_certificate(root_certificate))
+                    })
+                    .map_err(RdpError::TlsConnectorError)?;
+
+                (connector, server_name)
+            }
+            // domain is an empty string because client accepts IP address in the cli,
+            // the certificate being verified once the handshake is complete
+            TlsVerification::PinnedPublicKey(_) | TlsVerification::Skip => {
+                (connector.danger_accept_invalid_certs(true).use_sni(false), "")
+            }
+        };
+
+        match connector.connect(domain, stream).await {
+            Ok(tls) => tls,
+            Err(err) => return Err(RdpError::TlsHandshakeError(err)),
+        }
+    };
+
+    #[cfg(feature = "rustls")]
+    let mut tls_stream = {
+        // the certificate is verified once the handshake is complete, for the rejected one to be returned
+        let mut client_config = rustls::client::ClientConfig::builder()
+            .with_safe_defaults()
+            .with_custom_certificate_verifier(std::sync::Arc::new(danger::NoCertificateVerification))
+            .with_no_client_auth();
+        // This adds support for the SSLKEYLOGFILE env variable (https://wiki.wireshark.org/TLS#using-the-pre-master-secret)
+        client_config.key_log = std::sync::Arc::new(rustls::KeyLogFile::new());
+        let rc_config = std::sync::Arc::new(client_config);
+        // IP addresses are not sent as server names
+        let server_name = rustls::ServerName::try_from(server_name)
+            .or_else(|_| rustls::ServerName::try_from("stub_string"))
+            .unwrap();
+        let connector = tokio_rustls::TlsConnector::from(rc_config);
+        connector.connect(server_name, stream).await?
+    };
+
+    tls_stream.flush().await?;
+    let tls_stream = tls_stream.compat();
+
+    let certificates = peer_certificates(&tls_stream)?;
+    let server_certificate = ServerCertificate::from_der(&certificates[0])?;
+
+    #[cfg(feature = "rustls")]
+    let chain_verification = verify_certificate_chain(verification, &certificates, server_name);
+    // the chain has been verified during the handshake
+    #[cfg(all(feature = "native-tls", not(feature = "rustls")))]
+    let chain_verification = Ok(());
+
+    let verification_result = chain_verification.and_then(|()| match verification {
+        TlsVerification::PinnedPublicKey(public_key) if *public_key != server_certificate.public_key => {
+            Err(String::from("the public key is not the pinned one"))
+        }
+        _ => Ok(()),
+    });
+    if let Err(reason) = verification_result {
+        return Err(RdpError::UntrustedServerCertificate {
+            certificate: server_certificate,
+            reason,
+        });
+    }
+
+    Ok(UpgradedStream::new(tls_stream, server_certificate))
+}
+
+/// Returns the DER encoded certificates presented by the server, the end-entity one first,
+/// native-tls only giving access to the end-entity certificate.
+#[cfg(feature = "rustls")]
+pub fn peer_certificates<S>(tls_stream: &TlsStream<S>) -> Result<Vec<Vec<u8>>, RdpError>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let certificates = tls_stream
+        .get_ref()
+        .get_ref()
+        .1
+        .peer_certificates()
+        .filter(|certificates| !certificates.is_empty())
+        .ok_or(RdpError::MissingPeerCertificate)?;
+
+    Ok(certificates
+        .iter()
+        .map(|certificate| certificate.as_ref().to_vec())
+        .collect())
+}
+
+/// Returns the DER encoded certificates presented by the server, the end-entity one first,
+/// native-tls only giving access to the end-entity certificate.
+#[cfg(all(feature = "native-tls", not(feature = "rustls")))]
+pub fn peer_certificates<S>(tls_stream: &TlsStream<S>) -> Result<Vec<Vec<u8>>, RdpError>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let certificate = tls_stream
+        .get_ref()
+        .peer_certificate()
+        .map_err(RdpError::TlsConnectorError)?
+        .ok_or(RdpError::MissingPeerCertificate)?;
+
+    Ok(vec![certificate.to_der().map_err(RdpError::DerEncode)?])
+}
+
+#[cfg(feature = "rustls")]
+fn verify_certificate_chain(
+    verification: &TlsVerification,
+    certificates: &[Vec<u8>],
+    server_name: &str,
+) -> Result<(), String> {
+    use rustls::client::ServerCertVerifier as _;
+
+    let mut root_store = rustls::RootCertStore::empty();
+    match verification {
+        TlsVerification::SystemRoots => {
+            let root_certificates = rustls_native_certs::load_native_certs()
+                .map_err(|e| format!("failed to load the root certificates of the system: {}", e))?
+                .into_iter()
+                .map(|root_certificate| root_certificate.0)
+                .collect::<Vec<_>>();
+            root_store.add_parsable_certificates(&root_certificates);
+        }
+        TlsVerification::RootStore(root_certificates) => {
+            root_store.add_parsable_certificates(root_certificates);
+        }
+        TlsVerification::PinnedPublicKey(_) | TlsVerification::Skip => return Ok(()),
+    }
+
+    let server_name = rustls::ServerName::try_from(server_name)
+        .map_err(|e| format!("cannot verify the server name {}: {}", server_name, e))?;
+    let certificates = certificates
+        .iter()
+        .cloned()
+        .map(rustls::Certificate)
+        .collect::<Vec<_>>();
+    let (end_entity, intermediates) = certificates
+        .split_first()
+        .ok_or_else(|| String::from("no certificate has been presented"))?;
+
+    rustls::client::WebPkiVerifier::new(root_store, None)
+        .verify_server_cert(
+            end_entity,
+            intermediates,
+            &server_name,
+            &mut std::iter::empty(),
+            &[],
+            std::time::SystemTime::now(),
+        )
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+/// Returns the host to reconnect to, `None` if the client is to reconnect to the same server
+/// with the routing token, e.g. when the server is behind a load balancer.
+fn redirection_target(redirection: &ServerRedirectionPdu) -> Option<&str> {
+    if redirection.flags.contains(ServerRedirectionFlags::NO_REDIRECT) {
+        return None;
+    }
+
+    redirection
+        .target_fqdn
+        .as_deref()
+        .or(redirection.target_net_address.as_deref())
+        .or_else(|| redirection.target_net_addresses.as_ref()?.first().map(String::as_str))
+}
+
+/// The port of the `<host>:<port>` server address.
+fn server_port(server_addr: &str) -> Result<u16, RdpError> {
+    server_addr
+        .rsplit_once(':')
+        .and_then(|(_, port)| port.parse().ok())
+        .ok_or_else(|| {
+            RdpError::ConnectionError(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} does not match the format: <host>:<port>", server_addr),
+            ))
+        })
+}
+
+fn format_server_addr(host: &str, port: u16) -> String {
+    if host.contains(':') {
+        format!("[{}]:{}", host, port)
+    } else {
+        format!("{}:{}", host, port)
+    }
+}
+
+/// The host of the `<host>:<port>` server address, the brackets of an IPv6 address being removed.
+fn host_name(server_addr: &str) -> &str {
+    let host = server_addr.rsplit_once(':').map_or(server_addr, |(host, _)| host);
+
+    host.trim_start_matches('[').trim_end_matches(']')
+}
+
+async fn with_timeout<T>(
+    future: impl Future<Output = io::Result<T>>,
+    timeout: Duration,
+    operation: &str,
+) -> io::Result<T> {
+    tokio::time::timeout(timeout, future)
+        .await
+        .unwrap_or_else(|_| Err(timed_out(operation)))
+}
+
+fn timed_out(operation: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::TimedOut, format!("{} timed out", operation))
+}
+
+#[cfg(feature = "rustls")]
+mod danger {
+    use std::time::SystemTime;
+
+    use rustls::client::ServerCertVerified;
+    use rustls::{Certificate, Error, ServerName};
+
+    pub struct NoCertificateVerification;
+
+    impl rustls::client::ServerCertVerifier for NoCertificateVerification {
+        fn verify_server_cert(
+            &self,
+            _end_entity: &Certificate,
+            _intermediates: &[Certificate],
+            _server_name: &ServerName,
+            _scts: &mut dyn Iterator<Item = &[u8]>,
+            _ocsp_response: &[u8],
+            _now: SystemTime,
+        ) -> Result<ServerCertVerified, Error> {
+            Ok(rustls::client::ServerCertVerified::assertion())
+        }
+    }
+}
@@ -0,0 +1,207 @@
+//! Client side of the Network Level Authentication (CredSSP), as a state machine driven by the caller,
+//! which does not depend on any I/O and can be used by non-tokio and WASM clients.
+
+#[cfg(test)]
+mod tests;
+
+use std::fmt;
+
+use ironrdp::nego;
+use sspi::internal::credssp;
+use sspi::NegotiateConfig;
+use zeroize::Zeroizing;
+
+use crate::RdpError;
+
+/// Number of bytes needed by [`CredsspClient::next_message_length`] to read the length of a TSRequest.
+pub const TS_REQUEST_LENGTH_PREFIX_SIZE: usize = 4;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum CredsspState {
+    Initial,
+    WaitingForTsRequest,
+    WaitingForEarlyUserAuthResult,
+    Finished,
+}
+
+/// The messages are overwritten with zeros when dropped, the last one carrying the encrypted credentials.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CredsspOutput {
+    /// The message to send to the server, whose reply is to be passed to the next step.
+    ReplyNeeded(Zeroizing<Vec<u8>>),
+    /// The last message to send to the server. With HYBRID_EX, the Early User Authorization Result
+    /// sent by the server is then passed to the next step, otherwise the authentication is finished.
+    FinalMessage(Zeroizing<Vec<u8>>),
+    /// The server has granted access via the Early User Authorization Result.
+    Finished,
+}
+
+/// Reason of the rejection of the credentials by the server, for the interactive clients
+/// to prompt the user accordingly before retrying the authentication.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AuthenticationFailure {
+    /// The user name or the password is wrong.
+    LogonFailure,
+    /// The password has expired or has to be changed at the next logon, which is not possible over NLA.
+    PasswordExpired,
+    /// The account is not allowed to log on, e.g. at this time, from this workstation or with a blank password.
+    AccountRestriction,
+    AccountLockedOut,
+    /// The account has been disabled or has expired.
+    AccountDisabled,
+    /// The server has denied access via the Early User Authorization Result of HYBRID_EX,
+    /// typically because the user is not allowed to log on remotely.
+    AccessDenied,
+    /// Any other NTSTATUS code sent by the server.
+    Other(u32),
+}
+
+impl AuthenticationFailure {
+    /// Maps the NTSTATUS code sent in the `errorCode` field of the TSRequest.
+    pub fn from_status(status: u32) -> Self {
+        match status {
+            0xC000_006D | 0xC000_006A | 0xC000_0064 => Self::LogonFailure,
+            0xC000_0071 | 0xC000_0224 => Self::PasswordExpired,
+            0xC000_006E | 0xC000_006F | 0xC000_0070 | 0xC000_015B => Self::AccountRestriction,
+            0xC000_0234 => Self::AccountLockedOut,
+            0xC000_0072 | 0xC000_0193 => Self::AccountDisabled,
+            status => Self::Other(status),
+        }
+    }
+
+    /// Whether other credentials may be accepted, as opposed to failures of the account itself
+    /// which have to be solved by an administrator.
+    pub fn is_credentials_error(&self) -> bool {
+        matches!(self, Self::LogonFailure | Self::PasswordExpired)
+    }
+}
+
+impl fmt::Display for AuthenticationFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::LogonFailure => write!(f, "the user name or the password is incorrect"),
+            Self::PasswordExpired => write!(f, "the password has expired"),
+            Self::AccountRestriction => write!(f, "the account is not allowed to log on"),
+            Self::AccountLockedOut => write!(f, "the account is locked out"),
+            Self::AccountDisabled => write!(f, "the account is disabled"),
+            Self::AccessDenied => write!(f, "the server denied access via the Early User Authorization Result"),
+            Self::Other(status) => write!(f, "the server returned the NTSTATUS {:#010x}", status),
+        }
+    }
+}
+
+/// Goes through the TSRequest exchange with [`CredsspClient::step`], passing an empty input to the first step,
+/// then the messages received from the server, framed with [`CredsspClient::next_message_length`].
+pub struct CredsspClient {
+    client: credssp::CredSspClient,
+    state: CredsspState,
+    selected_protocol: nego::SecurityProtocol,
+}
+
+impl CredsspClient {
+    /// Creates the state machine for HYBRID or HYBRID_EX, `service_principal_name` being `TERMSRV/<host name>`.
+    /// The credentials are kept by the CredSSP client of `sspi` until the state machine is dropped,
+    /// without being zeroized.
+    pub fn new(
+        server_public_key: Vec<u8>,
+        credentials: sspi::AuthIdentity,
+        service_principal_name: String,
+        selected_protocol: nego::SecurityProtocol,
+    ) -> Result<Self, RdpError> {
+        let client = credssp::CredSspClient::new(
+            server_public_key,
+            credentials,
+            credssp::CredSspMode::WithCredentials,
+            credssp::ClientMode::Negotiate(NegotiateConfig::default()),
+            service_principal_name,
+        )
+        .map_err(RdpError::CredSspError)?;
+
+        Ok(Self {
+            client,
+            state: CredsspState::Initial,
+            selected_protocol,
+        })
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.state == CredsspState::Finished
+    }
+
+    /// Returns the length of the next message expected from the server, given the first received bytes,
+    /// or `None` if more bytes are needed to tell ([`TS_REQUEST_LENGTH_PREFIX_SIZE`] for a TSRequest).
+    pub fn next_message_length(&self, received: &[u8]) -> Result<Option<usize>, RdpError> {
+        match self.state {
+            CredsspState::WaitingForTsRequest if received.len() < TS_REQUEST_LENGTH_PREFIX_SIZE => Ok(None),
+            CredsspState::WaitingForTsRequest => Ok(Some(credssp::TsRequest::read_length(
+                &received[..TS_REQUEST_LENGTH_PREFIX_SIZE],
+            )?)),
+            CredsspState::WaitingForEarlyUserAuthResult => Ok(Some(credssp::EARLY_USER_AUTH_RESULT_PDU_SIZE)),
+            CredsspState::Initial | CredsspState::Finished => Ok(Some(0)),
+        }
+    }
+
+    /// Processes the message received from the server, which is empty on the first step.
+    pub fn step(&mut self, input: &[u8]) -> Result<CredsspOutput, RdpError> {
+        let ts_request = match self.state {
+            CredsspState::Initial => credssp::TsRequest::default(),
+            CredsspState::WaitingForTsRequest => {
+                let ts_request = credssp::TsRequest::from_buffer(input).map_err(RdpError::TsRequestError)?;
+                debug!("Got CredSSP TSRequest: {:x?}", ts_request);
+
+                if let Some(status) = ts_request.error_code.filter(|status| *status != 0) {
+                    self.state = CredsspState::Finished;
+
+                    return Err(RdpError::AuthenticationFailed(AuthenticationFailure::from_status(
+                        status,
+                    )));
+                }
+
+                ts_request
+            }
+            CredsspState::WaitingForEarlyUserAuthResult => {
+                let early_user_auth_result =
+                    credssp::EarlyUserAuthResult::from_buffer(input).map_err(RdpError::EarlyUserAuthResultError)?;
+                if let credssp::EarlyUserAuthResult::AccessDenied = early_user_auth_result {
+                    return Err(RdpError::AuthenticationFailed(AuthenticationFailure::AccessDenied));
+                }
+                self.state = CredsspState::Finished;
+
+                return Ok(CredsspOutput::Finished);
+            }
+            CredsspState::Finished => {
+                return Err(RdpError::UnexpectedPdu(
+                    "CredSSP message received after the authentication has finished".to_string(),
+                ))
+            }
+        };
+
+        match self.client.process(ts_request).map_err(RdpError::CredSspError)? {
+            credssp::ClientState::ReplyNeeded(ts_request) => {
+                debug!("Send CredSSP TSRequest (reply needed): {:x?}", ts_request);
+                self.state = CredsspState::WaitingForTsRequest;
+
+                Ok(CredsspOutput::ReplyNeeded(encode_ts_request(&ts_request)?))
+            }
+            credssp::ClientState::FinalMessage(ts_request) => {
+                debug!("Send CredSSP TSRequest (final): {:x?}", ts_request);
+                self.state = if self.selected_protocol.contains(nego::SecurityProtocol::HYBRID_EX) {
+                    CredsspState::WaitingForEarlyUserAuthResult
+                } else {
+                    CredsspState::Finished
+                };
+
+                Ok(CredsspOutput::FinalMessage(encode_ts_request(&ts_request)?))
+            }
+        }
+    }
+}
+
+fn encode_ts_request(ts_request: &credssp::TsRequest) -> Result<Zeroizing<Vec<u8>>, RdpError> {
+    let mut buf = Zeroizing::new(vec![0x00; ts_request.buffer_len() as usize]);
+    ts_request
+        .encode_ts_request(buf.as_mut_slice())
+        .map_err(RdpError::TsRequestError)?;
+
+    Ok(buf)
+}
@@ -0,0 +1,51 @@
+use super::*;
+
+#[test]
+fn password_expiration_statuses_are_mapped_to_password_expired() {
+    assert_eq!(
+        AuthenticationFailure::PasswordExpired,
+        AuthenticationFailure::from_status(0xC000_0071)
+    );
+    assert_eq!(
+        AuthenticationFailure::PasswordExpired,
+        AuthenticationFailure::from_status(0xC000_0224)
+    );
+    assert!(AuthenticationFailure::PasswordExpired.is_credentials_error());
+}
+
+#[test]
+fn unknown_status_is_kept() {
+    let failure = AuthenticationFailure::from_status(0xC000_00BB);
+
+    assert_eq!(AuthenticationFailure::Other(0xC000_00BB), failure);
+    assert!(!failure.is_credentials_error());
+}
+
+#[test]
+fn ts_request_error_code_fails_with_authentication_failure() {
+    let mut client = CredsspClient::new(
+        vec![0x00; 32],
+        sspi::AuthIdentity {
+            username: String::from("user"),
+            password: String::from("password"),
+            domain: None,
+        },
+        String::from("TERMSRV/server"),
+        nego::SecurityProtocol::HYBRID_EX,
+    )
+    .unwrap();
+    assert!(matches!(client.step(&[]).unwrap(), CredsspOutput::ReplyNeeded(_)));
+
+    let ts_request = credssp::TsRequest {
+        error_code: Some(0xC000_006E),
+        ..credssp::TsRequest::default()
+    };
+
+    assert!(matches!(
+        client.step(&encode_ts_request(&ts_request).unwrap()),
+        Err(RdpError::AuthenticationFailed(
+            AuthenticationFailure::AccountRestriction
+        ))
+    ));
+    assert!(client.is_finished());
+}
@@ -0,0 +1,189 @@
+//! Diagnostics bundle of a connection, for the users to attach to their reports of interoperability problems.
+
+#[cfg(test)]
+mod tests;
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use ironrdp::rdp::capability_sets::CapabilitySet;
+
+use crate::RdpError;
+
+/// The number of PDUs to be kept by a [`DiagnosticsRecorder`], enough to span the connection sequence.
+pub const DEFAULT_MAX_PDUS: usize = 64;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PduDirection {
+    Sent,
+    Received,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PduRecord {
+    pub direction: PduDirection,
+    pub name: String,
+}
+
+/// What has been recorded of the connection by a [`DiagnosticsRecorder`].
+///
+/// The secrets are never recorded: the PDUs carrying credentials, such as the Client Info PDU, are only recorded
+/// by their name, and the user name of the negotiation cookie is left out.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConnectionDiagnostics {
+    /// The steps of the negotiation and of the connection sequence, in their order.
+    pub transcript: Vec<String>,
+    pub phase_timings: Vec<(&'static str, Duration)>,
+    pub client_capability_sets: Vec<String>,
+    pub server_capability_sets: Vec<String>,
+    /// The last PDUs sent and received, the oldest first.
+    pub last_pdus: VecDeque<PduRecord>,
+    /// The error the connection has failed with, prefixed by its [`ErrorCode`](crate::ErrorCode).
+    pub error: Option<String>,
+}
+
+impl fmt::Display for ConnectionDiagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "IronRDP connection diagnostics, version {}",
+            env!("CARGO_PKG_VERSION")
+        )?;
+        if let Some(error) = &self.error {
+            writeln!(f, "Error: {}", error)?;
+        }
+
+        writeln!(f, "\n[Transcript]")?;
+        for step in &self.transcript {
+            writeln!(f, "{}", step)?;
+        }
+
+        writeln!(f, "\n[Phase timings]")?;
+        for (phase, duration) in &self.phase_timings {
+            writeln!(f, "{}: {:?}", phase, duration)?;
+        }
+
+        writeln!(f, "\n[Server capability sets]")?;
+        for capability_set in &self.server_capability_sets {
+            writeln!(f, "{}", capability_set)?;
+        }
+
+        writeln!(f, "\n[Client capability sets]")?;
+        for capability_set in &self.client_capability_sets {
+            writeln!(f, "{}", capability_set)?;
+        }
+
+        writeln!(f, "\n[Last PDUs]")?;
+        for pdu in &self.last_pdus {
+            let direction = match pdu.direction {
+                PduDirection::Sent => "->",
+                PduDirection::Received => "<-",
+            };
+            writeln!(f, "{} {}", direction, pdu.name)?;
+        }
+
+        Ok(())
+    }
+}
+
+struct RecorderState {
+    diagnostics: ConnectionDiagnostics,
+    max_pdus: usize,
+}
+
+/// Records the course of the connection, passed in [`InputConfig::diagnostics`](crate::InputConfig::diagnostics),
+/// for the [diagnostics](ConnectionDiagnostics) to be exported once the connection has failed.
+///
+/// The recorder is a handle, the clones kept by the embedder sharing the records of the connection.
+/// It records nothing unless created with [`Self::new`], e.g. when requested by the user.
+#[derive(Clone, Default)]
+pub struct DiagnosticsRecorder {
+    state: Option<Arc<Mutex<RecorderState>>>,
+}
+
+impl fmt::Debug for DiagnosticsRecorder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DiagnosticsRecorder")
+            .field("enabled", &self.is_enabled())
+            .finish()
+    }
+}
+
+impl DiagnosticsRecorder {
+    /// Creates a recorder keeping the last `max_pdus` PDUs, see [`DEFAULT_MAX_PDUS`].
+    pub fn new(max_pdus: usize) -> Self {
+        Self {
+            state: Some(Arc::new(Mutex::new(RecorderState {
+                diagnostics: ConnectionDiagnostics::default(),
+                max_pdus,
+            }))),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.state.is_some()
+    }
+
+    /// Returns what has been recorded so far, `None` if the recorder is disabled.
+    pub fn snapshot(&self) -> Option<ConnectionDiagnostics> {
+        self.state.as_ref().map(|state| lock(state).diagnostics.clone())
+    }
+
+    /// Adds a step to the transcript, e.g. a step of the embedder such as the reconnection.
+    pub fn record_event(&self, event: impl fmt::Display) {
+        self.with_diagnostics(|diagnostics| diagnostics.transcript.push(event.to_string()));
+    }
+
+    /// Records a PDU by its name, e.g. from the [PDU hooks](crate::ActiveStageProcessor::on_pdu_received)
+    /// of the active session, the oldest PDU being dropped once the maximal number of PDUs is reached.
+    pub fn record_pdu(&self, direction: PduDirection, name: &str) {
+        if let Some(state) = self.state.as_ref() {
+            let mut state = lock(state);
+            if state.max_pdus == 0 {
+                return;
+            }
+            if state.diagnostics.last_pdus.len() == state.max_pdus {
+                state.diagnostics.last_pdus.pop_front();
+            }
+            state.diagnostics.last_pdus.push_back(PduRecord {
+                direction,
+                name: String::from(name),
+            });
+        }
+    }
+
+    pub fn record_error(&self, error: &RdpError) {
+        self.with_diagnostics(|diagnostics| {
+            diagnostics.error = Some(format!("[{}] {}", error.code().as_u32(), error));
+        });
+    }
+
+    pub(crate) fn record_phase(&self, phase: &'static str, duration: Duration) {
+        self.with_diagnostics(|diagnostics| diagnostics.phase_timings.push((phase, duration)));
+    }
+
+    pub(crate) fn record_capability_sets(&self, direction: PduDirection, capability_sets: &[CapabilitySet]) {
+        self.with_diagnostics(|diagnostics| {
+            let capability_sets = capability_sets
+                .iter()
+                .map(|capability_set| format!("{:?}", capability_set));
+            match direction {
+                PduDirection::Sent => diagnostics.client_capability_sets = capability_sets.collect(),
+                PduDirection::Received => diagnostics.server_capability_sets = capability_sets.collect(),
+            }
+        });
+    }
+
+    fn with_diagnostics(&self, f: impl FnOnce(&mut ConnectionDiagnostics)) {
+        if let Some(state) = self.state.as_ref() {
+            f(&mut lock(state).diagnostics);
+        }
+    }
+}
+
+/// A recorder panicking while holding the lock does not corrupt the records, so the poisoning is ignored.
+fn lock(state: &Mutex<RecorderState>) -> MutexGuard<'_, RecorderState> {
+    state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
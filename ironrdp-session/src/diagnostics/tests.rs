@@ -0,0 +1,39 @@
+use super::*;
+
+#[test]
+fn disabled_recorder_records_nothing() {
+    let recorder = DiagnosticsRecorder::default();
+    recorder.record_event("Negotiation started");
+    recorder.record_pdu(PduDirection::Sent, "Client Info");
+
+    assert!(!recorder.is_enabled());
+    assert_eq!(None, recorder.snapshot());
+}
+
+#[test]
+fn recorder_keeps_last_pdus() {
+    let recorder = DiagnosticsRecorder::new(2);
+    recorder.record_pdu(PduDirection::Sent, "Client Info");
+    recorder.record_pdu(PduDirection::Received, "License Error");
+    recorder.record_pdu(PduDirection::Received, "Demand Active");
+
+    let names = recorder
+        .snapshot()
+        .unwrap()
+        .last_pdus
+        .into_iter()
+        .map(|pdu| pdu.name)
+        .collect::<Vec<_>>();
+    assert_eq!(vec!["License Error", "Demand Active"], names);
+}
+
+#[test]
+fn clones_of_recorder_share_records() {
+    let recorder = DiagnosticsRecorder::new(DEFAULT_MAX_PDUS);
+    recorder.clone().record_event("Negotiation started");
+    recorder.record_error(&RdpError::AccessDenied);
+
+    let diagnostics = recorder.snapshot().unwrap();
+    assert_eq!(vec![String::from("Negotiation started")], diagnostics.transcript);
+    assert!(diagnostics.error.unwrap().starts_with("[2003] "));
+}
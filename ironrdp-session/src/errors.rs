@@ -1,115 +1,498 @@
-use std::io;
+#[cfg(test)]
+mod tests;
+
+use std::{fmt, io};
 
-use failure::Fail;
 use ironrdp::{
-    codecs,
+    bitmap, codecs,
     dvc::{display, gfx},
     fast_path::FastPathError,
+    gcc,
+    input::InputEventError,
     nego,
-    rdp::{self, server_license::ServerLicenseError},
+    rdp::{self, server_license::ServerLicenseError, standard_security::StandardSecurityError},
+    rdstls::RdstlsError,
     McsError,
 };
+use thiserror::Error;
+
+use crate::PduChannel;
 
-#[derive(Debug, Fail)]
+#[derive(Debug, Error)]
 pub enum RdpError {
-    #[fail(display = "IO error: {}", _0)]
-    IOError(#[fail(cause)] io::Error),
-    #[fail(display = "connection error: {}", _0)]
-    ConnectionError(#[fail(cause)] io::Error),
-    #[fail(display = "X.224 error: {}", _0)]
-    X224Error(#[fail(cause)] io::Error),
-    #[fail(display = "negotiation error: {}", _0)]
-    NegotiationError(#[fail(cause)] nego::NegotiationError),
-    #[fail(display = "unexpected PDU: {}", _0)]
+    #[error("IO error: {}", .0)]
+    IOError(#[source] io::Error),
+    #[error("connection error: {}", .0)]
+    ConnectionError(#[source] io::Error),
+    #[error("X.224 error: {}", .0)]
+    X224Error(#[source] io::Error),
+    #[error("negotiation error: {}", .0)]
+    NegotiationError(#[source] nego::NegotiationError),
+    #[error("unexpected PDU: {}", .0)]
     UnexpectedPdu(String),
-    #[fail(display = "Unexpected disconnection: {}", _0)]
+    #[error("Unexpected disconnection: {}", .0)]
     UnexpectedDisconnection(String),
-    #[fail(display = "invalid response: {}", _0)]
+    #[error("invalid response: {}", .0)]
     InvalidResponse(String),
     #[cfg(all(feature = "native-tls", not(feature = "rustls")))]
-    #[fail(display = "TLS connector error: {}", _0)]
+    #[error("TLS connector error: {}", .0)]
     TlsConnectorError(native_tls::Error),
     #[cfg(all(feature = "native-tls", not(feature = "rustls")))]
-    #[fail(display = "TLS handshake error: {}", _0)]
+    #[error("TLS handshake error: {}", .0)]
     TlsHandshakeError(native_tls::Error),
     #[cfg(feature = "rustls")]
-    #[fail(display = "TLS connector error: {}", _0)]
+    #[error("TLS connector error: {}", .0)]
     TlsConnectorError(rustls::Error),
     #[cfg(feature = "rustls")]
-    #[fail(display = "TLS handshake error: {}", _0)]
+    #[error("TLS handshake error: {}", .0)]
     TlsHandshakeError(rustls::Error),
-    #[fail(display = "CredSSP error: {}", _0)]
-    CredSspError(#[fail(cause)] sspi::Error),
-    #[fail(display = "CredSSP TSRequest error: {}", _0)]
-    TsRequestError(#[fail(cause)] io::Error),
-    #[fail(display = "early User Authentication Result error: {}", _0)]
-    EarlyUserAuthResultError(#[fail(cause)] io::Error),
-    #[fail(display = "the server denied access via Early User Authentication Result")]
+    #[error("CredSSP error: {}", .0)]
+    CredSspError(#[source] sspi::Error),
+    #[error("CredSSP TSRequest error: {}", .0)]
+    TsRequestError(#[source] io::Error),
+    #[error("early User Authentication Result error: {}", .0)]
+    EarlyUserAuthResultError(#[source] io::Error),
+    #[error("the server denied access via Early User Authentication Result")]
     AccessDenied,
-    #[fail(display = "MCS Connect error: {}", _0)]
-    McsConnectError(#[fail(cause)] McsError),
-    #[fail(display = "failed to get info about the user: {}", _0)]
+    #[error("authentication failed: {}", .0)]
+    AuthenticationFailed(crate::AuthenticationFailure),
+    #[error("RDSTLS error: {}", .0)]
+    RdstlsError(#[source] RdstlsError),
+    #[error("the server rejected the RDSTLS authentication with the result code {:#x}", .0)]
+    RdstlsAuthenticationFailed(u32),
+    #[error("the server selected RDSTLS, which requires the redirection credentials")]
+    MissingRedirectionCredentials,
+    #[error("RD Gateway error: {}", .0)]
+    GatewayError(String),
+    #[error("the RD Gateway rejected the WebSocket upgrade with the HTTP status {}", .0)]
+    GatewayHttpError(u16),
+    #[error("the RD Gateway rejected the {} request with the error {:#010x}", .request, .error_code)]
+    GatewayRequestRejected { request: &'static str, error_code: u32 },
+    #[error("the server requires {:?} encryption level with {:?} encryption method, which is not supported", .0, .1)]
+    EncryptionNotSupported(gcc::EncryptionLevel, gcc::EncryptionMethod),
+    #[error("the client has requested the {:?} security protocols, none of which is accepted by the server", .0)]
+    UnsupportedSecurityProtocol(nego::SecurityProtocol),
+    #[error("Standard RDP Security error: {}", .0)]
+    StandardSecurityError(#[source] StandardSecurityError),
+    #[error("MCS Connect error: {}", .0)]
+    McsConnectError(#[source] McsError),
+    #[error("failed to get info about the user: {}", .0)]
     UserInfoError(String),
-    #[fail(display = "MCS error: {}", _0)]
+    #[error("invalid client metadata: {}", .0)]
+    InvalidClientMetadata(String),
+    #[error("invalid monitor layout: {}", .0)]
+    InvalidMonitorLayout(String),
+    #[error("MCS error: {}", .0)]
     McsError(McsError),
-    #[fail(display = "Client Info PDU error: {}", _0)]
+    #[error("Client Info PDU error: {}", .0)]
     ClientInfoError(rdp::RdpError),
-    #[fail(display = "Server License PDU error: {}", _0)]
+    #[error("Server License PDU error: {}", .0)]
     ServerLicenseError(rdp::RdpError),
-    #[fail(display = "Share Control Header error: {}", _0)]
+    #[error("Share Control Header error: {}", .0)]
     ShareControlHeaderError(rdp::RdpError),
-    #[fail(display = "capability sets error: {}", _0)]
+    #[error("capability sets error: {}", .0)]
     CapabilitySetsError(rdp::RdpError),
-    #[fail(display = "Virtual channel error: {}", _0)]
+    #[error("Virtual channel error: {}", .0)]
     VirtualChannelError(rdp::vc::ChannelError),
-    #[fail(display = "Invalid channel id error: {}", _0)]
+    #[error("Invalid channel name: {}", .0)]
+    InvalidChannelName(#[source] rdp::vc::ChannelNameError),
+    #[error("Invalid channel id error: {}", .0)]
     InvalidChannelIdError(String),
-    #[fail(display = "Graphics pipeline protocol error: {}", _0)]
+    #[error("Graphics pipeline protocol error: {}", .0)]
     GraphicsPipelineError(gfx::GraphicsPipelineError),
-    #[fail(display = "Display pipeline protocol error: {}", _0)]
+    #[error("Display pipeline protocol error: {}", .0)]
     DisplayPipelineError(display::DisplayPipelineError),
-    #[fail(display = "ZGFX error: {}", _0)]
-    ZgfxError(#[fail(cause)] gfx::zgfx::ZgfxError),
-    #[fail(display = "Fast-Path error: {}", _0)]
-    FastPathError(#[fail(cause)] FastPathError),
-    #[fail(display = "RDP error: {}", _0)]
-    RdpError(#[fail(cause)] ironrdp::RdpError),
-    #[fail(display = "access to the non-existing channel: {}", _0)]
+    #[error("Audio output channel error: {}", .0)]
+    RdpsndError(#[source] rdp::vc::rdpsnd::RdpsndError),
+    #[error("Device redirection channel error: {}", .0)]
+    RdpdrError(#[source] rdp::vc::rdpdr::RdpdrError),
+    #[cfg(feature = "zgfx")]
+    #[error("ZGFX error: {}", .0)]
+    ZgfxError(#[source] gfx::zgfx::ZgfxError),
+    #[error("Fast-Path error: {}", .0)]
+    FastPathError(#[source] FastPathError),
+    #[error("Bulk decompression error: {}", .0)]
+    BulkDecompressionError(#[source] rdp::bulk::BulkError),
+    #[error("RDP error: {}", .0)]
+    RdpError(#[source] ironrdp::RdpError),
+    #[error("input event error: {}", .0)]
+    InputEventError(#[source] InputEventError),
+    #[error("access to the non-existing channel: {}", .0)]
     AccessToNonExistingChannel(u32),
-    #[fail(display = "access to the non-existing channel name: {}", _0)]
-    AccessToNonExistingChannelName(String),
-    #[fail(display = "data in unexpected channel: {}", _0)]
-    UnexpectedChannel(u16),
-    #[fail(display = "unexpected Surface Command codec ID: {}", _0)]
+    #[error("access to the non-existing channel name: {}", .0)]
+    AccessToNonExistingChannelName(rdp::vc::DvcName),
+    #[error("data in unexpected channel: {}", .0)]
+    UnexpectedChannel(ironrdp::ChannelId),
+    #[error("compressed data in channel: {}", .0)]
+    CompressedChannelData(ironrdp::ChannelId),
+    #[error("unexpected Surface Command codec ID: {}", .0)]
     UnexpectedCodecId(u8),
-    #[fail(display = "RDP error: {}", _0)]
-    RfxError(#[fail(cause)] codecs::rfx::RfxError),
-    #[fail(display = "absence of mandatory Fast-Path header")]
+    #[error("{} codec is not compiled in", .0)]
+    CodecNotCompiledIn(crate::Codec),
+    #[error("H.264 decoding error: {}", .0)]
+    H264DecodingError(String),
+    #[error("RDP error: {}", .0)]
+    RfxError(#[source] codecs::rfx::RfxError),
+    #[error("absence of mandatory Fast-Path header")]
     MandatoryHeaderIsAbsent,
-    #[fail(display = "RLGR error: {}", _0)]
-    RlgrError(#[fail(cause)] codecs::rfx::rlgr::RlgrError),
-    #[fail(display = "absence of RFX channels")]
+    #[cfg(any(feature = "rfx", feature = "zgfx"))]
+    #[error("RLGR error: {}", .0)]
+    RlgrError(#[source] codecs::rfx::rlgr::RlgrError),
+    #[error("absence of RFX channels")]
     NoRfxChannelsAnnounced,
-    #[fail(
-        display = "the server that started working using the inconsistent protocol: {:?}",
-        _0
-    )]
+    #[cfg(any(feature = "bitmap", feature = "zgfx"))]
+    #[error("planar codec error: {}", .0)]
+    PlanarError(#[source] codecs::planar::PlanarError),
+    #[cfg(any(feature = "nscodec", feature = "zgfx"))]
+    #[error("NSCodec error: {}", .0)]
+    NsCodecError(#[source] codecs::nscodec::NsCodecError),
+    #[cfg(feature = "zgfx")]
+    #[error("RemoteFX Progressive error: {}", .0)]
+    ProgressiveError(#[source] codecs::progressive::ProgressiveError),
+    #[error("interleaved RLE error: {}", .0)]
+    RleError(#[source] bitmap::rle::RleError),
+    #[error("unsupported bitmap of {} bpp (compressed: {})", .bits_per_pixel, .compressed)]
+    UnsupportedBitmap { bits_per_pixel: u16, compressed: bool },
+    #[error("the server that started working using the inconsistent protocol: {:?}", .0)]
     UnexpectedFastPathUpdate(ironrdp::fast_path::UpdateCode),
-    #[fail(display = "server error: {}", _0)]
+    #[error("server error: {}", .0)]
     ServerError(String),
-    #[fail(display = "Missing peer certificate")]
+    #[error("Missing peer certificate")]
     MissingPeerCertificate,
-    #[fail(display = "Dynamic virtual channel not connected")]
+    #[error("invalid server certificate: {}", .0)]
+    InvalidServerCertificate(String),
+    /// The certificate has been rejected by the [`TlsVerification`](crate::connector::TlsVerification),
+    /// e.g. for the user to be asked whether to trust it anyway.
+    #[error("untrusted server certificate: {}", .reason)]
+    UntrustedServerCertificate {
+        certificate: crate::ServerCertificate,
+        reason: String,
+    },
+    /// The server has sent a Server Redirection PDU instead of the Server Demand Active PDU, for the client
+    /// to reconnect to the target with [`InputConfig::apply_redirection`](crate::InputConfig::apply_redirection).
+    #[error("the server has redirected the connection")]
+    ServerRedirection(Box<rdp::ServerRedirectionPdu>),
+    #[error("Dynamic virtual channel not connected")]
     DynamicVirtualChannelNotConnected,
-    #[fail(display = "Static global channel not connected")]
+    #[error("Static global channel not connected")]
     StaticChannelNotConnected,
-    #[fail(display = "Invalid Capabilities mask provided. Mask: {:X}", _0)]
+    #[error("Invalid Capabilities mask provided. Mask: {:X}", .0)]
     InvalidCapabilitiesMask(u32),
-    #[fail(display = "Stream terminated while waiting for some data")]
+    #[error("Stream terminated while waiting for some data")]
     UnexpectedStreamTermination,
+    /// The deadline of the [`ReadCancellation`](crate::ReadCancellation) has elapsed before the server sent a frame.
+    #[error("timed out while waiting for the server")]
+    ReadTimedOut,
+    /// The [`ShutdownSignal`](crate::ShutdownSignal) has been triggered while waiting for the server.
+    #[error("read cancelled by the shutdown signal")]
+    ReadCancelled,
+    /// An error of the active stage, with the context of the frame of the server it has occurred in.
+    #[error("{} ({})", .source, .context)]
+    InPdu {
+        context: Box<PduContext>,
+        #[source]
+        source: Box<RdpError>,
+    },
+    /// The server has exceeded one of the [`LimitsConfig`](ironrdp::LimitsConfig) budgets.
+    #[error("limit exceeded: {}", .0)]
+    LimitExceeded(String),
     #[cfg(all(feature = "native-tls", not(feature = "rustls")))]
-    #[fail(display = "Invalid DER structure: {}", _0)]
-    DerEncode(#[fail(cause)] native_tls::Error),
+    #[error("Invalid DER structure: {}", .0)]
+    DerEncode(#[source] native_tls::Error),
+}
+
+/// Where an error of the active stage has occurred in the data received from the server,
+/// see [`RdpError::pdu_context`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PduContext {
+    /// The offset of the frame in the bytes received since the start of the active stage.
+    pub offset: u64,
+    /// The channel of the frame, `None` if its header could not be decoded.
+    pub channel: Option<PduChannel>,
+    /// The short name of the last PDU decoded from the frame, `None` if none could be decoded.
+    pub pdu: Option<String>,
+}
+
+impl fmt::Display for PduContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "frame at offset {}", self.offset)?;
+        if let Some(channel) = self.channel {
+            write!(f, " on the {:?} channel", channel)?;
+        }
+        if let Some(pdu) = &self.pdu {
+            write!(f, " after the {}", pdu)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Stable numeric code of an [`RdpError`], for the embedders to localize the error messages from the code
+/// or from its message key instead of parsing the `Display` output, and for the support teams to reference it.
+///
+/// The codes are grouped by thousands: 1xxx for the transport, 2xxx for the security, 3xxx for the RD Gateway,
+/// 4xxx for the connection sequence, 5xxx for the channels, 6xxx for the graphics and 7xxx for the protocol.
+/// A code is never reassigned, new ones being added at the end of their group.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[repr(u32)]
+pub enum ErrorCode {
+    /// Reading from or writing to the connection has failed.
+    Io = 1000,
+    /// The connection to the server could not be established.
+    Connection = 1001,
+    ReadTimedOut = 1002,
+    ReadCancelled = 1003,
+    /// The connection has been closed in the middle of a PDU.
+    StreamTerminated = 1004,
+    DisconnectedByServer = 1005,
+    Tls = 2000,
+    UntrustedServerCertificate = 2001,
+    InvalidServerCertificate = 2002,
+    AccessDenied = 2003,
+    /// The server has rejected the credentials.
+    AuthenticationFailed = 2004,
+    CredSsp = 2005,
+    Rdstls = 2006,
+    MissingRedirectionCredentials = 2007,
+    UnsupportedSecurityProtocol = 2008,
+    UnsupportedEncryption = 2009,
+    Negotiation = 2010,
+    /// The session keys of Standard RDP Security could not be established with the server.
+    StandardSecurity = 2011,
+    Gateway = 3000,
+    GatewayRequestRejected = 3001,
+    /// The server has redirected the connection, which is to be reconnected to the target.
+    ServerRedirection = 4000,
+    ServerLicense = 4001,
+    /// The server has sent an unexpected or invalid PDU during the connection sequence.
+    ConnectionSequence = 4002,
+    /// The configuration of the client is invalid.
+    InvalidConfiguration = 4003,
+    Channel = 5000,
+    /// The graphics sent by the server could not be decoded.
+    Graphics = 6000,
+    CodecNotCompiledIn = 6001,
+    /// The server has sent an invalid PDU during the session.
+    Protocol = 7000,
+    /// The server has exceeded one of the limits of the client, e.g. on the length of the PDUs.
+    LimitExceeded = 7001,
+}
+
+/// The group of an [`ErrorCode`], for the embedders to tell e.g. the I/O failures from the errors of the server.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ErrorCategory {
+    Transport,
+    Security,
+    Gateway,
+    Connection,
+    Channel,
+    Graphics,
+    Protocol,
+}
+
+impl ErrorCode {
+    pub const ALL: [ErrorCode; 29] = [
+        Self::Io,
+        Self::Connection,
+        Self::ReadTimedOut,
+        Self::ReadCancelled,
+        Self::StreamTerminated,
+        Self::DisconnectedByServer,
+        Self::Tls,
+        Self::UntrustedServerCertificate,
+        Self::InvalidServerCertificate,
+        Self::AccessDenied,
+        Self::AuthenticationFailed,
+        Self::CredSsp,
+        Self::Rdstls,
+        Self::MissingRedirectionCredentials,
+        Self::UnsupportedSecurityProtocol,
+        Self::UnsupportedEncryption,
+        Self::Negotiation,
+        Self::StandardSecurity,
+        Self::Gateway,
+        Self::GatewayRequestRejected,
+        Self::ServerRedirection,
+        Self::ServerLicense,
+        Self::ConnectionSequence,
+        Self::InvalidConfiguration,
+        Self::Channel,
+        Self::Graphics,
+        Self::CodecNotCompiledIn,
+        Self::Protocol,
+        Self::LimitExceeded,
+    ];
+
+    pub fn as_u32(self) -> u32 {
+        self as u32
+    }
+
+    pub fn from_u32(code: u32) -> Option<Self> {
+        Self::ALL.into_iter().find(|error_code| error_code.as_u32() == code)
+    }
+
+    pub fn category(self) -> ErrorCategory {
+        match self.as_u32() / 1000 {
+            1 => ErrorCategory::Transport,
+            2 => ErrorCategory::Security,
+            3 => ErrorCategory::Gateway,
+            4 => ErrorCategory::Connection,
+            5 => ErrorCategory::Channel,
+            6 => ErrorCategory::Graphics,
+            _ => ErrorCategory::Protocol,
+        }
+    }
+
+    /// Whether the server has sent a PDU which is invalid or unexpected, or exceeds the limits of the client,
+    /// as opposed to e.g. the failures of the connection and the rejections of the credentials.
+    pub fn is_protocol_violation(self) -> bool {
+        matches!(
+            self,
+            Self::ConnectionSequence | Self::Channel | Self::Graphics | Self::Protocol | Self::LimitExceeded
+        )
+    }
+
+    /// The key of the localized message of the error, e.g. `security.access_denied`.
+    pub fn message_key(self) -> &'static str {
+        match self {
+            Self::Io => "transport.io",
+            Self::Connection => "transport.connection",
+            Self::ReadTimedOut => "transport.read_timed_out",
+            Self::ReadCancelled => "transport.read_cancelled",
+            Self::StreamTerminated => "transport.stream_terminated",
+            Self::DisconnectedByServer => "transport.disconnected_by_server",
+            Self::Tls => "security.tls",
+            Self::UntrustedServerCertificate => "security.untrusted_server_certificate",
+            Self::InvalidServerCertificate => "security.invalid_server_certificate",
+            Self::AccessDenied => "security.access_denied",
+            Self::AuthenticationFailed => "security.authentication_failed",
+            Self::CredSsp => "security.credssp",
+            Self::Rdstls => "security.rdstls",
+            Self::MissingRedirectionCredentials => "security.missing_redirection_credentials",
+            Self::UnsupportedSecurityProtocol => "security.unsupported_security_protocol",
+            Self::UnsupportedEncryption => "security.unsupported_encryption",
+            Self::Negotiation => "security.negotiation",
+            Self::StandardSecurity => "security.standard_security",
+            Self::Gateway => "gateway.error",
+            Self::GatewayRequestRejected => "gateway.request_rejected",
+            Self::ServerRedirection => "connection.server_redirection",
+            Self::ServerLicense => "connection.server_license",
+            Self::ConnectionSequence => "connection.sequence",
+            Self::InvalidConfiguration => "connection.invalid_configuration",
+            Self::Channel => "channel.error",
+            Self::Graphics => "graphics.decoding",
+            Self::CodecNotCompiledIn => "graphics.codec_not_compiled_in",
+            Self::Protocol => "protocol.error",
+            Self::LimitExceeded => "protocol.limit_exceeded",
+        }
+    }
+}
+
+impl RdpError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            RdpError::InPdu { source, .. } => source.code(),
+            RdpError::IOError(_) => ErrorCode::Io,
+            RdpError::ConnectionError(_) => ErrorCode::Connection,
+            RdpError::ReadTimedOut => ErrorCode::ReadTimedOut,
+            RdpError::ReadCancelled => ErrorCode::ReadCancelled,
+            RdpError::UnexpectedStreamTermination => ErrorCode::StreamTerminated,
+            RdpError::UnexpectedDisconnection(_) => ErrorCode::DisconnectedByServer,
+            #[cfg(any(feature = "native-tls", feature = "rustls"))]
+            RdpError::TlsConnectorError(_) | RdpError::TlsHandshakeError(_) => ErrorCode::Tls,
+            #[cfg(all(feature = "native-tls", not(feature = "rustls")))]
+            RdpError::DerEncode(_) => ErrorCode::Tls,
+            RdpError::UntrustedServerCertificate { .. } => ErrorCode::UntrustedServerCertificate,
+            RdpError::InvalidServerCertificate(_) | RdpError::MissingPeerCertificate => {
+                ErrorCode::InvalidServerCertificate
+            }
+            RdpError::AccessDenied => ErrorCode::AccessDenied,
+            RdpError::AuthenticationFailed(_) | RdpError::RdstlsAuthenticationFailed(_) => {
+                ErrorCode::AuthenticationFailed
+            }
+            RdpError::CredSspError(_) | RdpError::TsRequestError(_) | RdpError::EarlyUserAuthResultError(_) => {
+                ErrorCode::CredSsp
+            }
+            RdpError::RdstlsError(_) => ErrorCode::Rdstls,
+            RdpError::MissingRedirectionCredentials => ErrorCode::MissingRedirectionCredentials,
+            RdpError::UnsupportedSecurityProtocol(_) => ErrorCode::UnsupportedSecurityProtocol,
+            RdpError::EncryptionNotSupported(..) => ErrorCode::UnsupportedEncryption,
+            RdpError::NegotiationError(_) | RdpError::X224Error(_) => ErrorCode::Negotiation,
+            RdpError::StandardSecurityError(_) => ErrorCode::StandardSecurity,
+            RdpError::GatewayError(_) | RdpError::GatewayHttpError(_) => ErrorCode::Gateway,
+            RdpError::GatewayRequestRejected { .. } => ErrorCode::GatewayRequestRejected,
+            RdpError::ServerRedirection(_) => ErrorCode::ServerRedirection,
+            RdpError::ServerLicenseError(_) => ErrorCode::ServerLicense,
+            RdpError::McsConnectError(_)
+            | RdpError::McsError(_)
+            | RdpError::ClientInfoError(_)
+            | RdpError::ShareControlHeaderError(_)
+            | RdpError::CapabilitySetsError(_)
+            | RdpError::UnexpectedPdu(_)
+            | RdpError::InvalidResponse(_)
+            | RdpError::ServerError(_) => ErrorCode::ConnectionSequence,
+            RdpError::UserInfoError(_)
+            | RdpError::InvalidClientMetadata(_)
+            | RdpError::InvalidMonitorLayout(_)
+            | RdpError::InvalidCapabilitiesMask(_) => ErrorCode::InvalidConfiguration,
+            RdpError::VirtualChannelError(_)
+            | RdpError::InvalidChannelName(_)
+            | RdpError::InvalidChannelIdError(_)
+            | RdpError::AccessToNonExistingChannel(_)
+            | RdpError::AccessToNonExistingChannelName(_)
+            | RdpError::UnexpectedChannel(_)
+            | RdpError::CompressedChannelData(_)
+            | RdpError::DisplayPipelineError(_)
+            | RdpError::RdpsndError(_)
+            | RdpError::RdpdrError(_)
+            | RdpError::DynamicVirtualChannelNotConnected
+            | RdpError::StaticChannelNotConnected => ErrorCode::Channel,
+            #[cfg(feature = "zgfx")]
+            RdpError::ZgfxError(_) | RdpError::ProgressiveError(_) => ErrorCode::Graphics,
+            #[cfg(any(feature = "rfx", feature = "zgfx"))]
+            RdpError::RlgrError(_) => ErrorCode::Graphics,
+            #[cfg(any(feature = "bitmap", feature = "zgfx"))]
+            RdpError::PlanarError(_) => ErrorCode::Graphics,
+            #[cfg(any(feature = "nscodec", feature = "zgfx"))]
+            RdpError::NsCodecError(_) => ErrorCode::Graphics,
+            RdpError::GraphicsPipelineError(_)
+            | RdpError::FastPathError(_)
+            | RdpError::RfxError(_)
+            | RdpError::RleError(_)
+            | RdpError::UnexpectedCodecId(_)
+            | RdpError::H264DecodingError(_)
+            | RdpError::MandatoryHeaderIsAbsent
+            | RdpError::NoRfxChannelsAnnounced
+            | RdpError::UnsupportedBitmap { .. }
+            | RdpError::UnexpectedFastPathUpdate(_) => ErrorCode::Graphics,
+            RdpError::CodecNotCompiledIn(_) => ErrorCode::CodecNotCompiledIn,
+            RdpError::RdpError(_) | RdpError::InputEventError(_) | RdpError::BulkDecompressionError(_) => {
+                ErrorCode::Protocol
+            }
+            RdpError::LimitExceeded(_) => ErrorCode::LimitExceeded,
+        }
+    }
+
+    pub fn category(&self) -> ErrorCategory {
+        self.code().category()
+    }
+
+    /// Whether the connection could not be established, has been closed or has failed, or the server has not answered.
+    pub fn is_io(&self) -> bool {
+        self.category() == ErrorCategory::Transport
+    }
+
+    pub fn is_protocol_violation(&self) -> bool {
+        self.code().is_protocol_violation()
+    }
+
+    /// Returns the context of the frame of the server the error has occurred in, during the active stage.
+    pub fn pdu_context(&self) -> Option<&PduContext> {
+        match self {
+            RdpError::InPdu { context, .. } => Some(context),
+            _ => None,
+        }
+    }
 }
 
 impl From<io::Error> for RdpError {
@@ -130,12 +513,24 @@ impl From<rustls::Error> for RdpError {
     }
 }
 
+impl From<RdstlsError> for RdpError {
+    fn from(e: RdstlsError) -> Self {
+        RdpError::RdstlsError(e)
+    }
+}
+
 impl From<nego::NegotiationError> for RdpError {
     fn from(e: nego::NegotiationError) -> Self {
         RdpError::NegotiationError(e)
     }
 }
 
+impl From<StandardSecurityError> for RdpError {
+    fn from(e: StandardSecurityError) -> Self {
+        RdpError::StandardSecurityError(e)
+    }
+}
+
 impl From<McsError> for RdpError {
     fn from(e: McsError) -> Self {
         RdpError::McsError(e)
@@ -144,7 +539,16 @@ impl From<McsError> for RdpError {
 
 impl From<rdp::vc::ChannelError> for RdpError {
     fn from(e: rdp::vc::ChannelError) -> Self {
-        RdpError::VirtualChannelError(e)
+        match e {
+            rdp::vc::ChannelError::LimitExceeded(_) => RdpError::LimitExceeded(e.to_string()),
+            e => RdpError::VirtualChannelError(e),
+        }
+    }
+}
+
+impl From<rdp::vc::ChannelNameError> for RdpError {
+    fn from(e: rdp::vc::ChannelNameError) -> Self {
+        RdpError::InvalidChannelName(e)
     }
 }
 
@@ -160,6 +564,19 @@ impl From<display::DisplayPipelineError> for RdpError {
     }
 }
 
+impl From<rdp::vc::rdpsnd::RdpsndError> for RdpError {
+    fn from(e: rdp::vc::rdpsnd::RdpsndError) -> Self {
+        RdpError::RdpsndError(e)
+    }
+}
+
+impl From<rdp::vc::rdpdr::RdpdrError> for RdpError {
+    fn from(e: rdp::vc::rdpdr::RdpdrError) -> Self {
+        RdpError::RdpdrError(e)
+    }
+}
+
+#[cfg(feature = "zgfx")]
 impl From<gfx::zgfx::ZgfxError> for RdpError {
     fn from(e: gfx::zgfx::ZgfxError) -> Self {
         RdpError::ZgfxError(e)
@@ -172,9 +589,24 @@ impl From<FastPathError> for RdpError {
     }
 }
 
+impl From<rdp::bulk::BulkError> for RdpError {
+    fn from(e: rdp::bulk::BulkError) -> Self {
+        RdpError::BulkDecompressionError(e)
+    }
+}
+
+impl From<InputEventError> for RdpError {
+    fn from(e: InputEventError) -> Self {
+        RdpError::InputEventError(e)
+    }
+}
+
 impl From<ironrdp::RdpError> for RdpError {
     fn from(e: ironrdp::RdpError) -> Self {
-        RdpError::RdpError(e)
+        match e {
+            ironrdp::RdpError::FrameTooLong { .. } => RdpError::LimitExceeded(e.to_string()),
+            e => RdpError::RdpError(e),
+        }
     }
 }
 
@@ -184,12 +616,40 @@ impl From<codecs::rfx::RfxError> for RdpError {
     }
 }
 
+#[cfg(any(feature = "rfx", feature = "zgfx"))]
 impl From<codecs::rfx::rlgr::RlgrError> for RdpError {
     fn from(e: codecs::rfx::rlgr::RlgrError) -> Self {
         RdpError::RlgrError(e)
     }
 }
 
+#[cfg(any(feature = "bitmap", feature = "zgfx"))]
+impl From<codecs::planar::PlanarError> for RdpError {
+    fn from(e: codecs::planar::PlanarError) -> Self {
+        RdpError::PlanarError(e)
+    }
+}
+
+#[cfg(any(feature = "nscodec", feature = "zgfx"))]
+impl From<codecs::nscodec::NsCodecError> for RdpError {
+    fn from(e: codecs::nscodec::NsCodecError) -> Self {
+        RdpError::NsCodecError(e)
+    }
+}
+
+#[cfg(feature = "zgfx")]
+impl From<codecs::progressive::ProgressiveError> for RdpError {
+    fn from(e: codecs::progressive::ProgressiveError) -> Self {
+        RdpError::ProgressiveError(e)
+    }
+}
+
+impl From<bitmap::rle::RleError> for RdpError {
+    fn from(e: bitmap::rle::RleError) -> Self {
+        RdpError::RleError(e)
+    }
+}
+
 impl From<ServerLicenseError> for RdpError {
     fn from(e: ServerLicenseError) -> Self {
         RdpError::ServerLicenseError(rdp::RdpError::ServerLicenseError(e))
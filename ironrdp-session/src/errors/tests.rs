@@ -0,0 +1,73 @@
+use std::collections::HashSet;
+
+use super::*;
+
+#[test]
+fn error_codes_and_message_keys_are_unique() {
+    let codes = ErrorCode::ALL.iter().map(|code| code.as_u32()).collect::<HashSet<_>>();
+    let message_keys = ErrorCode::ALL
+        .iter()
+        .map(|code| code.message_key())
+        .collect::<HashSet<_>>();
+
+    assert_eq!(ErrorCode::ALL.len(), codes.len());
+    assert_eq!(ErrorCode::ALL.len(), message_keys.len());
+}
+
+#[test]
+fn error_code_is_parsed_from_number() {
+    for code in ErrorCode::ALL {
+        assert_eq!(Some(code), ErrorCode::from_u32(code.as_u32()));
+    }
+    assert_eq!(None, ErrorCode::from_u32(0));
+}
+
+#[test]
+fn errors_of_same_cause_share_code() {
+    assert_eq!(ErrorCode::AccessDenied, RdpError::AccessDenied.code());
+    assert_eq!(2003, RdpError::AccessDenied.code().as_u32());
+    assert_eq!(
+        ErrorCode::AuthenticationFailed,
+        RdpError::RdstlsAuthenticationFailed(0x8009_030c).code()
+    );
+    assert_eq!(
+        ErrorCode::Io,
+        RdpError::from(io::Error::from(io::ErrorKind::UnexpectedEof)).code()
+    );
+}
+
+#[test]
+fn error_codes_are_categorized_by_group() {
+    assert_eq!(ErrorCategory::Transport, ErrorCode::ReadTimedOut.category());
+    assert_eq!(ErrorCategory::Gateway, ErrorCode::GatewayRequestRejected.category());
+    assert_eq!(ErrorCategory::Protocol, ErrorCode::LimitExceeded.category());
+
+    let io_error = RdpError::from(io::Error::from(io::ErrorKind::ConnectionReset));
+    assert!(io_error.is_io());
+    assert!(!io_error.is_protocol_violation());
+    assert!(RdpError::UnexpectedPdu(String::from("Server Deactivate All PDU")).is_protocol_violation());
+    assert!(!RdpError::AccessDenied.is_protocol_violation());
+}
+
+#[test]
+fn error_in_pdu_has_code_of_its_source() {
+    let error = RdpError::InPdu {
+        context: Box::new(PduContext {
+            offset: 1024,
+            channel: Some(PduChannel::Dynamic(3)),
+            pdu: Some(String::from("Data First PDU")),
+        }),
+        source: Box::new(RdpError::LimitExceeded(String::from(
+            "DVC message of 128 bytes is larger than 64",
+        ))),
+    };
+
+    assert_eq!(ErrorCode::LimitExceeded, error.code());
+    assert!(error.is_protocol_violation());
+    assert_eq!(1024, error.pdu_context().unwrap().offset);
+    assert_eq!(
+        "limit exceeded: DVC message of 128 bytes is larger than 64 \
+         (frame at offset 1024 on the Dynamic(3) channel after the Data First PDU)",
+        error.to_string()
+    );
+}
@@ -0,0 +1,119 @@
+use std::time::{Duration, Instant};
+
+use ironrdp::Rectangle;
+
+/// Coalesces the graphics updates so that the image is rendered at most once per interval.
+///
+/// The updated regions received between two renders are merged into their bounding rectangle.
+/// When a region is pending, the embedder has to call [`FrameScheduler::poll`] at the
+/// [`FrameScheduler::deadline`] even if no new update has been received meanwhile.
+pub struct FrameScheduler {
+    interval: Duration,
+    next_render: Option<Instant>,
+    pending_region: Option<Rectangle>,
+}
+
+impl FrameScheduler {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            next_render: None,
+            pending_region: None,
+        }
+    }
+
+    /// Adds the updated region and returns the region to render if the interval has elapsed.
+    pub fn update(&mut self, region: Rectangle, now: Instant) -> Option<Rectangle> {
+        let region = match self.pending_region.take() {
+            Some(pending_region) => Rectangle::union_all(&[pending_region, region]),
+            None => region,
+        };
+        self.pending_region = Some(region);
+
+        self.poll(now)
+    }
+
+    /// Returns the pending region to render if the interval has elapsed.
+    pub fn poll(&mut self, now: Instant) -> Option<Rectangle> {
+        if self.next_render.map_or(true, |next_render| next_render <= now) {
+            self.flush(now)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the pending region to render regardless of the interval, e.g. when the session ends.
+    pub fn flush(&mut self, now: Instant) -> Option<Rectangle> {
+        let region = self.pending_region.take()?;
+        self.next_render = Some(now + self.interval);
+
+        Some(region)
+    }
+
+    /// Returns the instant at which the pending region, if any, is due to be rendered.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.pending_region.as_ref().and(self.next_render)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INTERVAL: Duration = Duration::from_millis(16);
+
+    fn rectangle(left: u32, top: u32, right: u32, bottom: u32) -> Rectangle {
+        Rectangle {
+            left,
+            top,
+            right,
+            bottom,
+        }
+    }
+
+    #[test]
+    fn first_update_is_rendered_immediately() {
+        let mut scheduler = FrameScheduler::new(INTERVAL);
+        let now = Instant::now();
+
+        assert_eq!(
+            Some(rectangle(0, 0, 10, 10)),
+            scheduler.update(rectangle(0, 0, 10, 10), now)
+        );
+        assert_eq!(None, scheduler.deadline());
+    }
+
+    #[test]
+    fn updates_within_interval_are_coalesced() {
+        let mut scheduler = FrameScheduler::new(INTERVAL);
+        let now = Instant::now();
+        scheduler.update(rectangle(0, 0, 10, 10), now);
+
+        assert_eq!(
+            None,
+            scheduler.update(rectangle(20, 20, 30, 30), now + Duration::from_millis(5))
+        );
+        assert_eq!(
+            None,
+            scheduler.update(rectangle(5, 40, 15, 50), now + Duration::from_millis(10))
+        );
+        assert_eq!(Some(now + INTERVAL), scheduler.deadline());
+        assert_eq!(None, scheduler.poll(now + Duration::from_millis(15)));
+        assert_eq!(Some(rectangle(5, 20, 30, 50)), scheduler.poll(now + INTERVAL));
+        assert_eq!(None, scheduler.deadline());
+    }
+
+    #[test]
+    fn flush_returns_pending_region_before_deadline() {
+        let mut scheduler = FrameScheduler::new(INTERVAL);
+        let now = Instant::now();
+        scheduler.update(rectangle(0, 0, 10, 10), now);
+        scheduler.update(rectangle(20, 20, 30, 30), now + Duration::from_millis(1));
+
+        assert_eq!(
+            Some(rectangle(20, 20, 30, 30)),
+            scheduler.flush(now + Duration::from_millis(2))
+        );
+        assert_eq!(None, scheduler.flush(now + Duration::from_millis(3)));
+    }
+}
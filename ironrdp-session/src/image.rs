@@ -1,30 +1,381 @@
-use crate::RdpError;
-use ironrdp::codecs::rfx::image_processing::{ImageRegion, ImageRegionMut, PixelFormat};
-use ironrdp::codecs::rfx::rectangles_processing::Region;
+#[cfg(feature = "image")]
+mod export;
+#[cfg(feature = "shared-memory")]
+pub mod shared_memory;
+#[cfg(test)]
+mod tests;
+mod viewers;
+
+use std::io;
+#[cfg(feature = "shared-memory")]
+use std::path::Path;
+
+use ironrdp::codecs::rfx::image_processing::{rgb16, ImageRegion, ImageRegionMut, PixelFormat};
 use ironrdp::Rectangle;
 
-const TILE_SIZE: u16 = 64;
-const SOURCE_PIXEL_FORMAT: PixelFormat = PixelFormat::BgrX32;
-const SOURCE_STRIDE: u16 = TILE_SIZE * SOURCE_PIXEL_FORMAT.bytes_per_pixel() as u16;
+#[cfg(feature = "image")]
+pub use self::export::DEFAULT_JPEG_QUALITY;
+pub use self::viewers::{SharedImage, Viewer, ViewerUpdate};
+use crate::{Codec, RdpError};
+
+/// Pixels decoded for a rectangle of the desktop.
+///
+/// The row `n` of the rectangle starts at `data[n * stride]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageUpdate<'a> {
+    pub rectangle: Rectangle,
+    pub pixel_format: PixelFormat,
+    pub stride: usize,
+    pub data: &'a [u8],
+    /// The codec the pixels have been decoded with.
+    pub codec: Codec,
+}
+
+/// Buffer lent by an [`ImageSink`] for a tile to be decoded into it.
+///
+/// The row `n` of the tile is written at `data[n * stride]`.
+#[derive(Debug)]
+pub struct TileBuffer<'a> {
+    pub data: &'a mut [u8],
+    pub stride: usize,
+}
+
+/// Destination of the decoded graphics.
+///
+/// [`DecodedImage`] maintains a framebuffer of the whole desktop, and [`SharedImage`] shares it between several
+/// viewers, while embedders already maintaining their own surface can pass a closure receiving the decoded
+/// rectangles instead.
+pub trait ImageSink {
+    fn update(&mut self, update: &ImageUpdate<'_>) -> Result<(), RdpError>;
+
+    /// Lends the buffer into which the RemoteFX tile covering `tile` is decoded, in the output
+    /// pixel format of the session, e.g. a persistently mapped GPU buffer, so that the decoded pixels are not
+    /// copied on their way to the renderer.
+    ///
+    /// It is only requested for the tiles entirely covered by the updated region, which are then
+    /// reported with [`ImageSink::tile_decoded`] instead of [`ImageSink::update`]. No buffer is lent by default.
+    fn tile_buffer(&mut self, _tile: &Rectangle) -> Option<TileBuffer<'_>> {
+        None
+    }
+
+    /// Called once the tile has been decoded into the buffer lent by [`ImageSink::tile_buffer`].
+    fn tile_decoded(&mut self, _tile: &Rectangle) -> Result<(), RdpError> {
+        Ok(())
+    }
+
+    /// Called when the server has changed the size of the desktop, e.g. after a request sent with
+    /// [`ActiveStageProcessor::encode_monitor_layout`](crate::ActiveStageProcessor::encode_monitor_layout).
+    /// The following updates are relative to the new size, and the whole desktop is redrawn.
+    fn resize(&mut self, _width: u32, _height: u32) -> Result<(), RdpError> {
+        Ok(())
+    }
+}
+
+impl<F> ImageSink for F
+where
+    F: FnMut(&ImageUpdate<'_>),
+{
+    fn update(&mut self, update: &ImageUpdate<'_>) -> Result<(), RdpError> {
+        self(update);
+
+        Ok(())
+    }
+}
+
+/// Forwards the updates to the wrapped sink, keeping track of the region they covered.
+pub(crate) struct UpdateTracker<'a> {
+    image: &'a mut dyn ImageSink,
+    update_region: Option<Rectangle>,
+}
+
+impl<'a> UpdateTracker<'a> {
+    pub(crate) fn new(image: &'a mut dyn ImageSink) -> Self {
+        Self {
+            image,
+            update_region: None,
+        }
+    }
+
+    pub(crate) fn update_region(self) -> Option<Rectangle> {
+        self.update_region
+    }
+}
+
+impl UpdateTracker<'_> {
+    fn track(&mut self, rectangle: &Rectangle) {
+        self.update_region = Some(match self.update_region.take() {
+            Some(update_region) => update_region.union(rectangle),
+            None => rectangle.clone(),
+        });
+    }
+}
+
+impl ImageSink for UpdateTracker<'_> {
+    fn update(&mut self, update: &ImageUpdate<'_>) -> Result<(), RdpError> {
+        self.image.update(update)?;
+        self.track(&update.rectangle);
+
+        Ok(())
+    }
+
+    fn tile_buffer(&mut self, tile: &Rectangle) -> Option<TileBuffer<'_>> {
+        self.image.tile_buffer(tile)
+    }
+
+    fn tile_decoded(&mut self, tile: &Rectangle) -> Result<(), RdpError> {
+        self.image.tile_decoded(tile)?;
+        self.track(tile);
+
+        Ok(())
+    }
+
+    fn resize(&mut self, width: u32, height: u32) -> Result<(), RdpError> {
+        // the updates preceding the resize are outdated
+        self.update_region = None;
+
+        self.image.resize(width, height)
+    }
+}
+
+/// Converts in place the `BgrX32` pixels of `data` into the pixel format. The converted pixels
+/// are packed at the start of `data` if the pixel format has fewer bytes per pixel.
+pub(crate) fn convert_bgrx_in_place(data: &mut [u8], pixel_format: PixelFormat) {
+    match pixel_format {
+        PixelFormat::BgrX32 | PixelFormat::BgrA32 => (),
+        PixelFormat::RgbX32 | PixelFormat::RgbA32 => data.chunks_exact_mut(4).for_each(|pixel| pixel.swap(0, 2)),
+        PixelFormat::XRgb32 | PixelFormat::ARgb32 => data.chunks_exact_mut(4).for_each(|pixel| pixel.reverse()),
+        PixelFormat::XBgr32 | PixelFormat::ABgr32 => data.chunks_exact_mut(4).for_each(|pixel| pixel.rotate_right(1)),
+        PixelFormat::Rgb16 => {
+            // each pixel is written before or over the one it is read from
+            for pixel in 0..data.len() / 4 {
+                let (b, g, r) = (data[pixel * 4], data[pixel * 4 + 1], data[pixel * 4 + 2]);
+                data[pixel * 2..][..2].copy_from_slice(&rgb16(r, g, b).to_le_bytes());
+            }
+        }
+    }
+}
+
+/// Called with the region of the [`DecodedImage`] which has just been updated.
+pub type DamageHook = Box<dyn FnMut(&Rectangle) + Send + Sync>;
+
+/// The memory the pixels of a [`DecodedImage`] are stored in.
+enum Framebuffer {
+    Owned(Vec<u8>),
+    #[cfg(feature = "shared-memory")]
+    Shared(shared_memory::SharedFramebuffer),
+}
+
+impl Framebuffer {
+    fn pixels(&self) -> &[u8] {
+        match self {
+            Self::Owned(data) => data,
+            #[cfg(feature = "shared-memory")]
+            Self::Shared(framebuffer) => framebuffer.pixels(),
+        }
+    }
+
+    fn pixels_mut(&mut self) -> &mut [u8] {
+        match self {
+            Self::Owned(data) => data,
+            #[cfg(feature = "shared-memory")]
+            Self::Shared(framebuffer) => framebuffer.pixels_mut(),
+        }
+    }
 
+    /// Runs `write` on the pixels, the consumers of a shared framebuffer discarding what they read meanwhile.
+    fn write<T>(&mut self, write: impl FnOnce(&mut [u8]) -> T) -> T {
+        #[cfg(feature = "shared-memory")]
+        if let Self::Shared(framebuffer) = self {
+            framebuffer.begin_write();
+            let result = write(framebuffer.pixels_mut());
+            framebuffer.end_write();
+
+            return result;
+        }
+
+        write(self.pixels_mut())
+    }
+
+    fn resize(&mut self, pixel_format: PixelFormat, width: u32, height: u32) -> io::Result<()> {
+        match self {
+            Self::Owned(data) => {
+                data.clear();
+                data.resize(image_len(pixel_format, width, height), 0);
+
+                Ok(())
+            }
+            #[cfg(feature = "shared-memory")]
+            Self::Shared(framebuffer) => framebuffer.resize(pixel_format, width, height),
+        }
+    }
+}
+
+/// Framebuffer of the whole desktop, keeping track of the region updated since the embedder last took it,
+/// so that only this region is copied to the screen or to the encoder of a recording.
+///
+/// It is `Send` and `Sync`, to be shared with the threads of the embedder behind a lock, or with
+/// [`SharedImage`] for the viewers not to hold back the decoding.
 pub struct DecodedImage {
     pixel_format: PixelFormat,
-    data: Vec<u8>,
+    data: Framebuffer,
     width: u32,
     height: u32,
+    dirty_region: Option<Rectangle>,
+    damage_hooks: Vec<DamageHook>,
 }
 
 impl DecodedImage {
     pub fn new(pixel_format: PixelFormat, width: u32, height: u32) -> Self {
-        let len = usize::try_from(width).unwrap()
-            * usize::try_from(height).unwrap()
-            * usize::from(pixel_format.bytes_per_pixel());
-
         Self {
             pixel_format,
-            data: vec![0; len],
+            data: Framebuffer::Owned(vec![0; image_len(pixel_format, width, height)]),
+            width,
+            height,
+            dirty_region: None,
+            damage_hooks: Vec::new(),
+        }
+    }
+
+    /// Creates an image backed by a file mapped in memory, e.g. of `/dev/shm`, for another process such as
+    /// a video encoder to consume the frames without them being copied. The file is replaced if it exists,
+    /// and removed when the image is dropped.
+    ///
+    /// The layout of the file is described in the [`shared_memory`] module, the frames being delimited
+    /// with [`DecodedImage::publish_frame`].
+    #[cfg(feature = "shared-memory")]
+    pub fn with_shared_memory(
+        pixel_format: PixelFormat,
+        width: u32,
+        height: u32,
+        path: impl AsRef<Path>,
+    ) -> io::Result<Self> {
+        let framebuffer = shared_memory::SharedFramebuffer::create(path.as_ref(), pixel_format, width, height)?;
+
+        Ok(Self {
+            pixel_format,
+            data: Framebuffer::Shared(framebuffer),
             width,
             height,
+            dirty_region: None,
+            damage_hooks: Vec::new(),
+        })
+    }
+
+    /// The file the image is mapped from, `None` if it is not backed by shared memory.
+    #[cfg(feature = "shared-memory")]
+    pub fn shared_memory_path(&self) -> Option<&Path> {
+        match &self.data {
+            Framebuffer::Shared(framebuffer) => Some(framebuffer.path()),
+            Framebuffer::Owned(_) => None,
+        }
+    }
+
+    /// Publishes to the consumers of the shared memory the regions updated since the previous frame,
+    /// e.g. on each [`ActiveStageOutput::GraphicsUpdate`](crate::ActiveStageOutput::GraphicsUpdate),
+    /// and returns the incremented frame counter, `None` if the image is not backed by shared memory.
+    #[cfg(feature = "shared-memory")]
+    pub fn publish_frame(&mut self) -> Option<u64> {
+        match &mut self.data {
+            Framebuffer::Shared(framebuffer) => Some(framebuffer.publish_frame()),
+            Framebuffer::Owned(_) => None,
+        }
+    }
+
+    /// Registers a hook called with each region updated, e.g. to schedule the repaint of a window.
+    pub fn on_damage(&mut self, hook: impl FnMut(&Rectangle) + Send + Sync + 'static) {
+        self.damage_hooks.push(Box::new(hook));
+    }
+
+    /// The region updated since the previous call to [`DecodedImage::take_dirty_region`], the whole desktop
+    /// once it has been resized.
+    pub fn dirty_region(&self) -> Option<&Rectangle> {
+        self.dirty_region.as_ref()
+    }
+
+    pub fn take_dirty_region(&mut self) -> Option<Rectangle> {
+        self.dirty_region.take()
+    }
+
+    /// Borrows the pixels of the region, `None` being returned if it exceeds the desktop.
+    ///
+    /// The pixels are not copied: the rows of the returned region are `step` bytes apart in `data`.
+    pub fn get_region(&self, rectangle: &Rectangle) -> Option<ImageRegion<'_>> {
+        let is_inside = rectangle.left <= rectangle.right
+            && rectangle.top <= rectangle.bottom
+            && rectangle.right <= self.width
+            && rectangle.bottom <= self.height;
+
+        is_inside.then(|| ImageRegion {
+            region: rectangle.clone(),
+            step: self.stride(),
+            pixel_format: self.pixel_format,
+            data: self.data.pixels(),
+        })
+    }
+
+    /// Copies the pixels of the region converted to the pixel format, e.g. `RgbA32` for a screenshot
+    /// to be saved. The row `n` of the region starts at `n * width * bytes_per_pixel`.
+    pub fn copy_region(&self, rectangle: &Rectangle, pixel_format: PixelFormat) -> Result<Vec<u8>, RdpError> {
+        let source = self.get_region(rectangle).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "The region {:?} exceeds the {}x{} desktop",
+                    rectangle, self.width, self.height
+                ),
+            )
+        })?;
+
+        let width = rectangle.width();
+        let height = rectangle.height();
+        let mut data = vec![0; image_len(pixel_format, width, height)];
+        let mut destination = ImageRegionMut {
+            region: Rectangle {
+                left: 0,
+                top: 0,
+                right: width,
+                bottom: height,
+            },
+            step: 0,
+            pixel_format,
+            data: &mut data,
+        };
+        source.copy_to(&mut destination)?;
+
+        Ok(data)
+    }
+
+    /// Copies the pixels of the whole desktop converted to the pixel format.
+    pub fn to_pixel_format(&self, pixel_format: PixelFormat) -> Result<Vec<u8>, RdpError> {
+        self.copy_region(
+            &Rectangle {
+                left: 0,
+                top: 0,
+                right: self.width,
+                bottom: self.height,
+            },
+            pixel_format,
+        )
+    }
+
+    /// The number of bytes between the starts of two rows of the framebuffer.
+    pub fn stride(&self) -> usize {
+        usize::try_from(self.width).unwrap() * usize::from(self.pixel_format.bytes_per_pixel())
+    }
+
+    fn damage(&mut self, rectangle: &Rectangle) {
+        self.dirty_region = Some(match self.dirty_region.take() {
+            Some(dirty_region) => dirty_region.union(rectangle),
+            None => rectangle.clone(),
+        });
+
+        #[cfg(feature = "shared-memory")]
+        if let Framebuffer::Shared(framebuffer) = &mut self.data {
+            framebuffer.add_dirty_rect(rectangle);
+        }
+
+        for hook in self.damage_hooks.iter_mut() {
+            hook(rectangle);
         }
     }
 
@@ -33,7 +384,7 @@ impl DecodedImage {
     }
 
     pub fn data(&self) -> &[u8] {
-        &self.data
+        self.data.pixels()
     }
 
     pub fn width(&self) -> u32 {
@@ -43,45 +394,65 @@ impl DecodedImage {
     pub fn height(&self) -> u32 {
         self.height
     }
+}
 
-    pub(crate) fn apply_tile(
-        &mut self,
-        tile_output: &[u8],
-        clipping_rectangles: &Region,
-        update_rectangle: &Rectangle,
-        width: u16,
-    ) -> Result<(), RdpError> {
-        debug!("Tile: {:?}", update_rectangle);
-
-        let update_region = clipping_rectangles.intersect_rectangle(update_rectangle);
-        for region_rectangle in &update_region.rectangles {
-            let source_x = region_rectangle.left - update_rectangle.left;
-            let source_y = region_rectangle.top - update_rectangle.top;
-            let source_image_region = ImageRegion {
-                region: Rectangle {
-                    left: source_x,
-                    top: source_y,
-                    right: source_x + region_rectangle.width(),
-                    bottom: source_y + region_rectangle.height(),
-                },
-                step: SOURCE_STRIDE,
-                pixel_format: SOURCE_PIXEL_FORMAT,
-                data: tile_output,
-            };
+impl ImageSink for DecodedImage {
+    fn update(&mut self, update: &ImageUpdate<'_>) -> Result<(), RdpError> {
+        let source_image_region = ImageRegion {
+            region: Rectangle {
+                left: 0,
+                top: 0,
+                right: update.rectangle.width(),
+                bottom: update.rectangle.height(),
+            },
+            step: update.stride,
+            pixel_format: update.pixel_format,
+            data: update.data,
+        };
 
+        let step = self.stride();
+        let pixel_format = self.pixel_format;
+        self.data.write(|data| {
             let mut destination_image_region = ImageRegionMut {
-                region: region_rectangle.clone(),
-                step: width * u16::from(self.pixel_format.bytes_per_pixel()),
-                pixel_format: self.pixel_format,
-                data: &mut self.data,
+                region: update.rectangle.clone(),
+                step,
+                pixel_format,
+                data,
             };
 
-            debug!("Source image region: {:?}", source_image_region.region);
             debug!("Destination image region: {:?}", destination_image_region.region);
 
-            source_image_region.copy_to(&mut destination_image_region)?;
-        }
+            source_image_region.copy_to(&mut destination_image_region)
+        })?;
+        self.damage(&update.rectangle);
+
+        Ok(())
+    }
+
+    /// Reallocates the framebuffer for the new size, blank until the server redraws the desktop.
+    fn resize(&mut self, width: u32, height: u32) -> Result<(), RdpError> {
+        debug!(
+            "Desktop resized from {}x{} to {}x{}",
+            self.width, self.height, width, height
+        );
+
+        self.data.resize(self.pixel_format, width, height)?;
+        self.width = width;
+        self.height = height;
+
+        // the updates preceding the resize are outdated
+        self.dirty_region = None;
+        self.damage(&Rectangle {
+            left: 0,
+            top: 0,
+            right: width,
+            bottom: height,
+        });
 
         Ok(())
     }
 }
+
+fn image_len(pixel_format: PixelFormat, width: u32, height: u32) -> usize {
+    usize::try_from(width).unwrap() * usize::try_from(height).unwrap() * usize::from(pixel_format.bytes_per_pixel())
+}
@@ -0,0 +1,58 @@
+//! Conversion of the decoded desktop to the images of the `image` crate, for the screenshots of the headless
+//! clients not handling the raw framebuffer.
+
+use std::fs::File;
+use std::io::{self, Write as _};
+use std::path::Path;
+
+use image::codecs::jpeg::JpegEncoder;
+use image::{ColorType, DynamicImage, ImageError, ImageFormat, RgbaImage};
+use ironrdp::codecs::rfx::image_processing::PixelFormat;
+
+use super::DecodedImage;
+use crate::RdpError;
+
+/// The quality of the JPEG snapshots, from 1 to 100, trading their size for the sharpness of the text.
+pub const DEFAULT_JPEG_QUALITY: u8 = 85;
+
+impl DecodedImage {
+    /// Copies the whole desktop into an RGBA image, e.g. to be cropped or encoded in another format.
+    pub fn to_image(&self) -> RgbaImage {
+        // the whole desktop is inside the framebuffer, and the copy has the size of the image
+        let data = self
+            .to_pixel_format(PixelFormat::RgbA32)
+            .expect("the desktop is copied from the framebuffer");
+
+        RgbaImage::from_raw(self.width, self.height, data).unwrap()
+    }
+
+    /// Saves the desktop as a PNG file, replacing the file if it exists.
+    pub fn save_png(&self, path: impl AsRef<Path>) -> Result<(), RdpError> {
+        self.to_image()
+            .save_with_format(path, ImageFormat::Png)
+            .map_err(image_error)?;
+
+        Ok(())
+    }
+
+    /// Saves the desktop as a JPEG file of the quality, from 1 to 100, replacing the file if it exists.
+    /// The alpha of the pixels is discarded, JPEG not supporting it.
+    pub fn save_jpeg(&self, path: impl AsRef<Path>, quality: u8) -> Result<(), RdpError> {
+        let rgb = DynamicImage::ImageRgba8(self.to_image()).into_rgb8();
+
+        let mut file = io::BufWriter::new(File::create(path)?);
+        JpegEncoder::new_with_quality(&mut file, quality)
+            .encode(rgb.as_raw(), self.width, self.height, ColorType::Rgb8)
+            .map_err(image_error)?;
+        file.flush()?;
+
+        Ok(())
+    }
+}
+
+fn image_error(e: ImageError) -> io::Error {
+    match e {
+        ImageError::IoError(e) => e,
+        e => io::Error::new(io::ErrorKind::Other, e),
+    }
+}
@@ -0,0 +1,207 @@
+//! Framebuffer mapped from a file shared with other processes, e.g. a video encoder consuming the frames
+//! of the desktop without them being copied through a pipe or a socket. On Linux, a file of `/dev/shm`
+//! keeps the framebuffer in memory.
+//!
+//! The file starts with a header of [`HEADER_SIZE`] bytes followed by the pixels, the row `n` of the desktop
+//! starting at `HEADER_SIZE + n * stride`. The fields of the header are in the byte order of the machine:
+//!
+//! | Offset | Size | Field                                                                       |
+//! |--------|------|-----------------------------------------------------------------------------|
+//! | 0      | 4    | `IRFB` magic                                                                |
+//! | 4      | 4    | version of the layout, [`VERSION`]                                          |
+//! | 8      | 4    | size of the header, the offset of the pixels                                |
+//! | 12     | 4    | pixel format, the value of [`PixelFormat`]                                  |
+//! | 16     | 4    | width of the desktop                                                        |
+//! | 20     | 4    | height of the desktop                                                       |
+//! | 24     | 4    | stride, the number of bytes between the starts of two rows                  |
+//! | 28     | 4    | number of the dirty rectangles of the frame                                 |
+//! | 32     | 8    | sequence, odd while the header or the pixels are written                    |
+//! | 40     | 8    | frame counter, incremented by [`DecodedImage::publish_frame`]               |
+//! | 64     | 256  | up to [`MAX_DIRTY_RECTS`] rectangles as `left`, `top`, `right` and `bottom` |
+//!
+//! The dirty rectangles are the regions updated since the previous frame. A consumer which has missed frames,
+//! the frame counter having been incremented more than once since it last read it, redraws the whole desktop.
+//!
+//! The sequence is a seqlock: a consumer reads it and waits for it to be even, copies the fields and the pixels
+//! it needs, then reads it again after an acquire fence, the copy being torn and retried if it has changed.
+//!
+//! The file is grown with the desktop but never shrunk, so that the mappings of the consumers stay valid
+//! after a resize. A consumer remaps the file when `HEADER_SIZE + stride * height` exceeds its mapping.
+//!
+//! [`DecodedImage::publish_frame`]: super::DecodedImage::publish_frame
+
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{self, AtomicU64, Ordering};
+
+use ironrdp::codecs::rfx::image_processing::PixelFormat;
+use ironrdp::Rectangle;
+use memmap2::MmapMut;
+
+use super::image_len;
+
+pub const MAGIC: [u8; 4] = *b"IRFB";
+pub const VERSION: u32 = 1;
+pub const MAX_DIRTY_RECTS: usize = 16;
+pub const HEADER_SIZE: usize = DIRTY_RECTS_OFFSET + MAX_DIRTY_RECTS * DIRTY_RECT_SIZE;
+
+const MAGIC_OFFSET: usize = 0;
+const VERSION_OFFSET: usize = 4;
+const HEADER_SIZE_OFFSET: usize = 8;
+const PIXEL_FORMAT_OFFSET: usize = 12;
+const WIDTH_OFFSET: usize = 16;
+const HEIGHT_OFFSET: usize = 20;
+const STRIDE_OFFSET: usize = 24;
+const DIRTY_RECT_COUNT_OFFSET: usize = 28;
+const SEQUENCE_OFFSET: usize = 32;
+const FRAME_OFFSET: usize = 40;
+const DIRTY_RECTS_OFFSET: usize = 64;
+const DIRTY_RECT_SIZE: usize = 16;
+
+pub(super) struct SharedFramebuffer {
+    path: PathBuf,
+    file: File,
+    map: MmapMut,
+    image_len: usize,
+    frame: u64,
+    /// The regions updated since the previous frame, merged into one when exceeding the slots of the header.
+    dirty_rects: Vec<Rectangle>,
+}
+
+impl SharedFramebuffer {
+    /// Creates the file, replacing an existing one, and maps it.
+    pub(super) fn create(path: &Path, pixel_format: PixelFormat, width: u32, height: u32) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        let image_len = image_len(pixel_format, width, height);
+        file.set_len(u64::try_from(HEADER_SIZE + image_len).unwrap())?;
+        // SAFETY: the file has just been created and is only written through this mapping,
+        // the consumers mapping it for reading
+        let map = unsafe { MmapMut::map_mut(&file)? };
+
+        let mut framebuffer = Self {
+            path: path.to_owned(),
+            file,
+            map,
+            image_len,
+            frame: 0,
+            dirty_rects: Vec::new(),
+        };
+        framebuffer.map[MAGIC_OFFSET..][..4].copy_from_slice(&MAGIC);
+        framebuffer.write_u32(VERSION_OFFSET, VERSION);
+        framebuffer.write_u32(HEADER_SIZE_OFFSET, HEADER_SIZE as u32);
+        framebuffer.write_geometry(pixel_format, width, height);
+
+        Ok(framebuffer)
+    }
+
+    pub(super) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub(super) fn pixels(&self) -> &[u8] {
+        &self.map[HEADER_SIZE..][..self.image_len]
+    }
+
+    pub(super) fn pixels_mut(&mut self) -> &mut [u8] {
+        &mut self.map[HEADER_SIZE..][..self.image_len]
+    }
+
+    /// Marks the start of a write for the consumers to discard what they are reading.
+    pub(super) fn begin_write(&self) {
+        let sequence = self.sequence();
+        sequence.store(sequence.load(Ordering::Relaxed).wrapping_add(1), Ordering::Relaxed);
+        atomic::fence(Ordering::Release);
+    }
+
+    pub(super) fn end_write(&self) {
+        let sequence = self.sequence();
+        sequence.store(sequence.load(Ordering::Relaxed).wrapping_add(1), Ordering::Release);
+    }
+
+    pub(super) fn add_dirty_rect(&mut self, rectangle: &Rectangle) {
+        if self.dirty_rects.len() == MAX_DIRTY_RECTS {
+            let union = self
+                .dirty_rects
+                .drain(..)
+                .fold(rectangle.clone(), |union, dirty_rect| union.union(&dirty_rect));
+            self.dirty_rects.push(union);
+        } else {
+            self.dirty_rects.push(rectangle.clone());
+        }
+    }
+
+    /// Publishes the dirty rectangles of the frame and increments the frame counter, returning it.
+    pub(super) fn publish_frame(&mut self) -> u64 {
+        self.begin_write();
+
+        let dirty_rects = std::mem::take(&mut self.dirty_rects);
+        for (index, dirty_rect) in dirty_rects.iter().enumerate() {
+            let offset = DIRTY_RECTS_OFFSET + index * DIRTY_RECT_SIZE;
+            self.write_u32(offset, dirty_rect.left);
+            self.write_u32(offset + 4, dirty_rect.top);
+            self.write_u32(offset + 8, dirty_rect.right);
+            self.write_u32(offset + 12, dirty_rect.bottom);
+        }
+        self.write_u32(DIRTY_RECT_COUNT_OFFSET, dirty_rects.len() as u32);
+
+        self.frame += 1;
+        self.map[FRAME_OFFSET..][..8].copy_from_slice(&self.frame.to_ne_bytes());
+
+        self.end_write();
+
+        self.frame
+    }
+
+    /// Grows the file if needed and blanks the pixels for the new size.
+    pub(super) fn resize(&mut self, pixel_format: PixelFormat, width: u32, height: u32) -> io::Result<()> {
+        let image_len = image_len(pixel_format, width, height);
+        if HEADER_SIZE + image_len > self.map.len() {
+            self.file.set_len(u64::try_from(HEADER_SIZE + image_len).unwrap())?;
+            // SAFETY: see `SharedFramebuffer::create`
+            self.map = unsafe { MmapMut::map_mut(&self.file)? };
+        }
+
+        self.begin_write();
+        self.image_len = image_len;
+        self.pixels_mut().fill(0);
+        self.write_geometry(pixel_format, width, height);
+        // the updates preceding the resize are outdated
+        self.dirty_rects.clear();
+        self.end_write();
+
+        Ok(())
+    }
+
+    fn write_geometry(&mut self, pixel_format: PixelFormat, width: u32, height: u32) {
+        let stride = width * u32::from(pixel_format.bytes_per_pixel());
+
+        self.write_u32(PIXEL_FORMAT_OFFSET, pixel_format as u32);
+        self.write_u32(WIDTH_OFFSET, width);
+        self.write_u32(HEIGHT_OFFSET, height);
+        self.write_u32(STRIDE_OFFSET, stride);
+    }
+
+    fn write_u32(&mut self, offset: usize, value: u32) {
+        self.map[offset..][..4].copy_from_slice(&value.to_ne_bytes());
+    }
+
+    fn sequence(&self) -> &AtomicU64 {
+        // SAFETY: the mapping starts on a page boundary, so the field is aligned, and it is only accessed atomically
+        unsafe { &*(self.map.as_ptr().add(SEQUENCE_OFFSET) as *const AtomicU64) }
+    }
+}
+
+impl Drop for SharedFramebuffer {
+    /// Removes the file, the consumers which have mapped it keeping their mapping.
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_file(&self.path) {
+            warn!("Failed to remove the shared framebuffer {}: {}", self.path.display(), e);
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use std::sync::{Arc, Mutex};
+
+use super::*;
+
+const WIDTH: u32 = 4;
+const HEIGHT: u32 = 2;
+
+fn rectangle(left: u32, top: u32, right: u32, bottom: u32) -> Rectangle {
+    Rectangle {
+        left,
+        top,
+        right,
+        bottom,
+    }
+}
+
+fn update(image: &mut DecodedImage, rectangle: Rectangle, pixel: [u8; 4]) {
+    let data = pixel.repeat(rectangle.width() as usize * rectangle.height() as usize);
+
+    image
+        .update(&ImageUpdate {
+            stride: rectangle.width() as usize * 4,
+            rectangle,
+            pixel_format: PixelFormat::BgrX32,
+            data: &data,
+            codec: Codec::Bitmap,
+        })
+        .unwrap();
+}
+
+#[test]
+fn decoded_image_is_thread_safe() {
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    assert_send_sync::<DecodedImage>();
+}
+
+#[test]
+fn dirty_region_is_union_of_updates_until_taken() {
+    let mut image = DecodedImage::new(PixelFormat::BgrX32, WIDTH, HEIGHT);
+    let damage = Arc::new(Mutex::new(Vec::new()));
+    let hook_damage = Arc::clone(&damage);
+    image.on_damage(move |rectangle| hook_damage.lock().unwrap().push(rectangle.clone()));
+
+    update(&mut image, rectangle(0, 0, 1, 1), [0; 4]);
+    update(&mut image, rectangle(2, 1, 3, 2), [0; 4]);
+
+    assert_eq!(Some(rectangle(0, 0, 3, 2)), image.take_dirty_region());
+    assert_eq!(None, image.dirty_region());
+    assert_eq!(
+        vec![rectangle(0, 0, 1, 1), rectangle(2, 1, 3, 2)],
+        *damage.lock().unwrap()
+    );
+}
+
+#[test]
+fn region_is_copied_in_requested_pixel_format() {
+    let mut image = DecodedImage::new(PixelFormat::BgrX32, WIDTH, HEIGHT);
+    update(&mut image, rectangle(1, 1, 3, 2), [0x10, 0x20, 0x30, 0xff]);
+
+    let region = image.copy_region(&rectangle(1, 1, 3, 2), PixelFormat::RgbA32).unwrap();
+
+    assert_eq!([0x30, 0x20, 0x10, 0xff].repeat(2), region);
+    assert!(image.get_region(&rectangle(2, 0, 5, 1)).is_none());
+}
+
+#[cfg(feature = "shared-memory")]
+#[test]
+fn shared_memory_image_publishes_frames_in_file() {
+    use super::shared_memory::{HEADER_SIZE, MAGIC};
+
+    fn read_u32(file: &[u8], offset: usize) -> u32 {
+        u32::from_ne_bytes(file[offset..][..4].try_into().unwrap())
+    }
+
+    let path = std::env::temp_dir().join(format!("ironrdp-shared-image-{}", std::process::id()));
+    let mut image = DecodedImage::with_shared_memory(PixelFormat::BgrX32, WIDTH, HEIGHT, &path).unwrap();
+    update(&mut image, rectangle(1, 1, 3, 2), [0x10, 0x20, 0x30, 0xff]);
+    assert_eq!(Some(1), image.publish_frame());
+
+    let file = std::fs::read(&path).unwrap();
+    assert_eq!(MAGIC, file[..4]);
+    assert_eq!(
+        [WIDTH, HEIGHT, WIDTH * 4],
+        [16, 20, 24].map(|offset| read_u32(&file, offset))
+    );
+    assert_eq!(1, read_u32(&file, 28));
+    assert_eq!([1, 1, 3, 2], [64, 68, 72, 76].map(|offset| read_u32(&file, offset)));
+    assert_eq!(1, u64::from_ne_bytes(file[40..48].try_into().unwrap()));
+    assert_eq!(0, u64::from_ne_bytes(file[32..40].try_into().unwrap()) % 2);
+    assert_eq!(image.data(), &file[HEADER_SIZE..]);
+
+    drop(image);
+    assert!(!path.exists());
+}
+
+#[cfg(feature = "image")]
+#[test]
+fn image_is_exported_as_rgba_and_saved_as_png() {
+    let mut image = DecodedImage::new(PixelFormat::BgrX32, WIDTH, HEIGHT);
+    update(&mut image, rectangle(1, 1, 3, 2), [0x10, 0x20, 0x30, 0x00]);
+
+    let rgba = image.to_image();
+    assert_eq!((WIDTH, HEIGHT), rgba.dimensions());
+    assert_eq!([0x30, 0x20, 0x10, 0xff], rgba.get_pixel(1, 1).0);
+    assert_eq!([0x00, 0x00, 0x00, 0xff], rgba.get_pixel(0, 0).0);
+
+    let path = std::env::temp_dir().join(format!("ironrdp-snapshot-{}.png", std::process::id()));
+    image.save_png(&path).unwrap();
+    let saved = ::image::open(&path).unwrap().into_rgba8();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(rgba, saved);
+}
@@ -0,0 +1,209 @@
+//! Sharing of the decoded desktop between several viewers, e.g. the window of the client, a recorder and
+//! a thumbnail generator, each taking the updates at its own pace.
+
+#[cfg(test)]
+mod tests;
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Poll, Waker};
+
+use futures_util::future::poll_fn;
+use ironrdp::codecs::rfx::image_processing::PixelFormat;
+use ironrdp::Rectangle;
+
+use super::{DecodedImage, ImageSink, ImageUpdate};
+use crate::RdpError;
+
+/// Image sink maintaining the framebuffer of the desktop for the [`Viewer`]s subscribed to it.
+///
+/// The viewers do not hold back the decoding: the region updated since a viewer last took an update grows
+/// until it does, the intermediate states of the region being skipped, so a slow viewer only gets fewer
+/// and larger updates.
+pub struct SharedImage {
+    shared: Arc<Mutex<SharedState>>,
+}
+
+struct SharedState {
+    image: DecodedImage,
+    viewers: HashMap<u64, ViewerState>,
+    next_viewer_id: u64,
+}
+
+#[derive(Default)]
+struct ViewerState {
+    damage: Option<Rectangle>,
+    resized: bool,
+    waker: Option<Waker>,
+}
+
+impl ViewerState {
+    fn add_damage(&mut self, rectangle: &Rectangle) {
+        self.damage = Some(match self.damage.take() {
+            Some(damage) => damage.union(rectangle),
+            None => rectangle.clone(),
+        });
+
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl SharedImage {
+    pub fn new(pixel_format: PixelFormat, width: u32, height: u32) -> Self {
+        Self {
+            shared: Arc::new(Mutex::new(SharedState {
+                image: DecodedImage::new(pixel_format, width, height),
+                viewers: HashMap::new(),
+                next_viewer_id: 0,
+            })),
+        }
+    }
+
+    /// Subscribes a viewer, whose first update is the whole desktop.
+    pub fn subscribe(&self) -> Viewer {
+        let mut shared = lock(&self.shared);
+        let id = shared.next_viewer_id;
+        shared.next_viewer_id += 1;
+
+        let whole_desktop = whole_desktop(&shared.image);
+        shared.viewers.insert(
+            id,
+            ViewerState {
+                damage: Some(whole_desktop),
+                ..ViewerState::default()
+            },
+        );
+
+        Viewer {
+            shared: Arc::clone(&self.shared),
+            id,
+        }
+    }
+
+    pub fn viewer_count(&self) -> usize {
+        lock(&self.shared).viewers.len()
+    }
+}
+
+impl ImageSink for SharedImage {
+    fn update(&mut self, update: &ImageUpdate<'_>) -> Result<(), RdpError> {
+        let mut shared = lock(&self.shared);
+        shared.image.update(update)?;
+
+        for viewer in shared.viewers.values_mut() {
+            viewer.add_damage(&update.rectangle);
+        }
+
+        Ok(())
+    }
+
+    fn resize(&mut self, width: u32, height: u32) -> Result<(), RdpError> {
+        let mut shared = lock(&self.shared);
+        shared.image.resize(width, height)?;
+
+        let whole_desktop = whole_desktop(&shared.image);
+        for viewer in shared.viewers.values_mut() {
+            // the damage preceding the resize is outdated
+            viewer.damage = None;
+            viewer.resized = true;
+            viewer.add_damage(&whole_desktop);
+        }
+
+        Ok(())
+    }
+}
+
+/// Pixels of the region of the desktop updated since the previous update taken by the viewer.
+///
+/// The row `n` of the region starts at `data[n * stride]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ViewerUpdate {
+    pub rectangle: Rectangle,
+    pub pixel_format: PixelFormat,
+    pub stride: usize,
+    pub data: Vec<u8>,
+    /// The size of the desktop, which has changed since the previous update if `resized` is set.
+    pub desktop_size: (u32, u32),
+    pub resized: bool,
+}
+
+/// Subscription to the updates of a [`SharedImage`], unsubscribed when dropped.
+pub struct Viewer {
+    shared: Arc<Mutex<SharedState>>,
+    id: u64,
+}
+
+impl Viewer {
+    /// Returns the region of the desktop updated since the previous call, `None` if it has not been.
+    pub fn take_update(&self) -> Option<ViewerUpdate> {
+        let mut shared = lock(&self.shared);
+        take_update(&mut shared, self.id)
+    }
+
+    /// Waits for the desktop to be updated, returning the region updated since the previous update taken.
+    pub async fn next_update(&self) -> ViewerUpdate {
+        poll_fn(|cx| self.poll_update(cx)).await
+    }
+
+    pub fn poll_update(&self, cx: &mut Context<'_>) -> Poll<ViewerUpdate> {
+        let mut shared = lock(&self.shared);
+        match take_update(&mut shared, self.id) {
+            Some(update) => Poll::Ready(update),
+            None => {
+                if let Some(viewer) = shared.viewers.get_mut(&self.id) {
+                    viewer.waker = Some(cx.waker().clone());
+                }
+
+                Poll::Pending
+            }
+        }
+    }
+
+    /// Runs `f` with the framebuffer of the whole desktop, e.g. for a thumbnail to be generated from it
+    /// regardless of the region updated. The decoding waits for `f` to return.
+    pub fn with_image<T>(&self, f: impl FnOnce(&DecodedImage) -> T) -> T {
+        f(&lock(&self.shared).image)
+    }
+}
+
+impl Drop for Viewer {
+    fn drop(&mut self) {
+        lock(&self.shared).viewers.remove(&self.id);
+    }
+}
+
+/// A viewer panicking while holding the lock does not corrupt the framebuffer, so the poisoning is ignored.
+fn lock(shared: &Mutex<SharedState>) -> MutexGuard<'_, SharedState> {
+    shared.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn whole_desktop(image: &DecodedImage) -> Rectangle {
+    Rectangle {
+        left: 0,
+        top: 0,
+        right: image.width(),
+        bottom: image.height(),
+    }
+}
+
+fn take_update(shared: &mut SharedState, id: u64) -> Option<ViewerUpdate> {
+    let viewer = shared.viewers.get_mut(&id)?;
+    let rectangle = viewer.damage.take()?.intersect(&whole_desktop(&shared.image))?;
+    let resized = std::mem::take(&mut viewer.resized);
+
+    let image = &shared.image;
+    let stride = rectangle.width() as usize * usize::from(image.pixel_format().bytes_per_pixel());
+    // the region has been clipped to the desktop
+    let data = image.copy_region(&rectangle, image.pixel_format()).ok()?;
+
+    Some(ViewerUpdate {
+        rectangle,
+        pixel_format: image.pixel_format(),
+        stride,
+        data,
+        desktop_size: (image.width(), image.height()),
+        resized,
+    })
+}
@@ -0,0 +1,85 @@
+use super::*;
+use crate::Codec;
+
+const WIDTH: u32 = 8;
+const HEIGHT: u32 = 4;
+
+fn update(image: &mut SharedImage, rectangle: Rectangle, value: u8) {
+    let data = vec![value; rectangle.width() as usize * rectangle.height() as usize * 4];
+
+    image
+        .update(&ImageUpdate {
+            stride: rectangle.width() as usize * 4,
+            rectangle,
+            pixel_format: PixelFormat::BgrX32,
+            data: &data,
+            codec: Codec::Bitmap,
+        })
+        .unwrap();
+}
+
+fn rectangle(left: u32, top: u32, right: u32, bottom: u32) -> Rectangle {
+    Rectangle {
+        left,
+        top,
+        right,
+        bottom,
+    }
+}
+
+#[test]
+fn first_update_of_viewer_is_whole_desktop() {
+    let image = SharedImage::new(PixelFormat::BgrX32, WIDTH, HEIGHT);
+    let viewer = image.subscribe();
+
+    let update = viewer.take_update().unwrap();
+
+    assert_eq!(rectangle(0, 0, WIDTH, HEIGHT), update.rectangle);
+    assert_eq!((WIDTH * HEIGHT * 4) as usize, update.data.len());
+    assert_eq!(None, viewer.take_update());
+}
+
+#[test]
+fn slow_viewer_gets_union_of_updates_it_skipped() {
+    let mut image = SharedImage::new(PixelFormat::BgrX32, WIDTH, HEIGHT);
+    let fast = image.subscribe();
+    let slow = image.subscribe();
+    fast.take_update();
+    slow.take_update();
+
+    update(&mut image, rectangle(0, 0, 2, 2), 1);
+    assert_eq!(rectangle(0, 0, 2, 2), fast.take_update().unwrap().rectangle);
+    update(&mut image, rectangle(4, 1, 6, 3), 2);
+    assert_eq!(rectangle(4, 1, 6, 3), fast.take_update().unwrap().rectangle);
+
+    let update = slow.take_update().unwrap();
+    assert_eq!(rectangle(0, 0, 6, 3), update.rectangle);
+    assert_eq!(6 * 4, update.stride);
+    assert_eq!(&[1; 8], &update.data[..8]);
+    assert_eq!(&[2; 8], &update.data[update.stride + 16..][..8]);
+}
+
+#[test]
+fn resize_is_reported_to_every_viewer() {
+    let mut image = SharedImage::new(PixelFormat::BgrX32, WIDTH, HEIGHT);
+    let viewer = image.subscribe();
+    viewer.take_update();
+
+    image.resize(16, 8).unwrap();
+
+    let update = viewer.take_update().unwrap();
+    assert!(update.resized);
+    assert_eq!((16, 8), update.desktop_size);
+    assert_eq!(rectangle(0, 0, 16, 8), update.rectangle);
+}
+
+#[test]
+fn dropped_viewer_is_unsubscribed() {
+    let image = SharedImage::new(PixelFormat::BgrX32, WIDTH, HEIGHT);
+    let viewer = image.subscribe();
+    assert_eq!(1, image.viewer_count());
+
+    drop(viewer);
+
+    assert_eq!(0, image.viewer_count());
+}
@@ -1,22 +1,77 @@
 #[macro_use]
 extern crate log;
 
+mod cancellation;
 mod codecs;
+mod diagnostics;
 mod errors;
+mod license_store;
+mod server_certificate;
+mod session_credentials;
 mod utils;
 
 pub mod active_session;
+#[cfg(feature = "alloc-audit")]
+pub mod alloc_audit;
+pub mod bridge;
+pub mod codec_registry;
 pub mod connection_sequence;
+#[cfg(any(feature = "rustls", feature = "native-tls"))]
+pub mod connector;
+pub mod credssp;
+pub mod frame_scheduler;
 pub mod image;
+pub mod server_connection_sequence;
 pub mod transport;
 
-use ironrdp::{gcc, nego};
+use std::net::IpAddr;
+use std::sync::Arc;
 
-pub use crate::active_session::{ActiveStageOutput, ActiveStageProcessor};
-pub use crate::codecs::{ErasedWriter, FramedReader};
-pub use crate::connection_sequence::{process_connection_sequence, ConnectionSequenceResult, UpgradedStream};
-pub use crate::errors::RdpError;
+use ironrdp::codecs::rfx::image_processing::PixelFormat;
+use ironrdp::rdp::session_info::ServerAutoReconnect;
+use ironrdp::rdp::vc::StaticChannelName;
+use ironrdp::rdp::{CompressionType, PerformanceFlags, ServerRedirectionPdu, TimezoneInfo};
+use ironrdp::{gcc, nego, LimitsConfig};
+use zeroize::Zeroize;
 
+pub use crate::active_session::{
+    ActiveStageOutput, ActiveStageProcessor, AudioSink, CardStatus, ChannelEvent, ChannelRegistry, ChannelState,
+    ChannelTraffic, CoalescingConfig, DecodeTiming, DecodedPointer, DomCodeMapper, DrivePolicy, FileHandle,
+    FileOpenOptions, FileSystemBackend, FocusInConfig, FrameAcknowledgePolicy, FrameMetadata, FramePacingConfig,
+    GraphicsFrameEvent, InputBatch, InputEventSender, KeyEvent, LocalDirectory, LowRateInputConfig, Modifiers,
+    PduChannel, PduSummary, PointerCache, PointerChange, PointerEvent, Scancode, ScancodeMapper, ScardBackend,
+    ScardResult, SessionLockState, TrafficCounters, TrafficSnapshot,
+};
+#[cfg(feature = "h264")]
+pub use crate::active_session::{Avc420Decoder, YuvFrame};
+pub use crate::bridge::{
+    BridgeSide, BridgedChunks, ChannelBridge, ClipboardTransfer, ClipboardTransferKind, InputEventKind, PolicyDecision,
+    PolicyEvent, PolicySubject,
+};
+pub use crate::cancellation::{ReadCancellation, ShutdownSignal};
+pub use crate::codec_registry::{Codec, CodecRegistry};
+pub use crate::codecs::{encode_next_frame, ErasedWriter, FramedReader};
+pub use crate::connection_sequence::{
+    continue_connection_sequence, probe_session, process_connection_sequence, ConnectionSequenceResult,
+    EstablishedStream, NegotiatedEncryption, PhaseTimings, ProbeResult, StandardSecurityData, UpgradedStream,
+};
+pub use crate::credssp::{AuthenticationFailure, CredsspClient, CredsspOutput};
+pub use crate::diagnostics::{ConnectionDiagnostics, DiagnosticsRecorder, PduDirection, PduRecord, DEFAULT_MAX_PDUS};
+pub use crate::errors::{ErrorCategory, ErrorCode, PduContext, RdpError};
+pub use crate::frame_scheduler::FrameScheduler;
+pub use crate::license_store::{DirectoryLicenseStore, LicenseKey, LicenseStore, MemoryLicenseStore};
+pub use crate::server_certificate::ServerCertificate;
+pub use crate::server_connection_sequence::{
+    process_server_connection_sequence, ServerConfig, ServerConnectionSequenceResult,
+};
+pub use crate::session_credentials::SessionCredentials;
+
+/// Key of the MCS I/O channel in the joined static channels. Not advertised to the server.
+pub const GLOBAL_CHANNEL_NAME: StaticChannelName = StaticChannelName::from_static("GLOBAL");
+/// Key of the MCS user channel in the joined static channels. Not advertised to the server.
+pub const USER_CHANNEL_NAME: StaticChannelName = StaticChannelName::from_static("USER");
+
+#[derive(Debug, Clone)]
 pub struct GraphicsConfig {
     pub avc444: bool,
     pub h264: bool,
@@ -25,17 +80,202 @@ pub struct GraphicsConfig {
     pub capabilities: u32,
 }
 
+impl GraphicsConfig {
+    /// Disables the parts of the configuration relying on codecs missing from the registry.
+    /// Returns `None` if the Graphics Pipeline itself is not usable.
+    pub fn restricted_to(mut self, codecs: &CodecRegistry) -> Option<Self> {
+        if !codecs.contains(Codec::Zgfx) {
+            return None;
+        }
+
+        if !codecs.contains(Codec::H264) {
+            self.avc444 = false;
+            self.h264 = false;
+        }
+
+        Some(self)
+    }
+}
+
+/// Extended fields of the Client Info PDU, which some VDI stacks use for policy decisions.
+#[derive(Debug, Clone, Default)]
+pub struct ClientInfoConfig {
+    /// Overrides the client address, the routing address is sent by default.
+    /// The address family is derived from it.
+    pub address: Option<IpAddr>,
+    /// Overrides the client directory, the current directory is sent by default.
+    pub dir: Option<String>,
+    /// The program started instead of the shell of the remote session.
+    pub alternate_shell: String,
+    /// The working directory of the remote session.
+    pub work_dir: String,
+    /// The time zone of the client, UTC is sent if only the performance flags are set.
+    pub timezone: Option<TimezoneInfo>,
+    /// The features of the remote session disabled to improve the performance, such as the wallpaper.
+    pub performance_flags: Option<PerformanceFlags>,
+    /// The auto-reconnect cookie of the session to reconnect to, returned by
+    /// [`ActiveStageProcessor::auto_reconnect_cookie`] before the connection has been lost.
+    pub auto_reconnect: Option<ServerAutoReconnect>,
+    /// The highest bulk compression the server may compress the data it sends with, none by default.
+    /// RDP 6.0 and RDP 6.1 are not supported, and are replaced with MPPC 64K.
+    pub compression_type: Option<CompressionType>,
+}
+
+/// The credentials of the Server Redirection PDU, with which the client authenticates to the target server
+/// over RDSTLS, e.g. when redirected by a connection broker. The user name and the domain are those of
+/// [`InputConfig::credentials`]. The password is overwritten with zeros when they are dropped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RedirectionCredentials {
+    /// The redirection GUID, passed as is.
+    pub redirection_guid: Vec<u8>,
+    /// The password, encrypted for the target server and passed as is.
+    pub password: Vec<u8>,
+}
+
+impl Drop for RedirectionCredentials {
+    fn drop(&mut self) {
+        self.password.zeroize();
+    }
+}
+
+/// A monitor of the client, positioned in the virtual desktop spanning all the monitors.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MonitorConfig {
+    /// Position of the upper-left corner, the primary monitor being at the origin.
+    pub left: i32,
+    pub top: i32,
+    pub width: u32,
+    pub height: u32,
+    pub primary: bool,
+    /// Physical size in millimeters, 0 if unknown.
+    pub physical_width: u32,
+    pub physical_height: u32,
+    pub orientation: gcc::MonitorOrientation,
+    /// Scale factor of the desktop in percent, such as 100 or 150.
+    pub scale_factor: u32,
+}
+
+impl MonitorConfig {
+    /// Creates a primary landscape monitor of the given size at the origin, without scaling.
+    pub fn primary(width: u32, height: u32) -> Self {
+        Self {
+            left: 0,
+            top: 0,
+            width,
+            height,
+            primary: true,
+            physical_width: 0,
+            physical_height: 0,
+            orientation: gcc::MonitorOrientation::Landscape,
+            scale_factor: 100,
+        }
+    }
+
+    pub fn with_position(mut self, left: i32, top: i32) -> Self {
+        self.left = left;
+        self.top = top;
+        self
+    }
+
+    pub fn with_scale_factor(mut self, scale_factor: u32) -> Self {
+        self.scale_factor = scale_factor;
+        self
+    }
+
+    /// The closest of the device scale factors accepted by the server, in percent.
+    pub fn device_scale_factor(&self) -> u32 {
+        match self.scale_factor {
+            0..=119 => 100,
+            120..=159 => 140,
+            _ => 180,
+        }
+    }
+}
+
 pub struct InputConfig {
     pub credentials: sspi::AuthIdentity,
     pub security_protocol: nego::SecurityProtocol,
+    pub encryption_methods: gcc::EncryptionMethod,
     pub keyboard_type: gcc::KeyboardType,
     pub keyboard_subtype: u32,
     pub keyboard_functional_keys_count: u32,
     pub ime_file_name: String,
-    pub dig_product_id: String,
+    /// Overrides the client name sent to the server, the host name is used by default.
+    pub client_name: Option<String>,
+    /// Overrides the client build number, derived from the crate version by default.
+    pub client_build: Option<u32>,
+    /// Overrides the dig product ID, derived from the crate name and version by default.
+    pub dig_product_id: Option<String>,
     pub width: u16,
     pub height: u16,
-    pub global_channel_name: String,
-    pub user_channel_name: String,
+    /// Layout of the monitors of the client, sent in the GCC Conference Create Request.
+    /// A single monitor of `width` by `height` is assumed if empty.
+    pub monitors: Vec<MonitorConfig>,
+    pub global_channel_name: StaticChannelName,
+    pub user_channel_name: StaticChannelName,
     pub graphics_config: Option<GraphicsConfig>,
+    /// Codecs advertised to the server, all the compiled-in codecs by default.
+    pub codecs: CodecRegistry,
+    /// Budgets for decoding the MCS Connect Response.
+    pub limits: LimitsConfig,
+    /// Joins the `rdpsnd` channel so that the server redirects the audio output to the client.
+    pub audio_playback: bool,
+    /// Joins the `rdpdr` channel so that the drives added with
+    /// [`ActiveStageProcessor::add_drive`] are redirected to the remote session.
+    pub drive_redirection: bool,
+    /// Joins the `rdpdr` channel so that the smart card readers of the backend set with
+    /// [`ActiveStageProcessor::set_smart_card_backend`] are redirected to the remote session.
+    pub smart_card_redirection: bool,
+    pub client_info: ClientInfoConfig,
+    /// The credentials to authenticate with if the server selects RDSTLS, which is only to be requested
+    /// in [`Self::security_protocol`] when they are set.
+    pub redirection_credentials: Option<RedirectionCredentials>,
+    /// The routing token sent in the X.224 Connection Request instead of the user name,
+    /// for the connection broker to route the connection to the server hosting the session.
+    pub routing_token: Option<String>,
+    /// The ID of the session the client has been redirected to, sent in the Client Cluster Data.
+    pub redirected_session_id: Option<u32>,
+    /// The RD Gateway through which the connection is tunneled by [`connector::connect`], with its own credentials.
+    pub gateway: Option<transport::GatewayConfig>,
+    /// Pixel format of the decoded graphics passed to the image sink, which is best chosen to match
+    /// the format of the embedder's surface, so that the pixels are converted only once while being decoded.
+    /// The desktop being opaque, the alpha formats hold both straight and premultiplied alpha.
+    pub output_pixel_format: PixelFormat,
+    /// Records the course of the connection for a diagnostics bundle, nothing being recorded by default.
+    pub diagnostics: DiagnosticsRecorder,
+    /// Stores the licenses issued by the server, presented again on the next connections.
+    /// A new license is requested on each connection if not set.
+    pub license_store: Option<Arc<dyn LicenseStore>>,
+}
+
+impl InputConfig {
+    /// Updates the configuration with the routing token, the session and the credentials of the
+    /// Server Redirection PDU, carried by [`RdpError::ServerRedirection`], for the connection to the target.
+    /// The credentials which are encrypted for the target are sent over RDSTLS, which is then requested.
+    pub fn apply_redirection(&mut self, redirection: &ServerRedirectionPdu) {
+        self.routing_token = redirection.routing_token();
+        self.redirected_session_id = Some(redirection.session_id);
+
+        if let Some(username) = redirection.username.as_ref() {
+            self.credentials.username.zeroize();
+            self.credentials.username = username.clone();
+        }
+        if let Some(domain) = redirection.domain.as_ref() {
+            self.credentials.domain.zeroize();
+            self.credentials.domain = Some(domain.clone());
+        }
+
+        if let (Some(redirection_guid), Some(password)) = (&redirection.redirection_guid, &redirection.password) {
+            // the previous credentials are zeroized when dropped
+            self.redirection_credentials = Some(RedirectionCredentials {
+                redirection_guid: redirection_guid.clone(),
+                password: password.clone(),
+            });
+            self.security_protocol |= nego::SecurityProtocol::RDSTLS;
+        }
+    }
+
+    fn is_graphics_pipeline_enabled(&self) -> bool {
+        self.graphics_config.is_some() && self.codecs.contains(Codec::Zgfx)
+    }
 }
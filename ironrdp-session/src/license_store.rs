@@ -0,0 +1,141 @@
+//! Storage of the client access licenses issued by the license servers, so that a per-device license
+//! is presented again on the next connections instead of a new one being requested each time.
+
+#[cfg(test)]
+mod tests;
+
+use std::collections::HashMap;
+use std::io;
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard};
+
+use ironrdp::rdp::server_license::{NewLicenseInformation, ServerLicenseRequest};
+
+/// Identifies a license by the product and the scope it has been issued for.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LicenseKey {
+    pub company_name: String,
+    pub product_id: String,
+    pub scope: String,
+}
+
+impl LicenseKey {
+    pub fn from_license_information(license_information: &NewLicenseInformation) -> Self {
+        Self {
+            company_name: license_information.company_name.clone(),
+            product_id: license_information.product_id.clone(),
+            scope: license_information.scope.clone(),
+        }
+    }
+
+    /// The keys of the licenses the server accepts, one for each scope of its license request.
+    pub fn from_license_request(license_request: &ServerLicenseRequest) -> impl Iterator<Item = Self> + '_ {
+        license_request.scope_list.iter().map(|scope| Self {
+            company_name: license_request.product_info.company_name.clone(),
+            product_id: license_request.product_info.product_id.clone(),
+            scope: scope.0.clone(),
+        })
+    }
+}
+
+/// Storage of the licenses, passed in [`InputConfig::license_store`](crate::InputConfig::license_store).
+///
+/// The licenses are bound to the client name, from which the hardware ID is derived,
+/// so a store is only to be shared by the connections using the same client name.
+/// The errors of the store do not fail the connection, a new license being requested instead.
+pub trait LicenseStore: Send + Sync {
+    /// Returns the license stored for the key, `None` if there is none.
+    fn load(&self, key: &LicenseKey) -> io::Result<Option<Vec<u8>>>;
+
+    /// Stores the license issued by the server, replacing the one previously stored for the key.
+    fn store(&self, key: &LicenseKey, license: &[u8]) -> io::Result<()>;
+}
+
+/// Store keeping the licenses in memory, for them to be reused by the connections of the process,
+/// e.g. the reconnections.
+#[derive(Debug, Default)]
+pub struct MemoryLicenseStore {
+    licenses: Mutex<HashMap<LicenseKey, Vec<u8>>>,
+}
+
+impl MemoryLicenseStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    /// A panic while holding the lock does not corrupt the licenses, so the poisoning is ignored.
+    fn lock(&self) -> MutexGuard<'_, HashMap<LicenseKey, Vec<u8>>> {
+        self.licenses.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl LicenseStore for MemoryLicenseStore {
+    fn load(&self, key: &LicenseKey) -> io::Result<Option<Vec<u8>>> {
+        Ok(self.lock().get(key).cloned())
+    }
+
+    fn store(&self, key: &LicenseKey, license: &[u8]) -> io::Result<()> {
+        self.lock().insert(key.clone(), Vec::from(license));
+
+        Ok(())
+    }
+}
+
+/// Store keeping each license in a file of a directory, for the licenses to persist across the runs of the client.
+#[derive(Debug, Clone)]
+pub struct DirectoryLicenseStore {
+    directory: PathBuf,
+}
+
+impl DirectoryLicenseStore {
+    /// Creates a store in the directory, which is created with the first license stored.
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+        }
+    }
+
+    /// The file of the license, named after the key, the characters unsafe in file names being replaced.
+    pub fn license_path(&self, key: &LicenseKey) -> PathBuf {
+        let name = [&key.company_name, &key.product_id, &key.scope]
+            .iter()
+            .map(|part| {
+                part.chars()
+                    .map(|c| {
+                        if c.is_ascii_alphanumeric() || c == '-' || c == '.' {
+                            c
+                        } else {
+                            '_'
+                        }
+                    })
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("_");
+
+        self.directory.join(format!("{}.lic", name))
+    }
+}
+
+impl LicenseStore for DirectoryLicenseStore {
+    fn load(&self, key: &LicenseKey) -> io::Result<Option<Vec<u8>>> {
+        match std::fs::read(self.license_path(key)) {
+            Ok(license) => Ok(Some(license)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn store(&self, key: &LicenseKey, license: &[u8]) -> io::Result<()> {
+        std::fs::create_dir_all(&self.directory)?;
+        std::fs::write(self.license_path(key), license)
+    }
+}
@@ -0,0 +1,53 @@
+use std::path::Path;
+
+use super::*;
+
+fn license_key(scope: &str) -> LicenseKey {
+    LicenseKey {
+        company_name: String::from("Microsoft Corporation"),
+        product_id: String::from("A02"),
+        scope: String::from(scope),
+    }
+}
+
+#[test]
+fn memory_store_replaces_license_of_key() {
+    let store = MemoryLicenseStore::new();
+    assert_eq!(None, store.load(&license_key("microsoft.com")).unwrap());
+
+    store.store(&license_key("microsoft.com"), &[1, 2, 3]).unwrap();
+    store.store(&license_key("microsoft.com"), &[4, 5]).unwrap();
+    store.store(&license_key("contoso.com"), &[6]).unwrap();
+
+    assert_eq!(2, store.len());
+    assert_eq!(Some(vec![4, 5]), store.load(&license_key("microsoft.com")).unwrap());
+    assert_eq!(Some(vec![6]), store.load(&license_key("contoso.com")).unwrap());
+}
+
+#[test]
+fn directory_store_names_file_after_key() {
+    let store = DirectoryLicenseStore::new("licenses");
+
+    assert_eq!(
+        Path::new("licenses").join("Microsoft_Corporation_A02_microsoft.com.lic"),
+        store.license_path(&license_key("microsoft.com"))
+    );
+    assert_eq!(
+        Path::new("licenses").join("Microsoft_Corporation_A02_.._.._etc.lic"),
+        store.license_path(&license_key("../../etc"))
+    );
+}
+
+#[test]
+fn directory_store_persists_licenses() {
+    let directory = std::env::temp_dir().join(format!("ironrdp-license-store-{}", std::process::id()));
+    let store = DirectoryLicenseStore::new(&directory);
+    assert_eq!(None, store.load(&license_key("microsoft.com")).unwrap());
+
+    store.store(&license_key("microsoft.com"), &[1, 2, 3]).unwrap();
+    let reopened = DirectoryLicenseStore::new(&directory);
+    let license = reopened.load(&license_key("microsoft.com"));
+    std::fs::remove_dir_all(&directory).unwrap();
+
+    assert_eq!(Some(vec![1, 2, 3]), license.unwrap());
+}
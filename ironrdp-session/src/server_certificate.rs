@@ -0,0 +1,95 @@
+#[cfg(test)]
+mod tests;
+
+use std::fmt::Write as _;
+
+use chrono::{DateTime, TimeZone as _, Utc};
+use ring::digest;
+use x509_parser::oid_registry::{
+    Oid, OID_PKCS1_SHA384WITHRSA, OID_PKCS1_SHA512WITHRSA, OID_SIG_ECDSA_WITH_SHA384, OID_SIG_ECDSA_WITH_SHA512,
+};
+use x509_parser::prelude::{FromDer as _, X509Certificate};
+
+use crate::RdpError;
+
+const SHA256_FINGERPRINT_SIZE: usize = 32;
+const TLS_SERVER_END_POINT_PREFIX: &[u8] = b"tls-server-end-point:";
+
+/// Details of the certificate presented by the server during the TLS handshake.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerCertificate {
+    pub subject: String,
+    pub issuer: String,
+    pub not_before: DateTime<Utc>,
+    pub not_after: DateTime<Utc>,
+    pub sha256_fingerprint: [u8; SHA256_FINGERPRINT_SIZE],
+    /// The subject public key, which is also bound to the CredSSP exchange, e.g. to be pinned
+    /// with [`TlsVerification::PinnedPublicKey`](crate::connector::TlsVerification::PinnedPublicKey).
+    pub public_key: Vec<u8>,
+    /// The hash of the certificate of the `tls-server-end-point` channel binding (RFC 5929), computed with
+    /// the hash function of its signature algorithm, SHA-256 standing in for MD5 and SHA-1.
+    pub tls_server_end_point: Vec<u8>,
+}
+
+impl ServerCertificate {
+    pub fn from_der(certificate: &[u8]) -> Result<Self, RdpError> {
+        let (_, x509) = X509Certificate::from_der(certificate)
+            .map_err(|e| RdpError::InvalidServerCertificate(format!("invalid DER structure: {}", e)))?;
+
+        let validity = x509.validity();
+        let not_before = asn1_time_to_date_time(validity.not_before.timestamp())?;
+        let not_after = asn1_time_to_date_time(validity.not_after.timestamp())?;
+
+        let mut sha256_fingerprint = [0; SHA256_FINGERPRINT_SIZE];
+        sha256_fingerprint.copy_from_slice(digest::digest(&digest::SHA256, certificate).as_ref());
+
+        let end_point_algorithm = end_point_hash_algorithm(&x509.signature_algorithm.algorithm);
+        let tls_server_end_point = digest::digest(end_point_algorithm, certificate).as_ref().to_vec();
+
+        Ok(Self {
+            subject: x509.subject().to_string(),
+            issuer: x509.issuer().to_string(),
+            not_before,
+            not_after,
+            sha256_fingerprint,
+            public_key: x509.tbs_certificate.subject_pki.subject_public_key.data.to_vec(),
+            tls_server_end_point,
+        })
+    }
+
+    /// Returns the application data of the `tls-server-end-point` channel bindings, which binds the authentication
+    /// to the TLS connection, as required by the servers enforcing Extended Protection for Authentication.
+    pub fn channel_binding_token(&self) -> Vec<u8> {
+        [TLS_SERVER_END_POINT_PREFIX, &self.tls_server_end_point].concat()
+    }
+
+    /// Returns the fingerprint in the usual `AB:CD:...` notation.
+    pub fn sha256_fingerprint_string(&self) -> String {
+        self.sha256_fingerprint
+            .iter()
+            .fold(String::new(), |mut fingerprint, byte| {
+                if !fingerprint.is_empty() {
+                    fingerprint.push(':');
+                }
+                let _ = write!(fingerprint, "{:02X}", byte);
+
+                fingerprint
+            })
+    }
+}
+
+fn end_point_hash_algorithm(signature_algorithm: &Oid<'_>) -> &'static digest::Algorithm {
+    if *signature_algorithm == OID_PKCS1_SHA384WITHRSA || *signature_algorithm == OID_SIG_ECDSA_WITH_SHA384 {
+        &digest::SHA384
+    } else if *signature_algorithm == OID_PKCS1_SHA512WITHRSA || *signature_algorithm == OID_SIG_ECDSA_WITH_SHA512 {
+        &digest::SHA512
+    } else {
+        &digest::SHA256
+    }
+}
+
+fn asn1_time_to_date_time(timestamp: i64) -> Result<DateTime<Utc>, RdpError> {
+    Utc.timestamp_opt(timestamp, 0)
+        .single()
+        .ok_or_else(|| RdpError::InvalidServerCertificate(format!("invalid validity time: {}", timestamp)))
+}
@@ -0,0 +1,463 @@
+use std::future::Future;
+use std::iter;
+
+use bytes::{BufMut as _, BytesMut};
+use futures_util::{AsyncRead, AsyncReadExt as _, AsyncWrite, AsyncWriteExt as _};
+use ironrdp::consts::{DEFAULT_USER_CHANNEL_ID, IO_CHANNEL_ID};
+use ironrdp::gcc::conference_create::ConferenceCreateResponse;
+use ironrdp::gcc::{
+    RdpVersion, ServerCoreData, ServerCoreOptionalData, ServerGccBlocks, ServerNetworkData, ServerSecurityData,
+};
+use ironrdp::mcs::{AttachUserConfirmPdu, ChannelJoinConfirmPdu, DomainParameters, SendDataContext};
+use ironrdp::rdp::capability_sets::CapabilitySet;
+use ironrdp::rdp::server_license::InitialServerLicenseMessage;
+use ironrdp::rdp::vc::StaticChannelName;
+use ironrdp::rdp::{
+    ClientInfo, ClientInfoPdu, CompressionFlags, CompressionType, ControlAction, ControlPdu, FontPdu, SequenceFlags,
+    ShareControlHeader, ShareControlPdu, ShareDataHeader, ShareDataPdu, StreamPriority, SynchronizePdu,
+    SERVER_CHANNEL_ID,
+};
+use ironrdp::server::CapabilitiesPreset;
+use ironrdp::{nego, ChannelId, ConnectInitial, ConnectResponse, McsPdu, PduParsing, UserId};
+
+use crate::codecs::{encode_next_frame, ErasedWriter, FramedReader};
+use crate::connection_sequence::StaticChannels;
+use crate::transport::{DataTransport, Decoder as _, Encoder as _, McsTransport, X224DataTransport};
+use crate::RdpError;
+
+const STATIC_CHANNELS_START_ID: u16 = DEFAULT_USER_CHANNEL_ID + 1;
+const SHARE_ID: u32 = 0x0001_03ea;
+const FONT_MAP_ENTRY_SIZE: u16 = 4;
+
+/// Configuration of the server side of the connection sequence.
+///
+/// Only TLS is accepted as security protocol: CredSSP and the Standard RDP Security are not
+/// implemented for the server role, so the clients requiring NLA are refused.
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    pub desktop_width: u16,
+    pub desktop_height: u16,
+    /// Capability sets advertised in the Demand Active PDU.
+    pub capabilities: CapabilitiesPreset,
+}
+
+pub struct ServerConnectionSequenceResult {
+    /// The security protocols requested by the client in the X.224 Connection Request.
+    pub requested_protocol: nego::SecurityProtocol,
+    /// The static channels requested by the client, with the IDs assigned by the server.
+    pub static_channels: StaticChannels,
+    pub io_channel_id: ChannelId,
+    pub user_channel_id: UserId,
+    pub client_info: ClientInfo,
+    /// The capability sets of the Client Confirm Active PDU.
+    pub client_capability_sets: Vec<CapabilitySet>,
+}
+
+/// Accepts an incoming connection, going through the negotiation, the security upgrade, the MCS connect,
+/// the licensing, the capabilities exchange and the finalization, so that the server can start sending
+/// the graphics updates.
+///
+/// The licensing is skipped by reporting the client as already licensed.
+pub async fn process_server_connection_sequence<S, UpgradeFn, FnRes, UpgradedS>(
+    stream: S,
+    config: &ServerConfig,
+    upgrade_stream: UpgradeFn,
+) -> Result<(ServerConnectionSequenceResult, FramedReader, ErasedWriter), RdpError>
+where
+    S: AsyncRead + AsyncWrite + Unpin + 'static,
+    UpgradeFn: FnOnce(S) -> FnRes,
+    FnRes: Future<Output = Result<UpgradedS, RdpError>>,
+    UpgradedS: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let (reader, mut writer) = stream.split();
+    let mut reader = FramedReader::new(reader);
+
+    let requested_protocol = accept_negotiation(&mut reader, &mut writer).await?;
+
+    let (reader, leftover) = reader.into_inner();
+    let stream = reader.reunite(writer).unwrap();
+
+    debug_assert_eq!(leftover.len(), 0, "no leftover is expected after initial negotiation");
+
+    let stream = upgrade_stream(stream).await?;
+
+    let (reader, writer) = stream.split();
+    let mut reader = FramedReader::new(reader).into_erased();
+    let mut writer = Box::pin(writer) as ErasedWriter;
+
+    let static_channels = accept_mcs_connect(&mut reader, &mut writer, requested_protocol).await?;
+    debug!("Assigned static channels: {:?}", static_channels);
+    accept_mcs(&mut reader, &mut writer, &static_channels).await?;
+
+    let client_info_pdu = read_send_data_request(&mut reader).await?;
+    let client_info = ClientInfoPdu::from_buffer(client_info_pdu.as_ref())
+        .map_err(RdpError::ClientInfoError)?
+        .client_info;
+    debug!("Got Client Info PDU: {:?}", client_info);
+
+    let mut license = Vec::new();
+    InitialServerLicenseMessage::new_status_valid_client_message().to_buffer(&mut license)?;
+    debug!("Send Server License Error PDU - Valid Client");
+    write_send_data_indication(&mut writer, license).await?;
+
+    let client_capability_sets = exchange_capability_sets(&mut reader, &mut writer, config).await?;
+    accept_finalization(&mut reader, &mut writer).await?;
+
+    Ok((
+        ServerConnectionSequenceResult {
+            requested_protocol,
+            static_channels,
+            io_channel_id: ChannelId(IO_CHANNEL_ID),
+            user_channel_id: UserId(DEFAULT_USER_CHANNEL_ID),
+            client_info,
+            client_capability_sets,
+        },
+        reader,
+        writer,
+    ))
+}
+
+async fn accept_negotiation<R: AsyncRead + Unpin, W: AsyncWrite + Unpin>(
+    reader: &mut FramedReader<R>,
+    mut writer: W,
+) -> Result<nego::SecurityProtocol, RdpError> {
+    let frame = reader
+        .read_frame()
+        .await?
+        .ok_or(RdpError::UnexpectedStreamTermination)?;
+    let connection_request = nego::Request::from_buffer(frame.as_ref())?;
+    debug!("Got X.224 Connection Request PDU: {:?}", connection_request);
+
+    let response = if connection_request.protocol.contains(nego::SecurityProtocol::SSL) {
+        nego::ResponseData::Response {
+            flags: nego::ResponseFlags::empty(),
+            protocol: nego::SecurityProtocol::SSL,
+        }
+    } else {
+        nego::ResponseData::Failure {
+            code: nego::FailureCode::SSLRequiredByServer,
+        }
+    };
+
+    let connection_confirm = nego::Response {
+        response: Some(response),
+        dst_ref: 0,
+        src_ref: connection_request.src_ref,
+    };
+    debug!("Send X.224 Connection Confirm PDU: {:?}", connection_confirm);
+    let mut buffer = Vec::with_capacity(connection_confirm.buffer_length());
+    connection_confirm.to_buffer(&mut buffer)?;
+    writer.write_all(&buffer).await?;
+    writer.flush().await?;
+
+    if let Some(nego::ResponseData::Failure { .. }) = connection_confirm.response {
+        return Err(RdpError::UnsupportedSecurityProtocol(connection_request.protocol));
+    }
+
+    Ok(connection_request.protocol)
+}
+
+async fn accept_mcs_connect(
+    reader: &mut FramedReader,
+    writer: &mut ErasedWriter,
+    requested_protocol: nego::SecurityProtocol,
+) -> Result<StaticChannels, RdpError> {
+    let frame = reader
+        .read_frame()
+        .await?
+        .ok_or(RdpError::UnexpectedStreamTermination)?;
+    let mut frame = frame.as_ref();
+    ironrdp::Data::from_buffer(&mut frame).map_err(ironrdp::RdpError::X224Error)?;
+    let connect_initial = ConnectInitial::from_buffer(frame)?;
+    debug!("Got MCS Connect Initial PDU: {:?}", connect_initial);
+
+    let channels = connect_initial.channel_names().unwrap_or_default();
+    // the channel IDs are listed in the order of the channels of the request
+    let channel_ids = (STATIC_CHANNELS_START_ID..).take(channels.len()).collect::<Vec<_>>();
+
+    let static_channels = channels
+        .into_iter()
+        .map(|channel| StaticChannelName::new(channel.name))
+        .zip(channel_ids.iter().copied().map(ChannelId))
+        .map(|(name, id)| name.map(|name| (name, id)))
+        .collect::<Result<StaticChannels, _>>()?;
+
+    let connect_response = ConnectResponse {
+        conference_create_response: ConferenceCreateResponse {
+            user_id: DEFAULT_USER_CHANNEL_ID,
+            gcc_blocks: ServerGccBlocks {
+                core: ServerCoreData {
+                    version: RdpVersion::V5_PLUS,
+                    optional_data: ServerCoreOptionalData {
+                        client_requested_protocols: Some(requested_protocol),
+                        early_capability_flags: None,
+                    },
+                },
+                network: ServerNetworkData {
+                    channel_ids,
+                    io_channel: IO_CHANNEL_ID,
+                },
+                // the encryption is provided by TLS
+                security: ServerSecurityData::no_security(),
+                message_channel: None,
+                multi_transport_channel: None,
+            },
+        },
+        called_connect_id: 0,
+        domain_parameters: DomainParameters::target(),
+    };
+    debug!("Send MCS Connect Response PDU: {:?}", connect_response);
+    encode_next_frame(
+        writer,
+        &mut X224DataTransport::<ConnectResponse>::default(),
+        connect_response,
+    )
+    .await?;
+
+    Ok(static_channels)
+}
+
+async fn accept_mcs(
+    reader: &mut FramedReader,
+    writer: &mut ErasedWriter,
+    static_channels: &StaticChannels,
+) -> Result<(), RdpError> {
+    let mut codec = X224DataTransport::<McsPdu>::default();
+
+    match reader.decode_next_frame(&mut codec).await? {
+        McsPdu::ErectDomainRequest(erect_domain_request) => {
+            debug!("Got MCS Erect Domain Request PDU: {:?}", erect_domain_request);
+        }
+        mcs_pdu => return Err(unexpected_mcs_pdu("MCS Erect Domain Request", &mcs_pdu)),
+    }
+
+    match reader.decode_next_frame(&mut codec).await? {
+        McsPdu::AttachUserRequest => {
+            debug!("Got MCS Attach User Request PDU");
+
+            let attach_user_confirm = AttachUserConfirmPdu {
+                initiator_id: UserId(DEFAULT_USER_CHANNEL_ID),
+                result: 0,
+            };
+            debug!("Send MCS Attach User Confirm PDU: {:?}", attach_user_confirm);
+            encode_next_frame(writer, &mut codec, McsPdu::AttachUserConfirm(attach_user_confirm)).await?;
+        }
+        mcs_pdu => return Err(unexpected_mcs_pdu("MCS Attach User Request", &mcs_pdu)),
+    }
+
+    let mut channels_to_join = static_channels
+        .values()
+        .copied()
+        .chain(iter::once(ChannelId(IO_CHANNEL_ID)))
+        .chain(iter::once(UserId(DEFAULT_USER_CHANNEL_ID).channel_id()))
+        .collect::<Vec<_>>();
+
+    while !channels_to_join.is_empty() {
+        let channel_join_request = match reader.decode_next_frame(&mut codec).await? {
+            McsPdu::ChannelJoinRequest(channel_join_request) => channel_join_request,
+            mcs_pdu => return Err(unexpected_mcs_pdu("MCS Channel Join Request", &mcs_pdu)),
+        };
+        debug!("Got MCS Channel Join Request PDU: {:?}", channel_join_request);
+
+        let channel_id = channel_join_request.channel_id;
+        match channels_to_join.iter().position(|&id| id == channel_id) {
+            Some(position) => channels_to_join.swap_remove(position),
+            None => {
+                return Err(RdpError::InvalidChannelIdError(format!(
+                    "the client has requested to join the unknown or already joined channel {}",
+                    channel_id
+                )))
+            }
+        };
+
+        let channel_join_confirm = ChannelJoinConfirmPdu {
+            channel_id,
+            result: 0,
+            initiator_id: channel_join_request.initiator_id,
+            requested_channel_id: channel_id,
+        };
+        debug!("Send MCS Channel Join Confirm PDU: {:?}", channel_join_confirm);
+        encode_next_frame(writer, &mut codec, McsPdu::ChannelJoinConfirm(channel_join_confirm)).await?;
+    }
+
+    Ok(())
+}
+
+async fn exchange_capability_sets(
+    reader: &mut FramedReader,
+    writer: &mut ErasedWriter,
+    config: &ServerConfig,
+) -> Result<Vec<CapabilitySet>, RdpError> {
+    let server_demand_active = config
+        .capabilities
+        .server_demand_active(config.desktop_width, config.desktop_height);
+    debug!("Send Server Demand Active PDU: {:?}", server_demand_active.pdu);
+    write_share_control_pdu(writer, ShareControlPdu::ServerDemandActive(server_demand_active)).await?;
+
+    match read_share_control_pdu(reader).await? {
+        ShareControlPdu::ClientConfirmActive(client_confirm_active) => {
+            debug!("Got Client Confirm Active PDU: {:?}", client_confirm_active.pdu);
+
+            Ok(client_confirm_active.pdu.capability_sets)
+        }
+        share_control_pdu => Err(RdpError::UnexpectedPdu(format!(
+            "Expected Client Confirm Active PDU, got: {:?}",
+            share_control_pdu.as_short_name()
+        ))),
+    }
+}
+
+/// Answers the finalization PDUs of the client, until its Font List PDU.
+async fn accept_finalization(reader: &mut FramedReader, writer: &mut ErasedWriter) -> Result<(), RdpError> {
+    loop {
+        let share_data_pdu = match read_share_control_pdu(reader).await? {
+            ShareControlPdu::Data(share_data_header) => share_data_header.share_data_pdu,
+            share_control_pdu => {
+                return Err(RdpError::UnexpectedPdu(format!(
+                    "Expected Share Data Header, got: {:?}",
+                    share_control_pdu.as_short_name()
+                )))
+            }
+        };
+        debug!("Got Finalization PDU: {:?}", share_data_pdu);
+
+        let (response, finished) = match share_data_pdu {
+            ShareDataPdu::Synchronize(_) => (
+                ShareDataPdu::Synchronize(SynchronizePdu {
+                    target_user_id: DEFAULT_USER_CHANNEL_ID,
+                }),
+                false,
+            ),
+            ShareDataPdu::Control(ControlPdu {
+                action: ControlAction::Cooperate,
+                ..
+            }) => (
+                ShareDataPdu::Control(ControlPdu {
+                    action: ControlAction::Cooperate,
+                    grant_id: 0,
+                    control_id: 0,
+                }),
+                false,
+            ),
+            ShareDataPdu::Control(ControlPdu {
+                action: ControlAction::RequestControl,
+                ..
+            }) => (
+                ShareDataPdu::Control(ControlPdu {
+                    action: ControlAction::GrantedControl,
+                    grant_id: DEFAULT_USER_CHANNEL_ID,
+                    control_id: u32::from(SERVER_CHANNEL_ID),
+                }),
+                false,
+            ),
+            ShareDataPdu::FontList(_) => (
+                ShareDataPdu::FontMap(FontPdu {
+                    number: 0,
+                    total_number: 0,
+                    flags: SequenceFlags::FIRST | SequenceFlags::LAST,
+                    entry_size: FONT_MAP_ENTRY_SIZE,
+                }),
+                true,
+            ),
+            share_data_pdu => {
+                warn!(
+                    "Ignoring unexpected {} PDU during the finalization",
+                    share_data_pdu.as_short_name()
+                );
+                continue;
+            }
+        };
+
+        debug!("Send Finalization PDU: {:?}", response);
+        write_share_control_pdu(
+            writer,
+            ShareControlPdu::Data(ShareDataHeader {
+                share_data_pdu: response,
+                stream_priority: StreamPriority::Medium,
+                compression_flags: CompressionFlags::empty(),
+                compression_type: CompressionType::K8, // ignored if CompressionFlags::empty()
+            }),
+        )
+        .await?;
+
+        if finished {
+            return Ok(());
+        }
+    }
+}
+
+/// Reads a PDU sent by the client on the I/O channel.
+async fn read_send_data_request(reader: &mut FramedReader) -> Result<BytesMut, RdpError> {
+    let mut frame = reader
+        .read_frame()
+        .await?
+        .ok_or(RdpError::UnexpectedStreamTermination)?;
+
+    let mut payload = frame.as_ref();
+    match McsTransport::new(DataTransport::new()).decode(&mut payload)? {
+        McsPdu::SendDataRequest(send_data_context) if send_data_context.channel_id == ChannelId(IO_CHANNEL_ID) => (),
+        McsPdu::SendDataRequest(send_data_context) => {
+            return Err(RdpError::UnexpectedChannel(send_data_context.channel_id));
+        }
+        McsPdu::DisconnectProviderUltimatum(disconnect_reason) => {
+            return Err(RdpError::UnexpectedDisconnection(format!(
+                "Client disconnection reason - {:?}",
+                disconnect_reason
+            )));
+        }
+        mcs_pdu => return Err(unexpected_mcs_pdu("MCS Send Data Request", &mcs_pdu)),
+    }
+
+    let header_length = frame.len() - payload.len();
+
+    Ok(frame.split_off(header_length))
+}
+
+async fn read_share_control_pdu(reader: &mut FramedReader) -> Result<ShareControlPdu, RdpError> {
+    let payload = read_send_data_request(reader).await?;
+    let share_control_header =
+        ShareControlHeader::from_buffer(payload.as_ref()).map_err(RdpError::ShareControlHeaderError)?;
+
+    Ok(share_control_header.share_control_pdu)
+}
+
+/// Sends a PDU to the client on the I/O channel.
+async fn write_send_data_indication(writer: &mut ErasedWriter, pdu: Vec<u8>) -> Result<(), RdpError> {
+    let send_data_context = SendDataContext {
+        initiator_id: UserId(SERVER_CHANNEL_ID),
+        channel_id: ChannelId(IO_CHANNEL_ID),
+        pdu_length: pdu.len(),
+    };
+
+    let mut buffer = BytesMut::new();
+    McsTransport::new(DataTransport::new()).encode(
+        McsTransport::prepare_data_to_encode(McsPdu::SendDataIndication(send_data_context), Some(pdu))?,
+        (&mut buffer).writer(),
+    )?;
+    writer.write_all(&buffer).await?;
+    writer.flush().await?;
+
+    Ok(())
+}
+
+async fn write_share_control_pdu(
+    writer: &mut ErasedWriter,
+    share_control_pdu: ShareControlPdu,
+) -> Result<(), RdpError> {
+    let share_control_header = ShareControlHeader {
+        share_control_pdu,
+        pdu_source: SERVER_CHANNEL_ID,
+        share_id: SHARE_ID,
+    };
+
+    let mut pdu = Vec::with_capacity(share_control_header.buffer_length());
+    share_control_header
+        .to_buffer(&mut pdu)
+        .map_err(RdpError::ShareControlHeaderError)?;
+
+    write_send_data_indication(writer, pdu).await
+}
+
+fn unexpected_mcs_pdu(expected: &str, mcs_pdu: &McsPdu) -> RdpError {
+    RdpError::UnexpectedPdu(format!("Expected {}, got: {:?}", expected, mcs_pdu.as_short_name()))
+}
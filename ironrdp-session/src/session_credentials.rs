@@ -0,0 +1,107 @@
+//! Credentials kept for the lifetime of the session, so that the connections re-authenticating to it,
+//! such as the auto-reconnection, do not have to prompt the user again.
+
+#[cfg(test)]
+mod tests;
+
+use std::{fmt, mem};
+
+use zeroize::Zeroize;
+
+use crate::{InputConfig, RedirectionCredentials};
+
+/// The credentials the session has been authenticated with, e.g. over CredSSP or RDSTLS,
+/// taken from the [`InputConfig`] passed to [`ActiveStageProcessor::new`](crate::ActiveStageProcessor::new).
+///
+/// They are only held in this structure, which is not cloneable, and overwritten with zeros when it is dropped
+/// or cleared with [`Self::clear`]. The copies made by the connection sequence are not.
+pub struct SessionCredentials {
+    identity: Option<sspi::AuthIdentity>,
+    redirection_credentials: Option<RedirectionCredentials>,
+}
+
+impl SessionCredentials {
+    pub fn new(identity: sspi::AuthIdentity, redirection_credentials: Option<RedirectionCredentials>) -> Self {
+        Self {
+            identity: Some(identity),
+            redirection_credentials,
+        }
+    }
+
+    pub(crate) fn cleared() -> Self {
+        Self {
+            identity: None,
+            redirection_credentials: None,
+        }
+    }
+
+    /// Takes the credentials of the configuration, which are left empty.
+    pub fn take_from(config: &mut InputConfig) -> Self {
+        let identity = sspi::AuthIdentity {
+            username: mem::take(&mut config.credentials.username),
+            password: mem::take(&mut config.credentials.password),
+            domain: config.credentials.domain.take(),
+        };
+
+        Self::new(identity, config.redirection_credentials.take())
+    }
+
+    /// The credentials, unless they have been cleared.
+    pub fn identity(&self) -> Option<&sspi::AuthIdentity> {
+        self.identity.as_ref()
+    }
+
+    pub fn is_cleared(&self) -> bool {
+        self.identity.is_none()
+    }
+
+    /// Copies the credentials into the configuration of the connection re-authenticating to the session,
+    /// e.g. before [`connector::reconnect`](crate::connector::reconnect). Returns `false` if they have been cleared.
+    pub fn apply_to(&self, config: &mut InputConfig) -> bool {
+        let identity = match self.identity.as_ref() {
+            Some(identity) => identity,
+            None => return false,
+        };
+
+        zeroize_identity(&mut config.credentials);
+        config.credentials = identity.clone();
+        config.redirection_credentials = self.redirection_credentials.clone();
+
+        true
+    }
+
+    /// Overwrites the credentials with zeros, e.g. at the end of the session.
+    pub fn clear(&mut self) {
+        if let Some(mut identity) = self.identity.take() {
+            zeroize_identity(&mut identity);
+        }
+        // the redirection credentials are zeroized when dropped
+        self.redirection_credentials = None;
+    }
+}
+
+impl Drop for SessionCredentials {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+impl fmt::Debug for SessionCredentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SessionCredentials")
+            .field("username", &self.identity.as_ref().map(|identity| &identity.username))
+            .field(
+                "domain",
+                &self.identity.as_ref().and_then(|identity| identity.domain.as_ref()),
+            )
+            .finish_non_exhaustive()
+    }
+}
+
+/// Overwrites the user name, the password and the domain with zeros, leaving them empty.
+/// The identity of `sspi` does not implement [`Zeroize`] itself.
+pub(crate) fn zeroize_identity(identity: &mut sspi::AuthIdentity) {
+    identity.username.zeroize();
+    identity.password.zeroize();
+    identity.domain.zeroize();
+}
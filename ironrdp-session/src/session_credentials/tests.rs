@@ -0,0 +1,50 @@
+use super::*;
+
+fn credentials() -> SessionCredentials {
+    SessionCredentials::new(
+        sspi::AuthIdentity {
+            username: String::from("user"),
+            password: String::from("secret"),
+            domain: Some(String::from("DOMAIN")),
+        },
+        Some(RedirectionCredentials {
+            redirection_guid: vec![0x01; 16],
+            password: vec![0x02; 32],
+        }),
+    )
+}
+
+#[test]
+fn cleared_credentials_are_no_longer_available() {
+    let mut credentials = credentials();
+    assert_eq!("user", credentials.identity().unwrap().username);
+
+    credentials.clear();
+
+    assert!(credentials.is_cleared());
+    assert!(credentials.identity().is_none());
+    assert!(credentials.redirection_credentials.is_none());
+}
+
+#[test]
+fn debug_output_omits_password() {
+    let output = format!("{:?}", credentials());
+
+    assert!(output.contains("user"));
+    assert!(!output.contains("secret"));
+}
+
+#[test]
+fn zeroized_identity_is_left_empty() {
+    let mut identity = sspi::AuthIdentity {
+        username: String::from("user"),
+        password: String::from("secret"),
+        domain: Some(String::from("DOMAIN")),
+    };
+
+    zeroize_identity(&mut identity);
+
+    assert!(identity.username.is_empty());
+    assert!(identity.password.is_empty());
+    assert_eq!(None, identity.domain);
+}
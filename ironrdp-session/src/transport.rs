@@ -1,17 +1,27 @@
+#[cfg(test)]
+mod tests;
+
+mod async_transport;
 mod channels;
 mod connection;
+mod gateway;
+mod standard_security;
 
 use std::io;
 use std::marker::PhantomData;
 
 use bytes::BytesMut;
+use ironrdp::rdp::bulk::BulkDecompressor;
 use ironrdp::rdp::SERVER_CHANNEL_ID;
-use ironrdp::{PduParsing, RdpPdu};
+use ironrdp::{ChannelId, PduParsing, RdpPdu, UserId};
 
 use crate::RdpError;
 
+pub use self::async_transport::{AsyncDecoder, AsyncEncoder, DecodeFrame, EncodeFrame};
 pub use self::channels::{ChannelIdentificators, DynamicVirtualChannelTransport, StaticVirtualChannelTransport};
-pub use self::connection::{connect, EarlyUserAuthResult, TsRequestTransport};
+pub use self::connection::connect;
+pub use self::gateway::{connect_gateway, GatewayConfig, GatewayStream};
+pub use self::standard_security::{StandardSecurityReader, StandardSecurityWriter};
 
 pub trait Encoder {
     type Item;
@@ -188,7 +198,7 @@ pub struct SendDataContextTransport {
 }
 
 impl SendDataContextTransport {
-    pub fn new(mcs_transport: McsTransport, initiator_id: u16, channel_id: u16) -> Self {
+    pub fn new(mcs_transport: McsTransport, initiator_id: UserId, channel_id: ChannelId) -> Self {
         Self {
             mcs_transport,
             channel_ids: ChannelIdentificators {
@@ -207,6 +217,41 @@ impl SendDataContextTransport {
         self.set_channel_ids(channel_ids);
         self.state = TransportState::Decoded;
     }
+
+    /// Encodes the PDU like [`Encoder::encode`], but writes it to the stream without copying it into intermediate
+    /// buffers, for the PDUs holding secrets, such as the Client Info PDU, not to be left in freed memory.
+    pub fn encode_sensitive(
+        &mut self,
+        send_data_context_pdu: &[u8],
+        mut stream: impl io::Write,
+    ) -> Result<(), RdpError> {
+        let mcs_pdu = self.send_data_request(send_data_context_pdu.len());
+
+        ironrdp::Data::new(mcs_pdu.buffer_length() + send_data_context_pdu.len()).to_buffer(&mut stream)?;
+        mcs_pdu.to_buffer(&mut stream).map_err(RdpError::McsError)?;
+        stream.write_all(send_data_context_pdu)?;
+        stream.flush()?;
+
+        Ok(())
+    }
+
+    /// Returns the length of the frame written by [`Self::encode_sensitive`], for its buffer to be allocated
+    /// at once instead of being reallocated, and its secrets copied, as it grows.
+    pub fn sensitive_frame_length(&self, send_data_context_pdu_length: usize) -> usize {
+        let mcs_pdu_length = self.send_data_request(send_data_context_pdu_length).buffer_length();
+
+        ironrdp::Data::new(mcs_pdu_length + send_data_context_pdu_length).buffer_length()
+            + mcs_pdu_length
+            + send_data_context_pdu_length
+    }
+
+    fn send_data_request(&self, pdu_length: usize) -> ironrdp::McsPdu {
+        ironrdp::McsPdu::SendDataRequest(ironrdp::mcs::SendDataContext {
+            channel_id: self.channel_ids.channel_id,
+            initiator_id: self.channel_ids.initiator_id,
+            pdu_length,
+        })
+    }
 }
 
 impl Default for SendDataContextTransport {
@@ -214,8 +259,8 @@ impl Default for SendDataContextTransport {
         Self {
             mcs_transport: McsTransport::new(DataTransport::default()),
             channel_ids: ChannelIdentificators {
-                initiator_id: 0,
-                channel_id: 0,
+                initiator_id: UserId(0),
+                channel_id: ChannelId(0),
             },
             state: TransportState::ToDecode,
         }
@@ -275,21 +320,33 @@ impl Decoder for SendDataContextTransport {
 }
 
 pub struct ShareControlHeaderTransport {
-    global_channel_id: u16,
+    global_channel_id: ChannelId,
     share_id: u32,
-    pdu_source: u16,
+    pdu_source: UserId,
     send_data_context_transport: SendDataContextTransport,
+    decompressor: BulkDecompressor,
 }
 
 impl ShareControlHeaderTransport {
-    pub fn new(send_data_context_transport: SendDataContextTransport, pdu_source: u16, global_channel_id: u16) -> Self {
+    pub fn new(
+        send_data_context_transport: SendDataContextTransport,
+        pdu_source: UserId,
+        global_channel_id: ChannelId,
+    ) -> Self {
         Self {
             global_channel_id,
             send_data_context_transport,
             pdu_source,
             share_id: 0,
+            decompressor: BulkDecompressor::new(),
         }
     }
+
+    /// Takes the decompressor of the Share Data PDUs compressed by the server, holding the history
+    /// of those decoded, for the PDUs received next with another transport to be decompressed.
+    pub fn into_decompressor(self) -> BulkDecompressor {
+        self.decompressor
+    }
 }
 
 impl Encoder for ShareControlHeaderTransport {
@@ -299,7 +356,7 @@ impl Encoder for ShareControlHeaderTransport {
     fn encode(&mut self, share_control_pdu: Self::Item, mut stream: impl io::Write) -> Result<(), RdpError> {
         let share_control_header = ironrdp::ShareControlHeader {
             share_control_pdu,
-            pdu_source: self.pdu_source,
+            pdu_source: self.pdu_source.0,
             share_id: self.share_id,
         };
 
@@ -325,8 +382,16 @@ impl Decoder for ShareControlHeaderTransport {
             )));
         }
 
+        let mut pdu = Vec::new();
+        stream.read_to_end(&mut pdu)?;
+        if let Some(decompressed) = ironrdp::ShareControlHeader::decompress(&pdu, &mut self.decompressor)
+            .map_err(RdpError::ShareControlHeaderError)?
+        {
+            pdu = decompressed;
+        }
+
         let share_control_header =
-            ironrdp::ShareControlHeader::from_buffer(&mut stream).map_err(RdpError::ShareControlHeaderError)?;
+            ironrdp::ShareControlHeader::from_buffer(pdu.as_slice()).map_err(RdpError::ShareControlHeaderError)?;
         self.share_id = share_control_header.share_id;
 
         if share_control_header.pdu_source != SERVER_CHANNEL_ID {
@@ -346,6 +411,10 @@ impl ShareDataHeaderTransport {
     pub fn new(transport: ShareControlHeaderTransport) -> Self {
         Self(transport)
     }
+
+    pub fn into_decompressor(self) -> BulkDecompressor {
+        self.0.into_decompressor()
+    }
 }
 
 impl Encoder for ShareDataHeaderTransport {
@@ -495,9 +564,7 @@ where
                 &mut stream,
             )
         } else {
-            Err(RdpError::AccessToNonExistingChannelName(
-                "Channel not connected".to_string(),
-            ))
+            Err(RdpError::StaticChannelNotConnected)
         }
     }
 }
@@ -0,0 +1,129 @@
+//! The async variants of the [`Encoder`] and [`Decoder`] traits, encoding to an `AsyncWrite` stream and decoding
+//! the frames of a [`FramedReader`], for the transports to be used from an async runtime without blocking.
+//!
+//! The transports encode and decode whole frames held in memory, so the variants are implemented for all of them:
+//! the encoded frame is written without blocking, and the frame to decode is read without blocking.
+
+#[cfg(test)]
+mod tests;
+
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use bytes::{Buf as _, BufMut as _, BytesMut};
+use futures_util::{ready, AsyncRead, AsyncWrite};
+
+use super::{Decoder, Encoder};
+use crate::codecs::FramedReader;
+use crate::RdpError;
+
+pub trait AsyncEncoder {
+    type Item;
+
+    /// Encodes the item and returns the future writing it to the stream, without flushing it.
+    fn encode_async<'a, W>(&mut self, item: Self::Item, writer: &'a mut W) -> EncodeFrame<'a, W>
+    where
+        W: AsyncWrite + Unpin + ?Sized;
+}
+
+pub trait AsyncDecoder: Sized {
+    type Item;
+
+    /// Returns the future reading the next frame and decoding it, failing if the stream ends first.
+    ///
+    /// The future can be cancelled while the frame is read, the bytes read so far being kept by the reader.
+    fn decode_async<'a, R>(&'a mut self, reader: &'a mut FramedReader<R>) -> DecodeFrame<'a, Self, R>
+    where
+        R: AsyncRead + Unpin;
+}
+
+impl<T> AsyncEncoder for T
+where
+    T: Encoder,
+    T::Error: Into<RdpError>,
+{
+    type Item = T::Item;
+
+    fn encode_async<'a, W>(&mut self, item: Self::Item, writer: &'a mut W) -> EncodeFrame<'a, W>
+    where
+        W: AsyncWrite + Unpin + ?Sized,
+    {
+        let mut frame = BytesMut::new();
+        let error = self.encode(item, (&mut frame).writer()).err().map(Into::into);
+
+        EncodeFrame { writer, frame, error }
+    }
+}
+
+impl<T> AsyncDecoder for T
+where
+    T: Decoder,
+    T::Error: Into<RdpError>,
+{
+    type Item = T::Item;
+
+    fn decode_async<'a, R>(&'a mut self, reader: &'a mut FramedReader<R>) -> DecodeFrame<'a, Self, R>
+    where
+        R: AsyncRead + Unpin,
+    {
+        DecodeFrame { decoder: self, reader }
+    }
+}
+
+/// The future returned by [`AsyncEncoder::encode_async`].
+#[must_use = "futures do nothing unless polled"]
+pub struct EncodeFrame<'a, W: ?Sized> {
+    writer: &'a mut W,
+    frame: BytesMut,
+    /// The encoding error, returned on the first poll.
+    error: Option<RdpError>,
+}
+
+impl<W> Future for EncodeFrame<'_, W>
+where
+    W: AsyncWrite + Unpin + ?Sized,
+{
+    type Output = Result<(), RdpError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        if let Some(error) = this.error.take() {
+            return Poll::Ready(Err(error));
+        }
+
+        while !this.frame.is_empty() {
+            let written = ready!(Pin::new(&mut *this.writer).poll_write(cx, &this.frame))?;
+            if written == 0 {
+                return Poll::Ready(Err(io::Error::from(io::ErrorKind::WriteZero).into()));
+            }
+            this.frame.advance(written);
+        }
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// The future returned by [`AsyncDecoder::decode_async`].
+#[must_use = "futures do nothing unless polled"]
+pub struct DecodeFrame<'a, D, R> {
+    decoder: &'a mut D,
+    reader: &'a mut FramedReader<R>,
+}
+
+impl<D, R> Future for DecodeFrame<'_, D, R>
+where
+    D: Decoder,
+    D::Error: Into<RdpError>,
+    R: AsyncRead + Unpin,
+{
+    type Output = Result<D::Item, RdpError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let frame = ready!(this.reader.poll_read_frame(cx))?.ok_or(RdpError::UnexpectedStreamTermination)?;
+
+        Poll::Ready(this.decoder.decode(&frame[..]).map_err(Into::into))
+    }
+}
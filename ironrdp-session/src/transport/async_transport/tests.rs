@@ -0,0 +1,68 @@
+use futures_util::io::Cursor;
+
+use super::*;
+use crate::transport::DataTransport;
+
+/// Writes a byte at a time, every other write being pending.
+#[derive(Default)]
+struct SlowWriter {
+    written: Vec<u8>,
+    pending: bool,
+}
+
+impl AsyncWrite for SlowWriter {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        self.pending = !self.pending;
+        if self.pending {
+            cx.waker().wake_by_ref();
+
+            return Poll::Pending;
+        }
+
+        self.written.extend_from_slice(&buf[..1]);
+
+        Poll::Ready(Ok(1))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+fn data_frame(data: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::new();
+    DataTransport::new().encode(BytesMut::from(data), &mut frame).unwrap();
+
+    frame
+}
+
+#[tokio::test]
+async fn encoded_frame_is_written_in_pieces() {
+    let mut writer = SlowWriter::default();
+
+    DataTransport::new()
+        .encode_async(BytesMut::from(b"data".as_ref()), &mut writer)
+        .await
+        .unwrap();
+
+    assert_eq!(data_frame(b"data"), writer.written);
+}
+
+#[tokio::test]
+async fn frames_are_decoded_one_at_a_time() {
+    let mut stream = data_frame(b"data");
+    stream.extend(data_frame(b"longer data"));
+    let mut reader = FramedReader::new(Cursor::new(stream));
+    let mut decoder = DataTransport::new();
+
+    assert_eq!(4, decoder.decode_async(&mut reader).await.unwrap());
+    assert_eq!(11, decoder.decode_async(&mut reader).await.unwrap());
+    assert!(matches!(
+        decoder.decode_async(&mut reader).await,
+        Err(RdpError::UnexpectedStreamTermination)
+    ));
+}
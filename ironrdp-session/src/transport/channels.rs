@@ -1,15 +1,16 @@
-use std::io;
+use std::{cmp, io};
 
+use ironrdp::consts::CHANNEL_CHUNK_LENGTH;
 use ironrdp::rdp::vc;
-use ironrdp::PduParsing;
+use ironrdp::{ChannelId, PduParsing, UserId};
 
 use super::{Decoder, Encoder, SendDataContextTransport};
 use crate::RdpError;
 
 #[derive(Copy, Clone, Debug)]
 pub struct ChannelIdentificators {
-    pub initiator_id: u16,
-    pub channel_id: u16,
+    pub initiator_id: UserId,
+    pub channel_id: ChannelId,
 }
 
 #[derive(Clone, Debug)]
@@ -22,8 +23,8 @@ impl StaticVirtualChannelTransport {
     pub fn new(transport: SendDataContextTransport) -> Self {
         Self {
             channel_ids: ChannelIdentificators {
-                channel_id: 0,
-                initiator_id: 0,
+                channel_id: ChannelId(0),
+                initiator_id: UserId(0),
             },
             transport,
         }
@@ -34,23 +35,42 @@ impl Encoder for StaticVirtualChannelTransport {
     type Item = Vec<u8>;
     type Error = RdpError;
 
-    fn encode(&mut self, mut channel_data_buffer: Self::Item, mut stream: impl io::Write) -> Result<(), RdpError> {
-        let channel_header = vc::ChannelPduHeader {
-            total_length: channel_data_buffer.len() as u32,
-            flags: vc::ChannelControlFlags::FLAG_FIRST | vc::ChannelControlFlags::FLAG_LAST,
-        };
-
-        let mut channel_buffer = Vec::with_capacity(channel_header.buffer_length() + channel_data_buffer.len());
-        channel_header.to_buffer(&mut channel_buffer)?;
-        channel_buffer.append(&mut channel_data_buffer);
-
-        self.transport.set_channel_ids(self.channel_ids);
-        self.transport.encode(channel_buffer, &mut stream)
+    /// Splits the messages longer than a chunk, e.g. the data read from a redirected file.
+    fn encode(&mut self, channel_data_buffer: Self::Item, mut stream: impl io::Write) -> Result<(), RdpError> {
+        let total_length = channel_data_buffer.len();
+        let mut offset = 0;
+
+        loop {
+            let end = cmp::min(offset + CHANNEL_CHUNK_LENGTH, total_length);
+            let mut flags = vc::ChannelControlFlags::empty();
+            if offset == 0 {
+                flags |= vc::ChannelControlFlags::FLAG_FIRST;
+            }
+            if end == total_length {
+                flags |= vc::ChannelControlFlags::FLAG_LAST;
+            }
+            let channel_header = vc::ChannelPduHeader {
+                total_length: total_length as u32,
+                flags,
+            };
+
+            let mut channel_buffer = Vec::with_capacity(channel_header.buffer_length() + end - offset);
+            channel_header.to_buffer(&mut channel_buffer)?;
+            channel_buffer.extend_from_slice(&channel_data_buffer[offset..end]);
+
+            self.transport.set_channel_ids(self.channel_ids);
+            self.transport.encode(channel_buffer, &mut stream)?;
+
+            if end == total_length {
+                return Ok(());
+            }
+            offset = end;
+        }
     }
 }
 
 impl Decoder for StaticVirtualChannelTransport {
-    type Item = (u16, usize);
+    type Item = (ChannelId, usize);
     type Error = RdpError;
 
     fn decode(&mut self, mut stream: impl io::Read) -> Result<Self::Item, RdpError> {
@@ -64,11 +84,11 @@ impl Decoder for StaticVirtualChannelTransport {
 
 pub struct DynamicVirtualChannelTransport {
     transport: StaticVirtualChannelTransport,
-    drdynvc_id: u16,
+    drdynvc_id: ChannelId,
 }
 
 impl DynamicVirtualChannelTransport {
-    pub fn new(transport: StaticVirtualChannelTransport, drdynvc_id: u16) -> Self {
+    pub fn new(transport: StaticVirtualChannelTransport, drdynvc_id: ChannelId) -> Self {
         Self { transport, drdynvc_id }
     }
 
@@ -1,74 +1,19 @@
-use bytes::BytesMut;
-use futures_util::{AsyncRead, AsyncReadExt as _, AsyncWrite, AsyncWriteExt as _};
+use futures_util::{AsyncRead, AsyncWrite, AsyncWriteExt as _};
 use ironrdp::{nego, PduParsing};
 use log::debug;
-use sspi::internal::credssp;
 
 use crate::{codecs::FramedReader, RdpError};
 
-const MAX_TS_REQUEST_LENGTH_BUFFER_SIZE: usize = 4;
-
-#[derive(Default)]
-pub struct TsRequestTransport;
-
-impl TsRequestTransport {
-    pub async fn encode(
-        &mut self,
-        ts_request: credssp::TsRequest,
-        mut stream: impl AsyncWrite + Unpin,
-    ) -> Result<(), RdpError> {
-        let mut buf = BytesMut::with_capacity(ts_request.buffer_len() as usize);
-        buf.resize(ts_request.buffer_len() as usize, 0x00);
-
-        ts_request
-            .encode_ts_request(buf.as_mut())
-            .map_err(RdpError::TsRequestError)?;
-
-        stream.write_all(buf.as_ref()).await?;
-        stream.flush().await?;
-
-        Ok(())
-    }
-
-    pub async fn decode(&mut self, mut stream: impl AsyncRead + Unpin) -> Result<credssp::TsRequest, RdpError> {
-        let mut buf = BytesMut::with_capacity(MAX_TS_REQUEST_LENGTH_BUFFER_SIZE);
-        buf.resize(MAX_TS_REQUEST_LENGTH_BUFFER_SIZE, 0x00);
-        stream.read_exact(&mut buf).await?;
-
-        let ts_request_buffer_length = credssp::TsRequest::read_length(buf.as_ref())?;
-        buf.resize(ts_request_buffer_length, 0x00);
-        stream.read_exact(&mut buf[MAX_TS_REQUEST_LENGTH_BUFFER_SIZE..]).await?;
-
-        let ts_request = credssp::TsRequest::from_buffer(buf.as_ref()).map_err(RdpError::TsRequestError)?;
-
-        Ok(ts_request)
-    }
-}
-
-pub struct EarlyUserAuthResult;
-
-impl EarlyUserAuthResult {
-    pub async fn read(mut stream: impl AsyncRead + Unpin) -> Result<credssp::EarlyUserAuthResult, RdpError> {
-        let mut buf = BytesMut::with_capacity(credssp::EARLY_USER_AUTH_RESULT_PDU_SIZE);
-        buf.resize(credssp::EARLY_USER_AUTH_RESULT_PDU_SIZE, 0x00);
-        stream.read_exact(&mut buf).await?;
-        let early_user_auth_result =
-            credssp::EarlyUserAuthResult::from_buffer(buf.as_ref()).map_err(RdpError::EarlyUserAuthResultError)?;
-
-        Ok(early_user_auth_result)
-    }
-}
-
 pub async fn connect<R: AsyncRead + Unpin, W: AsyncWrite + Unpin>(
     reader: &mut FramedReader<R>,
     writer: W,
     security_protocol: nego::SecurityProtocol,
-    username: String,
+    nego_data: nego::NegoData,
 ) -> Result<nego::SecurityProtocol, RdpError> {
     process_negotiation(
         reader,
         writer,
-        Some(nego::NegoData::Cookie(username)),
+        Some(nego_data),
         security_protocol,
         nego::RequestFlags::empty(),
         0,
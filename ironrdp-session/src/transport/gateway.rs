@@ -0,0 +1,577 @@
+//! The HTTP transport of the Remote Desktop Gateway ([MS-TSGU]), tunneling the RDP connection through
+//! a WebSocket established with the gateway, usually reached on port 443 from outside of the corporate network.
+//!
+//! Like the connection sequence, the tunnel is established over any stream, which is to be upgraded to TLS
+//! beforehand. The RDP connection is then established over the returned [`GatewayStream`].
+
+#[cfg(test)]
+mod tests;
+
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use byteorder::{LittleEndian, ReadBytesExt as _, WriteBytesExt as _};
+use bytes::{Buf as _, BufMut as _, BytesMut};
+use futures_util::future::poll_fn;
+use futures_util::{ready, AsyncRead, AsyncReadExt as _, AsyncWrite, AsyncWriteExt as _};
+use ring::rand::{SecureRandom as _, SystemRandom};
+use zeroize::Zeroizing;
+
+use crate::RdpError;
+
+const GATEWAY_PATH: &str = "/remoteDesktopGateway/";
+const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+const MAX_HTTP_RESPONSE_LENGTH: usize = 16 * 1024;
+const MAX_FRAME_LENGTH: u64 = 16 * 1024 * 1024;
+/// The maximum length of the data of the tunnel sent in a single packet.
+const MAX_DATA_LENGTH: usize = 16 * 1024;
+
+const OPCODE_CONTINUATION: u8 = 0x0;
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_BINARY: u8 = 0x2;
+const OPCODE_CLOSE: u8 = 0x8;
+const OPCODE_PING: u8 = 0x9;
+const OPCODE_PONG: u8 = 0xA;
+
+const PACKET_HEADER_LENGTH: usize = 8;
+const PKT_TYPE_HANDSHAKE_REQUEST: u16 = 0x1;
+const PKT_TYPE_HANDSHAKE_RESPONSE: u16 = 0x2;
+const PKT_TYPE_TUNNEL_CREATE: u16 = 0x4;
+const PKT_TYPE_TUNNEL_RESPONSE: u16 = 0x5;
+const PKT_TYPE_TUNNEL_AUTH: u16 = 0x6;
+const PKT_TYPE_TUNNEL_AUTH_RESPONSE: u16 = 0x7;
+const PKT_TYPE_CHANNEL_CREATE: u16 = 0x8;
+const PKT_TYPE_CHANNEL_RESPONSE: u16 = 0x9;
+const PKT_TYPE_DATA: u16 = 0xA;
+const PKT_TYPE_SERVICE_MESSAGE: u16 = 0xB;
+const PKT_TYPE_REAUTH_MESSAGE: u16 = 0xC;
+const PKT_TYPE_KEEPALIVE: u16 = 0xD;
+const PKT_TYPE_CLOSE_CHANNEL: u16 = 0x10;
+const PKT_TYPE_CLOSE_CHANNEL_RESPONSE: u16 = 0x11;
+
+const PROTOCOL_VERSION_MAJOR: u8 = 1;
+const PROTOCOL_VERSION_MINOR: u8 = 0;
+const HTTP_EXTENDED_AUTH_NONE: u16 = 0;
+const HTTP_TUNNEL_REDIR_PROTOCOL_RDP: u16 = 3;
+
+/// The gateway through which the connection to the RDP server is tunneled, see [`connect_gateway`].
+#[derive(Debug, Clone)]
+pub struct GatewayConfig {
+    /// The `<host>:<port>` address of the gateway.
+    pub address: String,
+    /// The credentials authenticating the user to the gateway with the HTTP Basic authentication,
+    /// which are distinct from the ones of the RDP server.
+    pub credentials: sspi::AuthIdentity,
+}
+
+/// Upgrades the stream established with the gateway of `config` to a WebSocket, and creates the tunnel
+/// and its channel to the `server_host` RDP server listening on `server_port`.
+///
+/// `client_name` is the name of the client reported to the gateway.
+pub async fn connect_gateway<S>(
+    mut stream: S,
+    config: &GatewayConfig,
+    server_host: &str,
+    server_port: u16,
+    client_name: &str,
+) -> Result<GatewayStream<S>, RdpError>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let rng = SystemRandom::new();
+
+    let received = upgrade_to_websocket(&mut stream, config, &rng).await?;
+    let mut stream = GatewayStream {
+        stream,
+        received,
+        payload: BytesMut::new(),
+        data: BytesMut::new(),
+        to_send: BytesMut::new(),
+        rng,
+    };
+
+    let mut request = Vec::new();
+    request.write_u8(PROTOCOL_VERSION_MAJOR)?;
+    request.write_u8(PROTOCOL_VERSION_MINOR)?;
+    request.write_u16::<LittleEndian>(0)?; // clientVersion
+    request.write_u16::<LittleEndian>(HTTP_EXTENDED_AUTH_NONE)?;
+    let response = stream
+        .exchange(
+            PKT_TYPE_HANDSHAKE_REQUEST,
+            &request,
+            PKT_TYPE_HANDSHAKE_RESPONSE,
+            "handshake",
+        )
+        .await?;
+    check_error_code(&response, "handshake")?;
+
+    let mut request = Vec::new();
+    request.write_u32::<LittleEndian>(0)?; // capsFlags
+    request.write_u16::<LittleEndian>(0)?; // fieldsPresent
+    request.write_u16::<LittleEndian>(0)?; // reserved
+    let response = stream
+        .exchange(PKT_TYPE_TUNNEL_CREATE, &request, PKT_TYPE_TUNNEL_RESPONSE, "tunnel")
+        .await?;
+    // the status code follows the server version
+    check_error_code(response.get(2..).unwrap_or_default(), "tunnel")?;
+
+    let client_name = encode_unicode(client_name);
+    let mut request = Vec::new();
+    request.write_u16::<LittleEndian>(0)?; // fieldsPresent
+    request.write_u16::<LittleEndian>(client_name.len() as u16)?;
+    request.extend_from_slice(&client_name);
+    let response = stream
+        .exchange(
+            PKT_TYPE_TUNNEL_AUTH,
+            &request,
+            PKT_TYPE_TUNNEL_AUTH_RESPONSE,
+            "tunnel authorization",
+        )
+        .await?;
+    check_error_code(&response, "tunnel authorization")?;
+
+    let server_host = encode_unicode(server_host);
+    let mut request = Vec::new();
+    request.write_u8(1)?; // numResources
+    request.write_u8(0)?; // numAltResources
+    request.write_u16::<LittleEndian>(server_port)?;
+    request.write_u16::<LittleEndian>(HTTP_TUNNEL_REDIR_PROTOCOL_RDP)?;
+    request.write_u16::<LittleEndian>(server_host.len() as u16)?;
+    request.extend_from_slice(&server_host);
+    let response = stream
+        .exchange(PKT_TYPE_CHANNEL_CREATE, &request, PKT_TYPE_CHANNEL_RESPONSE, "channel")
+        .await?;
+    check_error_code(&response, "channel")?;
+
+    debug!("RD Gateway tunnel established with {}", config.address);
+
+    Ok(stream)
+}
+
+/// The channel of the tunnel to the RDP server, the data written to it being sent in the data packets
+/// of the gateway, which are themselves sent in WebSocket frames.
+///
+/// The written data is only sent once the stream is flushed.
+pub struct GatewayStream<S> {
+    stream: S,
+    /// Bytes received from the gateway, not yet decoded as WebSocket frames.
+    received: BytesMut,
+    /// Payload of the WebSocket frames, not yet decoded as gateway packets.
+    payload: BytesMut,
+    /// Data of the tunnel, not yet read.
+    data: BytesMut,
+    /// WebSocket frames not yet sent to the gateway.
+    to_send: BytesMut,
+    rng: SystemRandom,
+}
+
+impl<S> GatewayStream<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    /// Sends the request packet and returns the body of the response, the keep-alive and service messages
+    /// received in the meantime being ignored.
+    async fn exchange(
+        &mut self,
+        request_type: u16,
+        request: &[u8],
+        response_type: u16,
+        name: &'static str,
+    ) -> Result<BytesMut, RdpError> {
+        self.queue_packet(request_type, request)?;
+        poll_fn(|cx| self.poll_send(cx)).await?;
+        self.stream.flush().await?;
+
+        loop {
+            let (packet_type, response) = poll_fn(|cx| self.poll_packet(cx)).await?.ok_or_else(|| {
+                RdpError::GatewayError(format!(
+                    "the gateway closed the connection instead of the {} response",
+                    name
+                ))
+            })?;
+
+            match packet_type {
+                PKT_TYPE_KEEPALIVE | PKT_TYPE_SERVICE_MESSAGE => continue,
+                packet_type if packet_type == response_type => return Ok(response),
+                packet_type => {
+                    return Err(RdpError::GatewayError(format!(
+                        "unexpected packet {:#x} instead of the {} response",
+                        packet_type, name
+                    )))
+                }
+            }
+        }
+    }
+
+    fn queue_packet(&mut self, packet_type: u16, body: &[u8]) -> io::Result<()> {
+        let mut packet = Vec::with_capacity(PACKET_HEADER_LENGTH + body.len());
+        packet.write_u16::<LittleEndian>(packet_type)?;
+        packet.write_u16::<LittleEndian>(0)?; // reserved
+        packet.write_u32::<LittleEndian>((PACKET_HEADER_LENGTH + body.len()) as u32)?;
+        packet.extend_from_slice(body);
+
+        let mut mask = [0; 4];
+        self.rng
+            .fill(&mut mask)
+            .map_err(|_| io::Error::new(io::ErrorKind::Other, "failed to generate the WebSocket mask"))?;
+        encode_frame(OPCODE_BINARY, &packet, Some(mask), &mut self.to_send);
+
+        Ok(())
+    }
+
+    fn poll_send(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while !self.to_send.is_empty() {
+            let written = ready!(Pin::new(&mut self.stream).poll_write(cx, &self.to_send))?;
+            if written == 0 {
+                return Poll::Ready(Err(io::Error::from(io::ErrorKind::WriteZero)));
+            }
+            self.to_send.advance(written);
+        }
+
+        Poll::Ready(Ok(()))
+    }
+
+    /// Returns the type and the body of the next packet, `None` once the gateway has closed the connection.
+    fn poll_packet(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<Option<(u16, BytesMut)>>> {
+        loop {
+            if let Some(packet) = decode_packet(&mut self.payload)? {
+                return Poll::Ready(Ok(Some(packet)));
+            }
+
+            if let Some((opcode, payload)) = decode_frame(&mut self.received)? {
+                match opcode {
+                    OPCODE_BINARY | OPCODE_TEXT | OPCODE_CONTINUATION => self.payload.extend_from_slice(&payload),
+                    OPCODE_PING => {
+                        let mut mask = [0; 4];
+                        self.rng.fill(&mut mask).map_err(|_| {
+                            io::Error::new(io::ErrorKind::Other, "failed to generate the WebSocket mask")
+                        })?;
+                        encode_frame(OPCODE_PONG, &payload, Some(mask), &mut self.to_send);
+                        // the pong is otherwise sent along with the next data
+                        let _ = self.poll_send(cx)?;
+                    }
+                    OPCODE_CLOSE => return Poll::Ready(Ok(None)),
+                    _ => (),
+                }
+
+                continue;
+            }
+
+            let mut buffer = [0; 4096];
+            match ready!(Pin::new(&mut self.stream).poll_read(cx, &mut buffer))? {
+                0 if self.received.is_empty() && self.payload.is_empty() => return Poll::Ready(Ok(None)),
+                0 => return Poll::Ready(Err(io::Error::from(io::ErrorKind::UnexpectedEof))),
+                read => self.received.extend_from_slice(&buffer[..read]),
+            }
+        }
+    }
+}
+
+impl<S> AsyncRead for GatewayStream<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+
+        while this.data.is_empty() {
+            match ready!(this.poll_packet(cx))? {
+                Some((PKT_TYPE_DATA, mut body)) => {
+                    let length = body.as_ref().read_u16::<LittleEndian>()?;
+                    body.advance(2);
+                    let data = body
+                        .get(..usize::from(length))
+                        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "the data packet is truncated"))?;
+                    this.data.extend_from_slice(data);
+                }
+                Some((PKT_TYPE_KEEPALIVE | PKT_TYPE_SERVICE_MESSAGE, _)) => (),
+                Some((PKT_TYPE_REAUTH_MESSAGE, _)) => warn!("The gateway reauthentication is not supported"),
+                Some((PKT_TYPE_CLOSE_CHANNEL | PKT_TYPE_CLOSE_CHANNEL_RESPONSE, _)) | None => {
+                    return Poll::Ready(Ok(0))
+                }
+                Some((packet_type, _)) => {
+                    return Poll::Ready(Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("unexpected gateway packet {:#x}", packet_type),
+                    )))
+                }
+            }
+        }
+
+        let read = buf.len().min(this.data.len());
+        buf[..read].copy_from_slice(&this.data[..read]);
+        this.data.advance(read);
+
+        Poll::Ready(Ok(read))
+    }
+}
+
+impl<S> AsyncWrite for GatewayStream<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        ready!(this.poll_send(cx))?;
+
+        let written = buf.len().min(MAX_DATA_LENGTH);
+        let mut body = Vec::with_capacity(2 + written);
+        body.write_u16::<LittleEndian>(written as u16)?;
+        body.extend_from_slice(&buf[..written]);
+        this.queue_packet(PKT_TYPE_DATA, &body)?;
+
+        Poll::Ready(Ok(written))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_send(cx))?;
+
+        Pin::new(&mut this.stream).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_send(cx))?;
+
+        Pin::new(&mut this.stream).poll_close(cx)
+    }
+}
+
+/// Sends the HTTP upgrade request, and returns the bytes received after the response.
+async fn upgrade_to_websocket<S>(
+    stream: &mut S,
+    config: &GatewayConfig,
+    rng: &SystemRandom,
+) -> Result<BytesMut, RdpError>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut nonce = [0; 16];
+    let mut connection_id = [0; 16];
+    rng.fill(&mut nonce)
+        .and_then(|()| rng.fill(&mut connection_id))
+        .map_err(|_| RdpError::GatewayError(String::from("failed to generate the WebSocket key")))?;
+    let key = base64::encode(nonce);
+
+    let username = match &config.credentials.domain {
+        Some(domain) => format!("{}\\{}", domain, config.credentials.username),
+        None => config.credentials.username.clone(),
+    };
+    // the buffers holding the password are overwritten with zeros once the request has been sent
+    let basic_credentials = Zeroizing::new(format!("{}:{}", username, config.credentials.password));
+    let authorization = Zeroizing::new(base64::encode(basic_credentials.as_bytes()));
+
+    let request = Zeroizing::new(format!(
+        "GET {path} HTTP/1.1\r\n\
+         Host: {host}\r\n\
+         Connection: Upgrade\r\n\
+         Upgrade: websocket\r\n\
+         Sec-WebSocket-Version: 13\r\n\
+         Sec-WebSocket-Key: {key}\r\n\
+         RDG-Connection-Id: {{{connection_id}}}\r\n\
+         Authorization: Basic {authorization}\r\n\
+         Cache-Control: no-cache\r\n\
+         Pragma: no-cache\r\n\
+         \r\n",
+        path = GATEWAY_PATH,
+        host = config.address,
+        key = key,
+        connection_id = format_guid(connection_id),
+        authorization = authorization.as_str(),
+    ));
+    stream.write_all(request.as_bytes()).await?;
+    stream.flush().await?;
+
+    let mut received = BytesMut::new();
+    let headers_length = loop {
+        if let Some(position) = received.windows(4).position(|window| window == b"\r\n\r\n") {
+            break position + 4;
+        }
+        if received.len() > MAX_HTTP_RESPONSE_LENGTH {
+            return Err(RdpError::GatewayError(String::from("the HTTP response is too long")));
+        }
+
+        let mut buffer = [0; 1024];
+        let read = stream.read(&mut buffer).await?;
+        if read == 0 {
+            return Err(RdpError::GatewayError(String::from(
+                "the gateway closed the connection instead of the HTTP response",
+            )));
+        }
+        received.extend_from_slice(&buffer[..read]);
+    };
+
+    let headers = received.split_to(headers_length);
+    let headers = String::from_utf8_lossy(&headers);
+    let mut lines = headers.split("\r\n");
+
+    let status = lines
+        .next()
+        .and_then(|status_line| status_line.split(' ').nth(1))
+        .and_then(|status| status.parse::<u16>().ok())
+        .ok_or_else(|| RdpError::GatewayError(String::from("invalid HTTP status line")))?;
+    if status != 101 {
+        return Err(RdpError::GatewayHttpError(status));
+    }
+
+    let accept = lines
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("Sec-WebSocket-Accept"))
+        .map(|(_, value)| value.trim().to_owned());
+    if accept.as_deref() != Some(websocket_accept(&key).as_str()) {
+        return Err(RdpError::GatewayError(String::from(
+            "invalid Sec-WebSocket-Accept header",
+        )));
+    }
+
+    Ok(received)
+}
+
+fn websocket_accept(key: &str) -> String {
+    let digest = ring::digest::digest(
+        &ring::digest::SHA1_FOR_LEGACY_USE_ONLY,
+        format!("{}{}", key, WEBSOCKET_GUID).as_bytes(),
+    );
+
+    base64::encode(digest.as_ref())
+}
+
+fn encode_frame(opcode: u8, payload: &[u8], mask: Option<[u8; 4]>, buffer: &mut BytesMut) {
+    buffer.put_u8(0x80 | opcode);
+
+    let mask_bit = if mask.is_some() { 0x80 } else { 0 };
+    match payload.len() {
+        length @ 0..=125 => buffer.put_u8(mask_bit | length as u8),
+        length @ 126..=0xFFFF => {
+            buffer.put_u8(mask_bit | 126);
+            buffer.put_u16(length as u16);
+        }
+        length => {
+            buffer.put_u8(mask_bit | 127);
+            buffer.put_u64(length as u64);
+        }
+    }
+
+    match mask {
+        Some(mask) => {
+            buffer.put_slice(&mask);
+            buffer.extend(payload.iter().zip(mask.iter().cycle()).map(|(byte, mask)| byte ^ mask));
+        }
+        None => buffer.put_slice(payload),
+    }
+}
+
+/// Returns the opcode and the unmasked payload of the next frame, `None` if it has not been completely received.
+fn decode_frame(buffer: &mut BytesMut) -> io::Result<Option<(u8, BytesMut)>> {
+    let mut header = buffer.as_ref();
+    if header.len() < 2 {
+        return Ok(None);
+    }
+
+    let opcode = header.get_u8() & 0x0F;
+    let length = header.get_u8();
+    let masked = length & 0x80 != 0;
+    let length = match length & 0x7F {
+        126 if header.len() >= 2 => u64::from(header.get_u16()),
+        127 if header.len() >= 8 => header.get_u64(),
+        126 | 127 => return Ok(None),
+        length => u64::from(length),
+    };
+    if length > MAX_FRAME_LENGTH {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("the WebSocket frame of {} bytes is too long", length),
+        ));
+    }
+    let length = length as usize;
+
+    let mask = if masked {
+        if header.len() < 4 {
+            return Ok(None);
+        }
+        let mut mask = [0; 4];
+        header.copy_to_slice(&mut mask);
+        Some(mask)
+    } else {
+        None
+    };
+
+    if header.len() < length {
+        return Ok(None);
+    }
+
+    let header_length = buffer.len() - header.len();
+    buffer.advance(header_length);
+    let mut payload = buffer.split_to(length);
+    if let Some(mask) = mask {
+        payload
+            .iter_mut()
+            .zip(mask.iter().cycle())
+            .for_each(|(byte, mask)| *byte ^= mask);
+    }
+
+    Ok(Some((opcode, payload)))
+}
+
+/// Returns the type and the body of the next packet, `None` if it has not been completely received.
+fn decode_packet(buffer: &mut BytesMut) -> io::Result<Option<(u16, BytesMut)>> {
+    if buffer.len() < PACKET_HEADER_LENGTH {
+        return Ok(None);
+    }
+
+    let mut header = &buffer[..PACKET_HEADER_LENGTH];
+    let packet_type = header.read_u16::<LittleEndian>()?;
+    let _reserved = header.read_u16::<LittleEndian>()?;
+    let packet_length = header.read_u32::<LittleEndian>()? as usize;
+    if packet_length < PACKET_HEADER_LENGTH {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("invalid gateway packet length: {}", packet_length),
+        ));
+    }
+    if buffer.len() < packet_length {
+        return Ok(None);
+    }
+
+    let mut packet = buffer.split_to(packet_length);
+    packet.advance(PACKET_HEADER_LENGTH);
+
+    Ok(Some((packet_type, packet)))
+}
+
+fn check_error_code(mut response: &[u8], name: &'static str) -> Result<(), RdpError> {
+    let error_code = response
+        .read_u32::<LittleEndian>()
+        .map_err(|_| RdpError::GatewayError(format!("the {} response is truncated", name)))?;
+
+    match error_code {
+        0 => Ok(()),
+        error_code => Err(RdpError::GatewayRequestRejected {
+            request: name,
+            error_code,
+        }),
+    }
+}
+
+/// Encodes the string in UTF-16 with the null terminator.
+fn encode_unicode(s: &str) -> Vec<u8> {
+    s.encode_utf16()
+        .chain(std::iter::once(0))
+        .flat_map(|c| c.to_le_bytes())
+        .collect()
+}
+
+fn format_guid(bytes: [u8; 16]) -> String {
+    let hex = bytes.iter().map(|byte| format!("{:02X}", byte)).collect::<String>();
+
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
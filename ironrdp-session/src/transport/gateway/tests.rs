@@ -0,0 +1,165 @@
+use std::io::{Read as _, Write as _};
+use std::net::{TcpListener, TcpStream};
+
+use byteorder::WriteBytesExt as _;
+use futures_util::{AsyncReadExt as _, AsyncWriteExt as _};
+use tokio_util::compat::TokioAsyncReadCompatExt as _;
+
+use super::*;
+
+fn packet(packet_type: u16, body: &[u8]) -> Vec<u8> {
+    let mut packet = Vec::new();
+    packet.write_u16::<LittleEndian>(packet_type).unwrap();
+    packet.write_u16::<LittleEndian>(0).unwrap();
+    packet
+        .write_u32::<LittleEndian>((PACKET_HEADER_LENGTH + body.len()) as u32)
+        .unwrap();
+    packet.extend_from_slice(body);
+
+    packet
+}
+
+fn send_frame(stream: &mut TcpStream, payload: &[u8]) {
+    let mut frame = BytesMut::new();
+    encode_frame(OPCODE_BINARY, payload, None, &mut frame);
+    stream.write_all(&frame).unwrap();
+}
+
+/// Answers the requests of the client, and echoes the data preceded by a keep-alive packet,
+/// the data packet being split across two frames. Returns the authorization header of the upgrade request.
+fn run_gateway(listener: TcpListener) -> String {
+    let (mut stream, _) = listener.accept().unwrap();
+
+    let mut request = Vec::new();
+    while !request.ends_with(b"\r\n\r\n") {
+        let mut byte = [0];
+        stream.read_exact(&mut byte).unwrap();
+        request.push(byte[0]);
+    }
+    let request = String::from_utf8(request).unwrap();
+    let header = |name: &str| {
+        request
+            .lines()
+            .find_map(|line| line.strip_prefix(name))
+            .unwrap()
+            .trim()
+            .to_owned()
+    };
+    write!(
+        stream,
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+        websocket_accept(&header("Sec-WebSocket-Key:"))
+    )
+    .unwrap();
+
+    let mut received = BytesMut::new();
+    let mut payload = BytesMut::new();
+    loop {
+        let (packet_type, body) = loop {
+            if let Some(packet) = decode_packet(&mut payload).unwrap() {
+                break packet;
+            }
+            if let Some((_, frame)) = decode_frame(&mut received).unwrap() {
+                payload.extend_from_slice(&frame);
+                continue;
+            }
+
+            let mut buffer = [0; 1024];
+            match stream.read(&mut buffer).unwrap() {
+                0 => return header("Authorization:"),
+                read => received.extend_from_slice(&buffer[..read]),
+            }
+        };
+
+        match packet_type {
+            PKT_TYPE_HANDSHAKE_REQUEST => send_frame(
+                &mut stream,
+                &packet(PKT_TYPE_HANDSHAKE_RESPONSE, &[0, 0, 0, 0, 1, 0, 0, 0, 0, 0]),
+            ),
+            PKT_TYPE_TUNNEL_CREATE => send_frame(
+                &mut stream,
+                &packet(PKT_TYPE_TUNNEL_RESPONSE, &[0, 0, 0, 0, 0, 0, 0, 0, 0, 0]),
+            ),
+            PKT_TYPE_TUNNEL_AUTH => send_frame(&mut stream, &packet(PKT_TYPE_TUNNEL_AUTH_RESPONSE, &[0; 8])),
+            PKT_TYPE_CHANNEL_CREATE => {
+                assert_eq!(3389u16.to_le_bytes(), body[2..4]);
+                assert_eq!(encode_unicode("server.example"), body[8..]);
+                send_frame(&mut stream, &packet(PKT_TYPE_CHANNEL_RESPONSE, &[0; 8]));
+            }
+            PKT_TYPE_DATA => {
+                let mut echo = packet(PKT_TYPE_KEEPALIVE, &[]);
+                echo.extend_from_slice(&packet(PKT_TYPE_DATA, &body));
+                let (first, second) = echo.split_at(echo.len() - 3);
+                send_frame(&mut stream, first);
+                send_frame(&mut stream, second);
+            }
+            packet_type => panic!("unexpected packet {:#x}", packet_type),
+        }
+    }
+}
+
+#[test]
+fn websocket_accept_is_derived_from_key() {
+    assert_eq!(
+        "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=",
+        websocket_accept("dGhlIHNhbXBsZSBub25jZQ==")
+    );
+}
+
+#[test]
+fn masked_frame_is_decoded_once_completely_received() {
+    let payload = vec![0xAB; 300];
+    let mut frame = BytesMut::new();
+    encode_frame(OPCODE_BINARY, &payload, Some([1, 2, 3, 4]), &mut frame);
+
+    assert_eq!([0x82, 0x80 | 126, 0x01, 0x2C], frame[..4]);
+    assert_ne!(payload, frame[8..]);
+
+    let mut received = BytesMut::from(&frame[..frame.len() - 1]);
+    assert!(decode_frame(&mut received).unwrap().is_none());
+
+    received.extend_from_slice(&frame[frame.len() - 1..]);
+    let (opcode, decoded) = decode_frame(&mut received).unwrap().unwrap();
+    assert_eq!(OPCODE_BINARY, opcode);
+    assert_eq!(payload, decoded);
+    assert!(received.is_empty());
+}
+
+#[test]
+fn packet_shorter_than_its_header_is_rejected() {
+    let mut payload = BytesMut::from(&[0x0A, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00][..]);
+
+    assert!(decode_packet(&mut payload).is_err());
+}
+
+#[tokio::test]
+async fn data_is_tunneled_through_gateway() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let gateway = std::thread::spawn(move || run_gateway(listener));
+
+    let config = GatewayConfig {
+        address: address.clone(),
+        credentials: sspi::AuthIdentity {
+            username: String::from("user"),
+            password: String::from("password"),
+            domain: Some(String::from("DOMAIN")),
+        },
+    };
+    let stream = tokio::net::TcpStream::connect(address.as_str()).await.unwrap().compat();
+    let mut tunnel = connect_gateway(stream, &config, "server.example", 3389, "client")
+        .await
+        .unwrap();
+
+    tunnel.write_all(b"hello").await.unwrap();
+    tunnel.flush().await.unwrap();
+    let mut echo = [0; 5];
+    tunnel.read_exact(&mut echo).await.unwrap();
+    assert_eq!(b"hello", &echo);
+
+    tunnel.close().await.unwrap();
+    assert_eq!(
+        format!("Basic {}", base64::encode("DOMAIN\\user:password")),
+        gateway.join().unwrap()
+    );
+}
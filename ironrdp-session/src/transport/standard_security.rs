@@ -0,0 +1,360 @@
+//! The Standard RDP Security layer, encrypting and signing the frames of the stream once the Client Security
+//! Exchange PDU has been sent, when the server has selected the RDP security protocol instead of TLS.
+//!
+//! The user data of the MCS Send Data PDUs is prefixed with a security header carrying its signature,
+//! and the Fast-Path PDUs carry their signature after their length. The other frames are passed as is.
+
+#[cfg(test)]
+mod tests;
+
+use std::io;
+use std::mem;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use bytes::{Buf as _, BytesMut};
+use futures_util::{ready, AsyncRead, AsyncWrite};
+use ironrdp::fast_path::EncryptionFlags;
+use ironrdp::rdp::standard_security::{SessionCipher, NON_FIPS_SECURITY_HEADER_SIZE, SIGNATURE_SIZE};
+use ironrdp::rdp::{BasicSecurityHeader, BasicSecurityHeaderFlags, BASIC_SECURITY_HEADER_SIZE};
+use ironrdp::{Action, McsPdu, PduParsing, SendDataContext};
+
+use crate::codecs::{decode_frame, frame_length};
+
+const FAST_PATH_FLAGS_SHIFT: u8 = 6;
+const FAST_PATH_SHORT_LENGTH_MAX: usize = 0x7f;
+
+/// Decrypts the frames received from the server, the security headers being removed from the MCS Send Data
+/// Indication PDUs but those of the licensing PDUs, which are parsed with their header.
+pub struct StandardSecurityReader<R> {
+    reader: R,
+    cipher: SessionCipher,
+    buf: BytesMut,
+    decrypted: BytesMut,
+}
+
+impl<R> StandardSecurityReader<R> {
+    /// `leftover` holds the bytes already read from the reader, e.g. returned by
+    /// [`FramedReader::into_inner`](crate::FramedReader::into_inner).
+    pub fn new(reader: R, cipher: SessionCipher, leftover: BytesMut) -> Self {
+        Self {
+            reader,
+            cipher,
+            buf: leftover,
+            decrypted: BytesMut::new(),
+        }
+    }
+}
+
+impl<R> AsyncRead for StandardSecurityReader<R>
+where
+    R: AsyncRead + Unpin,
+{
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+
+        loop {
+            if !this.decrypted.is_empty() {
+                let len = buf.len().min(this.decrypted.len());
+                buf[..len].copy_from_slice(&this.decrypted[..len]);
+                this.decrypted.advance(len);
+
+                return Poll::Ready(Ok(len));
+            }
+
+            if let Some(frame) = decode_frame(&mut this.buf, usize::MAX).map_err(invalid_data)? {
+                let decrypted = decrypt_frame(&mut this.cipher, &frame)?;
+                this.decrypted.extend_from_slice(&decrypted);
+
+                continue;
+            }
+
+            let mut read_bytes = [0; 1024];
+            let len = ready!(Pin::new(&mut this.reader).poll_read(cx, &mut read_bytes))?;
+            if len == 0 {
+                if this.buf.is_empty() {
+                    return Poll::Ready(Ok(0));
+                }
+
+                // the incomplete frame is left to the reader of the decrypted frames to report
+                this.decrypted = this.buf.split();
+            } else {
+                this.buf.extend_from_slice(&read_bytes[..len]);
+            }
+        }
+    }
+}
+
+/// Encrypts the frames sent to the server.
+///
+/// A frame is written to the underlying writer as soon as it is complete, its last byte being reported written
+/// once the encrypted frame has been written, so that the frames are sent without the writer being flushed.
+pub struct StandardSecurityWriter<W> {
+    writer: W,
+    cipher: SessionCipher,
+    pending: Vec<u8>,
+    encrypted: BytesMut,
+    holds_last_byte: bool,
+}
+
+impl<W> StandardSecurityWriter<W> {
+    pub fn new(writer: W, cipher: SessionCipher) -> Self {
+        Self {
+            writer,
+            cipher,
+            pending: Vec::new(),
+            encrypted: BytesMut::new(),
+            holds_last_byte: false,
+        }
+    }
+}
+
+impl<W> StandardSecurityWriter<W>
+where
+    W: AsyncWrite + Unpin,
+{
+    fn poll_write_encrypted(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while !self.encrypted.is_empty() {
+            let len = ready!(Pin::new(&mut self.writer).poll_write(cx, &self.encrypted))?;
+            if len == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.encrypted.advance(len);
+        }
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<W> AsyncWrite for StandardSecurityWriter<W>
+where
+    W: AsyncWrite + Unpin,
+{
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+
+        if !this.encrypted.is_empty() {
+            ready!(this.poll_write_encrypted(cx))?;
+
+            if mem::take(&mut this.holds_last_byte) {
+                return Poll::Ready(Ok(1));
+            }
+        }
+
+        let length = match frame_length(&this.pending).map_err(invalid_data)? {
+            Some(length) if length > this.pending.len() => length,
+            Some(length) => {
+                return Poll::Ready(Err(invalid_data(format!("invalid frame length: {}", length))));
+            }
+            // the header is completed byte after byte
+            None => {
+                this.pending.push(buf[0]);
+
+                return Poll::Ready(Ok(1));
+            }
+        };
+
+        let needed = length - this.pending.len();
+        if buf.len() < needed {
+            this.pending.extend_from_slice(buf);
+
+            return Poll::Ready(Ok(buf.len()));
+        }
+
+        this.pending.extend_from_slice(&buf[..needed]);
+        let frame = mem::take(&mut this.pending);
+        let encrypted = encrypt_frame(&mut this.cipher, &frame)?;
+        this.encrypted.extend_from_slice(&encrypted);
+
+        match this.poll_write_encrypted(cx)? {
+            Poll::Ready(()) => Poll::Ready(Ok(needed)),
+            Poll::Pending => {
+                this.holds_last_byte = true;
+
+                if needed > 1 {
+                    Poll::Ready(Ok(needed - 1))
+                } else {
+                    Poll::Pending
+                }
+            }
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_write_encrypted(cx))?;
+
+        Pin::new(&mut this.writer).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_write_encrypted(cx))?;
+
+        Pin::new(&mut this.writer).poll_close(cx)
+    }
+}
+
+/// Encrypts the user data of the MCS Send Data Request PDU or the events of the Fast-Path input PDU.
+pub(crate) fn encrypt_frame(cipher: &mut SessionCipher, frame: &[u8]) -> io::Result<Vec<u8>> {
+    match frame_action(frame)? {
+        Action::X224 => {
+            let (context, user_data) = match read_send_data(frame)? {
+                Some((McsPdu::SendDataRequest(context), user_data)) => (context, user_data),
+                _ => return Ok(frame.to_vec()),
+            };
+
+            let (signature, encrypted) = cipher.encrypt(user_data);
+            let mut payload = Vec::with_capacity(NON_FIPS_SECURITY_HEADER_SIZE + encrypted.len());
+            BasicSecurityHeader {
+                flags: BasicSecurityHeaderFlags::ENCRYPT,
+            }
+            .to_buffer(&mut payload)
+            .map_err(invalid_data)?;
+            payload.extend_from_slice(&signature);
+            payload.extend_from_slice(&encrypted);
+
+            write_send_data(McsPdu::SendDataRequest, context, &payload)
+        }
+        Action::FastPath => {
+            let (header, body) = split_fast_path(frame)?;
+            let (signature, encrypted) = cipher.encrypt(body);
+            let flags = EncryptionFlags::ENCRYPTED.bits() << FAST_PATH_FLAGS_SHIFT;
+
+            Ok(write_fast_path(
+                header | flags,
+                &[signature.as_ref(), &encrypted].concat(),
+            ))
+        }
+    }
+}
+
+/// Decrypts the user data of the MCS Send Data Indication PDU or the updates of the Fast-Path output PDU,
+/// verifying their signature.
+pub(crate) fn decrypt_frame(cipher: &mut SessionCipher, frame: &[u8]) -> io::Result<Vec<u8>> {
+    match frame_action(frame)? {
+        Action::X224 => {
+            let (context, user_data) = match read_send_data(frame)? {
+                Some((McsPdu::SendDataIndication(context), user_data)) => (context, user_data),
+                _ => return Ok(frame.to_vec()),
+            };
+
+            let mut security_header = BasicSecurityHeader::from_buffer(user_data).map_err(invalid_data)?;
+            let data = &user_data[BASIC_SECURITY_HEADER_SIZE..];
+            let data = if security_header.flags.contains(BasicSecurityHeaderFlags::ENCRYPT) {
+                let (signature, encrypted) = split_signature(data)?;
+                security_header.flags.remove(BasicSecurityHeaderFlags::ENCRYPT);
+
+                cipher.decrypt(signature, encrypted).map_err(invalid_data)?
+            } else {
+                data.to_vec()
+            };
+
+            let payload = if security_header.flags.contains(BasicSecurityHeaderFlags::LICENSE_PKT) {
+                let mut payload = Vec::with_capacity(BASIC_SECURITY_HEADER_SIZE + data.len());
+                security_header.to_buffer(&mut payload).map_err(invalid_data)?;
+                payload.extend_from_slice(&data);
+
+                payload
+            } else {
+                data
+            };
+
+            write_send_data(McsPdu::SendDataIndication, context, &payload)
+        }
+        Action::FastPath => {
+            let (header, body) = split_fast_path(frame)?;
+            let flags = EncryptionFlags::from_bits_truncate(header >> FAST_PATH_FLAGS_SHIFT);
+            if !flags.contains(EncryptionFlags::ENCRYPTED) {
+                return Ok(frame.to_vec());
+            }
+
+            let (signature, encrypted) = split_signature(body)?;
+            let decrypted = cipher.decrypt(signature, encrypted).map_err(invalid_data)?;
+            let header = header & !(EncryptionFlags::all().bits() << FAST_PATH_FLAGS_SHIFT);
+
+            Ok(write_fast_path(header, &decrypted))
+        }
+    }
+}
+
+fn frame_action(frame: &[u8]) -> io::Result<Action> {
+    let header = frame.first().ok_or_else(|| invalid_data("empty frame"))?;
+
+    num_traits::FromPrimitive::from_u8(header & 0x03).ok_or_else(|| invalid_data("invalid action code"))
+}
+
+/// Reads the MCS PDU of the X.224 Data frame, returning the Send Data PDUs with their user data.
+fn read_send_data(frame: &[u8]) -> io::Result<Option<(McsPdu, &[u8])>> {
+    let mut stream = frame;
+    ironrdp::Data::from_buffer(&mut stream).map_err(invalid_data)?;
+    let mcs_pdu = McsPdu::from_buffer(&mut stream).map_err(invalid_data)?;
+
+    Ok(match mcs_pdu {
+        McsPdu::SendDataRequest(_) | McsPdu::SendDataIndication(_) => Some((mcs_pdu, stream)),
+        _ => None,
+    })
+}
+
+fn write_send_data(
+    mcs_pdu: impl FnOnce(SendDataContext) -> McsPdu,
+    context: SendDataContext,
+    payload: &[u8],
+) -> io::Result<Vec<u8>> {
+    let mcs_pdu = mcs_pdu(SendDataContext {
+        pdu_length: payload.len(),
+        ..context
+    });
+
+    let mut frame = Vec::new();
+    ironrdp::Data::new(mcs_pdu.buffer_length() + payload.len())
+        .to_buffer(&mut frame)
+        .map_err(invalid_data)?;
+    mcs_pdu.to_buffer(&mut frame).map_err(invalid_data)?;
+    frame.extend_from_slice(payload);
+
+    Ok(frame)
+}
+
+/// Splits the Fast-Path frame into its first byte and the bytes following its length.
+fn split_fast_path(frame: &[u8]) -> io::Result<(u8, &[u8])> {
+    let length_size = match frame.get(1) {
+        Some(length) if length & 0x80 != 0 => 2,
+        Some(_) => 1,
+        None => return Err(invalid_data("truncated Fast-Path header")),
+    };
+    let body = frame
+        .get(1 + length_size..)
+        .ok_or_else(|| invalid_data("truncated Fast-Path header"))?;
+
+    Ok((frame[0], body))
+}
+
+fn write_fast_path(header: u8, body: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(body.len() + 3);
+    frame.push(header);
+
+    if body.len() + 2 <= FAST_PATH_SHORT_LENGTH_MAX {
+        frame.push((body.len() + 2) as u8);
+    } else {
+        frame.extend_from_slice(&(0x8000 | (body.len() + 3) as u16).to_be_bytes());
+    }
+    frame.extend_from_slice(body);
+
+    frame
+}
+
+fn split_signature(data: &[u8]) -> io::Result<(&[u8], &[u8])> {
+    if data.len() < SIGNATURE_SIZE {
+        return Err(invalid_data("truncated signature"));
+    }
+
+    Ok(data.split_at(SIGNATURE_SIZE))
+}
+
+fn invalid_data(e: impl ToString) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e.to_string())
+}
@@ -0,0 +1,146 @@
+use futures_util::io::Cursor;
+use futures_util::{AsyncReadExt as _, AsyncWriteExt as _};
+use ironrdp::gcc::EncryptionMethod;
+use ironrdp::rdp::standard_security::SessionKeys;
+use ironrdp::{ChannelId, UserId};
+
+use super::*;
+
+const CONTEXT: SendDataContext = SendDataContext {
+    initiator_id: UserId(1007),
+    channel_id: ChannelId(1003),
+    pdu_length: 0,
+};
+
+/// The cipher of the client and a copy of it for the server, decrypting what the client encrypts.
+fn ciphers() -> (SessionCipher, SessionCipher) {
+    let keys = SessionKeys::derive(EncryptionMethod::BIT_128, &[0x11; 32], &[0x22; 32]).unwrap();
+
+    (keys.encrypt.clone(), keys.encrypt)
+}
+
+fn fast_path_frame(body: &[u8]) -> Vec<u8> {
+    write_fast_path(0x04, body)
+}
+
+#[test]
+fn send_data_request_user_data_is_encrypted_after_security_header() {
+    let (mut client, mut server) = ciphers();
+    let frame = write_send_data(McsPdu::SendDataRequest, CONTEXT, b"share data").unwrap();
+
+    let encrypted = encrypt_frame(&mut client, &frame).unwrap();
+
+    let (mcs_pdu, user_data) = read_send_data(&encrypted).unwrap().unwrap();
+    assert_eq!(
+        McsPdu::SendDataRequest(SendDataContext {
+            pdu_length: NON_FIPS_SECURITY_HEADER_SIZE + 10,
+            ..CONTEXT
+        }),
+        mcs_pdu
+    );
+    let security_header = BasicSecurityHeader::from_buffer(user_data).unwrap();
+    assert_eq!(BasicSecurityHeaderFlags::ENCRYPT, security_header.flags);
+    let (signature, data) = split_signature(&user_data[BASIC_SECURITY_HEADER_SIZE..]).unwrap();
+    assert_eq!(
+        b"share data".as_ref(),
+        server.decrypt(signature, data).unwrap().as_slice()
+    );
+}
+
+#[test]
+fn send_data_indication_is_decrypted_and_license_header_is_kept() {
+    let (mut server, mut client) = ciphers();
+
+    let (signature, encrypted) = server.encrypt(b"license");
+    let mut payload = Vec::new();
+    BasicSecurityHeader {
+        flags: BasicSecurityHeaderFlags::ENCRYPT | BasicSecurityHeaderFlags::LICENSE_PKT,
+    }
+    .to_buffer(&mut payload)
+    .unwrap();
+    payload.extend_from_slice(&signature);
+    payload.extend_from_slice(&encrypted);
+    let frame = write_send_data(McsPdu::SendDataIndication, CONTEXT, &payload).unwrap();
+
+    let decrypted = decrypt_frame(&mut client, &frame).unwrap();
+
+    let mut expected = Vec::new();
+    BasicSecurityHeader {
+        flags: BasicSecurityHeaderFlags::LICENSE_PKT,
+    }
+    .to_buffer(&mut expected)
+    .unwrap();
+    expected.extend_from_slice(b"license");
+    assert_eq!(
+        write_send_data(McsPdu::SendDataIndication, CONTEXT, &expected).unwrap(),
+        decrypted
+    );
+}
+
+#[test]
+fn fast_path_frame_is_encrypted_and_decrypted() {
+    let (mut client, mut server) = ciphers();
+    let short_frame = fast_path_frame(&[0xab; 16]);
+    let long_frame = fast_path_frame(&[0xcd; 200]);
+
+    for frame in [short_frame, long_frame] {
+        let encrypted = encrypt_frame(&mut client, &frame).unwrap();
+        assert_eq!(frame.len() + SIGNATURE_SIZE, encrypted.len());
+        assert_eq!(0x84, encrypted[0]);
+
+        assert_eq!(frame, decrypt_frame(&mut server, &encrypted).unwrap());
+    }
+}
+
+#[test]
+fn tampered_fast_path_frame_is_rejected() {
+    let (mut client, mut server) = ciphers();
+
+    let mut encrypted = encrypt_frame(&mut client, &fast_path_frame(b"input events")).unwrap();
+    let last = encrypted.len() - 1;
+    encrypted[last] ^= 0xff;
+
+    assert_eq!(
+        io::ErrorKind::InvalidData,
+        decrypt_frame(&mut server, &encrypted).unwrap_err().kind()
+    );
+}
+
+#[tokio::test]
+async fn frames_written_in_pieces_are_encrypted() {
+    let (client, mut expected_cipher) = ciphers();
+    let first = write_send_data(McsPdu::SendDataRequest, CONTEXT, b"first").unwrap();
+    let second = fast_path_frame(&[0xef; 300]);
+
+    let mut writer = StandardSecurityWriter::new(Cursor::new(Vec::new()), client);
+    for piece in [first.as_slice(), &second].concat().chunks(7) {
+        writer.write_all(piece).await.unwrap();
+    }
+    writer.flush().await.unwrap();
+
+    let expected = [
+        encrypt_frame(&mut expected_cipher, &first).unwrap(),
+        encrypt_frame(&mut expected_cipher, &second).unwrap(),
+    ]
+    .concat();
+    assert_eq!(expected, writer.writer.into_inner());
+}
+
+#[tokio::test]
+async fn frames_are_read_decrypted() {
+    let (mut server, client) = ciphers();
+    let first = fast_path_frame(b"first");
+    let second = fast_path_frame(&[0xef; 300]);
+    let encrypted = [
+        encrypt_frame(&mut server, &first).unwrap(),
+        encrypt_frame(&mut server, &second).unwrap(),
+    ]
+    .concat();
+
+    // the first bytes have already been read by the framed reader
+    let mut reader = StandardSecurityReader::new(Cursor::new(encrypted[3..].to_vec()), client, encrypted[..3].into());
+    let mut decrypted = Vec::new();
+    reader.read_to_end(&mut decrypted).await.unwrap();
+
+    assert_eq!([first, second].concat(), decrypted);
+}
@@ -0,0 +1,15 @@
+use super::*;
+
+#[test]
+fn sensitive_frame_is_encoded_without_reallocating_its_buffer() {
+    let mut codec =
+        SendDataContextTransport::new(McsTransport::new(DataTransport::new()), UserId(1007), ChannelId(1003));
+    let pdu = [0x42; 300];
+
+    let mut frame = Vec::with_capacity(codec.sensitive_frame_length(pdu.len()));
+    let capacity = frame.capacity();
+    codec.encode_sensitive(&pdu, &mut frame).unwrap();
+
+    assert_eq!(capacity, frame.capacity());
+    assert_eq!(capacity, frame.len());
+}
@@ -1,8 +1,6 @@
 use std::collections::HashMap;
 use std::hash::Hash;
 
-use num_derive::{FromPrimitive, ToPrimitive};
-
 #[macro_export]
 macro_rules! eof_try {
     ($e:expr) => {
@@ -37,8 +35,3 @@ where
 
     result
 }
-
-#[derive(Debug, Copy, Clone, PartialEq, Eq, FromPrimitive, ToPrimitive)]
-pub enum CodecId {
-    RemoteFx = 0x3,
-}
@@ -0,0 +1,800 @@
+mod loopback_server;
+
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+use futures_util::{AsyncReadExt as _, AsyncWriteExt as _};
+use ironrdp::bitmap::Bitmap;
+use ironrdp::codecs::rfx::image_processing::PixelFormat;
+use ironrdp::fast_path::{FastPathHeader, FastPathUpdate, FastPathUpdatePdu};
+use ironrdp::input::fast_path::{FastPathInputEvent, KeyboardFlags};
+use ironrdp::input::mouse::{ButtonEvents, MovementEvents, WheelEvents};
+use ironrdp::input::{InputEvent, MousePdu};
+use ironrdp::rdp::session_info::ServerAutoReconnect;
+use ironrdp::rdp::{
+    AddressFamily, ClientInfoFlags, CompressionType, PerformanceFlags, ServerRedirectionFlags, ServerRedirectionPdu,
+};
+use ironrdp::server::CapabilitiesPreset;
+use ironrdp::{gcc, nego, LimitsConfig, PduBufferParsing, PduParsing};
+use ironrdp_session::image::DecodedImage;
+use ironrdp_session::{
+    continue_connection_sequence, probe_session, process_connection_sequence, process_server_connection_sequence,
+    transport, ActiveStageOutput, ActiveStageProcessor, ClientInfoConfig, CodecRegistry, DiagnosticsRecorder,
+    ErasedWriter, ErrorCode, EstablishedStream, FramedReader, InputConfig, PduChannel, PduSummary, RdpError,
+    ServerConfig, TrafficCounters, UpgradedStream, DEFAULT_MAX_PDUS, GLOBAL_CHANNEL_NAME, USER_CHANNEL_NAME,
+};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_util::compat::TokioAsyncReadCompatExt as _;
+
+use self::loopback_server::{LoopbackServer, ReceivedInput};
+
+const DESKTOP_WIDTH: u16 = 800;
+const DESKTOP_HEIGHT: u16 = 600;
+
+fn input_config() -> InputConfig {
+    InputConfig {
+        credentials: sspi::AuthIdentity {
+            username: String::from("user"),
+            password: String::from("password"),
+            domain: None,
+        },
+        security_protocol: nego::SecurityProtocol::SSL,
+        encryption_methods: gcc::EncryptionMethod::empty(),
+        keyboard_type: gcc::KeyboardType::IbmEnhanced,
+        keyboard_subtype: 0,
+        keyboard_functional_keys_count: 12,
+        ime_file_name: String::new(),
+        client_name: None,
+        client_build: None,
+        dig_product_id: None,
+        width: 1024,
+        height: 768,
+        monitors: Vec::new(),
+        global_channel_name: GLOBAL_CHANNEL_NAME,
+        user_channel_name: USER_CHANNEL_NAME,
+        graphics_config: None,
+        codecs: CodecRegistry::default(),
+        limits: LimitsConfig::default(),
+        audio_playback: false,
+        drive_redirection: false,
+        smart_card_redirection: false,
+        client_info: ClientInfoConfig::default(),
+        redirection_credentials: None,
+        routing_token: None,
+        redirected_session_id: None,
+        gateway: None,
+        output_pixel_format: PixelFormat::RgbA32,
+        diagnostics: DiagnosticsRecorder::default(),
+        license_store: None,
+    }
+}
+
+fn echoed_text(frame: &[u8]) -> String {
+    let mut frame = frame;
+    let header = FastPathHeader::from_buffer(&mut frame).unwrap();
+    let update_pdu = FastPathUpdatePdu::from_buffer(&frame[..header.data_length]).unwrap();
+
+    match FastPathUpdate::from_buffer_with_code(update_pdu.data, update_pdu.update_code).unwrap() {
+        FastPathUpdate::Bitmap(Bitmap { rectangles, .. }) => {
+            let pixels = rectangles[0].bitmap_data;
+            let text_length = pixels.iter().rposition(|&pixel| pixel != 0).map_or(0, |i| i + 1);
+
+            String::from_utf8(pixels[..text_length].to_vec()).unwrap()
+        }
+        update => panic!("Expected bitmap update, got: {}", update.as_short_name()),
+    }
+}
+
+async fn connect(server_addr: SocketAddr) -> (ActiveStageProcessor, FramedReader, ErasedWriter) {
+    connect_with_config(server_addr, input_config()).await
+}
+
+async fn connect_with_config(
+    server_addr: SocketAddr,
+    config: InputConfig,
+) -> (ActiveStageProcessor, FramedReader, ErasedWriter) {
+    let stream = TcpStream::connect(server_addr).await.unwrap();
+    let routing_addr = SocketAddr::new(server_addr.ip(), server_addr.port());
+    let upgrade_stream = |stream| async move {
+        Ok::<_, RdpError>(UpgradedStream {
+            stream,
+            server_public_key: Vec::new(),
+            server_certificate: None,
+        })
+    };
+
+    let (connection_sequence_result, reader, writer) =
+        process_connection_sequence(stream.compat(), &routing_addr, &config, upgrade_stream)
+            .await
+            .unwrap();
+
+    assert_eq!(DESKTOP_WIDTH, connection_sequence_result.desktop_size.width);
+    assert_eq!(DESKTOP_HEIGHT, connection_sequence_result.desktop_size.height);
+
+    (
+        ActiveStageProcessor::new(config, connection_sequence_result),
+        reader,
+        writer,
+    )
+}
+
+async fn process_echoes(
+    active_stage: &mut ActiveStageProcessor,
+    reader: &mut FramedReader,
+    expected_texts: impl IntoIterator<Item = String>,
+) {
+    let mut image = DecodedImage::new(PixelFormat::RgbA32, u32::from(DESKTOP_WIDTH), u32::from(DESKTOP_HEIGHT));
+
+    for expected_text in expected_texts {
+        let frame = reader.read_frame().await.unwrap().unwrap();
+        assert_eq!(expected_text, echoed_text(&frame));
+
+        let outputs = active_stage.process(&mut image, frame).await.unwrap();
+        assert!(!outputs
+            .iter()
+            .any(|output| matches!(output, ActiveStageOutput::Terminate)));
+    }
+}
+
+fn mouse_move_event() -> MousePdu {
+    MousePdu {
+        wheel_events: WheelEvents::empty(),
+        movement_events: MovementEvents::MOVE,
+        button_events: ButtonEvents::empty(),
+        number_of_wheel_rotations: 0,
+        x_position: 42,
+        y_position: 24,
+    }
+}
+
+#[tokio::test]
+async fn client_connects_to_loopback_server_and_gets_input_echo() {
+    let server = LoopbackServer::bind(DESKTOP_WIDTH, DESKTOP_HEIGHT).unwrap();
+    let server_addr = server.local_addr().unwrap();
+    let server = server.spawn();
+
+    let (mut active_stage, mut reader, mut writer) = connect(server_addr).await;
+
+    let received_pdus = Arc::new(Mutex::new(Vec::new()));
+    let hook_received_pdus = Arc::clone(&received_pdus);
+    active_stage.on_pdu_received(move |summary| hook_received_pdus.lock().unwrap().push(summary.clone()));
+
+    let events = vec![
+        FastPathInputEvent::KeyboardEvent(KeyboardFlags::empty(), 0x1e),
+        FastPathInputEvent::MouseEvent(mouse_move_event()),
+    ];
+    let input = active_stage.encode_fast_path_input(events.clone()).unwrap();
+    writer.write_all(&input).await.unwrap();
+    writer.flush().await.unwrap();
+
+    process_echoes(
+        &mut active_stage,
+        &mut reader,
+        events.iter().map(|event| format!("{:?}", event)),
+    )
+    .await;
+
+    assert_eq!(
+        vec![PduSummary::new(PduChannel::FastPath, "Bitmap"); events.len()],
+        *received_pdus.lock().unwrap()
+    );
+
+    let traffic = active_stage.take_traffic_snapshot().channel(PduChannel::FastPath);
+    assert_eq!(
+        TrafficCounters {
+            pdus: 1,
+            bytes: input.len() as u64,
+        },
+        traffic.sent
+    );
+    assert_eq!(events.len() as u64, traffic.received.pdus);
+
+    writer.close().await.unwrap();
+    drop(writer);
+    drop(reader);
+
+    assert_eq!(
+        events.into_iter().map(ReceivedInput::FastPath).collect::<Vec<_>>(),
+        server.join().unwrap().unwrap()
+    );
+}
+
+#[tokio::test]
+async fn client_sends_slow_path_input_to_loopback_server() {
+    let server = LoopbackServer::bind(DESKTOP_WIDTH, DESKTOP_HEIGHT).unwrap();
+    let server_addr = server.local_addr().unwrap();
+    let server = server.spawn();
+
+    let (mut active_stage, mut reader, mut writer) = connect(server_addr).await;
+
+    let events = vec![
+        InputEvent::try_from(FastPathInputEvent::KeyboardEvent(
+            KeyboardFlags::FASTPATH_INPUT_KBDFLAGS_RELEASE,
+            0x1e,
+        ))
+        .unwrap(),
+        InputEvent::Mouse(mouse_move_event()),
+    ];
+    let input = active_stage.encode_slow_path_input(events.clone()).unwrap();
+    writer.write_all(&input).await.unwrap();
+    writer.flush().await.unwrap();
+
+    process_echoes(
+        &mut active_stage,
+        &mut reader,
+        events.iter().map(|event| format!("{:?}", event)),
+    )
+    .await;
+
+    writer.close().await.unwrap();
+    drop(writer);
+    drop(reader);
+
+    assert_eq!(
+        events.into_iter().map(ReceivedInput::SlowPath).collect::<Vec<_>>(),
+        server.join().unwrap().unwrap()
+    );
+}
+
+#[tokio::test]
+async fn client_processes_next_frames_over_async_stream() {
+    let server = LoopbackServer::bind(DESKTOP_WIDTH, DESKTOP_HEIGHT).unwrap();
+    let server_addr = server.local_addr().unwrap();
+    let server = server.spawn();
+
+    let (mut active_stage, mut reader, mut writer) = connect(server_addr).await;
+    let mut image = DecodedImage::new(PixelFormat::RgbA32, u32::from(DESKTOP_WIDTH), u32::from(DESKTOP_HEIGHT));
+
+    let events = vec![
+        FastPathInputEvent::KeyboardEvent(KeyboardFlags::empty(), 0x1e),
+        FastPathInputEvent::MouseEvent(mouse_move_event()),
+    ];
+    let input = active_stage.encode_fast_path_input(events.clone()).unwrap();
+    writer.write_all(&input).await.unwrap();
+    writer.flush().await.unwrap();
+
+    for _ in 0..events.len() {
+        let outputs = active_stage
+            .process_next_frame(&mut reader, &mut writer, &mut image)
+            .await
+            .unwrap();
+        assert!(outputs.iter().all(|output| matches!(
+            output,
+            ActiveStageOutput::GraphicsUpdate(_) | ActiveStageOutput::SessionLockState(_)
+        )));
+    }
+
+    writer.close().await.unwrap();
+    drop(writer);
+
+    assert!(matches!(
+        active_stage
+            .process_next_frame(&mut reader, &mut futures_util::io::sink(), &mut image)
+            .await,
+        Err(RdpError::UnexpectedStreamTermination)
+    ));
+    drop(reader);
+
+    assert_eq!(
+        events.into_iter().map(ReceivedInput::FastPath).collect::<Vec<_>>(),
+        server.join().unwrap().unwrap()
+    );
+}
+
+#[tokio::test]
+async fn client_connects_and_processes_frames_from_spawned_task() {
+    let server = LoopbackServer::bind(DESKTOP_WIDTH, DESKTOP_HEIGHT).unwrap();
+    let server_addr = server.local_addr().unwrap();
+    let server = server.spawn();
+
+    let events = vec![FastPathInputEvent::KeyboardEvent(KeyboardFlags::empty(), 0x1e)];
+    let client_events = events.clone();
+
+    // the task requires the connection sequence and the session to be Send
+    tokio::spawn(async move {
+        let (mut active_stage, mut reader, mut writer) = connect(server_addr).await;
+        let mut image = DecodedImage::new(PixelFormat::RgbA32, u32::from(DESKTOP_WIDTH), u32::from(DESKTOP_HEIGHT));
+
+        let input = active_stage.encode_fast_path_input(client_events).unwrap();
+        writer.write_all(&input).await.unwrap();
+        writer.flush().await.unwrap();
+
+        active_stage
+            .process_next_frame(&mut reader, &mut writer, &mut image)
+            .await
+            .unwrap();
+
+        writer.close().await.unwrap();
+    })
+    .await
+    .unwrap();
+
+    assert_eq!(
+        events.into_iter().map(ReceivedInput::FastPath).collect::<Vec<_>>(),
+        server.join().unwrap().unwrap()
+    );
+}
+
+#[tokio::test]
+async fn client_polls_fed_bytes_one_frame_at_a_time() {
+    let server = LoopbackServer::bind(DESKTOP_WIDTH, DESKTOP_HEIGHT).unwrap();
+    let server_addr = server.local_addr().unwrap();
+    let server = server.spawn();
+
+    let (mut active_stage, reader, mut writer) = connect(server_addr).await;
+
+    let received_pdus = Arc::new(Mutex::new(Vec::new()));
+    let hook_received_pdus = Arc::clone(&received_pdus);
+    active_stage.on_pdu_received(move |summary| hook_received_pdus.lock().unwrap().push(summary.clone()));
+
+    let events = vec![
+        FastPathInputEvent::KeyboardEvent(KeyboardFlags::empty(), 0x1e),
+        FastPathInputEvent::MouseEvent(mouse_move_event()),
+    ];
+    let input = active_stage.encode_fast_path_input(events.clone()).unwrap();
+    writer.write_all(&input).await.unwrap();
+    writer.flush().await.unwrap();
+
+    let (mut reader, buffered) = reader.into_inner();
+    active_stage.feed(&buffered);
+
+    let mut image = DecodedImage::new(PixelFormat::RgbA32, u32::from(DESKTOP_WIDTH), u32::from(DESKTOP_HEIGHT));
+    let mut processed_frames = 0;
+
+    while processed_frames < events.len() {
+        match active_stage.poll_once(&mut image).unwrap() {
+            Some(outputs) => {
+                assert!(!outputs
+                    .iter()
+                    .any(|output| matches!(output, ActiveStageOutput::Terminate)));
+
+                processed_frames += 1;
+                assert_eq!(processed_frames, received_pdus.lock().unwrap().len());
+            }
+            None => {
+                let mut received = [0; 1024];
+                let len = reader.read(&mut received).await.unwrap();
+                assert_ne!(0, len, "the server closed the connection before echoing the input");
+
+                active_stage.feed(&received[..len]);
+            }
+        }
+    }
+
+    assert!(active_stage.poll_once(&mut image).unwrap().is_none());
+    assert_eq!(0, active_stage.buffered_len());
+
+    writer.close().await.unwrap();
+    drop(writer);
+    drop(reader);
+
+    assert_eq!(
+        events.into_iter().map(ReceivedInput::FastPath).collect::<Vec<_>>(),
+        server.join().unwrap().unwrap()
+    );
+}
+
+#[tokio::test]
+async fn client_handles_incoming_bytes_split_anywhere() {
+    let server = LoopbackServer::bind(DESKTOP_WIDTH, DESKTOP_HEIGHT).unwrap();
+    let server_addr = server.local_addr().unwrap();
+    let server = server.spawn();
+
+    let (mut active_stage, reader, mut writer) = connect(server_addr).await;
+
+    let received_pdus = Arc::new(Mutex::new(Vec::new()));
+    let hook_received_pdus = Arc::clone(&received_pdus);
+    active_stage.on_pdu_received(move |summary| hook_received_pdus.lock().unwrap().push(summary.clone()));
+
+    let events = vec![
+        FastPathInputEvent::KeyboardEvent(KeyboardFlags::empty(), 0x1e),
+        FastPathInputEvent::MouseEvent(mouse_move_event()),
+    ];
+    let input = active_stage.encode_fast_path_input(events.clone()).unwrap();
+    writer.write_all(&input).await.unwrap();
+    writer.flush().await.unwrap();
+
+    let (mut reader, buffered) = reader.into_inner();
+    let mut image = DecodedImage::new(PixelFormat::RgbA32, u32::from(DESKTOP_WIDTH), u32::from(DESKTOP_HEIGHT));
+    let mut outputs = active_stage.handle_incoming(&mut image, &buffered).unwrap();
+
+    while received_pdus.lock().unwrap().len() < events.len() {
+        // a few bytes at a time, for the frames to be split across the calls
+        let mut received = [0; 3];
+        let len = reader.read(&mut received).await.unwrap();
+        assert_ne!(0, len, "the server closed the connection before echoing the input");
+
+        outputs.extend(active_stage.handle_incoming(&mut image, &received[..len]).unwrap());
+    }
+
+    assert!(!outputs
+        .iter()
+        .any(|output| matches!(output, ActiveStageOutput::Terminate)));
+    assert_eq!(0, active_stage.buffered_len());
+
+    writer.close().await.unwrap();
+    drop(writer);
+    drop(reader);
+
+    assert_eq!(
+        events.into_iter().map(ReceivedInput::FastPath).collect::<Vec<_>>(),
+        server.join().unwrap().unwrap()
+    );
+}
+
+#[tokio::test]
+async fn client_resynchronizes_on_frame_following_corrupted_bytes() {
+    let server = LoopbackServer::bind(DESKTOP_WIDTH, DESKTOP_HEIGHT).unwrap();
+    let server_addr = server.local_addr().unwrap();
+    let server = server.spawn();
+
+    let (mut active_stage, reader, mut writer) = connect(server_addr).await;
+
+    let events = vec![FastPathInputEvent::KeyboardEvent(KeyboardFlags::empty(), 0x1e)];
+    let input = active_stage.encode_fast_path_input(events.clone()).unwrap();
+    writer.write_all(&input).await.unwrap();
+    writer.flush().await.unwrap();
+
+    // invalid action codes, then a TPKT version other than 3
+    let corrupted = [0x01, 0x02, 0xff];
+    let (mut reader, buffered) = reader.into_inner();
+    active_stage.feed(&corrupted);
+    active_stage.feed(&buffered);
+
+    let mut image = DecodedImage::new(PixelFormat::RgbA32, u32::from(DESKTOP_WIDTH), u32::from(DESKTOP_HEIGHT));
+    let mut outputs = Vec::new();
+
+    while outputs.len() < 2 {
+        match active_stage.poll_once(&mut image).unwrap() {
+            Some(frame_outputs) => outputs.push(frame_outputs),
+            None => {
+                let mut received = [0; 1024];
+                let len = reader.read(&mut received).await.unwrap();
+                assert_ne!(0, len, "the server closed the connection before echoing the input");
+
+                active_stage.feed(&received[..len]);
+            }
+        }
+    }
+
+    assert!(matches!(
+        outputs[0].as_slice(),
+        [ActiveStageOutput::Desynchronized { skipped_bytes }] if *skipped_bytes == corrupted.len()
+    ));
+    assert!(!outputs[1].iter().any(|output| matches!(
+        output,
+        ActiveStageOutput::Desynchronized { .. } | ActiveStageOutput::Terminate
+    )));
+
+    writer.close().await.unwrap();
+    drop(writer);
+    drop(reader);
+
+    assert_eq!(
+        events.into_iter().map(ReceivedInput::FastPath).collect::<Vec<_>>(),
+        server.join().unwrap().unwrap()
+    );
+}
+
+#[tokio::test]
+async fn client_continues_connection_sequence_on_established_stream() {
+    let server = LoopbackServer::bind(DESKTOP_WIDTH, DESKTOP_HEIGHT).unwrap();
+    let server_addr = server.local_addr().unwrap();
+    let server = server.spawn();
+
+    let config = input_config();
+    let (reader, mut writer) = TcpStream::connect(server_addr).await.unwrap().compat().split();
+    let mut reader = FramedReader::new(reader);
+    let selected_protocol = transport::connect(
+        &mut reader,
+        &mut writer,
+        config.security_protocol,
+        nego::NegoData::Cookie(config.credentials.username.clone()),
+    )
+    .await
+    .unwrap();
+    let (reader, _) = reader.into_inner();
+    let established_stream = EstablishedStream {
+        stream: reader.reunite(writer).unwrap(),
+        selected_protocol,
+        server_certificate: None,
+    };
+
+    let (connection_sequence_result, reader, mut writer) =
+        continue_connection_sequence(established_stream, &server_addr, &config)
+            .await
+            .unwrap();
+
+    assert_eq!(DESKTOP_WIDTH, connection_sequence_result.desktop_size.width);
+    assert_eq!(DESKTOP_HEIGHT, connection_sequence_result.desktop_size.height);
+
+    writer.close().await.unwrap();
+    drop(writer);
+    drop(reader);
+
+    assert_eq!(Vec::<ReceivedInput>::new(), server.join().unwrap().unwrap());
+}
+
+#[tokio::test]
+async fn client_probes_loopback_server_and_disconnects_after_mcs_connect() {
+    let server = LoopbackServer::bind(DESKTOP_WIDTH, DESKTOP_HEIGHT).unwrap();
+    let server_addr = server.local_addr().unwrap();
+    let server = server.spawn();
+
+    let config = input_config();
+    let stream = TcpStream::connect(server_addr).await.unwrap();
+    let upgrade_stream = |stream| async move {
+        Ok::<_, RdpError>(UpgradedStream {
+            stream,
+            server_public_key: Vec::new(),
+            server_certificate: None,
+        })
+    };
+
+    let probe_result = probe_session(stream.compat(), &server_addr, &config, upgrade_stream)
+        .await
+        .unwrap();
+
+    assert_eq!(nego::SecurityProtocol::SSL, probe_result.selected_protocol);
+    assert_eq!(None, probe_result.timings.cred_ssp);
+    assert!(probe_result.timings.total() >= probe_result.timings.mcs_connect);
+
+    assert_eq!(Vec::<ReceivedInput>::new(), server.join().unwrap().unwrap());
+}
+
+#[tokio::test]
+async fn client_reports_redirection_and_applies_it_to_config() {
+    let redirection = ServerRedirectionPdu {
+        session_id: 7,
+        flags: ServerRedirectionFlags::empty(),
+        target_net_address: Some(String::from("10.0.0.2")),
+        load_balance_info: Some(b"Cookie: msts=3640205228.15629.0000\r\n".to_vec()),
+        username: Some(String::from("redirected")),
+        domain: Some(String::from("Contoso")),
+        password: Some(vec![0x01, 0x02, 0x03]),
+        target_fqdn: None,
+        target_netbios_name: None,
+        tsv_url: None,
+        redirection_guid: Some(vec![0xaa, 0xbb]),
+        target_certificate: None,
+        target_net_addresses: None,
+    };
+    let server = LoopbackServer::bind(DESKTOP_WIDTH, DESKTOP_HEIGHT)
+        .unwrap()
+        .with_redirection(redirection.clone());
+    let server_addr = server.local_addr().unwrap();
+    let server = server.spawn();
+
+    let mut config = input_config();
+    let stream = TcpStream::connect(server_addr).await.unwrap();
+    let upgrade_stream = |stream| async move {
+        Ok::<_, RdpError>(UpgradedStream {
+            stream,
+            server_public_key: Vec::new(),
+            server_certificate: None,
+        })
+    };
+
+    let result = process_connection_sequence(stream.compat(), &server_addr, &config, upgrade_stream).await;
+    let received_redirection = match result {
+        Err(RdpError::ServerRedirection(received_redirection)) => received_redirection,
+        Err(e) => panic!("Expected Server Redirection PDU, got error: {}", e),
+        Ok(_) => panic!("Expected Server Redirection PDU, got a connected session"),
+    };
+    assert_eq!(redirection, *received_redirection);
+
+    config.apply_redirection(&received_redirection);
+    assert_eq!(Some("3640205228.15629.0000"), config.routing_token.as_deref());
+    assert_eq!(Some(7), config.redirected_session_id);
+    assert_eq!("redirected", config.credentials.username);
+    assert_eq!(Some("Contoso"), config.credentials.domain.as_deref());
+    assert!(config.security_protocol.contains(nego::SecurityProtocol::RDSTLS));
+
+    assert_eq!(Vec::<ReceivedInput>::new(), server.join().unwrap().unwrap());
+}
+
+fn server_config() -> ServerConfig {
+    ServerConfig {
+        desktop_width: DESKTOP_WIDTH,
+        desktop_height: DESKTOP_HEIGHT,
+        capabilities: CapabilitiesPreset::Windows10,
+    }
+}
+
+#[tokio::test]
+async fn client_connects_to_server_connection_sequence() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let server_addr = listener.local_addr().unwrap();
+    let server_config = server_config();
+
+    let server = async {
+        let (stream, _) = listener.accept().await.unwrap();
+
+        process_server_connection_sequence(stream.compat(), &server_config, |stream| async move {
+            Ok::<_, RdpError>(stream)
+        })
+        .await
+        .unwrap()
+    };
+
+    // the client checks the desktop size advertised by the server
+    let ((server_result, _, _), _) = tokio::join!(server, connect(server_addr));
+
+    assert!(server_result.requested_protocol.contains(nego::SecurityProtocol::SSL));
+    assert!(server_result.static_channels.is_empty());
+    assert_eq!("user", server_result.client_info.credentials.username);
+    assert!(!server_result.client_capability_sets.is_empty());
+}
+
+#[tokio::test]
+async fn client_records_connection_diagnostics() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let server_addr = listener.local_addr().unwrap();
+    let server_config = server_config();
+
+    let server = async {
+        let (stream, _) = listener.accept().await.unwrap();
+
+        process_server_connection_sequence(stream.compat(), &server_config, |stream| async move {
+            Ok::<_, RdpError>(stream)
+        })
+        .await
+        .unwrap()
+    };
+
+    let diagnostics = DiagnosticsRecorder::new(DEFAULT_MAX_PDUS);
+    let config = InputConfig {
+        diagnostics: diagnostics.clone(),
+        ..input_config()
+    };
+    tokio::join!(server, connect_with_config(server_addr, config));
+
+    let diagnostics = diagnostics.snapshot().unwrap();
+    let phases = diagnostics
+        .phase_timings
+        .iter()
+        .map(|(phase, _)| *phase)
+        .collect::<Vec<_>>();
+    assert!(phases.contains(&"capability exchange"));
+    assert!(!diagnostics.server_capability_sets.is_empty());
+    assert!(!diagnostics.client_capability_sets.is_empty());
+    assert!(!diagnostics.last_pdus.is_empty());
+    assert_eq!(None, diagnostics.error);
+}
+
+#[tokio::test]
+async fn client_fails_on_frame_longer_than_limit() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let server_addr = listener.local_addr().unwrap();
+    let server_config = server_config();
+
+    let server = async {
+        let (stream, _) = listener.accept().await.unwrap();
+
+        // the client disconnects on the first frame of the server
+        let _ = process_server_connection_sequence(stream.compat(), &server_config, |stream| async move {
+            Ok::<_, RdpError>(stream)
+        })
+        .await;
+    };
+
+    let config = InputConfig {
+        limits: LimitsConfig {
+            max_pdu_length: 8,
+            ..LimitsConfig::default()
+        },
+        ..input_config()
+    };
+    let client = async {
+        let stream = TcpStream::connect(server_addr).await.unwrap();
+        let upgrade_stream = |stream| async move {
+            Ok::<_, RdpError>(UpgradedStream {
+                stream,
+                server_public_key: Vec::new(),
+                server_certificate: None,
+            })
+        };
+
+        process_connection_sequence(stream.compat(), &server_addr, &config, upgrade_stream).await
+    };
+
+    let (_, result) = tokio::join!(server, client);
+
+    match result {
+        Err(e) => assert_eq!(ErrorCode::LimitExceeded, e.code()),
+        Ok(_) => panic!("the connection sequence has succeeded"),
+    }
+}
+
+#[tokio::test]
+async fn client_sends_configured_extended_client_info() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let server_addr = listener.local_addr().unwrap();
+    let server_config = server_config();
+
+    let server = async {
+        let (stream, _) = listener.accept().await.unwrap();
+
+        process_server_connection_sequence(stream.compat(), &server_config, |stream| async move {
+            Ok::<_, RdpError>(stream)
+        })
+        .await
+        .unwrap()
+    };
+
+    let auto_reconnect = ServerAutoReconnect {
+        logon_id: 7,
+        random_bits: [0xa5; 16],
+    };
+    let mut config = input_config();
+    config.client_info = ClientInfoConfig {
+        address: Some("fe80::1".parse().unwrap()),
+        dir: Some(String::from("C:\\Program Files\\Client")),
+        alternate_shell: String::from("C:\\Windows\\notepad.exe"),
+        work_dir: String::from("C:\\Users\\user"),
+        timezone: None,
+        performance_flags: Some(PerformanceFlags::DISABLE_WALLPAPER),
+        auto_reconnect: Some(auto_reconnect.clone()),
+        compression_type: Some(CompressionType::K64),
+    };
+
+    let ((server_result, _, _), _) = tokio::join!(server, connect_with_config(server_addr, config));
+
+    let client_info = server_result.client_info;
+    assert_eq!("C:\\Windows\\notepad.exe", client_info.alternate_shell);
+    assert_eq!("C:\\Users\\user", client_info.work_dir);
+    assert_eq!(AddressFamily::INet6, client_info.extra_info.address_family);
+    assert_eq!("fe80::1", client_info.extra_info.address);
+    assert_eq!("C:\\Program Files\\Client", client_info.extra_info.dir);
+    assert!(client_info.flags.contains(ClientInfoFlags::COMPRESSION));
+    assert_eq!(CompressionType::K64, client_info.compression_type);
+    assert_eq!(
+        Some(PerformanceFlags::DISABLE_WALLPAPER),
+        client_info.extra_info.optional_data.performance_flags
+    );
+    assert_eq!(
+        Some(auto_reconnect.client_cookie(&[0; 32])),
+        client_info.extra_info.optional_data.reconnect_cookie
+    );
+}
+
+#[tokio::test]
+async fn server_connection_sequence_refuses_client_without_tls() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let server_addr = listener.local_addr().unwrap();
+    let server_config = server_config();
+
+    let server = async {
+        let (stream, _) = listener.accept().await.unwrap();
+
+        process_server_connection_sequence(stream.compat(), &server_config, |stream| async move {
+            Ok::<_, RdpError>(stream)
+        })
+        .await
+    };
+
+    let client = async {
+        let config = InputConfig {
+            security_protocol: nego::SecurityProtocol::HYBRID,
+            ..input_config()
+        };
+        let stream = TcpStream::connect(server_addr).await.unwrap();
+        let upgrade_stream = |stream| async move {
+            Ok::<_, RdpError>(UpgradedStream {
+                stream,
+                server_public_key: Vec::new(),
+                server_certificate: None,
+            })
+        };
+
+        process_connection_sequence(stream.compat(), &server_addr, &config, upgrade_stream).await
+    };
+
+    let (server_result, client_result) = tokio::join!(server, client);
+
+    assert!(matches!(
+        server_result,
+        Err(RdpError::UnsupportedSecurityProtocol(protocol)) if protocol == nego::SecurityProtocol::HYBRID
+    ));
+    assert!(client_result.is_err());
+}
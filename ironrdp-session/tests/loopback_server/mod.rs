@@ -0,0 +1,488 @@
+//! A minimal RDP server used for end-to-end testing of the client without a Windows host.
+//!
+//! The server accepts one connection, goes through the connection sequence with the basic
+//! capabilities and echoes every Fast-Path and slow-path input event back to the client as a bitmap update
+//! whose pixels are the text of the event. TLS is not performed: the client is expected to
+//! request `SecurityProtocol::SSL` and to skip the actual stream upgrade.
+
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::thread;
+
+use ironrdp::bitmap::{Bitmap, BitmapData, Compression};
+use ironrdp::consts::{DEFAULT_USER_CHANNEL_ID, IO_CHANNEL_ID};
+use ironrdp::fast_path::{EncryptionFlags, FastPathHeader, FastPathUpdatePdu, Fragmentation, UpdateCode};
+use ironrdp::gcc::conference_create::ConferenceCreateResponse;
+use ironrdp::gcc::{
+    RdpVersion, ServerCoreData, ServerCoreOptionalData, ServerGccBlocks, ServerNetworkData, ServerSecurityData,
+};
+use ironrdp::input::fast_path::{FastPathInput, FastPathInputEvent};
+use ironrdp::input::{InputEvent, InputEventPdu};
+use ironrdp::mcs::{AttachUserConfirmPdu, ChannelJoinConfirmPdu, DomainParameters, SendDataContext};
+use ironrdp::rdp::server_license::InitialServerLicenseMessage;
+use ironrdp::rdp::{
+    ClientInfoPdu, CompressionFlags, CompressionType, ControlAction, ControlPdu, FontPdu, SequenceFlags,
+    ServerRedirectionPdu, ShareControlHeader, ShareControlPdu, ShareDataHeader, ShareDataPdu, StreamPriority,
+    SynchronizePdu, SERVER_CHANNEL_ID,
+};
+use ironrdp::server::CapabilitiesPreset;
+use ironrdp::{
+    nego, ChannelId, ConnectInitial, ConnectResponse, Data, McsPdu, PduBufferParsing, PduParsing, Rectangle, UserId,
+};
+
+const TPKT_VERSION: u8 = 3;
+const TPKT_HEADER_SIZE: usize = 4;
+const FAST_PATH_LONG_LENGTH_FLAG: u8 = 0x80;
+
+const STATIC_CHANNELS_START_ID: u16 = DEFAULT_USER_CHANNEL_ID + 1;
+const SHARE_ID: u32 = 0x0001_03ea;
+const FONT_MAP_ENTRY_SIZE: u16 = 4;
+const ECHO_BITS_PER_PIXEL: u16 = 8;
+const ECHO_ROW_ALIGNMENT: usize = 4;
+
+/// An input event received by the server, along with the path it was sent through.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReceivedInput {
+    FastPath(FastPathInputEvent),
+    SlowPath(InputEvent),
+}
+
+pub struct LoopbackServer {
+    listener: TcpListener,
+    desktop_width: u16,
+    desktop_height: u16,
+    redirection: Option<ServerRedirectionPdu>,
+}
+
+impl LoopbackServer {
+    pub fn bind(desktop_width: u16, desktop_height: u16) -> io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+
+        Ok(Self {
+            listener,
+            desktop_width,
+            desktop_height,
+            redirection: None,
+        })
+    }
+
+    /// Sends the Server Redirection PDU instead of the Server Demand Active PDU, and disconnects.
+    pub fn with_redirection(mut self, redirection: ServerRedirectionPdu) -> Self {
+        self.redirection = Some(redirection);
+        self
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Serves one connection on a separate thread until the client disconnects.
+    /// Returns the input events received during the active stage.
+    pub fn spawn(self) -> thread::JoinHandle<io::Result<Vec<ReceivedInput>>> {
+        thread::spawn(move || {
+            let (stream, _) = self.listener.accept()?;
+
+            self.serve(stream)
+        })
+    }
+
+    fn serve(&self, mut stream: TcpStream) -> io::Result<Vec<ReceivedInput>> {
+        accept_negotiation(&mut stream)?;
+        let static_channels = accept_mcs_connect(&mut stream)?;
+        if !accept_mcs_domain(&mut stream, static_channels)? {
+            return Ok(Vec::new());
+        }
+
+        let (_, client_info) = read_send_data_request(&mut stream)?;
+        ClientInfoPdu::from_buffer(client_info.as_slice()).map_err(invalid_data)?;
+
+        let mut license = Vec::new();
+        InitialServerLicenseMessage::new_status_valid_client_message()
+            .to_buffer(&mut license)
+            .map_err(invalid_data)?;
+        write_send_data_indication(&mut stream, &license)?;
+
+        if let Some(redirection) = self.redirection.clone() {
+            write_share_control_pdu(&mut stream, ShareControlPdu::ServerRedirection(redirection))?;
+            return Ok(Vec::new());
+        }
+
+        self.exchange_capabilities(&mut stream)?;
+        accept_finalization(&mut stream)?;
+
+        echo_input_events(&mut stream)
+    }
+
+    fn exchange_capabilities(&self, stream: &mut TcpStream) -> io::Result<()> {
+        let demand_active = ShareControlPdu::ServerDemandActive(
+            CapabilitiesPreset::Windows10.server_demand_active(self.desktop_width, self.desktop_height),
+        );
+        write_share_control_pdu(stream, demand_active)?;
+
+        match read_share_control_pdu(stream)? {
+            ShareControlPdu::ClientConfirmActive(_) => Ok(()),
+            pdu => Err(unexpected("Client Confirm Active PDU", pdu.as_short_name())),
+        }
+    }
+}
+
+fn accept_negotiation(stream: &mut TcpStream) -> io::Result<()> {
+    let frame = read_frame(stream)?.ok_or_else(unexpected_eof)?;
+    let request = nego::Request::from_buffer(frame.as_slice())?;
+    if !request.protocol.contains(nego::SecurityProtocol::SSL) {
+        return Err(invalid_data(format!(
+            "the client does not support TLS security, requested: {:?}",
+            request.protocol
+        )));
+    }
+
+    let response = nego::Response {
+        response: Some(nego::ResponseData::Response {
+            flags: nego::ResponseFlags::empty(),
+            protocol: nego::SecurityProtocol::SSL,
+        }),
+        dst_ref: 0,
+        src_ref: request.src_ref,
+    };
+    let mut buffer = Vec::with_capacity(response.buffer_length());
+    response.to_buffer(&mut buffer)?;
+
+    stream.write_all(&buffer)
+}
+
+fn accept_mcs_connect(stream: &mut TcpStream) -> io::Result<Vec<u16>> {
+    let frame = read_frame(stream)?.ok_or_else(unexpected_eof)?;
+    let mut payload = frame.as_slice();
+    Data::from_buffer(&mut payload)?;
+    let connect_initial = ConnectInitial::from_buffer(&mut payload)?;
+
+    let static_channels = (STATIC_CHANNELS_START_ID..)
+        .take(connect_initial.channel_names().unwrap_or_default().len())
+        .collect::<Vec<_>>();
+
+    let connect_response = ConnectResponse {
+        conference_create_response: ConferenceCreateResponse {
+            user_id: DEFAULT_USER_CHANNEL_ID,
+            gcc_blocks: ServerGccBlocks {
+                core: ServerCoreData {
+                    version: RdpVersion::V5_PLUS,
+                    optional_data: ServerCoreOptionalData {
+                        client_requested_protocols: Some(nego::SecurityProtocol::SSL),
+                        early_capability_flags: None,
+                    },
+                },
+                network: ServerNetworkData {
+                    channel_ids: static_channels.clone(),
+                    io_channel: IO_CHANNEL_ID,
+                },
+                security: ServerSecurityData::no_security(),
+                message_channel: None,
+                multi_transport_channel: None,
+            },
+        },
+        called_connect_id: 0,
+        domain_parameters: DomainParameters::target(),
+    };
+    let mut buffer = Vec::with_capacity(connect_response.buffer_length());
+    connect_response.to_buffer(&mut buffer)?;
+    write_x224_data(stream, &buffer)?;
+
+    Ok(static_channels)
+}
+
+/// Returns `false` if the client has disconnected right after the MCS connect, e.g. when probing the server.
+fn accept_mcs_domain(stream: &mut TcpStream, static_channels: Vec<u16>) -> io::Result<bool> {
+    match read_mcs_pdu(stream)?.0 {
+        McsPdu::ErectDomainRequest(_) => (),
+        McsPdu::DisconnectProviderUltimatum(_) => return Ok(false),
+        pdu => return Err(unexpected("MCS Erect Domain Request", pdu.as_short_name())),
+    }
+
+    match read_mcs_pdu(stream)?.0 {
+        McsPdu::AttachUserRequest => write_mcs_pdu(
+            stream,
+            &McsPdu::AttachUserConfirm(AttachUserConfirmPdu {
+                initiator_id: UserId(DEFAULT_USER_CHANNEL_ID),
+                result: 0,
+            }),
+        )?,
+        pdu => return Err(unexpected("MCS Attach User Request", pdu.as_short_name())),
+    }
+
+    // the static channels, the I/O channel and the user channel
+    for _ in 0..static_channels.len() + 2 {
+        match read_mcs_pdu(stream)?.0 {
+            McsPdu::ChannelJoinRequest(request) => write_mcs_pdu(
+                stream,
+                &McsPdu::ChannelJoinConfirm(ChannelJoinConfirmPdu {
+                    channel_id: request.channel_id,
+                    result: 0,
+                    initiator_id: request.initiator_id,
+                    requested_channel_id: request.channel_id,
+                }),
+            )?,
+            pdu => return Err(unexpected("MCS Channel Join Request", pdu.as_short_name())),
+        }
+    }
+
+    Ok(true)
+}
+
+fn accept_finalization(stream: &mut TcpStream) -> io::Result<()> {
+    loop {
+        let share_data_pdu = match read_share_control_pdu(stream)? {
+            ShareControlPdu::Data(header) => header.share_data_pdu,
+            pdu => return Err(unexpected("Data PDU", pdu.as_short_name())),
+        };
+
+        let response = match share_data_pdu {
+            ShareDataPdu::Synchronize(_) => ShareDataPdu::Synchronize(SynchronizePdu {
+                target_user_id: DEFAULT_USER_CHANNEL_ID,
+            }),
+            ShareDataPdu::Control(ControlPdu {
+                action: ControlAction::Cooperate,
+                ..
+            }) => ShareDataPdu::Control(ControlPdu {
+                action: ControlAction::Cooperate,
+                grant_id: 0,
+                control_id: 0,
+            }),
+            ShareDataPdu::Control(ControlPdu {
+                action: ControlAction::RequestControl,
+                ..
+            }) => ShareDataPdu::Control(ControlPdu {
+                action: ControlAction::GrantedControl,
+                grant_id: DEFAULT_USER_CHANNEL_ID,
+                control_id: u32::from(SERVER_CHANNEL_ID),
+            }),
+            ShareDataPdu::FontList(_) => {
+                let font_map = ShareDataPdu::FontMap(FontPdu {
+                    number: 0,
+                    total_number: 0,
+                    flags: SequenceFlags::FIRST | SequenceFlags::LAST,
+                    entry_size: FONT_MAP_ENTRY_SIZE,
+                });
+
+                return write_share_data_pdu(stream, font_map);
+            }
+            pdu => return Err(unexpected("Finalization PDU", pdu.as_short_name())),
+        };
+
+        write_share_data_pdu(stream, response)?;
+    }
+}
+
+fn echo_input_events(stream: &mut TcpStream) -> io::Result<Vec<ReceivedInput>> {
+    let mut received_events = Vec::new();
+
+    while let Some(frame) = read_frame(stream)? {
+        if frame[0] == TPKT_VERSION {
+            let mut payload = frame.as_slice();
+            Data::from_buffer(&mut payload)?;
+
+            match McsPdu::from_buffer(&mut payload)? {
+                McsPdu::DisconnectProviderUltimatum(_) => break,
+                McsPdu::SendDataRequest(_) => {
+                    if let ShareControlPdu::Data(ShareDataHeader {
+                        share_data_pdu: ShareDataPdu::Input(InputEventPdu(events)),
+                        ..
+                    }) = ShareControlHeader::from_buffer(payload)?.share_control_pdu
+                    {
+                        for event in events {
+                            write_echo_bitmap(stream, format!("{:?}", event).as_bytes())?;
+                            received_events.push(ReceivedInput::SlowPath(event));
+                        }
+                    }
+                    // other slow-path PDUs (e.g. frame acknowledgements) are not relevant for the echo
+                    continue;
+                }
+                _ => continue,
+            }
+        }
+
+        let FastPathInput(events) = FastPathInput::from_buffer(frame.as_slice()).map_err(invalid_data)?;
+        for event in events {
+            write_echo_bitmap(stream, format!("{:?}", event).as_bytes())?;
+            received_events.push(ReceivedInput::FastPath(event));
+        }
+    }
+
+    Ok(received_events)
+}
+
+fn write_echo_bitmap(stream: &mut TcpStream, text: &[u8]) -> io::Result<()> {
+    let mut pixels = text.to_vec();
+    pixels.resize(
+        (text.len() + ECHO_ROW_ALIGNMENT - 1) / ECHO_ROW_ALIGNMENT * ECHO_ROW_ALIGNMENT,
+        0,
+    );
+    let width = pixels.len() as u16;
+
+    let bitmap = Bitmap {
+        rectangles_number: 1,
+        rectangles: vec![BitmapData {
+            rectangle: Rectangle {
+                left: 0,
+                top: 0,
+                right: u32::from(width) - 1,
+                bottom: 0,
+            },
+            width,
+            height: 1,
+            bits_per_pixel: ECHO_BITS_PER_PIXEL,
+            compression_flags: Compression::NOT_COMPRESSED,
+            bitmap_data_length: pixels.len(),
+            compressed_data_header: None,
+            bitmap_data: pixels.as_slice(),
+        }],
+    };
+    let mut update = vec![0; bitmap.buffer_length()];
+    bitmap
+        .to_buffer_consume(&mut update.as_mut_slice())
+        .map_err(invalid_data)?;
+
+    let update_pdu = FastPathUpdatePdu {
+        fragmentation: Fragmentation::Single,
+        update_code: UpdateCode::Bitmap,
+        compression_flags: CompressionFlags::empty(),
+        compression_type: CompressionType::K8,
+        data: update.as_slice(),
+    };
+    let header = FastPathHeader::new(EncryptionFlags::empty(), update_pdu.buffer_length());
+
+    let mut buffer = vec![0; header.buffer_length() + update_pdu.buffer_length()];
+    header.to_buffer(buffer.as_mut_slice()).map_err(invalid_data)?;
+    let header_length = header.buffer_length();
+    update_pdu
+        .to_buffer_consume(&mut &mut buffer[header_length..])
+        .map_err(invalid_data)?;
+
+    stream.write_all(&buffer)
+}
+
+fn read_frame(stream: &mut TcpStream) -> io::Result<Option<Vec<u8>>> {
+    let mut action = [0; 1];
+    if stream.read(&mut action)? == 0 {
+        return Ok(None);
+    }
+
+    let mut frame = action.to_vec();
+    let length = if action[0] == TPKT_VERSION {
+        frame.resize(TPKT_HEADER_SIZE, 0);
+        stream.read_exact(&mut frame[1..])?;
+
+        usize::from(u16::from_be_bytes([frame[2], frame[3]]))
+    } else {
+        let mut length = [0; 1];
+        stream.read_exact(&mut length)?;
+        frame.push(length[0]);
+
+        if length[0] & FAST_PATH_LONG_LENGTH_FLAG != 0 {
+            let mut low_byte = [0; 1];
+            stream.read_exact(&mut low_byte)?;
+            frame.push(low_byte[0]);
+
+            usize::from(u16::from_be_bytes([
+                length[0] & !FAST_PATH_LONG_LENGTH_FLAG,
+                low_byte[0],
+            ]))
+        } else {
+            usize::from(length[0])
+        }
+    };
+
+    let header_length = frame.len();
+    frame.resize(length, 0);
+    stream.read_exact(&mut frame[header_length..])?;
+
+    Ok(Some(frame))
+}
+
+fn read_mcs_pdu(stream: &mut TcpStream) -> io::Result<(McsPdu, Vec<u8>)> {
+    let frame = read_frame(stream)?.ok_or_else(unexpected_eof)?;
+    let mut payload = frame.as_slice();
+    Data::from_buffer(&mut payload)?;
+    let mcs_pdu = McsPdu::from_buffer(&mut payload)?;
+
+    Ok((mcs_pdu, payload.to_vec()))
+}
+
+fn read_send_data_request(stream: &mut TcpStream) -> io::Result<(SendDataContext, Vec<u8>)> {
+    match read_mcs_pdu(stream)? {
+        (McsPdu::SendDataRequest(context), payload) => Ok((context, payload)),
+        (pdu, _) => Err(unexpected("MCS Send Data Request", pdu.as_short_name())),
+    }
+}
+
+fn read_share_control_pdu(stream: &mut TcpStream) -> io::Result<ShareControlPdu> {
+    let (_, payload) = read_send_data_request(stream)?;
+    let header = ShareControlHeader::from_buffer(payload.as_slice())?;
+
+    Ok(header.share_control_pdu)
+}
+
+fn write_x224_data(stream: &mut TcpStream, payload: &[u8]) -> io::Result<()> {
+    let data = Data::new(payload.len());
+    let mut buffer = Vec::with_capacity(data.buffer_length() + payload.len());
+    data.to_buffer(&mut buffer)?;
+    buffer.extend_from_slice(payload);
+
+    stream.write_all(&buffer)
+}
+
+fn write_mcs_pdu(stream: &mut TcpStream, mcs_pdu: &McsPdu) -> io::Result<()> {
+    let mut buffer = Vec::with_capacity(mcs_pdu.buffer_length());
+    mcs_pdu.to_buffer(&mut buffer)?;
+
+    write_x224_data(stream, &buffer)
+}
+
+fn write_send_data_indication(stream: &mut TcpStream, payload: &[u8]) -> io::Result<()> {
+    let mcs_pdu = McsPdu::SendDataIndication(SendDataContext {
+        initiator_id: UserId(SERVER_CHANNEL_ID),
+        channel_id: ChannelId(IO_CHANNEL_ID),
+        pdu_length: payload.len(),
+    });
+    let mut buffer = Vec::with_capacity(mcs_pdu.buffer_length() + payload.len());
+    mcs_pdu.to_buffer(&mut buffer)?;
+    buffer.extend_from_slice(payload);
+
+    write_x224_data(stream, &buffer)
+}
+
+fn write_share_control_pdu(stream: &mut TcpStream, share_control_pdu: ShareControlPdu) -> io::Result<()> {
+    let header = ShareControlHeader {
+        share_control_pdu,
+        pdu_source: SERVER_CHANNEL_ID,
+        share_id: SHARE_ID,
+    };
+    let mut buffer = Vec::with_capacity(header.buffer_length());
+    header.to_buffer(&mut buffer)?;
+
+    write_send_data_indication(stream, &buffer)
+}
+
+fn write_share_data_pdu(stream: &mut TcpStream, share_data_pdu: ShareDataPdu) -> io::Result<()> {
+    write_share_control_pdu(
+        stream,
+        ShareControlPdu::Data(ShareDataHeader {
+            share_data_pdu,
+            stream_priority: StreamPriority::Medium,
+            compression_flags: CompressionFlags::empty(),
+            compression_type: CompressionType::K8,
+        }),
+    )
+}
+
+fn invalid_data(error: impl fmt::Display) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error.to_string())
+}
+
+fn unexpected(expected: &str, actual: &str) -> io::Error {
+    invalid_data(format!("expected {}, got {}", expected, actual))
+}
+
+fn unexpected_eof() -> io::Error {
+    io::Error::from(io::ErrorKind::UnexpectedEof)
+}
@@ -1,3 +1,5 @@
+pub mod rle;
+
 #[cfg(test)]
 mod tests;
 
@@ -6,15 +8,17 @@ use std::io::{self, Write};
 
 use bitflags::bitflags;
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
-use failure::Fail;
+use thiserror::Error;
 
 use crate::utils::{Rectangle, SplitTo};
 use crate::{impl_from_error, PduBufferParsing, PduParsing};
 
 pub const COMPRESSED_DATA_HEADER_SIZE: usize = 8;
-pub const BITMAP_DATA_MAIN_DATA_SIZE: usize = 12;
+pub const BITMAP_DATA_MAIN_DATA_SIZE: usize = 18;
 pub const FIRST_ROW_SIZE_VALUE: u16 = 0;
 
+const BITMAP_UPDATE_HEADER_SIZE: usize = 4;
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Bitmap<'a> {
     pub rectangles_number: usize,
@@ -54,7 +58,7 @@ impl<'a> PduBufferParsing<'a> for Bitmap<'a> {
     }
 
     fn buffer_length(&self) -> usize {
-        self.rectangles.iter().map(|b| b.buffer_length()).sum::<usize>()
+        BITMAP_UPDATE_HEADER_SIZE + self.rectangles.iter().map(|b| b.buffer_length()).sum::<usize>()
     }
 }
 
@@ -90,7 +94,10 @@ impl<'a> PduBufferParsing<'a> for BitmapData<'a> {
             });
         }
 
-        let compressed_data_header = if !compression_flags.contains(Compression::NOT_COMPRESSED) {
+        // the uncompressed bitmaps have no header either
+        let compressed_data_header = if compression_flags.contains(Compression::COMPRESSED_HDR)
+            && !compression_flags.contains(Compression::NOT_COMPRESSED)
+        {
             Some(CompressedDataHeader::from_buffer_consume(buffer)?)
         } else {
             None
@@ -216,22 +223,19 @@ bitflags! {
     }
 }
 
-#[derive(Debug, Fail)]
+#[derive(Debug, Error)]
 pub enum BitmapError {
-    #[fail(display = "IO error: {}", _0)]
-    IOError(#[fail(cause)] io::Error),
-    #[fail(display = "Invalid update type for Bitmap Update")]
+    #[error("IO error: {}", .0)]
+    IOError(#[source] io::Error),
+    #[error("Invalid update type for Bitmap Update")]
     InvalidUpdateType,
-    #[fail(
-        display = "Input buffer len is shorter than the data length: {} < {}",
-        actual, expected
-    )]
+    #[error("Input buffer len is shorter than the data length: {} < {}", .actual, .expected)]
     InvalidDataLength { actual: usize, expected: usize },
-    #[fail(display = "Compression is not supported for Bitmap data")]
+    #[error("Compression is not supported for Bitmap data")]
     NotSupportedCompression,
-    #[fail(display = "Invalid first row size, expected: {}, but got: {}", actual, expected)]
+    #[error("Invalid first row size, expected: {}, but got: {}", .actual, .expected)]
     InvalidFirstRowSize { actual: usize, expected: usize },
-    #[fail(display = "The width of the bitmap must be divisible by 4")]
+    #[error("The width of the bitmap must be divisible by 4")]
     InvalidScanWidth,
 }
 
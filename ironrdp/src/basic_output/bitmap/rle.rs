@@ -0,0 +1,281 @@
+//! Decompression of the interleaved RLE, which compresses the bitmaps of fewer than 32 bpp
+//! of the bitmap updates and of the bitmap cache orders.
+
+#[cfg(test)]
+mod tests;
+
+use std::io;
+
+use byteorder::{LittleEndian, ReadBytesExt};
+use thiserror::Error;
+
+use crate::impl_from_error;
+
+const REGULAR_BG_RUN: u8 = 0x00;
+const REGULAR_FG_RUN: u8 = 0x01;
+const REGULAR_FGBG_IMAGE: u8 = 0x02;
+const REGULAR_COLOR_RUN: u8 = 0x03;
+const REGULAR_COLOR_IMAGE: u8 = 0x04;
+const LITE_SET_FG_FG_RUN: u8 = 0x0c;
+const LITE_SET_FG_FGBG_IMAGE: u8 = 0x0d;
+const LITE_DITHERED_RUN: u8 = 0x0e;
+const MEGA_MEGA_BG_RUN: u8 = 0xf0;
+const MEGA_MEGA_FG_RUN: u8 = 0xf1;
+const MEGA_MEGA_FGBG_IMAGE: u8 = 0xf2;
+const MEGA_MEGA_COLOR_RUN: u8 = 0xf3;
+const MEGA_MEGA_COLOR_IMAGE: u8 = 0xf4;
+const MEGA_MEGA_SET_FG_RUN: u8 = 0xf6;
+const MEGA_MEGA_SET_FGBG_IMAGE: u8 = 0xf7;
+const MEGA_MEGA_DITHERED_RUN: u8 = 0xf8;
+const SPECIAL_FGBG_1: u8 = 0xf9;
+const SPECIAL_FGBG_2: u8 = 0xfa;
+const SPECIAL_WHITE: u8 = 0xfd;
+const SPECIAL_BLACK: u8 = 0xfe;
+
+const REGULAR_RUN_LENGTH_MASK: u8 = 0x1f;
+const LITE_RUN_LENGTH_MASK: u8 = 0x0f;
+const SPECIAL_FGBG_1_BITMASK: u8 = 0x03;
+const SPECIAL_FGBG_2_BITMASK: u8 = 0x05;
+const BLACK_PIXEL: u32 = 0;
+
+/// Decompresses the bitmap of 8, 15, 16 or 24 bpp into `output`, the rows of `width` pixels being stored
+/// from the bottom to the top as in the uncompressed bitmaps, but without padding.
+pub fn decompress(
+    mut input: &[u8],
+    width: usize,
+    height: usize,
+    bits_per_pixel: u16,
+    output: &mut Vec<u8>,
+) -> Result<(), RleError> {
+    let (bytes_per_pixel, white_pixel) = match bits_per_pixel {
+        8 => (1, 0xff),
+        15 => (2, 0x7fff),
+        16 => (2, 0xffff),
+        24 => (3, 0x00ff_ffff),
+        _ => return Err(RleError::UnsupportedBitsPerPixel(bits_per_pixel)),
+    };
+    let row_delta = width * bytes_per_pixel;
+
+    output.clear();
+    output.resize(row_delta * height, 0);
+    if row_delta == 0 {
+        return Ok(());
+    }
+
+    let mut destination = Destination {
+        buffer: output,
+        position: 0,
+        row_delta,
+        bytes_per_pixel,
+    };
+    let mut foreground = white_pixel;
+    // a background run following another one starts with a foreground pixel, the runs being split at those pixels
+    let mut insert_foreground = false;
+    // the first scanline has no previous one, its background being black
+    let mut first_line = true;
+
+    while !input.is_empty() {
+        if first_line && destination.position >= row_delta {
+            first_line = false;
+            insert_foreground = false;
+        }
+
+        let header = input.read_u8()?;
+        let code = order_code(header);
+
+        if code == REGULAR_BG_RUN || code == MEGA_MEGA_BG_RUN {
+            let mut run_length = run_length(&mut input, header, code)?;
+            if insert_foreground && run_length > 0 {
+                destination.write(destination.background(first_line) ^ foreground)?;
+                run_length -= 1;
+            }
+            for _ in 0..run_length {
+                destination.write(destination.background(first_line))?;
+            }
+
+            insert_foreground = true;
+            continue;
+        }
+        insert_foreground = false;
+
+        match code {
+            REGULAR_FG_RUN | MEGA_MEGA_FG_RUN | LITE_SET_FG_FG_RUN | MEGA_MEGA_SET_FG_RUN => {
+                let run_length = run_length(&mut input, header, code)?;
+                if code == LITE_SET_FG_FG_RUN || code == MEGA_MEGA_SET_FG_RUN {
+                    foreground = read_pixel(&mut input, bytes_per_pixel)?;
+                }
+
+                for _ in 0..run_length {
+                    destination.write(destination.background(first_line) ^ foreground)?;
+                }
+            }
+            LITE_DITHERED_RUN | MEGA_MEGA_DITHERED_RUN => {
+                let run_length = run_length(&mut input, header, code)?;
+                let first = read_pixel(&mut input, bytes_per_pixel)?;
+                let second = read_pixel(&mut input, bytes_per_pixel)?;
+
+                for _ in 0..run_length {
+                    destination.write(first)?;
+                    destination.write(second)?;
+                }
+            }
+            REGULAR_COLOR_RUN | MEGA_MEGA_COLOR_RUN => {
+                let run_length = run_length(&mut input, header, code)?;
+                let pixel = read_pixel(&mut input, bytes_per_pixel)?;
+
+                for _ in 0..run_length {
+                    destination.write(pixel)?;
+                }
+            }
+            REGULAR_FGBG_IMAGE | MEGA_MEGA_FGBG_IMAGE | LITE_SET_FG_FGBG_IMAGE | MEGA_MEGA_SET_FGBG_IMAGE => {
+                let mut run_length = run_length(&mut input, header, code)?;
+                if code == LITE_SET_FG_FGBG_IMAGE || code == MEGA_MEGA_SET_FGBG_IMAGE {
+                    foreground = read_pixel(&mut input, bytes_per_pixel)?;
+                }
+
+                while run_length > 0 {
+                    let bits = run_length.min(8);
+                    let bitmask = input.read_u8()?;
+                    destination.write_foreground_background(bitmask, bits, foreground, first_line)?;
+                    run_length -= bits;
+                }
+            }
+            REGULAR_COLOR_IMAGE | MEGA_MEGA_COLOR_IMAGE => {
+                let run_length = run_length(&mut input, header, code)?;
+
+                for _ in 0..run_length {
+                    let pixel = read_pixel(&mut input, bytes_per_pixel)?;
+                    destination.write(pixel)?;
+                }
+            }
+            SPECIAL_FGBG_1 => {
+                destination.write_foreground_background(SPECIAL_FGBG_1_BITMASK, 8, foreground, first_line)?
+            }
+            SPECIAL_FGBG_2 => {
+                destination.write_foreground_background(SPECIAL_FGBG_2_BITMASK, 8, foreground, first_line)?
+            }
+            SPECIAL_WHITE => destination.write(white_pixel)?,
+            SPECIAL_BLACK => destination.write(BLACK_PIXEL)?,
+            _ => return Err(RleError::InvalidOrderHeader(header)),
+        }
+    }
+
+    Ok(())
+}
+
+/// The regular orders are identified by the 3 highest bits of the header, the lite ones by the 4 highest bits,
+/// and the mega mega and special ones by the whole header.
+fn order_code(header: u8) -> u8 {
+    if header & 0xc0 != 0xc0 {
+        header >> 5
+    } else if header & 0xf0 != 0xf0 {
+        header >> 4
+    } else {
+        header
+    }
+}
+
+/// Reads the run length of the order, held by the low bits of the header or by the following bytes.
+/// The run lengths of the foreground/background images count pixels, one bit of their bitmasks each.
+fn run_length(input: &mut &[u8], header: u8, code: u8) -> io::Result<usize> {
+    let run_length = match code {
+        REGULAR_FGBG_IMAGE => match header & REGULAR_RUN_LENGTH_MASK {
+            0 => usize::from(input.read_u8()?) + 1,
+            run_length => usize::from(run_length) * 8,
+        },
+        LITE_SET_FG_FGBG_IMAGE => match header & LITE_RUN_LENGTH_MASK {
+            0 => usize::from(input.read_u8()?) + 1,
+            run_length => usize::from(run_length) * 8,
+        },
+        REGULAR_BG_RUN | REGULAR_FG_RUN | REGULAR_COLOR_RUN | REGULAR_COLOR_IMAGE => {
+            match header & REGULAR_RUN_LENGTH_MASK {
+                0 => usize::from(input.read_u8()?) + 32,
+                run_length => usize::from(run_length),
+            }
+        }
+        LITE_SET_FG_FG_RUN | LITE_DITHERED_RUN => match header & LITE_RUN_LENGTH_MASK {
+            0 => usize::from(input.read_u8()?) + 16,
+            run_length => usize::from(run_length),
+        },
+        _ => usize::from(input.read_u16::<LittleEndian>()?),
+    };
+
+    Ok(run_length)
+}
+
+fn read_pixel(input: &mut &[u8], bytes_per_pixel: usize) -> io::Result<u32> {
+    let mut pixel = [0; 4];
+    io::Read::read_exact(input, &mut pixel[..bytes_per_pixel])?;
+
+    Ok(u32::from_le_bytes(pixel))
+}
+
+struct Destination<'a> {
+    buffer: &'a mut [u8],
+    position: usize,
+    row_delta: usize,
+    bytes_per_pixel: usize,
+}
+
+impl Destination<'_> {
+    fn write(&mut self, pixel: u32) -> Result<(), RleError> {
+        let end = self.position + self.bytes_per_pixel;
+        let destination = self
+            .buffer
+            .get_mut(self.position..end)
+            .ok_or(RleError::BufferOverflow)?;
+        destination.copy_from_slice(&pixel.to_le_bytes()[..self.bytes_per_pixel]);
+        self.position = end;
+
+        Ok(())
+    }
+
+    /// The pixel of the previous scanline, or black in the first scanline.
+    fn background(&self, first_line: bool) -> u32 {
+        if first_line {
+            return BLACK_PIXEL;
+        }
+
+        let start = self.position - self.row_delta;
+        let mut pixel = [0; 4];
+        pixel[..self.bytes_per_pixel].copy_from_slice(&self.buffer[start..start + self.bytes_per_pixel]);
+
+        u32::from_le_bytes(pixel)
+    }
+
+    /// Writes `bits` pixels, the background ones being the clear bits of the bitmask, from the lowest one.
+    fn write_foreground_background(
+        &mut self,
+        bitmask: u8,
+        bits: usize,
+        foreground: u32,
+        first_line: bool,
+    ) -> Result<(), RleError> {
+        for bit in 0..bits {
+            let background = self.background(first_line);
+            let pixel = if bitmask & (1 << bit) != 0 {
+                background ^ foreground
+            } else {
+                background
+            };
+
+            self.write(pixel)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum RleError {
+    #[error("IO error: {}", .0)]
+    IOError(#[source] io::Error),
+    #[error("Received bitmap of unsupported color depth: {} bpp", .0)]
+    UnsupportedBitsPerPixel(u16),
+    #[error("Received invalid order header: {:#x}", .0)]
+    InvalidOrderHeader(u8),
+    #[error("Decompressed data exceeds the size of the bitmap")]
+    BufferOverflow,
+}
+
+impl_from_error!(io::Error, RleError, RleError::IOError);
@@ -0,0 +1,72 @@
+use super::*;
+
+fn decompress_to_vec(input: &[u8], width: usize, height: usize, bits_per_pixel: u16) -> Vec<u8> {
+    let mut output = Vec::new();
+    decompress(input, width, height, bits_per_pixel, &mut output).unwrap();
+
+    output
+}
+
+#[test]
+fn foreground_run_is_xored_with_previous_scanline() {
+    #[rustfmt::skip]
+    let input = [
+        // a color image of one pixel, then a color run of one pixel
+        0x81, 0x11, 0x11, 0x61, 0x33, 0x33,
+        // a foreground run of two white pixels
+        0x22,
+    ];
+
+    assert_eq!(
+        vec![0x11, 0x11, 0x33, 0x33, 0xee, 0xee, 0xcc, 0xcc],
+        decompress_to_vec(&input, 2, 2, 16)
+    );
+}
+
+#[test]
+fn background_run_following_another_starts_with_foreground_pixel() {
+    #[rustfmt::skip]
+    let input = [
+        // white and black pixels, then a background run of two pixels
+        0xfd, 0xfe, 0x02,
+        // a background run starting the scanline, then another one following it
+        0x01, 0x02,
+        // a foreground/background image of one pixel, setting the foreground
+        0xd0, 0x00, 0x34, 0x12, 0x01,
+    ];
+
+    #[rustfmt::skip]
+    assert_eq!(
+        vec![
+            0xff, 0xff, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0xff, 0xff, 0xff, 0xff, 0x00, 0x00, 0x34, 0x12,
+        ],
+        decompress_to_vec(&input, 4, 2, 16)
+    );
+}
+
+#[test]
+fn dithered_run_and_mega_mega_color_run_are_decompressed() {
+    #[rustfmt::skip]
+    let input = [
+        // a dithered run of one pair of pixels
+        0xe1, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06,
+        // a color run of two pixels, its length in the following bytes
+        0xf3, 0x02, 0x00, 0x07, 0x08, 0x09,
+    ];
+
+    assert_eq!(
+        vec![0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x07, 0x08, 0x09],
+        decompress_to_vec(&input, 4, 1, 24)
+    );
+}
+
+#[test]
+fn run_exceeding_bitmap_is_rejected() {
+    let input = [0x62, 0x00, 0x00];
+
+    assert!(matches!(
+        decompress(&input, 1, 1, 16, &mut Vec::new()),
+        Err(RleError::BufferOverflow)
+    ));
+}
@@ -78,3 +78,8 @@ fn bitmap_data_length_is_correct() {
     let actual = actual.rectangles.get(0).unwrap().bitmap_data.len();
     assert_eq!(BITMAP_BUFFER[30..].len(), actual)
 }
+
+#[test]
+fn buffer_length_is_correct_for_bitmap() {
+    assert_eq!(BITMAP_BUFFER.len(), BITMAP.buffer_length());
+}
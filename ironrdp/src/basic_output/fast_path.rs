@@ -6,12 +6,14 @@ use std::io::{self, Write};
 use bit_field::BitField;
 use bitflags::bitflags;
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
-use failure::Fail;
 use num_derive::{FromPrimitive, ToPrimitive};
 use num_traits::{FromPrimitive, ToPrimitive};
+use thiserror::Error;
 
 use super::bitmap::{Bitmap, BitmapError};
+use super::orders::OrdersError;
 use super::surface_commands::{SurfaceCommand, SurfaceCommandsError, SURFACE_COMMAND_HEADER_SIZE};
+use crate::rdp::{CompressionFlags, CompressionType};
 use crate::utils::SplitTo;
 use crate::{impl_from_error, per, PduBufferParsing, PduParsing};
 
@@ -24,6 +26,14 @@ pub struct FastPathHeader {
 }
 
 impl FastPathHeader {
+    pub fn new(flags: EncryptionFlags, data_length: usize) -> Self {
+        Self {
+            flags,
+            data_length,
+            forced_long_length: false,
+        }
+    }
+
     pub fn from_buffer_with_header(mut stream: impl io::Read, header: u8) -> Result<Self, FastPathError> {
         let flags = EncryptionFlags::from_bits_truncate(header.get_bits(6..8));
 
@@ -87,6 +97,8 @@ impl PduParsing for FastPathHeader {
 pub struct FastPathUpdatePdu<'a> {
     pub fragmentation: Fragmentation,
     pub update_code: UpdateCode,
+    pub compression_flags: CompressionFlags,
+    pub compression_type: CompressionType,
     pub data: &'a [u8],
 }
 
@@ -104,9 +116,17 @@ impl<'a> PduBufferParsing<'a> for FastPathUpdatePdu<'a> {
             Fragmentation::from_u8(fragmentation).ok_or(FastPathError::InvalidFragmentation(fragmentation))?;
 
         let compression = Compression::from_bits_truncate(header.get_bits(6..8));
-        if compression.contains(Compression::COMPRESSION_USED) {
-            return Err(FastPathError::CompressionNotSupported);
-        }
+        let (compression_flags, compression_type) = if compression.contains(Compression::COMPRESSION_USED) {
+            let compression_flags_with_type = buffer.read_u8()?;
+            let compression_flags = CompressionFlags::from_bits_truncate(compression_flags_with_type);
+            let compression_type = compression_flags_with_type.get_bits(..4);
+            let compression_type = CompressionType::from_u8(compression_type)
+                .ok_or(FastPathError::InvalidCompressionType(compression_type))?;
+
+            (compression_flags, compression_type)
+        } else {
+            (CompressionFlags::empty(), CompressionType::K8)
+        };
 
         let data_length = usize::from(buffer.read_u16::<LittleEndian>()?);
         if buffer.len() < data_length {
@@ -120,6 +140,8 @@ impl<'a> PduBufferParsing<'a> for FastPathUpdatePdu<'a> {
         Ok(Self {
             fragmentation,
             update_code,
+            compression_flags,
+            compression_type,
             data,
         })
     }
@@ -128,7 +150,13 @@ impl<'a> PduBufferParsing<'a> for FastPathUpdatePdu<'a> {
         let mut header = 0u8;
         header.set_bits(0..4, self.update_code.to_u8().unwrap());
         header.set_bits(4..6, self.fragmentation.to_u8().unwrap());
+        if !self.compression_flags.is_empty() {
+            header.set_bits(6..8, Compression::COMPRESSION_USED.bits());
+        }
         buffer.write_u8(header)?;
+        if !self.compression_flags.is_empty() {
+            buffer.write_u8(self.compression_flags.bits() | self.compression_type.to_u8().unwrap())?;
+        }
         buffer.write_u16::<LittleEndian>(self.data.len() as u16)?;
         buffer.write_all(self.data)?;
 
@@ -136,7 +164,9 @@ impl<'a> PduBufferParsing<'a> for FastPathUpdatePdu<'a> {
     }
 
     fn buffer_length(&self) -> usize {
-        3 + self.data.len()
+        let compression_flags_length = if self.compression_flags.is_empty() { 0 } else { 1 };
+
+        3 + compression_flags_length + self.data.len()
     }
 }
 
@@ -154,12 +184,11 @@ impl<'a> FastPathUpdate<'a> {
     pub fn from_buffer_consume_with_code(buffer: &mut &'a [u8], code: UpdateCode) -> Result<Self, FastPathError> {
         match code {
             UpdateCode::SurfaceCommands => {
-                let mut commands = Vec::with_capacity(1);
-                while buffer.len() >= SURFACE_COMMAND_HEADER_SIZE {
-                    commands.push(SurfaceCommand::from_buffer_consume(buffer)?);
-                }
+                let mut commands = SurfaceCommands::new(*buffer);
+                let surface_commands = commands.by_ref().collect::<Result<Vec<_>, _>>()?;
+                *buffer = commands.buffer;
 
-                Ok(Self::SurfaceCommands(commands))
+                Ok(Self::SurfaceCommands(surface_commands))
             }
             UpdateCode::Bitmap => {
                 let bitmap = Bitmap::from_buffer_consume(buffer).map_err(FastPathError::BitmapError)?;
@@ -199,6 +228,36 @@ impl<'a> FastPathUpdate<'a> {
     }
 }
 
+/// Parses the surface commands of a Fast-Path update one at a time, borrowing their data from the buffer,
+/// so that they can be decoded without collecting them first. The parsing stops at the first error.
+#[derive(Debug, Clone)]
+pub struct SurfaceCommands<'a> {
+    buffer: &'a [u8],
+}
+
+impl<'a> SurfaceCommands<'a> {
+    pub fn new(buffer: &'a [u8]) -> Self {
+        Self { buffer }
+    }
+}
+
+impl<'a> Iterator for SurfaceCommands<'a> {
+    type Item = Result<SurfaceCommand<'a>, FastPathError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.buffer.len() < SURFACE_COMMAND_HEADER_SIZE {
+            return None;
+        }
+
+        let command = SurfaceCommand::from_buffer_consume(&mut self.buffer).map_err(FastPathError::from);
+        if command.is_err() {
+            self.buffer = &[];
+        }
+
+        Some(command)
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq, FromPrimitive, ToPrimitive)]
 pub enum UpdateCode {
     Orders = 0x0,
@@ -246,30 +305,35 @@ bitflags! {
 }
 
 /// The type of a Fast-Path parsing error. Includes *length error* and *I/O error*.
-#[derive(Debug, Fail)]
+#[derive(Debug, Error)]
 pub enum FastPathError {
     /// May be used in I/O related errors such as receiving empty Fast-Path packages.
-    #[fail(display = "IO error: {}", _0)]
-    IOError(#[fail(cause)] io::Error),
-    #[fail(display = "Surface Commands error: {}", _0)]
-    SurfaceCommandsError(#[fail(cause)] SurfaceCommandsError),
-    #[fail(display = "Bitmap error: {}", _0)]
-    BitmapError(#[fail(cause)] BitmapError),
+    #[error("IO error: {}", .0)]
+    IOError(#[source] io::Error),
+    #[error("Surface Commands error: {}", .0)]
+    SurfaceCommandsError(#[source] SurfaceCommandsError),
+    #[error("Bitmap error: {}", .0)]
+    BitmapError(#[source] BitmapError),
+    #[error("Drawing orders error: {}", .0)]
+    OrdersError(#[source] OrdersError),
     /// Used in the length-related error during Fast-Path parsing.
-    #[fail(display = "Received invalid Fast-Path package with 0 length")]
+    #[error("Received invalid Fast-Path package with 0 length")]
     NullLength { bytes_read: usize },
-    #[fail(display = "Received invalid update code: {}", _0)]
+    #[error("Received invalid update code: {}", .0)]
     InvalidUpdateCode(u8),
-    #[fail(display = "Received invalid fragmentation: {}", _0)]
+    #[error("Received invalid fragmentation: {}", .0)]
     InvalidFragmentation(u8),
-    #[fail(display = "Received compressed Fast-Path package")]
+    #[error("Received compressed Fast-Path package")]
     CompressionNotSupported,
-    #[fail(display = "Input buffer is shorter then the data length: {} < {}", actual, expected)]
+    #[error("Received invalid compression type: {}", .0)]
+    InvalidCompressionType(u8),
+    #[error("Input buffer is shorter then the data length: {} < {}", .actual, .expected)]
     InvalidDataLength { expected: usize, actual: usize },
-    #[fail(display = "Received unsupported Fast-Path Update: {:?}", _0)]
+    #[error("Received unsupported Fast-Path Update: {:?}", .0)]
     UnsupportedFastPathUpdate(UpdateCode),
 }
 
 impl_from_error!(io::Error, FastPathError, FastPathError::IOError);
 impl_from_error!(SurfaceCommandsError, FastPathError, FastPathError::SurfaceCommandsError);
 impl_from_error!(BitmapError, FastPathError, FastPathError::BitmapError);
+impl_from_error!(OrdersError, FastPathError, FastPathError::OrdersError);
@@ -10,6 +10,7 @@ const FAST_PATH_UPDATE_PDU_BUFFER: [u8; 19] = [
 const FAST_PATH_UPDATE_PDU_WITH_LONG_LEN_BUFFER: [u8; 19] = [
     0x4, 0xff, 0x0, 0x4, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x4, 0x0, 0x1, 0x0, 0x0, 0x0, 0x0, 0x0,
 ];
+const FAST_PATH_UPDATE_PDU_WITH_COMPRESSION_FLAGS_BUFFER: [u8; 8] = [0x84, 0x82, 0x4, 0x0, 0x1, 0x2, 0x3, 0x4];
 const FAST_PATH_HEADER_WITH_FORCED_LONG_LEN_BUFFER: [u8; 3] = [0x80, 0x80, 0x08];
 
 const FAST_PATH_HEADER_WITH_SHORT_LEN_PDU: FastPathHeader = FastPathHeader {
@@ -32,8 +33,17 @@ lazy_static! {
     static ref FAST_PATH_UPDATE_PDU: FastPathUpdatePdu<'static> = FastPathUpdatePdu {
         fragmentation: Fragmentation::Single,
         update_code: UpdateCode::SurfaceCommands,
+        compression_flags: CompressionFlags::empty(),
+        compression_type: CompressionType::K8,
         data: &FAST_PATH_UPDATE_PDU_BUFFER[3..],
     };
+    static ref FAST_PATH_UPDATE_PDU_WITH_COMPRESSION_FLAGS: FastPathUpdatePdu<'static> = FastPathUpdatePdu {
+        fragmentation: Fragmentation::Single,
+        update_code: UpdateCode::SurfaceCommands,
+        compression_flags: CompressionFlags::FLUSHED,
+        compression_type: CompressionType::Rdp6,
+        data: &FAST_PATH_UPDATE_PDU_WITH_COMPRESSION_FLAGS_BUFFER[4..],
+    };
 }
 
 #[test]
@@ -145,3 +155,52 @@ fn to_buffer_correctly_serializes_fast_path_update() {
 fn buffer_length_is_correct_for_fast_path_update() {
     assert_eq!(FAST_PATH_UPDATE_PDU_BUFFER.len(), FAST_PATH_UPDATE_PDU.buffer_length());
 }
+
+#[test]
+fn from_buffer_correctly_parses_fast_path_update_with_compression_flags() {
+    assert_eq!(
+        *FAST_PATH_UPDATE_PDU_WITH_COMPRESSION_FLAGS,
+        FastPathUpdatePdu::from_buffer(FAST_PATH_UPDATE_PDU_WITH_COMPRESSION_FLAGS_BUFFER.as_ref()).unwrap()
+    );
+}
+
+#[test]
+fn to_buffer_correctly_serializes_fast_path_update_with_compression_flags() {
+    let expected = FAST_PATH_UPDATE_PDU_WITH_COMPRESSION_FLAGS_BUFFER.as_ref();
+    let mut buffer = vec![0; expected.len()];
+
+    FAST_PATH_UPDATE_PDU_WITH_COMPRESSION_FLAGS
+        .to_buffer_consume(&mut buffer.as_mut_slice())
+        .unwrap();
+    assert_eq!(expected, buffer.as_slice());
+}
+
+#[test]
+fn buffer_length_is_correct_for_fast_path_update_with_compression_flags() {
+    assert_eq!(
+        FAST_PATH_UPDATE_PDU_WITH_COMPRESSION_FLAGS_BUFFER.len(),
+        FAST_PATH_UPDATE_PDU_WITH_COMPRESSION_FLAGS.buffer_length()
+    );
+}
+
+#[test]
+fn surface_commands_are_parsed_one_at_a_time() {
+    let data = FAST_PATH_UPDATE_PDU.data;
+    let mut commands = SurfaceCommands::new(data);
+
+    let first = commands.next().unwrap().unwrap();
+    let second = commands.next().unwrap().unwrap();
+    assert!(commands.next().is_none());
+    assert_eq!(
+        FastPathUpdate::SurfaceCommands(vec![first, second]),
+        FastPathUpdate::from_buffer_with_code(data, UpdateCode::SurfaceCommands).unwrap()
+    );
+}
+
+#[test]
+fn surface_commands_stop_at_the_first_error() {
+    let mut commands = SurfaceCommands::new(&FAST_PATH_UPDATE_PDU.data[..5]);
+
+    assert!(commands.next().unwrap().is_err());
+    assert!(commands.next().is_none());
+}
@@ -0,0 +1,436 @@
+#[cfg(test)]
+mod tests;
+
+use std::io;
+
+use bitflags::bitflags;
+use byteorder::{LittleEndian, ReadBytesExt};
+use num_derive::{FromPrimitive, ToPrimitive};
+use num_traits::FromPrimitive;
+use thiserror::Error;
+
+use super::bitmap::{BitmapError, CompressedDataHeader};
+use crate::utils::SplitTo;
+use crate::{impl_from_error, PduBufferParsing};
+
+/// The cache index of the bitmaps put on the waiting list instead of the cache.
+pub const BITMAP_CACHE_WAITING_LIST_INDEX: u16 = 32767;
+/// The raster operation copying the source bitmap to the destination.
+pub const SRCCOPY_ROP: u8 = 0xcc;
+
+const MEM_BLT_ORDER_TYPE: u8 = 0x0d;
+const PAT_BLT_ORDER_TYPE: u8 = 0x01;
+const MEM_BLT_FIELD_BYTES: usize = 2;
+/// The difference between the length of a secondary order following its header and its encoded length.
+const SECONDARY_ORDER_LENGTH_ADJUSTMENT: i32 = 7;
+
+bitflags! {
+    pub struct ControlFlags: u8 {
+        const STANDARD = 0x01;
+        const SECONDARY = 0x02;
+        const BOUNDS = 0x04;
+        const TYPE_CHANGE = 0x08;
+        const DELTA_COORDINATES = 0x10;
+        const ZERO_BOUNDS_DELTAS = 0x20;
+        const ZERO_FIELD_BYTE_BIT0 = 0x40;
+        const ZERO_FIELD_BYTE_BIT1 = 0x80;
+    }
+}
+
+bitflags! {
+    struct BoundsFlags: u8 {
+        const LEFT = 0x01;
+        const TOP = 0x02;
+        const RIGHT = 0x04;
+        const BOTTOM = 0x08;
+        const DELTA_LEFT = 0x10;
+        const DELTA_TOP = 0x20;
+        const DELTA_RIGHT = 0x40;
+        const DELTA_BOTTOM = 0x80;
+    }
+}
+
+bitflags! {
+    pub struct CacheBitmapRev2Flags: u16 {
+        const HEIGHT_SAME_AS_WIDTH = 0x01;
+        const PERSISTENT_KEY_PRESENT = 0x02;
+        const NO_BITMAP_COMPRESSION_HDR = 0x08;
+        const DO_NOT_CACHE = 0x10;
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, FromPrimitive, ToPrimitive)]
+pub enum SecondaryOrderType {
+    CacheBitmap = 0x00,
+    CacheColorTable = 0x01,
+    CacheBitmapCompressed = 0x02,
+    CacheGlyph = 0x03,
+    CacheBitmapRev2 = 0x04,
+    CacheBitmapCompressedRev2 = 0x05,
+    CacheBrush = 0x07,
+    CacheBitmapRev3 = 0x08,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DrawingOrder<'a> {
+    /// Draws a cached bitmap, clipped to the bounds if any.
+    MemBlt {
+        order: MemBlt,
+        bounds: Option<Bounds>,
+    },
+    CacheBitmapRev2(CacheBitmapRev2<'a>),
+    /// A secondary order which has been skipped.
+    UnsupportedSecondary(SecondaryOrderType),
+}
+
+/// The clipping rectangle of a primary order, its right and bottom bounds being inclusive.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct Bounds {
+    pub left: i16,
+    pub top: i16,
+    pub right: i16,
+    pub bottom: i16,
+}
+
+/// Copies the part of a cached bitmap starting at the source coordinates to the destination rectangle.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct MemBlt {
+    pub cache_id: u8,
+    pub color_table_index: u8,
+    pub left: i16,
+    pub top: i16,
+    pub width: i16,
+    pub height: i16,
+    pub rop: u8,
+    pub source_x: i16,
+    pub source_y: i16,
+    pub cache_index: u16,
+}
+
+/// The last primary order, which the fields omitted from the following primary orders are taken from.
+/// It is kept from one Orders update to the next.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrimaryOrderState {
+    order_type: u8,
+    bounds: Bounds,
+    mem_blt: MemBlt,
+}
+
+impl Default for PrimaryOrderState {
+    fn default() -> Self {
+        Self {
+            order_type: PAT_BLT_ORDER_TYPE,
+            bounds: Bounds::default(),
+            mem_blt: MemBlt::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheBitmapRev2<'a> {
+    pub cache_id: u8,
+    pub bits_per_pixel: u16,
+    pub flags: CacheBitmapRev2Flags,
+    pub persistent_key: Option<u64>,
+    pub width: u16,
+    pub height: u16,
+    pub cache_index: u16,
+    pub compressed: bool,
+    pub compressed_data_header: Option<CompressedDataHeader>,
+    pub bitmap_data: &'a [u8],
+}
+
+impl<'a> CacheBitmapRev2<'a> {
+    pub fn from_buffer_with_header(
+        buffer: &mut &'a [u8],
+        extra_flags: u16,
+        compressed: bool,
+    ) -> Result<Self, OrdersError> {
+        let cache_id = (extra_flags & 0x0007) as u8;
+        let bits_per_pixel_id = ((extra_flags & 0x0078) >> 3) as u8;
+        let bits_per_pixel = match bits_per_pixel_id {
+            0x03 => 8,
+            0x04 => 16,
+            0x05 => 24,
+            0x06 => 32,
+            _ => return Err(OrdersError::InvalidBitsPerPixelId(bits_per_pixel_id)),
+        };
+        let flags = CacheBitmapRev2Flags::from_bits_truncate(extra_flags >> 7);
+
+        let persistent_key = if flags.contains(CacheBitmapRev2Flags::PERSISTENT_KEY_PRESENT) {
+            let key1 = buffer.read_u32::<LittleEndian>()?;
+            let key2 = buffer.read_u32::<LittleEndian>()?;
+
+            Some((u64::from(key2) << 32) | u64::from(key1))
+        } else {
+            None
+        };
+
+        let width = read_two_byte_unsigned(buffer)?;
+        let height = if flags.contains(CacheBitmapRev2Flags::HEIGHT_SAME_AS_WIDTH) {
+            width
+        } else {
+            read_two_byte_unsigned(buffer)?
+        };
+        let bitmap_length = read_four_byte_unsigned(buffer)? as usize;
+        let cache_index = read_two_byte_unsigned(buffer)?;
+
+        let compressed_data_header = if compressed && !flags.contains(CacheBitmapRev2Flags::NO_BITMAP_COMPRESSION_HDR) {
+            Some(CompressedDataHeader::from_buffer_consume(buffer)?)
+        } else {
+            None
+        };
+        let bitmap_length = compressed_data_header
+            .as_ref()
+            .map_or(bitmap_length, |header| usize::from(header.main_body_size));
+
+        if buffer.len() < bitmap_length {
+            return Err(OrdersError::InvalidDataLength {
+                expected: bitmap_length,
+                actual: buffer.len(),
+            });
+        }
+        let bitmap_data = buffer.split_to(bitmap_length);
+
+        Ok(Self {
+            cache_id,
+            bits_per_pixel,
+            flags,
+            persistent_key,
+            width,
+            height,
+            cache_index,
+            compressed,
+            compressed_data_header,
+            bitmap_data,
+        })
+    }
+}
+
+/// Parses the drawing orders of a Fast-Path Orders update one at a time, borrowing their data from the buffer.
+/// Only the MemBlt primary order is supported, an unsupported primary or alternate secondary order ending
+/// the parsing since its length is unknown. The parsing stops at the first error.
+#[derive(Debug)]
+pub struct DrawingOrders<'a, 's> {
+    buffer: &'a [u8],
+    remaining_orders: u16,
+    state: &'s mut PrimaryOrderState,
+}
+
+impl<'a, 's> DrawingOrders<'a, 's> {
+    pub fn from_buffer(mut buffer: &'a [u8], state: &'s mut PrimaryOrderState) -> Result<Self, OrdersError> {
+        let remaining_orders = buffer.read_u16::<LittleEndian>()?;
+
+        Ok(Self {
+            buffer,
+            remaining_orders,
+            state,
+        })
+    }
+
+    fn read_order(&mut self) -> Result<DrawingOrder<'a>, OrdersError> {
+        let control_flags = ControlFlags::from_bits_truncate(self.buffer.read_u8()?);
+
+        if control_flags.contains(ControlFlags::STANDARD | ControlFlags::SECONDARY) {
+            self.read_secondary_order()
+        } else if control_flags.contains(ControlFlags::STANDARD) {
+            self.read_primary_order(control_flags)
+        } else if control_flags.contains(ControlFlags::SECONDARY) {
+            Err(OrdersError::UnsupportedAlternateSecondaryOrder(
+                control_flags.bits() >> 2,
+            ))
+        } else {
+            Err(OrdersError::InvalidControlFlags(control_flags))
+        }
+    }
+
+    fn read_primary_order(&mut self, control_flags: ControlFlags) -> Result<DrawingOrder<'a>, OrdersError> {
+        if control_flags.contains(ControlFlags::TYPE_CHANGE) {
+            self.state.order_type = self.buffer.read_u8()?;
+        }
+        if self.state.order_type != MEM_BLT_ORDER_TYPE {
+            return Err(OrdersError::UnsupportedPrimaryOrder(self.state.order_type));
+        }
+
+        let mut field_bytes = MEM_BLT_FIELD_BYTES;
+        if control_flags.contains(ControlFlags::ZERO_FIELD_BYTE_BIT0) {
+            field_bytes = field_bytes.saturating_sub(1);
+        }
+        if control_flags.contains(ControlFlags::ZERO_FIELD_BYTE_BIT1) {
+            field_bytes = field_bytes.saturating_sub(2);
+        }
+        let mut field_flags = 0u16;
+        for byte in 0..field_bytes {
+            field_flags |= u16::from(self.buffer.read_u8()?) << (8 * byte);
+        }
+
+        let bounds = if control_flags.contains(ControlFlags::BOUNDS) {
+            if !control_flags.contains(ControlFlags::ZERO_BOUNDS_DELTAS) {
+                read_bounds(&mut self.buffer, &mut self.state.bounds)?;
+            }
+
+            Some(self.state.bounds)
+        } else {
+            None
+        };
+
+        let delta = control_flags.contains(ControlFlags::DELTA_COORDINATES);
+        let buffer = &mut self.buffer;
+        let order = &mut self.state.mem_blt;
+        if field_flags & 0x0001 != 0 {
+            let [cache_id, color_table_index] = buffer.read_u16::<LittleEndian>()?.to_le_bytes();
+            order.cache_id = cache_id;
+            order.color_table_index = color_table_index;
+        }
+        if field_flags & 0x0002 != 0 {
+            read_coordinate(buffer, delta, &mut order.left)?;
+        }
+        if field_flags & 0x0004 != 0 {
+            read_coordinate(buffer, delta, &mut order.top)?;
+        }
+        if field_flags & 0x0008 != 0 {
+            read_coordinate(buffer, delta, &mut order.width)?;
+        }
+        if field_flags & 0x0010 != 0 {
+            read_coordinate(buffer, delta, &mut order.height)?;
+        }
+        if field_flags & 0x0020 != 0 {
+            order.rop = buffer.read_u8()?;
+        }
+        if field_flags & 0x0040 != 0 {
+            read_coordinate(buffer, delta, &mut order.source_x)?;
+        }
+        if field_flags & 0x0080 != 0 {
+            read_coordinate(buffer, delta, &mut order.source_y)?;
+        }
+        if field_flags & 0x0100 != 0 {
+            order.cache_index = buffer.read_u16::<LittleEndian>()?;
+        }
+
+        Ok(DrawingOrder::MemBlt { order: *order, bounds })
+    }
+
+    fn read_secondary_order(&mut self) -> Result<DrawingOrder<'a>, OrdersError> {
+        let order_length = self.buffer.read_i16::<LittleEndian>()?;
+        let extra_flags = self.buffer.read_u16::<LittleEndian>()?;
+        let order_type = self.buffer.read_u8()?;
+
+        let length = usize::try_from(i32::from(order_length) + SECONDARY_ORDER_LENGTH_ADJUSTMENT)
+            .map_err(|_| OrdersError::InvalidOrderLength(order_length))?;
+        if self.buffer.len() < length {
+            return Err(OrdersError::InvalidDataLength {
+                expected: length,
+                actual: self.buffer.len(),
+            });
+        }
+        let mut order_data = self.buffer.split_to(length);
+
+        let order_type =
+            SecondaryOrderType::from_u8(order_type).ok_or(OrdersError::InvalidSecondaryOrderType(order_type))?;
+        match order_type {
+            SecondaryOrderType::CacheBitmapRev2 => Ok(DrawingOrder::CacheBitmapRev2(
+                CacheBitmapRev2::from_buffer_with_header(&mut order_data, extra_flags, false)?,
+            )),
+            SecondaryOrderType::CacheBitmapCompressedRev2 => Ok(DrawingOrder::CacheBitmapRev2(
+                CacheBitmapRev2::from_buffer_with_header(&mut order_data, extra_flags, true)?,
+            )),
+            order_type => Ok(DrawingOrder::UnsupportedSecondary(order_type)),
+        }
+    }
+}
+
+impl<'a, 's> Iterator for DrawingOrders<'a, 's> {
+    type Item = Result<DrawingOrder<'a>, OrdersError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining_orders == 0 {
+            return None;
+        }
+        self.remaining_orders -= 1;
+
+        let order = self.read_order();
+        if order.is_err() {
+            self.remaining_orders = 0;
+        }
+
+        Some(order)
+    }
+}
+
+/// Updates the bounds present in the order, each of them being either absolute or relative to the previous value.
+fn read_bounds(buffer: &mut &[u8], bounds: &mut Bounds) -> io::Result<()> {
+    let flags = BoundsFlags::from_bits_truncate(buffer.read_u8()?);
+
+    for (absolute, relative, bound) in [
+        (BoundsFlags::LEFT, BoundsFlags::DELTA_LEFT, &mut bounds.left),
+        (BoundsFlags::TOP, BoundsFlags::DELTA_TOP, &mut bounds.top),
+        (BoundsFlags::RIGHT, BoundsFlags::DELTA_RIGHT, &mut bounds.right),
+        (BoundsFlags::BOTTOM, BoundsFlags::DELTA_BOTTOM, &mut bounds.bottom),
+    ] {
+        if flags.contains(absolute) {
+            *bound = buffer.read_i16::<LittleEndian>()?;
+        } else if flags.contains(relative) {
+            *bound = bound.wrapping_add(i16::from(buffer.read_i8()?));
+        }
+    }
+
+    Ok(())
+}
+
+fn read_coordinate(buffer: &mut &[u8], delta: bool, coordinate: &mut i16) -> io::Result<()> {
+    *coordinate = if delta {
+        coordinate.wrapping_add(i16::from(buffer.read_i8()?))
+    } else {
+        buffer.read_i16::<LittleEndian>()?
+    };
+
+    Ok(())
+}
+
+/// Reads a 15-bit value, stored on two bytes if the highest bit of the first one is set.
+fn read_two_byte_unsigned(buffer: &mut &[u8]) -> io::Result<u16> {
+    let first = buffer.read_u8()?;
+
+    if first & 0x80 != 0 {
+        Ok((u16::from(first & 0x7f) << 8) | u16::from(buffer.read_u8()?))
+    } else {
+        Ok(u16::from(first))
+    }
+}
+
+/// Reads a 30-bit value, the two highest bits of the first byte being the number of bytes following it.
+fn read_four_byte_unsigned(buffer: &mut &[u8]) -> io::Result<u32> {
+    let first = buffer.read_u8()?;
+
+    let mut value = u32::from(first & 0x3f);
+    for _ in 0..first >> 6 {
+        value = (value << 8) | u32::from(buffer.read_u8()?);
+    }
+
+    Ok(value)
+}
+
+#[derive(Debug, Error)]
+pub enum OrdersError {
+    #[error("IO error: {}", .0)]
+    IOError(#[source] io::Error),
+    #[error("Bitmap error: {}", .0)]
+    BitmapError(#[source] BitmapError),
+    #[error("Received invalid drawing order control flags: {:?}", .0)]
+    InvalidControlFlags(ControlFlags),
+    #[error("Received unsupported primary drawing order: {}", .0)]
+    UnsupportedPrimaryOrder(u8),
+    #[error("Received unsupported alternate secondary drawing order: {}", .0)]
+    UnsupportedAlternateSecondaryOrder(u8),
+    #[error("Received invalid secondary drawing order type: {}", .0)]
+    InvalidSecondaryOrderType(u8),
+    #[error("Received invalid secondary drawing order length: {}", .0)]
+    InvalidOrderLength(i16),
+    #[error("Input buffer is shorter then the data length: {} < {}", .actual, .expected)]
+    InvalidDataLength { expected: usize, actual: usize },
+    #[error("Received invalid bits per pixel ID: {}", .0)]
+    InvalidBitsPerPixelId(u8),
+}
+
+impl_from_error!(io::Error, OrdersError, OrdersError::IOError);
+impl_from_error!(BitmapError, OrdersError, OrdersError::BitmapError);
@@ -0,0 +1,157 @@
+use super::*;
+
+#[rustfmt::skip]
+const CACHE_BITMAP_REV2_ORDERS: [u8; 41] = [
+    0x02, 0x00, // numberOrders
+    // Cache Color Table, skipped
+    0x03, 0x00, 0x00, 0x00, 0x00, 0x01,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    // Cache Bitmap (Revision 2) of 32 bpp in the cache 1, its height being the same as its width
+    0x03, 0x0d, 0x00, 0xb1, 0x00, 0x04,
+    0x02, // bitmapWidth
+    0x10, // bitmapLength
+    0x81, 0x2c, // cacheIndex
+    0xab, 0xab, 0xab, 0xab, 0xab, 0xab, 0xab, 0xab, 0xab, 0xab, 0xab, 0xab, 0xab, 0xab, 0xab, 0xab,
+];
+
+#[rustfmt::skip]
+const COMPRESSED_CACHE_BITMAP_REV2_ORDERS: [u8; 33] = [
+    0x01, 0x00, // numberOrders
+    // Compressed Cache Bitmap (Revision 2) of 24 bpp in the cache 2, with a persistent key
+    0x03, 0x12, 0x00, 0x2a, 0x01, 0x05,
+    0x01, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, // key1, key2
+    0x04, // bitmapWidth
+    0x02, // bitmapHeight
+    0x40, 0x0c, // bitmapLength
+    0x05, // cacheIndex
+    0x00, 0x00, 0x04, 0x00, 0x0c, 0x00, 0x18, 0x00, // bitmapComprHdr
+    0x01, 0x02, 0x03, 0x04,
+];
+
+#[rustfmt::skip]
+const MEM_BLT_ORDERS: [u8; 36] = [
+    0x03, 0x00, // numberOrders
+    // all the fields and absolute bounds
+    0x0d, 0x0d, 0xff, 0x01,
+    0x0f, 0x00, 0x00, 0x00, 0x00, 0x63, 0x00, 0x63, 0x00,
+    0x01, 0x00, 0x0a, 0x00, 0x14, 0x00, 0x02, 0x00, 0x02, 0x00, 0xcc, 0x00, 0x00, 0x00, 0x00, 0x2c, 0x01,
+    // the left coordinate moved by -5, without bounds
+    0x51, 0x02, 0xfb,
+    // no field changed, with the previous bounds
+    0xa5,
+];
+
+fn parse(buffer: &[u8]) -> Vec<Result<DrawingOrder<'_>, OrdersError>> {
+    let mut state = PrimaryOrderState::default();
+
+    DrawingOrders::from_buffer(buffer, &mut state).unwrap().collect()
+}
+
+#[test]
+fn cache_bitmap_rev2_is_parsed_after_skipped_secondary_order() {
+    let orders = parse(&CACHE_BITMAP_REV2_ORDERS)
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+
+    assert_eq!(
+        vec![
+            DrawingOrder::UnsupportedSecondary(SecondaryOrderType::CacheColorTable),
+            DrawingOrder::CacheBitmapRev2(CacheBitmapRev2 {
+                cache_id: 1,
+                bits_per_pixel: 32,
+                flags: CacheBitmapRev2Flags::HEIGHT_SAME_AS_WIDTH,
+                persistent_key: None,
+                width: 2,
+                height: 2,
+                cache_index: 300,
+                compressed: false,
+                compressed_data_header: None,
+                bitmap_data: &CACHE_BITMAP_REV2_ORDERS[25..],
+            }),
+        ],
+        orders
+    );
+}
+
+#[test]
+fn compressed_cache_bitmap_rev2_is_parsed_with_its_header() {
+    let orders = parse(&COMPRESSED_CACHE_BITMAP_REV2_ORDERS)
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+
+    assert_eq!(
+        vec![DrawingOrder::CacheBitmapRev2(CacheBitmapRev2 {
+            cache_id: 2,
+            bits_per_pixel: 24,
+            flags: CacheBitmapRev2Flags::PERSISTENT_KEY_PRESENT,
+            persistent_key: Some(0x0000_0002_0000_0001),
+            width: 4,
+            height: 2,
+            cache_index: 5,
+            compressed: true,
+            compressed_data_header: Some(CompressedDataHeader {
+                main_body_size: 4,
+                scan_width: 12,
+                uncompressed_size: 24,
+            }),
+            bitmap_data: &[0x01, 0x02, 0x03, 0x04],
+        })],
+        orders
+    );
+}
+
+#[test]
+fn mem_blt_fields_are_relative_to_previous_order() {
+    let orders = parse(&MEM_BLT_ORDERS)
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+
+    let order = MemBlt {
+        cache_id: 1,
+        color_table_index: 0,
+        left: 10,
+        top: 20,
+        width: 2,
+        height: 2,
+        rop: SRCCOPY_ROP,
+        source_x: 0,
+        source_y: 0,
+        cache_index: 300,
+    };
+    let bounds = Bounds {
+        left: 0,
+        top: 0,
+        right: 99,
+        bottom: 99,
+    };
+    let moved_order = MemBlt { left: 5, ..order };
+
+    assert_eq!(
+        vec![
+            DrawingOrder::MemBlt {
+                order,
+                bounds: Some(bounds),
+            },
+            DrawingOrder::MemBlt {
+                order: moved_order,
+                bounds: None,
+            },
+            DrawingOrder::MemBlt {
+                order: moved_order,
+                bounds: Some(bounds),
+            },
+        ],
+        orders
+    );
+}
+
+#[test]
+fn unsupported_primary_order_ends_parsing() {
+    let orders = parse(&[0x02, 0x00, 0x09, 0x01, 0x00]);
+
+    assert_eq!(1, orders.len());
+    assert!(matches!(orders[0], Err(OrdersError::UnsupportedPrimaryOrder(0x01))));
+}
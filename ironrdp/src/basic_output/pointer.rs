@@ -0,0 +1,247 @@
+//! The pointer updates of the Fast-Path and slow-path outputs, moving the pointer or changing its shape.
+
+#[cfg(test)]
+mod tests;
+
+use std::io;
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use num_derive::{FromPrimitive, ToPrimitive};
+use num_traits::{FromPrimitive, ToPrimitive};
+use thiserror::Error;
+
+use super::fast_path::UpdateCode;
+use crate::impl_from_error;
+use crate::utils::SplitTo;
+use crate::PduParsing;
+
+/// The color depth of the XOR mask of the color pointer updates, the other updates carrying their own.
+const COLOR_POINTER_XOR_BPP: u16 = 24;
+
+const POINTER_PDU_HEADER_SIZE: usize = 4;
+
+const SYSPTR_NULL: u32 = 0;
+const SYSPTR_DEFAULT: u32 = 0x7F00;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PointerUpdate<'a> {
+    /// The pointer is hidden.
+    Hidden,
+    /// The pointer takes the default shape of the client's operating system.
+    Default,
+    /// The server has moved the pointer, e.g. to the default button of a dialog.
+    Position { x: u16, y: u16 },
+    /// The pointer takes the shape cached at the index.
+    Cached { cache_index: u16 },
+    /// The pointer takes the shape, which is cached at its index. Carried by the color, new and large pointer updates.
+    Shape(PointerShape<'a>),
+}
+
+/// A pointer shape, made of an AND mask of 1 bpp and of an XOR mask, whose rows are stored from the bottom to the top.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PointerShape<'a> {
+    pub xor_bpp: u16,
+    pub cache_index: u16,
+    pub hot_spot_x: u16,
+    pub hot_spot_y: u16,
+    pub width: u16,
+    pub height: u16,
+    pub xor_mask: &'a [u8],
+    pub and_mask: &'a [u8],
+}
+
+impl<'a> PointerUpdate<'a> {
+    /// Parses the update of the pointer update code, `None` being returned for the other codes.
+    pub fn from_buffer_with_code(mut buffer: &'a [u8], code: UpdateCode) -> Result<Option<Self>, PointerError> {
+        let update = match code {
+            UpdateCode::HiddenPointer => Self::Hidden,
+            UpdateCode::DefaultPointer => Self::Default,
+            UpdateCode::PositionPointer => Self::Position {
+                x: buffer.read_u16::<LittleEndian>()?,
+                y: buffer.read_u16::<LittleEndian>()?,
+            },
+            UpdateCode::CachedPointer => Self::Cached {
+                cache_index: buffer.read_u16::<LittleEndian>()?,
+            },
+            UpdateCode::ColorPointer => Self::Shape(PointerShape::from_buffer(&mut buffer, COLOR_POINTER_XOR_BPP)?),
+            UpdateCode::NewPointer => {
+                let xor_bpp = buffer.read_u16::<LittleEndian>()?;
+                Self::Shape(PointerShape::from_buffer(&mut buffer, xor_bpp)?)
+            }
+            UpdateCode::LargePointer => Self::Shape(PointerShape::from_buffer_large(&mut buffer)?),
+            _ => return Ok(None),
+        };
+
+        Ok(Some(update))
+    }
+
+    pub fn as_short_name(&self) -> &str {
+        match self {
+            Self::Hidden => "Hidden Pointer",
+            Self::Default => "Default Pointer",
+            Self::Position { .. } => "Pointer Position",
+            Self::Cached { .. } => "Cached Pointer",
+            Self::Shape(_) => "Pointer Shape",
+        }
+    }
+}
+
+/// The slow-path Pointer Update PDU, sent on the I/O channel by the servers not using the Fast-Path output.
+///
+/// The attributes of the update are kept as received, to be parsed by [`PointerPdu::update`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PointerPdu {
+    pub message_type: PointerMessageType,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, FromPrimitive, ToPrimitive)]
+pub enum PointerMessageType {
+    System = 0x0001,
+    Position = 0x0003,
+    Color = 0x0006,
+    Cached = 0x0007,
+    New = 0x0008,
+    Large = 0x0009,
+}
+
+impl PointerPdu {
+    pub fn update(&self) -> Result<PointerUpdate<'_>, PointerError> {
+        let mut buffer = self.data.as_slice();
+
+        let update = match self.message_type {
+            PointerMessageType::System => match buffer.read_u32::<LittleEndian>()? {
+                SYSPTR_NULL => PointerUpdate::Hidden,
+                SYSPTR_DEFAULT => PointerUpdate::Default,
+                system_pointer_type => return Err(PointerError::InvalidSystemPointerType(system_pointer_type)),
+            },
+            PointerMessageType::Position => PointerUpdate::Position {
+                x: buffer.read_u16::<LittleEndian>()?,
+                y: buffer.read_u16::<LittleEndian>()?,
+            },
+            PointerMessageType::Cached => PointerUpdate::Cached {
+                cache_index: buffer.read_u16::<LittleEndian>()?,
+            },
+            PointerMessageType::Color => {
+                PointerUpdate::Shape(PointerShape::from_buffer(&mut buffer, COLOR_POINTER_XOR_BPP)?)
+            }
+            PointerMessageType::New => {
+                let xor_bpp = buffer.read_u16::<LittleEndian>()?;
+                PointerUpdate::Shape(PointerShape::from_buffer(&mut buffer, xor_bpp)?)
+            }
+            PointerMessageType::Large => PointerUpdate::Shape(PointerShape::from_buffer_large(&mut buffer)?),
+        };
+
+        Ok(update)
+    }
+}
+
+impl PduParsing for PointerPdu {
+    type Error = PointerError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        let message_type = stream.read_u16::<LittleEndian>()?;
+        let message_type =
+            PointerMessageType::from_u16(message_type).ok_or(PointerError::InvalidMessageType(message_type))?;
+        let _padding = stream.read_u16::<LittleEndian>()?;
+
+        let mut data = Vec::new();
+        stream.read_to_end(&mut data)?;
+
+        Ok(Self { message_type, data })
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        stream.write_u16::<LittleEndian>(self.message_type.to_u16().unwrap())?;
+        stream.write_u16::<LittleEndian>(0)?; // padding
+        stream.write_all(&self.data)?;
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        POINTER_PDU_HEADER_SIZE + self.data.len()
+    }
+}
+
+impl<'a> PointerShape<'a> {
+    fn from_buffer(buffer: &mut &'a [u8], xor_bpp: u16) -> Result<Self, PointerError> {
+        let cache_index = buffer.read_u16::<LittleEndian>()?;
+        let hot_spot_x = buffer.read_u16::<LittleEndian>()?;
+        let hot_spot_y = buffer.read_u16::<LittleEndian>()?;
+        let width = buffer.read_u16::<LittleEndian>()?;
+        let height = buffer.read_u16::<LittleEndian>()?;
+        let and_mask_length = usize::from(buffer.read_u16::<LittleEndian>()?);
+        let xor_mask_length = usize::from(buffer.read_u16::<LittleEndian>()?);
+        let (xor_mask, and_mask) = read_masks(buffer, xor_mask_length, and_mask_length)?;
+
+        Ok(Self {
+            xor_bpp,
+            cache_index,
+            hot_spot_x,
+            hot_spot_y,
+            width,
+            height,
+            xor_mask,
+            and_mask,
+        })
+    }
+
+    /// The large pointers, up to 384x384 pixels, have masks of 32-bit lengths.
+    fn from_buffer_large(buffer: &mut &'a [u8]) -> Result<Self, PointerError> {
+        let xor_bpp = buffer.read_u16::<LittleEndian>()?;
+        let cache_index = buffer.read_u16::<LittleEndian>()?;
+        let hot_spot_x = buffer.read_u16::<LittleEndian>()?;
+        let hot_spot_y = buffer.read_u16::<LittleEndian>()?;
+        let width = buffer.read_u16::<LittleEndian>()?;
+        let height = buffer.read_u16::<LittleEndian>()?;
+        let and_mask_length = buffer.read_u32::<LittleEndian>()? as usize;
+        let xor_mask_length = buffer.read_u32::<LittleEndian>()? as usize;
+        let (xor_mask, and_mask) = read_masks(buffer, xor_mask_length, and_mask_length)?;
+
+        Ok(Self {
+            xor_bpp,
+            cache_index,
+            hot_spot_x,
+            hot_spot_y,
+            width,
+            height,
+            xor_mask,
+            and_mask,
+        })
+    }
+}
+
+/// The XOR mask precedes the AND mask, the padding byte which may follow them being ignored.
+fn read_masks<'a>(
+    buffer: &mut &'a [u8],
+    xor_mask_length: usize,
+    and_mask_length: usize,
+) -> Result<(&'a [u8], &'a [u8]), PointerError> {
+    let expected = xor_mask_length + and_mask_length;
+    if buffer.len() < expected {
+        return Err(PointerError::InvalidDataLength {
+            expected,
+            actual: buffer.len(),
+        });
+    }
+
+    let xor_mask = buffer.split_to(xor_mask_length);
+    let and_mask = buffer.split_to(and_mask_length);
+
+    Ok((xor_mask, and_mask))
+}
+
+#[derive(Debug, Error)]
+pub enum PointerError {
+    #[error("IO error: {}", .0)]
+    IOError(#[source] io::Error),
+    #[error("Input buffer is shorter then the data length: {} < {}", .actual, .expected)]
+    InvalidDataLength { expected: usize, actual: usize },
+    #[error("Invalid pointer message type: {}", .0)]
+    InvalidMessageType(u16),
+    #[error("Invalid system pointer type: {:#x}", .0)]
+    InvalidSystemPointerType(u32),
+}
+
+impl_from_error!(io::Error, PointerError, PointerError::IOError);
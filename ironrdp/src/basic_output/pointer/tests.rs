@@ -0,0 +1,81 @@
+use super::*;
+
+#[rustfmt::skip]
+const NEW_POINTER: [u8; 28] = [
+    0x20, 0x00, // xorBpp
+    0x02, 0x00, // cacheIndex
+    0x01, 0x00, 0x03, 0x00, // hotSpot
+    0x01, 0x00, 0x02, 0x00, // width, height
+    0x04, 0x00, // lengthAndMask
+    0x08, 0x00, // lengthXorMask
+    0x11, 0x12, 0x13, 0x14, 0x21, 0x22, 0x23, 0x24,
+    0x80, 0x00, 0x00, 0x00,
+];
+
+#[test]
+fn new_pointer_shape_is_parsed_with_its_masks() {
+    let update = PointerUpdate::from_buffer_with_code(&NEW_POINTER, UpdateCode::NewPointer).unwrap();
+
+    assert_eq!(
+        Some(PointerUpdate::Shape(PointerShape {
+            xor_bpp: 32,
+            cache_index: 2,
+            hot_spot_x: 1,
+            hot_spot_y: 3,
+            width: 1,
+            height: 2,
+            xor_mask: &NEW_POINTER[16..24],
+            and_mask: &NEW_POINTER[24..],
+        })),
+        update
+    );
+}
+
+#[test]
+fn position_pointer_is_parsed() {
+    let update = PointerUpdate::from_buffer_with_code(&[0x0a, 0x00, 0x14, 0x01], UpdateCode::PositionPointer).unwrap();
+
+    assert_eq!(Some(PointerUpdate::Position { x: 10, y: 276 }), update);
+}
+
+#[test]
+fn truncated_masks_are_rejected() {
+    let update = PointerUpdate::from_buffer_with_code(&NEW_POINTER[..20], UpdateCode::NewPointer);
+
+    assert!(matches!(
+        update,
+        Err(PointerError::InvalidDataLength {
+            expected: 12,
+            actual: 4
+        })
+    ));
+}
+
+#[test]
+fn other_updates_are_not_parsed() {
+    let update = PointerUpdate::from_buffer_with_code(&[], UpdateCode::Bitmap).unwrap();
+
+    assert_eq!(None, update);
+}
+
+#[test]
+fn slow_path_system_pointer_is_parsed() {
+    let pdu = PointerPdu::from_buffer([0x01, 0x00, 0x00, 0x00, 0x00, 0x7f, 0x00, 0x00].as_ref()).unwrap();
+
+    assert_eq!(PointerMessageType::System, pdu.message_type);
+    assert_eq!(PointerUpdate::Default, pdu.update().unwrap());
+}
+
+#[test]
+fn slow_path_new_pointer_is_parsed_like_fast_path_one() {
+    let mut buffer = vec![0x08, 0x00, 0x00, 0x00];
+    buffer.extend_from_slice(&NEW_POINTER);
+
+    let pdu = PointerPdu::from_buffer(buffer.as_slice()).unwrap();
+
+    assert_eq!(buffer.len(), pdu.buffer_length());
+    assert_eq!(
+        PointerUpdate::from_buffer_with_code(&NEW_POINTER, UpdateCode::NewPointer).unwrap(),
+        Some(pdu.update().unwrap())
+    );
+}
@@ -5,9 +5,9 @@ use std::io::{self, Write};
 
 use bitflags::bitflags;
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
-use failure::Fail;
 use num_derive::{FromPrimitive, ToPrimitive};
 use num_traits::{FromPrimitive, ToPrimitive};
+use thiserror::Error;
 
 use crate::utils::{Rectangle, SplitTo};
 use crate::{impl_from_error, PduBufferParsing, PduParsing};
@@ -270,15 +270,15 @@ bitflags! {
     }
 }
 
-#[derive(Debug, Fail)]
+#[derive(Debug, Error)]
 pub enum SurfaceCommandsError {
-    #[fail(display = "IO error: {}", _0)]
-    IOError(#[fail(cause)] io::Error),
-    #[fail(display = "Invalid Surface Command type: {}", _0)]
+    #[error("IO error: {}", .0)]
+    IOError(#[source] io::Error),
+    #[error("Invalid Surface Command type: {}", .0)]
     InvalidSurfaceCommandType(u16),
-    #[fail(display = "Invalid Frame Marker action: {}", _0)]
+    #[error("Invalid Frame Marker action: {}", .0)]
     InvalidFrameAction(u16),
-    #[fail(display = "Input buffer is shorter then the data length: {} < {}", actual, expected)]
+    #[error("Input buffer is shorter then the data length: {} < {}", .actual, .expected)]
     InvalidDataLength { expected: usize, actual: usize },
 }
 
@@ -243,6 +243,14 @@ pub fn read_octet_string_tag(mut stream: impl io::Read) -> io::Result<u16> {
     read_length(stream)
 }
 
+/// Reads the octet string tag, failing if the announced length is greater than `max_length`.
+pub fn read_octet_string_tag_with_limit(mut stream: impl io::Read, max_length: usize) -> io::Result<u16> {
+    let length = read_octet_string_tag(&mut stream)?;
+    check_length(usize::from(length), max_length)?;
+
+    Ok(length)
+}
+
 fn write_universal_tag(mut stream: impl io::Write, tag: Tag, pc: Pc) -> io::Result<usize> {
     let identifier = Class::Universal as u8 | pc as u8 | (TAG_MASK & tag as u8);
     stream.write_u8(identifier)?;
@@ -302,6 +310,17 @@ fn read_length(mut stream: impl io::Read) -> io::Result<u16> {
     }
 }
 
+fn check_length(length: usize, max_length: usize) -> io::Result<()> {
+    if length > max_length {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("BER length {} exceeds the limit of {}", length, max_length),
+        ))
+    } else {
+        Ok(())
+    }
+}
+
 fn sizeof_length(length: u16) -> u16 {
     if length > 0xff {
         3
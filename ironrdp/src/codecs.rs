@@ -1 +1,34 @@
+#[cfg(feature = "nscodec")]
+pub mod nscodec;
+#[cfg(feature = "planar")]
+pub mod planar;
+#[cfg(feature = "rfx")]
+pub mod progressive;
 pub mod rfx;
+
+#[cfg(any(feature = "planar", feature = "nscodec"))]
+use rfx::image_processing::Rgba;
+
+/// Converts the luma, orange chroma and green chroma reduced by the color loss level, shared by the planar codec
+/// and the NSCodec, to RGB.
+#[cfg(any(feature = "planar", feature = "nscodec"))]
+fn ycocg_to_rgba(y: u8, co: u8, cg: u8, color_loss_level: u8, a: u8) -> Rgba {
+    // shifting by one less than the color loss level yields half of the chroma values the formulas use
+    let shift = color_loss_level - 1;
+    let co = i16::from((co << shift) as i8);
+    let cg = i16::from((cg << shift) as i8);
+    let y = i16::from(y);
+    let t = y - cg;
+
+    Rgba {
+        r: clip(t + co),
+        g: clip(y + cg),
+        b: clip(t - co),
+        a,
+    }
+}
+
+#[cfg(any(feature = "planar", feature = "nscodec"))]
+fn clip(value: i16) -> u8 {
+    value.clamp(0, 255) as u8
+}
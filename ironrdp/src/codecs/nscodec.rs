@@ -0,0 +1,157 @@
+//! Decoding of the NSCodec, which compresses the bitmaps of the surface commands into a luma plane,
+//! two chroma planes possibly subsampled and an alpha plane, each of them run-length encoded.
+
+#[cfg(test)]
+mod tests;
+
+use std::io;
+
+use byteorder::{LittleEndian, ReadBytesExt};
+use thiserror::Error;
+
+use super::rfx::image_processing::PixelFormat;
+use super::ycocg_to_rgba;
+use crate::impl_from_error;
+use crate::utils::SplitTo;
+
+const PLANES_COUNT: usize = 4;
+const MIN_COLOR_LOSS_LEVEL: u8 = 1;
+const MAX_COLOR_LOSS_LEVEL: u8 = 7;
+/// The bytes of the plane stored after its runs, without being encoded.
+const RAW_TAIL_SIZE: usize = 4;
+const LONG_RUN_MARKER: u8 = 0xff;
+const FILLED_PLANE_VALUE: u8 = 0xff;
+
+/// Decodes the bitmap into `output` in the pixel format, the rows of `width` pixels being written
+/// in the order of the scanlines of the stream.
+pub fn decode(
+    mut input: &[u8],
+    width: usize,
+    height: usize,
+    pixel_format: PixelFormat,
+    output: &mut Vec<u8>,
+) -> Result<(), NsCodecError> {
+    let mut plane_byte_counts = [0; PLANES_COUNT];
+    for byte_count in plane_byte_counts.iter_mut() {
+        *byte_count = input.read_u32::<LittleEndian>()? as usize;
+    }
+    let color_loss_level = input.read_u8()?;
+    if !(MIN_COLOR_LOSS_LEVEL..=MAX_COLOR_LOSS_LEVEL).contains(&color_loss_level) {
+        return Err(NsCodecError::InvalidColorLossLevel(color_loss_level));
+    }
+    let chroma_subsampling = input.read_u8()? != 0;
+    let _reserved = input.read_u16::<LittleEndian>()?;
+
+    // the subsampled planes are computed from the scanlines padded to a multiple of 8 pixels, then halved
+    let (luma_width, chroma_width, chroma_height) = if chroma_subsampling {
+        let padded_width = (width + 7) & !7;
+        (padded_width, padded_width / 2, (height + 1) / 2)
+    } else {
+        (width, width, height)
+    };
+
+    let luma = decode_plane(&mut input, plane_byte_counts[0], luma_width * height)?;
+    let orange_chroma = decode_plane(&mut input, plane_byte_counts[1], chroma_width * chroma_height)?;
+    let green_chroma = decode_plane(&mut input, plane_byte_counts[2], chroma_width * chroma_height)?;
+    let alpha = decode_plane(&mut input, plane_byte_counts[3], width * height)?;
+
+    let bytes_per_pixel = usize::from(pixel_format.bytes_per_pixel());
+    output.clear();
+    output.resize(width * height * bytes_per_pixel, 0);
+
+    for y in 0..height {
+        for x in 0..width {
+            let index = y * width + x;
+            let chroma_index = if chroma_subsampling {
+                (y / 2) * chroma_width + x / 2
+            } else {
+                index
+            };
+
+            let color = ycocg_to_rgba(
+                luma[y * luma_width + x],
+                orange_chroma[chroma_index],
+                green_chroma[chroma_index],
+                color_loss_level,
+                alpha[index],
+            );
+
+            pixel_format.write_color(color, &mut output[index * bytes_per_pixel..])?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Decodes a plane of `size` bytes from its `byte_count` bytes. The planes shorter than their size are run-length
+/// encoded, and the empty ones, e.g. the alpha plane of the opaque bitmaps, are filled with `0xFF`.
+fn decode_plane(input: &mut &[u8], byte_count: usize, size: usize) -> Result<Vec<u8>, NsCodecError> {
+    if input.len() < byte_count {
+        return Err(NsCodecError::InvalidDataLength {
+            expected: byte_count,
+            actual: input.len(),
+        });
+    }
+    let data = input.split_to(byte_count);
+
+    if byte_count == 0 {
+        Ok(vec![FILLED_PLANE_VALUE; size])
+    } else if byte_count < size {
+        decode_rle_plane(data, size)
+    } else {
+        Ok(data[..size].to_vec())
+    }
+}
+
+/// A value repeated by the following byte is a run, whose length is stored in the next byte minus two,
+/// or in the next four bytes after a `0xFF` marker. The last four bytes of the plane are stored raw.
+fn decode_rle_plane(mut input: &[u8], size: usize) -> Result<Vec<u8>, NsCodecError> {
+    let mut plane = Vec::with_capacity(size);
+    let encoded_size = size.saturating_sub(RAW_TAIL_SIZE);
+
+    while plane.len() < encoded_size {
+        let value = input.read_u8()?;
+
+        // the last encoded value is never a run
+        if plane.len() + 1 == encoded_size || input.first() != Some(&value) {
+            plane.push(value);
+            continue;
+        }
+
+        input.read_u8()?;
+        let run_length = match input.read_u8()? {
+            LONG_RUN_MARKER => input.read_u32::<LittleEndian>()? as usize,
+            run_length => usize::from(run_length) + 2,
+        };
+        if plane.len() + run_length > encoded_size {
+            return Err(NsCodecError::InvalidRunLength);
+        }
+
+        plane.resize(plane.len() + run_length, value);
+    }
+
+    let raw_tail_size = size - plane.len();
+    if input.len() < raw_tail_size {
+        return Err(NsCodecError::InvalidDataLength {
+            expected: raw_tail_size,
+            actual: input.len(),
+        });
+    }
+    plane.extend_from_slice(&input[..raw_tail_size]);
+
+    Ok(plane)
+}
+
+#[derive(Debug, Error)]
+pub enum NsCodecError {
+    #[error("IO error: {}", .0)]
+    IOError(#[source] io::Error),
+    #[error("Input buffer is shorter then the plane data: {} < {}", .actual, .expected)]
+    InvalidDataLength { expected: usize, actual: usize },
+    #[error("Received run exceeding the plane")]
+    InvalidRunLength,
+    #[error("Received invalid color loss level: {}", .0)]
+    InvalidColorLossLevel(u8),
+}
+
+impl_from_error!(io::Error, NsCodecError, NsCodecError::IOError);
@@ -0,0 +1,99 @@
+use super::*;
+
+fn stream(planes: [&[u8]; 4], color_loss_level: u8, chroma_subsampling: u8) -> Vec<u8> {
+    let mut stream = Vec::new();
+    for plane in planes.iter() {
+        stream.extend_from_slice(&(plane.len() as u32).to_le_bytes());
+    }
+    stream.extend_from_slice(&[color_loss_level, chroma_subsampling, 0x00, 0x00]);
+    for plane in planes.iter() {
+        stream.extend_from_slice(plane);
+    }
+
+    stream
+}
+
+fn decode_bgra(input: &[u8], width: usize, height: usize) -> Vec<[u8; 4]> {
+    let mut output = Vec::new();
+    decode(input, width, height, PixelFormat::BgrA32, &mut output).unwrap();
+
+    output
+        .chunks_exact(4)
+        .map(|pixel| [pixel[0], pixel[1], pixel[2], pixel[3]])
+        .collect()
+}
+
+#[test]
+fn decode_raw_planes_with_alpha() {
+    // luma of 100 and 50, orange chroma of 10 and green chroma of -5 for the first pixel
+    let input = stream([&[100, 50], &[10, 0], &[0xfb, 0], &[0x80, 0x40]], 1, 0);
+
+    assert_eq!(vec![[95, 95, 115, 0x80], [50, 50, 50, 0x40]], decode_bgra(&input, 2, 1));
+}
+
+#[test]
+fn decode_rle_plane_and_opaque_alpha() {
+    // a run of 4, then the last 4 values stored raw
+    let input = stream([&[7, 7, 2, 1, 2, 3, 4], &[0; 8], &[0; 8], &[]], 1, 0);
+
+    let expected = [7, 7, 7, 7, 1, 2, 3, 4]
+        .iter()
+        .map(|&value| [value, value, value, 0xff])
+        .collect::<Vec<_>>();
+    assert_eq!(expected, decode_bgra(&input, 4, 2));
+}
+
+#[test]
+fn decode_long_runs() {
+    // a run of 296 stored after the marker, then the last 4 values stored raw
+    let luma: &[u8] = &[9, 9, 0xff, 0x28, 0x01, 0x00, 0x00, 9, 9, 9, 9];
+    let chroma: &[u8] = &[0, 0, 0xff, 0x28, 0x01, 0x00, 0x00, 0, 0, 0, 0];
+    let input = stream([luma, chroma, chroma, &[]], 1, 0);
+
+    assert_eq!(vec![[9, 9, 9, 0xff]; 300], decode_bgra(&input, 300, 1));
+}
+
+#[test]
+fn decode_subsampled_chroma_with_color_loss() {
+    // the luma scanlines are padded to 8 pixels, and the chroma of 10 and -6 halved by the color loss level of 2
+    // is shared by the 4 pixels
+    #[rustfmt::skip]
+    let luma: &[u8] = &[
+        100, 50, 0, 0, 0, 0, 0, 0,
+        100, 50, 0, 0, 0, 0, 0, 0,
+    ];
+    let input = stream([luma, &[5, 0, 0, 0], &[0xfd, 0, 0, 0], &[]], 2, 1);
+
+    assert_eq!(
+        vec![
+            [96, 94, 116, 0xff],
+            [46, 44, 66, 0xff],
+            [96, 94, 116, 0xff],
+            [46, 44, 66, 0xff],
+        ],
+        decode_bgra(&input, 2, 2)
+    );
+}
+
+#[test]
+fn run_exceeding_plane_is_rejected() {
+    let input = stream([&[7, 7, 5, 1, 2, 3, 4], &[0; 8], &[0; 8], &[]], 1, 0);
+
+    let mut output = Vec::new();
+    assert!(matches!(
+        decode(&input, 4, 2, PixelFormat::BgrA32, &mut output),
+        Err(NsCodecError::InvalidRunLength)
+    ));
+}
+
+#[test]
+fn truncated_plane_is_rejected() {
+    let mut input = stream([&[1, 2, 3, 4], &[0; 4], &[0; 4], &[]], 1, 0);
+    input.truncate(input.len() - 1);
+
+    let mut output = Vec::new();
+    assert!(matches!(
+        decode(&input, 2, 2, PixelFormat::BgrA32, &mut output),
+        Err(NsCodecError::InvalidDataLength { expected: 4, actual: 3 })
+    ));
+}
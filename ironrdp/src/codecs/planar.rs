@@ -0,0 +1,192 @@
+//! Decoding of the RDP 6.0 planar codec, which compresses the 32 bpp bitmaps of the bitmap updates
+//! and of the bitmap cache orders.
+
+#[cfg(test)]
+mod tests;
+
+use std::io;
+
+use bitflags::bitflags;
+use byteorder::ReadBytesExt;
+use thiserror::Error;
+
+use super::rfx::image_processing::{PixelFormat, Rgba};
+use super::ycocg_to_rgba;
+use crate::impl_from_error;
+use crate::utils::SplitTo;
+
+const COLOR_LOSS_LEVEL_MASK: u8 = 0x07;
+const OPAQUE_ALPHA: u8 = 0xff;
+
+bitflags! {
+    struct FormatHeader: u8 {
+        const CHROMA_SUBSAMPLING = 0x08;
+        const RLE = 0x10;
+        const NO_ALPHA = 0x20;
+    }
+}
+
+/// Decodes the bitmap into `output` in the pixel format, the rows of `width` pixels being written
+/// in the order of the scanlines of the stream.
+pub fn decode(
+    mut input: &[u8],
+    width: usize,
+    height: usize,
+    pixel_format: PixelFormat,
+    output: &mut Vec<u8>,
+) -> Result<(), PlanarError> {
+    let header = input.read_u8()?;
+    let color_loss_level = header & COLOR_LOSS_LEVEL_MASK;
+    let header = FormatHeader::from_bits_truncate(header);
+    let rle = header.contains(FormatHeader::RLE);
+
+    let chroma_subsampling = header.contains(FormatHeader::CHROMA_SUBSAMPLING);
+    if chroma_subsampling && color_loss_level == 0 {
+        return Err(PlanarError::InvalidChromaSubsampling);
+    }
+    let (chroma_width, chroma_height) = if chroma_subsampling {
+        ((width + 1) / 2, (height + 1) / 2)
+    } else {
+        (width, height)
+    };
+
+    let alpha = if header.contains(FormatHeader::NO_ALPHA) {
+        None
+    } else {
+        Some(decode_plane(&mut input, width, height, rle)?)
+    };
+    // red, green and blue, or luma, orange chroma and green chroma if the colors have been reduced
+    let first = decode_plane(&mut input, width, height, rle)?;
+    let second = decode_plane(&mut input, chroma_width, chroma_height, rle)?;
+    let third = decode_plane(&mut input, chroma_width, chroma_height, rle)?;
+
+    let bytes_per_pixel = usize::from(pixel_format.bytes_per_pixel());
+    output.clear();
+    output.resize(width * height * bytes_per_pixel, 0);
+
+    for y in 0..height {
+        for x in 0..width {
+            let index = y * width + x;
+            let a = alpha.as_ref().map_or(OPAQUE_ALPHA, |alpha| alpha[index]);
+
+            let color = if color_loss_level == 0 {
+                Rgba {
+                    r: first[index],
+                    g: second[index],
+                    b: third[index],
+                    a,
+                }
+            } else {
+                let chroma_index = if chroma_subsampling {
+                    (y / 2) * chroma_width + x / 2
+                } else {
+                    index
+                };
+
+                ycocg_to_rgba(
+                    first[index],
+                    second[chroma_index],
+                    third[chroma_index],
+                    color_loss_level,
+                    a,
+                )
+            };
+
+            pixel_format.write_color(color, &mut output[index * bytes_per_pixel..])?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Decodes a plane of `width` by `height` bytes. The run-length encoded planes store the values of their first
+/// scanline, and the differences with the previous scanline for the following ones.
+fn decode_plane(input: &mut &[u8], width: usize, height: usize, rle: bool) -> Result<Vec<u8>, PlanarError> {
+    if !rle {
+        if input.len() < width * height {
+            return Err(PlanarError::InvalidDataLength {
+                expected: width * height,
+                actual: input.len(),
+            });
+        }
+
+        return Ok(input.split_to(width * height).to_vec());
+    }
+
+    let mut plane = vec![0; width * height];
+
+    for y in 0..height {
+        let (previous_rows, rows) = plane.split_at_mut(y * width);
+        let previous_row = &previous_rows[previous_rows.len().saturating_sub(width)..];
+        let row = &mut rows[..width];
+
+        // the last raw value of the first scanline, and the last difference of the following ones
+        let mut value = 0u8;
+        let mut x = 0;
+
+        while x < width {
+            let control = input.read_u8()?;
+            let (run_length, raw_bytes) = match (control & 0x0f, control >> 4) {
+                (1, raw_bytes) => (usize::from(raw_bytes) + 16, 0),
+                (2, raw_bytes) => (usize::from(raw_bytes) + 32, 0),
+                (run_length, raw_bytes) => (usize::from(run_length), usize::from(raw_bytes)),
+            };
+
+            if x + raw_bytes + run_length > width {
+                return Err(PlanarError::InvalidRunLength);
+            }
+            if input.len() < raw_bytes {
+                return Err(PlanarError::InvalidDataLength {
+                    expected: raw_bytes,
+                    actual: input.len(),
+                });
+            }
+
+            for &raw_value in input.split_to(raw_bytes) {
+                value = if y == 0 {
+                    raw_value
+                } else {
+                    decode_difference(raw_value)
+                };
+                row[x] = scanline_value(previous_row, x, value);
+                x += 1;
+            }
+            for _ in 0..run_length {
+                row[x] = scanline_value(previous_row, x, value);
+                x += 1;
+            }
+        }
+    }
+
+    Ok(plane)
+}
+
+/// The value of the first scanline, or the difference added to the value of the previous scanline.
+fn scanline_value(previous_row: &[u8], x: usize, value: u8) -> u8 {
+    previous_row
+        .get(x)
+        .map_or(value, |previous| previous.wrapping_add(value))
+}
+
+/// The differences are stored as their magnitude shifted left by one, the lowest bit being the sign.
+fn decode_difference(value: u8) -> u8 {
+    if value & 1 == 0 {
+        value >> 1
+    } else {
+        0u8.wrapping_sub((value >> 1) + 1)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum PlanarError {
+    #[error("IO error: {}", .0)]
+    IOError(#[source] io::Error),
+    #[error("Input buffer is shorter then the plane data: {} < {}", .actual, .expected)]
+    InvalidDataLength { expected: usize, actual: usize },
+    #[error("Received run-length segment exceeding the scanline")]
+    InvalidRunLength,
+    #[error("Received subsampled chroma planes without color loss reduction")]
+    InvalidChromaSubsampling,
+}
+
+impl_from_error!(io::Error, PlanarError, PlanarError::IOError);
@@ -0,0 +1,74 @@
+use super::*;
+
+const NO_ALPHA_RLE: u8 = 0x30;
+
+fn decode_bgra(input: &[u8], width: usize, height: usize) -> Vec<[u8; 4]> {
+    let mut output = Vec::new();
+    decode(input, width, height, PixelFormat::BgrA32, &mut output).unwrap();
+
+    output
+        .chunks_exact(4)
+        .map(|pixel| [pixel[0], pixel[1], pixel[2], pixel[3]])
+        .collect()
+}
+
+#[test]
+fn decode_rle_planes_with_differences_from_previous_scanline() {
+    #[rustfmt::skip]
+    let input = [
+        NO_ALPHA_RLE,
+        // red: a raw value repeated, then no difference
+        0x13, 10, 0x04,
+        // green: raw values, then differences of -1, 0, 0 and +1
+        0x40, 1, 2, 3, 4, 0x40, 1, 0, 0, 2,
+        // blue: zeros, then a difference of +1 repeated
+        0x04, 0x13, 2,
+    ];
+
+    assert_eq!(
+        vec![
+            [0, 1, 10, 0xff],
+            [0, 2, 10, 0xff],
+            [0, 3, 10, 0xff],
+            [0, 4, 10, 0xff],
+            [1, 0, 10, 0xff],
+            [1, 2, 10, 0xff],
+            [1, 3, 10, 0xff],
+            [1, 5, 10, 0xff],
+        ],
+        decode_bgra(&input, 4, 2)
+    );
+}
+
+#[test]
+fn decode_long_runs() {
+    // a run of 16 plus the high nibble, the raw value being repeated
+    let input = [NO_ALPHA_RLE, 0x10, 7, 0x11, 0x21, 0x21];
+
+    assert_eq!(vec![[0, 0, 7, 0xff]; 18], decode_bgra(&input, 18, 1));
+}
+
+#[test]
+fn decode_raw_planes_with_alpha() {
+    let input = [0x00, 0x80, 0x40, 1, 2, 3, 4, 5, 6, 0x00];
+
+    assert_eq!(vec![[5, 3, 1, 0x80], [6, 4, 2, 0x40]], decode_bgra(&input, 2, 1));
+}
+
+#[test]
+fn decode_subsampled_ycocg() {
+    // luma of 100 and 50, orange chroma of 10 and green chroma of -5 shared by both pixels
+    let input = [0x29, 100, 50, 10, 0xfb, 0x00];
+
+    assert_eq!(vec![[95, 95, 115, 0xff], [45, 45, 65, 0xff]], decode_bgra(&input, 2, 1));
+}
+
+#[test]
+fn run_exceeding_scanline_is_rejected() {
+    let input = [NO_ALPHA_RLE, 0x05];
+
+    assert!(matches!(
+        decode(&input, 4, 1, PixelFormat::BgrA32, &mut Vec::new()),
+        Err(PlanarError::InvalidRunLength)
+    ));
+}
@@ -0,0 +1,621 @@
+//! Parsing of the RemoteFX Progressive codec (MS-RDPEGFX 2.2.4.2) of the Graphics Pipeline surfaces,
+//! and decoding of the tile components, sent at a low quality first and then upgraded by the next regions
+//! with the missing bits of their coefficients.
+
+pub mod dwt;
+#[cfg(test)]
+mod tests;
+
+use std::io;
+use std::ops::Range;
+
+use bitflags::bitflags;
+use byteorder::{LittleEndian, ReadBytesExt};
+use num_derive::FromPrimitive;
+use num_traits::FromPrimitive;
+use thiserror::Error;
+
+use super::rfx::rlgr::{self, RlgrError};
+use super::rfx::{subband_reconstruction, EntropyAlgorithm, RfxRectangle};
+use crate::impl_from_error;
+use crate::utils::SplitTo;
+
+const BLOCK_HEADER_SIZE: usize = 6;
+const SYNC_MAGIC: u32 = 0xCACC_ACCA;
+const SYNC_VERSION: u16 = 0x0100;
+const TILE_SIZE: u16 = 64;
+const RECTANGLE_SIZE: usize = 8;
+const QUANT_SIZE: usize = 5;
+const PROGRESSIVE_QUANT_SIZE: usize = 1 + 3 * QUANT_SIZE;
+
+/// The quality of the simple tiles, and of the first tiles sent at once, whose coefficients are not upgraded.
+pub const FULL_QUALITY: u8 = 0xFF;
+pub const COEFFICIENTS_COUNT: usize = 4096;
+pub const SUBBANDS_COUNT: usize = 10;
+
+/// Sizes of the subbands of the reduce-extrapolate DWT, in the order of the coefficients:
+/// HL1, LH1, HH1, HL2, LH2, HH2, HL3, LH3, HH3 and LL3.
+const EXTRAPOLATED_SUBBAND_SIZES: [usize; SUBBANDS_COUNT] = [1023, 1023, 961, 272, 272, 256, 72, 72, 64, 81];
+/// Sizes of the subbands of the DWT of RemoteFX, in the same order.
+const SUBBAND_SIZES: [usize; SUBBANDS_COUNT] = [1024, 1024, 1024, 256, 256, 256, 64, 64, 64, 64];
+
+/// Decodes the coefficients of a tile component sent by a simple or a first tile into `coefficients`,
+/// dequantized by the bit positions of the subbands. `sign` receives the quantized coefficients,
+/// whose signs tell the next upgrades which coefficients are already significant.
+pub fn decode_component(
+    data: &[u8],
+    bit_positions: &[u8; SUBBANDS_COUNT],
+    extrapolate: bool,
+    coefficients: &mut [i16],
+    sign: &mut [i16],
+) -> Result<(), RlgrError> {
+    // the components of a flat tile may have no data
+    if data.is_empty() {
+        coefficients.fill(0);
+    } else {
+        rlgr::decode(EntropyAlgorithm::Rlgr1, data, coefficients)?;
+    }
+    sign.copy_from_slice(coefficients);
+
+    let ll3 = subband_ranges(extrapolate).last().unwrap();
+    subband_reconstruction::decode(&mut coefficients[ll3]);
+
+    for (range, bit_position) in subband_ranges(extrapolate).zip(bit_positions.iter()) {
+        let shift = bit_position.saturating_sub(1);
+        for coefficient in &mut coefficients[range] {
+            *coefficient = (i32::from(*coefficient) << shift) as i16;
+        }
+    }
+
+    Ok(())
+}
+
+/// Adds to the coefficients of a tile component the bits of an upgrade tile, `bit_count` bits per subband
+/// down to its new bit position. The magnitudes of the significant coefficients and of LL3 are sent raw,
+/// the coefficients becoming significant being run-length encoded (SRL).
+pub fn upgrade_component(
+    srl_data: &[u8],
+    raw_data: &[u8],
+    bit_positions: &[u8; SUBBANDS_COUNT],
+    bit_counts: &[u8; SUBBANDS_COUNT],
+    extrapolate: bool,
+    coefficients: &mut [i16],
+    sign: &mut [i16],
+) {
+    let mut srl = SrlDecoder::new(srl_data);
+    let mut raw = BitReader::new(raw_data);
+
+    let subbands = subband_ranges(extrapolate).zip(bit_positions.iter().zip(bit_counts.iter()));
+    for (subband, (range, (&bit_position, &bit_count))) in subbands.enumerate() {
+        if bit_count == 0 {
+            continue;
+        }
+
+        let is_ll3 = subband == SUBBANDS_COUNT - 1;
+        let shift = bit_position.saturating_sub(1);
+
+        for index in range {
+            let value = if is_ll3 || sign[index] > 0 {
+                raw.read(bit_count) as i32
+            } else if sign[index] < 0 {
+                -(raw.read(bit_count) as i32)
+            } else {
+                let value = srl.read(bit_count);
+                sign[index] = value as i16;
+
+                value
+            };
+
+            coefficients[index] = coefficients[index].wrapping_add((value << shift) as i16);
+        }
+    }
+}
+
+/// Computes the pixels of a tile component from its coefficients, in place.
+pub fn inverse_dwt(coefficients: &mut [i16], temp: &mut [i16], extrapolate: bool) {
+    if extrapolate {
+        dwt::decode(coefficients, temp);
+    } else {
+        super::rfx::dwt::decode(coefficients, temp);
+    }
+}
+
+fn subband_ranges(extrapolate: bool) -> impl Iterator<Item = Range<usize>> {
+    let sizes = if extrapolate {
+        EXTRAPOLATED_SUBBAND_SIZES
+    } else {
+        SUBBAND_SIZES
+    };
+
+    sizes.into_iter().scan(0, |start, size| {
+        let range = *start..*start + size;
+        *start += size;
+
+        Some(range)
+    })
+}
+
+/// Reads the bits from the most significant one of each byte, the bits past the end of the data being zeros.
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+
+    fn read_bit(&mut self) -> u32 {
+        let byte = self.data.get(self.position / 8).copied().unwrap_or(0);
+        let bit = (byte >> (7 - self.position % 8)) & 1;
+        self.position += 1;
+
+        u32::from(bit)
+    }
+
+    fn read(&mut self, count: u8) -> u32 {
+        (0..count).fold(0, |value, _| (value << 1) | self.read_bit())
+    }
+}
+
+/// Decoder of the coefficients becoming significant in an upgrade, the runs of zeros being Golomb-Rice encoded
+/// with an adaptive parameter, and the magnitudes of the other coefficients unary encoded.
+struct SrlDecoder<'a> {
+    bits: BitReader<'a>,
+    kp: u32,
+    zeros: u32,
+    magnitude_next: bool,
+}
+
+impl<'a> SrlDecoder<'a> {
+    const KP_MAX: u32 = 80;
+
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            bits: BitReader::new(data),
+            kp: 8,
+            zeros: 0,
+            magnitude_next: false,
+        }
+    }
+
+    fn read(&mut self, bit_count: u8) -> i32 {
+        if self.zeros > 0 {
+            self.zeros -= 1;
+            return 0;
+        }
+
+        if !self.magnitude_next {
+            let k = self.kp / 8;
+            if self.bits.read_bit() == 0 {
+                // a full run of 2^k zeros
+                self.zeros = (1 << k) - 1;
+                self.kp = (self.kp + 4).min(Self::KP_MAX);
+
+                return 0;
+            }
+
+            // a shorter run, followed by a nonzero coefficient
+            self.zeros = self.bits.read(k as u8);
+            self.magnitude_next = true;
+            if self.zeros > 0 {
+                self.zeros -= 1;
+                return 0;
+            }
+        }
+
+        self.magnitude_next = false;
+        let is_negative = self.bits.read_bit() == 1;
+        self.kp = self.kp.saturating_sub(6);
+
+        let max_magnitude = (1 << bit_count) - 1;
+        let mut magnitude = 1;
+        while magnitude < max_magnitude && self.bits.read_bit() == 0 {
+            magnitude += 1;
+        }
+
+        if is_negative {
+            -magnitude
+        } else {
+            magnitude
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Block<'a> {
+    Sync,
+    FrameBegin { frame_index: u32, region_count: u16 },
+    FrameEnd,
+    Context { context_id: u8, flags: ContextFlags },
+    Region(Region<'a>),
+}
+
+impl<'a> Block<'a> {
+    pub fn from_buffer_consume(buffer: &mut &'a [u8]) -> Result<Self, ProgressiveError> {
+        let (ty, mut data) = read_block(buffer)?;
+
+        match ty {
+            BlockType::Sync => {
+                let magic = data.read_u32::<LittleEndian>()?;
+                if magic != SYNC_MAGIC {
+                    return Err(ProgressiveError::InvalidMagicNumber(magic));
+                }
+
+                let version = data.read_u16::<LittleEndian>()?;
+                if version != SYNC_VERSION {
+                    return Err(ProgressiveError::InvalidSyncVersion(version));
+                }
+
+                Ok(Self::Sync)
+            }
+            BlockType::FrameBegin => {
+                let frame_index = data.read_u32::<LittleEndian>()?;
+                let region_count = data.read_u16::<LittleEndian>()?;
+
+                Ok(Self::FrameBegin {
+                    frame_index,
+                    region_count,
+                })
+            }
+            BlockType::FrameEnd => Ok(Self::FrameEnd),
+            BlockType::Context => {
+                let context_id = data.read_u8()?;
+                let tile_size = data.read_u16::<LittleEndian>()?;
+                if tile_size != TILE_SIZE {
+                    return Err(ProgressiveError::InvalidTileSize(tile_size));
+                }
+                let flags = ContextFlags::from_bits_truncate(data.read_u8()?);
+
+                Ok(Self::Context { context_id, flags })
+            }
+            BlockType::Region => Ok(Self::Region(Region::from_buffer(data)?)),
+            BlockType::TileSimple | BlockType::TileFirst | BlockType::TileUpgrade => {
+                Err(ProgressiveError::UnexpectedBlockType(ty))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Region<'a> {
+    /// The updated parts of the surface, the tiles being clipped to them.
+    pub rectangles: Vec<RfxRectangle>,
+    pub quants: Vec<ComponentQuant>,
+    pub progressive_quants: Vec<ProgressiveQuant>,
+    pub flags: RegionFlags,
+    pub tiles: Vec<Tile<'a>>,
+}
+
+impl<'a> Region<'a> {
+    fn from_buffer(mut buffer: &'a [u8]) -> Result<Self, ProgressiveError> {
+        let tile_size = u16::from(buffer.read_u8()?);
+        if tile_size != TILE_SIZE {
+            return Err(ProgressiveError::InvalidTileSize(tile_size));
+        }
+
+        let rectangle_count = usize::from(buffer.read_u16::<LittleEndian>()?);
+        let quant_count = usize::from(buffer.read_u8()?);
+        let progressive_quant_count = usize::from(buffer.read_u8()?);
+        let flags = RegionFlags::from_bits_truncate(buffer.read_u8()?);
+        let tile_count = usize::from(buffer.read_u16::<LittleEndian>()?);
+        let tiles_data_size = buffer.read_u32::<LittleEndian>()? as usize;
+
+        let expected_length = rectangle_count * RECTANGLE_SIZE
+            + quant_count * QUANT_SIZE
+            + progressive_quant_count * PROGRESSIVE_QUANT_SIZE
+            + tiles_data_size;
+        if buffer.len() < expected_length {
+            return Err(ProgressiveError::InvalidDataLength {
+                expected: expected_length,
+                actual: buffer.len(),
+            });
+        }
+
+        let rectangles = (0..rectangle_count)
+            .map(|_| {
+                Ok(RfxRectangle {
+                    x: buffer.read_u16::<LittleEndian>()?,
+                    y: buffer.read_u16::<LittleEndian>()?,
+                    width: buffer.read_u16::<LittleEndian>()?,
+                    height: buffer.read_u16::<LittleEndian>()?,
+                })
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let quants = (0..quant_count)
+            .map(|_| ComponentQuant::from_buffer_consume(&mut buffer))
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let progressive_quants = (0..progressive_quant_count)
+            .map(|_| ProgressiveQuant::from_buffer_consume(&mut buffer))
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let mut tiles_data = buffer.split_to(tiles_data_size);
+        let mut tiles = Vec::with_capacity(tile_count);
+        while !tiles_data.is_empty() {
+            tiles.push(Tile::from_buffer_consume(&mut tiles_data)?);
+        }
+        if tiles.len() != tile_count {
+            return Err(ProgressiveError::InvalidTileCount {
+                expected: tile_count,
+                actual: tiles.len(),
+            });
+        }
+
+        Ok(Self {
+            rectangles,
+            quants,
+            progressive_quants,
+            flags,
+            tiles,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Tile<'a> {
+    /// A simple tile, or the first pass of a progressively encoded tile.
+    First(FirstTile<'a>),
+    Upgrade(UpgradeTile<'a>),
+}
+
+impl<'a> Tile<'a> {
+    fn from_buffer_consume(buffer: &mut &'a [u8]) -> Result<Self, ProgressiveError> {
+        let (ty, mut data) = read_block(buffer)?;
+
+        let mut quant_indices = [0; 3];
+        for quant_index in quant_indices.iter_mut() {
+            *quant_index = data.read_u8()?;
+        }
+        let x_index = data.read_u16::<LittleEndian>()?;
+        let y_index = data.read_u16::<LittleEndian>()?;
+
+        match ty {
+            BlockType::TileSimple | BlockType::TileFirst => {
+                let flags = TileFlags::from_bits_truncate(data.read_u8()?);
+                let quality = if ty == BlockType::TileFirst {
+                    data.read_u8()?
+                } else {
+                    FULL_QUALITY
+                };
+
+                let lengths = read_lengths::<4>(&mut data)?;
+                let [y_data, cb_data, cr_data, _tail_data] = split_data(&mut data, lengths)?;
+
+                Ok(Self::First(FirstTile {
+                    quant_indices,
+                    x_index,
+                    y_index,
+                    flags,
+                    quality,
+                    data: [y_data, cb_data, cr_data],
+                }))
+            }
+            BlockType::TileUpgrade => {
+                let quality = data.read_u8()?;
+
+                let lengths = read_lengths::<6>(&mut data)?;
+                let [y_srl_data, y_raw_data, cb_srl_data, cb_raw_data, cr_srl_data, cr_raw_data] =
+                    split_data(&mut data, lengths)?;
+
+                Ok(Self::Upgrade(UpgradeTile {
+                    quant_indices,
+                    x_index,
+                    y_index,
+                    quality,
+                    srl_data: [y_srl_data, cb_srl_data, cr_srl_data],
+                    raw_data: [y_raw_data, cb_raw_data, cr_raw_data],
+                }))
+            }
+            _ => Err(ProgressiveError::UnexpectedBlockType(ty)),
+        }
+    }
+
+    /// Position of the tile in the grid of the surface, in tiles.
+    pub fn position(&self) -> (u16, u16) {
+        match self {
+            Self::First(tile) => (tile.x_index, tile.y_index),
+            Self::Upgrade(tile) => (tile.x_index, tile.y_index),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FirstTile<'a> {
+    /// Indices of the quants of the Y, Cb and Cr components in the region.
+    pub quant_indices: [u8; 3],
+    pub x_index: u16,
+    pub y_index: u16,
+    pub flags: TileFlags,
+    /// Index of the progressive quant in the region, or [`FULL_QUALITY`].
+    pub quality: u8,
+    /// RLGR1 encoded coefficients of the Y, Cb and Cr components.
+    pub data: [&'a [u8]; 3],
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UpgradeTile<'a> {
+    pub quant_indices: [u8; 3],
+    pub x_index: u16,
+    pub y_index: u16,
+    pub quality: u8,
+    pub srl_data: [&'a [u8]; 3],
+    pub raw_data: [&'a [u8]; 3],
+}
+
+/// Quantization factors of the subbands of a component.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ComponentQuant {
+    pub ll3: u8,
+    pub hl3: u8,
+    pub lh3: u8,
+    pub hh3: u8,
+    pub hl2: u8,
+    pub lh2: u8,
+    pub hh2: u8,
+    pub hl1: u8,
+    pub lh1: u8,
+    pub hh1: u8,
+}
+
+impl ComponentQuant {
+    fn from_buffer_consume(buffer: &mut &[u8]) -> io::Result<Self> {
+        let mut values = [0; 2 * QUANT_SIZE];
+        for pair in values.chunks_exact_mut(2) {
+            let byte = buffer.read_u8()?;
+            pair[0] = byte & 0x0F;
+            pair[1] = byte >> 4;
+        }
+        let [ll3, hl3, lh3, hh3, hl2, lh2, hh2, hl1, lh1, hh1] = values;
+
+        Ok(Self {
+            ll3,
+            hl3,
+            lh3,
+            hh3,
+            hl2,
+            lh2,
+            hh2,
+            hl1,
+            lh1,
+            hh1,
+        })
+    }
+
+    /// The factors in the order of the subbands of the coefficients.
+    pub fn subbands(&self) -> [u8; SUBBANDS_COUNT] {
+        [
+            self.hl1, self.lh1, self.hh1, self.hl2, self.lh2, self.hh2, self.hl3, self.lh3, self.hh3, self.ll3,
+        ]
+    }
+}
+
+/// Quantization added to the one of the tiles for a quality, their coefficients being sent down
+/// to a higher bit position.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProgressiveQuant {
+    pub quality: u8,
+    pub y: ComponentQuant,
+    pub cb: ComponentQuant,
+    pub cr: ComponentQuant,
+}
+
+impl ProgressiveQuant {
+    fn from_buffer_consume(buffer: &mut &[u8]) -> io::Result<Self> {
+        let quality = buffer.read_u8()?;
+        let y = ComponentQuant::from_buffer_consume(buffer)?;
+        let cb = ComponentQuant::from_buffer_consume(buffer)?;
+        let cr = ComponentQuant::from_buffer_consume(buffer)?;
+
+        Ok(Self { quality, y, cb, cr })
+    }
+
+    pub fn components(&self) -> [&ComponentQuant; 3] {
+        [&self.y, &self.cb, &self.cr]
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, FromPrimitive)]
+#[repr(u16)]
+pub enum BlockType {
+    Sync = 0xCCC0,
+    FrameBegin = 0xCCC1,
+    FrameEnd = 0xCCC2,
+    Context = 0xCCC3,
+    Region = 0xCCC4,
+    TileSimple = 0xCCC5,
+    TileFirst = 0xCCC6,
+    TileUpgrade = 0xCCC7,
+}
+
+bitflags! {
+    pub struct ContextFlags: u8 {
+        const SUBBAND_DIFFING = 0x01;
+    }
+}
+
+bitflags! {
+    pub struct RegionFlags: u8 {
+        /// The tiles are transformed by the reduce-extrapolate DWT, otherwise by the DWT of RemoteFX.
+        const REDUCE_EXTRAPOLATE = 0x01;
+    }
+}
+
+bitflags! {
+    pub struct TileFlags: u8 {
+        /// The coefficients are the difference from the ones of the tile at the same position.
+        const DIFFERENCE = 0x01;
+    }
+}
+
+/// Reads the header of a block, returning its type and its data.
+fn read_block<'a>(buffer: &mut &'a [u8]) -> Result<(BlockType, &'a [u8]), ProgressiveError> {
+    let ty = buffer.read_u16::<LittleEndian>()?;
+    let ty = BlockType::from_u16(ty).ok_or(ProgressiveError::InvalidBlockType(ty))?;
+
+    let block_length = buffer.read_u32::<LittleEndian>()? as usize;
+    let data_length = block_length
+        .checked_sub(BLOCK_HEADER_SIZE)
+        .ok_or(ProgressiveError::InvalidBlockLength(block_length))?;
+    if buffer.len() < data_length {
+        return Err(ProgressiveError::InvalidDataLength {
+            expected: data_length,
+            actual: buffer.len(),
+        });
+    }
+
+    Ok((ty, buffer.split_to(data_length)))
+}
+
+fn read_lengths<const N: usize>(buffer: &mut &[u8]) -> io::Result<[usize; N]> {
+    let mut lengths = [0; N];
+    for length in lengths.iter_mut() {
+        *length = usize::from(buffer.read_u16::<LittleEndian>()?);
+    }
+
+    Ok(lengths)
+}
+
+fn split_data<'a, const N: usize>(
+    buffer: &mut &'a [u8],
+    lengths: [usize; N],
+) -> Result<[&'a [u8]; N], ProgressiveError> {
+    let expected_length = lengths.iter().sum::<usize>();
+    if buffer.len() < expected_length {
+        return Err(ProgressiveError::InvalidDataLength {
+            expected: expected_length,
+            actual: buffer.len(),
+        });
+    }
+
+    Ok(lengths.map(|length| buffer.split_to(length)))
+}
+
+#[derive(Debug, Error)]
+pub enum ProgressiveError {
+    #[error("IO error: {}", .0)]
+    IoError(#[source] io::Error),
+    #[error("RLGR error: {}", .0)]
+    RlgrError(#[source] RlgrError),
+    #[error("Got invalid block type: {:#x}", .0)]
+    InvalidBlockType(u16),
+    #[error("Got unexpected block type: {:?}", .0)]
+    UnexpectedBlockType(BlockType),
+    #[error("Got invalid block length: {}", .0)]
+    InvalidBlockLength(usize),
+    #[error("Input buffer is shorter than the data length: {} < {}", .actual, .expected)]
+    InvalidDataLength { expected: usize, actual: usize },
+    #[error("Got invalid Sync magic number: {:#x}", .0)]
+    InvalidMagicNumber(u32),
+    #[error("Got invalid Sync version: {:#x}", .0)]
+    InvalidSyncVersion(u16),
+    #[error("Got invalid tile size: {}", .0)]
+    InvalidTileSize(u16),
+    #[error("Got {} tiles in a region of {} tiles", .actual, .expected)]
+    InvalidTileCount { expected: usize, actual: usize },
+}
+
+impl_from_error!(io::Error, ProgressiveError, ProgressiveError::IoError);
+impl_from_error!(RlgrError, ProgressiveError, ProgressiveError::RlgrError);
@@ -0,0 +1,133 @@
+//! The reduce-extrapolate inverse DWT of the RemoteFX Progressive codec, whose subbands are one coefficient
+//! larger or smaller than the ones of RemoteFX: the low subbands of the first level hold 33 coefficients
+//! per row or column, and the high ones 31.
+
+const TILE_SIZE: usize = 64;
+
+/// Transforms the coefficients of a tile, laid out as HL1, LH1, HH1, HL2, LH2, HH2, HL3, LH3, HH3 and LL3,
+/// into its 64x64 values. `temp_buffer` holds at least 4096 values.
+pub fn decode(buffer: &mut [i16], temp_buffer: &mut [i16]) {
+    decode_block(&mut buffer[3807..], temp_buffer, 3);
+    decode_block(&mut buffer[3007..], temp_buffer, 2);
+    decode_block(buffer, temp_buffer, 1);
+}
+
+/// Combines the HL, LH, HH and LL subbands of the level into the LL subband of the previous level,
+/// written at the start of `buffer`.
+fn decode_block(buffer: &mut [i16], temp_buffer: &mut [i16], level: usize) {
+    let low_count = low_subband_size(level);
+    let high_count = high_subband_size(level);
+    let width = low_count + high_count;
+    let (low, high) = temp_buffer.split_at_mut(low_count * width);
+
+    {
+        let (hl, subbands) = buffer.split_at(high_count * low_count);
+        let (lh, subbands) = subbands.split_at(low_count * high_count);
+        let (hh, ll) = subbands.split_at(high_count * high_count);
+
+        // horizontally, LL and HL into L, then LH and HH into H
+        for row in 0..low_count {
+            synthesize(
+                &ll[row * low_count..],
+                1,
+                &hl[row * high_count..],
+                1,
+                &mut low[row * width..],
+                1,
+                low_count,
+                high_count,
+            );
+        }
+        for row in 0..high_count {
+            synthesize(
+                &lh[row * low_count..],
+                1,
+                &hh[row * high_count..],
+                1,
+                &mut high[row * width..],
+                1,
+                low_count,
+                high_count,
+            );
+        }
+    }
+
+    // vertically, L and H into the LL subband of the previous level
+    for column in 0..width {
+        synthesize(
+            &low[column..],
+            width,
+            &high[column..],
+            width,
+            &mut buffer[column..],
+            width,
+            low_count,
+            high_count,
+        );
+    }
+}
+
+fn low_subband_size(level: usize) -> usize {
+    (TILE_SIZE >> level) + 1
+}
+
+fn high_subband_size(level: usize) -> usize {
+    if level == 1 {
+        (TILE_SIZE >> 1) - 1
+    } else {
+        (TILE_SIZE + (1 << (level - 1))) >> level
+    }
+}
+
+/// Interleaves `low_count` low and `high_count` high coefficients, read every `low_step` and `high_step` values,
+/// into `low_count + high_count` values written every `output_step` values. The values past the last high
+/// coefficient are extrapolated from the remaining low ones.
+#[allow(clippy::too_many_arguments)]
+fn synthesize(
+    low: &[i16],
+    low_step: usize,
+    high: &[i16],
+    high_step: usize,
+    output: &mut [i16],
+    output_step: usize,
+    low_count: usize,
+    high_count: usize,
+) {
+    let low_at = |index: usize| i32::from(low[index * low_step]);
+    let high_at = |index: usize| i32::from(high[index * high_step]);
+    let mut write = |index: usize, value: i32| output[index * output_step] = value as i16;
+    // the even values are stored as 16-bit integers before the odd ones are computed from them
+    let truncate = |value: i32| i32::from(value as i16);
+
+    let mut h0 = high_at(0);
+    let mut x0 = truncate(low_at(0) - h0);
+    let mut x2 = x0;
+
+    for index in 1..high_count {
+        let h1 = high_at(index);
+        x2 = truncate(low_at(index) - (h0 + h1) / 2);
+
+        write(2 * index - 2, x0);
+        write(2 * index - 1, (x0 + x2) / 2 + 2 * h0);
+
+        x0 = x2;
+        h0 = h1;
+    }
+
+    let last = 2 * high_count - 2;
+    if low_count <= high_count {
+        write(last, x2);
+        write(last + 1, x2 + 2 * h0);
+    } else if low_count == high_count + 1 {
+        let x0 = truncate(low_at(high_count) - h0);
+        write(last, x2);
+        write(last + 1, (x0 + x2) / 2 + 2 * h0);
+        write(last + 2, x0);
+    } else {
+        let x0 = truncate(low_at(high_count) - h0 / 2);
+        write(last, x2);
+        write(last + 1, (x0 + x2) / 2 + 2 * h0);
+        write(last + 2, x0);
+        write(last + 3, (x0 + low_at(high_count + 1)) / 2);
+    }
+}
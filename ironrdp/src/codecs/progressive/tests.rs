@@ -0,0 +1,108 @@
+use super::*;
+
+#[rustfmt::skip]
+const FRAME_BUFFER: [u8; 96] = [
+    // sync
+    0xc0, 0xcc, 0x0c, 0x00, 0x00, 0x00, 0xca, 0xac, 0xcc, 0xca, 0x00, 0x01,
+    // context
+    0xc3, 0xcc, 0x0a, 0x00, 0x00, 0x00, 0x00, 0x40, 0x00, 0x00,
+    // frame begin
+    0xc1, 0xcc, 0x0c, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x00,
+    // region: tile size, 1 rectangle, 1 quant, no progressive quant, reduce-extrapolate, 1 tile of 25 bytes
+    0xc4, 0xcc, 0x38, 0x00, 0x00, 0x00, 0x40, 0x01, 0x00, 0x01, 0x00, 0x01, 0x01, 0x00, 0x19, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x40, 0x00, 0x40, 0x00,
+    0x66, 0x66, 0x66, 0x66, 0x66,
+    // simple tile (0, 0) of 1 byte per component
+    0xc5, 0xcc, 0x19, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x01, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x00, 0xaa, 0xbb, 0xcc,
+    // frame end
+    0xc2, 0xcc, 0x06, 0x00, 0x00, 0x00,
+];
+
+const QUANT: ComponentQuant = ComponentQuant {
+    ll3: 6,
+    hl3: 6,
+    lh3: 6,
+    hh3: 6,
+    hl2: 6,
+    lh2: 6,
+    hh2: 6,
+    hl1: 6,
+    lh1: 6,
+    hh1: 6,
+};
+
+#[test]
+fn from_buffer_correctly_parses_frame() {
+    let mut buffer = FRAME_BUFFER.as_ref();
+    let mut blocks = Vec::new();
+    while !buffer.is_empty() {
+        blocks.push(Block::from_buffer_consume(&mut buffer).unwrap());
+    }
+
+    let expected = vec![
+        Block::Sync,
+        Block::Context {
+            context_id: 0,
+            flags: ContextFlags::empty(),
+        },
+        Block::FrameBegin {
+            frame_index: 1,
+            region_count: 1,
+        },
+        Block::Region(Region {
+            rectangles: vec![RfxRectangle {
+                x: 0,
+                y: 0,
+                width: 64,
+                height: 64,
+            }],
+            quants: vec![QUANT],
+            progressive_quants: Vec::new(),
+            flags: RegionFlags::REDUCE_EXTRAPOLATE,
+            tiles: vec![Tile::First(FirstTile {
+                quant_indices: [0, 0, 0],
+                x_index: 0,
+                y_index: 0,
+                flags: TileFlags::empty(),
+                quality: FULL_QUALITY,
+                data: [&[0xaa], &[0xbb], &[0xcc]],
+            })],
+        }),
+        Block::FrameEnd,
+    ];
+    assert_eq!(expected, blocks);
+}
+
+#[test]
+fn from_buffer_returns_error_on_invalid_sync_magic() {
+    let mut buffer = FRAME_BUFFER;
+    buffer[6] = 0x00;
+
+    assert!(matches!(
+        Block::from_buffer_consume(&mut buffer.as_ref()),
+        Err(ProgressiveError::InvalidMagicNumber(0xcacc_ac00))
+    ));
+}
+
+#[test]
+fn srl_decoder_reads_runs_of_zeros_and_magnitudes() {
+    // a short run of no zero then -1, a full run of 1 zero, a short run of no zero then 1 and 2
+    let mut decoder = SrlDecoder::new(&[0b1010_1010, 0b0100_0000]);
+
+    assert_eq!(-1, decoder.read(1));
+    assert_eq!(0, decoder.read(1));
+    assert_eq!(1, decoder.read(1));
+    assert_eq!(2, decoder.read(2));
+}
+
+#[test]
+fn dwt_extrapolates_flat_tile() {
+    let mut buffer = vec![0; COEFFICIENTS_COUNT];
+    buffer[4015..].fill(100);
+    let mut temp = vec![0; COEFFICIENTS_COUNT];
+
+    dwt::decode(&mut buffer, &mut temp);
+
+    assert!(buffer.iter().all(|&value| value == 100));
+}
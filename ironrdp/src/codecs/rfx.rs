@@ -7,17 +7,22 @@ mod tests;
 use std::io;
 
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
-use failure::Fail;
 use num_derive::{FromPrimitive, ToPrimitive};
 use num_traits::{FromPrimitive, ToPrimitive};
+use thiserror::Error;
 
 use crate::{impl_from_error, PduBufferParsing, PduParsing};
+#[cfg(feature = "rfx")]
 pub mod color_conversion;
+#[cfg(feature = "rfx")]
 pub mod dwt;
 pub mod image_processing;
+#[cfg(feature = "rfx")]
 pub mod quantization;
 pub mod rectangles_processing;
+#[cfg(feature = "rfx")]
 pub mod rlgr;
+#[cfg(feature = "rfx")]
 pub mod subband_reconstruction;
 
 pub use self::data_messages::{
@@ -25,6 +30,7 @@ pub use self::data_messages::{
     TileSetPdu,
 };
 pub use self::header_messages::{Channel, ChannelsPdu, CodecVersionsPdu, SyncPdu};
+#[cfg(feature = "rfx")]
 pub use self::rlgr::RlgrError;
 
 const BLOCK_HEADER_SIZE: usize = 6;
@@ -211,59 +217,58 @@ pub enum BlockType {
     Extension = 0xCCC7,
 }
 
-#[derive(Debug, Fail)]
+#[derive(Debug, Error)]
 pub enum RfxError {
-    #[fail(display = "IO error: {}", _0)]
-    IoError(#[fail(cause)] io::Error),
-    #[fail(display = "Got invalid block type: {}", _0)]
+    #[error("IO error: {}", .0)]
+    IoError(#[source] io::Error),
+    #[error("Got invalid block type: {}", .0)]
     InvalidBlockType(u16),
-    #[fail(
-        display = "Got unexpected Block type: expected ({:?}) != actual({:?})",
-        expected, actual
-    )]
+    #[error("Got unexpected Block type: expected ({:?}) != actual({:?})", .expected, .actual)]
     UnexpectedBlockType { expected: BlockType, actual: BlockType },
-    #[fail(display = "Got unexpected Block type ({:?}) while was expected header message", _0)]
+    #[error("Got unexpected Block type ({:?}) while was expected header message", .0)]
     InvalidHeaderBlockType(BlockType),
-    #[fail(display = "Got invalid block length: {}", _0)]
+    #[error("Got invalid block length: {}", .0)]
     InvalidBlockLength(usize),
-    #[fail(display = "Got invalid Sync magic number: {}", _0)]
+    #[error("Got invalid Sync magic number: {}", .0)]
     InvalidMagicNumber(u32),
-    #[fail(display = "Got invalid Sync version: {}", _0)]
+    #[error("Got invalid Sync version: {}", .0)]
     InvalidSyncVersion(u16),
-    #[fail(display = "Got invalid codecs number: {}", _0)]
+    #[error("Got invalid codecs number: {}", .0)]
     InvalidCodecsNumber(u8),
-    #[fail(display = "Got invalid codec ID: {}", _0)]
+    #[error("Got invalid codec ID: {}", .0)]
     InvalidCodecId(u8),
-    #[fail(display = "Got invalid codec version: {}", _0)]
+    #[error("Got invalid codec version: {}", .0)]
     InvalidCodecVersion(u16),
-    #[fail(display = "Got invalid channel ID: {}", _0)]
+    #[error("Got invalid channel ID: {}", .0)]
     InvalidChannelId(u8),
-    #[fail(display = "Got invalid context ID: {}", _0)]
+    #[error("Got invalid context ID: {}", .0)]
     InvalidContextId(u8),
-    #[fail(display = "Got invalid context tile size: {}", _0)]
+    #[error("Got invalid context tile size: {}", .0)]
     InvalidTileSize(u16),
-    #[fail(display = "Got invalid conversion transform: {}", _0)]
+    #[error("Got invalid conversion transform: {}", .0)]
     InvalidColorConversionTransform(u16),
-    #[fail(display = "Got invalid DWT: {}", _0)]
+    #[error("Got invalid DWT: {}", .0)]
     InvalidDwt(u16),
-    #[fail(display = "Got invalid entropy algorithm: {}", _0)]
+    #[error("Got invalid entropy algorithm: {}", .0)]
     InvalidEntropyAlgorithm(u16),
-    #[fail(display = "Got invalid quantization type: {}", _0)]
+    #[error("Got invalid quantization type: {}", .0)]
     InvalidQuantizationType(u16),
-    #[fail(display = "Input buffer is shorter then the data length: {} < {}", actual, expected)]
+    #[error("Input buffer is shorter then the data length: {} < {}", .actual, .expected)]
     InvalidDataLength { expected: usize, actual: usize },
-    #[fail(display = "Got invalid Region LRF: {}", _0)]
+    #[error("Got invalid Region LRF: {}", .0)]
     InvalidLrf(bool),
-    #[fail(display = "Got invalid Region type: {}", _0)]
+    #[error("Got invalid Region type: {}", .0)]
     InvalidRegionType(u16),
-    #[fail(display = "Got invalid number of tilesets: {}", _0)]
+    #[error("Got invalid number of tilesets: {}", .0)]
     InvalidNumberOfTilesets(u16),
-    #[fail(display = "Got invalid ID of context: {}", _0)]
+    #[error("Got invalid ID of context: {}", .0)]
     InvalidIdOfContext(u16),
-    #[fail(display = "Got invalid TileSet subtype: {}", _0)]
+    #[error("Got invalid TileSet subtype: {}", .0)]
     InvalidSubtype(u16),
-    #[fail(display = "Got invalid IT flag of TileSet: {}", _0)]
+    #[error("Got invalid IT flag of TileSet: {}", .0)]
     InvalidItFlag(bool),
+    #[error("Got invalid quantization values index of tile: {}", .0)]
+    InvalidQuantIndex(u8),
 }
 
 impl_from_error!(io::Error, RfxError, RfxError::IoError);
@@ -1,3 +1,7 @@
+#[cfg(target_arch = "aarch64")]
+mod neon;
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+mod simd128;
 #[cfg(test)]
 mod tests;
 
@@ -9,7 +13,23 @@ use std::io::{self, Write};
 const DIVISOR: f32 = (1 << 16) as f32;
 const ALPHA: u8 = 255;
 
-pub fn ycbcr_to_bgra(input: YCbCrBuffer<'_>, mut output: &mut [u8]) -> io::Result<()> {
+pub fn ycbcr_to_bgra(input: YCbCrBuffer<'_>, output: &mut [u8]) -> io::Result<()> {
+    // The WASM SIMD path converts the pixels 4 at a time, the remaining ones are converted one by one
+    #[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+    let (input, output) = simd128::ycbcr_to_bgra(input, output);
+    // Same for the NEON path, which is selected at runtime as NEON is optional on some ARM cores
+    #[cfg(target_arch = "aarch64")]
+    let (input, output) = if std::arch::is_aarch64_feature_detected!("neon") {
+        // SAFETY: NEON is supported by the CPU
+        unsafe { neon::ycbcr_to_bgra(input, output) }
+    } else {
+        (input, output)
+    };
+
+    ycbcr_to_bgra_scalar(input, output)
+}
+
+fn ycbcr_to_bgra_scalar(input: YCbCrBuffer<'_>, mut output: &mut [u8]) -> io::Result<()> {
     for ycbcr in input {
         let pixel = Rgb::from(ycbcr);
 
@@ -0,0 +1,77 @@
+use std::arch::aarch64::*;
+
+use super::{YCbCrBuffer, ALPHA, DIVISOR};
+
+const LANES: usize = 4;
+const BGRA_PIXEL_SIZE: usize = 4;
+
+/// Converts the pixels 4 at a time, mirroring `Rgb::from(YCbCr)`.
+///
+/// Returns the pixels which do not fill a whole vector and the rest of the output.
+///
+/// # Safety
+///
+/// NEON has to be supported by the CPU.
+#[target_feature(enable = "neon")]
+pub(super) unsafe fn ycbcr_to_bgra<'a, 'b>(
+    mut input: YCbCrBuffer<'a>,
+    mut output: &'b mut [u8],
+) -> (YCbCrBuffer<'a>, &'b mut [u8]) {
+    let cr_r_factor = vdupq_n_s32((1.402_525 * DIVISOR) as i32);
+    let cb_g_factor = vdupq_n_s32((0.343_730 * DIVISOR) as i32);
+    let cr_g_factor = vdupq_n_s32((0.714_401 * DIVISOR) as i32);
+    let cb_b_factor = vdupq_n_s32((1.769_905 * DIVISOR) as i32);
+    let cr_b_factor = vdupq_n_s32((0.000_013 * DIVISOR) as i32);
+    let y_offset = vdupq_n_s32(4096);
+    let alpha = vdupq_n_s32(i32::from(ALPHA) << 24);
+
+    while input.y.len() >= LANES
+        && input.cb.len() >= LANES
+        && input.cr.len() >= LANES
+        && output.len() >= LANES * BGRA_PIXEL_SIZE
+    {
+        let y = load(input.y);
+        let cb = load(input.cb);
+        let cr = load(input.cr);
+
+        // the multiplications wrap around, as `overflowing_mul` does
+        let yy = vshlq_n_s32::<16>(vaddq_s32(y, y_offset));
+        let cr_r = vmulq_s32(cr, cr_r_factor);
+        let cb_g = vmulq_s32(cb, cb_g_factor);
+        let cr_g = vmulq_s32(cr, cr_g_factor);
+        let cb_b = vmulq_s32(cb, cb_b_factor);
+        let cr_b = vmulq_s32(cb, cr_b_factor);
+
+        let r = clip(vshrq_n_s32::<21>(vaddq_s32(yy, cr_r)));
+        let g = clip(vshrq_n_s32::<21>(vsubq_s32(vsubq_s32(yy, cb_g), cr_g)));
+        let b = clip(vshrq_n_s32::<21>(vaddq_s32(vaddq_s32(yy, cb_b), cr_b)));
+
+        let pixels = vorrq_s32(
+            vorrq_s32(b, vshlq_n_s32::<8>(g)),
+            vorrq_s32(vshlq_n_s32::<16>(r), alpha),
+        );
+
+        let (pixels_output, rest) = std::mem::take(&mut output).split_at_mut(LANES * BGRA_PIXEL_SIZE);
+        vst1q_u8(pixels_output.as_mut_ptr(), vreinterpretq_u8_s32(pixels));
+
+        input.y = &input.y[LANES..];
+        input.cb = &input.cb[LANES..];
+        input.cr = &input.cr[LANES..];
+        output = rest;
+    }
+
+    (input, output)
+}
+
+#[inline]
+#[target_feature(enable = "neon")]
+unsafe fn load(input: &[i16]) -> int32x4_t {
+    let input = &input[..LANES];
+    vmovl_s16(vld1_s16(input.as_ptr()))
+}
+
+#[inline]
+#[target_feature(enable = "neon")]
+unsafe fn clip(v: int32x4_t) -> int32x4_t {
+    vminq_s32(vmaxq_s32(v, vdupq_n_s32(0)), vdupq_n_s32(255))
+}
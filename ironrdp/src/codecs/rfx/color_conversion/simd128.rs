@@ -0,0 +1,66 @@
+use std::arch::wasm32::*;
+
+use super::{YCbCrBuffer, ALPHA, DIVISOR};
+
+const LANES: usize = 4;
+const BGRA_PIXEL_SIZE: usize = 4;
+
+/// Converts the pixels 4 at a time, mirroring `Rgb::from(YCbCr)`.
+///
+/// Returns the pixels which do not fill a whole vector and the rest of the output.
+pub(super) fn ycbcr_to_bgra<'a, 'b>(
+    mut input: YCbCrBuffer<'a>,
+    mut output: &'b mut [u8],
+) -> (YCbCrBuffer<'a>, &'b mut [u8]) {
+    let cr_r_factor = i32x4_splat((1.402_525 * DIVISOR) as i32);
+    let cb_g_factor = i32x4_splat((0.343_730 * DIVISOR) as i32);
+    let cr_g_factor = i32x4_splat((0.714_401 * DIVISOR) as i32);
+    let cb_b_factor = i32x4_splat((1.769_905 * DIVISOR) as i32);
+    let cr_b_factor = i32x4_splat((0.000_013 * DIVISOR) as i32);
+    let y_offset = i32x4_splat(4096);
+    let alpha = i32x4_splat(i32::from(ALPHA) << 24);
+
+    while input.y.len() >= LANES
+        && input.cb.len() >= LANES
+        && input.cr.len() >= LANES
+        && output.len() >= LANES * BGRA_PIXEL_SIZE
+    {
+        let y = load(input.y);
+        let cb = load(input.cb);
+        let cr = load(input.cr);
+
+        let yy = i32x4_shl(i32x4_add(y, y_offset), 16);
+        let cr_r = i32x4_mul(cr, cr_r_factor);
+        let cb_g = i32x4_mul(cb, cb_g_factor);
+        let cr_g = i32x4_mul(cr, cr_g_factor);
+        let cb_b = i32x4_mul(cb, cb_b_factor);
+        let cr_b = i32x4_mul(cb, cr_b_factor);
+
+        let r = clip(i32x4_shr(i32x4_add(yy, cr_r), 21));
+        let g = clip(i32x4_shr(i32x4_sub(i32x4_sub(yy, cb_g), cr_g), 21));
+        let b = clip(i32x4_shr(i32x4_add(i32x4_add(yy, cb_b), cr_b), 21));
+
+        let pixels = v128_or(v128_or(b, i32x4_shl(g, 8)), v128_or(i32x4_shl(r, 16), alpha));
+
+        let (pixels_output, rest) = std::mem::take(&mut output).split_at_mut(LANES * BGRA_PIXEL_SIZE);
+        // SAFETY: `pixels_output` is exactly 16 bytes long, and WASM stores do not require alignment
+        unsafe { v128_store(pixels_output.as_mut_ptr().cast(), pixels) };
+
+        input.y = &input.y[LANES..];
+        input.cb = &input.cb[LANES..];
+        input.cr = &input.cr[LANES..];
+        output = rest;
+    }
+
+    (input, output)
+}
+
+fn load(input: &[i16]) -> v128 {
+    let input = &input[..LANES];
+    // SAFETY: `input` holds 4 i16 values read as one 64-bit value, and WASM loads do not require alignment
+    i32x4_extend_low_i16x8(unsafe { v128_load64_zero(input.as_ptr().cast()) })
+}
+
+fn clip(v: v128) -> v128 {
+    i32x4_min(i32x4_max(v, i32x4_splat(0)), i32x4_splat(255))
+}
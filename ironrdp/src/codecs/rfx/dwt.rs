@@ -1,3 +1,7 @@
+#[cfg(target_arch = "aarch64")]
+mod neon;
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+mod simd128;
 #[cfg(test)]
 mod tests;
 
@@ -11,6 +15,18 @@ pub fn decode(buffer: &mut [i16], temp_buffer: &mut [i16]) {
 
 fn decode_block(buffer: &mut [i16], temp_buffer: &mut [i16], subband_width: usize) {
     inverse_horizontal(buffer, temp_buffer, subband_width);
+
+    // NEON is detected at runtime, as it is optional on some ARM cores
+    #[cfg(target_arch = "aarch64")]
+    if std::arch::is_aarch64_feature_detected!("neon") {
+        // SAFETY: NEON is supported by the CPU
+        unsafe { neon::inverse_vertical(buffer, temp_buffer, subband_width) };
+        return;
+    }
+
+    #[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+    simd128::inverse_vertical(buffer, temp_buffer, subband_width);
+    #[cfg(not(all(target_arch = "wasm32", target_feature = "simd128")))]
     inverse_vertical(buffer, temp_buffer, subband_width);
 }
 
@@ -60,6 +76,7 @@ fn inverse_horizontal(mut buffer: &[i16], temp_buffer: &mut [i16], subband_width
     }
 }
 
+#[cfg_attr(all(target_arch = "wasm32", target_feature = "simd128"), allow(dead_code))]
 fn inverse_vertical(mut buffer: &mut [i16], mut temp_buffer: &[i16], subband_width: usize) {
     let total_width = subband_width * 2;
 
@@ -0,0 +1,75 @@
+use std::arch::aarch64::*;
+
+const LANES: usize = 4;
+
+/// Inverse DWT in vertical direction, processing 4 columns at a time.
+///
+/// Mirrors the scalar `inverse_vertical`, including the truncation of the intermediate values to `i16`.
+///
+/// # Safety
+///
+/// NEON has to be supported by the CPU.
+#[target_feature(enable = "neon")]
+pub(super) unsafe fn inverse_vertical(buffer: &mut [i16], temp_buffer: &[i16], subband_width: usize) {
+    let total_width = subband_width * 2;
+    let h_offset = subband_width * total_width;
+    let lh_offset = (subband_width - 1) * total_width;
+    let one = vdupq_n_s32(1);
+
+    for column in (0..total_width).step_by(LANES) {
+        let buffer = &mut buffer[column..];
+        let temp_buffer = &temp_buffer[column..];
+
+        let h = load(&temp_buffer[h_offset..]);
+        let mut even = truncate(vsubq_s32(
+            load(temp_buffer),
+            vshrq_n_s32::<1>(vaddq_s32(vshlq_n_s32::<1>(h), one)),
+        ));
+        store(buffer, even);
+
+        for n in 1..subband_width {
+            let l = load(&temp_buffer[n * total_width..]);
+            let lh = load(&temp_buffer[lh_offset + n * total_width..]);
+            let h = load(&temp_buffer[h_offset + n * total_width..]);
+
+            // Even coefficients
+            let next_even = truncate(vsubq_s32(l, vshrq_n_s32::<1>(vaddq_s32(vaddq_s32(lh, h), one))));
+            store(&mut buffer[2 * n * total_width..], next_even);
+
+            // Odd coefficients
+            let odd = vaddq_s32(
+                truncate(vshlq_n_s32::<1>(lh)),
+                vshrq_n_s32::<1>(vaddq_s32(even, next_even)),
+            );
+            store(&mut buffer[(2 * n - 1) * total_width..], odd);
+
+            even = next_even;
+        }
+
+        let lh = load(&temp_buffer[lh_offset + subband_width * total_width..]);
+        let odd = vaddq_s32(truncate(vshlq_n_s32::<1>(lh)), vshrq_n_s32::<1>(vaddq_s32(even, even)));
+        store(&mut buffer[(2 * subband_width - 1) * total_width..], odd);
+    }
+}
+
+#[inline]
+#[target_feature(enable = "neon")]
+unsafe fn load(input: &[i16]) -> int32x4_t {
+    let input = &input[..LANES];
+    vmovl_s16(vld1_s16(input.as_ptr()))
+}
+
+#[inline]
+#[target_feature(enable = "neon")]
+unsafe fn store(output: &mut [i16], v: int32x4_t) {
+    let output = &mut output[..LANES];
+    // keep the low half of each lane, as `as i16` does
+    vst1_s16(output.as_mut_ptr(), vmovn_s32(v));
+}
+
+/// Sign-extends the low half of each lane, as `i32::from(v as i16)` does.
+#[inline]
+#[target_feature(enable = "neon")]
+unsafe fn truncate(v: int32x4_t) -> int32x4_t {
+    vmovl_s16(vmovn_s32(v))
+}
@@ -0,0 +1,64 @@
+use std::arch::wasm32::*;
+
+const LANES: usize = 4;
+
+/// Inverse DWT in vertical direction, processing 4 columns at a time.
+///
+/// Mirrors the scalar `inverse_vertical`, including the truncation of the intermediate values to `i16`.
+pub(super) fn inverse_vertical(buffer: &mut [i16], temp_buffer: &[i16], subband_width: usize) {
+    let total_width = subband_width * 2;
+    let h_offset = subband_width * total_width;
+    let lh_offset = (subband_width - 1) * total_width;
+    let one = i32x4_splat(1);
+
+    for column in (0..total_width).step_by(LANES) {
+        let buffer = &mut buffer[column..];
+        let temp_buffer = &temp_buffer[column..];
+
+        let h = load(&temp_buffer[h_offset..]);
+        let mut even = truncate(i32x4_sub(
+            load(temp_buffer),
+            i32x4_shr(i32x4_add(i32x4_shl(h, 1), one), 1),
+        ));
+        store(buffer, even);
+
+        for n in 1..subband_width {
+            let l = load(&temp_buffer[n * total_width..]);
+            let lh = load(&temp_buffer[lh_offset + n * total_width..]);
+            let h = load(&temp_buffer[h_offset + n * total_width..]);
+
+            // Even coefficients
+            let next_even = truncate(i32x4_sub(l, i32x4_shr(i32x4_add(i32x4_add(lh, h), one), 1)));
+            store(&mut buffer[2 * n * total_width..], next_even);
+
+            // Odd coefficients
+            let odd = i32x4_add(truncate(i32x4_shl(lh, 1)), i32x4_shr(i32x4_add(even, next_even), 1));
+            store(&mut buffer[(2 * n - 1) * total_width..], odd);
+
+            even = next_even;
+        }
+
+        let lh = load(&temp_buffer[lh_offset + subband_width * total_width..]);
+        let odd = i32x4_add(truncate(i32x4_shl(lh, 1)), i32x4_shr(i32x4_add(even, even), 1));
+        store(&mut buffer[(2 * subband_width - 1) * total_width..], odd);
+    }
+}
+
+fn load(input: &[i16]) -> v128 {
+    let input = &input[..LANES];
+    // SAFETY: `input` holds 4 i16 values read as one 64-bit value, and WASM loads do not require alignment
+    i32x4_extend_low_i16x8(unsafe { v128_load64_zero(input.as_ptr().cast()) })
+}
+
+fn store(output: &mut [i16], v: v128) {
+    let output = &mut output[..LANES];
+    // keep the low half of each lane, as `as i16` does
+    let v = i16x8_shuffle::<0, 2, 4, 6, 0, 2, 4, 6>(v, v);
+    // SAFETY: `output` holds 4 i16 values written as one 64-bit value, and WASM stores do not require alignment
+    unsafe { v128_store64_lane::<0>(v, output.as_mut_ptr().cast()) };
+}
+
+/// Sign-extends the low half of each lane, as `i32::from(v as i16)` does.
+fn truncate(v: v128) -> v128 {
+    i32x4_shr(i32x4_shl(v, 16), 16)
+}
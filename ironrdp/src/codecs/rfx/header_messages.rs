@@ -1,14 +1,13 @@
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 
 use super::{BlockHeader, BlockType, RfxError, BLOCK_HEADER_SIZE};
+use crate::consts::RFX_VERSION;
 use crate::utils::SplitTo;
 use crate::PduBufferParsing;
 
 const SYNC_MAGIC: u32 = 0xCACC_ACCA;
-const SYNC_VERSION: u16 = 0x0100;
 const CODECS_NUMBER: u8 = 1;
 const CODEC_ID: u8 = 1;
-const CODEC_VERSION: u16 = 0x0100;
 const CHANNEL_ID: u8 = 0;
 
 const CHANNEL_SIZE: usize = 5;
@@ -28,7 +27,7 @@ impl<'a> PduBufferParsing<'a> for SyncPdu {
             return Err(RfxError::InvalidMagicNumber(magic));
         }
         let version = buffer.read_u16::<LittleEndian>()?;
-        if version != SYNC_VERSION {
+        if version != RFX_VERSION {
             Err(RfxError::InvalidSyncVersion(version))
         } else {
             Ok(Self)
@@ -43,7 +42,7 @@ impl<'a> PduBufferParsing<'a> for SyncPdu {
 
         header.to_buffer_consume(buffer)?;
         buffer.write_u32::<LittleEndian>(SYNC_MAGIC)?;
-        buffer.write_u16::<LittleEndian>(SYNC_VERSION)?;
+        buffer.write_u16::<LittleEndian>(RFX_VERSION)?;
 
         Ok(())
     }
@@ -198,7 +197,7 @@ impl<'a> PduBufferParsing<'a> for CodecVersion {
         }
 
         let version = buffer.read_u16::<LittleEndian>()?;
-        if version != CODEC_VERSION {
+        if version != RFX_VERSION {
             Err(RfxError::InvalidCodecVersion(version))
         } else {
             Ok(Self)
@@ -207,7 +206,7 @@ impl<'a> PduBufferParsing<'a> for CodecVersion {
 
     fn to_buffer_consume(&self, buffer: &mut &mut [u8]) -> Result<(), Self::Error> {
         buffer.write_u8(CODEC_ID)?;
-        buffer.write_u16::<LittleEndian>(CODEC_VERSION)?;
+        buffer.write_u16::<LittleEndian>(RFX_VERSION)?;
 
         Ok(())
     }
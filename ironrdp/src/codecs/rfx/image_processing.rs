@@ -3,7 +3,7 @@ mod tests;
 
 use std::io;
 
-use byteorder::WriteBytesExt;
+use byteorder::{LittleEndian, WriteBytesExt};
 use num_derive::ToPrimitive;
 use num_traits::ToPrimitive;
 
@@ -14,46 +14,38 @@ const MAX_ALPHA: u8 = 0xff;
 
 pub struct ImageRegionMut<'a> {
     pub region: Rectangle,
-    pub step: u16,
+    pub step: usize,
     pub pixel_format: PixelFormat,
     pub data: &'a mut [u8],
 }
 
 pub struct ImageRegion<'a> {
     pub region: Rectangle,
-    pub step: u16,
+    pub step: usize,
     pub pixel_format: PixelFormat,
     pub data: &'a [u8],
 }
 
 impl<'a> ImageRegion<'a> {
     pub fn copy_to<'b>(&self, other: &mut ImageRegionMut<'b>) -> io::Result<()> {
-        let width = usize::from(other.region.width());
-        let height = usize::from(other.region.height());
+        let width = other.region.width() as usize;
+        let height = other.region.height() as usize;
 
         let dst_point = Point {
-            x: usize::from(other.region.left),
-            y: usize::from(other.region.top),
+            x: other.region.left as usize,
+            y: other.region.top as usize,
         };
         let src_point = Point {
-            x: usize::from(self.region.left),
-            y: usize::from(self.region.top),
+            x: self.region.left as usize,
+            y: self.region.top as usize,
         };
 
         let src_byte = usize::from(self.pixel_format.bytes_per_pixel());
         let dst_byte = usize::from(other.pixel_format.bytes_per_pixel());
         let dst_width = width * dst_byte;
 
-        let src_step = if self.step == 0 {
-            width * src_byte
-        } else {
-            usize::from(self.step)
-        };
-        let dst_step = if other.step == 0 {
-            width * dst_byte
-        } else {
-            usize::from(other.step)
-        };
+        let src_step = if self.step == 0 { width * src_byte } else { self.step };
+        let dst_step = if other.step == 0 { width * dst_byte } else { other.step };
 
         if self.pixel_format.eq_no_alpha(other.pixel_format) {
             for y in 0..height {
@@ -90,6 +82,8 @@ pub enum PixelFormat {
     BgrX32 = 537_135_240,
     RgbA32 = 537_102_472,
     RgbX32 = 537_069_704,
+    /// 5 bits of red, 6 bits of green and 5 bits of blue, in a little-endian 16-bit value.
+    Rgb16 = 268_502_373,
 }
 
 impl PixelFormat {
@@ -103,6 +97,7 @@ impl PixelFormat {
             | Self::BgrX32
             | Self::RgbA32
             | Self::RgbX32 => 4,
+            Self::Rgb16 => 2,
         }
     }
 
@@ -113,74 +108,79 @@ impl PixelFormat {
     }
 
     pub fn read_color(self, buffer: &[u8]) -> io::Result<Rgba> {
+        let bytes_per_pixel = usize::from(self.bytes_per_pixel());
+        if buffer.len() < bytes_per_pixel {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "The input buffer is not large enoght",
+            ));
+        }
+
+        let color = &buffer[..bytes_per_pixel];
+
         match self {
-            Self::ARgb32
-            | Self::XRgb32
-            | Self::ABgr32
-            | Self::XBgr32
-            | Self::BgrA32
-            | Self::BgrX32
-            | Self::RgbA32
-            | Self::RgbX32 => {
-                if buffer.len() < 4 {
-                    Err(io::Error::new(
-                        io::ErrorKind::InvalidInput,
-                        "The input buffer is not large enoght",
-                    ))
-                } else {
-                    let color = &buffer[..4];
-
-                    match self {
-                        Self::ARgb32 => Ok(Rgba {
-                            a: color[0],
-                            r: color[1],
-                            g: color[2],
-                            b: color[3],
-                        }),
-                        Self::XRgb32 => Ok(Rgba {
-                            a: MAX_ALPHA,
-                            r: color[1],
-                            g: color[2],
-                            b: color[3],
-                        }),
-                        Self::ABgr32 => Ok(Rgba {
-                            a: color[0],
-                            b: color[1],
-                            g: color[2],
-                            r: color[3],
-                        }),
-                        Self::XBgr32 => Ok(Rgba {
-                            a: MAX_ALPHA,
-                            b: color[1],
-                            g: color[2],
-                            r: color[3],
-                        }),
-                        Self::BgrA32 => Ok(Rgba {
-                            b: color[0],
-                            g: color[1],
-                            r: color[2],
-                            a: color[3],
-                        }),
-                        Self::BgrX32 => Ok(Rgba {
-                            b: color[0],
-                            g: color[1],
-                            r: color[2],
-                            a: MAX_ALPHA,
-                        }),
-                        Self::RgbA32 => Ok(Rgba {
-                            r: color[0],
-                            g: color[1],
-                            b: color[2],
-                            a: color[3],
-                        }),
-                        Self::RgbX32 => Ok(Rgba {
-                            r: color[0],
-                            g: color[1],
-                            b: color[2],
-                            a: MAX_ALPHA,
-                        }),
-                    }
-                }
+            Self::ARgb32 => Ok(Rgba {
+                a: color[0],
+                r: color[1],
+                g: color[2],
+                b: color[3],
+            }),
+            Self::XRgb32 => Ok(Rgba {
+                a: MAX_ALPHA,
+                r: color[1],
+                g: color[2],
+                b: color[3],
+            }),
+            Self::ABgr32 => Ok(Rgba {
+                a: color[0],
+                b: color[1],
+                g: color[2],
+                r: color[3],
+            }),
+            Self::XBgr32 => Ok(Rgba {
+                a: MAX_ALPHA,
+                b: color[1],
+                g: color[2],
+                r: color[3],
+            }),
+            Self::BgrA32 => Ok(Rgba {
+                b: color[0],
+                g: color[1],
+                r: color[2],
+                a: color[3],
+            }),
+            Self::BgrX32 => Ok(Rgba {
+                b: color[0],
+                g: color[1],
+                r: color[2],
+                a: MAX_ALPHA,
+            }),
+            Self::RgbA32 => Ok(Rgba {
+                r: color[0],
+                g: color[1],
+                b: color[2],
+                a: color[3],
+            }),
+            Self::RgbX32 => Ok(Rgba {
+                r: color[0],
+                g: color[1],
+                b: color[2],
+                a: MAX_ALPHA,
+            }),
+            Self::Rgb16 => {
+                let color = u16::from_le_bytes([buffer[0], buffer[1]]);
+                let r = (color >> 11) as u8;
+                let g = ((color >> 5) & 0x3f) as u8;
+                let b = (color & 0x1f) as u8;
+
+                // the most significant bits are replicated into the least significant ones,
+                // so that the full intensity is 0xff
+                Ok(Rgba {
+                    r: (r << 3) | (r >> 2),
+                    g: (g << 2) | (g >> 4),
+                    b: (b << 3) | (b >> 2),
+                    a: MAX_ALPHA,
+                })
             }
         }
     }
@@ -235,12 +235,20 @@ impl PixelFormat {
                 buffer.write_u8(color.b)?;
                 buffer.write_u8(MIN_ALPHA)?;
             }
+            Self::Rgb16 => {
+                buffer.write_u16::<LittleEndian>(rgb16(color.r, color.g, color.b))?;
+            }
         }
 
         Ok(())
     }
 }
 
+/// Packs the color into the RGB565 layout, keeping the most significant bits of each channel.
+pub fn rgb16(r: u8, g: u8, b: u8) -> u16 {
+    (u16::from(r >> 3) << 11) | (u16::from(g >> 2) << 5) | u16::from(b >> 3)
+}
+
 struct Point {
     pub x: usize,
     pub y: usize,
@@ -2712,3 +2712,24 @@ const CONVERTED_TO_BGRX_WITH_PARTIALLY_COVERED_RECTANGLE_BUFFER: [u8; 64 * 64 *
     0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
     0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
 ];
+
+#[test]
+fn rgb16_keeps_most_significant_bits_and_restores_full_intensity() {
+    let mut buffer = [0; 2];
+
+    PixelFormat::Rgb16
+        .write_color(
+            Rgba {
+                r: 0xff,
+                g: 0x84,
+                b: 0x07,
+                a: MAX_ALPHA,
+            },
+            &mut buffer,
+        )
+        .unwrap();
+    assert_eq!(0xfc20_u16.to_le_bytes(), buffer);
+
+    let color = PixelFormat::Rgb16.read_color(&buffer).unwrap();
+    assert_eq!((0xff, 0x86, 0x00), (color.r, color.g, color.b));
+}
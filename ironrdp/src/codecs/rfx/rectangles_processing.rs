@@ -215,7 +215,7 @@ fn handle_rectangle_between_bands(
     band: &[Rectangle],
     next_band: &[Rectangle],
     dst: &mut Vec<Rectangle>,
-    top_inter_band: u16,
+    top_inter_band: u32,
 ) {
     /* test if a piece of rect should be inserted as a new band between
      * the current band and the next one. band n and n+1 shouldn't touch.
@@ -271,8 +271,8 @@ fn rectangle_in_band(band: &[Rectangle], rectangle: &Rectangle) -> bool {
 fn copy_band_with_union(
     mut band: &[Rectangle],
     dst: &mut Vec<Rectangle>,
-    band_top: u16,
-    band_bottom: u16,
+    band_top: u32,
+    band_bottom: u32,
     union_rectangle: &Rectangle,
 ) {
     /* merges a band with the given rect
@@ -339,7 +339,7 @@ fn copy_band_with_union(
     copy_band(band, dst, band_top, band_bottom);
 }
 
-fn copy_band(band: &[Rectangle], dst: &mut Vec<Rectangle>, band_top: u16, band_bottom: u16) {
+fn copy_band(band: &[Rectangle], dst: &mut Vec<Rectangle>, band_top: u32, band_bottom: u32) {
     dst.extend(band.iter().map(|r| Rectangle {
         top: band_top,
         bottom: band_bottom,
@@ -7,7 +7,7 @@ use std::io;
 use bitvec::field::BitField as _;
 use bitvec::order::Msb0;
 use bitvec::slice::BitSlice;
-use failure::Fail;
+use thiserror::Error;
 
 use super::EntropyAlgorithm;
 use crate::impl_from_error;
@@ -74,7 +74,7 @@ pub fn decode(mode: EntropyAlgorithm, tile: &[u8], mut output: &mut [i16]) -> Re
                 let magnitude = compute_rl_magnitude(sign_bit, code_remainder);
 
                 let size = min(run as usize, output.len());
-                fill(&mut output[..size], 0);
+                output[..size].fill(0);
                 output = &mut output[size..];
                 write_byte!(output, magnitude);
             }
@@ -116,17 +116,11 @@ pub fn decode(mode: EntropyAlgorithm, tile: &[u8], mut output: &mut [i16]) -> Re
     }
 
     // fill remaining buffer with zeros
-    fill(output, 0);
+    output.fill(0);
 
     Ok(())
 }
 
-fn fill(buffer: &mut [i16], value: i16) {
-    for v in buffer {
-        *v = value;
-    }
-}
-
 fn load_be_u32(s: &BitSlice<u8, Msb0>) -> u32 {
     if s.is_empty() {
         0
@@ -230,11 +224,11 @@ impl From<u32> for CompressionMode {
     }
 }
 
-#[derive(Debug, Fail)]
+#[derive(Debug, Error)]
 pub enum RlgrError {
-    #[fail(display = "IO error: {}", _0)]
-    IoError(#[fail(cause)] io::Error),
-    #[fail(display = "The input tile is empty")]
+    #[error("IO error: {}", .0)]
+    IoError(#[source] io::Error),
+    #[error("The input tile is empty")]
     EmptyTile,
 }
 
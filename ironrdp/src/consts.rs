@@ -0,0 +1,47 @@
+//! Protocol constants shared by the client and the server sides, which the implementers of servers and proxies
+//! can rely on instead of redefining them.
+
+use crate::rdp::capability_sets::Guid;
+
+/// The maximum length of the data of a static virtual channel chunk which every peer accepts
+/// (`CHANNEL_CHUNK_LENGTH`), and of a dynamic virtual channel PDU.
+pub const CHANNEL_CHUNK_LENGTH: usize = 1600;
+/// The maximum length of the chunks a server can advertise in the Virtual Channel Capability Set.
+pub const MAX_CHANNEL_CHUNK_LENGTH: usize = 16256;
+
+/// The MCS channel of the server, the source of the Share Control PDUs it sends.
+pub const SERVER_CHANNEL_ID: u16 = 0x03ea;
+/// The MCS I/O channel, as assigned by the Windows servers.
+pub const IO_CHANNEL_ID: u16 = 0x03eb;
+/// The MCS user channel, as assigned by the Windows servers to the first client.
+pub const DEFAULT_USER_CHANNEL_ID: u16 = 0x03ef;
+
+/// The version of the Share Control Header (`TS_PROTOCOL_VERSION`).
+pub const SHARE_CONTROL_PROTOCOL_VERSION: u16 = 0x0010;
+
+/// The version of the RemoteFX codec, of its capability sets and of its messages.
+pub const RFX_VERSION: u16 = 0x0100;
+
+/// The GUID of the NSCodec in the Bitmap Codecs Capability Set.
+#[rustfmt::skip]
+pub const CODEC_GUID_NSCODEC: Guid = Guid(0xca8d_1bb9, 0x000f, 0x154f, 0x58, 0x9f, 0xae, 0x2d, 0x1a, 0x87, 0xe2, 0xd6);
+/// The GUID of RemoteFX in the Bitmap Codecs Capability Set, for the surface commands.
+#[rustfmt::skip]
+pub const CODEC_GUID_REMOTEFX: Guid = Guid(0x7677_2f12, 0xbd72, 0x4463, 0xaf, 0xb3, 0xb7, 0x3c, 0x9c, 0x6f, 0x78, 0x86);
+/// The GUID of RemoteFX in the Bitmap Codecs Capability Set, for the image mode.
+#[rustfmt::skip]
+pub const CODEC_GUID_IMAGE_REMOTEFX: Guid = Guid(0x2744_ccd4, 0x9d8a, 0x4e74, 0x80, 0x3c, 0x0e, 0xcb, 0xee, 0xa1, 0x9c, 0x54);
+/// The GUID of the codecs the server is to ignore in the Bitmap Codecs Capability Set.
+#[rustfmt::skip]
+pub const CODEC_GUID_IGNORE: Guid = Guid(0x9c43_51a6, 0x3535, 0x42ae, 0x91, 0x0c, 0xcd, 0xfc, 0xe5, 0x76, 0x0b, 0x58);
+
+/// The major version of the Device Redirection Virtual Channel Extension.
+pub const RDPDR_VERSION_MAJOR: u16 = 0x0001;
+/// The minor version of the Device Redirection Virtual Channel Extension implemented by the client,
+/// announcing the support of the User Logged On PDU and of the Unicode device names.
+pub const RDPDR_VERSION_MINOR_12: u16 = 0x000C;
+pub const RDPDR_GENERAL_CAPABILITY_VERSION_01: u32 = 0x0000_0001;
+/// The version of the General Capability Set of the device redirection carrying `special_type_device_cap`.
+pub const RDPDR_GENERAL_CAPABILITY_VERSION_02: u32 = 0x0000_0002;
+pub const RDPDR_DRIVE_CAPABILITY_VERSION_02: u32 = 0x0000_0002;
+pub const RDPDR_SMARTCARD_CAPABILITY_VERSION_01: u32 = 0x0000_0001;
@@ -1,11 +1,11 @@
 use std::io;
 
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
-use failure::Fail;
 use num_derive::{FromPrimitive, ToPrimitive};
 use num_traits::{FromPrimitive, ToPrimitive};
+use thiserror::Error;
 
-use crate::{impl_from_error, PduParsing};
+use crate::{impl_from_error, LimitsConfig, PduParsing};
 
 #[cfg(test)]
 pub mod test;
@@ -25,7 +25,8 @@ pub use self::cluster_data::{ClientClusterData, ClusterDataError, RedirectionFla
 pub use self::conference_create::{ConferenceCreateRequest, ConferenceCreateResponse};
 pub use self::core_data::client::{
     ClientColorDepth, ClientCoreData, ClientCoreOptionalData, ClientEarlyCapabilityFlags, ColorDepth, ConnectionType,
-    HighColorDepth, KeyboardType, SecureAccessSequence, SupportedColorDepths, IME_FILE_NAME_SIZE,
+    HighColorDepth, KeyboardType, SecureAccessSequence, SupportedColorDepths, CLIENT_NAME_SIZE, DIG_PRODUCT_ID_SIZE,
+    IME_FILE_NAME_SIZE,
 };
 pub use self::core_data::server::{ServerCoreData, ServerCoreOptionalData, ServerEarlyCapabilityFlags};
 pub use self::core_data::{CoreDataError, RdpVersion};
@@ -213,23 +214,50 @@ impl ServerGccBlocks {
     }
 }
 
-impl PduParsing for ServerGccBlocks {
-    type Error = GccError;
-
-    fn from_buffer(mut buffer: impl io::Read) -> Result<Self, Self::Error> {
+impl ServerGccBlocks {
+    /// Decodes the data blocks, failing if their number or total length exceed the budgets.
+    pub fn from_buffer_with_limits(mut buffer: impl io::Read, limits: &LimitsConfig) -> Result<Self, GccError> {
         let mut core = None;
         let mut network = None;
         let mut security = None;
         let mut message_channel = None;
         let mut multi_transport_channel = None;
 
+        let mut blocks_count = 0;
+        let mut user_data_length = 0;
+
         loop {
             let user_header = user_header_try!(UserDataHeader::<ServerGccType>::from_buffer(&mut buffer));
 
+            blocks_count += 1;
+            if blocks_count > limits.max_gcc_blocks {
+                return Err(GccError::LimitExceeded(format!(
+                    "more than {} GCC blocks",
+                    limits.max_gcc_blocks
+                )));
+            }
+
+            user_data_length += user_header.block_length();
+            if user_data_length > limits.max_user_data_length {
+                return Err(GccError::LimitExceeded(format!(
+                    "GCC blocks length {} is greater than {}",
+                    user_data_length, limits.max_user_data_length
+                )));
+            }
+
             match user_header.block_type {
                 ServerGccType::CoreData => core = Some(ServerCoreData::from_buffer(user_header.block_data.as_slice())?),
                 ServerGccType::NetworkData => {
-                    network = Some(ServerNetworkData::from_buffer(user_header.block_data.as_slice())?)
+                    let network_data = ServerNetworkData::from_buffer(user_header.block_data.as_slice())?;
+                    if network_data.channel_ids.len() > limits.max_channel_count {
+                        return Err(GccError::LimitExceeded(format!(
+                            "{} static channels is more than {}",
+                            network_data.channel_ids.len(),
+                            limits.max_channel_count
+                        )));
+                    }
+
+                    network = Some(network_data)
                 }
                 ServerGccType::SecurityData => {
                     security = Some(ServerSecurityData::from_buffer(user_header.block_data.as_slice())?)
@@ -255,6 +283,14 @@ impl PduParsing for ServerGccBlocks {
             multi_transport_channel,
         })
     }
+}
+
+impl PduParsing for ServerGccBlocks {
+    type Error = GccError;
+
+    fn from_buffer(buffer: impl io::Read) -> Result<Self, Self::Error> {
+        Self::from_buffer_with_limits(buffer, &LimitsConfig::default())
+    }
 
     fn to_buffer(&self, mut buffer: impl io::Write) -> Result<(), Self::Error> {
         UserDataHeader::from_gcc_block(ServerGccType::CoreData, &self.core)?.to_buffer(&mut buffer)?;
@@ -368,34 +404,36 @@ impl<T: FromPrimitive + ToPrimitive> PduParsing for UserDataHeader<T> {
     }
 }
 
-#[derive(Debug, Fail)]
+#[derive(Debug, Error)]
 pub enum GccError {
-    #[fail(display = "IO error: {}", _0)]
-    IOError(#[fail(cause)] io::Error),
-    #[fail(display = "Core data block error: {}", _0)]
-    CoreError(#[fail(cause)] CoreDataError),
-    #[fail(display = "Security data block error: {}", _0)]
-    SecurityError(#[fail(cause)] SecurityDataError),
-    #[fail(display = "Network data block error: {}", _0)]
-    NetworkError(#[fail(cause)] NetworkDataError),
-    #[fail(display = "Cluster data block error: {}", _0)]
-    ClusterError(#[fail(cause)] ClusterDataError),
-    #[fail(display = "Monitor data block error: {}", _0)]
-    MonitorError(#[fail(cause)] MonitorDataError),
-    #[fail(display = "Multi-transport channel data block error: {}", _0)]
-    MultiTransportChannelError(#[fail(cause)] MultiTransportChannelDataError),
-    #[fail(display = "Monitor extended data block error: {}", _0)]
-    MonitorExtendedError(#[fail(cause)] MonitorExtendedDataError),
-    #[fail(display = "Invalid GCC block type")]
+    #[error("IO error: {}", .0)]
+    IOError(#[source] io::Error),
+    #[error("Core data block error: {}", .0)]
+    CoreError(#[source] CoreDataError),
+    #[error("Security data block error: {}", .0)]
+    SecurityError(#[source] SecurityDataError),
+    #[error("Network data block error: {}", .0)]
+    NetworkError(#[source] NetworkDataError),
+    #[error("Cluster data block error: {}", .0)]
+    ClusterError(#[source] ClusterDataError),
+    #[error("Monitor data block error: {}", .0)]
+    MonitorError(#[source] MonitorDataError),
+    #[error("Multi-transport channel data block error: {}", .0)]
+    MultiTransportChannelError(#[source] MultiTransportChannelDataError),
+    #[error("Monitor extended data block error: {}", .0)]
+    MonitorExtendedError(#[source] MonitorExtendedDataError),
+    #[error("Invalid GCC block type")]
     InvalidGccType,
-    #[fail(display = "Invalid conference create request: {}", _0)]
+    #[error("Invalid conference create request: {}", .0)]
     InvalidConferenceCreateRequest(String),
-    #[fail(display = "Invalid Conference create response: {}", _0)]
+    #[error("Invalid Conference create response: {}", .0)]
     InvalidConferenceCreateResponse(String),
-    #[fail(display = "A server did not send the required GCC data block: {:?}", _0)]
+    #[error("A server did not send the required GCC data block: {:?}", .0)]
     RequiredClientDataBlockIsAbsent(ClientGccType),
-    #[fail(display = "A client did not send the required GCC data block: {:?}", _0)]
+    #[error("A client did not send the required GCC data block: {:?}", .0)]
     RequiredServerDataBlockIsAbsent(ServerGccType),
+    #[error("GCC data blocks exceed the limits: {}", .0)]
+    LimitExceeded(String),
 }
 
 impl_from_error!(io::Error, GccError, GccError::IOError);
@@ -5,9 +5,9 @@ use std::io;
 
 use bitflags::bitflags;
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
-use failure::Fail;
 use num_derive::{FromPrimitive, ToPrimitive};
 use num_traits::{FromPrimitive, ToPrimitive};
+use thiserror::Error;
 
 use crate::{impl_from_error, PduParsing};
 
@@ -75,11 +75,11 @@ pub enum RedirectionVersion {
     V6 = 5,
 }
 
-#[derive(Debug, Fail)]
+#[derive(Debug, Error)]
 pub enum ClusterDataError {
-    #[fail(display = "IO error: {}", _0)]
-    IOError(#[fail(cause)] io::Error),
-    #[fail(display = "Invalid redirection flags field")]
+    #[error("IO error: {}", .0)]
+    IOError(#[source] io::Error),
+    #[error("Invalid redirection flags field")]
     InvalidRedirectionFlags,
 }
 
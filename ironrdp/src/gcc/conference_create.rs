@@ -4,7 +4,7 @@ pub mod test;
 use std::io;
 
 use super::{ClientGccBlocks, GccError, ServerGccBlocks};
-use crate::{mcs, per, PduParsing};
+use crate::{mcs, per, LimitsConfig, PduParsing};
 
 const CONFERENCE_REQUEST_OBJECT_ID: [u8; 6] = [0, 0, 20, 124, 0, 1];
 const CONFERENCE_REQUEST_CLIENT_TO_SERVER_H221_NON_STANDARD: &[u8; 4] = b"Duca";
@@ -150,10 +150,10 @@ pub struct ConferenceCreateResponse {
     pub gcc_blocks: ServerGccBlocks,
 }
 
-impl PduParsing for ConferenceCreateResponse {
-    type Error = GccError;
-
-    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+impl ConferenceCreateResponse {
+    /// Decodes the PDU, failing early if the lengths and counts announced
+    /// in the data blocks exceed the budgets.
+    pub fn from_buffer_with_limits(mut stream: impl io::Read, limits: &LimitsConfig) -> Result<Self, GccError> {
         // ConnectData::Key: select type OBJECT_IDENTIFIER
         if per::read_choice(&mut stream)? != OBJECT_IDENTIFIER_KEY {
             return Err(GccError::InvalidConferenceCreateResponse(String::from(
@@ -200,18 +200,29 @@ impl PduParsing for ConferenceCreateResponse {
             )));
         }
         // h221NonStandard, server-to-client H.221 key, "McDn"
-        if per::read_octet_string(&mut stream, H221_NON_STANDARD_MIN_LENGTH)?
-            != CONFERENCE_REQUEST_SERVER_TO_CLIENT_H221_NON_STANDARD
+        if per::read_octet_string_with_limit(
+            &mut stream,
+            H221_NON_STANDARD_MIN_LENGTH,
+            limits.max_octet_string_length,
+        )? != CONFERENCE_REQUEST_SERVER_TO_CLIENT_H221_NON_STANDARD
         {
             return Err(GccError::InvalidConferenceCreateResponse(String::from(
                 "Got invalid H221NonStandard server-to-client key",
             )));
         }
-        let (_gcc_blocks_buffer_length, _) = per::read_length(&mut stream)?;
-        let gcc_blocks = ServerGccBlocks::from_buffer(&mut stream)?;
+        let (_gcc_blocks_buffer_length, _) = per::read_length_with_limit(&mut stream, limits.max_user_data_length)?;
+        let gcc_blocks = ServerGccBlocks::from_buffer_with_limits(&mut stream, limits)?;
 
         Ok(Self { user_id, gcc_blocks })
     }
+}
+
+impl PduParsing for ConferenceCreateResponse {
+    type Error = GccError;
+
+    fn from_buffer(stream: impl io::Read) -> Result<Self, Self::Error> {
+        Self::from_buffer_with_limits(stream, &LimitsConfig::default())
+    }
 
     fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
         let gcc_blocks_buffer_length = self.gcc_blocks.buffer_length();
@@ -95,3 +95,28 @@ fn buffer_length_is_correct_for_conference_create_response() {
 
     assert_eq!(expected_buffer_len, len);
 }
+
+#[test]
+fn from_buffer_fails_on_h221_key_length_bomb() {
+    let mut buffer = CONFERENCE_CREATE_RESPONSE_PREFIX_BUFFER[..17].to_vec();
+    buffer.extend_from_slice(&[0xbf, 0xff]); // the H.221 key length: 16383 + 4 bytes
+    buffer.extend_from_slice(b"McDn");
+
+    match ConferenceCreateResponse::from_buffer(buffer.as_slice()) {
+        Err(GccError::IOError(e)) => assert_eq!(io::ErrorKind::InvalidData, e.kind()),
+        result => panic!("Expected the length to exceed the limit, got: {:?}", result),
+    }
+}
+
+#[test]
+fn from_buffer_with_limits_fails_on_too_long_gcc_blocks() {
+    let limits = LimitsConfig {
+        max_user_data_length: gcc::test::SERVER_GCC_WITHOUT_OPTIONAL_FIELDS_BUFFER.len() - 1,
+        ..LimitsConfig::default()
+    };
+
+    match ConferenceCreateResponse::from_buffer_with_limits(CONFERENCE_CREATE_RESPONSE_BUFFER.as_slice(), &limits) {
+        Err(GccError::IOError(e)) => assert_eq!(io::ErrorKind::InvalidData, e.kind()),
+        result => panic!("Expected the length to exceed the limit, got: {:?}", result),
+    }
+}
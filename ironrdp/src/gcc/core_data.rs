@@ -3,7 +3,7 @@ pub mod server;
 
 use std::io;
 
-use failure::Fail;
+use thiserror::Error;
 
 use crate::impl_from_error;
 
@@ -43,29 +43,29 @@ impl RdpVersion {
     pub const V10_12: Self = Self(0x0008_0011);
 }
 
-#[derive(Debug, Fail)]
+#[derive(Debug, Error)]
 pub enum CoreDataError {
-    #[fail(display = "IO error: {}", _0)]
-    IOError(#[fail(cause)] io::Error),
-    #[fail(display = "Invalid version field")]
+    #[error("IO error: {}", .0)]
+    IOError(#[source] io::Error),
+    #[error("Invalid version field")]
     InvalidVersion,
-    #[fail(display = "Invalid color depth field")]
+    #[error("Invalid color depth field")]
     InvalidColorDepth,
-    #[fail(display = "Invalid post beta color depth field")]
+    #[error("Invalid post beta color depth field")]
     InvalidPostBetaColorDepth,
-    #[fail(display = "Invalid high color depth field")]
+    #[error("Invalid high color depth field")]
     InvalidHighColorDepth,
-    #[fail(display = "Invalid supported color depths field")]
+    #[error("Invalid supported color depths field")]
     InvalidSupportedColorDepths,
-    #[fail(display = "Invalid secure access sequence field")]
+    #[error("Invalid secure access sequence field")]
     InvalidSecureAccessSequence,
-    #[fail(display = "Invalid keyboard type field")]
+    #[error("Invalid keyboard type field")]
     InvalidKeyboardType,
-    #[fail(display = "Invalid early capability flags field")]
+    #[error("Invalid early capability flags field")]
     InvalidEarlyCapabilityFlags,
-    #[fail(display = "Invalid connection type field")]
+    #[error("Invalid connection type field")]
     InvalidConnectionType,
-    #[fail(display = "Invalid server security protocol field")]
+    #[error("Invalid server security protocol field")]
     InvalidServerSecurityProtocol,
 }
 
@@ -13,6 +13,8 @@ use super::{CoreDataError, RdpVersion, VERSION_SIZE};
 use crate::{nego, try_read_optional, try_write_optional, utils, PduParsing};
 
 pub const IME_FILE_NAME_SIZE: usize = 64;
+pub const CLIENT_NAME_SIZE: usize = 32;
+pub const DIG_PRODUCT_ID_SIZE: usize = 64;
 
 const DESKTOP_WIDTH_SIZE: usize = 2;
 const DESKTOP_HEIGHT_SIZE: usize = 2;
@@ -20,7 +22,6 @@ const COLOR_DEPTH_SIZE: usize = 2;
 const SEC_ACCESS_SEQUENCE_SIZE: usize = 2;
 const KEYBOARD_LAYOUT_SIZE: usize = 4;
 const CLIENT_BUILD_SIZE: usize = 4;
-const CLIENT_NAME_SIZE: usize = 32;
 const KEYBOARD_TYPE_SIZE: usize = 4;
 const KEYBOARD_SUB_TYPE_SIZE: usize = 4;
 const KEYBOARD_FUNCTIONAL_KEYS_COUNT_SIZE: usize = 4;
@@ -31,7 +32,6 @@ const SERIAL_NUMBER_SIZE: usize = 4;
 const HIGH_COLOR_DEPTH_SIZE: usize = 2;
 const SUPPORTED_COLOR_DEPTHS_SIZE: usize = 2;
 const EARLY_CAPABILITY_FLAGS_SIZE: usize = 2;
-const DIG_PRODUCT_ID_SIZE: usize = 64;
 const CONNECTION_TYPE_SIZE: usize = 1;
 const PADDING_SIZE: usize = 1;
 const SERVER_SELECTED_PROTOCOL_SIZE: usize = 4;
@@ -135,9 +135,11 @@ impl PduParsing for ClientCoreData {
     }
 
     fn to_buffer(&self, mut buffer: impl io::Write) -> Result<(), Self::Error> {
-        let mut client_name_buffer = utils::string_to_utf16(self.client_name.as_ref());
+        let mut client_name_buffer =
+            utils::string_to_truncated_utf16(self.client_name.as_ref(), CLIENT_NAME_SIZE / 2 - 1);
         client_name_buffer.resize(CLIENT_NAME_SIZE - 2, 0);
-        let mut ime_file_name_buffer = utils::string_to_utf16(self.ime_file_name.as_ref());
+        let mut ime_file_name_buffer =
+            utils::string_to_truncated_utf16(self.ime_file_name.as_ref(), IME_FILE_NAME_SIZE / 2 - 1);
         ime_file_name_buffer.resize(IME_FILE_NAME_SIZE - 2, 0);
 
         buffer.write_u32::<LittleEndian>(self.version.0)?;
@@ -278,7 +280,7 @@ impl PduParsing for ClientCoreOptionalData {
             .write_u16::<LittleEndian>(value.bits()));
 
         try_write_optional!(self.dig_product_id, |value: &str| {
-            let mut dig_product_id_buffer = utils::string_to_utf16(value);
+            let mut dig_product_id_buffer = utils::string_to_truncated_utf16(value, DIG_PRODUCT_ID_SIZE / 2 - 1);
             dig_product_id_buffer.resize(DIG_PRODUCT_ID_SIZE - 2, 0);
             dig_product_id_buffer.extend_from_slice([0; 2].as_ref()); // UTF-16 null terminator
 
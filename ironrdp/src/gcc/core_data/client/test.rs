@@ -274,3 +274,18 @@ fn client_color_depth_is_32_bpp_if_want_32_bpp_flag_is_set() {
     assert_eq!(expected_core_data, core_data);
     assert_eq!(expected_client_color_depth, core_data.client_color_depth());
 }
+
+#[test]
+fn to_buffer_truncates_client_name_without_splitting_surrogate_pair() {
+    let mut core_data = CLIENT_CORE_DATA_WITHOUT_OPTIONAL_FIELDS.clone();
+    core_data.client_name = String::from("ABCDEFGHIJKLMN\u{1F600}");
+
+    let mut buff = Vec::new();
+    core_data.to_buffer(&mut buff).unwrap();
+
+    assert_eq!(core_data.buffer_length(), buff.len());
+    assert_eq!(
+        "ABCDEFGHIJKLMN",
+        ClientCoreData::from_buffer(buff.as_slice()).unwrap().client_name
+    );
+}
@@ -5,7 +5,7 @@ use std::io;
 
 use bitflags::bitflags;
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
-use failure::Fail;
+use thiserror::Error;
 
 use crate::{impl_from_error, PduParsing};
 
@@ -102,13 +102,13 @@ bitflags! {
     }
 }
 
-#[derive(Debug, Fail)]
+#[derive(Debug, Error)]
 pub enum MonitorDataError {
-    #[fail(display = "IO error: {}", _0)]
-    IOError(#[fail(cause)] io::Error),
-    #[fail(display = "Invalid monitor count field")]
+    #[error("IO error: {}", .0)]
+    IOError(#[source] io::Error),
+    #[error("Invalid monitor count field")]
     InvalidMonitorCount,
-    #[fail(display = "Invalid monitor flags field")]
+    #[error("Invalid monitor flags field")]
     InvalidMonitorFlags,
 }
 
@@ -4,9 +4,9 @@ pub mod test;
 use std::io;
 
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
-use failure::Fail;
 use num_derive::{FromPrimitive, ToPrimitive};
 use num_traits::{FromPrimitive, ToPrimitive};
+use thiserror::Error;
 
 use crate::{impl_from_error, PduParsing};
 
@@ -117,15 +117,15 @@ pub enum MonitorOrientation {
     PortraitFlipped = 270,
 }
 
-#[derive(Debug, Fail)]
+#[derive(Debug, Error)]
 pub enum MonitorExtendedDataError {
-    #[fail(display = "IO error: {}", _0)]
-    IOError(#[fail(cause)] io::Error),
-    #[fail(display = "Invalid monitor attribute size field")]
+    #[error("IO error: {}", .0)]
+    IOError(#[source] io::Error),
+    #[error("Invalid monitor attribute size field")]
     InvalidMonitorAttributeSize,
-    #[fail(display = "Invalid monitor orientation field")]
+    #[error("Invalid monitor orientation field")]
     InvalidMonitorOrientation,
-    #[fail(display = "Invalid monitor count field")]
+    #[error("Invalid monitor count field")]
     InvalidMonitorCount,
 }
 
@@ -5,7 +5,7 @@ use std::io;
 
 use bitflags::bitflags;
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
-use failure::Fail;
+use thiserror::Error;
 
 use crate::{impl_from_error, PduParsing};
 
@@ -45,11 +45,11 @@ bitflags! {
     }
 }
 
-#[derive(Debug, Fail)]
+#[derive(Debug, Error)]
 pub enum MultiTransportChannelDataError {
-    #[fail(display = "IO error: {}", _0)]
-    IOError(#[fail(cause)] io::Error),
-    #[fail(display = "Invalid flags field")]
+    #[error("IO error: {}", .0)]
+    IOError(#[source] io::Error),
+    #[error("Invalid flags field")]
     InvalidMultiTransportFlags,
 }
 
@@ -6,8 +6,8 @@ use std::str;
 
 use bitflags::bitflags;
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
-use failure::Fail;
 use num_integer::Integer;
+use thiserror::Error;
 
 use crate::{impl_from_error, try_read_optional, PduParsing};
 
@@ -156,7 +156,7 @@ impl PduParsing for Channel {
     }
 
     fn buffer_length(&self) -> usize {
-        SERVER_CHANNEL_SIZE
+        CLIENT_CHANNEL_SIZE
     }
 }
 
@@ -176,15 +176,15 @@ bitflags! {
     }
 }
 
-#[derive(Debug, Fail)]
+#[derive(Debug, Error)]
 pub enum NetworkDataError {
-    #[fail(display = "IO error: {}", _0)]
-    IOError(#[fail(cause)] io::Error),
-    #[fail(display = "UTF-8 error: {}", _0)]
-    Utf8Error(#[fail(cause)] str::Utf8Error),
-    #[fail(display = "Invalid channel options field")]
+    #[error("IO error: {}", .0)]
+    IOError(#[source] io::Error),
+    #[error("UTF-8 error: {}", .0)]
+    Utf8Error(#[source] str::Utf8Error),
+    #[error("Invalid channel options field")]
     InvalidChannelOptions,
-    #[fail(display = "Invalid channel count field")]
+    #[error("Invalid channel count field")]
     InvalidChannelCount,
 }
 
@@ -5,9 +5,9 @@ use std::io;
 
 use bitflags::bitflags;
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
-use failure::Fail;
 use num_derive::{FromPrimitive, ToPrimitive};
 use num_traits::{FromPrimitive, ToPrimitive};
+use thiserror::Error;
 
 use crate::{impl_from_error, PduParsing};
 
@@ -79,6 +79,12 @@ impl ServerSecurityData {
             server_cert: Vec::new(),
         }
     }
+
+    /// Returns `true` if the server selected Standard RDP Security,
+    /// i.e. the client is expected to encrypt the MCS payloads itself.
+    pub fn is_encryption_required(&self) -> bool {
+        !self.encryption_method.is_empty() || self.encryption_level != EncryptionLevel::None
+    }
 }
 
 impl PduParsing for ServerSecurityData {
@@ -183,19 +189,19 @@ pub enum EncryptionLevel {
     Fips = 4,
 }
 
-#[derive(Debug, Fail)]
+#[derive(Debug, Error)]
 pub enum SecurityDataError {
-    #[fail(display = "IO error: {}", _0)]
-    IOError(#[fail(cause)] io::Error),
-    #[fail(display = "Invalid encryption methods field")]
+    #[error("IO error: {}", .0)]
+    IOError(#[source] io::Error),
+    #[error("Invalid encryption methods field")]
     InvalidEncryptionMethod,
-    #[fail(display = "Invalid encryption level field")]
+    #[error("Invalid encryption level field")]
     InvalidEncryptionLevel,
-    #[fail(display = "Invalid server random length field: {}", _0)]
+    #[error("Invalid server random length field: {}", .0)]
     InvalidServerRandomLen(u32),
-    #[fail(display = "Invalid input: {}", _0)]
+    #[error("Invalid input: {}", .0)]
     InvalidInput(String),
-    #[fail(display = "Invalid server certificate length: {}", _0)]
+    #[error("Invalid server certificate length: {}", .0)]
     InvalidServerCertificateLen(u32),
 }
 
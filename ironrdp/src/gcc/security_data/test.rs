@@ -201,3 +201,13 @@ fn buffer_length_is_correct_for_server_security_data_with_optional_fields() {
 
     assert_eq!(expected_buffer_len, len);
 }
+
+#[test]
+fn encryption_is_not_required_for_server_security_data_without_optional_fields() {
+    assert!(!SERVER_SECURITY_DATA_WITHOUT_OPTIONAL_FIELDS.is_encryption_required());
+}
+
+#[test]
+fn encryption_is_required_for_server_security_data_with_optional_fields() {
+    assert!(SERVER_SECURITY_DATA_WITH_OPTIONAL_FIELDS.is_encryption_required());
+}
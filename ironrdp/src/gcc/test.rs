@@ -1,5 +1,6 @@
 use lazy_static::lazy_static;
 use num_traits::{FromPrimitive, ToPrimitive};
+use proptest::prelude::*;
 
 use super::*;
 use crate::gcc::{
@@ -353,3 +354,109 @@ fn from_buffer_correctly_handles_invalid_lengths_in_user_data_header() {
 
     assert!(UserDataHeader::<ClientGccType>::from_buffer(buffer.as_ref()).is_err());
 }
+
+#[test]
+fn from_buffer_with_limits_fails_on_too_many_server_gcc_blocks() {
+    let buffer = SERVER_GCC_WITHOUT_OPTIONAL_FIELDS_BUFFER.repeat(LimitsConfig::DEFAULT_MAX_GCC_BLOCKS);
+
+    assert!(matches!(
+        ServerGccBlocks::from_buffer_with_limits(buffer.as_slice(), &LimitsConfig::default()),
+        Err(GccError::LimitExceeded(_))
+    ));
+}
+
+#[test]
+fn from_buffer_with_limits_fails_on_too_long_server_gcc_blocks() {
+    let limits = LimitsConfig {
+        max_user_data_length: SERVER_GCC_WITHOUT_OPTIONAL_FIELDS_BUFFER.len() - 1,
+        ..LimitsConfig::default()
+    };
+
+    assert!(matches!(
+        ServerGccBlocks::from_buffer_with_limits(SERVER_GCC_WITHOUT_OPTIONAL_FIELDS_BUFFER.as_slice(), &limits),
+        Err(GccError::LimitExceeded(_))
+    ));
+}
+
+#[test]
+fn from_buffer_with_limits_fails_on_too_many_static_channels() {
+    let limits = LimitsConfig {
+        max_channel_count: 2,
+        ..LimitsConfig::default()
+    };
+
+    assert!(matches!(
+        ServerGccBlocks::from_buffer_with_limits(SERVER_GCC_WITHOUT_OPTIONAL_FIELDS_BUFFER.as_slice(), &limits),
+        Err(GccError::LimitExceeded(_))
+    ));
+}
+
+pub fn any_monitor() -> impl Strategy<Value = Monitor> {
+    (any::<[i32; 4]>(), any::<u32>()).prop_map(|([left, top, right, bottom], flags)| Monitor {
+        left,
+        top,
+        right,
+        bottom,
+        flags: MonitorFlags::from_bits_truncate(flags),
+    })
+}
+
+fn any_extended_monitor_info() -> impl Strategy<Value = ExtendedMonitorInfo> {
+    (
+        any::<[u32; 4]>(),
+        prop_oneof![
+            Just(MonitorOrientation::Landscape),
+            Just(MonitorOrientation::Portrait),
+            Just(MonitorOrientation::LandscapeFlipped),
+            Just(MonitorOrientation::PortraitFlipped),
+        ],
+    )
+        .prop_map(
+            |([physical_width, physical_height, desktop_scale_factor, device_scale_factor], orientation)| {
+                ExtendedMonitorInfo {
+                    physical_width,
+                    physical_height,
+                    orientation,
+                    desktop_scale_factor,
+                    device_scale_factor,
+                }
+            },
+        )
+}
+
+fn any_channel() -> impl Strategy<Value = Channel> {
+    ("[a-z]{0,7}", any::<u32>()).prop_map(|(name, options)| Channel {
+        name,
+        options: ChannelOptions::from_bits_truncate(options),
+    })
+}
+
+crate::round_trip_proptest! {
+    client_security_data_round_trip: ClientSecurityData = (any::<u32>(), any::<u32>()).prop_map(
+        |(encryption_methods, ext_encryption_methods)| ClientSecurityData {
+            encryption_methods: EncryptionMethod::from_bits_truncate(encryption_methods),
+            ext_encryption_methods,
+        }
+    );
+    client_network_data_round_trip: ClientNetworkData =
+        prop::collection::vec(any_channel(), 0..=31).prop_map(|channels| ClientNetworkData { channels });
+    server_network_data_round_trip: ServerNetworkData = (prop::collection::vec(any::<u16>(), 0..32), any::<u16>())
+        .prop_map(|(channel_ids, io_channel)| ServerNetworkData { channel_ids, io_channel });
+    channel_round_trip: Channel = any_channel();
+    client_cluster_data_round_trip: ClientClusterData = (any::<u32>(), 0..=5u8, any::<u32>()).prop_map(
+        |(flags, redirection_version, redirected_session_id)| ClientClusterData {
+            flags: RedirectionFlags::from_bits_truncate(flags),
+            redirection_version: RedirectionVersion::from_u8(redirection_version).unwrap(),
+            redirected_session_id,
+        }
+    );
+    multi_transport_channel_data_round_trip: MultiTransportChannelData = any::<u32>()
+        .prop_map(|flags| MultiTransportChannelData { flags: MultiTransportFlags::from_bits_truncate(flags) });
+    client_monitor_data_round_trip: ClientMonitorData =
+        prop::collection::vec(any_monitor(), 0..=16).prop_map(|monitors| ClientMonitorData { monitors });
+    client_monitor_extended_data_round_trip: ClientMonitorExtendedData =
+        prop::collection::vec(any_extended_monitor_info(), 0..=16)
+            .prop_map(|extended_monitors_info| ClientMonitorExtendedData { extended_monitors_info });
+    server_message_channel_data_round_trip: ServerMessageChannelData = any::<u16>()
+        .prop_map(|mcs_message_channel_id| ServerMessageChannelData { mcs_message_channel_id });
+}
@@ -1,9 +1,9 @@
 use std::io;
 
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
-use failure::Fail;
 use num_derive::{FromPrimitive, ToPrimitive};
 use num_traits::{FromPrimitive, ToPrimitive};
+use thiserror::Error;
 
 use crate::{impl_from_error, PduParsing};
 
@@ -18,6 +18,7 @@ pub mod sync;
 pub mod unicode;
 pub mod unused;
 
+use self::fast_path::FastPathInputEvent;
 pub use self::mouse::MousePdu;
 pub use self::mouse_x::MouseXPdu;
 pub use self::scan_code::ScanCodePdu;
@@ -113,6 +114,55 @@ impl PduParsing for InputEvent {
     }
 }
 
+impl TryFrom<FastPathInputEvent> for InputEvent {
+    type Error = InputEventError;
+
+    /// Converts a Fast-Path input event into its slow-path equivalent,
+    /// e.g. to exercise a server which does not support Fast-Path input.
+    fn try_from(event: FastPathInputEvent) -> Result<Self, Self::Error> {
+        match event {
+            FastPathInputEvent::KeyboardEvent(flags, key_code) => {
+                let mut scan_code_flags = scan_code::KeyboardFlags::empty();
+                scan_code_flags.set(
+                    scan_code::KeyboardFlags::RELEASE,
+                    flags.contains(fast_path::KeyboardFlags::FASTPATH_INPUT_KBDFLAGS_RELEASE),
+                );
+                scan_code_flags.set(
+                    scan_code::KeyboardFlags::EXTENDED,
+                    flags.contains(fast_path::KeyboardFlags::FASTPATH_INPUT_KBDFLAGS_EXTENDED),
+                );
+                scan_code_flags.set(
+                    scan_code::KeyboardFlags::EXTENDED_1,
+                    flags.contains(fast_path::KeyboardFlags::FASTPATH_INPUT_KBDFLAGS_EXTENDED1),
+                );
+
+                Ok(Self::ScanCode(ScanCodePdu {
+                    flags: scan_code_flags,
+                    key_code: u16::from(key_code),
+                }))
+            }
+            FastPathInputEvent::UnicodeKeyboardEvent(flags, unicode_code) => {
+                let mut unicode_flags = unicode::KeyboardFlags::empty();
+                unicode_flags.set(
+                    unicode::KeyboardFlags::RELEASE,
+                    flags.contains(fast_path::KeyboardFlags::FASTPATH_INPUT_KBDFLAGS_RELEASE),
+                );
+
+                Ok(Self::Unicode(UnicodePdu {
+                    flags: unicode_flags,
+                    unicode_code,
+                }))
+            }
+            FastPathInputEvent::MouseEvent(pdu) => Ok(Self::Mouse(pdu)),
+            FastPathInputEvent::MouseEventEx(pdu) => Ok(Self::MouseX(pdu)),
+            FastPathInputEvent::SyncEvent(flags) => Ok(Self::Sync(SyncPdu {
+                flags: sync::SyncToggleFlags::from_bits_truncate(u32::from(flags.bits())),
+            })),
+            FastPathInputEvent::QoeEvent(_) => Err(InputEventError::NoSlowPathEquivalent),
+        }
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, FromPrimitive, ToPrimitive)]
 #[repr(u16)]
 enum InputEventType {
@@ -137,20 +187,22 @@ impl From<&InputEvent> for InputEventType {
     }
 }
 
-#[derive(Debug, Fail)]
+#[derive(Debug, Error)]
 pub enum InputEventError {
-    #[fail(display = "IO error: {}", _0)]
-    IOError(#[fail(cause)] io::Error),
-    #[fail(display = "Invalid Input Event type: {}", _0)]
+    #[error("IO error: {}", .0)]
+    IOError(#[source] io::Error),
+    #[error("Invalid Input Event type: {}", .0)]
     InvalidInputEventType(u16),
-    #[fail(display = "Encryption not supported")]
+    #[error("Encryption not supported")]
     EncryptionNotSupported,
-    #[fail(display = "Event code not supported {}", _0)]
+    #[error("Event code not supported {}", .0)]
     EventCodeUnsupported(u8),
-    #[fail(display = "Keyboard flags not supported {}", _0)]
+    #[error("Keyboard flags not supported {}", .0)]
     KeyboardFlagsUnsupported(u8),
-    #[fail(display = "Synchronize flags not supported {}", _0)]
+    #[error("Synchronize flags not supported {}", .0)]
     SynchronizeFlagsUnsupported(u8),
+    #[error("Fast-Path input event has no slow-path equivalent")]
+    NoSlowPathEquivalent,
 }
 
 impl_from_error!(io::Error, InputEventError, InputEventError::IOError);
@@ -7,6 +7,8 @@ use num_derive::{FromPrimitive, ToPrimitive};
 use num_traits::{FromPrimitive, ToPrimitive};
 
 use crate::fast_path::EncryptionFlags;
+use crate::input::mouse::{ButtonEvents, MouseButton, MovementEvents, WheelEvents};
+use crate::input::mouse_x::PointerFlags;
 use crate::input::{InputEventError, MousePdu, MouseXPdu};
 
 use crate::{per, PduParsing};
@@ -92,6 +94,45 @@ pub enum FastPathInputEvent {
     SyncEvent(SynchronizeFlags),
 }
 
+impl FastPathInputEvent {
+    /// Creates the event of a mouse button pressed or released at the position,
+    /// the extended buttons being carried by an extended mouse event, which requires
+    /// the server to advertise `INPUT_FLAG_MOUSEX`.
+    pub fn mouse_button(button: MouseButton, pressed: bool, x_position: u16, y_position: u16) -> Self {
+        let mut button_events = match button {
+            MouseButton::Left => ButtonEvents::LEFT_BUTTON,
+            MouseButton::Right => ButtonEvents::RIGHT_BUTTON,
+            MouseButton::Middle => ButtonEvents::MIDDLE_BUTTON_OR_WHEEL,
+            MouseButton::X1 => {
+                return Self::extended_mouse_button(PointerFlags::BUTTON1, pressed, x_position, y_position)
+            }
+            MouseButton::X2 => {
+                return Self::extended_mouse_button(PointerFlags::BUTTON2, pressed, x_position, y_position)
+            }
+        };
+        button_events.set(ButtonEvents::DOWN, pressed);
+
+        Self::MouseEvent(MousePdu {
+            wheel_events: WheelEvents::empty(),
+            movement_events: MovementEvents::empty(),
+            button_events,
+            number_of_wheel_rotations: 0,
+            x_position,
+            y_position,
+        })
+    }
+
+    fn extended_mouse_button(mut flags: PointerFlags, pressed: bool, x_position: u16, y_position: u16) -> Self {
+        flags.set(PointerFlags::DOWN, pressed);
+
+        Self::MouseEventEx(MouseXPdu {
+            flags,
+            x_position,
+            y_position,
+        })
+    }
+}
+
 impl PduParsing for FastPathInputEvent {
     type Error = InputEventError;
 
@@ -6,7 +6,14 @@ use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use super::InputEventError;
 use crate::PduParsing;
 
-const WHEEL_ROTATION_MASK: u16 = 0x00FF;
+const WHEEL_ROTATION_MASK: u16 = 0x01FF;
+const WHEEL_ROTATION_SIGN_EXTENSION: i16 = 0x0200;
+
+/// The number of rotation units reported for one detent of a standard mouse wheel.
+/// High-resolution devices (e.g. trackpads) report fractions of this value.
+pub const WHEEL_DELTA: i16 = 120;
+/// The maximum magnitude of the wheel rotation that fits in one pointer event.
+pub const MAX_WHEEL_ROTATION: i16 = 0x00FF;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct MousePdu {
@@ -27,10 +34,13 @@ impl PduParsing for MousePdu {
         let wheel_events = WheelEvents::from_bits_truncate(pointer_flags);
         let movement_events = MovementEvents::from_bits_truncate(pointer_flags);
         let button_events = ButtonEvents::from_bits_truncate(pointer_flags);
-        let mut number_of_wheel_rotations = (pointer_flags & WHEEL_ROTATION_MASK) as i16;
-        if wheel_events.contains(WheelEvents::WHEEL_NEGATIVE) {
-            number_of_wheel_rotations *= -1;
-        }
+        // the rotation is a 9-bit two's complement value, where WHEEL_NEGATIVE is the sign bit
+        let number_of_wheel_rotations = (pointer_flags & WHEEL_ROTATION_MASK) as i16;
+        let number_of_wheel_rotations = if wheel_events.contains(WheelEvents::WHEEL_NEGATIVE) {
+            number_of_wheel_rotations - WHEEL_ROTATION_SIGN_EXTENSION
+        } else {
+            number_of_wheel_rotations
+        };
 
         let x_position = stream.read_u16::<LittleEndian>()?;
         let y_position = stream.read_u16::<LittleEndian>()?;
@@ -46,18 +56,12 @@ impl PduParsing for MousePdu {
     }
 
     fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
-        let wheel_negative_flag = if self.number_of_wheel_rotations < 0 {
-            WheelEvents::WHEEL_NEGATIVE
-        } else {
-            WheelEvents::empty()
-        };
-        let number_of_wheel_rotations = self.number_of_wheel_rotations as u16 & !WHEEL_ROTATION_MASK;
+        // WHEEL_NEGATIVE is set by the two's complement representation of the rotation
+        let wheel_events = self.wheel_events - WheelEvents::WHEEL_NEGATIVE;
+        let number_of_wheel_rotations = self.number_of_wheel_rotations as u16 & WHEEL_ROTATION_MASK;
 
-        let flags = self.wheel_events.bits()
-            | self.movement_events.bits()
-            | self.button_events.bits()
-            | wheel_negative_flag.bits()
-            | number_of_wheel_rotations;
+        let flags =
+            wheel_events.bits() | self.movement_events.bits() | self.button_events.bits() | number_of_wheel_rotations;
 
         stream.write_u16::<LittleEndian>(flags)?;
         stream.write_u16::<LittleEndian>(self.x_position)?;
@@ -71,6 +75,61 @@ impl PduParsing for MousePdu {
     }
 }
 
+impl MousePdu {
+    /// Creates the pointer events for a wheel rotation of an arbitrary amount of units
+    /// (see [`WHEEL_DELTA`]). A positive rotation scrolls up for the vertical wheel
+    /// and right for the horizontal one. The rotations which do not fit in one event
+    /// are split over several events.
+    pub fn wheel_rotation(wheel: WheelOrientation, rotation_units: i32, x_position: u16, y_position: u16) -> Vec<Self> {
+        let wheel_events = match wheel {
+            WheelOrientation::Vertical => WheelEvents::VERTICAL_WHEEL,
+            WheelOrientation::Horizontal => WheelEvents::HORIZONTAL_WHEEL,
+        };
+
+        let mut remaining_units = rotation_units;
+        let mut events = Vec::new();
+        while remaining_units != 0 {
+            let units = remaining_units.clamp(-i32::from(MAX_WHEEL_ROTATION), i32::from(MAX_WHEEL_ROTATION));
+            remaining_units -= units;
+
+            // WHEEL_NEGATIVE is the sign bit of the rotation, which the decoded events carry
+            let wheel_events = if units < 0 {
+                wheel_events | WheelEvents::WHEEL_NEGATIVE
+            } else {
+                wheel_events
+            };
+
+            events.push(Self {
+                wheel_events,
+                movement_events: MovementEvents::empty(),
+                button_events: ButtonEvents::empty(),
+                number_of_wheel_rotations: units as i16,
+                x_position,
+                y_position,
+            });
+        }
+
+        events
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+    /// The first extended button, usually navigating back.
+    X1,
+    /// The second extended button, usually navigating forward.
+    X2,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum WheelOrientation {
+    Vertical,
+    Horizontal,
+}
+
 bitflags! {
     pub struct WheelEvents: u16 {
         const HORIZONTAL_WHEEL = 0x0400;
@@ -91,6 +150,5 @@ bitflags! {
         const LEFT_BUTTON = 0x1000;
         const RIGHT_BUTTON = 0x2000;
         const MIDDLE_BUTTON_OR_WHEEL = 0x4000;
-
     }
 }
@@ -44,7 +44,9 @@ impl PduParsing for MouseXPdu {
 bitflags! {
     pub struct PointerFlags: u16 {
         const DOWN = 0x8000;
+        /// The first extended button (XBUTTON1).
         const BUTTON1 = 0x0001;
+        /// The second extended button (XBUTTON2).
         const BUTTON2 = 0x0002;
     }
 }
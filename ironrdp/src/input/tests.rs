@@ -1,11 +1,12 @@
 use lazy_static::lazy_static;
 
 use crate::input::{
-    mouse::{ButtonEvents, MovementEvents, WheelEvents},
-    MousePdu,
+    mouse::{ButtonEvents, MouseButton, MovementEvents, WheelEvents, WheelOrientation},
+    mouse_x::PointerFlags,
+    scan_code, sync, InputEvent, InputEventError, MousePdu, MouseXPdu, ScanCodePdu, SyncPdu,
 };
 
-use super::fast_path::{FastPathInput, FastPathInputEvent};
+use super::fast_path::{FastPathInput, FastPathInputEvent, KeyboardFlags, SynchronizeFlags};
 use crate::PduParsing;
 
 const FASTPATH_INPUT_MESSAGE: [u8; 44] = [
@@ -14,6 +15,12 @@ const FASTPATH_INPUT_MESSAGE: [u8; 44] = [
     0x0, 0x28, 0x4,
 ];
 
+const NEGATIVE_VERTICAL_WHEEL_MOUSE_EVENT_BUFFER: [u8; 6] = [0x88, 0x03, 0x10, 0x00, 0x20, 0x00];
+
+const POSITIVE_HORIZONTAL_WHEEL_MOUSE_EVENT_BUFFER: [u8; 6] = [0xf0, 0x04, 0x10, 0x00, 0x20, 0x00];
+
+const X2_BUTTON_PRESSED_EVENT_BUFFER: [u8; 7] = [0x40, 0x02, 0x80, 0x10, 0x00, 0x20, 0x00];
+
 lazy_static! {
     pub static ref FASTPATH_INPUT: FastPathInput = FastPathInput(vec![
         FastPathInputEvent::MouseEvent(MousePdu {
@@ -65,6 +72,22 @@ lazy_static! {
             y_position: 1064
         })
     ]);
+    pub static ref NEGATIVE_VERTICAL_WHEEL_MOUSE_EVENT: MousePdu = MousePdu {
+        wheel_events: WheelEvents::VERTICAL_WHEEL | WheelEvents::WHEEL_NEGATIVE,
+        movement_events: MovementEvents::empty(),
+        button_events: ButtonEvents::empty(),
+        number_of_wheel_rotations: -120,
+        x_position: 16,
+        y_position: 32
+    };
+    pub static ref POSITIVE_HORIZONTAL_WHEEL_MOUSE_EVENT: MousePdu = MousePdu {
+        wheel_events: WheelEvents::HORIZONTAL_WHEEL,
+        movement_events: MovementEvents::empty(),
+        button_events: ButtonEvents::empty(),
+        number_of_wheel_rotations: 240,
+        x_position: 16,
+        y_position: 32
+    };
 }
 
 #[test]
@@ -82,3 +105,136 @@ fn to_buffer_correctly_serializes_fastpath_input_message() {
 
     assert_eq!(buffer, FASTPATH_INPUT_MESSAGE.as_ref());
 }
+
+#[test]
+fn from_buffer_correctly_parses_negative_vertical_wheel_mouse_event() {
+    let buffer = NEGATIVE_VERTICAL_WHEEL_MOUSE_EVENT_BUFFER.as_ref();
+
+    assert_eq!(
+        *NEGATIVE_VERTICAL_WHEEL_MOUSE_EVENT,
+        MousePdu::from_buffer(buffer).unwrap()
+    );
+}
+
+#[test]
+fn to_buffer_correctly_serializes_negative_vertical_wheel_mouse_event() {
+    let mut buffer = Vec::new();
+    NEGATIVE_VERTICAL_WHEEL_MOUSE_EVENT.to_buffer(&mut buffer).unwrap();
+
+    assert_eq!(buffer, NEGATIVE_VERTICAL_WHEEL_MOUSE_EVENT_BUFFER.as_ref());
+}
+
+#[test]
+fn from_buffer_correctly_parses_positive_horizontal_wheel_mouse_event() {
+    let buffer = POSITIVE_HORIZONTAL_WHEEL_MOUSE_EVENT_BUFFER.as_ref();
+
+    assert_eq!(
+        *POSITIVE_HORIZONTAL_WHEEL_MOUSE_EVENT,
+        MousePdu::from_buffer(buffer).unwrap()
+    );
+}
+
+#[test]
+fn to_buffer_correctly_serializes_positive_horizontal_wheel_mouse_event() {
+    let mut buffer = Vec::new();
+    POSITIVE_HORIZONTAL_WHEEL_MOUSE_EVENT.to_buffer(&mut buffer).unwrap();
+
+    assert_eq!(buffer, POSITIVE_HORIZONTAL_WHEEL_MOUSE_EVENT_BUFFER.as_ref());
+}
+
+#[test]
+fn wheel_rotation_splits_large_rotation_over_several_events() {
+    let events = MousePdu::wheel_rotation(WheelOrientation::Vertical, -600, 16, 32);
+
+    assert_eq!(
+        vec![-255, -255, -90],
+        events
+            .iter()
+            .map(|event| event.number_of_wheel_rotations)
+            .collect::<Vec<_>>()
+    );
+    assert!(events
+        .iter()
+        .all(|event| event.wheel_events == WheelEvents::VERTICAL_WHEEL | WheelEvents::WHEEL_NEGATIVE));
+}
+
+#[test]
+fn negative_wheel_rotation_is_parsed_back_as_is() {
+    for event in MousePdu::wheel_rotation(WheelOrientation::Horizontal, -300, 16, 32) {
+        let mut buffer = Vec::new();
+        event.to_buffer(&mut buffer).unwrap();
+
+        assert_eq!(event, MousePdu::from_buffer(buffer.as_slice()).unwrap());
+    }
+}
+
+#[test]
+fn extended_mouse_button_is_serialized_as_extended_mouse_event() {
+    let mut buffer = Vec::new();
+    FastPathInputEvent::mouse_button(MouseButton::X2, true, 16, 32)
+        .to_buffer(&mut buffer)
+        .unwrap();
+
+    assert_eq!(buffer, X2_BUTTON_PRESSED_EVENT_BUFFER.as_ref());
+}
+
+#[test]
+fn mouse_button_release_converts_to_slow_path_event_without_down_flag() {
+    assert_eq!(
+        InputEvent::MouseX(MouseXPdu {
+            flags: PointerFlags::BUTTON1,
+            x_position: 16,
+            y_position: 32,
+        }),
+        InputEvent::try_from(FastPathInputEvent::mouse_button(MouseButton::X1, false, 16, 32)).unwrap()
+    );
+    assert_eq!(
+        InputEvent::Mouse(MousePdu {
+            wheel_events: WheelEvents::empty(),
+            movement_events: MovementEvents::empty(),
+            button_events: ButtonEvents::MIDDLE_BUTTON_OR_WHEEL,
+            number_of_wheel_rotations: 0,
+            x_position: 16,
+            y_position: 32,
+        }),
+        InputEvent::try_from(FastPathInputEvent::mouse_button(MouseButton::Middle, false, 16, 32)).unwrap()
+    );
+}
+
+#[test]
+fn fast_path_keyboard_event_converts_to_slow_path_scan_code_event() {
+    let event = FastPathInputEvent::KeyboardEvent(
+        KeyboardFlags::FASTPATH_INPUT_KBDFLAGS_RELEASE | KeyboardFlags::FASTPATH_INPUT_KBDFLAGS_EXTENDED,
+        0x1d,
+    );
+
+    assert_eq!(
+        InputEvent::ScanCode(ScanCodePdu {
+            flags: scan_code::KeyboardFlags::RELEASE | scan_code::KeyboardFlags::EXTENDED,
+            key_code: 0x1d,
+        }),
+        InputEvent::try_from(event).unwrap()
+    );
+}
+
+#[test]
+fn fast_path_sync_event_converts_to_slow_path_sync_event() {
+    let event = FastPathInputEvent::SyncEvent(
+        SynchronizeFlags::FASTPATH_INPUT_SYNC_NUM_LOCK | SynchronizeFlags::FASTPATH_INPUT_SYNC_CAPS_LOCK,
+    );
+
+    assert_eq!(
+        InputEvent::Sync(SyncPdu {
+            flags: sync::SyncToggleFlags::NUM_LOCK | sync::SyncToggleFlags::CAPS_LOCK,
+        }),
+        InputEvent::try_from(event).unwrap()
+    );
+}
+
+#[test]
+fn fast_path_qoe_event_has_no_slow_path_equivalent() {
+    assert!(matches!(
+        InputEvent::try_from(FastPathInputEvent::QoeEvent(0)),
+        Err(InputEventError::NoSlowPathEquivalent)
+    ));
+}
@@ -1,9 +1,13 @@
 pub mod codecs;
+pub mod consts;
 pub mod gcc;
 pub mod input;
+pub mod limits;
 pub mod mcs;
 pub mod nego;
 pub mod rdp;
+pub mod rdstls;
+pub mod server;
 
 mod basic_output;
 mod ber;
@@ -12,8 +16,9 @@ mod preconnection;
 mod utils;
 mod x224;
 
-pub use crate::basic_output::{bitmap, fast_path, surface_commands};
-pub use crate::mcs::{ConnectInitial, ConnectResponse, McsError, McsPdu, SendDataContext};
+pub use crate::basic_output::{bitmap, fast_path, orders, pointer, surface_commands};
+pub use crate::limits::LimitsConfig;
+pub use crate::mcs::{ChannelId, ConnectInitial, ConnectResponse, McsError, McsPdu, SendDataContext, UserId};
 pub use crate::nego::*;
 pub use crate::preconnection::{PreconnectionPdu, PreconnectionPduError};
 pub use crate::rdp::vc::dvc;
@@ -86,16 +91,18 @@ impl PduParsing for RdpPdu {
     }
 }
 
-#[derive(Debug, failure::Fail)]
+#[derive(Debug, thiserror::Error)]
 pub enum RdpError {
-    #[fail(display = "IO error: {}", _0)]
-    IOError(#[fail(cause)] std::io::Error),
-    #[fail(display = "X224 error: {}", _0)]
-    X224Error(#[fail(cause)] nego::NegotiationError),
-    #[fail(display = "Surface Commands error: {}", _0)]
-    FastPathError(#[fail(cause)] fast_path::FastPathError),
-    #[fail(display = "Received invalid action code: {}", _0)]
+    #[error("IO error: {}", .0)]
+    IOError(#[source] std::io::Error),
+    #[error("X224 error: {}", .0)]
+    X224Error(#[source] nego::NegotiationError),
+    #[error("Surface Commands error: {}", .0)]
+    FastPathError(#[source] fast_path::FastPathError),
+    #[error("Received invalid action code: {}", .0)]
     InvalidActionCode(u8),
+    #[error("frame of {} bytes is longer than {}", .length, .max)]
+    FrameTooLong { length: usize, max: usize },
 }
 
 impl_from_error!(std::io::Error, RdpError, RdpError::IOError);
@@ -0,0 +1,46 @@
+/// Budgets enforced while decoding the PDUs received from the peer.
+///
+/// The BER/PER readers are not recursive, so the nesting depth is bounded by the PDU layout itself.
+/// The budgets bound the lengths and counts announced by the peer, which are otherwise
+/// only checked once the memory has been allocated.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct LimitsConfig {
+    /// Maximum length of a single BER or PER octet string, except the user data.
+    pub max_octet_string_length: usize,
+    /// Maximum length of the user data, i.e. the GCC Conference Create Response and its data blocks.
+    pub max_user_data_length: usize,
+    /// Maximum number of GCC data blocks in the user data.
+    pub max_gcc_blocks: usize,
+    /// Maximum length of a frame, the TPKT and Fast-Path headers allowing up to 64 KiB.
+    pub max_pdu_length: usize,
+    /// Maximum size of a message of a dynamic virtual channel, once reassembled from its fragments.
+    pub max_dvc_reassembly_size: usize,
+    /// Maximum size of a message of a static virtual channel, once reassembled from its chunks and decompressed.
+    pub max_svc_reassembly_size: usize,
+    /// Maximum number of the static channels joined, and of the dynamic channels opened at once.
+    pub max_channel_count: usize,
+}
+
+impl LimitsConfig {
+    pub const DEFAULT_MAX_OCTET_STRING_LENGTH: usize = 1024;
+    pub const DEFAULT_MAX_USER_DATA_LENGTH: usize = 16 * 1024;
+    pub const DEFAULT_MAX_GCC_BLOCKS: usize = 16;
+    pub const DEFAULT_MAX_PDU_LENGTH: usize = 0xFFFF;
+    pub const DEFAULT_MAX_DVC_REASSEMBLY_SIZE: usize = 64 * 1024 * 1024;
+    pub const DEFAULT_MAX_SVC_REASSEMBLY_SIZE: usize = 64 * 1024 * 1024;
+    pub const DEFAULT_MAX_CHANNEL_COUNT: usize = 256;
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        Self {
+            max_octet_string_length: Self::DEFAULT_MAX_OCTET_STRING_LENGTH,
+            max_user_data_length: Self::DEFAULT_MAX_USER_DATA_LENGTH,
+            max_gcc_blocks: Self::DEFAULT_MAX_GCC_BLOCKS,
+            max_pdu_length: Self::DEFAULT_MAX_PDU_LENGTH,
+            max_dvc_reassembly_size: Self::DEFAULT_MAX_DVC_REASSEMBLY_SIZE,
+            max_svc_reassembly_size: Self::DEFAULT_MAX_SVC_REASSEMBLY_SIZE,
+            max_channel_count: Self::DEFAULT_MAX_CHANNEL_COUNT,
+        }
+    }
+}
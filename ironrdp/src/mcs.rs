@@ -1,9 +1,9 @@
-use std::io;
+use std::{fmt, io};
 
 use byteorder::{ReadBytesExt, WriteBytesExt};
-use failure::Fail;
 use num_derive::{FromPrimitive, ToPrimitive};
 use num_traits::{FromPrimitive, ToPrimitive};
+use thiserror::Error;
 
 use crate::gcc::GccError;
 use crate::{impl_from_error, per, PduParsing};
@@ -20,6 +20,34 @@ pub const RESULT_ENUM_LENGTH: u8 = 16;
 const BASE_CHANNEL_ID: u16 = 1001;
 const SEND_DATA_PDU_DATA_PRIORITY_AND_SEGMENTATION: u8 = 0x70;
 
+/// The ID assigned by the server to the client in the MCS Attach User Confirm PDU,
+/// the initiator of the PDUs the client sends.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct UserId(pub u16);
+
+impl UserId {
+    /// The user channel, which the client joins to receive the PDUs addressed to it, has the ID of the user.
+    pub fn channel_id(self) -> ChannelId {
+        ChannelId(self.0)
+    }
+}
+
+/// The ID of an MCS channel: the I/O channel, the user channel or a static virtual channel.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ChannelId(pub u16);
+
+impl fmt::Display for UserId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl fmt::Display for ChannelId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
 /// The kind of the RDP header message that may carry additional data.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum McsPdu {
@@ -169,7 +197,7 @@ impl PduParsing for ErectDomainPdu {
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct AttachUserConfirmPdu {
-    pub initiator_id: u16,
+    pub initiator_id: UserId,
     pub result: u8,
 }
 
@@ -182,12 +210,12 @@ impl PduParsing for AttachUserConfirmPdu {
 
         Ok(Self {
             result,
-            initiator_id: user_id,
+            initiator_id: UserId(user_id),
         })
     }
     fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
         per::write_enum(&mut stream, self.result)?;
-        per::write_u16(&mut stream, self.initiator_id, BASE_CHANNEL_ID)?;
+        per::write_u16(&mut stream, self.initiator_id.0, BASE_CHANNEL_ID)?;
 
         Ok(())
     }
@@ -198,8 +226,8 @@ impl PduParsing for AttachUserConfirmPdu {
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ChannelJoinRequestPdu {
-    pub initiator_id: u16,
-    pub channel_id: u16,
+    pub initiator_id: UserId,
+    pub channel_id: ChannelId,
 }
 
 impl PduParsing for ChannelJoinRequestPdu {
@@ -210,13 +238,13 @@ impl PduParsing for ChannelJoinRequestPdu {
         let channel_id = per::read_u16(&mut stream, 0)?;
 
         Ok(Self {
-            initiator_id: user_id,
-            channel_id,
+            initiator_id: UserId(user_id),
+            channel_id: ChannelId(channel_id),
         })
     }
     fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
-        per::write_u16(&mut stream, self.initiator_id, BASE_CHANNEL_ID)?;
-        per::write_u16(&mut stream, self.channel_id, 0)?;
+        per::write_u16(&mut stream, self.initiator_id.0, BASE_CHANNEL_ID)?;
+        per::write_u16(&mut stream, self.channel_id.0, 0)?;
 
         Ok(())
     }
@@ -227,10 +255,10 @@ impl PduParsing for ChannelJoinRequestPdu {
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ChannelJoinConfirmPdu {
-    pub channel_id: u16,
+    pub channel_id: ChannelId,
     pub result: u8,
-    pub initiator_id: u16,
-    pub requested_channel_id: u16,
+    pub initiator_id: UserId,
+    pub requested_channel_id: ChannelId,
 }
 
 impl PduParsing for ChannelJoinConfirmPdu {
@@ -244,16 +272,16 @@ impl PduParsing for ChannelJoinConfirmPdu {
 
         Ok(Self {
             result,
-            initiator_id,
-            requested_channel_id,
-            channel_id,
+            initiator_id: UserId(initiator_id),
+            requested_channel_id: ChannelId(requested_channel_id),
+            channel_id: ChannelId(channel_id),
         })
     }
     fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
         per::write_enum(&mut stream, self.result)?;
-        per::write_u16(&mut stream, self.initiator_id, BASE_CHANNEL_ID)?;
-        per::write_u16(&mut stream, self.requested_channel_id, 0)?;
-        per::write_u16(&mut stream, self.channel_id, 0)?;
+        per::write_u16(&mut stream, self.initiator_id.0, BASE_CHANNEL_ID)?;
+        per::write_u16(&mut stream, self.requested_channel_id.0, 0)?;
+        per::write_u16(&mut stream, self.channel_id.0, 0)?;
 
         Ok(())
     }
@@ -266,8 +294,8 @@ impl PduParsing for ChannelJoinConfirmPdu {
 /// [`RdpHeaderMessage`](enum.RdpHeaderMessage.html).
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SendDataContext {
-    pub initiator_id: u16,
-    pub channel_id: u16,
+    pub initiator_id: UserId,
+    pub channel_id: ChannelId,
     pub pdu_length: usize,
 }
 
@@ -281,15 +309,15 @@ impl PduParsing for SendDataContext {
         let (pdu_length, _) = per::read_length(&mut stream)?;
 
         Ok(Self {
-            initiator_id,
-            channel_id,
+            initiator_id: UserId(initiator_id),
+            channel_id: ChannelId(channel_id),
             pdu_length: pdu_length as usize,
         })
     }
 
     fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
-        per::write_u16(&mut stream, self.initiator_id, BASE_CHANNEL_ID)?;
-        per::write_u16(&mut stream, self.channel_id, 0)?;
+        per::write_u16(&mut stream, self.initiator_id.0, BASE_CHANNEL_ID)?;
+        per::write_u16(&mut stream, self.channel_id.0, 0)?;
         stream.write_u8(SEND_DATA_PDU_DATA_PRIORITY_AND_SEGMENTATION)?;
         per::write_length(&mut stream, self.pdu_length as u16)?;
 
@@ -340,21 +368,21 @@ impl DisconnectUltimatumReason {
     }
 }
 
-#[derive(Debug, Fail)]
+#[derive(Debug, Error)]
 pub enum McsError {
-    #[fail(display = "IO error: {}", _0)]
-    IOError(#[fail(cause)] io::Error),
-    #[fail(display = "RDP error: {}", _0)]
-    RdpError(#[fail(cause)] crate::RdpError),
-    #[fail(display = "GCC block error: {}", _0)]
-    GccError(#[fail(cause)] GccError),
-    #[fail(display = "Invalid disconnect provider ultimatum")]
+    #[error("IO error: {}", .0)]
+    IOError(#[source] io::Error),
+    #[error("RDP error: {}", .0)]
+    RdpError(#[source] crate::RdpError),
+    #[error("GCC block error: {}", .0)]
+    GccError(#[source] GccError),
+    #[error("Invalid disconnect provider ultimatum")]
     InvalidDisconnectProviderUltimatum,
-    #[fail(display = "Invalid domain MCS PDU")]
+    #[error("Invalid domain MCS PDU")]
     InvalidDomainMcsPdu,
-    #[fail(display = "Invalid MCS Connection Sequence PDU: {}", _0)]
+    #[error("Invalid MCS Connection Sequence PDU: {}", .0)]
     InvalidPdu(String),
-    #[fail(display = "Invalid invalid MCS channel id: {}", _0)]
+    #[error("Invalid invalid MCS channel id: {}", .0)]
     UnexpectedChannelId(String),
 }
 
@@ -3,10 +3,10 @@ mod test;
 
 use std::io;
 
-use super::{McsError, RESULT_ENUM_LENGTH};
+use super::{ChannelId, McsError, RESULT_ENUM_LENGTH};
 use crate::gcc::conference_create::{ConferenceCreateRequest, ConferenceCreateResponse};
 use crate::gcc::{Channel, ClientGccBlocks};
-use crate::{ber, PduParsing};
+use crate::{ber, LimitsConfig, PduParsing};
 
 const MCS_TYPE_CONNECT_INITIAL: u8 = 0x65;
 const MCS_TYPE_CONNECT_RESPONSE: u8 = 0x66;
@@ -105,11 +105,33 @@ pub struct ConnectResponse {
 }
 
 impl ConnectResponse {
-    pub fn channel_ids(&self) -> Vec<u16> {
-        self.conference_create_response.gcc_blocks.channel_ids()
+    pub fn channel_ids(&self) -> Vec<ChannelId> {
+        self.conference_create_response
+            .gcc_blocks
+            .channel_ids()
+            .into_iter()
+            .map(ChannelId)
+            .collect()
     }
-    pub fn global_channel_id(&self) -> u16 {
-        self.conference_create_response.gcc_blocks.global_channel_id()
+    pub fn global_channel_id(&self) -> ChannelId {
+        ChannelId(self.conference_create_response.gcc_blocks.global_channel_id())
+    }
+
+    /// Decodes the PDU, failing early if the lengths and counts announced
+    /// in the user data exceed the budgets.
+    pub fn from_buffer_with_limits(mut stream: impl io::Read, limits: &LimitsConfig) -> Result<Self, McsError> {
+        ber::read_application_tag(&mut stream, MCS_TYPE_CONNECT_RESPONSE)?;
+        ber::read_enumerated(&mut stream, RESULT_ENUM_LENGTH)?;
+        let called_connect_id = ber::read_integer(&mut stream)? as u32;
+        let domain_parameters = DomainParameters::from_buffer(&mut stream)?;
+        let _user_data_buffer_length = ber::read_octet_string_tag_with_limit(&mut stream, limits.max_user_data_length)?;
+        let conference_create_response = ConferenceCreateResponse::from_buffer_with_limits(&mut stream, limits)?;
+
+        Ok(Self {
+            called_connect_id,
+            domain_parameters,
+            conference_create_response,
+        })
     }
 
     fn fields_buffer_ber_length(&self) -> u16 {
@@ -123,19 +145,8 @@ impl ConnectResponse {
 impl PduParsing for ConnectResponse {
     type Error = McsError;
 
-    fn from_buffer(mut stream: impl io::Read) -> Result<Self, McsError> {
-        ber::read_application_tag(&mut stream, MCS_TYPE_CONNECT_RESPONSE)?;
-        ber::read_enumerated(&mut stream, RESULT_ENUM_LENGTH)?;
-        let called_connect_id = ber::read_integer(&mut stream)? as u32;
-        let domain_parameters = DomainParameters::from_buffer(&mut stream)?;
-        let _user_data_buffer_length = ber::read_octet_string_tag(&mut stream)?;
-        let conference_create_response = ConferenceCreateResponse::from_buffer(&mut stream)?;
-
-        Ok(Self {
-            called_connect_id,
-            domain_parameters,
-            conference_create_response,
-        })
+    fn from_buffer(stream: impl io::Read) -> Result<Self, McsError> {
+        Self::from_buffer_with_limits(stream, &LimitsConfig::default())
     }
 
     fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), McsError> {
@@ -146,3 +146,24 @@ fn buffer_length_is_correct_for_connect_response() {
 
     assert_eq!(expected_buffer_len, len);
 }
+
+#[test]
+fn from_buffer_with_limits_fails_on_user_data_length_bomb() {
+    let limits = LimitsConfig {
+        max_user_data_length: conference_create::test::CONFERENCE_CREATE_RESPONSE_BUFFER.len() - 1,
+        ..LimitsConfig::default()
+    };
+
+    match ConnectResponse::from_buffer_with_limits(CONNECT_RESPONSE_BUFFER.as_slice(), &limits) {
+        Err(McsError::IOError(e)) => assert_eq!(io::ErrorKind::InvalidData, e.kind()),
+        result => panic!("Expected the length to exceed the limit, got: {:?}", result),
+    }
+}
+
+#[test]
+fn from_buffer_with_limits_accepts_connect_response_within_budget() {
+    assert_eq!(
+        *CONNECT_RESPONSE,
+        ConnectResponse::from_buffer_with_limits(CONNECT_RESPONSE_BUFFER.as_slice(), &LimitsConfig::default()).unwrap()
+    );
+}
@@ -37,17 +37,17 @@ const ERECT_DOMAIN_PDU: McsPdu = McsPdu::ErectDomainRequest(ErectDomainPdu {
 const ATTACH_USER_REQUEST_PDU: McsPdu = McsPdu::AttachUserRequest;
 const ATTACH_USER_CONFIRM_PDU: McsPdu = McsPdu::AttachUserConfirm(AttachUserConfirmPdu {
     result: 0,
-    initiator_id: 1007,
+    initiator_id: UserId(1007),
 });
 const CHANNEL_JOIN_REQUEST_PDU: McsPdu = McsPdu::ChannelJoinRequest(ChannelJoinRequestPdu {
-    initiator_id: 1007,
-    channel_id: 1007,
+    initiator_id: UserId(1007),
+    channel_id: ChannelId(1007),
 });
 const CHANNEL_JOIN_CONFIRM_PDU: McsPdu = McsPdu::ChannelJoinConfirm(ChannelJoinConfirmPdu {
     result: 0,
-    initiator_id: 1007,
-    requested_channel_id: 1007,
-    channel_id: 1007,
+    initiator_id: UserId(1007),
+    requested_channel_id: ChannelId(1007),
+    channel_id: ChannelId(1007),
 });
 const DISCONNECT_PROVIDER_ULTIMATUM_PDU: McsPdu =
     McsPdu::DisconnectProviderUltimatum(DisconnectUltimatumReason::UserRequested);
@@ -66,13 +66,13 @@ lazy_static! {
         result
     };
     static ref SEND_DATA_REQUEST_PDU: McsPdu = McsPdu::SendDataRequest(SendDataContext {
-        initiator_id: 1007,
-        channel_id: 1003,
+        initiator_id: UserId(1007),
+        channel_id: ChannelId(1003),
         pdu_length: rdp::test::CLIENT_INFO_PDU_BUFFER.len(),
     });
     static ref SEND_DATA_INDICATION_PDU: McsPdu = McsPdu::SendDataIndication(SendDataContext {
-        initiator_id: 1002,
-        channel_id: 1003,
+        initiator_id: UserId(1002),
+        channel_id: ChannelId(1003),
         pdu_length: rdp::test::SERVER_LICENSE_BUFFER.len(),
     });
 }
@@ -6,9 +6,9 @@ use std::io::prelude::*;
 
 use bitflags::bitflags;
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
-use failure::Fail;
 use num_derive::{FromPrimitive, ToPrimitive};
 use num_traits::{FromPrimitive, ToPrimitive};
+use thiserror::Error;
 
 use crate::x224::{TpktHeader, X224TPDUType, TPDU_REQUEST_LENGTH, TPKT_HEADER_LENGTH};
 use crate::{impl_from_error, PduParsing};
@@ -91,16 +91,16 @@ pub enum NegoData {
 }
 
 ///  The type of the error that may result from a negotiation process.
-#[derive(Debug, Fail)]
+#[derive(Debug, Error)]
 pub enum NegotiationError {
     /// Corresponds for an I/O error that may occur during a negotiation process
     /// (invalid response code, invalid security protocol code, etc.)
-    #[fail(display = "IO error: {}", _0)]
-    IOError(#[fail(cause)] io::Error),
+    #[error("IO error: {}", .0)]
+    IOError(#[source] io::Error),
     /// May indicate about a negotiation error recieved from a server.
-    #[fail(display = "Received negotiation error from server, code={:?}", 0)]
+    #[error("Received negotiation error from server, code={:?}", .0)]
     ResponseFailure(FailureCode),
-    #[fail(display = "Invalid tpkt header version")]
+    #[error("Invalid tpkt header version")]
     TpktVersionError,
 }
 
@@ -218,6 +218,25 @@ pub fn read_octet_string(mut stream: impl io::Read, min: usize) -> io::Result<Ve
     Ok(read_octet_string)
 }
 
+/// Reads the octet string, failing before allocating it if its length is greater than `max_length`.
+pub fn read_octet_string_with_limit(mut stream: impl io::Read, min: usize, max_length: usize) -> io::Result<Vec<u8>> {
+    let (read_length, _) = read_length(&mut stream)?;
+    check_length(min + usize::from(read_length), max_length)?;
+
+    let mut read_octet_string = vec![0; min + read_length as usize];
+    stream.read_exact(read_octet_string.as_mut())?;
+
+    Ok(read_octet_string)
+}
+
+/// Reads the length, failing if it is greater than `max_length`.
+pub fn read_length_with_limit(mut stream: impl io::Read, max_length: usize) -> io::Result<(u16, usize)> {
+    let (length, sizeof_length) = read_length(&mut stream)?;
+    check_length(usize::from(length), max_length)?;
+
+    Ok((length, sizeof_length))
+}
+
 pub fn write_octet_string(mut stream: impl io::Write, octet_string: &[u8], min: usize) -> io::Result<usize> {
     let length = if octet_string.len() >= min {
         octet_string.len() - min
@@ -261,3 +280,14 @@ pub fn write_numeric_string(mut stream: impl io::Write, num_str: &[u8], min: usi
 
     Ok(size)
 }
+
+fn check_length(length: usize, max_length: usize) -> io::Result<()> {
+    if length > max_length {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("PER length {} exceeds the limit of {}", length, max_length),
+        ))
+    } else {
+        Ok(())
+    }
+}
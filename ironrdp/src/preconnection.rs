@@ -1,9 +1,9 @@
 use std::io;
 
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
-use failure::Fail;
 use num_derive::{FromPrimitive, ToPrimitive};
 use num_traits::{FromPrimitive, ToPrimitive};
+use thiserror::Error;
 
 use crate::utils::{self, SplitTo};
 use crate::{impl_from_error, PduBufferParsing};
@@ -121,13 +121,13 @@ impl From<&PreconnectionPdu> for Version {
     }
 }
 
-#[derive(Debug, Fail)]
+#[derive(Debug, Error)]
 pub enum PreconnectionPduError {
-    #[fail(display = "IO error: {}", _0)]
-    IoError(#[fail(cause)] io::Error),
-    #[fail(display = "Provided data is not an valid preconnection Pdu")]
+    #[error("IO error: {}", .0)]
+    IoError(#[source] io::Error),
+    #[error("Provided data is not an valid preconnection Pdu")]
     InvalidHeader,
-    #[fail(display = "Unexpected version: {}", _0)]
+    #[error("Unexpected version: {}", .0)]
     UnexpectedVersion(u32),
 }
 
@@ -1,24 +1,32 @@
 use std::io;
 
-use failure::Fail;
+use thiserror::Error;
 
+use self::bulk::BulkError;
 use self::client_info::ClientInfoError;
 use self::finalization_messages::FinalizationMessagesError;
 use self::server_license::ServerLicenseError;
+use crate::pointer::PointerError;
 use crate::{impl_from_error, input::InputEventError, PduParsing};
 
 #[cfg(test)]
 pub mod test;
 
+pub mod bulk;
 pub mod capability_sets;
 pub mod server_license;
 pub mod session_info;
+pub mod standard_security;
 pub mod vc;
 
 mod client_info;
 mod finalization_messages;
 mod headers;
+mod keyboard_status;
+mod refresh_rectangle;
 mod server_error_info;
+mod server_redirection;
+mod server_status_info;
 
 pub use self::capability_sets::{
     CapabilitySet, CapabilitySetsError, ClientConfirmActive, DemandActive, ServerDemandActive, VirtualChannel,
@@ -35,10 +43,16 @@ pub use self::headers::{
     BasicSecurityHeader, BasicSecurityHeaderFlags, CompressionFlags, ShareControlHeader, ShareControlPdu,
     ShareControlPduType, ShareDataHeader, ShareDataPdu, ShareDataPduType, StreamPriority, BASIC_SECURITY_HEADER_SIZE,
 };
+pub use self::keyboard_status::{
+    ImeConversionMode, ImeState, KeyboardStatusError, LedFlags, SetKeyboardImeStatusPdu, SetKeyboardIndicatorsPdu,
+};
+pub use self::refresh_rectangle::RefreshRectanglePdu;
 pub use self::server_error_info::{
     ErrorInfo, ProtocolIndependentCode, ProtocolIndependentConnectionBrokerCode, ProtocolIndependentLicensingCode,
     RdpSpecificCode, ServerSetErrorInfoError, ServerSetErrorInfoPdu,
 };
+pub use self::server_redirection::{ServerRedirectionError, ServerRedirectionFlags, ServerRedirectionPdu};
+pub use self::server_status_info::{ServerStatusInfoError, ServerStatusInfoPdu, StatusCode};
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ClientInfoPdu {
     pub security_header: BasicSecurityHeader,
@@ -76,36 +90,46 @@ impl PduParsing for ClientInfoPdu {
     }
 }
 
-#[derive(Debug, Fail)]
+#[derive(Debug, Error)]
 pub enum RdpError {
-    #[fail(display = "IO error: {}", _0)]
-    IOError(#[fail(cause)] io::Error),
-    #[fail(display = "Client Info PDU error: {}", _0)]
+    #[error("IO error: {}", .0)]
+    IOError(#[source] io::Error),
+    #[error("Client Info PDU error: {}", .0)]
     ClientInfoError(ClientInfoError),
-    #[fail(display = "Server License PDU error: {}", _0)]
+    #[error("Server License PDU error: {}", .0)]
     ServerLicenseError(ServerLicenseError),
-    #[fail(display = "Capability sets error: {}", _0)]
+    #[error("Capability sets error: {}", .0)]
     CapabilitySetsError(CapabilitySetsError),
-    #[fail(display = "Finalization PDUs error: {}", _0)]
+    #[error("Finalization PDUs error: {}", .0)]
     FinalizationMessagesError(FinalizationMessagesError),
-    #[fail(display = "Invalid RDP security header")]
+    #[error("Invalid RDP security header")]
     InvalidSecurityHeader,
-    #[fail(display = "Invalid RDP Share Control Header: {}", _0)]
+    #[error("Invalid RDP Share Control Header: {}", .0)]
     InvalidShareControlHeader(String),
-    #[fail(display = "Invalid RDP Share Data Header: {}", _0)]
+    #[error("Invalid RDP Share Data Header: {}", .0)]
     InvalidShareDataHeader(String),
-    #[fail(display = "Invalid RDP Connection Sequence PDU")]
+    #[error("Invalid RDP Connection Sequence PDU")]
     InvalidPdu(String),
-    #[fail(display = "Unexpected RDP Share Control Header PDU type: {:?}", _0)]
+    #[error("Unexpected RDP Share Control Header PDU type: {:?}", .0)]
     UnexpectedShareControlPdu(ShareControlPduType),
-    #[fail(display = "Unexpected RDP Share Data Header PDU type: {:?}", _0)]
+    #[error("Unexpected RDP Share Data Header PDU type: {:?}", .0)]
     UnexpectedShareDataPdu(ShareDataPduType),
-    #[fail(display = "Save session info PDU error: {}", _0)]
+    #[error("Save session info PDU error: {}", .0)]
     SaveSessionInfoError(session_info::SessionError),
-    #[fail(display = "Server set error info PDU error: {}", _0)]
+    #[error("Server set error info PDU error: {}", .0)]
     ServerSetErrorInfoError(ServerSetErrorInfoError),
-    #[fail(display = "Input event PDU error: Err: {}", _0)]
+    #[error("Input event PDU error: Err: {}", .0)]
     InputEventError(InputEventError),
+    #[error("Keyboard status PDU error: {}", .0)]
+    KeyboardStatusError(KeyboardStatusError),
+    #[error("Server status info PDU error: {}", .0)]
+    ServerStatusInfoError(ServerStatusInfoError),
+    #[error("Server redirection PDU error: {}", .0)]
+    ServerRedirectionError(ServerRedirectionError),
+    #[error("Pointer update PDU error: {}", .0)]
+    PointerError(PointerError),
+    #[error("Bulk decompression error: {}", .0)]
+    BulkError(BulkError),
 }
 
 impl_from_error!(io::Error, RdpError, RdpError::IOError);
@@ -116,6 +140,11 @@ impl_from_error!(FinalizationMessagesError, RdpError, RdpError::FinalizationMess
 impl_from_error!(session_info::SessionError, RdpError, RdpError::SaveSessionInfoError);
 impl_from_error!(ServerSetErrorInfoError, RdpError, RdpError::ServerSetErrorInfoError);
 impl_from_error!(InputEventError, RdpError, RdpError::InputEventError);
+impl_from_error!(KeyboardStatusError, RdpError, RdpError::KeyboardStatusError);
+impl_from_error!(ServerStatusInfoError, RdpError, RdpError::ServerStatusInfoError);
+impl_from_error!(ServerRedirectionError, RdpError, RdpError::ServerRedirectionError);
+impl_from_error!(PointerError, RdpError, RdpError::PointerError);
+impl_from_error!(BulkError, RdpError, RdpError::BulkError);
 
 impl From<RdpError> for io::Error {
     fn from(e: RdpError) -> io::Error {
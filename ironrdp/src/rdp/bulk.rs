@@ -0,0 +1,51 @@
+//! Bulk decompression of the data the server compresses with the compression type advertised by the client
+//! in the Client Info PDU, as described in [MS-RDPBCGR] 3.1.8.
+
+pub mod mppc;
+
+use thiserror::Error;
+
+use self::mppc::{MppcDecompressor, MppcError};
+use crate::impl_from_error;
+use crate::rdp::{CompressionFlags, CompressionType};
+
+/// Decompresses the Share Data PDUs and the Fast-Path updates, which the server compresses with the same
+/// history, of the compression type it has selected up to the one advertised by the client.
+///
+/// The RDP 6.0 and RDP 6.1 compressions are not supported, so the clients advertise MPPC 64K at most.
+#[derive(Debug, Default)]
+pub struct BulkDecompressor {
+    mppc: MppcDecompressor,
+}
+
+impl BulkDecompressor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Decompresses the data according to the compression type and flags of its header,
+    /// the data not being compressed being returned as is.
+    pub fn decompress<'a>(
+        &'a mut self,
+        compression_type: CompressionType,
+        flags: CompressionFlags,
+        data: &'a [u8],
+    ) -> Result<&'a [u8], BulkError> {
+        match compression_type {
+            CompressionType::K8 | CompressionType::K64 => Ok(self.mppc.decompress(compression_type, flags, data)?),
+            CompressionType::Rdp6 | CompressionType::Rdp61 => {
+                Err(BulkError::UnsupportedCompressionType(compression_type))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum BulkError {
+    #[error("the {:?} compression type is not supported", .0)]
+    UnsupportedCompressionType(CompressionType),
+    #[error("MPPC decompression error: {}", .0)]
+    MppcError(#[source] MppcError),
+}
+
+impl_from_error!(MppcError, BulkError, BulkError::MppcError);
@@ -0,0 +1,215 @@
+//! Decompression of the MPPC bulk compression of RDP 4.0 and RDP 5.0, as described in [MS-RDPBCGR] 3.1.8.4,
+//! with which the servers compress the Share Data PDUs, the Fast-Path updates and the chunks of the static
+//! virtual channels.
+
+#[cfg(test)]
+mod tests;
+
+use std::fmt;
+
+use thiserror::Error;
+
+use crate::rdp::{CompressionFlags, CompressionType};
+
+const RDP4_HISTORY_SIZE: usize = 8 * 1024;
+const RDP5_HISTORY_SIZE: usize = 64 * 1024;
+/// The longest prefix of the length of a match, of the 4096 to 8191 bytes matches for RDP 4.0.
+const RDP4_MAX_LENGTH_PREFIX: usize = 11;
+/// The longest prefix of the length of a match, of the 32768 to 65535 bytes matches for RDP 5.0.
+const RDP5_MAX_LENGTH_PREFIX: usize = 14;
+
+/// Decompresses the data with the history of the data previously decompressed, to which the compressor
+/// refers with the copy tuples.
+///
+/// The history is kept from one call to the next until the compressor flushes it, so the same decompressor
+/// is to be used for all the data compressed with the same context. The history is allocated on the first call.
+#[derive(Clone)]
+pub struct MppcDecompressor {
+    history: Vec<u8>,
+    history_offset: usize,
+}
+
+impl MppcDecompressor {
+    pub fn new() -> Self {
+        Self {
+            history: Vec::new(),
+            history_offset: 0,
+        }
+    }
+
+    /// Decompresses the data according to the compression flags, the data not being compressed being returned
+    /// as is once the history has been reset as requested by the flags.
+    pub fn decompress<'a>(
+        &'a mut self,
+        compression_type: CompressionType,
+        flags: CompressionFlags,
+        data: &'a [u8],
+    ) -> Result<&'a [u8], MppcError> {
+        let (history_size, max_length_prefix) = match compression_type {
+            CompressionType::K8 => (RDP4_HISTORY_SIZE, RDP4_MAX_LENGTH_PREFIX),
+            CompressionType::K64 => (RDP5_HISTORY_SIZE, RDP5_MAX_LENGTH_PREFIX),
+            compression_type => return Err(MppcError::UnsupportedCompressionType(compression_type)),
+        };
+
+        if self.history.is_empty() {
+            self.history = vec![0; RDP5_HISTORY_SIZE];
+        }
+        if flags.contains(CompressionFlags::FLUSHED) {
+            self.history.fill(0);
+            self.history_offset = 0;
+        }
+        if flags.contains(CompressionFlags::AT_FRONT) {
+            self.history_offset = 0;
+        }
+        if !flags.contains(CompressionFlags::COMPRESSED) {
+            return Ok(data);
+        }
+
+        let start = self.history_offset;
+        let mut bits = BitReader::new(data);
+        // the shortest token is an 8 bits literal, the remaining bits being the padding of the last byte
+        while bits.remaining() >= 8 {
+            match bits.read_prefix(2)? {
+                0 => self.push_literal(bits.read(7)? as u8, history_size)?,
+                1 => self.push_literal(0x80 | bits.read(7)? as u8, history_size)?,
+                _ => {
+                    let copy_offset = match compression_type {
+                        CompressionType::K8 => read_rdp4_copy_offset(&mut bits)?,
+                        _ => read_rdp5_copy_offset(&mut bits)?,
+                    };
+                    let length = match bits.read_prefix(max_length_prefix + 1)? {
+                        0 => 3,
+                        prefix if prefix <= max_length_prefix => (1 << (prefix + 1)) + bits.read(prefix + 1)?,
+                        _ => return Err(MppcError::InvalidLengthOfMatch),
+                    };
+
+                    self.copy_match(copy_offset, length, history_size)?;
+                }
+            }
+        }
+
+        Ok(&self.history[start..self.history_offset])
+    }
+
+    fn push_literal(&mut self, literal: u8, history_size: usize) -> Result<(), MppcError> {
+        // the offset can be past the end of a smaller history when the compression type has changed
+        if self.history_offset >= history_size {
+            return Err(MppcError::HistoryOverflow);
+        }
+
+        self.history[self.history_offset] = literal;
+        self.history_offset += 1;
+
+        Ok(())
+    }
+
+    /// Appends the bytes of the history starting `copy_offset` bytes before the end of the history,
+    /// the match overlapping the appended bytes when it is longer than the offset.
+    fn copy_match(&mut self, copy_offset: usize, length: usize, history_size: usize) -> Result<(), MppcError> {
+        if copy_offset == 0 || copy_offset >= history_size {
+            return Err(MppcError::InvalidCopyOffset(copy_offset));
+        }
+        if self.history_offset + length > history_size {
+            return Err(MppcError::HistoryOverflow);
+        }
+
+        // the history wraps around when the compressor has moved back to its front
+        let source = (self.history_offset + history_size - copy_offset) % history_size;
+        for index in 0..length {
+            self.history[self.history_offset] = self.history[(source + index) % history_size];
+            self.history_offset += 1;
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for MppcDecompressor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for MppcDecompressor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MppcDecompressor")
+            .field("history_offset", &self.history_offset)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Reads the copy offset following the `11` of a copy tuple: `11` + 6 bits, `10` + 8 bits or `0` + 13 bits.
+fn read_rdp4_copy_offset(bits: &mut BitReader<'_>) -> Result<usize, MppcError> {
+    match bits.read_prefix(2)? {
+        2 => bits.read(6),
+        1 => Ok(bits.read(8)? + 64),
+        _ => Ok(bits.read(13)? + 320),
+    }
+}
+
+/// Reads the copy offset following the `11` of a copy tuple: `111` + 6 bits, `110` + 8 bits, `10` + 11 bits
+/// or `0` + 16 bits.
+fn read_rdp5_copy_offset(bits: &mut BitReader<'_>) -> Result<usize, MppcError> {
+    match bits.read_prefix(3)? {
+        3 => bits.read(6),
+        2 => Ok(bits.read(8)? + 64),
+        1 => Ok(bits.read(11)? + 320),
+        _ => Ok(bits.read(16)? + 2368),
+    }
+}
+
+/// Reads the bits from the most significant bit of the first byte.
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+
+    fn remaining(&self) -> usize {
+        self.data.len() * 8 - self.position
+    }
+
+    fn read(&mut self, count: usize) -> Result<usize, MppcError> {
+        if count > self.remaining() {
+            return Err(MppcError::TruncatedData);
+        }
+
+        let mut value = 0;
+        for _ in 0..count {
+            let bit = (self.data[self.position / 8] >> (7 - self.position % 8)) & 1;
+            value = (value << 1) | usize::from(bit);
+            self.position += 1;
+        }
+
+        Ok(value)
+    }
+
+    /// Reads the `1` bits up to the `0` ending the prefix, returning their number,
+    /// the prefix of `max` bits not being followed by a `0`.
+    fn read_prefix(&mut self, max: usize) -> Result<usize, MppcError> {
+        let mut count = 0;
+        while count < max && self.read(1)? == 1 {
+            count += 1;
+        }
+
+        Ok(count)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum MppcError {
+    #[error("the {:?} compression type is not supported", .0)]
+    UnsupportedCompressionType(CompressionType),
+    #[error("the compressed data ends in the middle of a token")]
+    TruncatedData,
+    #[error("invalid copy offset: {}", .0)]
+    InvalidCopyOffset(usize),
+    #[error("invalid length of match")]
+    InvalidLengthOfMatch,
+    #[error("the decompressed data exceeds the history buffer")]
+    HistoryOverflow,
+}
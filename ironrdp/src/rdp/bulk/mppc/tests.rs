@@ -0,0 +1,136 @@
+use super::*;
+
+const FLUSHED_COMPRESSED: CompressionFlags = CompressionFlags::FLUSHED.union(CompressionFlags::COMPRESSED);
+
+// literals `a`, `b` and `c`
+const ABC: [u8; 3] = [0x61, 0x62, 0x63];
+
+fn decompress(compression_type: CompressionType, flags: CompressionFlags, data: &[u8]) -> Result<Vec<u8>, MppcError> {
+    MppcDecompressor::new()
+        .decompress(compression_type, flags, data)
+        .map(<[u8]>::to_vec)
+}
+
+#[test]
+fn rdp5_literals_and_copy_tuple_are_decompressed() {
+    let data = [
+        0x61, 0x62, 0x63, // literals `a`, `b` and `c`
+        0xf8, 0x74, // `11111` + 6 bits copy offset 3, `10` + 2 bits length 6
+    ];
+
+    assert_eq!(
+        b"abcabcabc".as_ref(),
+        decompress(CompressionType::K64, FLUSHED_COMPRESSED, &data).unwrap()
+    );
+}
+
+#[test]
+fn rdp4_literals_and_copy_tuple_are_decompressed() {
+    let data = [
+        0x61, 0x62, 0x63, // literals `a`, `b` and `c`
+        0xf0, 0xe8, // `1111` + 6 bits copy offset 3, `10` + 2 bits length 6
+    ];
+
+    assert_eq!(
+        b"abcabcabc".as_ref(),
+        decompress(CompressionType::K8, FLUSHED_COMPRESSED, &data).unwrap()
+    );
+}
+
+#[test]
+fn literals_above_0x7f_are_decompressed() {
+    // `10` + 7 bits for 0xff and 0x80
+    let data = [0xbf, 0xc0, 0x00];
+
+    assert_eq!(
+        [0xff, 0x80].as_ref(),
+        decompress(CompressionType::K64, FLUSHED_COMPRESSED, &data).unwrap()
+    );
+}
+
+#[test]
+fn overlapping_long_match_repeats_history() {
+    let data = [
+        0x61, // literal `a`
+        0xf8, 0x3f, 0xc5, 0x80, // copy offset 1, `11111110` + 8 bits length 300
+    ];
+
+    assert_eq!(
+        vec![b'a'; 301],
+        decompress(CompressionType::K64, FLUSHED_COMPRESSED, &data).unwrap()
+    );
+}
+
+#[test]
+fn history_is_kept_until_flushed() {
+    let mut decompressor = MppcDecompressor::new();
+    decompressor
+        .decompress(CompressionType::K64, FLUSHED_COMPRESSED, &ABC)
+        .unwrap();
+
+    // copy offset 3, length 3
+    let copy = [0xf8, 0x60];
+    assert_eq!(
+        b"abc".as_ref(),
+        decompressor
+            .decompress(CompressionType::K64, CompressionFlags::COMPRESSED, &copy)
+            .unwrap()
+    );
+    assert_eq!(
+        [0, 0, 0].as_ref(),
+        decompressor
+            .decompress(CompressionType::K64, FLUSHED_COMPRESSED, &copy)
+            .unwrap()
+    );
+}
+
+#[test]
+fn uncompressed_data_is_returned_as_is() {
+    let mut decompressor = MppcDecompressor::new();
+
+    assert_eq!(
+        ABC.as_ref(),
+        decompressor
+            .decompress(CompressionType::K64, CompressionFlags::FLUSHED, &ABC)
+            .unwrap()
+    );
+}
+
+#[test]
+fn truncated_copy_tuple_fails() {
+    // `110` + 16 bits copy offset, of which only 5 bits are present
+    assert!(matches!(
+        decompress(CompressionType::K64, FLUSHED_COMPRESSED, &[0xc0]),
+        Err(MppcError::TruncatedData)
+    ));
+}
+
+#[test]
+fn copy_offset_before_start_of_history_fails() {
+    // copy offset 0, length 3
+    assert!(matches!(
+        decompress(CompressionType::K64, FLUSHED_COMPRESSED, &[0xf8, 0x00]),
+        Err(MppcError::InvalidCopyOffset(0))
+    ));
+}
+
+#[test]
+fn literal_past_end_of_smaller_history_fails() {
+    let mut decompressor = MppcDecompressor::new();
+    decompressor
+        .decompress(CompressionType::K64, FLUSHED_COMPRESSED, &[0x61; 9000])
+        .unwrap();
+
+    assert!(matches!(
+        decompressor.decompress(CompressionType::K8, CompressionFlags::COMPRESSED, &ABC),
+        Err(MppcError::HistoryOverflow)
+    ));
+}
+
+#[test]
+fn rdp6_compression_is_not_supported() {
+    assert!(matches!(
+        decompress(CompressionType::Rdp6, FLUSHED_COMPRESSED, &ABC),
+        Err(MppcError::UnsupportedCompressionType(CompressionType::Rdp6))
+    ));
+}
@@ -1,9 +1,9 @@
 use std::io;
 
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
-use failure::Fail;
 use num_derive::{FromPrimitive, ToPrimitive};
 use num_traits::{FromPrimitive, ToPrimitive};
+use thiserror::Error;
 
 use crate::{impl_from_error, PduParsing};
 
@@ -30,10 +30,11 @@ mod virtual_channel;
 pub use self::bitmap::{Bitmap, BitmapDrawingFlags};
 pub use self::bitmap_cache::{
     BitmapCache, BitmapCacheRev2, CacheEntry, CacheFlags, CellInfo, BITMAP_CACHE_ENTRIES_NUM,
+    BITMAP_CACHE_REV2_CELL_INFO_NUM,
 };
 pub use self::bitmap_codecs::{
-    BitmapCodecs, CaptureFlags, Codec, CodecProperty, EntropyBits, Guid, NsCodec, RemoteFxContainer, RfxCaps,
-    RfxCapset, RfxClientCapsContainer, RfxICap, RfxICapFlags,
+    BitmapCodecs, BitmapCodecsBuilder, CaptureFlags, Codec, CodecGuid, CodecProperty, EntropyBits, Guid, NsCodec,
+    RemoteFxContainer, RfxCaps, RfxCapset, RfxClientCapsContainer, RfxICap, RfxICapFlags,
 };
 pub use self::brush::{Brush, SupportLevel};
 pub use self::frame_acknowledge::FrameAcknowledge;
@@ -49,7 +50,7 @@ pub use self::sound::{Sound, SoundFlags};
 pub use self::surface_commands::{CmdFlags, SurfaceCommands};
 pub use self::virtual_channel::{VirtualChannel, VirtualChannelFlags};
 
-pub const SERVER_CHANNEL_ID: u16 = 0x03ea;
+pub use crate::consts::SERVER_CHANNEL_ID;
 
 const SOURCE_DESCRIPTOR_LENGTH_FIELD_SIZE: usize = 2;
 const COMBINED_CAPABILITIES_LENGTH_FIELD_SIZE: usize = 2;
@@ -101,6 +102,48 @@ pub struct ClientConfirmActive {
     pub pdu: DemandActive,
 }
 
+impl ClientConfirmActive {
+    /// Answers the Server Demand Active PDU with the capability sets of the client, mirrored from the ones
+    /// advertised by the server:
+    ///
+    /// * the Multifragment Update capability set takes the maximal request size of the server,
+    ///   the client adding its own only if the server has not advertised one;
+    /// * the orders, the surface commands and the large pointers not supported by the server are dropped.
+    ///
+    /// The share ID is not part of the PDU: it is mirrored in the Share Control Header by the transport.
+    pub fn mirrored(
+        server_demand_active: &ServerDemandActive,
+        source_descriptor: String,
+        mut capability_sets: Vec<CapabilitySet>,
+    ) -> Self {
+        for capability_set in capability_sets.iter_mut() {
+            for server_capability_set in server_demand_active.pdu.capability_sets.iter() {
+                match (&mut *capability_set, server_capability_set) {
+                    (CapabilitySet::MultiFragmentUpdate(client), CapabilitySet::MultiFragmentUpdate(server)) => {
+                        client.max_request_size = server.max_request_size;
+                    }
+                    (CapabilitySet::Order(client), CapabilitySet::Order(server)) => client.retain_supported_by(server),
+                    (CapabilitySet::SurfaceCommands(client), CapabilitySet::SurfaceCommands(server)) => {
+                        client.flags &= server.flags;
+                    }
+                    (CapabilitySet::LargePointer(client), CapabilitySet::LargePointer(server)) => {
+                        client.flags &= server.flags;
+                    }
+                    _ => (),
+                }
+            }
+        }
+
+        Self {
+            originator_id: SERVER_CHANNEL_ID,
+            pdu: DemandActive {
+                source_descriptor,
+                capability_sets,
+            },
+        }
+    }
+}
+
 impl PduParsing for ClientConfirmActive {
     type Error = CapabilitySetsError;
 
@@ -535,65 +578,65 @@ enum CapabilitySetType {
     FrameAcknowledge = 0x1e,
 }
 
-#[derive(Debug, Fail)]
+#[derive(Debug, Error)]
 pub enum CapabilitySetsError {
-    #[fail(display = "IO error: {}", _0)]
-    IOError(#[fail(cause)] io::Error),
-    #[fail(display = "Utf8 error: {}", _0)]
-    Utf8Error(#[fail(cause)] std::string::FromUtf8Error),
-    #[fail(display = "Invalid type field")]
+    #[error("IO error: {}", .0)]
+    IOError(#[source] io::Error),
+    #[error("Utf8 error: {}", .0)]
+    Utf8Error(#[source] std::string::FromUtf8Error),
+    #[error("Invalid type field")]
     InvalidType,
-    #[fail(display = "Invalid bitmap compression field")]
+    #[error("Invalid bitmap compression field")]
     InvalidCompressionFlag,
-    #[fail(display = "Invalid multiple rectangle support field")]
+    #[error("Invalid multiple rectangle support field")]
     InvalidMultipleRectSupport,
-    #[fail(display = "Invalid major platform type field")]
+    #[error("Invalid major platform type field")]
     InvalidMajorPlatformType,
-    #[fail(display = "Invalid minor platform type field")]
+    #[error("Invalid minor platform type field")]
     InvalidMinorPlatformType,
-    #[fail(display = "Invalid protocol version field")]
+    #[error("Invalid protocol version field")]
     InvalidProtocolVersion,
-    #[fail(display = "Invalid compression types field")]
+    #[error("Invalid compression types field")]
     InvalidCompressionTypes,
-    #[fail(display = "Invalid update capability flags field")]
+    #[error("Invalid update capability flags field")]
     InvalidUpdateCapFlag,
-    #[fail(display = "Invalid remote unshare flag field")]
+    #[error("Invalid remote unshare flag field")]
     InvalidRemoteUnshareFlag,
-    #[fail(display = "Invalid compression level field")]
+    #[error("Invalid compression level field")]
     InvalidCompressionLevel,
-    #[fail(display = "Invalid brush support level field")]
+    #[error("Invalid brush support level field")]
     InvalidBrushSupportLevel,
-    #[fail(display = "Invalid glyph support level field")]
+    #[error("Invalid glyph support level field")]
     InvalidGlyphSupportLevel,
-    #[fail(display = "Invalid RemoteFX capability version")]
+    #[error("Invalid RemoteFX capability version")]
     InvalidRfxICapVersion,
-    #[fail(display = "Invalid RemoteFX capability tile size")]
+    #[error("Invalid RemoteFX capability tile size")]
     InvalidRfxICapTileSize,
-    #[fail(display = "Invalid RemoteFXICap color conversion bits")]
+    #[error("Invalid RemoteFXICap color conversion bits")]
     InvalidRfxICapColorConvBits,
-    #[fail(display = "Invalid RemoteFXICap transform bits")]
+    #[error("Invalid RemoteFXICap transform bits")]
     InvalidRfxICapTransformBits,
-    #[fail(display = "Invalid RemoteFXICap entropy bits field")]
+    #[error("Invalid RemoteFXICap entropy bits field")]
     InvalidRfxICapEntropyBits,
-    #[fail(display = "Invalid RemoteFX capability set block type")]
+    #[error("Invalid RemoteFX capability set block type")]
     InvalidRfxCapsetBlockType,
-    #[fail(display = "Invalid RemoteFX capability set type")]
+    #[error("Invalid RemoteFX capability set type")]
     InvalidRfxCapsetType,
-    #[fail(display = "Invalid RemoteFX capabilities block type")]
+    #[error("Invalid RemoteFX capabilities block type")]
     InvalidRfxCapsBlockType,
-    #[fail(display = "Invalid RemoteFX capabilities block length")]
+    #[error("Invalid RemoteFX capabilities block length")]
     InvalidRfxCapsBockLength,
-    #[fail(display = "Invalid number of capability sets in RemoteFX capabilities")]
+    #[error("Invalid number of capability sets in RemoteFX capabilities")]
     InvalidRfxCapsNumCapsets,
-    #[fail(display = "Invalid codec property field")]
+    #[error("Invalid codec property field")]
     InvalidCodecProperty,
-    #[fail(display = "Invalid codec ID")]
+    #[error("Invalid codec ID")]
     InvalidCodecID,
-    #[fail(display = "Invalid channel chunk size field")]
+    #[error("Invalid channel chunk size field")]
     InvalidChunkSize,
-    #[fail(display = "Invalid codec property length for the current property ID")]
+    #[error("Invalid codec property length for the current property ID")]
     InvalidPropertyLength,
-    #[fail(display = "Invalid data length")]
+    #[error("Invalid data length")]
     InvalidLength,
 }
 
@@ -8,10 +8,12 @@ use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use num_derive::{FromPrimitive, ToPrimitive};
 use num_traits::{FromPrimitive, ToPrimitive};
 
+use crate::consts::{
+    CODEC_GUID_IGNORE, CODEC_GUID_IMAGE_REMOTEFX, CODEC_GUID_NSCODEC, CODEC_GUID_REMOTEFX, RFX_VERSION,
+};
 use crate::rdp::CapabilitySetsError;
 use crate::PduParsing;
 
-const RFX_ICAP_VERSION: u16 = 0x0100;
 const RFX_ICAP_TILE_SIZE: u16 = 0x40;
 const RFX_ICAP_COLOR_CONVERSION: u8 = 1;
 const RFX_ICAP_TRANSFORM_BITS: u8 = 1;
@@ -32,18 +34,20 @@ const NSCODEC_LENGTH: usize = 3;
 const CODEC_STATIC_DATA_LENGTH: usize = 19;
 const BITMAP_CODECS_STATIC_DATA: usize = 1;
 
-// TO DO: Make it enum when #[repr(u128)] becomes stable
-#[rustfmt::skip]
-const GUID_NSCODEC: Guid = Guid(0xca8d_1bb9, 0x000f, 0x154f, 0x58, 0x9f, 0xae, 0x2d, 0x1a, 0x87, 0xe2, 0xd6);
-#[rustfmt::skip]
-const GUID_REMOTEFX: Guid = Guid(0x7677_2f12, 0xbd72, 0x4463, 0xaf, 0xb3, 0xb7, 0x3c, 0x9c, 0x6f, 0x78, 0x86);
-#[rustfmt::skip]
-const GUID_IMAGE_REMOTEFX: Guid = Guid(0x2744_ccd4, 0x9d8a, 0x4e74, 0x80, 0x3c, 0x0e, 0xcb, 0xee, 0xa1, 0x9c, 0x54);
-#[rustfmt::skip]
-const GUID_IGNORE: Guid = Guid(0x9c43_51a6, 0x3535, 0x42ae, 0x91, 0x0c, 0xcd, 0xfc, 0xe5, 0x76, 0x0b, 0x58);
-
-#[derive(Debug, PartialEq, Eq)]
-pub struct Guid(u32, u16, u16, u8, u8, u8, u8, u8, u8, u8, u8);
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Guid(
+    pub(crate) u32,
+    pub(crate) u16,
+    pub(crate) u16,
+    pub(crate) u8,
+    pub(crate) u8,
+    pub(crate) u8,
+    pub(crate) u8,
+    pub(crate) u8,
+    pub(crate) u8,
+    pub(crate) u8,
+    pub(crate) u8,
+);
 
 impl PduParsing for Guid {
     type Error = CapabilitySetsError;
@@ -87,9 +91,117 @@ impl PduParsing for Guid {
     }
 }
 
+/// The codecs of the Bitmap Codecs Capability Set known by their GUID.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum CodecGuid {
+    NsCodec,
+    RemoteFx,
+    ImageRemoteFx,
+    /// Advertised by the clients for the server to ignore the entry.
+    Ignore,
+}
+
+impl CodecGuid {
+    pub const ALL: [CodecGuid; 4] = [
+        CodecGuid::NsCodec,
+        CodecGuid::RemoteFx,
+        CodecGuid::ImageRemoteFx,
+        CodecGuid::Ignore,
+    ];
+
+    pub fn guid(self) -> Guid {
+        match self {
+            CodecGuid::NsCodec => CODEC_GUID_NSCODEC,
+            CodecGuid::RemoteFx => CODEC_GUID_REMOTEFX,
+            CodecGuid::ImageRemoteFx => CODEC_GUID_IMAGE_REMOTEFX,
+            CodecGuid::Ignore => CODEC_GUID_IGNORE,
+        }
+    }
+
+    pub fn from_guid(guid: &Guid) -> Option<Self> {
+        Self::ALL.iter().copied().find(|codec| codec.guid() == *guid)
+    }
+
+    /// The ID assigned to the codec by the [`BitmapCodecsBuilder`], by which the surface commands
+    /// of the server refer to it.
+    pub fn id(self) -> u8 {
+        match self {
+            CodecGuid::Ignore => 0x0,
+            CodecGuid::NsCodec => 0x1,
+            CodecGuid::RemoteFx => 0x3,
+            CodecGuid::ImageRemoteFx => 0x4,
+        }
+    }
+
+    /// The codec the surface commands refer to by the ID, `None` being returned for the unknown IDs.
+    pub fn from_id(id: u8) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|codec| *codec != CodecGuid::Ignore && codec.id() == id)
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct BitmapCodecs(pub Vec<Codec>);
 
+impl BitmapCodecs {
+    pub fn builder() -> BitmapCodecsBuilder {
+        BitmapCodecsBuilder::default()
+    }
+
+    /// Returns the entry of the codec, e.g. for the properties of the codecs supported by the server.
+    pub fn find(&self, codec: CodecGuid) -> Option<&Codec> {
+        self.0.iter().find(|entry| entry.guid() == Some(codec))
+    }
+}
+
+/// Builds the Bitmap Codecs Capability Set of the client, assigning the [`CodecGuid::id`] to each codec.
+///
+/// Each codec is advertised once, the entry of a codec added again replacing the previous one.
+#[derive(Debug, Clone, Default)]
+pub struct BitmapCodecsBuilder {
+    codecs: Vec<Codec>,
+}
+
+impl BitmapCodecsBuilder {
+    pub fn with_remote_fx(self, container: RfxClientCapsContainer) -> Self {
+        self.with_codec(
+            CodecGuid::RemoteFx,
+            CodecProperty::RemoteFx(RemoteFxContainer::ClientContainer(container)),
+        )
+    }
+
+    pub fn with_image_remote_fx(self, container: RfxClientCapsContainer) -> Self {
+        self.with_codec(
+            CodecGuid::ImageRemoteFx,
+            CodecProperty::ImageRemoteFx(RemoteFxContainer::ClientContainer(container)),
+        )
+    }
+
+    pub fn with_ns_codec(self, ns_codec: NsCodec) -> Self {
+        self.with_codec(CodecGuid::NsCodec, CodecProperty::NsCodec(ns_codec))
+    }
+
+    pub fn with_ignore(self) -> Self {
+        self.with_codec(CodecGuid::Ignore, CodecProperty::Ignore)
+    }
+
+    pub fn build(self) -> BitmapCodecs {
+        BitmapCodecs(self.codecs)
+    }
+
+    fn with_codec(mut self, guid: CodecGuid, property: CodecProperty) -> Self {
+        self.codecs.retain(|entry| entry.guid() != Some(guid));
+        self.codecs.push(Codec {
+            id: guid.id(),
+            property,
+        });
+
+        self
+    }
+}
+
 impl PduParsing for BitmapCodecs {
     type Error = CapabilitySetsError;
 
@@ -125,6 +237,19 @@ pub struct Codec {
     pub property: CodecProperty,
 }
 
+impl Codec {
+    /// The codec of the entry, `None` being returned for the codecs of unknown GUIDs.
+    pub fn guid(&self) -> Option<CodecGuid> {
+        match self.property {
+            CodecProperty::NsCodec(_) => Some(CodecGuid::NsCodec),
+            CodecProperty::RemoteFx(_) => Some(CodecGuid::RemoteFx),
+            CodecProperty::ImageRemoteFx(_) => Some(CodecGuid::ImageRemoteFx),
+            CodecProperty::Ignore => Some(CodecGuid::Ignore),
+            CodecProperty::None => None,
+        }
+    }
+}
+
 impl PduParsing for Codec {
     type Error = CapabilitySetsError;
 
@@ -139,8 +264,8 @@ impl PduParsing for Codec {
             buffer.read_exact(&mut property_buffer)?;
 
             match guid {
-                GUID_NSCODEC => CodecProperty::NsCodec(NsCodec::from_buffer(&mut property_buffer.as_slice())?),
-                GUID_REMOTEFX | GUID_IMAGE_REMOTEFX => {
+                CODEC_GUID_NSCODEC => CodecProperty::NsCodec(NsCodec::from_buffer(&mut property_buffer.as_slice())?),
+                CODEC_GUID_REMOTEFX | CODEC_GUID_IMAGE_REMOTEFX => {
                     let property = if property_buffer[0] == 0 {
                         RemoteFxContainer::ServerContainer(codec_properties_len)
                     } else {
@@ -150,20 +275,20 @@ impl PduParsing for Codec {
                     };
 
                     match guid {
-                        GUID_REMOTEFX => CodecProperty::RemoteFx(property),
-                        GUID_IMAGE_REMOTEFX => CodecProperty::ImageRemoteFx(property),
+                        CODEC_GUID_REMOTEFX => CodecProperty::RemoteFx(property),
+                        CODEC_GUID_IMAGE_REMOTEFX => CodecProperty::ImageRemoteFx(property),
                         _ => unreachable!(),
                     }
                 }
-                GUID_IGNORE => CodecProperty::Ignore,
+                CODEC_GUID_IGNORE => CodecProperty::Ignore,
                 _ => CodecProperty::None,
             }
         } else {
             match guid {
-                GUID_NSCODEC | GUID_REMOTEFX | GUID_IMAGE_REMOTEFX => {
+                CODEC_GUID_NSCODEC | CODEC_GUID_REMOTEFX | CODEC_GUID_IMAGE_REMOTEFX => {
                     return Err(CapabilitySetsError::InvalidPropertyLength)
                 }
-                GUID_IGNORE => CodecProperty::Ignore,
+                CODEC_GUID_IGNORE => CodecProperty::Ignore,
                 _ => CodecProperty::None,
             }
         };
@@ -172,14 +297,8 @@ impl PduParsing for Codec {
     }
 
     fn to_buffer(&self, mut buffer: impl io::Write) -> Result<(), Self::Error> {
-        let guid = match &self.property {
-            CodecProperty::NsCodec(_) => GUID_NSCODEC,
-            CodecProperty::RemoteFx(_) => GUID_REMOTEFX,
-            CodecProperty::ImageRemoteFx(_) => GUID_IMAGE_REMOTEFX,
-            CodecProperty::Ignore => GUID_IGNORE,
-            _ => return Err(CapabilitySetsError::InvalidCodecID),
-        };
-        guid.to_buffer(&mut buffer)?;
+        let guid = self.guid().ok_or(CapabilitySetsError::InvalidCodecID)?;
+        guid.guid().to_buffer(&mut buffer)?;
 
         buffer.write_u8(self.id)?;
 
@@ -445,7 +564,7 @@ impl PduParsing for RfxICap {
 
     fn from_buffer(mut buffer: impl io::Read) -> Result<Self, Self::Error> {
         let version = buffer.read_u16::<LittleEndian>()?;
-        if version != RFX_ICAP_VERSION {
+        if version != RFX_VERSION {
             return Err(CapabilitySetsError::InvalidRfxICapVersion);
         }
 
@@ -473,7 +592,7 @@ impl PduParsing for RfxICap {
     }
 
     fn to_buffer(&self, mut buffer: impl io::Write) -> Result<(), Self::Error> {
-        buffer.write_u16::<LittleEndian>(RFX_ICAP_VERSION)?;
+        buffer.write_u16::<LittleEndian>(RFX_VERSION)?;
         buffer.write_u16::<LittleEndian>(RFX_ICAP_TILE_SIZE)?;
         buffer.write_u8(self.flags.bits())?;
         buffer.write_u8(RFX_ICAP_COLOR_CONVERSION)?;
@@ -565,3 +565,47 @@ fn ns_codec_with_too_low_color_loss_level_handled_correctly() {
 
     assert_eq!(codec, Codec::from_buffer(&mut codec_buffer.as_slice()).unwrap());
 }
+
+#[test]
+fn codec_guids_are_found_from_their_guid_and_id() {
+    for codec in CodecGuid::ALL {
+        assert_eq!(Some(codec), CodecGuid::from_guid(&codec.guid()));
+    }
+
+    assert_eq!(Some(CodecGuid::NsCodec), CodecGuid::from_guid(&GUID));
+    assert_eq!(Some(CodecGuid::RemoteFx), CodecGuid::from_id(0x3));
+    assert_eq!(None, CodecGuid::from_id(CodecGuid::Ignore.id()));
+}
+
+#[test]
+fn builder_assigns_ids_and_replaces_codec_added_again() {
+    let ns_codec = NsCodec {
+        is_dynamic_fidelity_allowed: false,
+        is_subsampling_allowed: false,
+        color_loss_level: 7,
+    };
+
+    let codecs = BitmapCodecs::builder()
+        .with_ns_codec(NsCodec {
+            color_loss_level: 1,
+            ..ns_codec.clone()
+        })
+        .with_ignore()
+        .with_ns_codec(ns_codec.clone())
+        .build();
+
+    assert_eq!(
+        BitmapCodecs(vec![
+            Codec {
+                id: 0x0,
+                property: CodecProperty::Ignore,
+            },
+            Codec {
+                id: 0x1,
+                property: CodecProperty::NsCodec(ns_codec),
+            },
+        ]),
+        codecs
+    );
+    assert_eq!(Some(&codecs.0[1]), codecs.find(CodecGuid::NsCodec));
+}
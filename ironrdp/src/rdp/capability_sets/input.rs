@@ -80,7 +80,8 @@ impl PduParsing for Input {
         buffer.write_u32::<LittleEndian>(self.keyboard_subtype)?;
         buffer.write_u32::<LittleEndian>(self.keyboard_function_key)?;
 
-        let mut keyboard_ime_file_name_buffer = utils::string_to_utf16(self.keyboard_ime_filename.as_ref());
+        let mut keyboard_ime_file_name_buffer =
+            utils::string_to_truncated_utf16(self.keyboard_ime_filename.as_ref(), IME_FILE_NAME_SIZE / 2 - 1);
         keyboard_ime_file_name_buffer.resize(IME_FILE_NAME_SIZE - 2, 0);
         buffer.write_all(keyboard_ime_file_name_buffer.as_ref())?;
         buffer.write_u16::<LittleEndian>(0)?; // ime file name null terminator
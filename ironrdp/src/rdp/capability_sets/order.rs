@@ -88,6 +88,14 @@ impl Order {
     pub fn get_support_flag(&mut self, flag: OrderSupportIndex) -> bool {
         self.order_support[flag as usize] == 1
     }
+
+    /// Drops the support of the orders, and of the extra orders, which are not supported by the peer.
+    pub fn retain_supported_by(&mut self, peer: &Order) {
+        for (support, peer_support) in self.order_support.iter_mut().zip(peer.order_support.iter()) {
+            *support = u8::from(*support != 0 && *peer_support != 0);
+        }
+        self.order_support_ex_flags &= peer.order_support_ex_flags;
+    }
 }
 
 impl PduParsing for Order {
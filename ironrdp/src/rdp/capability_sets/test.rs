@@ -403,3 +403,71 @@ fn buffer_length_is_correct_for_client_demand_active() {
 
     assert_eq!(expected_buffer_len, len);
 }
+
+#[test]
+fn mirrored_client_confirm_active_drops_what_the_server_does_not_support() {
+    let mut server_order = Order::new(OrderFlags::NEGOTIATE_ORDER_SUPPORT, OrderSupportExFlags::empty(), 0, 0);
+    server_order.set_support_flag(OrderSupportIndex::MemBlt, true);
+    let server_demand_active = ServerDemandActive {
+        pdu: DemandActive {
+            source_descriptor: String::from("RDP"),
+            capability_sets: vec![
+                CapabilitySet::Order(server_order),
+                CapabilitySet::MultiFragmentUpdate(MultifragmentUpdate {
+                    max_request_size: 0x0010_0000,
+                }),
+                CapabilitySet::SurfaceCommands(SurfaceCommands {
+                    flags: CmdFlags::SET_SURFACE_BITS | CmdFlags::FRAME_MARKER,
+                }),
+            ],
+        },
+    };
+
+    let mut client_order = Order::new(
+        OrderFlags::NEGOTIATE_ORDER_SUPPORT,
+        OrderSupportExFlags::ALTSEC_FRAME_MARKER_SUPPORT,
+        0,
+        0,
+    );
+    client_order.set_support_flag(OrderSupportIndex::MemBlt, true);
+    client_order.set_support_flag(OrderSupportIndex::DstBlt, true);
+    let client_confirm_active = ClientConfirmActive::mirrored(
+        &server_demand_active,
+        String::from("IRONRDP"),
+        vec![
+            CapabilitySet::Order(client_order),
+            CapabilitySet::MultiFragmentUpdate(MultifragmentUpdate { max_request_size: 1024 }),
+            CapabilitySet::SurfaceCommands(SurfaceCommands {
+                flags: CmdFlags::SET_SURFACE_BITS | CmdFlags::STREAM_SURFACE_BITS | CmdFlags::FRAME_MARKER,
+            }),
+            CapabilitySet::LargePointer(LargePointer {
+                flags: LargePointerSupportFlags::UP_TO_96X96_PIXELS,
+            }),
+        ],
+    );
+
+    assert_eq!(SERVER_CHANNEL_ID, client_confirm_active.originator_id);
+    let mut capability_sets = client_confirm_active.pdu.capability_sets.into_iter();
+    match capability_sets.next() {
+        Some(CapabilitySet::Order(mut order)) => {
+            assert!(order.get_support_flag(OrderSupportIndex::MemBlt));
+            assert!(!order.get_support_flag(OrderSupportIndex::DstBlt));
+            assert_eq!(OrderSupportExFlags::empty(), order.order_support_ex_flags);
+        }
+        capability_set => panic!("unexpected capability set: {:?}", capability_set),
+    }
+    assert_eq!(
+        vec![
+            CapabilitySet::MultiFragmentUpdate(MultifragmentUpdate {
+                max_request_size: 0x0010_0000,
+            }),
+            CapabilitySet::SurfaceCommands(SurfaceCommands {
+                flags: CmdFlags::SET_SURFACE_BITS | CmdFlags::FRAME_MARKER,
+            }),
+            CapabilitySet::LargePointer(LargePointer {
+                flags: LargePointerSupportFlags::UP_TO_96X96_PIXELS,
+            }),
+        ],
+        capability_sets.collect::<Vec<_>>()
+    );
+}
@@ -1,13 +1,14 @@
 #[cfg(test)]
 pub mod test;
 
-use std::io;
+use std::{fmt, io};
 
 use bitflags::bitflags;
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
-use failure::Fail;
 use num_derive::{FromPrimitive, ToPrimitive};
 use num_traits::{FromPrimitive, ToPrimitive};
+use thiserror::Error;
+use zeroize::Zeroize;
 
 use crate::utils::CharacterSet;
 use crate::{impl_from_error, try_read_optional, try_write_optional, utils, PduParsing};
@@ -151,13 +152,32 @@ impl PduParsing for ClientInfo {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// The credentials of the Client Info PDU, which are overwritten with zeros when dropped.
+/// The password is not printed by their `Debug` implementation.
+#[derive(Clone, PartialEq, Eq)]
 pub struct Credentials {
     pub username: String,
     pub password: String,
     pub domain: Option<String>,
 }
 
+impl fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Credentials")
+            .field("username", &self.username)
+            .field("domain", &self.domain)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for Credentials {
+    fn drop(&mut self) {
+        self.username.zeroize();
+        self.password.zeroize();
+        self.domain.zeroize();
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ExtendedClientInfo {
     pub address_family: AddressFamily,
@@ -543,19 +563,19 @@ pub enum CompressionType {
     Rdp61 = 3,
 }
 
-#[derive(Debug, Fail)]
+#[derive(Debug, Error)]
 pub enum ClientInfoError {
-    #[fail(display = "IO error: {}", _0)]
-    IOError(#[fail(cause)] io::Error),
-    #[fail(display = "UTF-8 error: {}", _0)]
-    Utf8Error(#[fail(cause)] std::string::FromUtf8Error),
-    #[fail(display = "Invalid address family field")]
+    #[error("IO error: {}", .0)]
+    IOError(#[source] io::Error),
+    #[error("UTF-8 error: {}", .0)]
+    Utf8Error(#[source] std::string::FromUtf8Error),
+    #[error("Invalid address family field")]
     InvalidAddressFamily,
-    #[fail(display = "Invalid flags field")]
+    #[error("Invalid flags field")]
     InvalidClientInfoFlags,
-    #[fail(display = "Invalid performance flags field")]
+    #[error("Invalid performance flags field")]
     InvalidPerformanceFlags,
-    #[fail(display = "Invalid reconnect cookie field")]
+    #[error("Invalid reconnect cookie field")]
     InvalidReconnectCookie,
 }
 
@@ -241,3 +241,25 @@ fn buffer_length_is_correct_for_client_info_pdu_unicode_without_optional_fields(
 
     assert_eq!(expected_buffer_len, len);
 }
+
+#[test]
+fn credentials_debug_output_omits_password() {
+    let credentials = Credentials {
+        username: String::from("user"),
+        password: String::from("secret"),
+        domain: Some(String::from("DOMAIN")),
+    };
+
+    let output = format!("{:?}", credentials);
+
+    assert!(output.contains("user"));
+    assert!(!output.contains("secret"));
+}
+
+#[test]
+fn password_is_encoded_without_reallocating_its_buffer() {
+    let password = utils::string_to_utf16("sécret\u{1f511}");
+
+    assert_eq!(16, password.len());
+    assert_eq!(password.len(), password.capacity());
+}
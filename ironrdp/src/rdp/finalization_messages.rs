@@ -2,9 +2,9 @@ use std::io;
 
 use bitflags::bitflags;
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
-use failure::Fail;
 use num_derive::{FromPrimitive, ToPrimitive};
 use num_traits::{FromPrimitive, ToPrimitive};
+use thiserror::Error;
 
 use crate::{gcc, impl_from_error, PduParsing};
 
@@ -174,23 +174,23 @@ bitflags! {
     }
 }
 
-#[derive(Debug, Fail)]
+#[derive(Debug, Error)]
 pub enum FinalizationMessagesError {
-    #[fail(display = "IO error: {}", _0)]
-    IOError(#[fail(cause)] io::Error),
-    #[fail(display = "Monitor Data error: {}", _0)]
-    MonitorDataError(#[fail(cause)] gcc::MonitorDataError),
-    #[fail(display = "Invalid message type field in Synchronize PDU")]
+    #[error("IO error: {}", .0)]
+    IOError(#[source] io::Error),
+    #[error("Monitor Data error: {}", .0)]
+    MonitorDataError(#[source] gcc::MonitorDataError),
+    #[error("Invalid message type field in Synchronize PDU")]
     InvalidMessageType,
-    #[fail(display = "Invalid control action field in Control PDU")]
+    #[error("Invalid control action field in Control PDU")]
     InvalidControlAction,
-    #[fail(display = "Invalid grant id field in Control PDU")]
+    #[error("Invalid grant id field in Control PDU")]
     InvalidGrantId,
-    #[fail(display = "Invalid control id field in Control PDU")]
+    #[error("Invalid control id field in Control PDU")]
     InvalidControlId,
-    #[fail(display = "Invalid list flags field in Font List PDU")]
+    #[error("Invalid list flags field in Font List PDU")]
     InvalidListFlags,
-    #[fail(display = "Invalid monitor count field: {}", _0)]
+    #[error("Invalid monitor count field: {}", .0)]
     InvalidMonitorCount(u32),
 }
 
@@ -5,12 +5,16 @@ use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use num_derive::{FromPrimitive, ToPrimitive};
 use num_traits::{FromPrimitive, ToPrimitive};
 
+use super::bulk::BulkDecompressor;
 use super::{
-    client_info, ClientConfirmActive, ControlPdu, MonitorLayoutPdu, RdpError, ServerDemandActive,
-    ServerSetErrorInfoPdu, SynchronizePdu,
+    client_info, ClientConfirmActive, ControlPdu, MonitorLayoutPdu, RdpError, RefreshRectanglePdu, ServerDemandActive,
+    ServerRedirectionPdu, ServerSetErrorInfoPdu, ServerStatusInfoPdu, SetKeyboardImeStatusPdu,
+    SetKeyboardIndicatorsPdu, SynchronizePdu,
 };
 use crate::codecs::rfx::FrameAcknowledgePdu;
+use crate::consts::SHARE_CONTROL_PROTOCOL_VERSION;
 use crate::input::InputEventPdu;
+use crate::pointer::PointerPdu;
 use crate::rdp::finalization_messages::FontPdu;
 use crate::rdp::session_info::SaveSessionInfoPdu;
 use crate::PduParsing;
@@ -19,8 +23,7 @@ pub const BASIC_SECURITY_HEADER_SIZE: usize = 4;
 const SHARE_CONTROL_HEADER_MASK: u16 = 0xf;
 const SHARE_DATA_HEADER_MASK: u8 = 0xf;
 const SHARE_CONTROL_HEADER_SIZE: usize = 2 * 3 + 4;
-
-const PROTOCOL_VERSION: u16 = 0x10;
+const SERVER_REDIRECTION_PADDING_SIZE: usize = 2;
 
 // ShareDataHeader
 const PADDING_FIELD_SIZE: usize = 1;
@@ -29,6 +32,12 @@ const UNCOMPRESSED_LENGTH_FIELD_SIZE: usize = 2;
 const PDU_TYPE_FIELD_SIZE: usize = 1;
 const COMPRESSION_TYPE_FIELD_SIZE: usize = 1;
 const COMPRESSED_LENGTH_FIELD_SIZE: usize = 2;
+const SHARE_DATA_HEADER_SIZE: usize = PADDING_FIELD_SIZE
+    + STREAM_ID_FIELD_SIZE
+    + UNCOMPRESSED_LENGTH_FIELD_SIZE
+    + PDU_TYPE_FIELD_SIZE
+    + COMPRESSION_TYPE_FIELD_SIZE
+    + COMPRESSED_LENGTH_FIELD_SIZE;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct BasicSecurityHeader {
@@ -77,7 +86,7 @@ impl PduParsing for ShareControlHeader {
         let pdu_type = ShareControlPduType::from_u16(pdu_type_with_version & SHARE_CONTROL_HEADER_MASK)
             .ok_or_else(|| RdpError::InvalidShareControlHeader(String::from("Invalid pdu type")))?;
         let pdu_version = pdu_type_with_version & !SHARE_CONTROL_HEADER_MASK;
-        if pdu_version != PROTOCOL_VERSION {
+        if pdu_version != SHARE_CONTROL_PROTOCOL_VERSION {
             return Err(RdpError::InvalidShareControlHeader(format!(
                 "Invalid PDU version: {}",
                 pdu_version
@@ -90,12 +99,12 @@ impl PduParsing for ShareControlHeader {
             pdu_source,
             share_id,
         };
-        if pdu_type == ShareControlPduType::DataPdu {
+        if pdu_type == ShareControlPduType::DataPdu || pdu_type == ShareControlPduType::ServerRedirect {
             // Some windows version have an issue where PDU
             // there is some padding not part of the inner unit.
-            // Consume that data
+            // Consume that data, as well as the optional padding following the Server Redirection PDU
             let header_length = header.buffer_length();
-            if header_length != total_length {
+            if header_length < total_length {
                 let padding = total_length - header_length;
                 let mut data = vec![0u8; padding];
                 stream.read_exact(data.as_mut())?;
@@ -105,7 +114,8 @@ impl PduParsing for ShareControlHeader {
         Ok(header)
     }
     fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
-        let pdu_type_with_version = PROTOCOL_VERSION | self.share_control_pdu.share_header_type().to_u16().unwrap();
+        let pdu_type_with_version =
+            SHARE_CONTROL_PROTOCOL_VERSION | self.share_control_pdu.share_header_type().to_u16().unwrap();
 
         stream
             .write_u16::<LittleEndian>((self.share_control_pdu.buffer_length() + SHARE_CONTROL_HEADER_SIZE) as u16)?;
@@ -120,11 +130,73 @@ impl PduParsing for ShareControlHeader {
     }
 }
 
+impl ShareControlHeader {
+    /// Decompresses the data of the Share Data PDU compressed by the server, returning the PDU with its data
+    /// uncompressed, to be decoded with [`PduParsing::from_buffer`], or `None` if the PDU is not compressed.
+    ///
+    /// The Share Data PDUs are to be passed in the order they are received, the decompressor keeping the history
+    /// the compressed data refers to.
+    pub fn decompress(pdu: &[u8], decompressor: &mut BulkDecompressor) -> Result<Option<Vec<u8>>, RdpError> {
+        let mut stream = pdu;
+        let _total_length = stream.read_u16::<LittleEndian>()?;
+        let pdu_type_with_version = stream.read_u16::<LittleEndian>()?;
+        if ShareControlPduType::from_u16(pdu_type_with_version & SHARE_CONTROL_HEADER_MASK)
+            != Some(ShareControlPduType::DataPdu)
+        {
+            return Ok(None);
+        }
+        let pdu_source = stream.read_u16::<LittleEndian>()?;
+        let share_id = stream.read_u32::<LittleEndian>()?;
+        let _padding = stream.read_u8()?;
+        let stream_priority = stream.read_u8()?;
+        let uncompressed_length = stream.read_u16::<LittleEndian>()?;
+        let pdu_type = stream.read_u8()?;
+        let compression_flags_with_type = stream.read_u8()?;
+        let compressed_length = usize::from(stream.read_u16::<LittleEndian>()?);
+
+        let compression_flags =
+            CompressionFlags::from_bits_truncate(compression_flags_with_type & !SHARE_DATA_HEADER_MASK);
+        if !compression_flags.contains(CompressionFlags::COMPRESSED) {
+            return Ok(None);
+        }
+        let compression_type =
+            client_info::CompressionType::from_u8(compression_flags_with_type & SHARE_DATA_HEADER_MASK)
+                .ok_or_else(|| RdpError::InvalidShareDataHeader(String::from("Invalid compression type")))?;
+
+        // the compressed length includes the headers, the data being followed by the padding if any
+        let headers_length = SHARE_CONTROL_HEADER_SIZE + SHARE_DATA_HEADER_SIZE;
+        let data = compressed_length
+            .checked_sub(headers_length)
+            .and_then(|data_length| stream.get(..data_length))
+            .ok_or_else(|| RdpError::InvalidShareDataHeader(String::from("Invalid compressed length")))?;
+        let data = decompressor.decompress(compression_type, compression_flags, data)?;
+
+        let mut decompressed = Vec::with_capacity(headers_length + data.len());
+        // the total length is only used to skip the padding, which has been dropped with the compressed data
+        let total_length = u16::try_from(headers_length + data.len()).unwrap_or(u16::MAX);
+        decompressed.write_u16::<LittleEndian>(total_length)?;
+        decompressed.write_u16::<LittleEndian>(pdu_type_with_version)?;
+        decompressed.write_u16::<LittleEndian>(pdu_source)?;
+        decompressed.write_u32::<LittleEndian>(share_id)?;
+        decompressed.write_u8(0)?; // padding
+        decompressed.write_u8(stream_priority)?;
+        decompressed.write_u16::<LittleEndian>(uncompressed_length)?;
+        decompressed.write_u8(pdu_type)?;
+        decompressed.write_u8(0)?; // compression flags and type
+        decompressed.write_u16::<LittleEndian>(0)?; // compressed length
+        decompressed.extend_from_slice(data);
+
+        Ok(Some(decompressed))
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ShareControlPdu {
     ServerDemandActive(ServerDemandActive),
     ClientConfirmActive(ClientConfirmActive),
     Data(ShareDataHeader),
+    /// The Enhanced Security Server Redirection PDU, sent instead of the Server Demand Active PDU.
+    ServerRedirection(ServerRedirectionPdu),
 }
 
 impl ShareControlPdu {
@@ -133,6 +205,7 @@ impl ShareControlPdu {
             ShareControlPdu::ServerDemandActive(_) => "Server Demand Active PDU",
             ShareControlPdu::ClientConfirmActive(_) => "Client Confirm Active PDU",
             ShareControlPdu::Data(_) => "Data PDU",
+            ShareControlPdu::ServerRedirection(_) => "Server Redirection PDU",
         }
     }
 }
@@ -147,6 +220,13 @@ impl ShareControlPdu {
                 ClientConfirmActive::from_buffer(&mut stream)?,
             )),
             ShareControlPduType::DataPdu => Ok(ShareControlPdu::Data(ShareDataHeader::from_buffer(&mut stream)?)),
+            ShareControlPduType::ServerRedirect => {
+                let _padding = stream.read_u16::<LittleEndian>()?;
+
+                Ok(ShareControlPdu::ServerRedirection(ServerRedirectionPdu::from_buffer(
+                    &mut stream,
+                )?))
+            }
             _ => Err(RdpError::UnexpectedShareControlPdu(share_type)),
         }
     }
@@ -155,6 +235,11 @@ impl ShareControlPdu {
             ShareControlPdu::ServerDemandActive(pdu) => pdu.to_buffer(&mut stream).map_err(RdpError::from),
             ShareControlPdu::ClientConfirmActive(pdu) => pdu.to_buffer(&mut stream).map_err(RdpError::from),
             ShareControlPdu::Data(share_data_header) => share_data_header.to_buffer(&mut stream),
+            ShareControlPdu::ServerRedirection(pdu) => {
+                stream.write_u16::<LittleEndian>(0)?; // padding
+
+                pdu.to_buffer(&mut stream).map_err(RdpError::from)
+            }
         }
     }
     pub fn buffer_length(&self) -> usize {
@@ -162,6 +247,7 @@ impl ShareControlPdu {
             ShareControlPdu::ServerDemandActive(pdu) => pdu.buffer_length(),
             ShareControlPdu::ClientConfirmActive(pdu) => pdu.buffer_length(),
             ShareControlPdu::Data(share_data_header) => share_data_header.buffer_length(),
+            ShareControlPdu::ServerRedirection(pdu) => SERVER_REDIRECTION_PADDING_SIZE + pdu.buffer_length(),
         }
     }
     pub fn share_header_type(&self) -> ShareControlPduType {
@@ -169,6 +255,7 @@ impl ShareControlPdu {
             ShareControlPdu::ServerDemandActive(_) => ShareControlPduType::DemandActivePdu,
             ShareControlPdu::ClientConfirmActive(_) => ShareControlPduType::ConfirmActivePdu,
             ShareControlPdu::Data(_) => ShareControlPduType::DataPdu,
+            ShareControlPdu::ServerRedirection(_) => ShareControlPduType::ServerRedirect,
         }
     }
 }
@@ -194,11 +281,16 @@ impl PduParsing for ShareDataHeader {
         let compression_flags_with_type = stream.read_u8()?;
 
         let compression_flags =
-            CompressionFlags::from_bits_truncate(compression_flags_with_type & SHARE_DATA_HEADER_MASK);
+            CompressionFlags::from_bits_truncate(compression_flags_with_type & !SHARE_DATA_HEADER_MASK);
         let compression_type =
-            client_info::CompressionType::from_u8(compression_flags_with_type & !SHARE_DATA_HEADER_MASK)
+            client_info::CompressionType::from_u8(compression_flags_with_type & SHARE_DATA_HEADER_MASK)
                 .ok_or_else(|| RdpError::InvalidShareDataHeader(String::from("Invalid compression type")))?;
         let _compressed_length = stream.read_u16::<LittleEndian>()?;
+        if compression_flags.contains(CompressionFlags::COMPRESSED) {
+            return Err(RdpError::InvalidShareDataHeader(String::from(
+                "The compressed data is to be decompressed with ShareControlHeader::decompress",
+            )));
+        }
 
         let share_data_pdu = ShareDataPdu::from_type(&mut stream, pdu_type)?;
 
@@ -256,6 +348,11 @@ pub enum ShareDataPdu {
     FrameAcknowledge(FrameAcknowledgePdu),
     ServerSetErrorInfo(ServerSetErrorInfoPdu),
     Input(InputEventPdu),
+    SetKeyboardIndicators(SetKeyboardIndicatorsPdu),
+    SetKeyboardImeStatus(SetKeyboardImeStatusPdu),
+    ServerStatusInfo(ServerStatusInfoPdu),
+    RefreshRectangle(RefreshRectanglePdu),
+    Pointer(PointerPdu),
 }
 
 impl ShareDataPdu {
@@ -270,6 +367,11 @@ impl ShareDataPdu {
             ShareDataPdu::FrameAcknowledge(_) => "Frame Acknowledge PDU",
             ShareDataPdu::ServerSetErrorInfo(_) => "Server Set Error Info PDU",
             ShareDataPdu::Input(_) => "Server Input PDU",
+            ShareDataPdu::SetKeyboardIndicators(_) => "Set Keyboard Indicators PDU",
+            ShareDataPdu::SetKeyboardImeStatus(_) => "Set Keyboard IME Status PDU",
+            ShareDataPdu::ServerStatusInfo(_) => "Server Status Info PDU",
+            ShareDataPdu::RefreshRectangle(_) => "Refresh Rect PDU",
+            ShareDataPdu::Pointer(_) => "Pointer Update PDU",
         }
     }
 }
@@ -294,22 +396,30 @@ impl ShareDataPdu {
                 ServerSetErrorInfoPdu::from_buffer(&mut stream)?,
             )),
             ShareDataPduType::Input => Ok(ShareDataPdu::Input(InputEventPdu::from_buffer(&mut stream)?)),
+            ShareDataPduType::SetKeyboardIndicators => Ok(ShareDataPdu::SetKeyboardIndicators(
+                SetKeyboardIndicatorsPdu::from_buffer(&mut stream)?,
+            )),
+            ShareDataPduType::SetKeyboardImeStatus => Ok(ShareDataPdu::SetKeyboardImeStatus(
+                SetKeyboardImeStatusPdu::from_buffer(&mut stream)?,
+            )),
+            ShareDataPduType::StatusInfoPdu => Ok(ShareDataPdu::ServerStatusInfo(ServerStatusInfoPdu::from_buffer(
+                &mut stream,
+            )?)),
+            ShareDataPduType::RefreshRectangle => Ok(ShareDataPdu::RefreshRectangle(RefreshRectanglePdu::from_buffer(
+                &mut stream,
+            )?)),
+            ShareDataPduType::Pointer => Ok(ShareDataPdu::Pointer(PointerPdu::from_buffer(&mut stream)?)),
             ShareDataPduType::Update
-            | ShareDataPduType::Pointer
-            | ShareDataPduType::RefreshRectangle
             | ShareDataPduType::PlaySound
             | ShareDataPduType::SuppressOutput
             | ShareDataPduType::ShutdownRequest
             | ShareDataPduType::ShutdownDenied
-            | ShareDataPduType::SetKeyboardIndicators
             | ShareDataPduType::BitmapCachePersistentList
             | ShareDataPduType::BitmapCacheErrorPdu
-            | ShareDataPduType::SetKeyboardImeStatus
             | ShareDataPduType::OffscreenCacheErrorPdu
             | ShareDataPduType::DrawNineGridErrorPdu
             | ShareDataPduType::DrawGdiPusErrorPdu
-            | ShareDataPduType::ArcStatusPdu
-            | ShareDataPduType::StatusInfoPdu => Err(RdpError::UnexpectedShareDataPdu(share_type)),
+            | ShareDataPduType::ArcStatusPdu => Err(RdpError::UnexpectedShareDataPdu(share_type)),
         }
     }
     pub fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), RdpError> {
@@ -324,6 +434,11 @@ impl ShareDataPdu {
             ShareDataPdu::FrameAcknowledge(pdu) => pdu.to_buffer(&mut stream).map_err(RdpError::from),
             ShareDataPdu::ServerSetErrorInfo(pdu) => pdu.to_buffer(&mut stream).map_err(RdpError::from),
             ShareDataPdu::Input(pdu) => pdu.to_buffer(&mut stream).map_err(RdpError::from),
+            ShareDataPdu::SetKeyboardIndicators(pdu) => pdu.to_buffer(&mut stream).map_err(RdpError::from),
+            ShareDataPdu::SetKeyboardImeStatus(pdu) => pdu.to_buffer(&mut stream).map_err(RdpError::from),
+            ShareDataPdu::ServerStatusInfo(pdu) => pdu.to_buffer(&mut stream).map_err(RdpError::from),
+            ShareDataPdu::RefreshRectangle(pdu) => pdu.to_buffer(&mut stream).map_err(RdpError::from),
+            ShareDataPdu::Pointer(pdu) => pdu.to_buffer(&mut stream).map_err(RdpError::from),
         }
     }
     pub fn buffer_length(&self) -> usize {
@@ -336,6 +451,11 @@ impl ShareDataPdu {
             ShareDataPdu::FrameAcknowledge(pdu) => pdu.buffer_length(),
             ShareDataPdu::ServerSetErrorInfo(pdu) => pdu.buffer_length(),
             ShareDataPdu::Input(pdu) => pdu.buffer_length(),
+            ShareDataPdu::SetKeyboardIndicators(pdu) => pdu.buffer_length(),
+            ShareDataPdu::SetKeyboardImeStatus(pdu) => pdu.buffer_length(),
+            ShareDataPdu::ServerStatusInfo(pdu) => pdu.buffer_length(),
+            ShareDataPdu::RefreshRectangle(pdu) => pdu.buffer_length(),
+            ShareDataPdu::Pointer(pdu) => pdu.buffer_length(),
         }
     }
     pub fn share_header_type(&self) -> ShareDataPduType {
@@ -349,6 +469,11 @@ impl ShareDataPdu {
             ShareDataPdu::FrameAcknowledge(_) => ShareDataPduType::FrameAcknowledgePdu,
             ShareDataPdu::ServerSetErrorInfo(_) => ShareDataPduType::SetErrorInfoPdu,
             ShareDataPdu::Input(_) => ShareDataPduType::Input,
+            ShareDataPdu::SetKeyboardIndicators(_) => ShareDataPduType::SetKeyboardIndicators,
+            ShareDataPdu::SetKeyboardImeStatus(_) => ShareDataPduType::SetKeyboardImeStatus,
+            ShareDataPdu::ServerStatusInfo(_) => ShareDataPduType::StatusInfoPdu,
+            ShareDataPdu::RefreshRectangle(_) => ShareDataPduType::RefreshRectangle,
+            ShareDataPdu::Pointer(_) => ShareDataPduType::Pointer,
         }
     }
 }
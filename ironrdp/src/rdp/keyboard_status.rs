@@ -0,0 +1,205 @@
+use std::io;
+
+use bitflags::bitflags;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use num_derive::{FromPrimitive, ToPrimitive};
+use num_traits::{FromPrimitive, ToPrimitive};
+use thiserror::Error;
+
+use crate::{impl_from_error, PduParsing};
+
+const UNIT_ID: u16 = 0;
+const SET_KEYBOARD_INDICATORS_PDU_SIZE: usize = 2 + 2;
+const SET_KEYBOARD_IME_STATUS_PDU_SIZE: usize = 2 + 4 + 4;
+
+/// Sent by the server to synchronize the toggle keys state (e.g. after the input language
+/// or the keyboard layout has been changed in the remote session).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SetKeyboardIndicatorsPdu {
+    pub led_flags: LedFlags,
+}
+
+impl PduParsing for SetKeyboardIndicatorsPdu {
+    type Error = KeyboardStatusError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        let _unit_id = stream.read_u16::<LittleEndian>()?;
+        let led_flags = LedFlags::from_bits_truncate(stream.read_u16::<LittleEndian>()?);
+
+        Ok(Self { led_flags })
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        stream.write_u16::<LittleEndian>(UNIT_ID)?;
+        stream.write_u16::<LittleEndian>(self.led_flags.bits())?;
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        SET_KEYBOARD_INDICATORS_PDU_SIZE
+    }
+}
+
+/// Sent by the server when the state of the Input Method Editor (IME)
+/// of the remote session has been changed (e.g. by an input language switch).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SetKeyboardImeStatusPdu {
+    pub ime_state: ImeState,
+    pub ime_conversion_mode: ImeConversionMode,
+}
+
+impl PduParsing for SetKeyboardImeStatusPdu {
+    type Error = KeyboardStatusError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        let _unit_id = stream.read_u16::<LittleEndian>()?;
+        let ime_state = stream.read_u32::<LittleEndian>()?;
+        let ime_state = ImeState::from_u32(ime_state).ok_or(KeyboardStatusError::InvalidImeState(ime_state))?;
+        let ime_conversion_mode = ImeConversionMode::from_bits_truncate(stream.read_u32::<LittleEndian>()?);
+
+        Ok(Self {
+            ime_state,
+            ime_conversion_mode,
+        })
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        stream.write_u16::<LittleEndian>(UNIT_ID)?;
+        stream.write_u32::<LittleEndian>(self.ime_state.to_u32().unwrap())?;
+        stream.write_u32::<LittleEndian>(self.ime_conversion_mode.bits())?;
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        SET_KEYBOARD_IME_STATUS_PDU_SIZE
+    }
+}
+
+bitflags! {
+    pub struct LedFlags: u16 {
+        const SCROLL_LOCK = 0x0001;
+        const NUM_LOCK = 0x0002;
+        const CAPS_LOCK = 0x0004;
+        const KANA_LOCK = 0x0008;
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, FromPrimitive, ToPrimitive)]
+pub enum ImeState {
+    Closed = 0x0000_0000,
+    Open = 0x0000_0001,
+}
+
+bitflags! {
+    pub struct ImeConversionMode: u32 {
+        const NATIVE = 0x0000_0001;
+        const KATAKANA = 0x0000_0002;
+        const FULLSHAPE = 0x0000_0008;
+        const ROMAN = 0x0000_0010;
+        const CHARCODE = 0x0000_0020;
+        const HANJACONVERT = 0x0000_0040;
+        const SOFTKBD = 0x0000_0080;
+        const NOCONVERSION = 0x0000_0100;
+        const EUDC = 0x0000_0200;
+        const SYMBOL = 0x0000_0400;
+        const FIXED = 0x0000_0800;
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum KeyboardStatusError {
+    #[error("IO error: {}", .0)]
+    IOError(#[source] io::Error),
+    #[error("Invalid IME state: {}", .0)]
+    InvalidImeState(u32),
+}
+
+impl_from_error!(io::Error, KeyboardStatusError, KeyboardStatusError::IOError);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SET_KEYBOARD_INDICATORS_BUFFER: [u8; 4] = [
+        0x00, 0x00, // unit ID
+        0x06, 0x00, // LED flags
+    ];
+
+    const SET_KEYBOARD_IME_STATUS_BUFFER: [u8; 10] = [
+        0x00, 0x00, // unit ID
+        0x01, 0x00, 0x00, 0x00, // IME state
+        0x19, 0x00, 0x00, 0x00, // IME conversion mode
+    ];
+
+    const SET_KEYBOARD_INDICATORS: SetKeyboardIndicatorsPdu = SetKeyboardIndicatorsPdu {
+        led_flags: LedFlags::from_bits_truncate(LedFlags::NUM_LOCK.bits() | LedFlags::CAPS_LOCK.bits()),
+    };
+
+    const SET_KEYBOARD_IME_STATUS: SetKeyboardImeStatusPdu = SetKeyboardImeStatusPdu {
+        ime_state: ImeState::Open,
+        ime_conversion_mode: ImeConversionMode::from_bits_truncate(
+            ImeConversionMode::NATIVE.bits() | ImeConversionMode::FULLSHAPE.bits() | ImeConversionMode::ROMAN.bits(),
+        ),
+    };
+
+    #[test]
+    fn from_buffer_correctly_parses_set_keyboard_indicators() {
+        assert_eq!(
+            SET_KEYBOARD_INDICATORS,
+            SetKeyboardIndicatorsPdu::from_buffer(SET_KEYBOARD_INDICATORS_BUFFER.as_ref()).unwrap()
+        );
+    }
+
+    #[test]
+    fn to_buffer_correctly_serializes_set_keyboard_indicators() {
+        let mut buffer = Vec::new();
+
+        SET_KEYBOARD_INDICATORS.to_buffer(&mut buffer).unwrap();
+        assert_eq!(SET_KEYBOARD_INDICATORS_BUFFER.as_ref(), buffer.as_slice());
+    }
+
+    #[test]
+    fn buffer_length_is_correct_for_set_keyboard_indicators() {
+        assert_eq!(
+            SET_KEYBOARD_INDICATORS_BUFFER.len(),
+            SET_KEYBOARD_INDICATORS.buffer_length()
+        );
+    }
+
+    #[test]
+    fn from_buffer_correctly_parses_set_keyboard_ime_status() {
+        assert_eq!(
+            SET_KEYBOARD_IME_STATUS,
+            SetKeyboardImeStatusPdu::from_buffer(SET_KEYBOARD_IME_STATUS_BUFFER.as_ref()).unwrap()
+        );
+    }
+
+    #[test]
+    fn from_buffer_set_keyboard_ime_status_fails_on_invalid_ime_state() {
+        let mut buffer = SET_KEYBOARD_IME_STATUS_BUFFER;
+        buffer[2] = 0x02;
+
+        match SetKeyboardImeStatusPdu::from_buffer(buffer.as_ref()) {
+            Err(KeyboardStatusError::InvalidImeState(2)) => (),
+            res => panic!("Expected the invalid IME state error, got: {:?}", res),
+        }
+    }
+
+    #[test]
+    fn to_buffer_correctly_serializes_set_keyboard_ime_status() {
+        let mut buffer = Vec::new();
+
+        SET_KEYBOARD_IME_STATUS.to_buffer(&mut buffer).unwrap();
+        assert_eq!(SET_KEYBOARD_IME_STATUS_BUFFER.as_ref(), buffer.as_slice());
+    }
+
+    #[test]
+    fn buffer_length_is_correct_for_set_keyboard_ime_status() {
+        assert_eq!(
+            SET_KEYBOARD_IME_STATUS_BUFFER.len(),
+            SET_KEYBOARD_IME_STATUS.buffer_length()
+        );
+    }
+}
@@ -0,0 +1,56 @@
+use std::io;
+
+use byteorder::{ReadBytesExt, WriteBytesExt};
+
+use crate::utils::Rectangle;
+use crate::PduParsing;
+
+const NUMBER_OF_AREAS_FIELD_SIZE: usize = 1;
+const PADDING_SIZE: usize = 3;
+const INCLUSIVE_RECTANGLE_SIZE: usize = 8;
+
+/// Sent by the client to request the server to send again the graphics of the areas of the desktop,
+/// e.g. after the client has lost the pictures a codec predicts the next ones from.
+///
+/// The bounds of the areas are inclusive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RefreshRectanglePdu {
+    pub areas_to_refresh: Vec<Rectangle>,
+}
+
+impl PduParsing for RefreshRectanglePdu {
+    type Error = io::Error;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        let number_of_areas = stream.read_u8()?;
+        let mut padding = [0; PADDING_SIZE];
+        stream.read_exact(&mut padding)?;
+
+        let areas_to_refresh = (0..number_of_areas)
+            .map(|_| Rectangle::from_buffer(&mut stream))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self { areas_to_refresh })
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        let number_of_areas = u8::try_from(self.areas_to_refresh.len()).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Too many areas to refresh: {}", self.areas_to_refresh.len()),
+            )
+        })?;
+
+        stream.write_u8(number_of_areas)?;
+        stream.write_all(&[0; PADDING_SIZE])?;
+        for area in self.areas_to_refresh.iter() {
+            area.to_buffer(&mut stream)?;
+        }
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        NUMBER_OF_AREAS_FIELD_SIZE + PADDING_SIZE + self.areas_to_refresh.len() * INCLUSIVE_RECTANGLE_SIZE
+    }
+}
@@ -1,9 +1,9 @@
 use std::io;
 
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
-use failure::Fail;
 use num_derive::{FromPrimitive, ToPrimitive};
 use num_traits::{FromPrimitive, ToPrimitive};
+use thiserror::Error;
 
 use crate::{impl_from_error, PduParsing};
 
@@ -385,11 +385,11 @@ impl RdpSpecificCode {
     }
 }
 
-#[derive(Debug, Fail)]
+#[derive(Debug, Error)]
 pub enum ServerSetErrorInfoError {
-    #[fail(display = "IO error: {}", _0)]
-    IoError(#[fail(cause)] io::Error),
-    #[fail(display = "Unexpected info code: {}", _0)]
+    #[error("IO error: {}", .0)]
+    IoError(#[source] io::Error),
+    #[error("Unexpected info code: {}", .0)]
     UnexpectedInfoCode(u32),
 }
 
@@ -2,11 +2,11 @@ use std::io;
 
 use bitflags::bitflags;
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
-use failure::Fail;
 use md5::Digest;
 use num_derive::{FromPrimitive, ToPrimitive};
 use num_traits::{FromPrimitive, ToPrimitive};
 use ring::digest;
+use thiserror::Error;
 
 use crate::rdp::{BasicSecurityHeader, BasicSecurityHeaderFlags, BASIC_SECURITY_HEADER_SIZE};
 use crate::{impl_from_error, PduParsing};
@@ -14,6 +14,7 @@ use crate::{impl_from_error, PduParsing};
 #[cfg(test)]
 pub mod test;
 
+mod client_license_information;
 mod client_new_license_request;
 mod client_platform_challenge_response;
 mod licensing_error_message;
@@ -21,12 +22,16 @@ mod server_license_request;
 mod server_platform_challenge;
 mod server_upgrade_license;
 
+pub use self::client_license_information::ClientLicenseInformation;
+pub(crate) use self::client_new_license_request::salted_hash;
 pub use self::client_new_license_request::{ClientNewLicenseRequest, PLATFORM_ID};
 pub use self::client_platform_challenge_response::ClientPlatformChallengeResponse;
 pub use self::licensing_error_message::{LicenseErrorCode, LicensingErrorMessage, LicensingStateTransition};
-pub use self::server_license_request::{InitialMessageType, InitialServerLicenseMessage, ServerLicenseRequest};
+pub use self::server_license_request::{
+    InitialMessageType, InitialServerLicenseMessage, ServerCertificate, ServerLicenseRequest,
+};
 pub use self::server_platform_challenge::ServerPlatformChallenge;
-pub use self::server_upgrade_license::ServerUpgradeLicense;
+pub use self::server_upgrade_license::{NewLicenseInformation, ServerUpgradeLicense};
 
 pub const PREAMBLE_SIZE: usize = 4;
 pub const PREMASTER_SECRET_SIZE: usize = 48;
@@ -161,73 +166,77 @@ pub enum BlobType {
     ClientMachineNameBlob = 0x10,
 }
 
-#[derive(Debug, Fail)]
+#[derive(Debug, Error)]
 pub enum ServerLicenseError {
-    #[fail(display = "IO error: {}", _0)]
-    IOError(#[fail(cause)] io::Error),
-    #[fail(display = "UTF-8 error: {}", _0)]
-    Utf8Error(#[fail(cause)] std::string::FromUtf8Error),
-    #[fail(display = "Invalid preamble field: {}", _0)]
+    #[error("IO error: {}", .0)]
+    IOError(#[source] io::Error),
+    #[error("UTF-8 error: {}", .0)]
+    Utf8Error(#[source] std::string::FromUtf8Error),
+    #[error("Invalid preamble field: {}", .0)]
     InvalidPreamble(String),
-    #[fail(display = "Invalid preamble message type field")]
+    #[error("Invalid preamble message type field")]
     InvalidLicenseType,
-    #[fail(display = "Invalid error code field")]
+    #[error("Invalid error code field")]
     InvalidErrorCode,
-    #[fail(display = "Invalid state transition field")]
+    #[error("Invalid state transition field")]
     InvalidStateTransition,
-    #[fail(display = "Invalid blob type field")]
+    #[error("Invalid blob type field")]
     InvalidBlobType,
-    #[fail(display = "Unable to generate random number {}", _0)]
+    #[error("Unable to generate random number {}", .0)]
     RandomNumberGenerationError(String),
-    #[fail(display = "Unable to retrieve public key from the certificate")]
+    #[error("Unable to retrieve public key from the certificate")]
     UnableToGetPublicKey,
-    #[fail(display = "Unable to encrypt RSA public key")]
+    #[error("Unable to encrypt RSA public key")]
     RsaKeyEncryptionError,
-    #[fail(display = "Invalid License Request key exchange algorithm value")]
+    #[error("Invalid License Request key exchange algorithm value")]
     InvalidKeyExchangeValue,
-    #[fail(display = "MAC checksum generated over decrypted data does not match the server's checksum")]
+    #[error("MAC checksum generated over decrypted data does not match the server's checksum")]
     InvalidMacData,
-    #[fail(display = "Invalid platform challenge response data version")]
+    #[error("Invalid platform challenge response data version")]
     InvalidChallengeResponseDataVersion,
-    #[fail(display = "Invalid platform challenge response data client type")]
+    #[error("Invalid platform challenge response data client type")]
     InvalidChallengeResponseDataClientType,
-    #[fail(display = "Invalid platform challenge response data license detail level")]
+    #[error("Invalid platform challenge response data license detail level")]
     InvalidChallengeResponseDataLicenseDetail,
-    #[fail(display = "Invalid x509 certificate")]
+    #[error("Invalid x509 certificate")]
     InvalidX509Certificate,
-    #[fail(display = "Invalid certificate version")]
+    #[error("Invalid certificate version")]
     InvalidCertificateVersion,
-    #[fail(display = "Invalid x509 certificates amount")]
+    #[error("Invalid x509 certificates amount")]
     InvalidX509CertificatesAmount,
-    #[fail(display = "Invalid proprietary certificate signature algorithm ID")]
+    #[error("Invalid proprietary certificate signature algorithm ID")]
     InvalidPropCertSignatureAlgorithmId,
-    #[fail(display = "Invalid proprietary certificate key algorithm ID")]
+    #[error("Invalid proprietary certificate key algorithm ID")]
     InvalidPropCertKeyAlgorithmId,
-    #[fail(display = "Invalid RSA public key magic")]
+    #[error("Invalid RSA public key magic")]
     InvalidRsaPublicKeyMagic,
-    #[fail(display = "Invalid RSA public key length")]
+    #[error("Invalid RSA public key length")]
     InvalidRsaPublicKeyLength,
-    #[fail(display = "Invalid RSA public key data length")]
+    #[error("Invalid RSA public key data length")]
     InvalidRsaPublicKeyDataLength,
-    #[fail(display = "Invalid License Header security flags")]
+    #[error("Invalid RSA public key exponent")]
+    InvalidRsaPublicKeyExponent,
+    #[error("Invalid RSA public key modulus")]
+    InvalidRsaPublicKeyModulus,
+    #[error("Invalid License Header security flags")]
     InvalidSecurityFlags,
-    #[fail(display = "The server returned unexpected error")]
+    #[error("The server returned unexpected error")]
     UnexpectedError(LicensingErrorMessage),
-    #[fail(display = "Got unexpected license message")]
+    #[error("Got unexpected license message")]
     UnexpectedLicenseMessage,
-    #[fail(display = "The server has returned an unexpected error")]
+    #[error("The server has returned an unexpected error")]
     UnexpectedServerError(LicensingErrorMessage),
-    #[fail(display = "The server has returned STATUS_VALID_CLIENT unexpectedly")]
+    #[error("The server has returned STATUS_VALID_CLIENT unexpectedly")]
     UnexpectedValidClientError(LicensingErrorMessage),
-    #[fail(display = "Invalid Key Exchange List field")]
+    #[error("Invalid Key Exchange List field")]
     InvalidKeyExchangeAlgorithm,
-    #[fail(display = "Received invalid company name length (Product Information): {}", _0)]
+    #[error("Received invalid company name length (Product Information): {}", .0)]
     InvalidCompanyNameLength(u32),
-    #[fail(display = "Received invalid product ID length (Product Information): {}", _0)]
+    #[error("Received invalid product ID length (Product Information): {}", .0)]
     InvalidProductIdLength(u32),
-    #[fail(display = "Received invalid scope count field: {}", _0)]
+    #[error("Received invalid scope count field: {}", .0)]
     InvalidScopeCount(u32),
-    #[fail(display = "Received invalid sertificate length: {}", _0)]
+    #[error("Received invalid sertificate length: {}", .0)]
     InvalidCertificateLength(u32),
 }
 
@@ -283,7 +292,7 @@ impl_from_error!(
     ServerLicenseError::Utf8Error
 );
 
-fn compute_mac_data(mac_salt_key: &[u8], data: &[u8]) -> Vec<u8> {
+pub(crate) fn compute_mac_data(mac_salt_key: &[u8], data: &[u8]) -> Vec<u8> {
     let data_len_buffer = (data.len() as u32).to_le_bytes();
 
     let pad_one: [u8; 40] = [0x36; 40];
@@ -0,0 +1,160 @@
+#[cfg(test)]
+mod test;
+
+use std::io;
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use super::client_new_license_request::encrypt_premaster_secret;
+use super::client_platform_challenge_response::compute_hardware_id;
+use super::{
+    BasicSecurityHeader, BasicSecurityHeaderFlags, BlobHeader, BlobType, LicenseEncryptionData, LicenseHeader,
+    PreambleFlags, PreambleType, PreambleVersion, ServerLicenseError, ServerLicenseRequest, BLOB_LENGTH_SIZE,
+    BLOB_TYPE_SIZE, KEY_EXCHANGE_ALGORITHM_RSA, MAC_SIZE, PLATFORM_ID, PREAMBLE_SIZE, RANDOM_NUMBER_SIZE,
+};
+use crate::utils::rc4::Rc4;
+use crate::PduParsing;
+
+const LICENSE_INFO_STATIC_FIELDS_SIZE: usize = 8;
+
+/// The Client License Information PDU, sent instead of the Client New License Request
+/// when the client holds a license previously issued by the server.
+#[derive(Debug, PartialEq, Eq)]
+pub struct ClientLicenseInformation {
+    pub license_header: LicenseHeader,
+    pub client_random: Vec<u8>,
+    pub encrypted_premaster_secret: Vec<u8>,
+    pub license_info: Vec<u8>,
+    pub encrypted_hwid: Vec<u8>,
+    pub mac_data: Vec<u8>,
+}
+
+impl ClientLicenseInformation {
+    /// Builds the PDU presenting the `license_info` of a [`NewLicenseInformation`](super::NewLicenseInformation)
+    /// previously received, with the hardware ID of `hostname` to which the license is bound.
+    pub fn from_server_license_request(
+        license_request: &ServerLicenseRequest,
+        client_random: &[u8],
+        premaster_secret: &[u8],
+        license_info: &[u8],
+        hostname: &str,
+    ) -> Result<(Self, LicenseEncryptionData), ServerLicenseError> {
+        let (encrypted_premaster_secret, encryption_data) =
+            encrypt_premaster_secret(license_request, client_random, premaster_secret)?;
+
+        let hardware_id = compute_hardware_id(hostname);
+        let mut rc4 = Rc4::new(&encryption_data.license_key);
+        let encrypted_hwid = rc4.process(&hardware_id);
+        let mac_data = super::compute_mac_data(encryption_data.mac_salt_key.as_slice(), hardware_id.as_slice());
+
+        let license_header = LicenseHeader {
+            security_header: BasicSecurityHeader {
+                flags: BasicSecurityHeaderFlags::LICENSE_PKT,
+            },
+            preamble_message_type: PreambleType::LicenseInfo,
+            preamble_flags: PreambleFlags::empty(),
+            preamble_version: PreambleVersion::V3,
+            preamble_message_size: (PREAMBLE_SIZE
+                + LICENSE_INFO_STATIC_FIELDS_SIZE
+                + RANDOM_NUMBER_SIZE
+                + (BLOB_TYPE_SIZE + BLOB_LENGTH_SIZE) * 3 // 3 blobs in this structure
+                + encrypted_premaster_secret.len()
+                + license_info.len()
+                + encrypted_hwid.len()
+                + MAC_SIZE) as u16,
+        };
+
+        Ok((
+            Self {
+                license_header,
+                client_random: Vec::from(client_random),
+                encrypted_premaster_secret,
+                license_info: Vec::from(license_info),
+                encrypted_hwid,
+                mac_data,
+            },
+            encryption_data,
+        ))
+    }
+}
+
+impl PduParsing for ClientLicenseInformation {
+    type Error = ServerLicenseError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        let license_header = LicenseHeader::from_buffer(&mut stream)?;
+        if license_header.preamble_message_type != PreambleType::LicenseInfo {
+            return Err(ServerLicenseError::InvalidPreamble(format!(
+                "Got {:?} but expected {:?}",
+                license_header.preamble_message_type,
+                PreambleType::LicenseInfo
+            )));
+        }
+
+        let key_exchange_algorithm = stream.read_u32::<LittleEndian>()?;
+        if key_exchange_algorithm != KEY_EXCHANGE_ALGORITHM_RSA {
+            return Err(ServerLicenseError::InvalidKeyExchangeValue);
+        }
+
+        let _platform_id = stream.read_u32::<LittleEndian>()?;
+
+        let mut client_random = vec![0u8; RANDOM_NUMBER_SIZE];
+        stream.read_exact(&mut client_random)?;
+
+        let premaster_secret_blob = BlobHeader::read_from_buffer(BlobType::Random, &mut stream)?;
+        let mut encrypted_premaster_secret = vec![0u8; premaster_secret_blob.length];
+        stream.read_exact(&mut encrypted_premaster_secret)?;
+
+        let license_info_blob = BlobHeader::read_from_buffer(BlobType::Data, &mut stream)?;
+        let mut license_info = vec![0u8; license_info_blob.length];
+        stream.read_exact(&mut license_info)?;
+
+        let encrypted_hwid_blob = BlobHeader::read_from_buffer(BlobType::EncryptedData, &mut stream)?;
+        let mut encrypted_hwid = vec![0u8; encrypted_hwid_blob.length];
+        stream.read_exact(&mut encrypted_hwid)?;
+
+        let mut mac_data = vec![0u8; MAC_SIZE];
+        stream.read_exact(&mut mac_data)?;
+
+        Ok(Self {
+            license_header,
+            client_random,
+            encrypted_premaster_secret,
+            license_info,
+            encrypted_hwid,
+            mac_data,
+        })
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        self.license_header.to_buffer(&mut stream)?;
+
+        stream.write_u32::<LittleEndian>(KEY_EXCHANGE_ALGORITHM_RSA)?;
+        stream.write_u32::<LittleEndian>(PLATFORM_ID)?;
+        stream.write_all(&self.client_random)?;
+
+        BlobHeader::new(BlobType::Random, self.encrypted_premaster_secret.len()).write_to_buffer(&mut stream)?;
+        stream.write_all(&self.encrypted_premaster_secret)?;
+
+        BlobHeader::new(BlobType::Data, self.license_info.len()).write_to_buffer(&mut stream)?;
+        stream.write_all(&self.license_info)?;
+
+        BlobHeader::new(BlobType::EncryptedData, self.encrypted_hwid.len()).write_to_buffer(&mut stream)?;
+        stream.write_all(&self.encrypted_hwid)?;
+
+        stream.write_all(&self.mac_data)?;
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        self.license_header.buffer_length()
+            + LICENSE_INFO_STATIC_FIELDS_SIZE
+            + RANDOM_NUMBER_SIZE
+            + (BLOB_TYPE_SIZE + BLOB_LENGTH_SIZE) * 3 // 3 blobs in this structure
+            + self.encrypted_premaster_secret.len()
+            + self.license_info.len()
+            + self.encrypted_hwid.len()
+            + MAC_SIZE
+    }
+}
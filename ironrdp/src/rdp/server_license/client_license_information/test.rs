@@ -0,0 +1,58 @@
+use super::*;
+use crate::rdp::server_license::client_new_license_request::test::{
+    CLIENT_NEW_LICENSE_REQUEST, CLIENT_RANDOM_BUFFER, LICENSE_KEY_BUFFER, PREMASTER_SECRET_BUFFER,
+    SERVER_LICENSE_REQUEST,
+};
+
+const LICENSE_INFO: [u8; 8] = [0x30, 0x82, 0x04, 0x02, 0x06, 0x09, 0x2a, 0x86];
+const CLIENT_HOSTNAME: &str = "sample-machine-name";
+
+fn client_license_information() -> (ClientLicenseInformation, LicenseEncryptionData) {
+    ClientLicenseInformation::from_server_license_request(
+        &SERVER_LICENSE_REQUEST,
+        CLIENT_RANDOM_BUFFER.as_ref(),
+        PREMASTER_SECRET_BUFFER.as_ref(),
+        LICENSE_INFO.as_ref(),
+        CLIENT_HOSTNAME,
+    )
+    .unwrap()
+}
+
+#[test]
+fn client_license_information_creates_correctly() {
+    let (license_information, encryption_data) = client_license_information();
+
+    assert_eq!(encryption_data.license_key, LICENSE_KEY_BUFFER.as_ref());
+    assert_eq!(
+        license_information.encrypted_premaster_secret,
+        CLIENT_NEW_LICENSE_REQUEST.encrypted_premaster_secret
+    );
+    assert_eq!(license_information.license_info, LICENSE_INFO.as_ref());
+
+    let mut rc4 = Rc4::new(&encryption_data.license_key);
+    let hardware_id = rc4.process(&license_information.encrypted_hwid);
+    assert_eq!(hardware_id, compute_hardware_id(CLIENT_HOSTNAME));
+    assert_eq!(&hardware_id[..4], PLATFORM_ID.to_le_bytes().as_ref());
+    assert_eq!(
+        license_information.mac_data,
+        crate::rdp::server_license::compute_mac_data(&encryption_data.mac_salt_key, &hardware_id)
+    );
+}
+
+#[test]
+fn client_license_information_round_trips() {
+    let (license_information, _) = client_license_information();
+
+    let mut buffer = Vec::new();
+    license_information.to_buffer(&mut buffer).unwrap();
+
+    assert_eq!(buffer.len(), license_information.buffer_length());
+    assert_eq!(
+        usize::from(license_information.license_header.preamble_message_size),
+        buffer.len() - crate::rdp::BASIC_SECURITY_HEADER_SIZE
+    );
+    assert_eq!(
+        ClientLicenseInformation::from_buffer(buffer.as_slice()).unwrap(),
+        license_information
+    );
+}
@@ -54,35 +54,8 @@ impl ClientNewLicenseRequest {
         client_username: &str,
         client_machine_name: &str,
     ) -> Result<(Self, LicenseEncryptionData), ServerLicenseError> {
-        let public_key = license_request.get_public_key()?
-            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData,
-                "attempted to retrieve the server public key from a server license request message that does not have a certificate"))?;
-
-        let encrypted_premaster_secret = encrypt_with_public_key(premaster_secret, &public_key)?;
-
-        let master_secret = compute_master_secret(
-            premaster_secret,
-            client_random,
-            license_request.server_random.as_slice(),
-        );
-        let session_key_blob = compute_session_key_blob(
-            master_secret.as_slice(),
-            client_random,
-            license_request.server_random.as_slice(),
-        );
-        let mac_salt_key = &session_key_blob[..16];
-
-        let mut md5 = md5::Md5::new();
-        md5.update(
-            [
-                &session_key_blob[16..32],
-                client_random,
-                license_request.server_random.as_slice(),
-            ]
-            .concat()
-            .as_slice(),
-        );
-        let license_key = md5.finalize().to_vec();
+        let (encrypted_premaster_secret, encryption_data) =
+            encrypt_premaster_secret(license_request, client_random, premaster_secret)?;
 
         let license_header = LicenseHeader {
             security_header: BasicSecurityHeader {
@@ -109,11 +82,7 @@ impl ClientNewLicenseRequest {
                 client_username: client_username.to_string(),
                 client_machine_name: client_machine_name.to_string(),
             },
-            LicenseEncryptionData {
-                premaster_secret: Vec::from(premaster_secret),
-                mac_salt_key: Vec::from(mac_salt_key),
-                license_key,
-            },
+            encryption_data,
         ))
     }
 }
@@ -199,7 +168,54 @@ impl PduParsing for ClientNewLicenseRequest {
     }
 }
 
-fn salted_hash(salt: &[u8], salt_first: &[u8], salt_second: &[u8], input: &[u8]) -> Vec<u8> {
+/// Encrypts the premaster secret with the public key of the server certificate and derives the keys
+/// of the licensing exchange, shared by the Client New License Request and the Client License Information.
+pub(super) fn encrypt_premaster_secret(
+    license_request: &ServerLicenseRequest,
+    client_random: &[u8],
+    premaster_secret: &[u8],
+) -> Result<(Vec<u8>, LicenseEncryptionData), ServerLicenseError> {
+    let public_key = license_request.get_public_key()?
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData,
+            "attempted to retrieve the server public key from a server license request message that does not have a certificate"))?;
+
+    let encrypted_premaster_secret = encrypt_with_public_key(premaster_secret, &public_key)?;
+
+    let master_secret = compute_master_secret(
+        premaster_secret,
+        client_random,
+        license_request.server_random.as_slice(),
+    );
+    let session_key_blob = compute_session_key_blob(
+        master_secret.as_slice(),
+        client_random,
+        license_request.server_random.as_slice(),
+    );
+    let mac_salt_key = &session_key_blob[..16];
+
+    let mut md5 = md5::Md5::new();
+    md5.update(
+        [
+            &session_key_blob[16..32],
+            client_random,
+            license_request.server_random.as_slice(),
+        ]
+        .concat()
+        .as_slice(),
+    );
+    let license_key = md5.finalize().to_vec();
+
+    Ok((
+        encrypted_premaster_secret,
+        LicenseEncryptionData {
+            premaster_secret: Vec::from(premaster_secret),
+            mac_salt_key: Vec::from(mac_salt_key),
+            license_key,
+        },
+    ))
+}
+
+pub(crate) fn salted_hash(salt: &[u8], salt_first: &[u8], salt_second: &[u8], input: &[u8]) -> Vec<u8> {
     let sha_result = digest::digest(
         &digest::SHA1_FOR_LEGACY_USE_ONLY,
         [input, salt, salt_first, salt_second].concat().as_slice(),
@@ -11,7 +11,7 @@ const LICENSE_HEADER_BUFFER_NO_SIZE: [u8; 6] = [
     0x13, 0x03,
 ];
 
-const CLIENT_RANDOM_BUFFER: [u8; 32] = [
+pub const CLIENT_RANDOM_BUFFER: [u8; 32] = [
     0x4b, 0x5b, 0x7b, 0x43, 0x63, 0x8a, 0x8, 0xfe, 0xd1, 0x7a, 0xba, 0xf5, 0x91, 0x85, 0x77, 0xfe, 0x39, 0x36, 0xf6,
     0xd7, 0x78, 0xec, 0x6a, 0xcc, 0x89, 0x4a, 0x90, 0x41, 0x2c, 0xac, 0x5a, 0x49,
 ];
@@ -28,7 +28,7 @@ const ENCRYPTED_PREMASTER_SECRET: [u8; 72] = [
     0xb, 0xb, 0xc9, 0xd, 0x1c, 0xe7, 0x17, 0, 0, 0, 0, 0, 0, 0, 0,
 ];
 
-const PREMASTER_SECRET_BUFFER: [u8; 48] = [
+pub const PREMASTER_SECRET_BUFFER: [u8; 48] = [
     0x14, 0x28, 0xda, 0xfb, 0xb9, 0xea, 0x38, 0xab, 0x5e, 0xa2, 0xf9, 0x4, 0xf7, 0x89, 0x9c, 0x98, 0x3d, 0x50, 0x45,
     0x77, 0xbf, 0x17, 0x81, 0x1c, 0x37, 0x87, 0xc2, 0x48, 0x13, 0xe8, 0xc9, 0x20, 0x4d, 0xdf, 0xf3, 0x27, 0xbd, 0xb6,
     0x98, 0x7e, 0x64, 0xda, 0xfe, 0x1d, 0x31, 0x2f, 0x62, 0xca,
@@ -50,7 +50,7 @@ const SESSION_KEY_BLOB: [u8; 48] = [
     0x6, 0xd, 0x5b, 0xaa, 0x63, 0x14, 0xaf, 0xa5, 0x46, 0xf,
 ];
 
-const LICENSE_KEY_BUFFER: [u8; 16] = [
+pub const LICENSE_KEY_BUFFER: [u8; 16] = [
     0xfa, 0x44, 0xe8, 0x78, 0xd8, 0x2b, 0x3f, 0x1d, 0x4d, 0x0, 0xa0, 0xa6, 0x55, 0xce, 0x8a, 0xb7,
 ];
 
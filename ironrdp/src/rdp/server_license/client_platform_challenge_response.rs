@@ -53,13 +53,7 @@ impl ClientPlatformChallengeResponse {
         challenge_response_data.write_u16::<LittleEndian>(decrypted_challenge.len() as u16)?;
         challenge_response_data.write_all(&decrypted_challenge)?;
 
-        let mut hardware_id = Vec::with_capacity(CLIENT_HARDWARE_IDENTIFICATION_SIZE);
-        let mut md5 = md5::Md5::new();
-        md5.update(hostname.as_bytes());
-        let hardware_data = &md5.finalize();
-
-        hardware_id.write_u32::<LittleEndian>(PLATFORM_ID)?;
-        hardware_id.write_all(hardware_data)?;
+        let hardware_id = compute_hardware_id(hostname);
 
         let mut rc4 = Rc4::new(&encryption_data.license_key);
         let encrypted_hwid = rc4.process(&hardware_id);
@@ -95,6 +89,19 @@ impl ClientPlatformChallengeResponse {
     }
 }
 
+/// The Client Hardware Identification, the platform ID followed by the MD5 hash of the client host name,
+/// to which the per-device licenses are bound, so the host name is to be stable across the connections.
+pub(super) fn compute_hardware_id(hostname: &str) -> Vec<u8> {
+    let mut md5 = md5::Md5::new();
+    md5.update(hostname.as_bytes());
+
+    let mut hardware_id = Vec::with_capacity(CLIENT_HARDWARE_IDENTIFICATION_SIZE);
+    hardware_id.extend_from_slice(&PLATFORM_ID.to_le_bytes());
+    hardware_id.extend_from_slice(&md5.finalize());
+
+    hardware_id
+}
+
 impl PduParsing for ClientPlatformChallengeResponse {
     type Error = ServerLicenseError;
 
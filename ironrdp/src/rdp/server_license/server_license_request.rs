@@ -14,6 +14,7 @@ use super::{
     BLOB_LENGTH_SIZE, BLOB_TYPE_SIZE, KEY_EXCHANGE_ALGORITHM_RSA, PREAMBLE_SIZE, RANDOM_NUMBER_SIZE,
     UTF16_NULL_TERMINATOR_SIZE, UTF8_NULL_TERMINATOR_SIZE,
 };
+use crate::utils::rsa::encrypt_with_public_key;
 use crate::{utils, PduParsing};
 
 const CERT_VERSION_FIELD_SIZE: usize = 4;
@@ -284,6 +285,15 @@ impl ServerCertificate {
             }
         }
     }
+
+    /// Encrypts the message with the public key of the certificate, e.g. the client random
+    /// of the Client Security Exchange PDU.
+    pub fn encrypt(&self, message: &[u8]) -> Result<Vec<u8>, ServerLicenseError> {
+        match &self.certificate {
+            CertificateType::Proprietary(certificate) => certificate.public_key.encrypt(message),
+            CertificateType::X509(_) => Ok(encrypt_with_public_key(message, &self.get_public_key()?)?),
+        }
+    }
 }
 
 impl PduParsing for ServerCertificate {
@@ -1,6 +1,7 @@
 use std::io;
 
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use num_bigint::BigUint;
 
 use super::{BlobHeader, BlobType, ServerLicenseError, KEY_EXCHANGE_ALGORITHM_RSA};
 use crate::PduParsing;
@@ -138,6 +139,29 @@ pub struct RsaPublicKey {
     pub modulus: Vec<u8>,
 }
 
+impl RsaPublicKey {
+    /// Encrypts the little-endian message with raw RSA, the result being padded with zeros
+    /// to the length of the modulus, which includes 8 bytes of padding.
+    ///
+    /// The message is to be smaller than the modulus, for it to be recovered from the result.
+    pub fn encrypt(&self, message: &[u8]) -> Result<Vec<u8>, ServerLicenseError> {
+        let n = BigUint::from_bytes_le(&self.modulus);
+        let e = BigUint::from(self.public_exponent);
+        let m = BigUint::from_bytes_le(message);
+        if m >= n {
+            return Err(ServerLicenseError::InvalidRsaPublicKeyModulus);
+        }
+        if self.public_exponent == 0 {
+            return Err(ServerLicenseError::InvalidRsaPublicKeyExponent);
+        }
+
+        let mut encrypted = m.modpow(&e, &n).to_bytes_le();
+        encrypted.resize(self.modulus.len(), 0);
+
+        Ok(encrypted)
+    }
+}
+
 impl PduParsing for RsaPublicKey {
     type Error = ServerLicenseError;
 
@@ -150,7 +174,7 @@ impl PduParsing for RsaPublicKey {
         let keylen = stream.read_u32::<LittleEndian>()?;
 
         let bitlen = stream.read_u32::<LittleEndian>()?;
-        if keylen != (bitlen / 8) + 8 {
+        if bitlen / 8 == 0 || keylen != (bitlen / 8) + 8 {
             return Err(ServerLicenseError::InvalidRsaPublicKeyLength);
         }
 
@@ -160,9 +184,15 @@ impl PduParsing for RsaPublicKey {
         }
 
         let public_exponent = stream.read_u32::<LittleEndian>()?;
+        if public_exponent == 0 {
+            return Err(ServerLicenseError::InvalidRsaPublicKeyExponent);
+        }
 
         let mut modulus = vec![0u8; keylen as usize];
         stream.read_exact(&mut modulus)?;
+        if modulus.iter().all(|&byte| byte == 0) {
+            return Err(ServerLicenseError::InvalidRsaPublicKeyModulus);
+        }
 
         Ok(Self {
             public_exponent,
@@ -172,8 +202,12 @@ impl PduParsing for RsaPublicKey {
 
     fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
         let keylen = self.modulus.len() as u32;
-        let bitlen = (keylen - RSA_KEY_PADDING_LENGTH) * 8;
-        let datalen = bitlen / 8 - 1;
+        let modulus_length = keylen
+            .checked_sub(RSA_KEY_PADDING_LENGTH)
+            .filter(|&length| length > 0)
+            .ok_or(ServerLicenseError::InvalidRsaPublicKeyLength)?;
+        let bitlen = modulus_length * 8;
+        let datalen = modulus_length - 1;
 
         stream.write_u32::<LittleEndian>(RSA_SENTINEL)?; // magic
         stream.write_u32::<LittleEndian>(keylen)?;
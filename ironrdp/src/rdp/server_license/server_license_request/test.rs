@@ -393,6 +393,78 @@ fn to_buffer_correctly_serializes_rsa_public_key() {
     assert_eq!(&serialized_rsa_key, &buffer);
 }
 
+#[test]
+fn from_buffer_fails_on_rsa_public_key_without_modulus() {
+    let buffer = [&MAGIC[..], &[0x08, 0, 0, 0], &[0; 4], &[0xff; 4], &PUB_EXP[..], &[0; 8]].concat();
+
+    assert!(matches!(
+        RsaPublicKey::from_buffer(&mut buffer.as_slice()),
+        Err(ServerLicenseError::InvalidRsaPublicKeyLength)
+    ));
+}
+
+#[test]
+fn from_buffer_fails_on_rsa_public_key_with_zero_exponent() {
+    let buffer = [
+        &MAGIC[..],
+        &KEYLEN[..],
+        &BITLEN[..],
+        &DATALEN[..],
+        &[0; 4],
+        &MODULUS[..],
+    ]
+    .concat();
+
+    assert!(matches!(
+        RsaPublicKey::from_buffer(&mut buffer.as_slice()),
+        Err(ServerLicenseError::InvalidRsaPublicKeyExponent)
+    ));
+}
+
+#[test]
+fn from_buffer_fails_on_rsa_public_key_with_zero_modulus() {
+    let buffer = [
+        &MAGIC[..],
+        &KEYLEN[..],
+        &BITLEN[..],
+        &DATALEN[..],
+        &PUB_EXP[..],
+        &[0; 72],
+    ]
+    .concat();
+
+    assert!(matches!(
+        RsaPublicKey::from_buffer(&mut buffer.as_slice()),
+        Err(ServerLicenseError::InvalidRsaPublicKeyModulus)
+    ));
+}
+
+#[test]
+fn encrypt_fails_with_modulus_shorter_than_message() {
+    let public_key = RsaPublicKey {
+        public_exponent: 0x0001_0001,
+        modulus: vec![0xff; 16],
+    };
+
+    assert!(matches!(
+        public_key.encrypt(&[0x01; 32]),
+        Err(ServerLicenseError::InvalidRsaPublicKeyModulus)
+    ));
+}
+
+#[test]
+fn encrypt_fails_with_zero_modulus() {
+    let public_key = RsaPublicKey {
+        public_exponent: 0x0001_0001,
+        modulus: vec![0; 72],
+    };
+
+    assert!(matches!(
+        public_key.encrypt(&[0x01; 32]),
+        Err(ServerLicenseError::InvalidRsaPublicKeyModulus)
+    ));
+}
+
 #[test]
 fn buffer_length_is_correct_for_rsa_public_key() {
     assert_eq!(PUBLIC_KEY.buffer_length(), RSA_KEY_SIZE_WITHOUT_MODULUS + MODULUS.len());
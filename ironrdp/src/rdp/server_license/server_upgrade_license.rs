@@ -24,6 +24,24 @@ pub struct ServerUpgradeLicense {
 
 impl ServerUpgradeLicense {
     pub fn verify_server_license(&self, encryption_data: &LicenseEncryptionData) -> Result<(), ServerLicenseError> {
+        self.decrypt_license_info_data(encryption_data).map(|_| ())
+    }
+
+    /// Decrypts the license issued by the server, once its MAC has been verified,
+    /// for the license to be stored and sent back in the Client License Information of the next connections.
+    pub fn decrypt_license_info(
+        &self,
+        encryption_data: &LicenseEncryptionData,
+    ) -> Result<NewLicenseInformation, ServerLicenseError> {
+        let decrypted_license_info = self.decrypt_license_info_data(encryption_data)?;
+
+        NewLicenseInformation::from_buffer(decrypted_license_info.as_slice())
+    }
+
+    fn decrypt_license_info_data(
+        &self,
+        encryption_data: &LicenseEncryptionData,
+    ) -> Result<Vec<u8>, ServerLicenseError> {
         let mut rc4 = Rc4::new(encryption_data.license_key.as_slice());
         let decrypted_license_info = rc4.process(self.encrypted_license_info.as_slice());
         let mac_data =
@@ -33,7 +51,7 @@ impl ServerUpgradeLicense {
             return Err(ServerLicenseError::InvalidMacData);
         }
 
-        Ok(())
+        Ok(decrypted_license_info)
     }
 }
 
@@ -89,7 +107,7 @@ impl PduParsing for ServerUpgradeLicense {
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct NewLicenseInformation {
     pub version: u32,
     pub scope: String,
@@ -650,3 +650,48 @@ fn upgrade_license_verifies_correctly() {
 
     upgrade_license.verify_server_license(&encryption_info).unwrap();
 }
+
+#[test]
+fn upgrade_license_decrypts_new_license_information() {
+    let license_key = [
+        0x88, 0x7d, 0x33, 0xa6, 0x13, 0xd, 0x76, 0xbf, 0x76, 0x2a, 0xf, 0x57, 0x71, 0x1d, 0x40, 0xa3,
+    ];
+    let mac_salt_key = [
+        0xd5, 0x2c, 0x7c, 0xd2, 0x71, 0x15, 0x2c, 0x41, 0xbb, 0xd8, 0x36, 0xdb, 0x19, 0x3e, 0xc0, 0xf3,
+    ];
+    let encryption_info = LicenseEncryptionData {
+        premaster_secret: Vec::new(), // this field is not involved in this unit test
+        mac_salt_key: mac_salt_key.to_vec(),
+        license_key: license_key.to_vec(),
+    };
+
+    let mut rc4 = Rc4::new(&license_key);
+    let mut upgrade_license = ServerUpgradeLicense {
+        license_header: LicenseHeader {
+            security_header: BasicSecurityHeader {
+                flags: BasicSecurityHeaderFlags::LICENSE_PKT,
+            },
+            preamble_message_type: PreambleType::NewLicense,
+            preamble_flags: PreambleFlags::empty(),
+            preamble_version: PreambleVersion::V3,
+            preamble_message_size: (PREAMBLE_SIZE
+                + BLOB_LENGTH_SIZE
+                + BLOB_TYPE_SIZE
+                + NEW_LICENSE_INFORMATION_BUFFER.len()
+                + MAC_SIZE) as u16,
+        },
+        encrypted_license_info: rc4.process(NEW_LICENSE_INFORMATION_BUFFER.as_ref()),
+        mac_data: crate::rdp::server_license::compute_mac_data(&mac_salt_key, NEW_LICENSE_INFORMATION_BUFFER.as_ref()),
+    };
+
+    assert_eq!(
+        upgrade_license.decrypt_license_info(&encryption_info).unwrap(),
+        *NEW_LICENSE_INFORMATION
+    );
+
+    upgrade_license.mac_data[0] ^= 0xff;
+    assert!(matches!(
+        upgrade_license.decrypt_license_info(&encryption_info),
+        Err(ServerLicenseError::InvalidMacData)
+    ));
+}
@@ -0,0 +1,340 @@
+#[cfg(test)]
+mod test;
+
+use std::io;
+
+use bitflags::bitflags;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use thiserror::Error;
+
+use crate::{impl_from_error, utils, PduParsing};
+
+const SEC_REDIRECTION_PKT: u16 = 0x0400;
+const SERVER_REDIRECTION_HEADER_SIZE: usize = 2 * 2 + 4 * 2;
+const FIELD_LENGTH_SIZE: usize = 4;
+const ADDRESS_COUNT_SIZE: usize = 4;
+const NULL_TERMINATOR_SIZE: usize = 2;
+const ROUTING_TOKEN_PREFIX: &[u8] = b"Cookie: msts=";
+
+bitflags! {
+    pub struct ServerRedirectionFlags: u32 {
+        const TARGET_NET_ADDRESS = 0x0000_0001;
+        const LOAD_BALANCE_INFO = 0x0000_0002;
+        const USERNAME = 0x0000_0004;
+        const DOMAIN = 0x0000_0008;
+        const PASSWORD = 0x0000_0010;
+        const DONT_STORE_USERNAME = 0x0000_0020;
+        const SMARTCARD_LOGON = 0x0000_0040;
+        const NO_REDIRECT = 0x0000_0080;
+        const TARGET_FQDN = 0x0000_0100;
+        const TARGET_NETBIOS_NAME = 0x0000_0200;
+        const TARGET_NET_ADDRESSES = 0x0000_0800;
+        const CLIENT_TSV_URL = 0x0000_1000;
+        const SERVER_TSV_CAPABLE = 0x0000_2000;
+        const PASSWORD_IS_PK_ENCRYPTED = 0x0000_4000;
+        const REDIRECTION_GUID = 0x0000_8000;
+        const TARGET_CERTIFICATE = 0x0001_0000;
+    }
+}
+
+impl ServerRedirectionFlags {
+    /// The flags telling which fields are present, set by [`ServerRedirectionPdu::to_buffer`]
+    /// from the fields themselves.
+    const FIELDS: Self = Self::from_bits_truncate(
+        Self::TARGET_NET_ADDRESS.bits
+            | Self::LOAD_BALANCE_INFO.bits
+            | Self::USERNAME.bits
+            | Self::DOMAIN.bits
+            | Self::PASSWORD.bits
+            | Self::TARGET_FQDN.bits
+            | Self::TARGET_NETBIOS_NAME.bits
+            | Self::TARGET_NET_ADDRESSES.bits
+            | Self::CLIENT_TSV_URL.bits
+            | Self::REDIRECTION_GUID.bits
+            | Self::TARGET_CERTIFICATE.bits,
+    );
+}
+
+/// Sent by a connection broker, or by the server the broker has connected the client to,
+/// for the client to reconnect to the server hosting the session of the user.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerRedirectionPdu {
+    pub session_id: u32,
+    /// The flags which do not tell the presence of a field, e.g. [`ServerRedirectionFlags::NO_REDIRECT`].
+    pub flags: ServerRedirectionFlags,
+    pub target_net_address: Option<String>,
+    /// The routing token to send in the X.224 Connection Request to the target, see [`Self::routing_token`].
+    pub load_balance_info: Option<Vec<u8>>,
+    pub username: Option<String>,
+    pub domain: Option<String>,
+    /// Opaque to the client, to be sent back over RDSTLS along with [`Self::redirection_guid`].
+    pub password: Option<Vec<u8>>,
+    pub target_fqdn: Option<String>,
+    pub target_netbios_name: Option<String>,
+    pub tsv_url: Option<Vec<u8>>,
+    pub redirection_guid: Option<Vec<u8>>,
+    pub target_certificate: Option<Vec<u8>>,
+    pub target_net_addresses: Option<Vec<String>>,
+}
+
+impl ServerRedirectionPdu {
+    /// Returns the routing token of the load balance info, without the `Cookie: msts=` prefix
+    /// and the trailing CR LF, which are added back to the X.224 Connection Request.
+    pub fn routing_token(&self) -> Option<String> {
+        let load_balance_info = self.load_balance_info.as_deref()?;
+        let routing_token = load_balance_info
+            .strip_prefix(ROUTING_TOKEN_PREFIX)
+            .unwrap_or(load_balance_info);
+        let routing_token = routing_token.strip_suffix(b"\r\n").unwrap_or(routing_token);
+
+        Some(String::from_utf8_lossy(routing_token).into_owned())
+    }
+
+    fn field_flags(&self) -> ServerRedirectionFlags {
+        let fields = [
+            (
+                self.target_net_address.is_some(),
+                ServerRedirectionFlags::TARGET_NET_ADDRESS,
+            ),
+            (
+                self.load_balance_info.is_some(),
+                ServerRedirectionFlags::LOAD_BALANCE_INFO,
+            ),
+            (self.username.is_some(), ServerRedirectionFlags::USERNAME),
+            (self.domain.is_some(), ServerRedirectionFlags::DOMAIN),
+            (self.password.is_some(), ServerRedirectionFlags::PASSWORD),
+            (self.target_fqdn.is_some(), ServerRedirectionFlags::TARGET_FQDN),
+            (
+                self.target_netbios_name.is_some(),
+                ServerRedirectionFlags::TARGET_NETBIOS_NAME,
+            ),
+            (self.tsv_url.is_some(), ServerRedirectionFlags::CLIENT_TSV_URL),
+            (
+                self.redirection_guid.is_some(),
+                ServerRedirectionFlags::REDIRECTION_GUID,
+            ),
+            (
+                self.target_certificate.is_some(),
+                ServerRedirectionFlags::TARGET_CERTIFICATE,
+            ),
+            (
+                self.target_net_addresses.is_some(),
+                ServerRedirectionFlags::TARGET_NET_ADDRESSES,
+            ),
+        ];
+
+        fields
+            .iter()
+            .filter(|(present, _)| *present)
+            .fold(ServerRedirectionFlags::empty(), |flags, (_, flag)| flags | *flag)
+    }
+
+    fn fields_length(&self) -> usize {
+        let strings = [
+            &self.target_net_address,
+            &self.username,
+            &self.domain,
+            &self.target_fqdn,
+            &self.target_netbios_name,
+        ];
+        let blobs = [
+            &self.load_balance_info,
+            &self.password,
+            &self.tsv_url,
+            &self.redirection_guid,
+            &self.target_certificate,
+        ];
+
+        strings
+            .iter()
+            .filter_map(|value| value.as_deref())
+            .map(|value| FIELD_LENGTH_SIZE + string_length(value))
+            .chain(
+                blobs
+                    .iter()
+                    .filter_map(|value| value.as_deref())
+                    .map(|value| FIELD_LENGTH_SIZE + value.len()),
+            )
+            .chain(
+                self.target_net_addresses
+                    .as_deref()
+                    .map(|addresses| FIELD_LENGTH_SIZE + target_net_addresses_length(addresses)),
+            )
+            .sum()
+    }
+}
+
+impl PduParsing for ServerRedirectionPdu {
+    type Error = ServerRedirectionError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        let security_flags = stream.read_u16::<LittleEndian>()?;
+        if security_flags != SEC_REDIRECTION_PKT {
+            return Err(ServerRedirectionError::UnexpectedSecurityFlags(security_flags));
+        }
+
+        // the optional padding following the fields, included in the length, is left to the caller
+        let _length = stream.read_u16::<LittleEndian>()?;
+        let session_id = stream.read_u32::<LittleEndian>()?;
+        let flags = ServerRedirectionFlags::from_bits_truncate(stream.read_u32::<LittleEndian>()?);
+
+        let target_net_address = read_optional_string(&mut stream, flags, ServerRedirectionFlags::TARGET_NET_ADDRESS)?;
+        let load_balance_info = read_optional_data(&mut stream, flags, ServerRedirectionFlags::LOAD_BALANCE_INFO)?;
+        let username = read_optional_string(&mut stream, flags, ServerRedirectionFlags::USERNAME)?;
+        let domain = read_optional_string(&mut stream, flags, ServerRedirectionFlags::DOMAIN)?;
+        let password = read_optional_data(&mut stream, flags, ServerRedirectionFlags::PASSWORD)?;
+        let target_fqdn = read_optional_string(&mut stream, flags, ServerRedirectionFlags::TARGET_FQDN)?;
+        let target_netbios_name =
+            read_optional_string(&mut stream, flags, ServerRedirectionFlags::TARGET_NETBIOS_NAME)?;
+        let tsv_url = read_optional_data(&mut stream, flags, ServerRedirectionFlags::CLIENT_TSV_URL)?;
+        let redirection_guid = read_optional_data(&mut stream, flags, ServerRedirectionFlags::REDIRECTION_GUID)?;
+        let target_certificate = read_optional_data(&mut stream, flags, ServerRedirectionFlags::TARGET_CERTIFICATE)?;
+        let target_net_addresses =
+            read_optional_data(&mut stream, flags, ServerRedirectionFlags::TARGET_NET_ADDRESSES)?
+                .map(|data| read_target_net_addresses(data.as_slice()))
+                .transpose()?;
+
+        Ok(Self {
+            session_id,
+            flags: flags - ServerRedirectionFlags::FIELDS,
+            target_net_address,
+            load_balance_info,
+            username,
+            domain,
+            password,
+            target_fqdn,
+            target_netbios_name,
+            tsv_url,
+            redirection_guid,
+            target_certificate,
+            target_net_addresses,
+        })
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        let length = self.buffer_length();
+        let length = u16::try_from(length).map_err(|_| ServerRedirectionError::PduTooLong(length))?;
+
+        stream.write_u16::<LittleEndian>(SEC_REDIRECTION_PKT)?;
+        stream.write_u16::<LittleEndian>(length)?;
+        stream.write_u32::<LittleEndian>(self.session_id)?;
+        stream
+            .write_u32::<LittleEndian>(((self.flags - ServerRedirectionFlags::FIELDS) | self.field_flags()).bits())?;
+
+        write_optional_string(&mut stream, self.target_net_address.as_deref())?;
+        write_optional_data(&mut stream, self.load_balance_info.as_deref())?;
+        write_optional_string(&mut stream, self.username.as_deref())?;
+        write_optional_string(&mut stream, self.domain.as_deref())?;
+        write_optional_data(&mut stream, self.password.as_deref())?;
+        write_optional_string(&mut stream, self.target_fqdn.as_deref())?;
+        write_optional_string(&mut stream, self.target_netbios_name.as_deref())?;
+        write_optional_data(&mut stream, self.tsv_url.as_deref())?;
+        write_optional_data(&mut stream, self.redirection_guid.as_deref())?;
+        write_optional_data(&mut stream, self.target_certificate.as_deref())?;
+        if let Some(addresses) = self.target_net_addresses.as_deref() {
+            stream.write_u32::<LittleEndian>(target_net_addresses_length(addresses) as u32)?;
+            stream.write_u32::<LittleEndian>(addresses.len() as u32)?;
+            for address in addresses {
+                write_string(&mut stream, address)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        SERVER_REDIRECTION_HEADER_SIZE + self.fields_length()
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ServerRedirectionError {
+    #[error("IO error: {}", .0)]
+    IOError(#[source] io::Error),
+    #[error("Unexpected security flags: {:#06x}", .0)]
+    UnexpectedSecurityFlags(u16),
+    #[error("The PDU is too long to be encoded: {} bytes", .0)]
+    PduTooLong(usize),
+}
+
+impl_from_error!(io::Error, ServerRedirectionError, ServerRedirectionError::IOError);
+
+fn read_optional_data(
+    stream: impl io::Read,
+    flags: ServerRedirectionFlags,
+    flag: ServerRedirectionFlags,
+) -> Result<Option<Vec<u8>>, ServerRedirectionError> {
+    if flags.contains(flag) {
+        Ok(Some(read_data(stream)?))
+    } else {
+        Ok(None)
+    }
+}
+
+fn read_optional_string(
+    stream: impl io::Read,
+    flags: ServerRedirectionFlags,
+    flag: ServerRedirectionFlags,
+) -> Result<Option<String>, ServerRedirectionError> {
+    if flags.contains(flag) {
+        Ok(Some(read_string(stream)?))
+    } else {
+        Ok(None)
+    }
+}
+
+fn read_data(mut stream: impl io::Read) -> Result<Vec<u8>, ServerRedirectionError> {
+    let length = stream.read_u32::<LittleEndian>()?;
+    let mut data = vec![0; length as usize];
+    stream.read_exact(&mut data)?;
+
+    Ok(data)
+}
+
+/// Reads a null-terminated UTF-16 string, whose length includes the null terminator.
+fn read_string(stream: impl io::Read) -> Result<String, ServerRedirectionError> {
+    let data = read_data(stream)?;
+
+    Ok(utils::bytes_to_utf16_string(&data).trim_end_matches('\0').into())
+}
+
+fn read_target_net_addresses(mut data: &[u8]) -> Result<Vec<String>, ServerRedirectionError> {
+    let address_count = data.read_u32::<LittleEndian>()?;
+
+    (0..address_count).map(|_| read_string(&mut data)).collect()
+}
+
+fn write_optional_data(mut stream: impl io::Write, data: Option<&[u8]>) -> Result<(), ServerRedirectionError> {
+    if let Some(data) = data {
+        stream.write_u32::<LittleEndian>(data.len() as u32)?;
+        stream.write_all(data)?;
+    }
+
+    Ok(())
+}
+
+fn write_optional_string(stream: impl io::Write, value: Option<&str>) -> Result<(), ServerRedirectionError> {
+    match value {
+        Some(value) => write_string(stream, value),
+        None => Ok(()),
+    }
+}
+
+fn write_string(mut stream: impl io::Write, value: &str) -> Result<(), ServerRedirectionError> {
+    stream.write_u32::<LittleEndian>(string_length(value) as u32)?;
+    utils::write_string_with_null_terminator(&mut stream, value, utils::CharacterSet::Unicode)?;
+
+    Ok(())
+}
+
+fn string_length(value: &str) -> usize {
+    value.encode_utf16().count() * 2 + NULL_TERMINATOR_SIZE
+}
+
+fn target_net_addresses_length(addresses: &[String]) -> usize {
+    ADDRESS_COUNT_SIZE
+        + addresses
+            .iter()
+            .map(|address| FIELD_LENGTH_SIZE + string_length(address))
+            .sum::<usize>()
+}
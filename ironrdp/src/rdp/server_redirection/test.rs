@@ -0,0 +1,102 @@
+use super::*;
+use crate::rdp::{ShareControlHeader, ShareControlPdu};
+
+const SERVER_REDIRECTION_BUFFER: [u8; 78] = [
+    0x00, 0x04, // flags
+    0x4e, 0x00, // length
+    0x01, 0x00, 0x00, 0x00, // session ID
+    0x17, 0x80, 0x00, 0x00, // redirection flags
+    0x12, 0x00, 0x00, 0x00, // target net address length
+    0x31, 0x00, 0x30, 0x00, 0x2e, 0x00, 0x30, 0x00, 0x2e, 0x00, 0x30, 0x00, 0x2e, 0x00, 0x32, 0x00, 0x00,
+    0x00, // target net address
+    0x12, 0x00, 0x00, 0x00, // load balance info length
+    0x43, 0x6f, 0x6f, 0x6b, 0x69, 0x65, 0x3a, 0x20, 0x6d, 0x73, 0x74, 0x73, 0x3d, 0x31, 0x32, 0x33, 0x0d,
+    0x0a, // load balance info
+    0x04, 0x00, 0x00, 0x00, // username length
+    0x75, 0x00, 0x00, 0x00, // username
+    0x04, 0x00, 0x00, 0x00, // password length
+    0x01, 0x02, 0x03, 0x04, // password
+    0x02, 0x00, 0x00, 0x00, // redirection GUID length
+    0xaa, 0xbb, // redirection GUID
+];
+
+fn server_redirection() -> ServerRedirectionPdu {
+    ServerRedirectionPdu {
+        session_id: 1,
+        flags: ServerRedirectionFlags::empty(),
+        target_net_address: Some(String::from("10.0.0.2")),
+        load_balance_info: Some(b"Cookie: msts=123\r\n".to_vec()),
+        username: Some(String::from("u")),
+        domain: None,
+        password: Some(vec![0x01, 0x02, 0x03, 0x04]),
+        target_fqdn: None,
+        target_netbios_name: None,
+        tsv_url: None,
+        redirection_guid: Some(vec![0xaa, 0xbb]),
+        target_certificate: None,
+        target_net_addresses: None,
+    }
+}
+
+#[test]
+fn from_buffer_correctly_parses_server_redirection_pdu() {
+    assert_eq!(
+        server_redirection(),
+        ServerRedirectionPdu::from_buffer(SERVER_REDIRECTION_BUFFER.as_ref()).unwrap()
+    );
+}
+
+#[test]
+fn to_buffer_correctly_serializes_server_redirection_pdu() {
+    let mut buffer = Vec::new();
+    server_redirection().to_buffer(&mut buffer).unwrap();
+
+    assert_eq!(SERVER_REDIRECTION_BUFFER.as_ref(), buffer.as_slice());
+    assert_eq!(SERVER_REDIRECTION_BUFFER.len(), server_redirection().buffer_length());
+}
+
+#[test]
+fn server_redirection_pdu_with_target_net_addresses_round_trips() {
+    let pdu = ServerRedirectionPdu {
+        flags: ServerRedirectionFlags::NO_REDIRECT,
+        target_net_addresses: Some(vec![String::from("10.0.0.2"), String::from("fe80::1")]),
+        ..server_redirection()
+    };
+
+    let mut buffer = Vec::new();
+    pdu.to_buffer(&mut buffer).unwrap();
+
+    assert_eq!(pdu.buffer_length(), buffer.len());
+    assert_eq!(pdu, ServerRedirectionPdu::from_buffer(buffer.as_slice()).unwrap());
+}
+
+#[test]
+fn routing_token_is_stripped_of_cookie_prefix_and_line_ending() {
+    assert_eq!(Some(String::from("123")), server_redirection().routing_token());
+}
+
+#[test]
+fn enhanced_security_server_redirection_is_parsed_with_its_padding() {
+    let mut packet = SERVER_REDIRECTION_BUFFER.to_vec();
+    packet[2] += 8; // length including the padding
+    packet.extend_from_slice(&[0; 8]);
+
+    let total_length = 10 + 2 + packet.len() + 1;
+    let mut buffer = Vec::new();
+    buffer.extend_from_slice(&(total_length as u16).to_le_bytes());
+    buffer.extend_from_slice(&[0x1a, 0x00]); // PDUTYPE_SERVER_REDIR_PKT
+    buffer.extend_from_slice(&[0xea, 0x03]); // PDU source
+    buffer.extend_from_slice(&[0x00, 0x00, 0x00, 0x00]); // share ID
+    buffer.extend_from_slice(&[0x00, 0x00]); // padding
+    buffer.extend_from_slice(&packet);
+    buffer.push(0x00); // padding
+
+    let mut stream = buffer.as_slice();
+    let header = ShareControlHeader::from_buffer(&mut stream).unwrap();
+
+    assert_eq!(
+        ShareControlPdu::ServerRedirection(server_redirection()),
+        header.share_control_pdu
+    );
+    assert!(stream.is_empty());
+}
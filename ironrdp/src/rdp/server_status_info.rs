@@ -0,0 +1,116 @@
+use std::io;
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use num_derive::{FromPrimitive, ToPrimitive};
+use num_traits::{FromPrimitive, ToPrimitive};
+use thiserror::Error;
+
+use crate::{impl_from_error, PduParsing};
+
+const SERVER_STATUS_INFO_PDU_SIZE: usize = 4;
+
+/// Sent by the server while the session is not ready to be displayed yet,
+/// e.g. when it is being located by the connection broker or brought online.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerStatusInfoPdu(pub StatusCode);
+
+impl PduParsing for ServerStatusInfoPdu {
+    type Error = ServerStatusInfoError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        let status_code = stream.read_u32::<LittleEndian>()?;
+        let status_code =
+            StatusCode::from_u32(status_code).ok_or(ServerStatusInfoError::UnexpectedStatusCode(status_code))?;
+
+        Ok(Self(status_code))
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        stream.write_u32::<LittleEndian>(self.0.to_u32().unwrap())?;
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        SERVER_STATUS_INFO_PDU_SIZE
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, FromPrimitive, ToPrimitive)]
+pub enum StatusCode {
+    FindingDestination = 0x0000_0401,
+    LoadingDestination = 0x0000_0402,
+    BringingSessionOnline = 0x0000_0403,
+    RedirectingToDestination = 0x0000_0404,
+    VmLoading = 0x0000_0501,
+    VmWaking = 0x0000_0502,
+    VmStarting = 0x0000_0503,
+    VmStartingMonitoring = 0x0000_0504,
+    VmResuming = 0x0000_0505,
+}
+
+impl StatusCode {
+    pub fn description(self) -> &'static str {
+        match self {
+            Self::FindingDestination => "The server is looking for the destination of the session",
+            Self::LoadingDestination => "The server is loading the destination of the session",
+            Self::BringingSessionOnline => "The server is bringing the session online",
+            Self::RedirectingToDestination => "The server is redirecting the client to the destination",
+            Self::VmLoading => "The virtual machine hosting the session is loading",
+            Self::VmWaking => "The virtual machine hosting the session is waking up",
+            Self::VmStarting => "The virtual machine hosting the session is starting",
+            Self::VmStartingMonitoring => "The virtual machine hosting the session is starting its monitoring",
+            Self::VmResuming => "The virtual machine hosting the session is resuming",
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ServerStatusInfoError {
+    #[error("IO error: {}", .0)]
+    IOError(#[source] io::Error),
+    #[error("Unexpected status code: {}", .0)]
+    UnexpectedStatusCode(u32),
+}
+
+impl_from_error!(io::Error, ServerStatusInfoError, ServerStatusInfoError::IOError);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SERVER_STATUS_INFO_BUFFER: [u8; 4] = [0x03, 0x04, 0x00, 0x00];
+
+    const SERVER_STATUS_INFO: ServerStatusInfoPdu = ServerStatusInfoPdu(StatusCode::BringingSessionOnline);
+
+    #[test]
+    fn from_buffer_correctly_parses_server_status_info() {
+        assert_eq!(
+            SERVER_STATUS_INFO,
+            ServerStatusInfoPdu::from_buffer(SERVER_STATUS_INFO_BUFFER.as_ref()).unwrap()
+        );
+    }
+
+    #[test]
+    fn from_buffer_server_status_info_fails_on_unexpected_status_code() {
+        let buffer = [0x00, 0x06, 0x00, 0x00];
+
+        match ServerStatusInfoPdu::from_buffer(buffer.as_ref()) {
+            Err(ServerStatusInfoError::UnexpectedStatusCode(0x600)) => (),
+            res => panic!("Expected the unexpected status code error, got: {:?}", res),
+        }
+    }
+
+    #[test]
+    fn to_buffer_correctly_serializes_server_status_info() {
+        let mut buffer = Vec::new();
+
+        SERVER_STATUS_INFO.to_buffer(&mut buffer).unwrap();
+        assert_eq!(SERVER_STATUS_INFO_BUFFER.as_ref(), buffer.as_slice());
+    }
+
+    #[test]
+    fn buffer_length_is_correct_for_server_status_info() {
+        assert_eq!(SERVER_STATUS_INFO_BUFFER.len(), SERVER_STATUS_INFO.buffer_length());
+    }
+}
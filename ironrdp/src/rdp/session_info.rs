@@ -1,9 +1,9 @@
 use std::io;
 
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
-use failure::Fail;
 use num_derive::{FromPrimitive, ToPrimitive};
 use num_traits::{FromPrimitive, ToPrimitive};
+use thiserror::Error;
 
 use crate::{impl_from_error, PduParsing};
 
@@ -100,27 +100,27 @@ pub enum InfoData {
     LogonExtended(LogonInfoExtended),
 }
 
-#[derive(Debug, Fail)]
+#[derive(Debug, Error)]
 pub enum SessionError {
-    #[fail(display = "IO error: {}", _0)]
-    IOError(#[fail(cause)] io::Error),
-    #[fail(display = "Invalid save session info type value")]
+    #[error("IO error: {}", .0)]
+    IOError(#[source] io::Error),
+    #[error("Invalid save session info type value")]
     InvalidSaveSessionInfoType,
-    #[fail(display = "Invalid domain name size value")]
+    #[error("Invalid domain name size value")]
     InvalidDomainNameSize,
-    #[fail(display = "Invalid user name size value")]
+    #[error("Invalid user name size value")]
     InvalidUserNameSize,
-    #[fail(display = "Invalid logon version value")]
+    #[error("Invalid logon version value")]
     InvalidLogonVersion2,
-    #[fail(display = "Invalid logon info version2 size value")]
+    #[error("Invalid logon info version2 size value")]
     InvalidLogonVersion2Size,
-    #[fail(display = "Invalid server auto-reconnect packet size value")]
+    #[error("Invalid server auto-reconnect packet size value")]
     InvalidAutoReconnectPacketSize,
-    #[fail(display = "Invalid server auto-reconnect version")]
+    #[error("Invalid server auto-reconnect version")]
     InvalidAutoReconnectVersion,
-    #[fail(display = "Invalid logon error type value")]
+    #[error("Invalid logon error type value")]
     InvalidLogonErrorType,
-    #[fail(display = "Invalid logon error data value")]
+    #[error("Invalid logon error data value")]
     InvalidLogonErrorData,
 }
 
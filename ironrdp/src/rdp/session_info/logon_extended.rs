@@ -1,7 +1,8 @@
 use std::io;
 
 use bitflags::bitflags;
-use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use byteorder::{ByteOrder as _, LittleEndian, ReadBytesExt, WriteBytesExt};
+use md5::{Digest as _, Md5};
 use num_derive::{FromPrimitive, ToPrimitive};
 use num_traits::{FromPrimitive, ToPrimitive};
 
@@ -17,6 +18,7 @@ const LOGON_INFO_FIELD_DATA_SIZE: usize = 4;
 const AUTO_RECONNECT_VERSION_1: u32 = 0x0000_0001;
 const AUTO_RECONNECT_PACKET_SIZE: usize = 28;
 const AUTO_RECONNECT_RANDOM_BITS_SIZE: usize = 16;
+const HMAC_MD5_BLOCK_SIZE: usize = 64;
 const LOGON_ERRORS_INFO_SIZE: usize = 8;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -129,6 +131,38 @@ impl PduParsing for ServerAutoReconnect {
     }
 }
 
+impl ServerAutoReconnect {
+    /// Creates the Client Auto-Reconnect Packet, sent as the reconnect cookie of the Client Info PDU
+    /// to reconnect to the session. Its security verifier is the HMAC-MD5 of the client random
+    /// keyed with the random bits, the client random being 32 zero bytes with Enhanced RDP Security.
+    pub fn client_cookie(&self, client_random: &[u8]) -> [u8; AUTO_RECONNECT_PACKET_SIZE] {
+        let mut cookie = [0; AUTO_RECONNECT_PACKET_SIZE];
+        LittleEndian::write_u32(&mut cookie[0..4], AUTO_RECONNECT_PACKET_SIZE as u32);
+        LittleEndian::write_u32(&mut cookie[4..8], AUTO_RECONNECT_VERSION_1);
+        LittleEndian::write_u32(&mut cookie[8..12], self.logon_id);
+        cookie[12..].copy_from_slice(&hmac_md5(&self.random_bits, client_random));
+
+        cookie
+    }
+}
+
+fn hmac_md5(key: &[u8; AUTO_RECONNECT_RANDOM_BITS_SIZE], data: &[u8]) -> [u8; 16] {
+    let mut inner_pad = [0x36; HMAC_MD5_BLOCK_SIZE];
+    let mut outer_pad = [0x5c; HMAC_MD5_BLOCK_SIZE];
+    for (i, byte) in key.iter().enumerate() {
+        inner_pad[i] ^= byte;
+        outer_pad[i] ^= byte;
+    }
+
+    let inner_hash = Md5::new().chain_update(inner_pad).chain_update(data).finalize();
+    let hash = Md5::new().chain_update(outer_pad).chain_update(inner_hash).finalize();
+
+    let mut mac = [0; 16];
+    mac.copy_from_slice(&hash);
+
+    mac
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct LogonErrorsInfo {
     pub error_type: LogonErrorNotificationType,
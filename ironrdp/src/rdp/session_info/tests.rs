@@ -397,3 +397,19 @@ fn from_buffer_parsing_with_invalid_logon_error_data_fails() {
         res => panic!("Expected InvalidLogonErrorData error, got: {:?}", res),
     };
 }
+
+#[test]
+fn client_cookie_has_hmac_md5_of_client_random_as_security_verifier() {
+    let server_auto_reconnect = ServerAutoReconnect {
+        logon_id: 2,
+        random_bits: [0x0b; 16],
+    };
+
+    // RFC 2202 HMAC-MD5 test case 1
+    let expected = [
+        0x1c, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x92, 0x94, 0x72, 0x7a, 0x36, 0x38,
+        0xbb, 0x1c, 0x13, 0xf4, 0x8e, 0xf8, 0x15, 0x8b, 0xfc, 0x9d,
+    ];
+
+    assert_eq!(expected, server_auto_reconnect.client_cookie(b"Hi There"));
+}
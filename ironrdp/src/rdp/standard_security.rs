@@ -0,0 +1,263 @@
+//! Standard RDP Security, in which the client and the server encrypt the MCS payloads with RC4 and sign them
+//! with a MAC, using the session keys derived from the client and server randoms, for the servers not
+//! accepting the TLS based security protocols.
+
+#[cfg(test)]
+pub mod test;
+
+use std::io;
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use md5::Digest;
+use ring::digest;
+use thiserror::Error;
+
+use crate::gcc::EncryptionMethod;
+use crate::rdp::server_license::{compute_mac_data, salted_hash, ServerCertificate, ServerLicenseError};
+use crate::rdp::{BasicSecurityHeader, BasicSecurityHeaderFlags, BASIC_SECURITY_HEADER_SIZE};
+use crate::utils::rc4::Rc4;
+use crate::{impl_from_error, PduParsing};
+
+/// The size of the client and server randoms.
+pub const RANDOM_SIZE: usize = 32;
+pub const SIGNATURE_SIZE: usize = 8;
+/// The size of the security header of the encrypted PDUs, carrying their signature.
+pub const NON_FIPS_SECURITY_HEADER_SIZE: usize = BASIC_SECURITY_HEADER_SIZE + SIGNATURE_SIZE;
+
+const ENCRYPTED_CLIENT_RANDOM_LENGTH_SIZE: usize = 4;
+const RANDOM_PREFIX_SIZE: usize = 24;
+const SALT_40_BIT: [u8; 3] = [0xd1, 0x26, 0x9e];
+const SALT_56_BIT: [u8; 1] = [0xd1];
+/// The number of PDUs encrypted or decrypted with a key before it is updated.
+const KEY_UPDATE_INTERVAL: u32 = 4096;
+
+/// The Client Security Exchange PDU, carrying the client random encrypted with the public key of the server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientSecurityExchangePdu {
+    pub encrypted_client_random: Vec<u8>,
+}
+
+impl ClientSecurityExchangePdu {
+    /// Encrypts the client random with the public key of the certificate of the Server Security Data.
+    pub fn new(server_certificate: &[u8], client_random: &[u8]) -> Result<Self, StandardSecurityError> {
+        let server_certificate = ServerCertificate::from_buffer(server_certificate)?;
+
+        Ok(Self {
+            encrypted_client_random: server_certificate.encrypt(client_random)?,
+        })
+    }
+}
+
+impl PduParsing for ClientSecurityExchangePdu {
+    type Error = StandardSecurityError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        let security_header =
+            BasicSecurityHeader::from_buffer(&mut stream).map_err(|_| StandardSecurityError::InvalidSecurityHeader)?;
+        if !security_header.flags.contains(BasicSecurityHeaderFlags::EXCHANGE_PKT) {
+            return Err(StandardSecurityError::InvalidSecurityHeader);
+        }
+
+        let length = stream.read_u32::<LittleEndian>()?;
+        let mut encrypted_client_random = vec![0; length as usize];
+        stream.read_exact(&mut encrypted_client_random)?;
+
+        Ok(Self {
+            encrypted_client_random,
+        })
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        BasicSecurityHeader {
+            flags: BasicSecurityHeaderFlags::EXCHANGE_PKT,
+        }
+        .to_buffer(&mut stream)
+        .map_err(|_| StandardSecurityError::InvalidSecurityHeader)?;
+        stream.write_u32::<LittleEndian>(self.encrypted_client_random.len() as u32)?;
+        stream.write_all(&self.encrypted_client_random)?;
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        BASIC_SECURITY_HEADER_SIZE + ENCRYPTED_CLIENT_RANDOM_LENGTH_SIZE + self.encrypted_client_random.len()
+    }
+}
+
+/// The ciphers of the client, derived from the client and server randoms for the encryption method
+/// selected by the server.
+#[derive(Debug, Clone)]
+pub struct SessionKeys {
+    /// Encrypts the PDUs sent to the server.
+    pub encrypt: SessionCipher,
+    /// Decrypts the PDUs received from the server.
+    pub decrypt: SessionCipher,
+}
+
+impl SessionKeys {
+    /// Derives the keys as described in [MS-RDPBCGR] 5.3.5.1. The FIPS encryption method is not supported.
+    pub fn derive(
+        method: EncryptionMethod,
+        client_random: &[u8],
+        server_random: &[u8],
+    ) -> Result<Self, StandardSecurityError> {
+        if ![
+            EncryptionMethod::BIT_40,
+            EncryptionMethod::BIT_56,
+            EncryptionMethod::BIT_128,
+        ]
+        .contains(&method)
+        {
+            return Err(StandardSecurityError::UnsupportedEncryptionMethod(method));
+        }
+        if client_random.len() != RANDOM_SIZE || server_random.len() != RANDOM_SIZE {
+            return Err(StandardSecurityError::InvalidRandomLength);
+        }
+
+        let premaster_secret = [
+            &client_random[..RANDOM_PREFIX_SIZE],
+            &server_random[..RANDOM_PREFIX_SIZE],
+        ]
+        .concat();
+        let master_secret = [b"A".as_ref(), b"BB", b"CCC"]
+            .iter()
+            .flat_map(|input| salted_hash(&premaster_secret, client_random, server_random, input))
+            .collect::<Vec<_>>();
+        let session_key_blob = [b"X".as_ref(), b"YY", b"ZZZ"]
+            .iter()
+            .flat_map(|input| salted_hash(&master_secret, client_random, server_random, input))
+            .collect::<Vec<_>>();
+
+        let final_hash = |key: &[u8]| {
+            let mut md5 = md5::Md5::new();
+            md5.update([key, client_random, server_random].concat());
+            md5.finalize().to_vec()
+        };
+
+        let mac_key = reduce_key(method, &session_key_blob[..16]);
+        let decrypt_key = reduce_key(method, &final_hash(&session_key_blob[16..32]));
+        let encrypt_key = reduce_key(method, &final_hash(&session_key_blob[32..48]));
+
+        Ok(Self {
+            encrypt: SessionCipher::new(method, mac_key.clone(), encrypt_key),
+            decrypt: SessionCipher::new(method, mac_key, decrypt_key),
+        })
+    }
+}
+
+/// Encrypts or decrypts the PDUs of one direction with RC4, the key being updated every 4096 PDUs,
+/// and computes their MAC signature.
+#[derive(Debug, Clone)]
+pub struct SessionCipher {
+    method: EncryptionMethod,
+    mac_key: Vec<u8>,
+    initial_key: Vec<u8>,
+    current_key: Vec<u8>,
+    rc4: Rc4,
+    use_count: u32,
+}
+
+impl SessionCipher {
+    fn new(method: EncryptionMethod, mac_key: Vec<u8>, key: Vec<u8>) -> Self {
+        Self {
+            method,
+            mac_key,
+            rc4: Rc4::new(&key),
+            initial_key: key.clone(),
+            current_key: key,
+            use_count: 0,
+        }
+    }
+
+    /// Encrypts the data, returning its signature and the encrypted data.
+    pub fn encrypt(&mut self, data: &[u8]) -> ([u8; SIGNATURE_SIZE], Vec<u8>) {
+        let signature = self.signature(data);
+
+        (signature, self.process(data))
+    }
+
+    /// Decrypts the data, failing if the signature does not match the decrypted data.
+    pub fn decrypt(&mut self, signature: &[u8], data: &[u8]) -> Result<Vec<u8>, StandardSecurityError> {
+        let decrypted = self.process(data);
+        if self.signature(&decrypted) != signature {
+            return Err(StandardSecurityError::InvalidSignature);
+        }
+
+        Ok(decrypted)
+    }
+
+    /// The MAC signature of the data, as described in [MS-RDPBCGR] 5.3.6.1.
+    pub fn signature(&self, data: &[u8]) -> [u8; SIGNATURE_SIZE] {
+        let mut signature = [0; SIGNATURE_SIZE];
+        signature.copy_from_slice(&compute_mac_data(&self.mac_key, data)[..SIGNATURE_SIZE]);
+
+        signature
+    }
+
+    fn process(&mut self, data: &[u8]) -> Vec<u8> {
+        if self.use_count == KEY_UPDATE_INTERVAL {
+            self.update_key();
+        }
+        self.use_count += 1;
+
+        self.rc4.process(data)
+    }
+
+    /// Updates the key as described in [MS-RDPBCGR] 5.3.7.1.
+    fn update_key(&mut self) {
+        let pad_one = [0x36; 40];
+        let pad_two = [0x5c; 48];
+
+        let sha_component = digest::digest(
+            &digest::SHA1_FOR_LEGACY_USE_ONLY,
+            [
+                self.initial_key.as_slice(),
+                pad_one.as_ref(),
+                self.current_key.as_slice(),
+            ]
+            .concat()
+            .as_slice(),
+        );
+        let mut md5 = md5::Md5::new();
+        md5.update([self.initial_key.as_slice(), pad_two.as_ref(), sha_component.as_ref()].concat());
+        let digest = md5.finalize();
+        let temp_key = &digest[..self.initial_key.len()];
+
+        let new_key = Rc4::new(temp_key).process(temp_key);
+        self.current_key = reduce_key(self.method, &new_key);
+        self.rc4 = Rc4::new(&self.current_key);
+        self.use_count = 0;
+    }
+}
+
+/// Reduces the 128-bit key to the strength of the encryption method, the 40-bit and 56-bit keys being salted.
+fn reduce_key(method: EncryptionMethod, key: &[u8]) -> Vec<u8> {
+    match method {
+        EncryptionMethod::BIT_40 => [SALT_40_BIT.as_ref(), &key[3..8]].concat(),
+        EncryptionMethod::BIT_56 => [SALT_56_BIT.as_ref(), &key[1..8]].concat(),
+        _ => key[..16].to_vec(),
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum StandardSecurityError {
+    #[error("IO error: {}", .0)]
+    IOError(#[source] io::Error),
+    #[error("invalid server certificate: {}", .0)]
+    InvalidServerCertificate(#[source] ServerLicenseError),
+    #[error("the {:?} encryption method is not supported", .0)]
+    UnsupportedEncryptionMethod(EncryptionMethod),
+    #[error("the client and server randoms must be 32 bytes long")]
+    InvalidRandomLength,
+    #[error("invalid security header")]
+    InvalidSecurityHeader,
+    #[error("the signature does not match the decrypted data")]
+    InvalidSignature,
+}
+
+impl_from_error!(io::Error, StandardSecurityError, StandardSecurityError::IOError);
+impl_from_error!(
+    ServerLicenseError,
+    StandardSecurityError,
+    StandardSecurityError::InvalidServerCertificate
+);
@@ -0,0 +1,117 @@
+use super::*;
+
+const CLIENT_RANDOM: [u8; RANDOM_SIZE] = [0x11; RANDOM_SIZE];
+const SERVER_RANDOM: [u8; RANDOM_SIZE] = [0x22; RANDOM_SIZE];
+
+// proprietary certificate with the 64-bit textbook key n = 3233, e = 17
+const PROPRIETARY_CERTIFICATE_BUFFER: [u8; 64] = [
+    0x01, 0x00, 0x00, 0x00, // dwVersion
+    0x01, 0x00, 0x00, 0x00, // dwSigAlgId
+    0x01, 0x00, 0x00, 0x00, // dwKeyAlgId
+    0x06, 0x00, // wPublicKeyBlobType
+    0x24, 0x00, // wPublicKeyBlobLen
+    0x52, 0x53, 0x41, 0x31, // magic
+    0x10, 0x00, 0x00, 0x00, // keylen
+    0x40, 0x00, 0x00, 0x00, // bitlen
+    0x07, 0x00, 0x00, 0x00, // datalen
+    0x11, 0x00, 0x00, 0x00, // pubExp
+    0xa1, 0x0c, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // modulus
+    0x08, 0x00, // wSignatureBlobType
+    0x08, 0x00, // wSignatureBlobLen
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // SignatureBlob
+];
+
+fn session_keys(method: EncryptionMethod) -> SessionKeys {
+    SessionKeys::derive(method, CLIENT_RANDOM.as_ref(), SERVER_RANDOM.as_ref()).unwrap()
+}
+
+/// The cipher of the server decrypting what the client encrypts.
+fn server_decrypt(keys: &SessionKeys) -> SessionCipher {
+    SessionCipher::new(
+        keys.encrypt.method,
+        keys.encrypt.mac_key.clone(),
+        keys.encrypt.initial_key.clone(),
+    )
+}
+
+#[test]
+fn session_keys_are_reduced_to_encryption_method() {
+    let keys = session_keys(EncryptionMethod::BIT_40);
+    assert_eq!(8, keys.encrypt.initial_key.len());
+    assert_eq!(SALT_40_BIT, keys.encrypt.initial_key[..3]);
+    assert_eq!(SALT_40_BIT, keys.decrypt.mac_key[..3]);
+
+    let keys = session_keys(EncryptionMethod::BIT_56);
+    assert_eq!(8, keys.decrypt.initial_key.len());
+    assert_eq!(SALT_56_BIT, keys.decrypt.initial_key[..1]);
+
+    let keys = session_keys(EncryptionMethod::BIT_128);
+    assert_eq!(16, keys.encrypt.initial_key.len());
+    assert_eq!(16, keys.encrypt.mac_key.len());
+    assert_ne!(keys.encrypt.initial_key, keys.decrypt.initial_key);
+}
+
+#[test]
+fn encrypted_data_is_decrypted_by_peer_across_key_updates() {
+    for method in [
+        EncryptionMethod::BIT_40,
+        EncryptionMethod::BIT_56,
+        EncryptionMethod::BIT_128,
+    ] {
+        let mut keys = session_keys(method);
+        let mut server = server_decrypt(&keys);
+
+        for index in 0..KEY_UPDATE_INTERVAL + 2 {
+            let data = index.to_le_bytes();
+            let (signature, encrypted) = keys.encrypt.encrypt(&data);
+            assert_ne!(data.as_ref(), encrypted.as_slice());
+            assert_eq!(
+                data.as_ref(),
+                server.decrypt(&signature, &encrypted).unwrap().as_slice()
+            );
+        }
+
+        assert_ne!(keys.encrypt.initial_key, keys.encrypt.current_key);
+        assert_eq!(keys.encrypt.current_key, server.current_key);
+    }
+}
+
+#[test]
+fn tampered_data_fails_signature_check() {
+    let mut keys = session_keys(EncryptionMethod::BIT_128);
+    let mut server = server_decrypt(&keys);
+
+    let (signature, mut encrypted) = keys.encrypt.encrypt(b"share data");
+    encrypted[0] ^= 0xff;
+
+    assert!(matches!(
+        server.decrypt(&signature, &encrypted),
+        Err(StandardSecurityError::InvalidSignature)
+    ));
+}
+
+#[test]
+fn fips_encryption_method_is_not_supported() {
+    assert!(matches!(
+        SessionKeys::derive(EncryptionMethod::FIPS, CLIENT_RANDOM.as_ref(), SERVER_RANDOM.as_ref()),
+        Err(StandardSecurityError::UnsupportedEncryptionMethod(
+            EncryptionMethod::FIPS
+        ))
+    ));
+}
+
+#[test]
+fn client_security_exchange_pdu_encrypts_random_with_proprietary_certificate() {
+    let pdu = ClientSecurityExchangePdu::new(PROPRIETARY_CERTIFICATE_BUFFER.as_ref(), &[65]).unwrap();
+
+    // 65^17 mod 3233 = 2790, padded to the length of the modulus
+    let mut expected = vec![0; 16];
+    expected[..2].copy_from_slice(&2790u16.to_le_bytes());
+    assert_eq!(expected, pdu.encrypted_client_random);
+
+    let mut buffer = Vec::new();
+    pdu.to_buffer(&mut buffer).unwrap();
+    assert_eq!(pdu.buffer_length(), buffer.len());
+    assert_eq!([0x01, 0x00, 0x00, 0x00, 0x10, 0x00, 0x00, 0x00], buffer[..8]);
+    assert_eq!(pdu, ClientSecurityExchangePdu::from_buffer(buffer.as_slice()).unwrap());
+}
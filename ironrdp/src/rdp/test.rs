@@ -1,4 +1,5 @@
 use lazy_static::lazy_static;
+use proptest::prelude::*;
 
 use super::finalization_messages::*;
 use super::headers::*;
@@ -52,6 +53,19 @@ const CLIENT_SYNCHRONIZE_BUFFER: [u8; 22] = [
     0x01, 0x00, // message type
     0xea, 0x03, // target user
 ];
+const COMPRESSED_CLIENT_SYNCHRONIZE_BUFFER: [u8; 23] = [
+    0x17, 0x00, // ShareControlHeader::totalLength
+    0x17, 0x00, // ShareControlHeader::pduType
+    0xef, 0x03, // ShareControlHeader::PduSource
+    0xea, 0x03, 0x01, 0x00, // share id
+    0x00, // padding
+    0x01, // stream id
+    0x08, 0x00, // uncompressed length
+    0x1f, // pdu type
+    0xa1, // compression type
+    0x17, 0x00, // compressed length
+    0x01, 0x00, 0xb5, 0x01, 0x80, // MPPC literals 0x01, 0x00, 0xea and 0x03
+];
 const CONTROL_COOPERATE_BUFFER: [u8; 26] = [
     0x1a, 0x00, // ShareControlHeader::totalLength
     0x17, 0x00, // ShareControlHeader::pduType
@@ -350,6 +364,27 @@ fn from_buffer_correctly_parses_rdp_pdu_client_synchronize() {
     );
 }
 
+#[test]
+fn decompress_correctly_decompresses_rdp_pdu_client_synchronize() {
+    let mut decompressor = bulk::BulkDecompressor::new();
+    let buf = ShareControlHeader::decompress(COMPRESSED_CLIENT_SYNCHRONIZE_BUFFER.as_ref(), &mut decompressor)
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(CLIENT_SYNCHRONIZE_BUFFER.as_ref(), buf.as_slice());
+    assert!(ShareControlHeader::decompress(buf.as_slice(), &mut decompressor)
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn from_buffer_fails_for_compressed_rdp_pdu() {
+    assert!(matches!(
+        ShareControlHeader::from_buffer(COMPRESSED_CLIENT_SYNCHRONIZE_BUFFER.as_ref()),
+        Err(RdpError::InvalidShareDataHeader(_))
+    ));
+}
+
 #[test]
 fn from_buffer_correctly_parses_rdp_pdu_client_control_cooperate() {
     let buf = CONTROL_COOPERATE_BUFFER.as_ref();
@@ -625,3 +660,77 @@ fn buffer_length_is_correct_for_rdp_pdu_server_monitor_layout() {
 
     assert_eq!(expected_buf_len, len);
 }
+
+#[test]
+fn to_buffer_fails_for_refresh_rectangle_beyond_16_bit_coordinates() {
+    let area = crate::Rectangle {
+        left: 0,
+        top: 0,
+        right: u32::from(u16::MAX) + 1,
+        bottom: 1080,
+    };
+    let pdu = RefreshRectanglePdu {
+        areas_to_refresh: vec![area.clone()],
+    };
+
+    assert!(pdu.to_buffer(&mut Vec::new()).is_err());
+    assert_eq!(Some([0, 0, u16::MAX, 1080]), area.clamp_to_u16().to_u16());
+}
+
+crate::round_trip_proptest! {
+    synchronize_pdu_round_trip: SynchronizePdu =
+        any::<u16>().prop_map(|target_user_id| SynchronizePdu { target_user_id });
+    control_pdu_round_trip: ControlPdu = (
+        prop_oneof![
+            Just(ControlAction::RequestControl),
+            Just(ControlAction::GrantedControl),
+            Just(ControlAction::Detach),
+            Just(ControlAction::Cooperate),
+        ],
+        any::<u16>(),
+        any::<u32>(),
+    )
+        .prop_map(|(action, grant_id, control_id)| ControlPdu {
+            action,
+            grant_id,
+            control_id,
+        });
+    font_pdu_round_trip: FontPdu = any::<[u16; 4]>().prop_map(|[number, total_number, flags, entry_size]| FontPdu {
+        number,
+        total_number,
+        flags: SequenceFlags::from_bits_truncate(flags),
+        entry_size,
+    });
+    monitor_layout_pdu_round_trip: MonitorLayoutPdu = prop::collection::vec(gcc::test::any_monitor(), 0..=64)
+        .prop_map(|monitors| MonitorLayoutPdu { monitors });
+    set_keyboard_indicators_pdu_round_trip: SetKeyboardIndicatorsPdu = any::<u16>()
+        .prop_map(|led_flags| SetKeyboardIndicatorsPdu { led_flags: LedFlags::from_bits_truncate(led_flags) });
+    set_keyboard_ime_status_pdu_round_trip: SetKeyboardImeStatusPdu =
+        (prop_oneof![Just(ImeState::Closed), Just(ImeState::Open)], any::<u32>()).prop_map(
+            |(ime_state, ime_conversion_mode)| SetKeyboardImeStatusPdu {
+                ime_state,
+                ime_conversion_mode: ImeConversionMode::from_bits_truncate(ime_conversion_mode),
+            }
+        );
+    server_status_info_pdu_round_trip: ServerStatusInfoPdu = prop_oneof![
+        Just(StatusCode::FindingDestination),
+        Just(StatusCode::LoadingDestination),
+        Just(StatusCode::BringingSessionOnline),
+        Just(StatusCode::RedirectingToDestination),
+        Just(StatusCode::VmLoading),
+        Just(StatusCode::VmWaking),
+        Just(StatusCode::VmStarting),
+        Just(StatusCode::VmStartingMonitoring),
+        Just(StatusCode::VmResuming),
+    ]
+    .prop_map(ServerStatusInfoPdu);
+    refresh_rectangle_pdu_round_trip: RefreshRectanglePdu = prop::collection::vec(any::<[u16; 4]>(), 0..=255)
+        .prop_map(|areas| RefreshRectanglePdu {
+            areas_to_refresh: areas
+                .into_iter()
+                .map(|[left, top, right, bottom]| crate::Rectangle::from_u16(left, top, right, bottom))
+                .collect(),
+        });
+    basic_security_header_round_trip: BasicSecurityHeader = any::<u16>()
+        .prop_map(|flags| BasicSecurityHeader { flags: BasicSecurityHeaderFlags::from_bits_truncate(flags) });
+}
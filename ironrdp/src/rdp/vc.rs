@@ -1,19 +1,31 @@
 pub mod dvc;
+pub mod rdpdr;
+pub mod rdpsnd;
+
+mod channel_name;
 
 #[cfg(test)]
 mod tests;
 
-use std::{io, str};
+use std::collections::HashMap;
+use std::io;
 
 use bitflags::bitflags;
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
-use failure::Fail;
+use num_traits::FromPrimitive;
+use thiserror::Error;
 
-use crate::{impl_from_error, PduParsing};
+pub use self::channel_name::{ChannelNameError, DvcName, StaticChannelName, STATIC_CHANNEL_NAME_MAX_LENGTH};
+use crate::rdp::bulk::mppc::{MppcDecompressor, MppcError};
+use crate::rdp::{CompressionFlags, CompressionType};
+use crate::{impl_from_error, ChannelId, PduParsing};
 
-pub const DRDYNVC_CHANNEL_NAME: &str = "drdynvc";
+#[deprecated(note = "use `StaticChannelName::DRDYNVC` instead")]
+pub const DRDYNVC_CHANNEL_NAME: &str = channel_name::DRDYNVC_NAME;
 
 const CHANNEL_PDU_HEADER_SIZE: usize = 8;
+/// The compression flags and type of the chunk are those of the Share Data Header, shifted by 16 bits.
+const COMPRESSION_FLAGS_SHIFT: u32 = 16;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ChannelPduHeader {
@@ -22,6 +34,20 @@ pub struct ChannelPduHeader {
     pub flags: ChannelControlFlags,
 }
 
+impl ChannelPduHeader {
+    /// The MPPC compression flags of the chunk.
+    pub fn compression_flags(&self) -> CompressionFlags {
+        CompressionFlags::from_bits_truncate((self.flags.bits() >> COMPRESSION_FLAGS_SHIFT) as u8)
+    }
+
+    /// The MPPC compression type of the chunk, `None` if it is not a known one.
+    pub fn compression_type(&self) -> Option<CompressionType> {
+        CompressionType::from_u32(
+            (self.flags & ChannelControlFlags::COMPRESSION_TYPE_MASK).bits() >> COMPRESSION_FLAGS_SHIFT,
+        )
+    }
+}
+
 impl PduParsing for ChannelPduHeader {
     type Error = ChannelError;
 
@@ -59,35 +85,128 @@ bitflags! {
     }
 }
 
-#[derive(Debug, Fail)]
+/// Reassembles the messages of the static virtual channels from their chunks, decompressing the chunks
+/// compressed by the server.
+///
+/// The chunks of all the channels are decompressed with the same MPPC history, the server compressing
+/// the virtual channel data it sends with a single context.
+#[derive(Debug)]
+pub struct ChannelReassembler {
+    decompressor: MppcDecompressor,
+    /// The messages of which the last chunk has not been received yet, by channel.
+    partial_messages: HashMap<ChannelId, PartialMessage>,
+    max_message_size: usize,
+}
+
+impl ChannelReassembler {
+    /// Creates a reassembler failing on the messages larger than `max_message_size` once decompressed.
+    pub fn new(max_message_size: usize) -> Self {
+        Self {
+            decompressor: MppcDecompressor::new(),
+            partial_messages: HashMap::new(),
+            max_message_size,
+        }
+    }
+
+    /// Processes a chunk received on the channel, `data` following its Channel PDU Header, and returns
+    /// the message once its last chunk has been received.
+    ///
+    /// An incomplete message is discarded when the first chunk of the next message of the channel is received.
+    pub fn process(
+        &mut self,
+        channel_id: ChannelId,
+        header: &ChannelPduHeader,
+        data: &[u8],
+    ) -> Result<Option<Vec<u8>>, ChannelError> {
+        let compression_flags = header.compression_flags();
+        let data = if compression_flags.is_empty() {
+            data
+        } else {
+            let compression_type = header.compression_type().ok_or(ChannelError::InvalidChannelPduHeader)?;
+            self.decompressor
+                .decompress(compression_type, compression_flags, data)?
+        };
+
+        if header.flags.contains(ChannelControlFlags::FLAG_FIRST) {
+            let total_length = header.total_length as usize;
+            if total_length > self.max_message_size {
+                return Err(ChannelError::LimitExceeded(format!(
+                    "static channel message of {} bytes is larger than {}",
+                    total_length, self.max_message_size
+                )));
+            }
+
+            self.partial_messages.insert(
+                channel_id,
+                PartialMessage {
+                    total_length,
+                    data: Vec::new(),
+                },
+            );
+        }
+
+        let message = self
+            .partial_messages
+            .get_mut(&channel_id)
+            .ok_or(ChannelError::InvalidChannelPduHeader)?;
+        if message.data.len() + data.len() > message.total_length {
+            self.partial_messages.remove(&channel_id);
+
+            return Err(ChannelError::InvalidChannelTotalDataLength);
+        }
+        message.data.extend_from_slice(data);
+
+        if !header.flags.contains(ChannelControlFlags::FLAG_LAST) {
+            return Ok(None);
+        }
+
+        let message = self.partial_messages.remove(&channel_id).unwrap();
+        if message.data.len() != message.total_length {
+            return Err(ChannelError::InvalidChannelTotalDataLength);
+        }
+
+        Ok(Some(message.data))
+    }
+}
+
+#[derive(Debug)]
+struct PartialMessage {
+    /// The length of the message once decompressed, announced by the header of each of its chunks.
+    total_length: usize,
+    data: Vec<u8>,
+}
+
+#[derive(Debug, Error)]
 pub enum ChannelError {
-    #[fail(display = "IO error: {}", _0)]
-    IOError(#[fail(cause)] io::Error),
-    #[fail(display = "From UTF8 error: {}", _0)]
-    FromUtf8Error(#[fail(cause)] std::string::FromUtf8Error),
-    #[fail(display = "Invalid channel PDU header")]
+    #[error("IO error: {}", .0)]
+    IOError(#[source] io::Error),
+    #[error("From UTF8 error: {}", .0)]
+    FromUtf8Error(#[source] std::string::FromUtf8Error),
+    #[error("Invalid channel PDU header")]
     InvalidChannelPduHeader,
-    #[fail(display = "Invalid channel total data length")]
+    #[error("Invalid channel total data length")]
     InvalidChannelTotalDataLength,
-    #[fail(display = "Invalid DVC PDU type")]
+    #[error("Invalid DVC PDU type")]
     InvalidDvcPduType,
-    #[fail(display = "Invalid DVC id length value")]
+    #[error("Invalid DVC id length value")]
     InvalidDVChannelIdLength,
-    #[fail(display = "Invalid DVC data length value")]
+    #[error("Invalid DVC data length value")]
     InvalidDvcDataLength,
-    #[fail(display = "Invalid DVC capabilities version")]
+    #[error("Invalid DVC capabilities version")]
     InvalidDvcCapabilitiesVersion,
-    #[fail(display = "Invalid DVC message size")]
+    #[error("Invalid DVC message size")]
     InvalidDvcMessageSize,
-    #[fail(
-        display = "Invalid DVC total message size: actual ({}) > expected ({})",
-        actual, expected
-    )]
+    #[error("Invalid DVC total message size: actual ({}) > expected ({})", .actual, .expected)]
     InvalidDvcTotalMessageSize { actual: usize, expected: usize },
+    #[error("MPPC decompression error: {}", .0)]
+    MppcError(#[source] MppcError),
+    #[error("Channel message exceeds the limits: {}", .0)]
+    LimitExceeded(String),
 }
 
 impl_from_error!(io::Error, ChannelError, ChannelError::IOError);
 impl_from_error!(std::string::FromUtf8Error, ChannelError, ChannelError::FromUtf8Error);
+impl_from_error!(MppcError, ChannelError, ChannelError::MppcError);
 
 impl From<ChannelError> for io::Error {
     fn from(e: ChannelError) -> io::Error {
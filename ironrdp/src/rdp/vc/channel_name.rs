@@ -0,0 +1,159 @@
+use std::borrow::Cow;
+use std::fmt;
+
+use thiserror::Error;
+
+/// The static channel names are 8 bytes ANSI null-terminated strings.
+pub const STATIC_CHANNEL_NAME_MAX_LENGTH: usize = 7;
+
+/// Shared with the deprecated `DRDYNVC_CHANNEL_NAME`, which is a `&str`.
+pub(super) const DRDYNVC_NAME: &str = "drdynvc";
+
+/// The name of a static virtual channel, as advertised in the Client Network Data.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct StaticChannelName(Cow<'static, str>);
+
+impl StaticChannelName {
+    pub const DRDYNVC: Self = Self::from_static(DRDYNVC_NAME);
+    pub const CLIPRDR: Self = Self::from_static("cliprdr");
+    pub const RDPDR: Self = Self::from_static("rdpdr");
+    pub const RDPSND: Self = Self::from_static("rdpsnd");
+    pub const RAIL: Self = Self::from_static("rail");
+
+    pub fn new(name: impl Into<String>) -> Result<Self, ChannelNameError> {
+        let name = name.into();
+        validate_name(&name, Some(STATIC_CHANNEL_NAME_MAX_LENGTH))?;
+
+        Ok(Self(Cow::Owned(name)))
+    }
+
+    /// # Panics
+    ///
+    /// Panics if the name is not a valid static channel name,
+    /// at compile time when used in a constant.
+    pub const fn from_static(name: &'static str) -> Self {
+        if !is_valid_name(name, STATIC_CHANNEL_NAME_MAX_LENGTH) {
+            panic!("invalid static channel name");
+        }
+
+        Self(Cow::Borrowed(name))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+/// The name of a dynamic virtual channel, as received in the DVC Create Request PDU.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct DvcName(Cow<'static, str>);
+
+impl DvcName {
+    pub const GRAPHICS_PIPELINE: Self = Self::from_static("Microsoft::Windows::RDS::Graphics");
+    pub const DISPLAY_CONTROL: Self = Self::from_static("Microsoft::Windows::RDS::DisplayControl");
+
+    pub fn new(name: impl Into<String>) -> Result<Self, ChannelNameError> {
+        let name = name.into();
+        validate_name(&name, None)?;
+
+        Ok(Self(Cow::Owned(name)))
+    }
+
+    /// # Panics
+    ///
+    /// Panics if the name is not a valid dynamic channel name,
+    /// at compile time when used in a constant.
+    pub const fn from_static(name: &'static str) -> Self {
+        if !is_valid_name(name, usize::MAX) {
+            panic!("invalid dynamic channel name");
+        }
+
+        Self(Cow::Borrowed(name))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+macro_rules! impl_channel_name_traits {
+    ($name:ident) => {
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+
+        impl AsRef<str> for $name {
+            fn as_ref(&self) -> &str {
+                self.as_str()
+            }
+        }
+
+        impl PartialEq<str> for $name {
+            fn eq(&self, other: &str) -> bool {
+                self.as_str() == other
+            }
+        }
+
+        impl PartialEq<&str> for $name {
+            fn eq(&self, other: &&str) -> bool {
+                self.as_str() == *other
+            }
+        }
+
+        impl From<$name> for String {
+            fn from(name: $name) -> Self {
+                name.0.into_owned()
+            }
+        }
+    };
+}
+
+impl_channel_name_traits!(StaticChannelName);
+impl_channel_name_traits!(DvcName);
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ChannelNameError {
+    #[error("Channel name is empty")]
+    Empty,
+    #[error("Channel name {} is longer than {} characters", .0, .1)]
+    TooLong(String, usize),
+    #[error("Channel name {:?} contains a character other than a printable ASCII one", .0)]
+    InvalidCharacter(String),
+}
+
+fn validate_name(name: &str, max_length: Option<usize>) -> Result<(), ChannelNameError> {
+    if name.is_empty() {
+        return Err(ChannelNameError::Empty);
+    }
+
+    if let Some(max_length) = max_length {
+        if name.len() > max_length {
+            return Err(ChannelNameError::TooLong(String::from(name), max_length));
+        }
+    }
+
+    if !name.bytes().all(|byte| byte.is_ascii_graphic()) {
+        return Err(ChannelNameError::InvalidCharacter(String::from(name)));
+    }
+
+    Ok(())
+}
+
+const fn is_valid_name(name: &str, max_length: usize) -> bool {
+    let bytes = name.as_bytes();
+    if bytes.is_empty() || bytes.len() > max_length {
+        return false;
+    }
+
+    let mut i = 0;
+    while i < bytes.len() {
+        if !bytes[i].is_ascii_graphic() {
+            return false;
+        }
+        i += 1;
+    }
+
+    true
+}
@@ -28,7 +28,7 @@ pub use self::data::DataPdu;
 pub use self::data_first::DataFirstPdu;
 
 const HEADER_SIZE: usize = 1;
-const PDU_WITH_DATA_MAX_SIZE: usize = 1600;
+const PDU_WITH_DATA_MAX_SIZE: usize = crate::consts::CHANNEL_CHUNK_LENGTH;
 
 const UNUSED_U8: u8 = 0;
 
@@ -59,7 +59,9 @@ impl ServerPdu {
         let channel_id_type =
             FieldType::from_u8(dvc_header.channel_id_type).ok_or(ChannelError::InvalidDVChannelIdLength)?;
 
-        dvc_data_size -= HEADER_SIZE;
+        dvc_data_size = dvc_data_size
+            .checked_sub(HEADER_SIZE)
+            .ok_or(ChannelError::InvalidDvcMessageSize)?;
 
         match dvc_header.pdu_type {
             PduType::Capabilities => Ok(ServerPdu::CapabilitiesRequest(CapabilitiesRequestPdu::from_buffer(
@@ -141,7 +143,9 @@ impl ClientPdu {
         let channel_id_type =
             FieldType::from_u8(dvc_header.channel_id_type).ok_or(ChannelError::InvalidDVChannelIdLength)?;
 
-        dvc_data_size -= HEADER_SIZE;
+        dvc_data_size = dvc_data_size
+            .checked_sub(HEADER_SIZE)
+            .ok_or(ChannelError::InvalidDvcMessageSize)?;
 
         match dvc_header.pdu_type {
             PduType::Capabilities => Ok(ClientPdu::CapabilitiesResponse(CapabilitiesResponsePdu::from_buffer(
@@ -29,7 +29,9 @@ impl CreateRequestPdu {
     ) -> Result<Self, ChannelError> {
         let channel_id = channel_id_type.read_buffer_according_to_type(&mut stream)?;
 
-        data_size -= channel_id_type.get_type_size();
+        data_size = data_size
+            .checked_sub(channel_id_type.get_type_size())
+            .ok_or(ChannelError::InvalidDvcMessageSize)?;
         let channel_name = utils::read_string(&mut stream, data_size, utils::CharacterSet::Ansi, false)?;
 
         Ok(Self {
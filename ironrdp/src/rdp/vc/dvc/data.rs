@@ -21,7 +21,9 @@ impl DataPdu {
         mut data_size: usize,
     ) -> Result<Self, ChannelError> {
         let channel_id = channel_id_type.read_buffer_according_to_type(&mut stream)?;
-        data_size -= channel_id_type.get_type_size();
+        data_size = data_size
+            .checked_sub(channel_id_type.get_type_size())
+            .ok_or(ChannelError::InvalidDvcMessageSize)?;
 
         let expected_max_data_size = PDU_WITH_DATA_MAX_SIZE - (HEADER_SIZE + channel_id_type.get_type_size());
 
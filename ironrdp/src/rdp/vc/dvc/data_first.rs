@@ -26,7 +26,9 @@ impl DataFirstPdu {
         let channel_id = channel_id_type.read_buffer_according_to_type(&mut stream)?;
         let total_data_size = total_data_size_type.read_buffer_according_to_type(&mut stream)?;
 
-        data_size -= channel_id_type.get_type_size() + total_data_size_type.get_type_size();
+        data_size = data_size
+            .checked_sub(channel_id_type.get_type_size() + total_data_size_type.get_type_size())
+            .ok_or(ChannelError::InvalidDvcMessageSize)?;
         if data_size > total_data_size as usize {
             return Err(ChannelError::InvalidDvcTotalMessageSize {
                 actual: data_size,
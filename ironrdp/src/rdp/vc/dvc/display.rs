@@ -4,9 +4,9 @@
 use std::io::{self, Read, Write};
 
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
-use failure::Fail;
 use num_derive::{FromPrimitive, ToPrimitive};
 use num_traits::{FromPrimitive, ToPrimitive};
+use thiserror::Error;
 
 use crate::{impl_from_error, PduParsing};
 use bitflags::bitflags;
@@ -289,13 +289,13 @@ impl<'a> From<&'a ClientPdu> for ClientPduType {
     }
 }
 
-#[derive(Debug, Fail)]
+#[derive(Debug, Error)]
 pub enum DisplayPipelineError {
-    #[fail(display = "IO error: {}", _0)]
-    IOError(#[fail(cause)] io::Error),
-    #[fail(display = "Invalid Header cmd ID")]
+    #[error("IO error: {}", .0)]
+    IOError(#[source] io::Error),
+    #[error("Invalid Header cmd ID")]
     InvalidCmdId,
-    #[fail(display = "Invalid PDU length: expected ({}) != actual ({})", expected, actual)]
+    #[error("Invalid PDU length: expected ({}) != actual ({})", .expected, .actual)]
     InvalidPduLength { expected: usize, actual: usize },
 }
 
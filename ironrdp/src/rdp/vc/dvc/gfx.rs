@@ -1,3 +1,4 @@
+#[cfg(feature = "zgfx")]
 pub mod zgfx;
 
 mod graphics_messages;
@@ -7,7 +8,6 @@ mod test;
 use std::io;
 
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
-use failure::Fail;
 use graphics_messages::RESET_GRAPHICS_PDU_SIZE;
 pub use graphics_messages::{
     Avc420BitmapStream, Avc444BitmapStream, CacheImportReplyPdu, CacheToSurfacePdu, CapabilitiesAdvertisePdu,
@@ -20,6 +20,7 @@ pub use graphics_messages::{
 };
 use num_derive::{FromPrimitive, ToPrimitive};
 use num_traits::{FromPrimitive, ToPrimitive};
+use thiserror::Error;
 
 use crate::{impl_from_error, PduParsing};
 
@@ -300,24 +301,21 @@ impl<'a> From<&'a ServerPdu> for ServerPduType {
     }
 }
 
-#[derive(Debug, Fail)]
+#[derive(Debug, Error)]
 pub enum GraphicsPipelineError {
-    #[fail(display = "IO error: {}", _0)]
-    IOError(#[fail(cause)] io::Error),
-    #[fail(display = "Graphics messages error: {}", _0)]
-    GraphicsMessagesError(#[fail(cause)] graphics_messages::GraphicsMessagesError),
-    #[fail(display = "Invalid Header cmd ID")]
+    #[error("IO error: {}", .0)]
+    IOError(#[source] io::Error),
+    #[error("Graphics messages error: {}", .0)]
+    GraphicsMessagesError(#[source] graphics_messages::GraphicsMessagesError),
+    #[error("Invalid Header cmd ID")]
     InvalidCmdId,
-    #[fail(display = "Unexpected client's PDU type: {:?}", _0)]
+    #[error("Unexpected client's PDU type: {:?}", .0)]
     UnexpectedClientPduType(ClientPduType),
-    #[fail(display = "Unexpected server's PDU type: {:?}", _0)]
+    #[error("Unexpected server's PDU type: {:?}", .0)]
     UnexpectedServerPduType(ServerPduType),
-    #[fail(
-        display = "Invalid ResetGraphics PDU size: expected ({}) != actual ({})",
-        expected, actual
-    )]
+    #[error("Invalid ResetGraphics PDU size: expected ({}) != actual ({})", .expected, .actual)]
     InvalidResetGraphicsPduSize { expected: usize, actual: usize },
-    #[fail(display = "Invalid PDU length: expected ({}) != actual ({})", expected, actual)]
+    #[error("Invalid PDU length: expected ({}) != actual ({})", .expected, .actual)]
     InvalidPduLength { expected: usize, actual: usize },
 }
 
@@ -8,7 +8,6 @@ mod avc_messages;
 use bitflags::bitflags;
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 pub use client::{CacheImportReplyPdu, CapabilitiesAdvertisePdu, FrameAcknowledgePdu, QueueDepth};
-use failure::Fail;
 use num_derive::{FromPrimitive, ToPrimitive};
 use num_traits::{FromPrimitive, ToPrimitive};
 pub use server::{
@@ -17,10 +16,11 @@ pub use server::{
     MapSurfaceToScaledWindowPdu, PixelFormat, ResetGraphicsPdu, SolidFillPdu, StartFramePdu, SurfaceToCachePdu,
     SurfaceToSurfacePdu, Timestamp, WireToSurface1Pdu, WireToSurface2Pdu, RESET_GRAPHICS_PDU_SIZE,
 };
+use thiserror::Error;
 
 use super::RDP_GFX_HEADER_SIZE;
 use crate::gcc::MonitorDataError;
-use crate::utils::Rectangle;
+use crate::utils::{self, Rectangle};
 use crate::{impl_from_error, PduParsing};
 pub use avc_messages::{Avc420BitmapStream, Avc444BitmapStream, Encoding, QuantQuality};
 
@@ -71,8 +71,7 @@ impl PduParsing for CapabilitySet {
             .ok_or(GraphicsMessagesError::InvalidCapabilitiesVersion)?;
         let data_length = stream.read_u32::<LittleEndian>()?;
 
-        let mut data = vec![0; data_length as usize];
-        stream.read_exact(data.as_mut())?;
+        let data = utils::read_vec(&mut stream, data_length as usize)?;
 
         match version {
             CapabilityVersion::V8 => Ok(CapabilitySet::V8 {
@@ -279,28 +278,32 @@ bitflags! {
     }
 }
 
-#[derive(Debug, Fail)]
+#[derive(Debug, Error)]
 pub enum GraphicsMessagesError {
-    #[fail(display = "IO error: {}", _0)]
-    IOError(#[fail(cause)] io::Error),
-    #[fail(display = "Invalid codec ID version 1")]
+    #[error("IO error: {}", .0)]
+    IOError(#[source] io::Error),
+    #[error("Invalid codec ID version 1")]
     InvalidCodec1Id,
-    #[fail(display = "Invalid codec ID version 2")]
+    #[error("Invalid codec ID version 2")]
     InvalidCodec2Id,
-    #[fail(display = "Invalid pixel format")]
+    #[error("Invalid pixel format")]
     InvalidFixelFormat,
-    #[fail(display = "Monitor error: {}", _0)]
-    MonitorError(#[fail(cause)] MonitorDataError),
-    #[fail(display = "Invalid ResetGraphics PDU width: {} > MAX ({})", actual, max)]
+    #[error("Monitor error: {}", .0)]
+    MonitorError(#[source] MonitorDataError),
+    #[error("Invalid ResetGraphics PDU width: {} > MAX ({})", .actual, .max)]
     InvalidResetGraphicsPduWidth { actual: u32, max: u32 },
-    #[fail(display = "Invalid ResetGraphics PDU height: {} > MAX ({})", actual, max)]
+    #[error("Invalid ResetGraphics PDU height: {} > MAX ({})", .actual, .max)]
     InvalidResetGraphicsPduHeight { actual: u32, max: u32 },
-    #[fail(display = "Invalid ResetGraphics PDU monitors count: {} > MAX ({})", actual, max)]
+    #[error("Invalid ResetGraphics PDU monitors count: {} > MAX ({})", .actual, .max)]
     InvalidResetGraphicsPduMonitorsCount { actual: u32, max: u32 },
-    #[fail(display = "Invalid capabilities version")]
+    #[error("Invalid capabilities version")]
     InvalidCapabilitiesVersion,
-    #[fail(display = "Both luma and chroma packets specified but length is missing")]
+    #[error("Both luma and chroma packets specified but length is missing")]
     InvalidAvcEncoding,
+    #[error("Invalid AVC420 regions count: {} > MAX ({})", .actual, .max)]
+    InvalidAvcRegionsCount { actual: usize, max: usize },
+    #[error("Invalid AVC444 first stream length: {} > MAX ({})", .actual, .max)]
+    InvalidAvcStreamLength { actual: usize, max: usize },
 }
 
 impl_from_error!(io::Error, GraphicsMessagesError, GraphicsMessagesError::IOError);
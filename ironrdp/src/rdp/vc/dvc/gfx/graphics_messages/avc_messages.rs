@@ -66,9 +66,18 @@ impl<'a> PduBufferParsing<'a> for Avc420BitmapStream<'a> {
     type Error = GraphicsMessagesError;
 
     fn from_buffer_consume(mut buffer: &mut &'a [u8]) -> Result<Self, Self::Error> {
-        let num_regions = buffer.read_u32::<LittleEndian>()?;
-        let mut rectangles = Vec::with_capacity(num_regions as usize);
-        let mut quant_qual_vals = Vec::with_capacity(num_regions as usize);
+        let num_regions = buffer.read_u32::<LittleEndian>()? as usize;
+        // Each region is described by a rectangle of 8 bytes and by 2 bytes of quant vals
+        let max_regions = buffer.len() / 10;
+        if num_regions > max_regions {
+            return Err(GraphicsMessagesError::InvalidAvcRegionsCount {
+                actual: num_regions,
+                max: max_regions,
+            });
+        }
+
+        let mut rectangles = Vec::with_capacity(num_regions);
+        let mut quant_qual_vals = Vec::with_capacity(num_regions);
         for _ in 0..num_regions {
             rectangles.push(Rectangle::from_buffer(&mut buffer)?);
         }
@@ -138,6 +147,13 @@ impl<'a> PduBufferParsing<'a> for Avc444BitmapStream<'a> {
                 stream2: None,
             })
         } else {
+            if stream_len as usize > buffer.len() {
+                return Err(GraphicsMessagesError::InvalidAvcStreamLength {
+                    actual: stream_len as usize,
+                    max: buffer.len(),
+                });
+            }
+
             let (mut stream1, mut stream2) = buffer.split_at(stream_len as usize);
             let stream1 = Avc420BitmapStream::from_buffer_consume(&mut stream1)?;
             let stream2 = if encoding == Encoding::LUMA_AND_CHROMA {
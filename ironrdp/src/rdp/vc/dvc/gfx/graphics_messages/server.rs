@@ -1,3 +1,4 @@
+use std::time::Duration;
 use std::{fmt, io};
 
 use bit_field::BitField;
@@ -7,7 +8,7 @@ use num_traits::{FromPrimitive, ToPrimitive};
 
 use super::{CapabilitySet, Color, GraphicsMessagesError, Point, Rectangle, RDP_GFX_HEADER_SIZE};
 use crate::gcc::{Monitor, MonitorDataError};
-use crate::PduParsing;
+use crate::{utils, PduParsing};
 
 pub const RESET_GRAPHICS_PDU_SIZE: usize = 340;
 
@@ -45,8 +46,7 @@ impl PduParsing for WireToSurface1Pdu {
         let pixel_format = PixelFormat::from_u8(stream.read_u8()?).ok_or(GraphicsMessagesError::InvalidFixelFormat)?;
         let destination_rectangle = Rectangle::from_buffer(&mut stream)?;
         let bitmap_data_length = stream.read_u32::<LittleEndian>()? as usize;
-        let mut bitmap_data = vec![0; bitmap_data_length];
-        stream.read_exact(&mut bitmap_data)?;
+        let bitmap_data = utils::read_vec(&mut stream, bitmap_data_length)?;
         Ok(Self {
             surface_id,
             codec_id,
@@ -102,8 +102,7 @@ impl PduParsing for WireToSurface2Pdu {
         let codec_context_id = stream.read_u32::<LittleEndian>()?;
         let pixel_format = PixelFormat::from_u8(stream.read_u8()?).ok_or(GraphicsMessagesError::InvalidFixelFormat)?;
         let bitmap_data_length = stream.read_u32::<LittleEndian>()? as usize;
-        let mut bitmap_data = vec![0; bitmap_data_length];
-        stream.read_exact(&mut bitmap_data)?;
+        let bitmap_data = utils::read_vec(&mut stream, bitmap_data_length)?;
 
         Ok(Self {
             surface_id,
@@ -738,6 +737,15 @@ pub struct Timestamp {
     pub hours: u16,
 }
 
+impl Timestamp {
+    /// The time elapsed since the start of the day, in the clock of the server.
+    pub fn as_duration(&self) -> Duration {
+        let seconds = (u64::from(self.hours) * 60 + u64::from(self.minutes)) * 60 + u64::from(self.seconds);
+
+        Duration::from_secs(seconds) + Duration::from_millis(u64::from(self.milliseconds))
+    }
+}
+
 impl PduParsing for Timestamp {
     type Error = GraphicsMessagesError;
 
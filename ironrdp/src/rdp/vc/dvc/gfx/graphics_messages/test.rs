@@ -1,4 +1,5 @@
 use lazy_static::lazy_static;
+use proptest::prelude::*;
 
 use super::*;
 use crate::{
@@ -883,3 +884,68 @@ fn to_buffer_consume_correctly_serializes_avc_444_message() {
     AVC_444_BITMAP.to_buffer_consume(&mut buffer.as_mut_slice()).unwrap();
     assert_eq!(expected, buffer.as_slice());
 }
+
+#[test]
+fn from_buffer_consume_returns_error_on_avc_420_regions_count_exceeding_data() {
+    let mut buffer = [0xff, 0xff, 0xff, 0xff, 0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x10, 0x00].as_ref();
+    match Avc420BitmapStream::from_buffer_consume(&mut buffer) {
+        Err(GraphicsMessagesError::InvalidAvcRegionsCount {
+            actual: 0xffff_ffff,
+            max: 0,
+        }) => (),
+        res => panic!("Expected InvalidAvcRegionsCount error, got: {:?}", res),
+    }
+}
+
+fn any_pixel_format() -> impl Strategy<Value = PixelFormat> {
+    prop_oneof![Just(PixelFormat::XRgb), Just(PixelFormat::ARgb)]
+}
+
+fn any_timestamp() -> impl Strategy<Value = Timestamp> {
+    (0..1024u16, 0..64u8, 0..64u8, 0..1024u16).prop_map(|(milliseconds, seconds, minutes, hours)| Timestamp {
+        milliseconds,
+        seconds,
+        minutes,
+        hours,
+    })
+}
+
+crate::round_trip_proptest! {
+    frame_acknowledge_pdu_round_trip: FrameAcknowledgePdu = any::<[u32; 3]>().prop_map(
+        |[queue_depth, frame_id, total_frames_decoded]| FrameAcknowledgePdu {
+            queue_depth: QueueDepth::from_u32(queue_depth),
+            frame_id,
+            total_frames_decoded,
+        }
+    );
+    cache_import_reply_pdu_round_trip: CacheImportReplyPdu = prop::collection::vec(any::<u16>(), 0..128)
+        .prop_map(|cache_slots| CacheImportReplyPdu { cache_slots });
+    start_frame_pdu_round_trip: StartFramePdu = (any_timestamp(), any::<u32>())
+        .prop_map(|(timestamp, frame_id)| StartFramePdu { timestamp, frame_id });
+    end_frame_pdu_round_trip: EndFramePdu = any::<u32>().prop_map(|frame_id| EndFramePdu { frame_id });
+    create_surface_pdu_round_trip: CreateSurfacePdu = (any::<[u16; 3]>(), any_pixel_format()).prop_map(
+        |([surface_id, width, height], pixel_format)| CreateSurfacePdu {
+            surface_id,
+            width,
+            height,
+            pixel_format,
+        }
+    );
+    delete_surface_pdu_round_trip: DeleteSurfacePdu =
+        any::<u16>().prop_map(|surface_id| DeleteSurfacePdu { surface_id });
+    evict_cache_entry_pdu_round_trip: EvictCacheEntryPdu =
+        any::<u16>().prop_map(|cache_slot| EvictCacheEntryPdu { cache_slot });
+    map_surface_to_output_pdu_round_trip: MapSurfaceToOutputPdu = (any::<u16>(), any::<[u32; 2]>()).prop_map(
+        |(surface_id, [output_origin_x, output_origin_y])| MapSurfaceToOutputPdu {
+            surface_id,
+            output_origin_x,
+            output_origin_y,
+        }
+    );
+    delete_encoding_context_pdu_round_trip: DeleteEncodingContextPdu = (any::<u16>(), any::<u32>()).prop_map(
+        |(surface_id, codec_context_id)| DeleteEncodingContextPdu {
+            surface_id,
+            codec_context_id,
+        }
+    );
+}
@@ -13,8 +13,8 @@ use bitvec::slice::BitSlice;
 use byteorder::WriteBytesExt;
 use circular_buffer::FixedCircularBuffer;
 use control_messages::{BulkEncodedData, CompressionFlags, SegmentedDataPdu};
-use failure::Fail;
 use lazy_static::lazy_static;
+use thiserror::Error;
 
 use crate::impl_from_error;
 use crate::utils::Bits;
@@ -429,24 +429,26 @@ lazy_static! {
     ];
 }
 
-#[derive(Debug, Fail)]
+#[derive(Debug, Error)]
 pub enum ZgfxError {
-    #[fail(display = "IO error: {}", _0)]
-    IOError(#[fail(cause)] io::Error),
-    #[fail(display = "Invalid compression type")]
+    #[error("IO error: {}", .0)]
+    IOError(#[source] io::Error),
+    #[error("Invalid compression type")]
     InvalidCompressionType,
-    #[fail(display = "Invalid segmented descriptor")]
+    #[error("Invalid segmented descriptor")]
     InvalidSegmentedDescriptor,
-    #[fail(
-        display = "Decompressed size of segments ({}) does not equal to uncompressed size ({})",
-        decompressed_size, uncompressed_size
+    #[error(
+        "Decompressed size of segments ({}) does not equal to uncompressed size ({})",
+        .decompressed_size, .uncompressed_size
     )]
     InvalidDecompressedSize {
         decompressed_size: usize,
         uncompressed_size: usize,
     },
-    #[fail(display = "Token bits not found")]
+    #[error("Token bits not found")]
     TokenBitsNotFound,
+    #[error("Segment size ({}) exceeds the remaining data ({})", .size, .remaining)]
+    InvalidSegmentSize { size: usize, remaining: usize },
 }
 
 impl_from_error!(io::Error, ZgfxError, ZgfxError::IOError);
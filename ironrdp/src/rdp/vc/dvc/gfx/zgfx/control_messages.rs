@@ -29,6 +29,13 @@ impl<'a> SegmentedDataPdu<'a> {
                 let mut segments = Vec::with_capacity(segment_count);
                 for _ in 0..segment_count {
                     let size = buffer.read_u32::<LittleEndian>()? as usize;
+                    if size > buffer.len() {
+                        return Err(ZgfxError::InvalidSegmentSize {
+                            size,
+                            remaining: buffer.len(),
+                        });
+                    }
+
                     let (segment_data, new_buffer) = buffer.split_at(size);
                     buffer = new_buffer;
 
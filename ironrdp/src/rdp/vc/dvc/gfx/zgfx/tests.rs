@@ -197,3 +197,19 @@ fn zgfx_decopresses_unencoded_block_without_padding() {
     zgfx.decompress_segment(buffer.as_ref(), &mut decompressed).unwrap();
     assert_eq!(decompressed, expected);
 }
+
+#[test]
+fn zgfx_returns_error_on_segment_size_exceeding_data() {
+    // Multipart PDU of one segment of 100 bytes, only one of them being sent
+    let buffer = [0xe1, 0x01, 0x00, 0x64, 0x00, 0x00, 0x00, 0x64, 0x00, 0x00, 0x00, 0xe4];
+
+    let mut zgfx = Decompressor::new();
+    let mut decompressed = Vec::new();
+    match zgfx.decompress(buffer.as_ref(), &mut decompressed) {
+        Err(ZgfxError::InvalidSegmentSize {
+            size: 100,
+            remaining: 1,
+        }) => (),
+        res => panic!("Expected InvalidSegmentSize error, got: {:?}", res),
+    }
+}
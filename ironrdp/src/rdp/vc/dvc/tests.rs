@@ -1,4 +1,5 @@
 use lazy_static::lazy_static;
+use proptest::prelude::*;
 
 use super::*;
 
@@ -69,6 +70,15 @@ fn from_buffer_parsing_for_server_dvc_pdu_with_invalid_id_length_type_fails() {
     };
 }
 
+#[test]
+fn from_buffer_parsing_for_server_dvc_pdu_with_too_small_data_size_fails() {
+    // Data PDU header, followed by a channel ID of 1 byte not accounted for by the data size
+    match ServerPdu::from_buffer([0x30, 0x01].as_ref(), HEADER_SIZE) {
+        Err(ChannelError::InvalidDvcMessageSize) => (),
+        res => panic!("Expected InvalidDvcMessageSize error, got: {:?}", res),
+    };
+}
+
 #[test]
 fn from_buffer_according_to_type_u8_test() {
     let channel_id = FieldType::U8
@@ -152,3 +162,116 @@ fn get_length_according_to_type_u32_test() {
     let length = FieldType::U32.get_type_size();
     assert_eq!(mem::size_of::<u32>(), length);
 }
+
+fn any_channel_id() -> impl Strategy<Value = (FieldType, u32)> {
+    prop_oneof![
+        any::<u8>().prop_map(|channel_id| (FieldType::U8, u32::from(channel_id))),
+        any::<u16>().prop_map(|channel_id| (FieldType::U16, u32::from(channel_id))),
+        any::<u32>().prop_map(|channel_id| (FieldType::U32, channel_id)),
+    ]
+}
+
+fn any_caps_version() -> impl Strategy<Value = CapsVersion> {
+    prop_oneof![Just(CapsVersion::V1), Just(CapsVersion::V2), Just(CapsVersion::V3)]
+}
+
+fn any_capabilities_request_pdu() -> impl Strategy<Value = CapabilitiesRequestPdu> {
+    prop_oneof![
+        Just(CapabilitiesRequestPdu::V1),
+        any::<[u16; 4]>().prop_map(|charges| CapabilitiesRequestPdu::V2 { charges }),
+        any::<[u16; 4]>().prop_map(|charges| CapabilitiesRequestPdu::V3 { charges }),
+    ]
+}
+
+fn any_create_request_pdu() -> impl Strategy<Value = CreateRequestPdu> {
+    (any_channel_id(), "[A-Za-z0-9_:]{1,32}").prop_map(|((channel_id_type, channel_id), channel_name)| {
+        CreateRequestPdu {
+            channel_id_type,
+            channel_id,
+            channel_name,
+        }
+    })
+}
+
+fn any_create_response_pdu() -> impl Strategy<Value = CreateResponsePdu> {
+    (any_channel_id(), any::<u32>()).prop_map(|((channel_id_type, channel_id), creation_status)| CreateResponsePdu {
+        channel_id_type,
+        channel_id,
+        creation_status,
+    })
+}
+
+fn any_close_pdu() -> impl Strategy<Value = ClosePdu> {
+    any_channel_id().prop_map(|(channel_id_type, channel_id)| ClosePdu {
+        channel_id_type,
+        channel_id,
+    })
+}
+
+fn any_display_monitor() -> impl Strategy<Value = display::Monitor> {
+    (
+        any::<[u32; 8]>(),
+        any::<bool>(),
+        prop_oneof![
+            Just(display::Orientation::Landscape),
+            Just(display::Orientation::Portrait),
+            Just(display::Orientation::LandscapeFlipped),
+            Just(display::Orientation::PortraitFlipped),
+        ],
+    )
+        .prop_map(
+            |(
+                [left, top, width, height, physical_width, physical_height, desktop_scale_factor, device_scale_factor],
+                is_primary,
+                orientation,
+            )| display::Monitor {
+                flags: if is_primary {
+                    display::MonitorFlags::PRIMARY
+                } else {
+                    display::MonitorFlags::empty()
+                },
+                left,
+                top,
+                width,
+                height,
+                physical_width,
+                physical_height,
+                orientation,
+                desktop_scale_factor,
+                device_scale_factor,
+            },
+        )
+}
+
+fn decode_server_pdu(buffer: &[u8]) -> Result<ServerPdu, ChannelError> {
+    ServerPdu::from_buffer(buffer, buffer.len())
+}
+
+fn decode_client_pdu(buffer: &[u8]) -> Result<ClientPdu, ChannelError> {
+    ClientPdu::from_buffer(buffer, buffer.len())
+}
+
+crate::round_trip_proptest! {
+    server_capabilities_request_pdu_round_trip: ServerPdu =
+        any_capabilities_request_pdu().prop_map(ServerPdu::CapabilitiesRequest), decode = decode_server_pdu;
+    server_create_request_pdu_round_trip: ServerPdu =
+        any_create_request_pdu().prop_map(ServerPdu::CreateRequest), decode = decode_server_pdu;
+    server_close_request_pdu_round_trip: ServerPdu =
+        any_close_pdu().prop_map(ServerPdu::CloseRequest), decode = decode_server_pdu;
+    client_capabilities_response_pdu_round_trip: ClientPdu = any_caps_version()
+        .prop_map(|version| ClientPdu::CapabilitiesResponse(CapabilitiesResponsePdu { version })),
+        decode = decode_client_pdu;
+    client_create_response_pdu_round_trip: ClientPdu =
+        any_create_response_pdu().prop_map(ClientPdu::CreateResponse), decode = decode_client_pdu;
+    client_close_response_pdu_round_trip: ClientPdu =
+        any_close_pdu().prop_map(ClientPdu::CloseResponse), decode = decode_client_pdu;
+    display_control_caps_pdu_round_trip: display::DisplayControlCapsPdu = any::<[u32; 3]>().prop_map(
+        |[max_num_monitors, max_monitor_area_factora, max_monitor_area_factorb]| display::DisplayControlCapsPdu {
+            max_num_monitors,
+            max_monitor_area_factora,
+            max_monitor_area_factorb,
+        }
+    );
+    display_monitor_layout_pdu_round_trip: display::MonitorLayoutPdu =
+        prop::collection::vec(any_display_monitor(), 0..16).prop_map(|monitors| display::MonitorLayoutPdu { monitors });
+}
@@ -0,0 +1,1502 @@
+//! PDUs of the File System Virtual Channel Extension (MS-RDPEFS), exchanged on the `rdpdr` static channel
+//! to redirect the devices of the client, e.g. its drives, to the remote session.
+
+#[cfg(test)]
+mod tests;
+
+pub mod scard;
+
+mod file_information;
+
+use std::io;
+
+use bitflags::bitflags;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use num_derive::{FromPrimitive, ToPrimitive};
+use num_traits::{FromPrimitive, ToPrimitive};
+use thiserror::Error;
+
+pub use self::file_information::{
+    DirectoryEntry, FileAttributes, FileBasicInformation, FileInformation, FileInformationClass, FsInformationClass,
+    VolumeInformation,
+};
+use crate::consts::RDPDR_GENERAL_CAPABILITY_VERSION_02;
+use crate::utils::{self, CharacterSet};
+use crate::{impl_from_error, PduParsing};
+
+const COMPONENT_CORE: u16 = 0x4472;
+const COMPONENT_PRINTER: u16 = 0x5052;
+
+const HEADER_SIZE: usize = 4;
+const VERSION_AND_ID_PDU_SIZE: usize = 8;
+const CLIENT_NAME_PDU_FIXED_PART_SIZE: usize = 12;
+const CAPABILITIES_PDU_FIXED_PART_SIZE: usize = 4;
+const CAPABILITY_HEADER_SIZE: usize = 8;
+const GENERAL_CAPABILITY_V1_BODY_SIZE: usize = 32;
+const SPECIAL_TYPE_DEVICE_CAP_SIZE: usize = 4;
+const DEVICE_COUNT_SIZE: usize = 4;
+const DEVICE_ANNOUNCE_FIXED_PART_SIZE: usize = 20;
+const PREFERRED_DOS_NAME_SIZE: usize = 8;
+const DEVICE_REPLY_PDU_SIZE: usize = 8;
+const DEVICE_IO_REQUEST_HEADER_SIZE: usize = 20;
+const DEVICE_IO_RESPONSE_HEADER_SIZE: usize = 12;
+
+const CREATE_REQUEST_FIXED_PART_SIZE: usize = 32;
+const CLOSE_REQUEST_PADDING_SIZE: usize = 32;
+const READ_WRITE_REQUEST_PADDING_SIZE: usize = 20;
+const INFORMATION_REQUEST_PADDING_SIZE: usize = 24;
+const QUERY_DIRECTORY_REQUEST_PADDING_SIZE: usize = 23;
+const NOTIFY_CHANGE_DIRECTORY_REQUEST_PADDING_SIZE: usize = 27;
+const DEVICE_CONTROL_REQUEST_PADDING_SIZE: usize = 20;
+
+const MINOR_FUNCTION_NONE: u32 = 0x0000_0000;
+const MINOR_FUNCTION_QUERY_DIRECTORY: u32 = 0x0000_0001;
+const MINOR_FUNCTION_NOTIFY_CHANGE_DIRECTORY: u32 = 0x0000_0002;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ServerPdu {
+    ServerAnnounce(VersionAndIdPdu),
+    ServerCapability(CoreCapabilityPdu),
+    ClientIdConfirm(VersionAndIdPdu),
+    DeviceReply(DeviceReplyPdu),
+    DeviceIoRequest(DeviceIoRequestPdu),
+    /// Sent once the user has logged on, the client then announces the devices which are
+    /// not needed during the logon, e.g. the drives.
+    UserLoggedOn,
+}
+
+impl ServerPdu {
+    pub fn as_short_name(&self) -> &str {
+        match self {
+            ServerPdu::ServerAnnounce(_) => "Server Announce Request PDU",
+            ServerPdu::ServerCapability(_) => "Server Core Capability Request PDU",
+            ServerPdu::ClientIdConfirm(_) => "Server Client ID Confirm PDU",
+            ServerPdu::DeviceReply(_) => "Server Device Announce Response PDU",
+            ServerPdu::DeviceIoRequest(_) => "Device I/O Request PDU",
+            ServerPdu::UserLoggedOn => "Server User Logged On PDU",
+        }
+    }
+
+    fn packet_id(&self) -> PacketId {
+        match self {
+            ServerPdu::ServerAnnounce(_) => PacketId::ServerAnnounce,
+            ServerPdu::ServerCapability(_) => PacketId::ServerCapability,
+            ServerPdu::ClientIdConfirm(_) => PacketId::ClientIdConfirm,
+            ServerPdu::DeviceReply(_) => PacketId::DeviceReply,
+            ServerPdu::DeviceIoRequest(_) => PacketId::DeviceIoRequest,
+            ServerPdu::UserLoggedOn => PacketId::UserLoggedOn,
+        }
+    }
+}
+
+impl PduParsing for ServerPdu {
+    type Error = RdpdrError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        let packet_id = read_header(&mut stream)?;
+
+        match packet_id {
+            PacketId::ServerAnnounce => Ok(ServerPdu::ServerAnnounce(VersionAndIdPdu::from_buffer(&mut stream)?)),
+            PacketId::ServerCapability => Ok(ServerPdu::ServerCapability(CoreCapabilityPdu::from_buffer(
+                &mut stream,
+            )?)),
+            PacketId::ClientIdConfirm => Ok(ServerPdu::ClientIdConfirm(VersionAndIdPdu::from_buffer(&mut stream)?)),
+            PacketId::DeviceReply => Ok(ServerPdu::DeviceReply(DeviceReplyPdu::from_buffer(&mut stream)?)),
+            PacketId::DeviceIoRequest => Ok(ServerPdu::DeviceIoRequest(DeviceIoRequestPdu::from_buffer(
+                &mut stream,
+            )?)),
+            PacketId::UserLoggedOn => Ok(ServerPdu::UserLoggedOn),
+            PacketId::ClientName
+            | PacketId::ClientCapability
+            | PacketId::DeviceListAnnounce
+            | PacketId::DeviceListRemove
+            | PacketId::DeviceIoCompletion => Err(RdpdrError::UnexpectedPacketId(packet_id)),
+        }
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        write_header(&mut stream, self.packet_id())?;
+
+        match self {
+            ServerPdu::ServerAnnounce(pdu) | ServerPdu::ClientIdConfirm(pdu) => pdu.to_buffer(&mut stream),
+            ServerPdu::ServerCapability(pdu) => pdu.to_buffer(&mut stream),
+            ServerPdu::DeviceReply(pdu) => pdu.to_buffer(&mut stream),
+            ServerPdu::DeviceIoRequest(pdu) => pdu.to_buffer(&mut stream),
+            ServerPdu::UserLoggedOn => Ok(()),
+        }
+    }
+
+    fn buffer_length(&self) -> usize {
+        HEADER_SIZE
+            + match self {
+                ServerPdu::ServerAnnounce(pdu) | ServerPdu::ClientIdConfirm(pdu) => pdu.buffer_length(),
+                ServerPdu::ServerCapability(pdu) => pdu.buffer_length(),
+                ServerPdu::DeviceReply(pdu) => pdu.buffer_length(),
+                ServerPdu::DeviceIoRequest(pdu) => pdu.buffer_length(),
+                ServerPdu::UserLoggedOn => 0,
+            }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClientPdu {
+    ClientAnnounceReply(VersionAndIdPdu),
+    ClientName(ClientNamePdu),
+    ClientCapability(CoreCapabilityPdu),
+    DeviceListAnnounce(DeviceListAnnouncePdu),
+    DeviceListRemove(DeviceListRemovePdu),
+    DeviceIoCompletion(DeviceIoCompletionPdu),
+}
+
+impl ClientPdu {
+    pub fn as_short_name(&self) -> &str {
+        match self {
+            ClientPdu::ClientAnnounceReply(_) => "Client Announce Reply PDU",
+            ClientPdu::ClientName(_) => "Client Name Request PDU",
+            ClientPdu::ClientCapability(_) => "Client Core Capability Response PDU",
+            ClientPdu::DeviceListAnnounce(_) => "Client Device List Announce Request PDU",
+            ClientPdu::DeviceListRemove(_) => "Client Drive Device List Remove PDU",
+            ClientPdu::DeviceIoCompletion(_) => "Device I/O Response PDU",
+        }
+    }
+
+    fn packet_id(&self) -> PacketId {
+        match self {
+            ClientPdu::ClientAnnounceReply(_) => PacketId::ClientIdConfirm,
+            ClientPdu::ClientName(_) => PacketId::ClientName,
+            ClientPdu::ClientCapability(_) => PacketId::ClientCapability,
+            ClientPdu::DeviceListAnnounce(_) => PacketId::DeviceListAnnounce,
+            ClientPdu::DeviceListRemove(_) => PacketId::DeviceListRemove,
+            ClientPdu::DeviceIoCompletion(_) => PacketId::DeviceIoCompletion,
+        }
+    }
+}
+
+impl PduParsing for ClientPdu {
+    type Error = RdpdrError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        let packet_id = read_header(&mut stream)?;
+
+        match packet_id {
+            PacketId::ClientIdConfirm => Ok(ClientPdu::ClientAnnounceReply(VersionAndIdPdu::from_buffer(
+                &mut stream,
+            )?)),
+            PacketId::ClientName => Ok(ClientPdu::ClientName(ClientNamePdu::from_buffer(&mut stream)?)),
+            PacketId::ClientCapability => Ok(ClientPdu::ClientCapability(CoreCapabilityPdu::from_buffer(
+                &mut stream,
+            )?)),
+            PacketId::DeviceListAnnounce => Ok(ClientPdu::DeviceListAnnounce(DeviceListAnnouncePdu::from_buffer(
+                &mut stream,
+            )?)),
+            PacketId::DeviceListRemove => Ok(ClientPdu::DeviceListRemove(DeviceListRemovePdu::from_buffer(
+                &mut stream,
+            )?)),
+            PacketId::DeviceIoCompletion => Ok(ClientPdu::DeviceIoCompletion(DeviceIoCompletionPdu::from_buffer(
+                &mut stream,
+            )?)),
+            PacketId::ServerAnnounce
+            | PacketId::ServerCapability
+            | PacketId::DeviceReply
+            | PacketId::DeviceIoRequest
+            | PacketId::UserLoggedOn => Err(RdpdrError::UnexpectedPacketId(packet_id)),
+        }
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        write_header(&mut stream, self.packet_id())?;
+
+        match self {
+            ClientPdu::ClientAnnounceReply(pdu) => pdu.to_buffer(&mut stream),
+            ClientPdu::ClientName(pdu) => pdu.to_buffer(&mut stream),
+            ClientPdu::ClientCapability(pdu) => pdu.to_buffer(&mut stream),
+            ClientPdu::DeviceListAnnounce(pdu) => pdu.to_buffer(&mut stream),
+            ClientPdu::DeviceListRemove(pdu) => pdu.to_buffer(&mut stream),
+            ClientPdu::DeviceIoCompletion(pdu) => pdu.to_buffer(&mut stream),
+        }
+    }
+
+    fn buffer_length(&self) -> usize {
+        HEADER_SIZE
+            + match self {
+                ClientPdu::ClientAnnounceReply(pdu) => pdu.buffer_length(),
+                ClientPdu::ClientName(pdu) => pdu.buffer_length(),
+                ClientPdu::ClientCapability(pdu) => pdu.buffer_length(),
+                ClientPdu::DeviceListAnnounce(pdu) => pdu.buffer_length(),
+                ClientPdu::DeviceListRemove(pdu) => pdu.buffer_length(),
+                ClientPdu::DeviceIoCompletion(pdu) => pdu.buffer_length(),
+            }
+    }
+}
+
+/// The Server Announce Request, Client Announce Reply and Server Client ID Confirm PDUs,
+/// which share the same layout.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionAndIdPdu {
+    pub version_major: u16,
+    pub version_minor: u16,
+    pub client_id: u32,
+}
+
+impl PduParsing for VersionAndIdPdu {
+    type Error = RdpdrError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        let version_major = stream.read_u16::<LittleEndian>()?;
+        let version_minor = stream.read_u16::<LittleEndian>()?;
+        let client_id = stream.read_u32::<LittleEndian>()?;
+
+        Ok(Self {
+            version_major,
+            version_minor,
+            client_id,
+        })
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        stream.write_u16::<LittleEndian>(self.version_major)?;
+        stream.write_u16::<LittleEndian>(self.version_minor)?;
+        stream.write_u32::<LittleEndian>(self.client_id)?;
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        VERSION_AND_ID_PDU_SIZE
+    }
+}
+
+/// The name of the client, displayed in the remote session along with the name of the redirected drives.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientNamePdu {
+    pub computer_name: String,
+}
+
+impl PduParsing for ClientNamePdu {
+    type Error = RdpdrError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        let character_set = if stream.read_u32::<LittleEndian>()? == 0 {
+            CharacterSet::Ansi
+        } else {
+            CharacterSet::Unicode
+        };
+        let _code_page = stream.read_u32::<LittleEndian>()?;
+        let computer_name_length = stream.read_u32::<LittleEndian>()? as usize;
+        let computer_name = utils::read_string(&mut stream, computer_name_length, character_set, false)?;
+
+        Ok(Self { computer_name })
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        stream.write_u32::<LittleEndian>(1)?; // Unicode flag
+        stream.write_u32::<LittleEndian>(0)?; // code page
+        stream.write_u32::<LittleEndian>(unicode_string_length(&self.computer_name) as u32)?;
+        utils::write_string_with_null_terminator(&mut stream, &self.computer_name, CharacterSet::Unicode)?;
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        CLIENT_NAME_PDU_FIXED_PART_SIZE + unicode_string_length(&self.computer_name)
+    }
+}
+
+/// The Server Core Capability Request and Client Core Capability Response PDUs, which share the same layout.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoreCapabilityPdu {
+    pub capabilities: Vec<CapabilitySet>,
+}
+
+impl PduParsing for CoreCapabilityPdu {
+    type Error = RdpdrError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        let capabilities_count = stream.read_u16::<LittleEndian>()?;
+        let _padding = stream.read_u16::<LittleEndian>()?;
+        let capabilities = (0..capabilities_count)
+            .map(|_| CapabilitySet::from_buffer(&mut stream))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self { capabilities })
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        stream.write_u16::<LittleEndian>(self.capabilities.len() as u16)?;
+        stream.write_u16::<LittleEndian>(0)?; // padding
+
+        for capability in self.capabilities.iter() {
+            capability.to_buffer(&mut stream)?;
+        }
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        CAPABILITIES_PDU_FIXED_PART_SIZE
+            + self
+                .capabilities
+                .iter()
+                .map(|capability| capability.buffer_length())
+                .sum::<usize>()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CapabilitySet {
+    General(GeneralCapabilitySet),
+    Printer { version: u32 },
+    Port { version: u32 },
+    Drive { version: u32 },
+    Smartcard { version: u32 },
+}
+
+impl CapabilitySet {
+    fn capability_type(&self) -> CapabilityType {
+        match self {
+            CapabilitySet::General(_) => CapabilityType::General,
+            CapabilitySet::Printer { .. } => CapabilityType::Printer,
+            CapabilitySet::Port { .. } => CapabilityType::Port,
+            CapabilitySet::Drive { .. } => CapabilityType::Drive,
+            CapabilitySet::Smartcard { .. } => CapabilityType::Smartcard,
+        }
+    }
+
+    fn version(&self) -> u32 {
+        match self {
+            CapabilitySet::General(capability) => capability.version,
+            CapabilitySet::Printer { version }
+            | CapabilitySet::Port { version }
+            | CapabilitySet::Drive { version }
+            | CapabilitySet::Smartcard { version } => *version,
+        }
+    }
+}
+
+impl PduParsing for CapabilitySet {
+    type Error = RdpdrError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        let capability_type = stream.read_u16::<LittleEndian>()?;
+        let capability_type =
+            CapabilityType::from_u16(capability_type).ok_or(RdpdrError::InvalidCapabilityType(capability_type))?;
+        let length = stream.read_u16::<LittleEndian>()?;
+        let body_size = usize::from(length)
+            .checked_sub(CAPABILITY_HEADER_SIZE)
+            .ok_or(RdpdrError::InvalidCapabilityLength(length))?;
+        let version = stream.read_u32::<LittleEndian>()?;
+
+        let mut body = vec![0; body_size];
+        stream.read_exact(&mut body)?;
+
+        match capability_type {
+            CapabilityType::General => Ok(CapabilitySet::General(GeneralCapabilitySet::from_body(
+                body.as_slice(),
+                version,
+            )?)),
+            // the capability sets of the devices have no body, which is ignored if any
+            CapabilityType::Printer => Ok(CapabilitySet::Printer { version }),
+            CapabilityType::Port => Ok(CapabilitySet::Port { version }),
+            CapabilityType::Drive => Ok(CapabilitySet::Drive { version }),
+            CapabilityType::Smartcard => Ok(CapabilitySet::Smartcard { version }),
+        }
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        stream.write_u16::<LittleEndian>(self.capability_type().to_u16().unwrap())?;
+        stream.write_u16::<LittleEndian>(self.buffer_length() as u16)?;
+        stream.write_u32::<LittleEndian>(self.version())?;
+
+        if let CapabilitySet::General(capability) = self {
+            capability.to_body(&mut stream)?;
+        }
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        CAPABILITY_HEADER_SIZE
+            + match self {
+                CapabilitySet::General(capability) => capability.body_size(),
+                _ => 0,
+            }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GeneralCapabilitySet {
+    /// [`RDPDR_GENERAL_CAPABILITY_VERSION_02`] to send `special_type_device_cap`.
+    pub version: u32,
+    pub os_type: u32,
+    pub os_version: u32,
+    pub protocol_major_version: u16,
+    pub protocol_minor_version: u16,
+    pub io_code1: IoCode1,
+    pub extended_pdu: ExtendedPdu,
+    pub extra_flags1: ExtraFlags1,
+    /// The number of special devices (smart cards, serial ports) which can be redirected before the user logs on.
+    pub special_type_device_cap: u32,
+}
+
+impl GeneralCapabilitySet {
+    fn from_body(mut stream: impl io::Read, version: u32) -> Result<Self, RdpdrError> {
+        let os_type = stream.read_u32::<LittleEndian>()?;
+        let os_version = stream.read_u32::<LittleEndian>()?;
+        let protocol_major_version = stream.read_u16::<LittleEndian>()?;
+        let protocol_minor_version = stream.read_u16::<LittleEndian>()?;
+        let io_code1 = IoCode1::from_bits_truncate(stream.read_u32::<LittleEndian>()?);
+        let _io_code2 = stream.read_u32::<LittleEndian>()?;
+        let extended_pdu = ExtendedPdu::from_bits_truncate(stream.read_u32::<LittleEndian>()?);
+        let extra_flags1 = ExtraFlags1::from_bits_truncate(stream.read_u32::<LittleEndian>()?);
+        let _extra_flags2 = stream.read_u32::<LittleEndian>()?;
+        let special_type_device_cap = if version >= RDPDR_GENERAL_CAPABILITY_VERSION_02 {
+            stream.read_u32::<LittleEndian>()?
+        } else {
+            0
+        };
+
+        Ok(Self {
+            version,
+            os_type,
+            os_version,
+            protocol_major_version,
+            protocol_minor_version,
+            io_code1,
+            extended_pdu,
+            extra_flags1,
+            special_type_device_cap,
+        })
+    }
+
+    fn to_body(&self, mut stream: impl io::Write) -> Result<(), RdpdrError> {
+        stream.write_u32::<LittleEndian>(self.os_type)?;
+        stream.write_u32::<LittleEndian>(self.os_version)?;
+        stream.write_u16::<LittleEndian>(self.protocol_major_version)?;
+        stream.write_u16::<LittleEndian>(self.protocol_minor_version)?;
+        stream.write_u32::<LittleEndian>(self.io_code1.bits())?;
+        stream.write_u32::<LittleEndian>(0)?; // I/O code 2
+        stream.write_u32::<LittleEndian>(self.extended_pdu.bits())?;
+        stream.write_u32::<LittleEndian>(self.extra_flags1.bits())?;
+        stream.write_u32::<LittleEndian>(0)?; // extra flags 2
+        if self.version >= RDPDR_GENERAL_CAPABILITY_VERSION_02 {
+            stream.write_u32::<LittleEndian>(self.special_type_device_cap)?;
+        }
+
+        Ok(())
+    }
+
+    fn body_size(&self) -> usize {
+        GENERAL_CAPABILITY_V1_BODY_SIZE
+            + if self.version >= RDPDR_GENERAL_CAPABILITY_VERSION_02 {
+                SPECIAL_TYPE_DEVICE_CAP_SIZE
+            } else {
+                0
+            }
+    }
+}
+
+#[repr(u16)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, FromPrimitive, ToPrimitive)]
+pub enum CapabilityType {
+    General = 0x0001,
+    Printer = 0x0002,
+    Port = 0x0003,
+    Drive = 0x0004,
+    Smartcard = 0x0005,
+}
+
+bitflags! {
+    /// The I/O requests the client supports.
+    pub struct IoCode1: u32 {
+        const CREATE = 0x0000_0001;
+        const CLEANUP = 0x0000_0002;
+        const CLOSE = 0x0000_0004;
+        const READ = 0x0000_0008;
+        const WRITE = 0x0000_0010;
+        const FLUSH_BUFFERS = 0x0000_0020;
+        const SHUTDOWN = 0x0000_0040;
+        const DEVICE_CONTROL = 0x0000_0080;
+        const QUERY_VOLUME_INFORMATION = 0x0000_0100;
+        const SET_VOLUME_INFORMATION = 0x0000_0200;
+        const QUERY_INFORMATION = 0x0000_0400;
+        const SET_INFORMATION = 0x0000_0800;
+        const DIRECTORY_CONTROL = 0x0000_1000;
+        const LOCK_CONTROL = 0x0000_2000;
+        const QUERY_SECURITY = 0x0000_4000;
+        const SET_SECURITY = 0x0000_8000;
+    }
+}
+
+bitflags! {
+    pub struct ExtendedPdu: u32 {
+        const DEVICE_REMOVE_PDUS = 0x0000_0001;
+        const CLIENT_DISPLAY_NAME_PDU = 0x0000_0002;
+        const USER_LOGGEDON_PDU = 0x0000_0004;
+    }
+}
+
+bitflags! {
+    pub struct ExtraFlags1: u32 {
+        const ENABLE_ASYNCIO = 0x0000_0001;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceListAnnouncePdu {
+    pub devices: Vec<DeviceAnnounce>,
+}
+
+impl PduParsing for DeviceListAnnouncePdu {
+    type Error = RdpdrError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        let device_count = stream.read_u32::<LittleEndian>()?;
+        let devices = (0..device_count)
+            .map(|_| DeviceAnnounce::from_buffer(&mut stream))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self { devices })
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        stream.write_u32::<LittleEndian>(self.devices.len() as u32)?;
+
+        for device in self.devices.iter() {
+            device.to_buffer(&mut stream)?;
+        }
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        DEVICE_COUNT_SIZE + self.devices.iter().map(|device| device.buffer_length()).sum::<usize>()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceAnnounce {
+    pub device_type: DeviceType,
+    /// Chosen by the client, unique among its devices.
+    pub device_id: u32,
+    /// ASCII name of at most 7 characters.
+    pub preferred_dos_name: String,
+    pub device_data: Vec<u8>,
+}
+
+impl DeviceAnnounce {
+    /// A drive displayed with its name in the remote session, which is also its DOS name
+    /// when it is made of at most 7 ASCII characters.
+    pub fn drive(device_id: u32, name: &str) -> Self {
+        let preferred_dos_name = name
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric())
+            .take(PREFERRED_DOS_NAME_SIZE - 1)
+            .collect();
+
+        let mut device_data = utils::string_to_utf16(name);
+        device_data.extend_from_slice(&[0, 0]);
+
+        Self {
+            device_type: DeviceType::Filesystem,
+            device_id,
+            preferred_dos_name,
+            device_data,
+        }
+    }
+
+    /// The smart card device, whose I/O control requests are the calls of the [`scard`] module.
+    pub fn smartcard(device_id: u32) -> Self {
+        Self {
+            device_type: DeviceType::Smartcard,
+            device_id,
+            preferred_dos_name: String::from("SCARD"),
+            device_data: Vec::new(),
+        }
+    }
+}
+
+impl PduParsing for DeviceAnnounce {
+    type Error = RdpdrError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        let device_type = stream.read_u32::<LittleEndian>()?;
+        let device_type = DeviceType::from_u32(device_type).ok_or(RdpdrError::InvalidDeviceType(device_type))?;
+        let device_id = stream.read_u32::<LittleEndian>()?;
+        let preferred_dos_name = utils::read_string(&mut stream, PREFERRED_DOS_NAME_SIZE, CharacterSet::Ansi, false)?;
+        let device_data_length = stream.read_u32::<LittleEndian>()?;
+        let mut device_data = vec![0; device_data_length as usize];
+        stream.read_exact(&mut device_data)?;
+
+        Ok(Self {
+            device_type,
+            device_id,
+            preferred_dos_name,
+            device_data,
+        })
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        if !self.preferred_dos_name.is_ascii() || self.preferred_dos_name.len() >= PREFERRED_DOS_NAME_SIZE {
+            return Err(RdpdrError::InvalidPreferredDosName(self.preferred_dos_name.clone()));
+        }
+
+        stream.write_u32::<LittleEndian>(self.device_type.to_u32().unwrap())?;
+        stream.write_u32::<LittleEndian>(self.device_id)?;
+        let mut preferred_dos_name = [0; PREFERRED_DOS_NAME_SIZE];
+        preferred_dos_name[..self.preferred_dos_name.len()].copy_from_slice(self.preferred_dos_name.as_bytes());
+        stream.write_all(&preferred_dos_name)?;
+        stream.write_u32::<LittleEndian>(self.device_data.len() as u32)?;
+        stream.write_all(&self.device_data)?;
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        DEVICE_ANNOUNCE_FIXED_PART_SIZE + self.device_data.len()
+    }
+}
+
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, FromPrimitive, ToPrimitive)]
+pub enum DeviceType {
+    Serial = 0x0000_0001,
+    Parallel = 0x0000_0002,
+    Print = 0x0000_0004,
+    Filesystem = 0x0000_0008,
+    Smartcard = 0x0000_0020,
+}
+
+/// Sent by the client when devices are not available anymore, e.g. when a drive is unplugged.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceListRemovePdu {
+    pub device_ids: Vec<u32>,
+}
+
+impl PduParsing for DeviceListRemovePdu {
+    type Error = RdpdrError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        let device_count = stream.read_u32::<LittleEndian>()?;
+        let device_ids = (0..device_count)
+            .map(|_| stream.read_u32::<LittleEndian>())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self { device_ids })
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        stream.write_u32::<LittleEndian>(self.device_ids.len() as u32)?;
+
+        for device_id in self.device_ids.iter() {
+            stream.write_u32::<LittleEndian>(*device_id)?;
+        }
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        DEVICE_COUNT_SIZE + self.device_ids.len() * 4
+    }
+}
+
+/// Sent by the server for each announced device, `result_code` being [`NtStatus::SUCCESS`] if the device has been accepted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceReplyPdu {
+    pub device_id: u32,
+    pub result_code: NtStatus,
+}
+
+impl PduParsing for DeviceReplyPdu {
+    type Error = RdpdrError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        let device_id = stream.read_u32::<LittleEndian>()?;
+        let result_code = NtStatus(stream.read_u32::<LittleEndian>()?);
+
+        Ok(Self { device_id, result_code })
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        stream.write_u32::<LittleEndian>(self.device_id)?;
+        stream.write_u32::<LittleEndian>(self.result_code.0)?;
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        DEVICE_REPLY_PDU_SIZE
+    }
+}
+
+/// An I/O Request Packet (IRP) sent by the server to a device, answered with a [`DeviceIoCompletionPdu`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceIoRequestPdu {
+    pub device_id: u32,
+    /// The file opened by a previous [`IoRequest::Create`] request, which the client has chosen.
+    pub file_id: u32,
+    /// Identifies the request in its response.
+    pub completion_id: u32,
+    pub request: IoRequest,
+}
+
+impl PduParsing for DeviceIoRequestPdu {
+    type Error = RdpdrError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        let device_id = stream.read_u32::<LittleEndian>()?;
+        let file_id = stream.read_u32::<LittleEndian>()?;
+        let completion_id = stream.read_u32::<LittleEndian>()?;
+        let major_function = stream.read_u32::<LittleEndian>()?;
+        let minor_function = stream.read_u32::<LittleEndian>()?;
+        let request = IoRequest::from_buffer(&mut stream, major_function, minor_function)?;
+
+        Ok(Self {
+            device_id,
+            file_id,
+            completion_id,
+            request,
+        })
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        let (major_function, minor_function) = self.request.functions();
+
+        stream.write_u32::<LittleEndian>(self.device_id)?;
+        stream.write_u32::<LittleEndian>(self.file_id)?;
+        stream.write_u32::<LittleEndian>(self.completion_id)?;
+        stream.write_u32::<LittleEndian>(major_function)?;
+        stream.write_u32::<LittleEndian>(minor_function)?;
+        self.request.to_buffer(&mut stream)
+    }
+
+    fn buffer_length(&self) -> usize {
+        DEVICE_IO_REQUEST_HEADER_SIZE + self.request.buffer_length()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IoRequest {
+    Create(CreateRequest),
+    Close,
+    Read {
+        length: u32,
+        offset: u64,
+    },
+    Write {
+        offset: u64,
+        data: Vec<u8>,
+    },
+    QueryInformation {
+        information_class: FileInformationClass,
+    },
+    SetInformation(SetInformation),
+    QueryVolumeInformation {
+        information_class: FsInformationClass,
+    },
+    /// Requests the next entry of the directory matching the pattern, the pattern being
+    /// only sent with the initial query.
+    QueryDirectory {
+        information_class: FileInformationClass,
+        initial_query: bool,
+        pattern: String,
+    },
+    NotifyChangeDirectory {
+        watch_tree: bool,
+        completion_filter: u32,
+    },
+    DeviceControl {
+        output_buffer_length: u32,
+        io_control_code: u32,
+        input_buffer: Vec<u8>,
+    },
+    /// A request the client does not interpret, e.g. a lock control request.
+    Other {
+        major_function: u32,
+        minor_function: u32,
+        data: Vec<u8>,
+    },
+}
+
+impl IoRequest {
+    fn from_buffer(mut stream: impl io::Read, major_function: u32, minor_function: u32) -> Result<Self, RdpdrError> {
+        match (MajorFunction::from_u32(major_function), minor_function) {
+            (Some(MajorFunction::Create), _) => Ok(IoRequest::Create(CreateRequest::from_buffer(&mut stream)?)),
+            (Some(MajorFunction::Close), _) => {
+                let mut padding = [0; CLOSE_REQUEST_PADDING_SIZE];
+                stream.read_exact(&mut padding)?;
+
+                Ok(IoRequest::Close)
+            }
+            (Some(MajorFunction::Read), _) => {
+                let length = stream.read_u32::<LittleEndian>()?;
+                let offset = stream.read_u64::<LittleEndian>()?;
+                let mut padding = [0; READ_WRITE_REQUEST_PADDING_SIZE];
+                stream.read_exact(&mut padding)?;
+
+                Ok(IoRequest::Read { length, offset })
+            }
+            (Some(MajorFunction::Write), _) => {
+                let length = stream.read_u32::<LittleEndian>()?;
+                let offset = stream.read_u64::<LittleEndian>()?;
+                let mut padding = [0; READ_WRITE_REQUEST_PADDING_SIZE];
+                stream.read_exact(&mut padding)?;
+                let mut data = vec![0; length as usize];
+                stream.read_exact(&mut data)?;
+
+                Ok(IoRequest::Write { offset, data })
+            }
+            (Some(MajorFunction::QueryInformation), _) => {
+                let (information_class, _query_buffer) = read_information_request(&mut stream)?;
+
+                Ok(IoRequest::QueryInformation {
+                    information_class: FileInformationClass(information_class),
+                })
+            }
+            (Some(MajorFunction::SetInformation), _) => {
+                let (information_class, set_buffer) = read_information_request(&mut stream)?;
+
+                Ok(IoRequest::SetInformation(SetInformation::from_buffer(
+                    FileInformationClass(information_class),
+                    set_buffer.as_slice(),
+                )?))
+            }
+            (Some(MajorFunction::QueryVolumeInformation), _) => {
+                let (information_class, _query_buffer) = read_information_request(&mut stream)?;
+
+                Ok(IoRequest::QueryVolumeInformation {
+                    information_class: FsInformationClass(information_class),
+                })
+            }
+            (Some(MajorFunction::DirectoryControl), MINOR_FUNCTION_QUERY_DIRECTORY) => {
+                let information_class = FileInformationClass(stream.read_u32::<LittleEndian>()?);
+                let initial_query = stream.read_u8()? != 0;
+                let path_length = stream.read_u32::<LittleEndian>()? as usize;
+                let mut padding = [0; QUERY_DIRECTORY_REQUEST_PADDING_SIZE];
+                stream.read_exact(&mut padding)?;
+                let pattern = utils::read_string(&mut stream, path_length, CharacterSet::Unicode, false)?;
+
+                Ok(IoRequest::QueryDirectory {
+                    information_class,
+                    initial_query,
+                    pattern,
+                })
+            }
+            (Some(MajorFunction::DirectoryControl), MINOR_FUNCTION_NOTIFY_CHANGE_DIRECTORY) => {
+                let watch_tree = stream.read_u8()? != 0;
+                let completion_filter = stream.read_u32::<LittleEndian>()?;
+                let mut padding = [0; NOTIFY_CHANGE_DIRECTORY_REQUEST_PADDING_SIZE];
+                stream.read_exact(&mut padding)?;
+
+                Ok(IoRequest::NotifyChangeDirectory {
+                    watch_tree,
+                    completion_filter,
+                })
+            }
+            (Some(MajorFunction::DeviceControl), _) => {
+                let output_buffer_length = stream.read_u32::<LittleEndian>()?;
+                let input_buffer_length = stream.read_u32::<LittleEndian>()?;
+                let io_control_code = stream.read_u32::<LittleEndian>()?;
+                let mut padding = [0; DEVICE_CONTROL_REQUEST_PADDING_SIZE];
+                stream.read_exact(&mut padding)?;
+                let mut input_buffer = vec![0; input_buffer_length as usize];
+                stream.read_exact(&mut input_buffer)?;
+
+                Ok(IoRequest::DeviceControl {
+                    output_buffer_length,
+                    io_control_code,
+                    input_buffer,
+                })
+            }
+            _ => {
+                let mut data = Vec::new();
+                stream.read_to_end(&mut data)?;
+
+                Ok(IoRequest::Other {
+                    major_function,
+                    minor_function,
+                    data,
+                })
+            }
+        }
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), RdpdrError> {
+        match self {
+            IoRequest::Create(request) => request.to_buffer(&mut stream)?,
+            IoRequest::Close => stream.write_all(&[0; CLOSE_REQUEST_PADDING_SIZE])?,
+            IoRequest::Read { length, offset } => {
+                stream.write_u32::<LittleEndian>(*length)?;
+                stream.write_u64::<LittleEndian>(*offset)?;
+                stream.write_all(&[0; READ_WRITE_REQUEST_PADDING_SIZE])?;
+            }
+            IoRequest::Write { offset, data } => {
+                stream.write_u32::<LittleEndian>(data.len() as u32)?;
+                stream.write_u64::<LittleEndian>(*offset)?;
+                stream.write_all(&[0; READ_WRITE_REQUEST_PADDING_SIZE])?;
+                stream.write_all(data)?;
+            }
+            IoRequest::QueryInformation { information_class } => {
+                write_information_request(&mut stream, information_class.0, &[])?;
+            }
+            IoRequest::SetInformation(set_information) => {
+                let mut set_buffer = Vec::with_capacity(set_information.buffer_length());
+                set_information.to_buffer(&mut set_buffer)?;
+                write_information_request(&mut stream, set_information.information_class().0, &set_buffer)?;
+            }
+            IoRequest::QueryVolumeInformation { information_class } => {
+                write_information_request(&mut stream, information_class.0, &[])?;
+            }
+            IoRequest::QueryDirectory {
+                information_class,
+                initial_query,
+                pattern,
+            } => {
+                stream.write_u32::<LittleEndian>(information_class.0)?;
+                stream.write_u8(u8::from(*initial_query))?;
+                stream.write_u32::<LittleEndian>(optional_unicode_string_length(pattern) as u32)?;
+                stream.write_all(&[0; QUERY_DIRECTORY_REQUEST_PADDING_SIZE])?;
+                if !pattern.is_empty() {
+                    utils::write_string_with_null_terminator(&mut stream, pattern, CharacterSet::Unicode)?;
+                }
+            }
+            IoRequest::NotifyChangeDirectory {
+                watch_tree,
+                completion_filter,
+            } => {
+                stream.write_u8(u8::from(*watch_tree))?;
+                stream.write_u32::<LittleEndian>(*completion_filter)?;
+                stream.write_all(&[0; NOTIFY_CHANGE_DIRECTORY_REQUEST_PADDING_SIZE])?;
+            }
+            IoRequest::DeviceControl {
+                output_buffer_length,
+                io_control_code,
+                input_buffer,
+            } => {
+                stream.write_u32::<LittleEndian>(*output_buffer_length)?;
+                stream.write_u32::<LittleEndian>(input_buffer.len() as u32)?;
+                stream.write_u32::<LittleEndian>(*io_control_code)?;
+                stream.write_all(&[0; DEVICE_CONTROL_REQUEST_PADDING_SIZE])?;
+                stream.write_all(input_buffer)?;
+            }
+            IoRequest::Other { data, .. } => stream.write_all(data)?,
+        }
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        match self {
+            IoRequest::Create(request) => request.buffer_length(),
+            IoRequest::Close => CLOSE_REQUEST_PADDING_SIZE,
+            IoRequest::Read { .. } => 12 + READ_WRITE_REQUEST_PADDING_SIZE,
+            IoRequest::Write { data, .. } => 12 + READ_WRITE_REQUEST_PADDING_SIZE + data.len(),
+            IoRequest::QueryInformation { .. } | IoRequest::QueryVolumeInformation { .. } => {
+                8 + INFORMATION_REQUEST_PADDING_SIZE
+            }
+            IoRequest::SetInformation(set_information) => {
+                8 + INFORMATION_REQUEST_PADDING_SIZE + set_information.buffer_length()
+            }
+            IoRequest::QueryDirectory { pattern, .. } => {
+                9 + QUERY_DIRECTORY_REQUEST_PADDING_SIZE + optional_unicode_string_length(pattern)
+            }
+            IoRequest::NotifyChangeDirectory { .. } => 5 + NOTIFY_CHANGE_DIRECTORY_REQUEST_PADDING_SIZE,
+            IoRequest::DeviceControl { input_buffer, .. } => {
+                12 + DEVICE_CONTROL_REQUEST_PADDING_SIZE + input_buffer.len()
+            }
+            IoRequest::Other { data, .. } => data.len(),
+        }
+    }
+
+    fn functions(&self) -> (u32, u32) {
+        let major_function = match self {
+            IoRequest::Create(_) => MajorFunction::Create,
+            IoRequest::Close => MajorFunction::Close,
+            IoRequest::Read { .. } => MajorFunction::Read,
+            IoRequest::Write { .. } => MajorFunction::Write,
+            IoRequest::QueryInformation { .. } => MajorFunction::QueryInformation,
+            IoRequest::SetInformation(_) => MajorFunction::SetInformation,
+            IoRequest::QueryVolumeInformation { .. } => MajorFunction::QueryVolumeInformation,
+            IoRequest::QueryDirectory { .. } => {
+                return (
+                    MajorFunction::DirectoryControl.to_u32().unwrap(),
+                    MINOR_FUNCTION_QUERY_DIRECTORY,
+                )
+            }
+            IoRequest::NotifyChangeDirectory { .. } => {
+                return (
+                    MajorFunction::DirectoryControl.to_u32().unwrap(),
+                    MINOR_FUNCTION_NOTIFY_CHANGE_DIRECTORY,
+                )
+            }
+            IoRequest::DeviceControl { .. } => MajorFunction::DeviceControl,
+            IoRequest::Other {
+                major_function,
+                minor_function,
+                ..
+            } => return (*major_function, *minor_function),
+        };
+
+        (major_function.to_u32().unwrap(), MINOR_FUNCTION_NONE)
+    }
+}
+
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, FromPrimitive, ToPrimitive)]
+pub enum MajorFunction {
+    Create = 0x0000_0000,
+    Close = 0x0000_0002,
+    Read = 0x0000_0003,
+    Write = 0x0000_0004,
+    QueryInformation = 0x0000_0005,
+    SetInformation = 0x0000_0006,
+    QueryVolumeInformation = 0x0000_000A,
+    SetVolumeInformation = 0x0000_000B,
+    DirectoryControl = 0x0000_000C,
+    DeviceControl = 0x0000_000E,
+    LockControl = 0x0000_0011,
+}
+
+/// Opens or creates the file or directory at the path, relative to the root of the device.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CreateRequest {
+    pub desired_access: u32,
+    pub allocation_size: u64,
+    pub file_attributes: FileAttributes,
+    pub shared_access: u32,
+    pub create_disposition: CreateDisposition,
+    pub create_options: CreateOptions,
+    /// `\` separated path, empty for the root of the device.
+    pub path: String,
+}
+
+impl PduParsing for CreateRequest {
+    type Error = RdpdrError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        let desired_access = stream.read_u32::<LittleEndian>()?;
+        let allocation_size = stream.read_u64::<LittleEndian>()?;
+        let file_attributes = FileAttributes::from_bits_truncate(stream.read_u32::<LittleEndian>()?);
+        let shared_access = stream.read_u32::<LittleEndian>()?;
+        let create_disposition = stream.read_u32::<LittleEndian>()?;
+        let create_disposition = CreateDisposition::from_u32(create_disposition)
+            .ok_or(RdpdrError::InvalidCreateDisposition(create_disposition))?;
+        let create_options = CreateOptions::from_bits_truncate(stream.read_u32::<LittleEndian>()?);
+        let path_length = stream.read_u32::<LittleEndian>()? as usize;
+        let path = utils::read_string(&mut stream, path_length, CharacterSet::Unicode, false)?;
+
+        Ok(Self {
+            desired_access,
+            allocation_size,
+            file_attributes,
+            shared_access,
+            create_disposition,
+            create_options,
+            path,
+        })
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        stream.write_u32::<LittleEndian>(self.desired_access)?;
+        stream.write_u64::<LittleEndian>(self.allocation_size)?;
+        stream.write_u32::<LittleEndian>(self.file_attributes.bits())?;
+        stream.write_u32::<LittleEndian>(self.shared_access)?;
+        stream.write_u32::<LittleEndian>(self.create_disposition.to_u32().unwrap())?;
+        stream.write_u32::<LittleEndian>(self.create_options.bits())?;
+        stream.write_u32::<LittleEndian>(optional_unicode_string_length(&self.path) as u32)?;
+        if !self.path.is_empty() {
+            utils::write_string_with_null_terminator(&mut stream, &self.path, CharacterSet::Unicode)?;
+        }
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        CREATE_REQUEST_FIXED_PART_SIZE + optional_unicode_string_length(&self.path)
+    }
+}
+
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, FromPrimitive, ToPrimitive)]
+pub enum CreateDisposition {
+    /// Replaces the file if it exists, creates it otherwise.
+    Supersede = 0x0000_0000,
+    /// Fails if the file does not exist.
+    Open = 0x0000_0001,
+    /// Fails if the file exists.
+    Create = 0x0000_0002,
+    OpenIf = 0x0000_0003,
+    /// Truncates the file, fails if it does not exist.
+    Overwrite = 0x0000_0004,
+    OverwriteIf = 0x0000_0005,
+}
+
+bitflags! {
+    pub struct CreateOptions: u32 {
+        const DIRECTORY_FILE = 0x0000_0001;
+        const WRITE_THROUGH = 0x0000_0002;
+        const SEQUENTIAL_ONLY = 0x0000_0004;
+        const SYNCHRONOUS_IO_NONALERT = 0x0000_0020;
+        const NON_DIRECTORY_FILE = 0x0000_0040;
+        const DELETE_ON_CLOSE = 0x0000_1000;
+        const OPEN_REPARSE_POINT = 0x0020_0000;
+    }
+}
+
+/// The information set on an opened file by a [`IoRequest::SetInformation`] request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SetInformation {
+    Basic(FileBasicInformation),
+    EndOfFile(u64),
+    Allocation(u64),
+    Disposition {
+        delete_pending: bool,
+    },
+    Rename {
+        replace_if_exists: bool,
+        file_name: String,
+    },
+    Other {
+        information_class: FileInformationClass,
+        data: Vec<u8>,
+    },
+}
+
+impl SetInformation {
+    pub fn information_class(&self) -> FileInformationClass {
+        match self {
+            SetInformation::Basic(_) => FileInformationClass::BASIC,
+            SetInformation::EndOfFile(_) => FileInformationClass::END_OF_FILE,
+            SetInformation::Allocation(_) => FileInformationClass::ALLOCATION,
+            SetInformation::Disposition { .. } => FileInformationClass::DISPOSITION,
+            SetInformation::Rename { .. } => FileInformationClass::RENAME,
+            SetInformation::Other { information_class, .. } => *information_class,
+        }
+    }
+
+    fn from_buffer(information_class: FileInformationClass, mut buffer: &[u8]) -> Result<Self, RdpdrError> {
+        match information_class {
+            FileInformationClass::BASIC => Ok(SetInformation::Basic(FileBasicInformation::from_buffer(buffer)?)),
+            FileInformationClass::END_OF_FILE => Ok(SetInformation::EndOfFile(buffer.read_u64::<LittleEndian>()?)),
+            FileInformationClass::ALLOCATION => Ok(SetInformation::Allocation(buffer.read_u64::<LittleEndian>()?)),
+            // the deletion is implied when the buffer is empty
+            FileInformationClass::DISPOSITION => Ok(SetInformation::Disposition {
+                delete_pending: buffer.first().map_or(true, |delete_pending| *delete_pending != 0),
+            }),
+            FileInformationClass::RENAME => {
+                let replace_if_exists = buffer.read_u8()? != 0;
+                let _root_directory = buffer.read_u8()?;
+                let file_name_length = buffer.read_u32::<LittleEndian>()? as usize;
+                let file_name = utils::read_string(&mut buffer, file_name_length, CharacterSet::Unicode, false)?;
+
+                Ok(SetInformation::Rename {
+                    replace_if_exists,
+                    file_name,
+                })
+            }
+            information_class => Ok(SetInformation::Other {
+                information_class,
+                data: buffer.to_vec(),
+            }),
+        }
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), RdpdrError> {
+        match self {
+            SetInformation::Basic(information) => information.to_buffer(&mut stream)?,
+            SetInformation::EndOfFile(size) | SetInformation::Allocation(size) => {
+                stream.write_u64::<LittleEndian>(*size)?
+            }
+            SetInformation::Disposition { delete_pending } => stream.write_u8(u8::from(*delete_pending))?,
+            SetInformation::Rename {
+                replace_if_exists,
+                file_name,
+            } => {
+                stream.write_u8(u8::from(*replace_if_exists))?;
+                stream.write_u8(0)?; // root directory
+                stream.write_u32::<LittleEndian>(unicode_string_length(file_name) as u32)?;
+                utils::write_string_with_null_terminator(&mut stream, file_name, CharacterSet::Unicode)?;
+            }
+            SetInformation::Other { data, .. } => stream.write_all(data)?,
+        }
+
+        Ok(())
+    }
+
+    pub fn buffer_length(&self) -> usize {
+        match self {
+            SetInformation::Basic(information) => information.buffer_length(),
+            SetInformation::EndOfFile(_) | SetInformation::Allocation(_) => 8,
+            SetInformation::Disposition { .. } => 1,
+            SetInformation::Rename { file_name, .. } => 6 + unicode_string_length(file_name),
+            SetInformation::Other { data, .. } => data.len(),
+        }
+    }
+}
+
+/// The completion of a [`DeviceIoRequestPdu`], whose output depends on the request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceIoCompletionPdu {
+    pub device_id: u32,
+    pub completion_id: u32,
+    pub io_status: NtStatus,
+    /// The output encoded by [`IoResponse::to_buffer`].
+    pub output: Vec<u8>,
+}
+
+impl DeviceIoCompletionPdu {
+    pub fn new(request: &DeviceIoRequestPdu, io_status: NtStatus, response: &IoResponse) -> Self {
+        let mut output = Vec::with_capacity(response.buffer_length());
+        response.to_buffer(&mut output);
+
+        Self {
+            device_id: request.device_id,
+            completion_id: request.completion_id,
+            io_status,
+            output,
+        }
+    }
+}
+
+impl PduParsing for DeviceIoCompletionPdu {
+    type Error = RdpdrError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        let device_id = stream.read_u32::<LittleEndian>()?;
+        let completion_id = stream.read_u32::<LittleEndian>()?;
+        let io_status = NtStatus(stream.read_u32::<LittleEndian>()?);
+        let mut output = Vec::new();
+        stream.read_to_end(&mut output)?;
+
+        Ok(Self {
+            device_id,
+            completion_id,
+            io_status,
+            output,
+        })
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        stream.write_u32::<LittleEndian>(self.device_id)?;
+        stream.write_u32::<LittleEndian>(self.completion_id)?;
+        stream.write_u32::<LittleEndian>(self.io_status.0)?;
+        stream.write_all(&self.output)?;
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        DEVICE_IO_RESPONSE_HEADER_SIZE + self.output.len()
+    }
+}
+
+/// The output of the response to an I/O request, which is sent even if the request failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IoResponse {
+    Create {
+        file_id: u32,
+        information: CreateInformation,
+    },
+    Close,
+    Read {
+        data: Vec<u8>,
+    },
+    Write {
+        length: u32,
+    },
+    /// The response to the query of the file, volume or directory information, holding the encoded information.
+    Information {
+        buffer: Vec<u8>,
+    },
+    SetInformation {
+        length: u32,
+    },
+    DeviceControl {
+        output_buffer: Vec<u8>,
+    },
+    LockControl,
+    /// The response to a request which is not supported.
+    Empty,
+}
+
+impl IoResponse {
+    pub fn to_buffer(&self, output: &mut Vec<u8>) {
+        match self {
+            IoResponse::Create { file_id, information } => {
+                output.extend_from_slice(&file_id.to_le_bytes());
+                output.push(information.to_u8().unwrap());
+            }
+            IoResponse::Close => output.extend_from_slice(&[0; 4]),
+            IoResponse::Read { data } => {
+                output.extend_from_slice(&(data.len() as u32).to_le_bytes());
+                output.extend_from_slice(data);
+            }
+            IoResponse::Write { length } | IoResponse::SetInformation { length } => {
+                output.extend_from_slice(&length.to_le_bytes());
+                output.push(0); // padding
+            }
+            IoResponse::Information { buffer } => {
+                output.extend_from_slice(&(buffer.len() as u32).to_le_bytes());
+                output.extend_from_slice(buffer);
+                if buffer.is_empty() {
+                    output.push(0); // padding
+                }
+            }
+            IoResponse::DeviceControl { output_buffer } => {
+                output.extend_from_slice(&(output_buffer.len() as u32).to_le_bytes());
+                output.extend_from_slice(output_buffer);
+            }
+            IoResponse::LockControl => output.extend_from_slice(&[0; 5]),
+            IoResponse::Empty => (),
+        }
+    }
+
+    pub fn buffer_length(&self) -> usize {
+        match self {
+            IoResponse::Create { .. } => 5,
+            IoResponse::Close => 4,
+            IoResponse::Read { data } => 4 + data.len(),
+            IoResponse::Write { .. } | IoResponse::SetInformation { .. } => 5,
+            IoResponse::Information { buffer } => 4 + buffer.len().max(1),
+            IoResponse::DeviceControl { output_buffer } => 4 + output_buffer.len(),
+            IoResponse::LockControl => 5,
+            IoResponse::Empty => 0,
+        }
+    }
+}
+
+#[repr(u8)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, FromPrimitive, ToPrimitive)]
+pub enum CreateInformation {
+    /// A new file has been created.
+    Superseded = 0x00,
+    Opened = 0x01,
+    Overwritten = 0x03,
+}
+
+/// The status of a completed request (MS-ERREF 2.3).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct NtStatus(pub u32);
+
+impl NtStatus {
+    pub const SUCCESS: Self = Self(0x0000_0000);
+    pub const NO_MORE_FILES: Self = Self(0x8000_0006);
+    pub const UNSUCCESSFUL: Self = Self(0xC000_0001);
+    pub const NOT_IMPLEMENTED: Self = Self(0xC000_0002);
+    pub const INVALID_HANDLE: Self = Self(0xC000_0008);
+    pub const INVALID_PARAMETER: Self = Self(0xC000_000D);
+    pub const NO_SUCH_FILE: Self = Self(0xC000_000F);
+    pub const END_OF_FILE: Self = Self(0xC000_0011);
+    pub const ACCESS_DENIED: Self = Self(0xC000_0022);
+    pub const OBJECT_NAME_INVALID: Self = Self(0xC000_0033);
+    pub const OBJECT_NAME_NOT_FOUND: Self = Self(0xC000_0034);
+    pub const OBJECT_NAME_COLLISION: Self = Self(0xC000_0035);
+    pub const OBJECT_PATH_NOT_FOUND: Self = Self(0xC000_003A);
+    pub const DISK_FULL: Self = Self(0xC000_007F);
+    pub const FILE_IS_A_DIRECTORY: Self = Self(0xC000_00BA);
+    pub const NOT_SUPPORTED: Self = Self(0xC000_00BB);
+    pub const DIRECTORY_NOT_EMPTY: Self = Self(0xC000_0101);
+    pub const NOT_A_DIRECTORY: Self = Self(0xC000_0103);
+
+    pub fn is_success(self) -> bool {
+        self.0 & 0xC000_0000 == 0
+    }
+}
+
+#[repr(u16)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, FromPrimitive, ToPrimitive)]
+pub enum PacketId {
+    ServerAnnounce = 0x496E,
+    /// The Server Client ID Confirm PDU, or the Client Announce Reply PDU when sent by the client.
+    ClientIdConfirm = 0x4343,
+    ClientName = 0x434E,
+    DeviceListAnnounce = 0x4441,
+    DeviceReply = 0x6472,
+    DeviceIoRequest = 0x4952,
+    DeviceIoCompletion = 0x4943,
+    ServerCapability = 0x5350,
+    ClientCapability = 0x4350,
+    DeviceListRemove = 0x444D,
+    UserLoggedOn = 0x554C,
+}
+
+fn read_header(mut stream: impl io::Read) -> Result<PacketId, RdpdrError> {
+    let component = stream.read_u16::<LittleEndian>()?;
+    let packet_id = stream.read_u16::<LittleEndian>()?;
+
+    match component {
+        COMPONENT_CORE => PacketId::from_u16(packet_id).ok_or(RdpdrError::InvalidPacketId(packet_id)),
+        COMPONENT_PRINTER => Err(RdpdrError::UnexpectedComponent(component)),
+        _ => Err(RdpdrError::InvalidComponent(component)),
+    }
+}
+
+fn write_header(mut stream: impl io::Write, packet_id: PacketId) -> Result<(), RdpdrError> {
+    stream.write_u16::<LittleEndian>(COMPONENT_CORE)?;
+    stream.write_u16::<LittleEndian>(packet_id.to_u16().unwrap())?;
+
+    Ok(())
+}
+
+fn read_information_request(mut stream: impl io::Read) -> Result<(u32, Vec<u8>), RdpdrError> {
+    let information_class = stream.read_u32::<LittleEndian>()?;
+    let length = stream.read_u32::<LittleEndian>()?;
+    let mut padding = [0; INFORMATION_REQUEST_PADDING_SIZE];
+    stream.read_exact(&mut padding)?;
+    let mut buffer = vec![0; length as usize];
+    stream.read_exact(&mut buffer)?;
+
+    Ok((information_class, buffer))
+}
+
+fn write_information_request(
+    mut stream: impl io::Write,
+    information_class: u32,
+    buffer: &[u8],
+) -> Result<(), RdpdrError> {
+    stream.write_u32::<LittleEndian>(information_class)?;
+    stream.write_u32::<LittleEndian>(buffer.len() as u32)?;
+    stream.write_all(&[0; INFORMATION_REQUEST_PADDING_SIZE])?;
+    stream.write_all(buffer)?;
+
+    Ok(())
+}
+
+/// Length in bytes of the UTF-16 string, null terminator included.
+fn unicode_string_length(value: &str) -> usize {
+    (value.encode_utf16().count() + 1) * 2
+}
+
+/// Length in bytes of the UTF-16 string, which is sent without null terminator when empty.
+fn optional_unicode_string_length(value: &str) -> usize {
+    if value.is_empty() {
+        0
+    } else {
+        unicode_string_length(value)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum RdpdrError {
+    #[error("IO error: {}", .0)]
+    IOError(#[source] io::Error),
+    #[error("Invalid RDPDR component: {:#06x}", .0)]
+    InvalidComponent(u16),
+    #[error("Unexpected RDPDR component: {:#06x}", .0)]
+    UnexpectedComponent(u16),
+    #[error("Invalid RDPDR packet ID: {:#06x}", .0)]
+    InvalidPacketId(u16),
+    #[error("Unexpected RDPDR packet ID: {:?}", .0)]
+    UnexpectedPacketId(PacketId),
+    #[error("Invalid capability type: {}", .0)]
+    InvalidCapabilityType(u16),
+    #[error("Invalid capability length: {}", .0)]
+    InvalidCapabilityLength(u16),
+    #[error("Invalid device type: {}", .0)]
+    InvalidDeviceType(u32),
+    #[error("Invalid preferred DOS name: {}", .0)]
+    InvalidPreferredDosName(String),
+    #[error("Invalid create disposition: {}", .0)]
+    InvalidCreateDisposition(u32),
+}
+
+impl_from_error!(io::Error, RdpdrError, RdpdrError::IOError);
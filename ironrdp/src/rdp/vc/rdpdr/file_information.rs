@@ -0,0 +1,277 @@
+//! Encoding of the file system information classes (MS-FSCC 2.4 and 2.5) queried on the redirected drives.
+
+use std::io;
+
+use bitflags::bitflags;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use crate::utils;
+use crate::PduParsing;
+
+const FILE_BASIC_INFORMATION_SIZE: usize = 36;
+const FILE_STANDARD_INFORMATION_SIZE: usize = 22;
+const FILE_ATTRIBUTE_TAG_INFORMATION_SIZE: usize = 8;
+const FILE_DIRECTORY_INFORMATION_FIXED_PART_SIZE: usize = 64;
+const FILE_FULL_DIRECTORY_INFORMATION_FIXED_PART_SIZE: usize = 68;
+/// Without the reserved byte following the short name length, which the servers do not expect.
+const FILE_BOTH_DIRECTORY_INFORMATION_FIXED_PART_SIZE: usize = 93;
+const FILE_NAMES_INFORMATION_FIXED_PART_SIZE: usize = 12;
+const SHORT_NAME_SIZE: usize = 24;
+
+/// Without the reserved byte following the support of the objects, which the servers do not expect.
+const FILE_FS_VOLUME_INFORMATION_FIXED_PART_SIZE: usize = 17;
+const FILE_FS_SIZE_INFORMATION_SIZE: usize = 24;
+const FILE_FS_ATTRIBUTE_INFORMATION_FIXED_PART_SIZE: usize = 12;
+const FILE_FS_FULL_SIZE_INFORMATION_SIZE: usize = 32;
+const FILE_FS_DEVICE_INFORMATION_SIZE: usize = 8;
+
+const FILE_DEVICE_DISK: u32 = 0x0000_0007;
+const FILE_CASE_SENSITIVE_SEARCH: u32 = 0x0000_0001;
+const FILE_CASE_PRESERVED_NAMES: u32 = 0x0000_0002;
+const FILE_UNICODE_ON_DISK: u32 = 0x0000_0004;
+const MAXIMUM_COMPONENT_NAME_LENGTH: u32 = 255;
+
+bitflags! {
+    pub struct FileAttributes: u32 {
+        const READONLY = 0x0000_0001;
+        const HIDDEN = 0x0000_0002;
+        const SYSTEM = 0x0000_0004;
+        const DIRECTORY = 0x0000_0010;
+        const ARCHIVE = 0x0000_0020;
+        const NORMAL = 0x0000_0080;
+        const TEMPORARY = 0x0000_0100;
+    }
+}
+
+/// The class of the information queried or set on a file.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct FileInformationClass(pub u32);
+
+impl FileInformationClass {
+    pub const DIRECTORY: Self = Self(1);
+    pub const FULL_DIRECTORY: Self = Self(2);
+    pub const BOTH_DIRECTORY: Self = Self(3);
+    pub const BASIC: Self = Self(4);
+    pub const STANDARD: Self = Self(5);
+    pub const RENAME: Self = Self(10);
+    pub const NAMES: Self = Self(12);
+    pub const DISPOSITION: Self = Self(13);
+    pub const ALLOCATION: Self = Self(19);
+    pub const END_OF_FILE: Self = Self(20);
+    pub const ATTRIBUTE_TAG: Self = Self(35);
+}
+
+/// The class of the information queried on a volume.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct FsInformationClass(pub u32);
+
+impl FsInformationClass {
+    pub const VOLUME: Self = Self(1);
+    pub const SIZE: Self = Self(3);
+    pub const DEVICE: Self = Self(4);
+    pub const ATTRIBUTE: Self = Self(5);
+    pub const FULL_SIZE: Self = Self(7);
+}
+
+/// The times are FILETIMEs, i.e. the number of 100-nanosecond intervals since January 1, 1601 (UTC),
+/// 0 meaning that the time is not changed when set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileBasicInformation {
+    pub creation_time: u64,
+    pub last_access_time: u64,
+    pub last_write_time: u64,
+    pub change_time: u64,
+    pub file_attributes: FileAttributes,
+}
+
+impl PduParsing for FileBasicInformation {
+    type Error = io::Error;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        let creation_time = stream.read_u64::<LittleEndian>()?;
+        let last_access_time = stream.read_u64::<LittleEndian>()?;
+        let last_write_time = stream.read_u64::<LittleEndian>()?;
+        let change_time = stream.read_u64::<LittleEndian>()?;
+        let file_attributes = FileAttributes::from_bits_truncate(stream.read_u32::<LittleEndian>()?);
+
+        Ok(Self {
+            creation_time,
+            last_access_time,
+            last_write_time,
+            change_time,
+            file_attributes,
+        })
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        stream.write_u64::<LittleEndian>(self.creation_time)?;
+        stream.write_u64::<LittleEndian>(self.last_access_time)?;
+        stream.write_u64::<LittleEndian>(self.last_write_time)?;
+        stream.write_u64::<LittleEndian>(self.change_time)?;
+        stream.write_u32::<LittleEndian>(self.file_attributes.bits())?;
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        FILE_BASIC_INFORMATION_SIZE
+    }
+}
+
+/// The information of a file, from which the information classes queried by the server are encoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileInformation {
+    pub basic: FileBasicInformation,
+    pub end_of_file: u64,
+    pub allocation_size: u64,
+}
+
+impl FileInformation {
+    pub fn is_directory(&self) -> bool {
+        self.basic.file_attributes.contains(FileAttributes::DIRECTORY)
+    }
+
+    /// Encodes the information class, `None` if the class is not supported.
+    pub fn encode(&self, information_class: FileInformationClass) -> Option<Vec<u8>> {
+        let mut buffer = Vec::new();
+
+        match information_class {
+            FileInformationClass::BASIC => {
+                self.basic.to_buffer(&mut buffer).ok()?;
+            }
+            FileInformationClass::STANDARD => {
+                buffer.extend_from_slice(&self.allocation_size.to_le_bytes());
+                buffer.extend_from_slice(&self.end_of_file.to_le_bytes());
+                buffer.extend_from_slice(&1u32.to_le_bytes()); // number of links
+                buffer.push(0); // delete pending
+                buffer.push(u8::from(self.is_directory()));
+                debug_assert_eq!(FILE_STANDARD_INFORMATION_SIZE, buffer.len());
+            }
+            FileInformationClass::ATTRIBUTE_TAG => {
+                buffer.extend_from_slice(&self.basic.file_attributes.bits().to_le_bytes());
+                buffer.extend_from_slice(&0u32.to_le_bytes()); // reparse tag
+                debug_assert_eq!(FILE_ATTRIBUTE_TAG_INFORMATION_SIZE, buffer.len());
+            }
+            _ => return None,
+        }
+
+        Some(buffer)
+    }
+}
+
+/// An entry returned by the query of a directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirectoryEntry {
+    pub file_name: String,
+    pub information: FileInformation,
+}
+
+impl DirectoryEntry {
+    /// Encodes the entry as the information class, `None` if the class is not supported.
+    pub fn encode(&self, information_class: FileInformationClass) -> Option<Vec<u8>> {
+        let file_name = utils::string_to_utf16(&self.file_name);
+        let fixed_part_size = match information_class {
+            FileInformationClass::DIRECTORY => FILE_DIRECTORY_INFORMATION_FIXED_PART_SIZE,
+            FileInformationClass::FULL_DIRECTORY => FILE_FULL_DIRECTORY_INFORMATION_FIXED_PART_SIZE,
+            FileInformationClass::BOTH_DIRECTORY => FILE_BOTH_DIRECTORY_INFORMATION_FIXED_PART_SIZE,
+            FileInformationClass::NAMES => FILE_NAMES_INFORMATION_FIXED_PART_SIZE,
+            _ => return None,
+        };
+        let mut buffer = Vec::with_capacity(fixed_part_size + file_name.len());
+
+        buffer.extend_from_slice(&0u32.to_le_bytes()); // next entry offset
+        buffer.extend_from_slice(&0u32.to_le_bytes()); // file index
+        if information_class != FileInformationClass::NAMES {
+            let information = &self.information;
+            buffer.extend_from_slice(&information.basic.creation_time.to_le_bytes());
+            buffer.extend_from_slice(&information.basic.last_access_time.to_le_bytes());
+            buffer.extend_from_slice(&information.basic.last_write_time.to_le_bytes());
+            buffer.extend_from_slice(&information.basic.change_time.to_le_bytes());
+            buffer.extend_from_slice(&information.end_of_file.to_le_bytes());
+            buffer.extend_from_slice(&information.allocation_size.to_le_bytes());
+            buffer.extend_from_slice(&information.basic.file_attributes.bits().to_le_bytes());
+        }
+        buffer.extend_from_slice(&(file_name.len() as u32).to_le_bytes());
+        match information_class {
+            FileInformationClass::FULL_DIRECTORY => {
+                buffer.extend_from_slice(&0u32.to_le_bytes()); // extended attributes size
+            }
+            FileInformationClass::BOTH_DIRECTORY => {
+                buffer.extend_from_slice(&0u32.to_le_bytes()); // extended attributes size
+                buffer.push(0); // short name length
+                buffer.extend_from_slice(&[0; SHORT_NAME_SIZE]);
+            }
+            _ => (),
+        }
+        debug_assert_eq!(fixed_part_size, buffer.len());
+        buffer.extend_from_slice(&file_name);
+
+        Some(buffer)
+    }
+}
+
+/// The information of the volume of a redirected drive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VolumeInformation {
+    pub label: String,
+    pub serial_number: u32,
+    /// FILETIME, see [`FileBasicInformation`].
+    pub creation_time: u64,
+    pub file_system_name: String,
+    pub total_allocation_units: u64,
+    pub available_allocation_units: u64,
+    pub sectors_per_allocation_unit: u32,
+    pub bytes_per_sector: u32,
+}
+
+impl VolumeInformation {
+    /// Encodes the information class, `None` if the class is not supported.
+    pub fn encode(&self, information_class: FsInformationClass) -> Option<Vec<u8>> {
+        let mut buffer = Vec::new();
+
+        match information_class {
+            FsInformationClass::VOLUME => {
+                let label = utils::string_to_utf16(&self.label);
+                buffer.extend_from_slice(&self.creation_time.to_le_bytes());
+                buffer.extend_from_slice(&self.serial_number.to_le_bytes());
+                buffer.extend_from_slice(&(label.len() as u32).to_le_bytes());
+                buffer.push(0); // supports objects
+                debug_assert_eq!(FILE_FS_VOLUME_INFORMATION_FIXED_PART_SIZE, buffer.len());
+                buffer.extend_from_slice(&label);
+            }
+            FsInformationClass::SIZE => {
+                buffer.extend_from_slice(&self.total_allocation_units.to_le_bytes());
+                buffer.extend_from_slice(&self.available_allocation_units.to_le_bytes());
+                buffer.extend_from_slice(&self.sectors_per_allocation_unit.to_le_bytes());
+                buffer.extend_from_slice(&self.bytes_per_sector.to_le_bytes());
+                debug_assert_eq!(FILE_FS_SIZE_INFORMATION_SIZE, buffer.len());
+            }
+            FsInformationClass::ATTRIBUTE => {
+                let file_system_name = utils::string_to_utf16(&self.file_system_name);
+                let attributes = FILE_CASE_SENSITIVE_SEARCH | FILE_CASE_PRESERVED_NAMES | FILE_UNICODE_ON_DISK;
+                buffer.extend_from_slice(&attributes.to_le_bytes());
+                buffer.extend_from_slice(&MAXIMUM_COMPONENT_NAME_LENGTH.to_le_bytes());
+                buffer.extend_from_slice(&(file_system_name.len() as u32).to_le_bytes());
+                debug_assert_eq!(FILE_FS_ATTRIBUTE_INFORMATION_FIXED_PART_SIZE, buffer.len());
+                buffer.extend_from_slice(&file_system_name);
+            }
+            FsInformationClass::FULL_SIZE => {
+                buffer.extend_from_slice(&self.total_allocation_units.to_le_bytes());
+                // available to the caller, then actually available
+                buffer.extend_from_slice(&self.available_allocation_units.to_le_bytes());
+                buffer.extend_from_slice(&self.available_allocation_units.to_le_bytes());
+                buffer.extend_from_slice(&self.sectors_per_allocation_unit.to_le_bytes());
+                buffer.extend_from_slice(&self.bytes_per_sector.to_le_bytes());
+                debug_assert_eq!(FILE_FS_FULL_SIZE_INFORMATION_SIZE, buffer.len());
+            }
+            FsInformationClass::DEVICE => {
+                buffer.extend_from_slice(&FILE_DEVICE_DISK.to_le_bytes());
+                buffer.extend_from_slice(&0u32.to_le_bytes()); // characteristics
+                debug_assert_eq!(FILE_FS_DEVICE_INFORMATION_SIZE, buffer.len());
+            }
+            _ => return None,
+        }
+
+        Some(buffer)
+    }
+}
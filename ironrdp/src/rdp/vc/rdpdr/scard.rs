@@ -0,0 +1,810 @@
+//! The smart card calls of the Smart Card Virtual Channel Extension (MS-RDPESC), which the server
+//! sends as I/O control requests to the smart card device, their calls and returns being encoded with NDR.
+
+#[cfg(test)]
+mod tests;
+
+mod ndr;
+
+use std::io;
+
+use thiserror::Error;
+
+use self::ndr::{NdrReader, NdrWriter};
+use crate::impl_from_error;
+use crate::utils::{self, CharacterSet};
+
+/// The fixed size of the ATR in the reader states.
+pub const READER_STATE_ATR_SIZE: usize = 36;
+/// The fixed size of the ATR in the status of a card.
+pub const STATUS_ATR_SIZE: usize = 32;
+/// The timeout of the status change calls waiting for a change with no time limit.
+pub const INFINITE_TIMEOUT: u32 = 0xFFFF_FFFF;
+
+const ESTABLISH_CONTEXT: u32 = 0x0009_0014;
+const RELEASE_CONTEXT: u32 = 0x0009_0018;
+const IS_VALID_CONTEXT: u32 = 0x0009_001C;
+const LIST_READERS_A: u32 = 0x0009_0028;
+const LIST_READERS_W: u32 = 0x0009_002C;
+const GET_STATUS_CHANGE_A: u32 = 0x0009_00A0;
+const GET_STATUS_CHANGE_W: u32 = 0x0009_00A4;
+const CANCEL: u32 = 0x0009_00A8;
+const CONNECT_A: u32 = 0x0009_00AC;
+const CONNECT_W: u32 = 0x0009_00B0;
+const RECONNECT: u32 = 0x0009_00B4;
+const DISCONNECT: u32 = 0x0009_00B8;
+const BEGIN_TRANSACTION: u32 = 0x0009_00BC;
+const END_TRANSACTION: u32 = 0x0009_00C0;
+const STATUS_A: u32 = 0x0009_00C8;
+const STATUS_W: u32 = 0x0009_00CC;
+const TRANSMIT: u32 = 0x0009_00D0;
+const CONTROL: u32 = 0x0009_00D4;
+const GET_ATTRIB: u32 = 0x0009_00D8;
+const ACCESS_STARTED_EVENT: u32 = 0x0009_00E0;
+const RELEASE_STARTED_EVENT: u32 = 0x0009_00E4;
+const READ_CACHE_A: u32 = 0x0009_00F0;
+const READ_CACHE_W: u32 = 0x0009_00F4;
+const WRITE_CACHE_A: u32 = 0x0009_00F8;
+const WRITE_CACHE_W: u32 = 0x0009_00FC;
+const GET_DEVICE_TYPE_ID: u32 = 0x0009_0108;
+
+/// A context of the smart card resource manager, whose value is chosen by the client.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScardContext(pub Vec<u8>);
+
+impl ScardContext {
+    fn read(reader: &mut NdrReader<'_>) -> Result<(Self, bool), ScardError> {
+        let _length = reader.read_u32()?;
+        let present = reader.read_pointer()?;
+
+        Ok((Self::default(), present))
+    }
+
+    fn read_referent(&mut self, reader: &mut NdrReader<'_>, present: bool) -> Result<(), ScardError> {
+        if present {
+            self.0 = reader.read_conformant_bytes()?;
+        }
+
+        Ok(())
+    }
+
+    fn write(&self, writer: &mut NdrWriter) -> bool {
+        writer.write_u32(self.0.len() as u32);
+        writer.write_pointer(!self.0.is_empty())
+    }
+
+    fn write_referent(&self, writer: &mut NdrWriter, present: bool) {
+        if present {
+            writer.write_conformant_bytes(&self.0);
+        }
+    }
+}
+
+/// A connection to a card, whose value is chosen by the client.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScardHandle {
+    pub context: ScardContext,
+    pub handle: Vec<u8>,
+}
+
+impl ScardHandle {
+    fn read(reader: &mut NdrReader<'_>) -> Result<(Self, [bool; 2]), ScardError> {
+        let (context, context_present) = ScardContext::read(reader)?;
+        let _length = reader.read_u32()?;
+        let handle_present = reader.read_pointer()?;
+
+        Ok((
+            Self {
+                context,
+                handle: Vec::new(),
+            },
+            [context_present, handle_present],
+        ))
+    }
+
+    fn read_referents(&mut self, reader: &mut NdrReader<'_>, present: [bool; 2]) -> Result<(), ScardError> {
+        self.context.read_referent(reader, present[0])?;
+        if present[1] {
+            self.handle = reader.read_conformant_bytes()?;
+        }
+
+        Ok(())
+    }
+
+    fn write(&self, writer: &mut NdrWriter) -> [bool; 2] {
+        let context_present = self.context.write(writer);
+        writer.write_u32(self.handle.len() as u32);
+        let handle_present = writer.write_pointer(!self.handle.is_empty());
+
+        [context_present, handle_present]
+    }
+
+    fn write_referents(&self, writer: &mut NdrWriter, present: [bool; 2]) {
+        self.context.write_referent(writer, present[0]);
+        if present[1] {
+            writer.write_conformant_bytes(&self.handle);
+        }
+    }
+}
+
+/// The protocol control information of a transmission.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScardIoRequest {
+    pub protocol: u32,
+    pub extra_bytes: Vec<u8>,
+}
+
+impl ScardIoRequest {
+    fn read(reader: &mut NdrReader<'_>) -> Result<(Self, bool), ScardError> {
+        let protocol = reader.read_u32()?;
+        let _extra_bytes_length = reader.read_u32()?;
+        let extra_bytes_present = reader.read_pointer()?;
+
+        Ok((
+            Self {
+                protocol,
+                extra_bytes: Vec::new(),
+            },
+            extra_bytes_present,
+        ))
+    }
+
+    fn read_referent(&mut self, reader: &mut NdrReader<'_>, present: bool) -> Result<(), ScardError> {
+        if present {
+            self.extra_bytes = reader.read_conformant_bytes()?;
+        }
+
+        Ok(())
+    }
+
+    fn write(&self, writer: &mut NdrWriter) -> bool {
+        writer.write_u32(self.protocol);
+        writer.write_u32(self.extra_bytes.len() as u32);
+        writer.write_pointer(!self.extra_bytes.is_empty())
+    }
+
+    fn write_referent(&self, writer: &mut NdrWriter, present: bool) {
+        if present {
+            writer.write_conformant_bytes(&self.extra_bytes);
+        }
+    }
+}
+
+/// The state of a reader, known by the caller of a status change call and updated by the client.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReaderState {
+    pub reader: String,
+    pub current_state: u32,
+    pub event_state: u32,
+    /// The ATR of the card in the reader, at most [`READER_STATE_ATR_SIZE`] bytes.
+    pub atr: Vec<u8>,
+}
+
+impl ReaderState {
+    fn read_common(reader: &mut NdrReader<'_>) -> Result<(u32, u32, Vec<u8>), ScardError> {
+        let current_state = reader.read_u32()?;
+        let event_state = reader.read_u32()?;
+        let atr_length = reader.read_u32()? as usize;
+        let atr = reader.read_bytes(READER_STATE_ATR_SIZE)?;
+
+        Ok((
+            current_state,
+            event_state,
+            atr[..atr_length.min(READER_STATE_ATR_SIZE)].to_vec(),
+        ))
+    }
+
+    fn write_common(&self, writer: &mut NdrWriter) {
+        let atr_length = self.atr.len().min(READER_STATE_ATR_SIZE);
+        let mut atr = [0; READER_STATE_ATR_SIZE];
+        atr[..atr_length].copy_from_slice(&self.atr[..atr_length]);
+
+        writer.write_u32(self.current_state);
+        writer.write_u32(self.event_state);
+        writer.write_u32(atr_length as u32);
+        writer.write_bytes(&atr);
+    }
+}
+
+/// A call to the smart card API of the client, decoded from the input buffer of an I/O control request.
+///
+/// The calls existing in ANSI and Unicode variants hold the character set of their strings,
+/// which also applies to the strings they return.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScardCall {
+    EstablishContext {
+        scope: u32,
+    },
+    ReleaseContext {
+        context: ScardContext,
+    },
+    IsValidContext {
+        context: ScardContext,
+    },
+    ListReaders {
+        context: ScardContext,
+        character_set: CharacterSet,
+    },
+    GetStatusChange {
+        context: ScardContext,
+        character_set: CharacterSet,
+        timeout: u32,
+        reader_states: Vec<ReaderState>,
+    },
+    /// Cancels the status change calls of the context.
+    Cancel {
+        context: ScardContext,
+    },
+    Connect {
+        context: ScardContext,
+        character_set: CharacterSet,
+        reader: String,
+        share_mode: u32,
+        preferred_protocols: u32,
+    },
+    Reconnect {
+        handle: ScardHandle,
+        share_mode: u32,
+        preferred_protocols: u32,
+        initialization: u32,
+    },
+    Disconnect {
+        handle: ScardHandle,
+        disposition: u32,
+    },
+    BeginTransaction {
+        handle: ScardHandle,
+    },
+    EndTransaction {
+        handle: ScardHandle,
+        disposition: u32,
+    },
+    Status {
+        handle: ScardHandle,
+        character_set: CharacterSet,
+    },
+    Transmit {
+        handle: ScardHandle,
+        send_pci: ScardIoRequest,
+        send_buffer: Vec<u8>,
+        /// Whether the caller expects the protocol control information of the response.
+        receive_pci: bool,
+        receive_length: u32,
+    },
+    Control {
+        handle: ScardHandle,
+        control_code: u32,
+        in_buffer: Vec<u8>,
+        out_buffer_size: u32,
+    },
+    GetAttrib {
+        handle: ScardHandle,
+        attribute_id: u32,
+    },
+    GetDeviceTypeId {
+        context: ScardContext,
+        reader: String,
+    },
+    /// Waits for the smart card resource manager to be started.
+    AccessStartedEvent,
+    ReleaseStartedEvent,
+    /// Reads from the cache of the data read from the cards, which the client does not keep.
+    ReadCache,
+    WriteCache,
+    Unsupported {
+        io_control_code: u32,
+    },
+}
+
+impl ScardCall {
+    pub fn decode(io_control_code: u32, input_buffer: &[u8]) -> Result<Self, ScardError> {
+        let call = match io_control_code {
+            ACCESS_STARTED_EVENT => return Ok(ScardCall::AccessStartedEvent),
+            RELEASE_STARTED_EVENT => return Ok(ScardCall::ReleaseStartedEvent),
+            READ_CACHE_A | READ_CACHE_W => return Ok(ScardCall::ReadCache),
+            WRITE_CACHE_A | WRITE_CACHE_W => return Ok(ScardCall::WriteCache),
+            ESTABLISH_CONTEXT => {
+                let mut reader = NdrReader::new(input_buffer)?;
+
+                ScardCall::EstablishContext {
+                    scope: reader.read_u32()?,
+                }
+            }
+            RELEASE_CONTEXT | IS_VALID_CONTEXT | CANCEL => {
+                let mut reader = NdrReader::new(input_buffer)?;
+                let (mut context, present) = ScardContext::read(&mut reader)?;
+                context.read_referent(&mut reader, present)?;
+
+                match io_control_code {
+                    RELEASE_CONTEXT => ScardCall::ReleaseContext { context },
+                    IS_VALID_CONTEXT => ScardCall::IsValidContext { context },
+                    _ => ScardCall::Cancel { context },
+                }
+            }
+            LIST_READERS_A | LIST_READERS_W => {
+                let mut reader = NdrReader::new(input_buffer)?;
+                let (mut context, context_present) = ScardContext::read(&mut reader)?;
+                let _groups_length = reader.read_u32()?;
+                let groups_present = reader.read_pointer()?;
+                let _readers_is_null = reader.read_bool()?;
+                let _readers_length = reader.read_u32()?;
+                context.read_referent(&mut reader, context_present)?;
+                if groups_present {
+                    // the readers of all the groups are listed
+                    let _groups = reader.read_conformant_bytes()?;
+                }
+
+                ScardCall::ListReaders {
+                    context,
+                    character_set: character_set(io_control_code == LIST_READERS_W),
+                }
+            }
+            GET_STATUS_CHANGE_A | GET_STATUS_CHANGE_W => {
+                let character_set = character_set(io_control_code == GET_STATUS_CHANGE_W);
+                let mut reader = NdrReader::new(input_buffer)?;
+                let (mut context, context_present) = ScardContext::read(&mut reader)?;
+                let timeout = reader.read_u32()?;
+                let _readers_count = reader.read_u32()?;
+                let reader_states_present = reader.read_pointer()?;
+                context.read_referent(&mut reader, context_present)?;
+
+                let mut reader_states = Vec::new();
+                if reader_states_present {
+                    let count = reader.read_u32()? as usize;
+                    let mut names_present = Vec::new();
+                    for _ in 0..count {
+                        names_present.push(reader.read_pointer()?);
+                        let (current_state, event_state, atr) = ReaderState::read_common(&mut reader)?;
+                        reader_states.push(ReaderState {
+                            reader: String::new(),
+                            current_state,
+                            event_state,
+                            atr,
+                        });
+                    }
+                    for (reader_state, name_present) in reader_states.iter_mut().zip(names_present) {
+                        if name_present {
+                            reader_state.reader = reader.read_string(character_set)?;
+                        }
+                    }
+                }
+
+                ScardCall::GetStatusChange {
+                    context,
+                    character_set,
+                    timeout,
+                    reader_states,
+                }
+            }
+            CONNECT_A | CONNECT_W => {
+                let character_set = character_set(io_control_code == CONNECT_W);
+                let mut reader = NdrReader::new(input_buffer)?;
+                let reader_name_present = reader.read_pointer()?;
+                let (mut context, context_present) = ScardContext::read(&mut reader)?;
+                let share_mode = reader.read_u32()?;
+                let preferred_protocols = reader.read_u32()?;
+                let reader_name = if reader_name_present {
+                    reader.read_string(character_set)?
+                } else {
+                    return Err(ScardError::UnexpectedNullPointer("szReader"));
+                };
+                context.read_referent(&mut reader, context_present)?;
+
+                ScardCall::Connect {
+                    context,
+                    character_set,
+                    reader: reader_name,
+                    share_mode,
+                    preferred_protocols,
+                }
+            }
+            RECONNECT => {
+                let mut reader = NdrReader::new(input_buffer)?;
+                let (mut handle, present) = ScardHandle::read(&mut reader)?;
+                let share_mode = reader.read_u32()?;
+                let preferred_protocols = reader.read_u32()?;
+                let initialization = reader.read_u32()?;
+                handle.read_referents(&mut reader, present)?;
+
+                ScardCall::Reconnect {
+                    handle,
+                    share_mode,
+                    preferred_protocols,
+                    initialization,
+                }
+            }
+            DISCONNECT | BEGIN_TRANSACTION | END_TRANSACTION => {
+                let mut reader = NdrReader::new(input_buffer)?;
+                let (mut handle, present) = ScardHandle::read(&mut reader)?;
+                let disposition = reader.read_u32()?;
+                handle.read_referents(&mut reader, present)?;
+
+                match io_control_code {
+                    DISCONNECT => ScardCall::Disconnect { handle, disposition },
+                    BEGIN_TRANSACTION => ScardCall::BeginTransaction { handle },
+                    _ => ScardCall::EndTransaction { handle, disposition },
+                }
+            }
+            STATUS_A | STATUS_W => {
+                let mut reader = NdrReader::new(input_buffer)?;
+                let (mut handle, present) = ScardHandle::read(&mut reader)?;
+                let _reader_names_is_null = reader.read_bool()?;
+                let _reader_names_length = reader.read_u32()?;
+                let _atr_length = reader.read_u32()?;
+                handle.read_referents(&mut reader, present)?;
+
+                ScardCall::Status {
+                    handle,
+                    character_set: character_set(io_control_code == STATUS_W),
+                }
+            }
+            TRANSMIT => {
+                let mut reader = NdrReader::new(input_buffer)?;
+                let (mut handle, handle_present) = ScardHandle::read(&mut reader)?;
+                let (mut send_pci, send_pci_present) = ScardIoRequest::read(&mut reader)?;
+                let _send_length = reader.read_u32()?;
+                let send_buffer_present = reader.read_pointer()?;
+                let receive_pci = reader.read_pointer()?;
+                let _receive_buffer_is_null = reader.read_bool()?;
+                let receive_length = reader.read_u32()?;
+                handle.read_referents(&mut reader, handle_present)?;
+                send_pci.read_referent(&mut reader, send_pci_present)?;
+                let send_buffer = if send_buffer_present {
+                    reader.read_conformant_bytes()?
+                } else {
+                    Vec::new()
+                };
+                if receive_pci {
+                    // the protocol control information of the response is the one of the card
+                    let (mut receive_pci, present) = ScardIoRequest::read(&mut reader)?;
+                    receive_pci.read_referent(&mut reader, present)?;
+                }
+
+                ScardCall::Transmit {
+                    handle,
+                    send_pci,
+                    send_buffer,
+                    receive_pci,
+                    receive_length,
+                }
+            }
+            CONTROL => {
+                let mut reader = NdrReader::new(input_buffer)?;
+                let (mut handle, present) = ScardHandle::read(&mut reader)?;
+                let control_code = reader.read_u32()?;
+                let _in_buffer_size = reader.read_u32()?;
+                let in_buffer_present = reader.read_pointer()?;
+                let _out_buffer_is_null = reader.read_bool()?;
+                let out_buffer_size = reader.read_u32()?;
+                handle.read_referents(&mut reader, present)?;
+                let in_buffer = if in_buffer_present {
+                    reader.read_conformant_bytes()?
+                } else {
+                    Vec::new()
+                };
+
+                ScardCall::Control {
+                    handle,
+                    control_code,
+                    in_buffer,
+                    out_buffer_size,
+                }
+            }
+            GET_ATTRIB => {
+                let mut reader = NdrReader::new(input_buffer)?;
+                let (mut handle, present) = ScardHandle::read(&mut reader)?;
+                let attribute_id = reader.read_u32()?;
+                let _attribute_is_null = reader.read_bool()?;
+                let _attribute_length = reader.read_u32()?;
+                handle.read_referents(&mut reader, present)?;
+
+                ScardCall::GetAttrib { handle, attribute_id }
+            }
+            GET_DEVICE_TYPE_ID => {
+                let mut reader = NdrReader::new(input_buffer)?;
+                let (mut context, context_present) = ScardContext::read(&mut reader)?;
+                let reader_name_present = reader.read_pointer()?;
+                context.read_referent(&mut reader, context_present)?;
+                let reader_name = if reader_name_present {
+                    reader.read_string(CharacterSet::Unicode)?
+                } else {
+                    return Err(ScardError::UnexpectedNullPointer("szReaderName"));
+                };
+
+                ScardCall::GetDeviceTypeId {
+                    context,
+                    reader: reader_name,
+                }
+            }
+            _ => ScardCall::Unsupported { io_control_code },
+        };
+
+        Ok(call)
+    }
+
+    /// The return of the call failing with the code, shaped as the caller expects it.
+    pub fn failure(&self, return_code: ScardReturnCode) -> ScardReturn {
+        match self {
+            ScardCall::EstablishContext { .. } => ScardReturn::EstablishContext {
+                return_code,
+                context: ScardContext::default(),
+            },
+            ScardCall::ListReaders { .. } => ScardReturn::ListReaders {
+                return_code,
+                readers: Vec::new(),
+            },
+            ScardCall::GetStatusChange { .. } => ScardReturn::GetStatusChange {
+                return_code,
+                reader_states: Vec::new(),
+            },
+            ScardCall::Connect { .. } => ScardReturn::Connect {
+                return_code,
+                handle: ScardHandle::default(),
+                active_protocol: 0,
+            },
+            ScardCall::Reconnect { .. } => ScardReturn::Reconnect {
+                return_code,
+                active_protocol: 0,
+            },
+            ScardCall::Status { .. } => ScardReturn::Status {
+                return_code,
+                reader_names: Vec::new(),
+                state: 0,
+                protocol: 0,
+                atr: Vec::new(),
+            },
+            ScardCall::Transmit { .. } => ScardReturn::Transmit {
+                return_code,
+                receive_pci: None,
+                receive_buffer: Vec::new(),
+            },
+            ScardCall::Control { .. } => ScardReturn::Control {
+                return_code,
+                out_buffer: Vec::new(),
+            },
+            ScardCall::GetAttrib { .. } => ScardReturn::GetAttrib {
+                return_code,
+                attribute: Vec::new(),
+            },
+            ScardCall::GetDeviceTypeId { .. } => ScardReturn::GetDeviceTypeId {
+                return_code,
+                device_type_id: 0,
+            },
+            ScardCall::ReadCache => ScardReturn::ReadCache {
+                return_code,
+                data: Vec::new(),
+            },
+            ScardCall::ReleaseContext { .. }
+            | ScardCall::IsValidContext { .. }
+            | ScardCall::Cancel { .. }
+            | ScardCall::Disconnect { .. }
+            | ScardCall::BeginTransaction { .. }
+            | ScardCall::EndTransaction { .. }
+            | ScardCall::AccessStartedEvent
+            | ScardCall::ReleaseStartedEvent
+            | ScardCall::WriteCache
+            | ScardCall::Unsupported { .. } => ScardReturn::Long { return_code },
+        }
+    }
+}
+
+/// The return of a call, encoded into the output buffer of the I/O control response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScardReturn {
+    Long {
+        return_code: ScardReturnCode,
+    },
+    EstablishContext {
+        return_code: ScardReturnCode,
+        context: ScardContext,
+    },
+    ListReaders {
+        return_code: ScardReturnCode,
+        /// The names of the readers, see [`encode_multi_string`].
+        readers: Vec<u8>,
+    },
+    GetStatusChange {
+        return_code: ScardReturnCode,
+        reader_states: Vec<ReaderState>,
+    },
+    Connect {
+        return_code: ScardReturnCode,
+        handle: ScardHandle,
+        active_protocol: u32,
+    },
+    Reconnect {
+        return_code: ScardReturnCode,
+        active_protocol: u32,
+    },
+    Status {
+        return_code: ScardReturnCode,
+        /// The names of the reader, see [`encode_multi_string`].
+        reader_names: Vec<u8>,
+        state: u32,
+        protocol: u32,
+        /// At most [`STATUS_ATR_SIZE`] bytes.
+        atr: Vec<u8>,
+    },
+    Transmit {
+        return_code: ScardReturnCode,
+        receive_pci: Option<ScardIoRequest>,
+        receive_buffer: Vec<u8>,
+    },
+    Control {
+        return_code: ScardReturnCode,
+        out_buffer: Vec<u8>,
+    },
+    GetAttrib {
+        return_code: ScardReturnCode,
+        attribute: Vec<u8>,
+    },
+    GetDeviceTypeId {
+        return_code: ScardReturnCode,
+        device_type_id: u32,
+    },
+    ReadCache {
+        return_code: ScardReturnCode,
+        data: Vec<u8>,
+    },
+}
+
+impl ScardReturn {
+    pub fn return_code(&self) -> ScardReturnCode {
+        match self {
+            ScardReturn::Long { return_code }
+            | ScardReturn::EstablishContext { return_code, .. }
+            | ScardReturn::ListReaders { return_code, .. }
+            | ScardReturn::GetStatusChange { return_code, .. }
+            | ScardReturn::Connect { return_code, .. }
+            | ScardReturn::Reconnect { return_code, .. }
+            | ScardReturn::Status { return_code, .. }
+            | ScardReturn::Transmit { return_code, .. }
+            | ScardReturn::Control { return_code, .. }
+            | ScardReturn::GetAttrib { return_code, .. }
+            | ScardReturn::GetDeviceTypeId { return_code, .. }
+            | ScardReturn::ReadCache { return_code, .. } => *return_code,
+        }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut writer = NdrWriter::new();
+        writer.write_u32(self.return_code().0);
+
+        match self {
+            ScardReturn::Long { .. } => (),
+            ScardReturn::EstablishContext { context, .. } => {
+                let present = context.write(&mut writer);
+                context.write_referent(&mut writer, present);
+            }
+            ScardReturn::ListReaders { readers: buffer, .. }
+            | ScardReturn::Control { out_buffer: buffer, .. }
+            | ScardReturn::GetAttrib { attribute: buffer, .. }
+            | ScardReturn::ReadCache { data: buffer, .. } => write_buffer(&mut writer, buffer),
+            ScardReturn::GetStatusChange { reader_states, .. } => {
+                writer.write_u32(reader_states.len() as u32);
+                if writer.write_pointer(!reader_states.is_empty()) {
+                    writer.write_u32(reader_states.len() as u32);
+                    for reader_state in reader_states.iter() {
+                        reader_state.write_common(&mut writer);
+                    }
+                }
+            }
+            ScardReturn::Connect {
+                handle,
+                active_protocol,
+                ..
+            } => {
+                let present = handle.write(&mut writer);
+                writer.write_u32(*active_protocol);
+                handle.write_referents(&mut writer, present);
+            }
+            ScardReturn::Reconnect { active_protocol, .. } => writer.write_u32(*active_protocol),
+            ScardReturn::Status {
+                reader_names,
+                state,
+                protocol,
+                atr,
+                ..
+            } => {
+                let atr_length = atr.len().min(STATUS_ATR_SIZE);
+                let mut fixed_atr = [0; STATUS_ATR_SIZE];
+                fixed_atr[..atr_length].copy_from_slice(&atr[..atr_length]);
+
+                writer.write_u32(reader_names.len() as u32);
+                let reader_names_present = writer.write_pointer(!reader_names.is_empty());
+                writer.write_u32(*state);
+                writer.write_u32(*protocol);
+                writer.write_bytes(&fixed_atr);
+                writer.write_u32(atr_length as u32);
+                if reader_names_present {
+                    writer.write_conformant_bytes(reader_names);
+                }
+            }
+            ScardReturn::Transmit {
+                receive_pci,
+                receive_buffer,
+                ..
+            } => {
+                writer.write_pointer(receive_pci.is_some());
+                writer.write_u32(receive_buffer.len() as u32);
+                let receive_buffer_present = writer.write_pointer(!receive_buffer.is_empty());
+                if let Some(receive_pci) = receive_pci {
+                    let present = receive_pci.write(&mut writer);
+                    receive_pci.write_referent(&mut writer, present);
+                }
+                if receive_buffer_present {
+                    writer.write_conformant_bytes(receive_buffer);
+                }
+            }
+            ScardReturn::GetDeviceTypeId { device_type_id, .. } => writer.write_u32(*device_type_id),
+        }
+
+        writer.finish()
+    }
+}
+
+/// The return code of a smart card call, `SCARD_S_SUCCESS` or one of the `SCARD_E_*`,
+/// `SCARD_F_*` and `SCARD_W_*` codes.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ScardReturnCode(pub u32);
+
+impl ScardReturnCode {
+    pub const SUCCESS: Self = Self(0x0000_0000);
+    pub const INTERNAL_ERROR: Self = Self(0x8010_0001);
+    pub const CANCELLED: Self = Self(0x8010_0002);
+    pub const INVALID_HANDLE: Self = Self(0x8010_0003);
+    pub const INVALID_PARAMETER: Self = Self(0x8010_0004);
+    pub const INSUFFICIENT_BUFFER: Self = Self(0x8010_0008);
+    pub const UNKNOWN_READER: Self = Self(0x8010_0009);
+    pub const TIMEOUT: Self = Self(0x8010_000A);
+    pub const NO_SMARTCARD: Self = Self(0x8010_000C);
+    pub const NOT_TRANSACTED: Self = Self(0x8010_0016);
+    pub const READER_UNAVAILABLE: Self = Self(0x8010_0017);
+    pub const NO_SERVICE: Self = Self(0x8010_001D);
+    pub const UNSUPPORTED_FEATURE: Self = Self(0x8010_0022);
+    pub const NO_READERS_AVAILABLE: Self = Self(0x8010_002E);
+    pub const REMOVED_CARD: Self = Self(0x8010_0069);
+    pub const CACHE_ITEM_NOT_FOUND: Self = Self(0x8010_0070);
+}
+
+/// Encodes the names as a multi-string, each name being null-terminated and the list being terminated
+/// by an additional null character.
+pub fn encode_multi_string(names: &[String], character_set: CharacterSet) -> Vec<u8> {
+    let mut buffer = Vec::new();
+    for name in names.iter() {
+        utils::write_string_with_null_terminator(&mut buffer, name, character_set)
+            .expect("writing to a vector cannot fail");
+    }
+    utils::write_string_with_null_terminator(&mut buffer, "", character_set).expect("writing to a vector cannot fail");
+
+    buffer
+}
+
+fn character_set(unicode: bool) -> CharacterSet {
+    if unicode {
+        CharacterSet::Unicode
+    } else {
+        CharacterSet::Ansi
+    }
+}
+
+fn write_buffer(writer: &mut NdrWriter, buffer: &[u8]) {
+    writer.write_u32(buffer.len() as u32);
+    if writer.write_pointer(!buffer.is_empty()) {
+        writer.write_conformant_bytes(buffer);
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ScardError {
+    #[error("IO error: {}", .0)]
+    IOError(#[source] io::Error),
+    #[error("Invalid type serialization header")]
+    InvalidTypeHeader,
+    #[error("Invalid length: {}", .0)]
+    InvalidLength(usize),
+    #[error("Unexpected null pointer: {}", .0)]
+    UnexpectedNullPointer(&'static str),
+}
+
+impl_from_error!(io::Error, ScardError, ScardError::IOError);
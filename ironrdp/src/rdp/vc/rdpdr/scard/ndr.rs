@@ -0,0 +1,172 @@
+//! The subset of the Network Data Representation (NDR) type serialization version 1 (MS-RPCE 2.2.6)
+//! the smart card calls and returns are encoded with.
+
+use std::io;
+
+use super::ScardError;
+use crate::utils::{self, CharacterSet};
+
+const TYPE_SERIALIZATION_VERSION: u8 = 1;
+const LITTLE_ENDIAN: u8 = 0x10;
+const COMMON_HEADER_LENGTH: u16 = 8;
+const COMMON_HEADER_FILLER: u32 = 0xCCCC_CCCC;
+const HEADERS_SIZE: usize = 16;
+const FIRST_REFERENT_ID: u32 = 0x0002_0000;
+
+/// Reads the fields in their order, the pointers being read as flags telling whether their referents,
+/// which are deferred after the structure holding them, are present.
+pub struct NdrReader<'a> {
+    buffer: &'a [u8],
+    position: usize,
+}
+
+impl<'a> NdrReader<'a> {
+    /// Checks the type serialization headers preceding the data.
+    pub fn new(buffer: &'a [u8]) -> Result<Self, ScardError> {
+        if buffer.len() < HEADERS_SIZE
+            || buffer[0] != TYPE_SERIALIZATION_VERSION
+            || buffer[1] != LITTLE_ENDIAN
+            || u16::from_le_bytes([buffer[2], buffer[3]]) != COMMON_HEADER_LENGTH
+        {
+            return Err(ScardError::InvalidTypeHeader);
+        }
+
+        Ok(Self {
+            buffer,
+            position: HEADERS_SIZE,
+        })
+    }
+
+    pub fn read_u32(&mut self) -> Result<u32, ScardError> {
+        self.align(4);
+        let bytes = self.read_bytes(4)?;
+
+        Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    pub fn read_bool(&mut self) -> Result<bool, ScardError> {
+        Ok(self.read_u32()? != 0)
+    }
+
+    /// Reads the referent ID of a pointer, returning `true` if the pointer is not null.
+    pub fn read_pointer(&mut self) -> Result<bool, ScardError> {
+        self.read_bool()
+    }
+
+    pub fn read_bytes(&mut self, length: usize) -> Result<&'a [u8], ScardError> {
+        let end = self
+            .position
+            .checked_add(length)
+            .filter(|end| *end <= self.buffer.len())
+            .ok_or_else(|| ScardError::IOError(io::Error::from(io::ErrorKind::UnexpectedEof)))?;
+        let bytes = &self.buffer[self.position..end];
+        self.position = end;
+
+        Ok(bytes)
+    }
+
+    /// Reads the referent of a `[size_is]` byte pointer, preceded by its number of elements.
+    pub fn read_conformant_bytes(&mut self) -> Result<Vec<u8>, ScardError> {
+        let max_count = self.read_u32()? as usize;
+
+        Ok(self.read_bytes(max_count)?.to_vec())
+    }
+
+    /// Reads the referent of a `[string]` pointer, null terminator excluded.
+    pub fn read_string(&mut self, character_set: CharacterSet) -> Result<String, ScardError> {
+        let _max_count = self.read_u32()?;
+        let _offset = self.read_u32()?;
+        let actual_count = self.read_u32()? as usize;
+        let character_size = match character_set {
+            CharacterSet::Ansi => 1,
+            CharacterSet::Unicode => 2,
+        };
+        let length = actual_count
+            .checked_mul(character_size)
+            .ok_or(ScardError::InvalidLength(actual_count))?;
+
+        Ok(utils::read_string(
+            self.read_bytes(length)?,
+            length,
+            character_set,
+            false,
+        )?)
+    }
+
+    fn align(&mut self, alignment: usize) {
+        self.position = align(self.position, alignment);
+    }
+}
+
+/// Writes the fields in their order, the referents of the pointers being written by the caller
+/// after the structure holding them.
+pub struct NdrWriter {
+    buffer: Vec<u8>,
+    next_referent_id: u32,
+}
+
+impl NdrWriter {
+    pub fn new() -> Self {
+        Self {
+            buffer: vec![0; HEADERS_SIZE],
+            next_referent_id: FIRST_REFERENT_ID,
+        }
+    }
+
+    pub fn write_u32(&mut self, value: u32) {
+        self.align(4);
+        self.buffer.extend_from_slice(&value.to_le_bytes());
+    }
+
+    /// Writes a unique referent ID if the pointer is not null, returning whether the referent is to be written.
+    pub fn write_pointer(&mut self, present: bool) -> bool {
+        if present {
+            let referent_id = self.next_referent_id;
+            self.next_referent_id += 4;
+            self.write_u32(referent_id);
+        } else {
+            self.write_u32(0);
+        }
+
+        present
+    }
+
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    pub fn write_conformant_bytes(&mut self, bytes: &[u8]) {
+        self.write_u32(bytes.len() as u32);
+        self.write_bytes(bytes);
+    }
+
+    /// Returns the data preceded by the type serialization headers, padded to a multiple of 8 bytes.
+    pub fn finish(mut self) -> Vec<u8> {
+        self.align(8);
+        let object_buffer_length = (self.buffer.len() - HEADERS_SIZE) as u32;
+
+        self.buffer[0] = TYPE_SERIALIZATION_VERSION;
+        self.buffer[1] = LITTLE_ENDIAN;
+        self.buffer[2..4].copy_from_slice(&COMMON_HEADER_LENGTH.to_le_bytes());
+        self.buffer[4..8].copy_from_slice(&COMMON_HEADER_FILLER.to_le_bytes());
+        self.buffer[8..12].copy_from_slice(&object_buffer_length.to_le_bytes());
+        // the filler of the private header is left zeroed
+
+        self.buffer
+    }
+
+    fn align(&mut self, alignment: usize) {
+        let length = align(self.buffer.len(), alignment);
+        self.buffer.resize(length, 0);
+    }
+}
+
+impl Default for NdrWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn align(position: usize, alignment: usize) -> usize {
+    (position + alignment - 1) / alignment * alignment
+}
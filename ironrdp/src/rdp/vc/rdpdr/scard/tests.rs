@@ -0,0 +1,143 @@
+use super::*;
+
+const ESTABLISH_CONTEXT_CALL_BUFFER: [u8; 24] = [
+    0x01, 0x10, 0x08, 0x00, 0xcc, 0xcc, 0xcc, 0xcc, // common type header
+    0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // private header
+    0x02, 0x00, 0x00, 0x00, // scope
+    0x00, 0x00, 0x00, 0x00, // padding
+];
+
+const CONNECT_W_CALL_BUFFER: [u8; 64] = [
+    0x01, 0x10, 0x08, 0x00, 0xcc, 0xcc, 0xcc, 0xcc, // common type header
+    0x30, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // private header
+    0x00, 0x00, 0x02, 0x00, // reader pointer
+    0x04, 0x00, 0x00, 0x00, // context length
+    0x04, 0x00, 0x02, 0x00, // context pointer
+    0x02, 0x00, 0x00, 0x00, // share mode
+    0x03, 0x00, 0x00, 0x00, // preferred protocols
+    0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, // reader counts
+    0x52, 0x00, 0x00, 0x00, // reader
+    0x04, 0x00, 0x00, 0x00, // context count
+    0x01, 0x02, 0x03, 0x04, // context
+    0x00, 0x00, 0x00, 0x00, // padding
+];
+
+const ESTABLISH_CONTEXT_RETURN_BUFFER: [u8; 40] = [
+    0x01, 0x10, 0x08, 0x00, 0xcc, 0xcc, 0xcc, 0xcc, // common type header
+    0x18, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // private header
+    0x00, 0x00, 0x00, 0x00, // return code
+    0x04, 0x00, 0x00, 0x00, // context length
+    0x00, 0x00, 0x02, 0x00, // context pointer
+    0x04, 0x00, 0x00, 0x00, // context count
+    0x01, 0x02, 0x03, 0x04, // context
+    0x00, 0x00, 0x00, 0x00, // padding
+];
+
+const CONNECT_RETURN_BUFFER: [u8; 56] = [
+    0x01, 0x10, 0x08, 0x00, 0xcc, 0xcc, 0xcc, 0xcc, // common type header
+    0x28, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // private header
+    0x00, 0x00, 0x00, 0x00, // return code
+    0x04, 0x00, 0x00, 0x00, // context length
+    0x00, 0x00, 0x02, 0x00, // context pointer
+    0x04, 0x00, 0x00, 0x00, // handle length
+    0x04, 0x00, 0x02, 0x00, // handle pointer
+    0x02, 0x00, 0x00, 0x00, // active protocol
+    0x04, 0x00, 0x00, 0x00, // context count
+    0x01, 0x02, 0x03, 0x04, // context
+    0x04, 0x00, 0x00, 0x00, // handle count
+    0x05, 0x06, 0x07, 0x08, // handle
+];
+
+const TIMEOUT_RETURN_BUFFER: [u8; 24] = [
+    0x01, 0x10, 0x08, 0x00, 0xcc, 0xcc, 0xcc, 0xcc, // common type header
+    0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // private header
+    0x0a, 0x00, 0x10, 0x80, // return code
+    0x00, 0x00, 0x00, 0x00, // padding
+];
+
+fn context() -> ScardContext {
+    ScardContext(vec![0x01, 0x02, 0x03, 0x04])
+}
+
+#[test]
+fn from_buffer_correctly_parses_establish_context_call() {
+    assert_eq!(
+        ScardCall::EstablishContext { scope: 2 },
+        ScardCall::decode(ESTABLISH_CONTEXT, ESTABLISH_CONTEXT_CALL_BUFFER.as_ref()).unwrap()
+    );
+}
+
+#[test]
+fn from_buffer_correctly_parses_connect_w_call() {
+    assert_eq!(
+        ScardCall::Connect {
+            context: context(),
+            character_set: CharacterSet::Unicode,
+            reader: String::from("R"),
+            share_mode: 2,
+            preferred_protocols: 3,
+        },
+        ScardCall::decode(CONNECT_W, CONNECT_W_CALL_BUFFER.as_ref()).unwrap()
+    );
+}
+
+#[test]
+fn from_buffer_returns_error_on_invalid_type_header() {
+    let mut buffer = ESTABLISH_CONTEXT_CALL_BUFFER;
+    buffer[1] = 0x00;
+
+    assert!(matches!(
+        ScardCall::decode(ESTABLISH_CONTEXT, buffer.as_ref()),
+        Err(ScardError::InvalidTypeHeader)
+    ));
+}
+
+#[test]
+fn from_buffer_returns_error_on_truncated_call() {
+    assert!(ScardCall::decode(CONNECT_W, &CONNECT_W_CALL_BUFFER[..40]).is_err());
+}
+
+#[test]
+fn to_buffer_correctly_serializes_establish_context_return() {
+    let scard_return = ScardReturn::EstablishContext {
+        return_code: ScardReturnCode::SUCCESS,
+        context: context(),
+    };
+
+    assert_eq!(
+        ESTABLISH_CONTEXT_RETURN_BUFFER.as_ref(),
+        scard_return.encode().as_slice()
+    );
+}
+
+#[test]
+fn to_buffer_correctly_serializes_connect_return() {
+    let scard_return = ScardReturn::Connect {
+        return_code: ScardReturnCode::SUCCESS,
+        handle: ScardHandle {
+            context: context(),
+            handle: vec![0x05, 0x06, 0x07, 0x08],
+        },
+        active_protocol: 2,
+    };
+
+    assert_eq!(CONNECT_RETURN_BUFFER.as_ref(), scard_return.encode().as_slice());
+}
+
+#[test]
+fn failure_of_long_return_call_is_encoded_with_return_code_only() {
+    let call = ScardCall::Cancel { context: context() };
+
+    assert_eq!(
+        TIMEOUT_RETURN_BUFFER.as_ref(),
+        call.failure(ScardReturnCode::TIMEOUT).encode().as_slice()
+    );
+}
+
+#[test]
+fn encode_multi_string_terminates_the_list_with_an_additional_null() {
+    assert_eq!(
+        vec![b'A', 0, b'B', 0, 0],
+        encode_multi_string(&[String::from("A"), String::from("B")], CharacterSet::Ansi)
+    );
+}
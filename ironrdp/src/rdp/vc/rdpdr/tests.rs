@@ -0,0 +1,257 @@
+use lazy_static::lazy_static;
+use proptest::prelude::*;
+
+use super::*;
+use crate::consts::{RDPDR_GENERAL_CAPABILITY_VERSION_01, RDPDR_VERSION_MAJOR, RDPDR_VERSION_MINOR_12};
+
+const SERVER_ANNOUNCE_BUFFER: [u8; 12] = [
+    0x72, 0x44, 0x6e, 0x49, // header
+    0x01, 0x00, // version major
+    0x0c, 0x00, // version minor
+    0x02, 0x00, 0x00, 0x00, // client id
+];
+
+const CLIENT_NAME_BUFFER: [u8; 22] = [
+    0x72, 0x44, 0x4e, 0x43, // header
+    0x01, 0x00, 0x00, 0x00, // unicode flag
+    0x00, 0x00, 0x00, 0x00, // code page
+    0x06, 0x00, 0x00, 0x00, // computer name length
+    0x50, 0x00, 0x43, 0x00, 0x00, 0x00, // computer name
+];
+
+const DEVICE_LIST_ANNOUNCE_BUFFER: [u8; 38] = [
+    0x72, 0x44, 0x41, 0x44, // header
+    0x01, 0x00, 0x00, 0x00, // device count
+    0x08, 0x00, 0x00, 0x00, // device type
+    0x01, 0x00, 0x00, 0x00, // device id
+    0x68, 0x6f, 0x6d, 0x65, 0x00, 0x00, 0x00, 0x00, // preferred DOS name
+    0x0a, 0x00, 0x00, 0x00, // device data length
+    0x68, 0x00, 0x6f, 0x00, 0x6d, 0x00, 0x65, 0x00, 0x00, 0x00, // device data
+];
+
+const CREATE_REQUEST_BUFFER: [u8; 62] = [
+    0x72, 0x44, 0x52, 0x49, // header
+    0x01, 0x00, 0x00, 0x00, // device id
+    0x00, 0x00, 0x00, 0x00, // file id
+    0x05, 0x00, 0x00, 0x00, // completion id
+    0x00, 0x00, 0x00, 0x00, // major function
+    0x00, 0x00, 0x00, 0x00, // minor function
+    0x89, 0x00, 0x12, 0x00, // desired access
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // allocation size
+    0x80, 0x00, 0x00, 0x00, // file attributes
+    0x07, 0x00, 0x00, 0x00, // shared access
+    0x01, 0x00, 0x00, 0x00, // create disposition
+    0x60, 0x00, 0x00, 0x00, // create options
+    0x06, 0x00, 0x00, 0x00, // path length
+    0x5c, 0x00, 0x61, 0x00, 0x00, 0x00, // path
+];
+
+const CREATE_COMPLETION_BUFFER: [u8; 21] = [
+    0x72, 0x44, 0x43, 0x49, // header
+    0x01, 0x00, 0x00, 0x00, // device id
+    0x05, 0x00, 0x00, 0x00, // completion id
+    0x00, 0x00, 0x00, 0x00, // I/O status
+    0x07, 0x00, 0x00, 0x00, // file id
+    0x01, // information
+];
+
+lazy_static! {
+    static ref SERVER_ANNOUNCE: ServerPdu = ServerPdu::ServerAnnounce(VersionAndIdPdu {
+        version_major: RDPDR_VERSION_MAJOR,
+        version_minor: RDPDR_VERSION_MINOR_12,
+        client_id: 2,
+    });
+    static ref CLIENT_NAME: ClientPdu = ClientPdu::ClientName(ClientNamePdu {
+        computer_name: String::from("PC"),
+    });
+    static ref DEVICE_LIST_ANNOUNCE: ClientPdu = ClientPdu::DeviceListAnnounce(DeviceListAnnouncePdu {
+        devices: vec![DeviceAnnounce::drive(1, "home")],
+    });
+    static ref CREATE_REQUEST: DeviceIoRequestPdu = DeviceIoRequestPdu {
+        device_id: 1,
+        file_id: 0,
+        completion_id: 5,
+        request: IoRequest::Create(CreateRequest {
+            desired_access: 0x0012_0089,
+            allocation_size: 0,
+            file_attributes: FileAttributes::NORMAL,
+            shared_access: 7,
+            create_disposition: CreateDisposition::Open,
+            create_options: CreateOptions::SYNCHRONOUS_IO_NONALERT | CreateOptions::NON_DIRECTORY_FILE,
+            path: String::from("\\a"),
+        }),
+    };
+}
+
+#[test]
+fn from_buffer_correctly_parses_server_announce_pdu() {
+    assert_eq!(
+        *SERVER_ANNOUNCE,
+        ServerPdu::from_buffer(SERVER_ANNOUNCE_BUFFER.as_ref()).unwrap()
+    );
+}
+
+#[test]
+fn to_buffer_correctly_serializes_client_name_pdu() {
+    let mut buffer = Vec::new();
+    CLIENT_NAME.to_buffer(&mut buffer).unwrap();
+
+    assert_eq!(CLIENT_NAME_BUFFER.as_ref(), buffer.as_slice());
+    assert_eq!(CLIENT_NAME_BUFFER.len(), CLIENT_NAME.buffer_length());
+}
+
+#[test]
+fn to_buffer_correctly_serializes_device_list_announce_pdu() {
+    let mut buffer = Vec::new();
+    DEVICE_LIST_ANNOUNCE.to_buffer(&mut buffer).unwrap();
+
+    assert_eq!(DEVICE_LIST_ANNOUNCE_BUFFER.as_ref(), buffer.as_slice());
+    assert_eq!(DEVICE_LIST_ANNOUNCE_BUFFER.len(), DEVICE_LIST_ANNOUNCE.buffer_length());
+}
+
+#[test]
+fn from_buffer_correctly_parses_create_request_pdu() {
+    assert_eq!(
+        ServerPdu::DeviceIoRequest(CREATE_REQUEST.clone()),
+        ServerPdu::from_buffer(CREATE_REQUEST_BUFFER.as_ref()).unwrap()
+    );
+}
+
+#[test]
+fn to_buffer_correctly_serializes_create_completion_pdu() {
+    let completion = ClientPdu::DeviceIoCompletion(DeviceIoCompletionPdu::new(
+        &CREATE_REQUEST,
+        NtStatus::SUCCESS,
+        &IoResponse::Create {
+            file_id: 7,
+            information: CreateInformation::Opened,
+        },
+    ));
+
+    let mut buffer = Vec::new();
+    completion.to_buffer(&mut buffer).unwrap();
+
+    assert_eq!(CREATE_COMPLETION_BUFFER.as_ref(), buffer.as_slice());
+    assert_eq!(CREATE_COMPLETION_BUFFER.len(), completion.buffer_length());
+}
+
+#[test]
+fn from_buffer_fails_on_printer_component() {
+    match ServerPdu::from_buffer([0x52, 0x50, 0x43, 0x50].as_ref()) {
+        Err(RdpdrError::UnexpectedComponent(COMPONENT_PRINTER)) => (),
+        res => panic!("Expected the unexpected component error, got: {:?}", res),
+    }
+}
+
+#[test]
+fn disposition_without_buffer_deletes_the_file() {
+    assert_eq!(
+        SetInformation::Disposition { delete_pending: true },
+        SetInformation::from_buffer(FileInformationClass::DISPOSITION, &[]).unwrap()
+    );
+}
+
+#[test]
+fn directory_entries_have_the_size_of_their_information_class() {
+    let entry = DirectoryEntry {
+        file_name: String::from("file.txt"),
+        information: FileInformation {
+            basic: FileBasicInformation {
+                creation_time: 0,
+                last_access_time: 0,
+                last_write_time: 0,
+                change_time: 0,
+                file_attributes: FileAttributes::ARCHIVE,
+            },
+            end_of_file: 3,
+            allocation_size: 4096,
+        },
+    };
+
+    for (information_class, fixed_part_size) in [
+        (FileInformationClass::DIRECTORY, 64),
+        (FileInformationClass::FULL_DIRECTORY, 68),
+        (FileInformationClass::BOTH_DIRECTORY, 93),
+        (FileInformationClass::NAMES, 12),
+    ] {
+        assert_eq!(fixed_part_size + 16, entry.encode(information_class).unwrap().len());
+    }
+    assert_eq!(None, entry.encode(FileInformationClass::BASIC));
+}
+
+fn any_capability_set() -> impl Strategy<Value = CapabilitySet> {
+    prop_oneof![
+        (any::<[u32; 4]>(), any::<[u16; 2]>(), any::<bool>()).prop_map(
+            |([os_type, os_version, io_code1, special_type_device_cap], [major, minor], version_2)| {
+                CapabilitySet::General(GeneralCapabilitySet {
+                    version: if version_2 {
+                        RDPDR_GENERAL_CAPABILITY_VERSION_02
+                    } else {
+                        RDPDR_GENERAL_CAPABILITY_VERSION_01
+                    },
+                    os_type,
+                    os_version,
+                    protocol_major_version: major,
+                    protocol_minor_version: minor,
+                    io_code1: IoCode1::from_bits_truncate(io_code1),
+                    extended_pdu: ExtendedPdu::USER_LOGGEDON_PDU,
+                    extra_flags1: ExtraFlags1::empty(),
+                    special_type_device_cap: if version_2 { special_type_device_cap } else { 0 },
+                })
+            }
+        ),
+        any::<u32>().prop_map(|version| CapabilitySet::Printer { version }),
+        any::<u32>().prop_map(|version| CapabilitySet::Port { version }),
+        any::<u32>().prop_map(|version| CapabilitySet::Drive { version }),
+        any::<u32>().prop_map(|version| CapabilitySet::Smartcard { version }),
+    ]
+}
+
+fn any_io_request() -> impl Strategy<Value = IoRequest> {
+    prop_oneof![
+        Just(IoRequest::Close),
+        (any::<u32>(), any::<u64>()).prop_map(|(length, offset)| IoRequest::Read { length, offset }),
+        (any::<u64>(), prop::collection::vec(any::<u8>(), 0..64))
+            .prop_map(|(offset, data)| IoRequest::Write { offset, data }),
+        any::<u32>().prop_map(|class| IoRequest::QueryInformation {
+            information_class: FileInformationClass(class),
+        }),
+        any::<u64>().prop_map(|size| IoRequest::SetInformation(SetInformation::EndOfFile(size))),
+        (any::<bool>(), "[a-z.\\\\]{1,16}").prop_map(|(replace_if_exists, file_name)| IoRequest::SetInformation(
+            SetInformation::Rename {
+                replace_if_exists,
+                file_name,
+            }
+        )),
+        (any::<u32>(), any::<bool>(), "[a-z*.\\\\]{0,16}").prop_map(|(class, initial_query, pattern)| {
+            IoRequest::QueryDirectory {
+                information_class: FileInformationClass(class),
+                initial_query,
+                pattern,
+            }
+        }),
+        (any::<u32>(), any::<u32>(), prop::collection::vec(any::<u8>(), 0..32)).prop_map(
+            |(output_buffer_length, io_control_code, input_buffer)| IoRequest::DeviceControl {
+                output_buffer_length,
+                io_control_code,
+                input_buffer,
+            }
+        ),
+    ]
+}
+
+crate::round_trip_proptest! {
+    client_capability_pdu_round_trip: ClientPdu = prop::collection::vec(any_capability_set(), 0..6)
+        .prop_map(|capabilities| ClientPdu::ClientCapability(CoreCapabilityPdu { capabilities }));
+    device_list_announce_pdu_round_trip: ClientPdu = prop::collection::vec((any::<u32>(), "[a-zA-Z0-9 ]{1,16}"), 0..4)
+        .prop_map(|devices| ClientPdu::DeviceListAnnounce(DeviceListAnnouncePdu {
+            devices: devices.iter().map(|(device_id, name)| DeviceAnnounce::drive(*device_id, name)).collect(),
+        }));
+    device_io_request_pdu_round_trip: ServerPdu = (any::<[u32; 3]>(), any_io_request())
+        .prop_map(|([device_id, file_id, completion_id], request)| ServerPdu::DeviceIoRequest(DeviceIoRequestPdu {
+            device_id,
+            file_id,
+            completion_id,
+            request,
+        }));
+}
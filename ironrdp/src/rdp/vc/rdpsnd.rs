@@ -0,0 +1,726 @@
+//! PDUs of the Audio Output Virtual Channel Extension (MS-RDPEA), exchanged on the `rdpsnd` static channel.
+
+#[cfg(test)]
+mod tests;
+
+use std::io;
+
+use bitflags::bitflags;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use num_derive::{FromPrimitive, ToPrimitive};
+use num_traits::{FromPrimitive, ToPrimitive};
+use thiserror::Error;
+
+use crate::{impl_from_error, PduParsing};
+
+pub const WAVE_FORMAT_PCM: u16 = 0x0001;
+
+const HEADER_SIZE: usize = 4;
+const AUDIO_FORMATS_PDU_FIXED_PART_SIZE: usize = 20;
+const AUDIO_FORMAT_FIXED_PART_SIZE: usize = 18;
+const TRAINING_PDU_FIXED_PART_SIZE: usize = 4;
+const WAVE_INFO_PDU_SIZE: usize = 12;
+const WAVE_INFO_DATA_SIZE: usize = 4;
+const WAVE2_PDU_FIXED_PART_SIZE: usize = 12;
+const WAVE_CONFIRM_PDU_SIZE: usize = 4;
+const QUALITY_MODE_PDU_SIZE: usize = 4;
+const VOLUME_PDU_SIZE: usize = 4;
+const PITCH_PDU_SIZE: usize = 4;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ServerPdu {
+    AudioFormats(AudioFormatsPdu),
+    Training(TrainingPdu),
+    /// Always followed by a [`WavePdu`], sent in its own channel message.
+    WaveInfo(WaveInfoPdu),
+    Wave2(Wave2Pdu),
+    Volume(VolumePdu),
+    Pitch(PitchPdu),
+    Close,
+}
+
+impl ServerPdu {
+    pub fn as_short_name(&self) -> &str {
+        match self {
+            ServerPdu::AudioFormats(_) => "Server Audio Formats and Version PDU",
+            ServerPdu::Training(_) => "Training PDU",
+            ServerPdu::WaveInfo(_) => "WaveInfo PDU",
+            ServerPdu::Wave2(_) => "Wave2 PDU",
+            ServerPdu::Volume(_) => "Volume PDU",
+            ServerPdu::Pitch(_) => "Pitch PDU",
+            ServerPdu::Close => "Close PDU",
+        }
+    }
+
+    fn pdu_type(&self) -> PduType {
+        match self {
+            ServerPdu::AudioFormats(_) => PduType::Formats,
+            ServerPdu::Training(_) => PduType::Training,
+            ServerPdu::WaveInfo(_) => PduType::Wave,
+            ServerPdu::Wave2(_) => PduType::Wave2,
+            ServerPdu::Volume(_) => PduType::SetVolume,
+            ServerPdu::Pitch(_) => PduType::SetPitch,
+            ServerPdu::Close => PduType::Close,
+        }
+    }
+
+    fn body_size(&self) -> usize {
+        match self {
+            ServerPdu::AudioFormats(pdu) => pdu.buffer_length(),
+            ServerPdu::Training(pdu) => pdu.buffer_length(),
+            ServerPdu::WaveInfo(pdu) => pdu.body_size(),
+            ServerPdu::Wave2(pdu) => pdu.buffer_length(),
+            ServerPdu::Volume(pdu) => pdu.buffer_length(),
+            ServerPdu::Pitch(pdu) => pdu.buffer_length(),
+            ServerPdu::Close => 0,
+        }
+    }
+}
+
+impl PduParsing for ServerPdu {
+    type Error = RdpsndError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        let (pdu_type, body_size) = read_header(&mut stream)?;
+
+        match pdu_type {
+            PduType::Formats => Ok(ServerPdu::AudioFormats(AudioFormatsPdu::from_buffer(&mut stream)?)),
+            PduType::Training => Ok(ServerPdu::Training(TrainingPdu::from_buffer_with_body_size(
+                &mut stream,
+                body_size,
+            )?)),
+            PduType::Wave => Ok(ServerPdu::WaveInfo(WaveInfoPdu::from_buffer_with_body_size(
+                &mut stream,
+                body_size,
+            )?)),
+            PduType::Wave2 => Ok(ServerPdu::Wave2(Wave2Pdu::from_buffer_with_body_size(
+                &mut stream,
+                body_size,
+            )?)),
+            PduType::SetVolume => Ok(ServerPdu::Volume(VolumePdu::from_buffer(&mut stream)?)),
+            PduType::SetPitch => Ok(ServerPdu::Pitch(PitchPdu::from_buffer(&mut stream)?)),
+            PduType::Close => Ok(ServerPdu::Close),
+            PduType::WaveConfirm
+            | PduType::CryptKey
+            | PduType::WaveEncrypt
+            | PduType::UdpWave
+            | PduType::UdpWaveLast
+            | PduType::QualityMode => Err(RdpsndError::UnexpectedPduType(pdu_type)),
+        }
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        write_header(&mut stream, self.pdu_type(), self.body_size())?;
+
+        match self {
+            ServerPdu::AudioFormats(pdu) => pdu.to_buffer(&mut stream),
+            ServerPdu::Training(pdu) => pdu.to_buffer(&mut stream),
+            ServerPdu::WaveInfo(pdu) => pdu.to_buffer(&mut stream),
+            ServerPdu::Wave2(pdu) => pdu.to_buffer(&mut stream),
+            ServerPdu::Volume(pdu) => pdu.to_buffer(&mut stream),
+            ServerPdu::Pitch(pdu) => pdu.to_buffer(&mut stream),
+            ServerPdu::Close => Ok(()),
+        }
+    }
+
+    fn buffer_length(&self) -> usize {
+        let body_length = match self {
+            // the body size of the WaveInfo PDU also accounts for the following Wave PDU
+            ServerPdu::WaveInfo(pdu) => pdu.buffer_length(),
+            pdu => pdu.body_size(),
+        };
+
+        HEADER_SIZE + body_length
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClientPdu {
+    AudioFormats(AudioFormatsPdu),
+    QualityMode(QualityModePdu),
+    TrainingConfirm(TrainingConfirmPdu),
+    WaveConfirm(WaveConfirmPdu),
+}
+
+impl ClientPdu {
+    pub fn as_short_name(&self) -> &str {
+        match self {
+            ClientPdu::AudioFormats(_) => "Client Audio Formats and Version PDU",
+            ClientPdu::QualityMode(_) => "Quality Mode PDU",
+            ClientPdu::TrainingConfirm(_) => "Training Confirm PDU",
+            ClientPdu::WaveConfirm(_) => "Wave Confirm PDU",
+        }
+    }
+
+    fn pdu_type(&self) -> PduType {
+        match self {
+            ClientPdu::AudioFormats(_) => PduType::Formats,
+            ClientPdu::QualityMode(_) => PduType::QualityMode,
+            ClientPdu::TrainingConfirm(_) => PduType::Training,
+            ClientPdu::WaveConfirm(_) => PduType::WaveConfirm,
+        }
+    }
+
+    fn body_size(&self) -> usize {
+        match self {
+            ClientPdu::AudioFormats(pdu) => pdu.buffer_length(),
+            ClientPdu::QualityMode(pdu) => pdu.buffer_length(),
+            ClientPdu::TrainingConfirm(pdu) => pdu.buffer_length(),
+            ClientPdu::WaveConfirm(pdu) => pdu.buffer_length(),
+        }
+    }
+}
+
+impl PduParsing for ClientPdu {
+    type Error = RdpsndError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        let (pdu_type, _body_size) = read_header(&mut stream)?;
+
+        match pdu_type {
+            PduType::Formats => Ok(ClientPdu::AudioFormats(AudioFormatsPdu::from_buffer(&mut stream)?)),
+            PduType::QualityMode => Ok(ClientPdu::QualityMode(QualityModePdu::from_buffer(&mut stream)?)),
+            PduType::Training => Ok(ClientPdu::TrainingConfirm(TrainingConfirmPdu::from_buffer(
+                &mut stream,
+            )?)),
+            PduType::WaveConfirm => Ok(ClientPdu::WaveConfirm(WaveConfirmPdu::from_buffer(&mut stream)?)),
+            PduType::Close
+            | PduType::Wave
+            | PduType::SetVolume
+            | PduType::SetPitch
+            | PduType::CryptKey
+            | PduType::WaveEncrypt
+            | PduType::UdpWave
+            | PduType::UdpWaveLast
+            | PduType::Wave2 => Err(RdpsndError::UnexpectedPduType(pdu_type)),
+        }
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        write_header(&mut stream, self.pdu_type(), self.body_size())?;
+
+        match self {
+            ClientPdu::AudioFormats(pdu) => pdu.to_buffer(&mut stream),
+            ClientPdu::QualityMode(pdu) => pdu.to_buffer(&mut stream),
+            ClientPdu::TrainingConfirm(pdu) => pdu.to_buffer(&mut stream),
+            ClientPdu::WaveConfirm(pdu) => pdu.to_buffer(&mut stream),
+        }
+    }
+
+    fn buffer_length(&self) -> usize {
+        HEADER_SIZE + self.body_size()
+    }
+}
+
+/// The Server and Client Audio Formats and Version PDUs, which share the same layout.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AudioFormatsPdu {
+    /// Ignored when sent by the server.
+    pub flags: AudioFormatsFlags,
+    /// Ignored when sent by the server.
+    pub volume: u32,
+    /// Ignored when sent by the server.
+    pub pitch: u32,
+    /// The UDP port on which the client listens for wave data, 0 for the static channel only.
+    pub dgram_port: u16,
+    pub last_block_confirmed: u8,
+    pub version: u16,
+    pub formats: Vec<AudioFormat>,
+}
+
+impl PduParsing for AudioFormatsPdu {
+    type Error = RdpsndError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        let flags = AudioFormatsFlags::from_bits_truncate(stream.read_u32::<LittleEndian>()?);
+        let volume = stream.read_u32::<LittleEndian>()?;
+        let pitch = stream.read_u32::<LittleEndian>()?;
+        let dgram_port = stream.read_u16::<LittleEndian>()?;
+        let formats_count = stream.read_u16::<LittleEndian>()?;
+        let last_block_confirmed = stream.read_u8()?;
+        let version = stream.read_u16::<LittleEndian>()?;
+        let _padding = stream.read_u8()?;
+        let formats = (0..formats_count)
+            .map(|_| AudioFormat::from_buffer(&mut stream))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            flags,
+            volume,
+            pitch,
+            dgram_port,
+            last_block_confirmed,
+            version,
+            formats,
+        })
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        stream.write_u32::<LittleEndian>(self.flags.bits())?;
+        stream.write_u32::<LittleEndian>(self.volume)?;
+        stream.write_u32::<LittleEndian>(self.pitch)?;
+        stream.write_u16::<LittleEndian>(self.dgram_port)?;
+        stream.write_u16::<LittleEndian>(self.formats.len() as u16)?;
+        stream.write_u8(self.last_block_confirmed)?;
+        stream.write_u16::<LittleEndian>(self.version)?;
+        stream.write_u8(0)?; // padding
+
+        for format in &self.formats {
+            format.to_buffer(&mut stream)?;
+        }
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        AUDIO_FORMATS_PDU_FIXED_PART_SIZE + self.formats.iter().map(|format| format.buffer_length()).sum::<usize>()
+    }
+}
+
+bitflags! {
+    pub struct AudioFormatsFlags: u32 {
+        const ALIVE = 0x0000_0001;
+        const VOLUME = 0x0000_0002;
+        const PITCH = 0x0000_0004;
+    }
+}
+
+/// The `AUDIO_FORMAT` structure, based on the `WAVEFORMATEX` structure.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AudioFormat {
+    /// The format tag, e.g. [`WAVE_FORMAT_PCM`].
+    pub format_tag: u16,
+    pub channels: u16,
+    pub samples_per_sec: u32,
+    pub avg_bytes_per_sec: u32,
+    pub block_align: u16,
+    pub bits_per_sample: u16,
+    /// Format specific data.
+    pub extra_data: Vec<u8>,
+}
+
+impl AudioFormat {
+    pub fn is_pcm(&self) -> bool {
+        self.format_tag == WAVE_FORMAT_PCM
+    }
+}
+
+impl PduParsing for AudioFormat {
+    type Error = RdpsndError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        let format_tag = stream.read_u16::<LittleEndian>()?;
+        let channels = stream.read_u16::<LittleEndian>()?;
+        let samples_per_sec = stream.read_u32::<LittleEndian>()?;
+        let avg_bytes_per_sec = stream.read_u32::<LittleEndian>()?;
+        let block_align = stream.read_u16::<LittleEndian>()?;
+        let bits_per_sample = stream.read_u16::<LittleEndian>()?;
+        let extra_data_size = stream.read_u16::<LittleEndian>()?;
+        let mut extra_data = vec![0; usize::from(extra_data_size)];
+        stream.read_exact(&mut extra_data)?;
+
+        Ok(Self {
+            format_tag,
+            channels,
+            samples_per_sec,
+            avg_bytes_per_sec,
+            block_align,
+            bits_per_sample,
+            extra_data,
+        })
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        stream.write_u16::<LittleEndian>(self.format_tag)?;
+        stream.write_u16::<LittleEndian>(self.channels)?;
+        stream.write_u32::<LittleEndian>(self.samples_per_sec)?;
+        stream.write_u32::<LittleEndian>(self.avg_bytes_per_sec)?;
+        stream.write_u16::<LittleEndian>(self.block_align)?;
+        stream.write_u16::<LittleEndian>(self.bits_per_sample)?;
+        stream.write_u16::<LittleEndian>(self.extra_data.len() as u16)?;
+        stream.write_all(&self.extra_data)?;
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        AUDIO_FORMAT_FIXED_PART_SIZE + self.extra_data.len()
+    }
+}
+
+/// Sent by the server to measure the network latency, the client replies with the Training Confirm PDU.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrainingPdu {
+    pub time_stamp: u16,
+    pub pack_size: u16,
+    pub data: Vec<u8>,
+}
+
+impl TrainingPdu {
+    fn from_buffer_with_body_size(mut stream: impl io::Read, body_size: usize) -> Result<Self, RdpsndError> {
+        let data_size = body_size
+            .checked_sub(TRAINING_PDU_FIXED_PART_SIZE)
+            .ok_or(RdpsndError::InvalidBodySize(body_size))?;
+
+        let time_stamp = stream.read_u16::<LittleEndian>()?;
+        let pack_size = stream.read_u16::<LittleEndian>()?;
+        let mut data = vec![0; data_size];
+        stream.read_exact(&mut data)?;
+
+        Ok(Self {
+            time_stamp,
+            pack_size,
+            data,
+        })
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), RdpsndError> {
+        stream.write_u16::<LittleEndian>(self.time_stamp)?;
+        stream.write_u16::<LittleEndian>(self.pack_size)?;
+        stream.write_all(&self.data)?;
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        TRAINING_PDU_FIXED_PART_SIZE + self.data.len()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrainingConfirmPdu {
+    pub time_stamp: u16,
+    pub pack_size: u16,
+}
+
+impl PduParsing for TrainingConfirmPdu {
+    type Error = RdpsndError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        let time_stamp = stream.read_u16::<LittleEndian>()?;
+        let pack_size = stream.read_u16::<LittleEndian>()?;
+
+        Ok(Self { time_stamp, pack_size })
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        stream.write_u16::<LittleEndian>(self.time_stamp)?;
+        stream.write_u16::<LittleEndian>(self.pack_size)?;
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        TRAINING_PDU_FIXED_PART_SIZE
+    }
+}
+
+/// The first part of an audio sample, the rest of it is sent in the following [`WavePdu`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WaveInfoPdu {
+    pub time_stamp: u16,
+    /// Index of the format in the formats sent by the client.
+    pub format_no: u16,
+    pub block_no: u8,
+    /// The first 4 bytes of the audio sample.
+    pub data: [u8; WAVE_INFO_DATA_SIZE],
+    /// The length of the whole audio sample.
+    pub wave_length: usize,
+}
+
+impl WaveInfoPdu {
+    fn from_buffer_with_body_size(mut stream: impl io::Read, body_size: usize) -> Result<Self, RdpsndError> {
+        // the body size accounts for this PDU and the data of the following Wave PDU, without its padding
+        let wave_length = body_size
+            .checked_sub(WAVE_INFO_PDU_SIZE - WAVE_INFO_DATA_SIZE)
+            .filter(|wave_length| *wave_length >= WAVE_INFO_DATA_SIZE)
+            .ok_or(RdpsndError::InvalidBodySize(body_size))?;
+
+        let time_stamp = stream.read_u16::<LittleEndian>()?;
+        let format_no = stream.read_u16::<LittleEndian>()?;
+        let block_no = stream.read_u8()?;
+        let mut padding = [0; 3];
+        stream.read_exact(&mut padding)?;
+        let mut data = [0; WAVE_INFO_DATA_SIZE];
+        stream.read_exact(&mut data)?;
+
+        Ok(Self {
+            time_stamp,
+            format_no,
+            block_no,
+            data,
+            wave_length,
+        })
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), RdpsndError> {
+        stream.write_u16::<LittleEndian>(self.time_stamp)?;
+        stream.write_u16::<LittleEndian>(self.format_no)?;
+        stream.write_u8(self.block_no)?;
+        stream.write_all(&[0; 3])?; // padding
+        stream.write_all(&self.data)?;
+
+        Ok(())
+    }
+
+    fn body_size(&self) -> usize {
+        WAVE_INFO_PDU_SIZE - WAVE_INFO_DATA_SIZE + self.wave_length
+    }
+
+    fn buffer_length(&self) -> usize {
+        WAVE_INFO_PDU_SIZE
+    }
+}
+
+/// The rest of the audio sample announced by the preceding [`WaveInfoPdu`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WavePdu {
+    /// The whole audio sample, including the first 4 bytes sent in the WaveInfo PDU.
+    pub data: Vec<u8>,
+}
+
+impl WavePdu {
+    pub fn from_buffer(mut stream: impl io::Read, wave_info: &WaveInfoPdu) -> Result<Self, RdpsndError> {
+        // the padding takes the place of the data sent in the WaveInfo PDU
+        let mut data = vec![0; wave_info.wave_length];
+        stream.read_exact(&mut data)?;
+        data[..WAVE_INFO_DATA_SIZE].copy_from_slice(&wave_info.data);
+
+        Ok(Self { data })
+    }
+
+    pub fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), RdpsndError> {
+        stream.write_all(&[0; WAVE_INFO_DATA_SIZE])?; // padding
+        stream.write_all(&self.data[WAVE_INFO_DATA_SIZE..])?;
+
+        Ok(())
+    }
+
+    pub fn buffer_length(&self) -> usize {
+        self.data.len()
+    }
+}
+
+/// An audio sample sent in a single PDU, along with the time it was generated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Wave2Pdu {
+    pub time_stamp: u16,
+    /// Index of the format in the formats sent by the client.
+    pub format_no: u16,
+    pub block_no: u8,
+    pub audio_time_stamp: u32,
+    pub data: Vec<u8>,
+}
+
+impl Wave2Pdu {
+    fn from_buffer_with_body_size(mut stream: impl io::Read, body_size: usize) -> Result<Self, RdpsndError> {
+        let data_size = body_size
+            .checked_sub(WAVE2_PDU_FIXED_PART_SIZE)
+            .ok_or(RdpsndError::InvalidBodySize(body_size))?;
+
+        let time_stamp = stream.read_u16::<LittleEndian>()?;
+        let format_no = stream.read_u16::<LittleEndian>()?;
+        let block_no = stream.read_u8()?;
+        let mut padding = [0; 3];
+        stream.read_exact(&mut padding)?;
+        let audio_time_stamp = stream.read_u32::<LittleEndian>()?;
+        let mut data = vec![0; data_size];
+        stream.read_exact(&mut data)?;
+
+        Ok(Self {
+            time_stamp,
+            format_no,
+            block_no,
+            audio_time_stamp,
+            data,
+        })
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), RdpsndError> {
+        stream.write_u16::<LittleEndian>(self.time_stamp)?;
+        stream.write_u16::<LittleEndian>(self.format_no)?;
+        stream.write_u8(self.block_no)?;
+        stream.write_all(&[0; 3])?; // padding
+        stream.write_u32::<LittleEndian>(self.audio_time_stamp)?;
+        stream.write_all(&self.data)?;
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        WAVE2_PDU_FIXED_PART_SIZE + self.data.len()
+    }
+}
+
+/// Sent by the client once an audio sample has been played.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WaveConfirmPdu {
+    pub time_stamp: u16,
+    pub confirmed_block_no: u8,
+}
+
+impl PduParsing for WaveConfirmPdu {
+    type Error = RdpsndError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        let time_stamp = stream.read_u16::<LittleEndian>()?;
+        let confirmed_block_no = stream.read_u8()?;
+        let _padding = stream.read_u8()?;
+
+        Ok(Self {
+            time_stamp,
+            confirmed_block_no,
+        })
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        stream.write_u16::<LittleEndian>(self.time_stamp)?;
+        stream.write_u8(self.confirmed_block_no)?;
+        stream.write_u8(0)?; // padding
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        WAVE_CONFIRM_PDU_SIZE
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QualityModePdu {
+    pub quality_mode: QualityMode,
+}
+
+impl PduParsing for QualityModePdu {
+    type Error = RdpsndError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        let quality_mode = stream.read_u16::<LittleEndian>()?;
+        let quality_mode = QualityMode::from_u16(quality_mode).ok_or(RdpsndError::InvalidQualityMode(quality_mode))?;
+        let _reserved = stream.read_u16::<LittleEndian>()?;
+
+        Ok(Self { quality_mode })
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        stream.write_u16::<LittleEndian>(self.quality_mode.to_u16().unwrap())?;
+        stream.write_u16::<LittleEndian>(0)?; // reserved
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        QUALITY_MODE_PDU_SIZE
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, FromPrimitive, ToPrimitive)]
+pub enum QualityMode {
+    Dynamic = 0x0000,
+    Medium = 0x0001,
+    High = 0x0002,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VolumePdu {
+    pub left: u16,
+    pub right: u16,
+}
+
+impl PduParsing for VolumePdu {
+    type Error = RdpsndError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        let left = stream.read_u16::<LittleEndian>()?;
+        let right = stream.read_u16::<LittleEndian>()?;
+
+        Ok(Self { left, right })
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        stream.write_u16::<LittleEndian>(self.left)?;
+        stream.write_u16::<LittleEndian>(self.right)?;
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        VOLUME_PDU_SIZE
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PitchPdu {
+    pub pitch: u32,
+}
+
+impl PduParsing for PitchPdu {
+    type Error = RdpsndError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        let pitch = stream.read_u32::<LittleEndian>()?;
+
+        Ok(Self { pitch })
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        stream.write_u32::<LittleEndian>(self.pitch)?;
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        PITCH_PDU_SIZE
+    }
+}
+
+#[repr(u8)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, FromPrimitive, ToPrimitive)]
+pub enum PduType {
+    Close = 0x01,
+    Wave = 0x02,
+    SetVolume = 0x03,
+    SetPitch = 0x04,
+    WaveConfirm = 0x05,
+    Training = 0x06,
+    Formats = 0x07,
+    CryptKey = 0x08,
+    WaveEncrypt = 0x09,
+    UdpWave = 0x0A,
+    UdpWaveLast = 0x0B,
+    QualityMode = 0x0C,
+    Wave2 = 0x0D,
+}
+
+fn read_header(mut stream: impl io::Read) -> Result<(PduType, usize), RdpsndError> {
+    let pdu_type = stream.read_u8()?;
+    let pdu_type = PduType::from_u8(pdu_type).ok_or(RdpsndError::InvalidPduType(pdu_type))?;
+    let _padding = stream.read_u8()?;
+    let body_size = stream.read_u16::<LittleEndian>()?;
+
+    Ok((pdu_type, usize::from(body_size)))
+}
+
+fn write_header(mut stream: impl io::Write, pdu_type: PduType, body_size: usize) -> Result<(), RdpsndError> {
+    stream.write_u8(pdu_type.to_u8().unwrap())?;
+    stream.write_u8(0)?; // padding
+    stream.write_u16::<LittleEndian>(body_size as u16)?;
+
+    Ok(())
+}
+
+#[derive(Debug, Error)]
+pub enum RdpsndError {
+    #[error("IO error: {}", .0)]
+    IOError(#[source] io::Error),
+    #[error("Invalid RDPSND PDU type: {}", .0)]
+    InvalidPduType(u8),
+    #[error("Unexpected RDPSND PDU type: {:?}", .0)]
+    UnexpectedPduType(PduType),
+    #[error("Invalid RDPSND PDU body size: {}", .0)]
+    InvalidBodySize(usize),
+    #[error("Invalid quality mode: {}", .0)]
+    InvalidQualityMode(u16),
+}
+
+impl_from_error!(io::Error, RdpsndError, RdpsndError::IOError);
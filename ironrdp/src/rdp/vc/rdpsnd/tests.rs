@@ -0,0 +1,215 @@
+use lazy_static::lazy_static;
+
+use super::*;
+
+const SERVER_AUDIO_FORMATS_BUFFER: [u8; 42] = [
+    0x07, 0x00, 0x26, 0x00, // header
+    0x00, 0x00, 0x00, 0x00, // flags
+    0x00, 0x00, 0x00, 0x00, // volume
+    0x00, 0x00, 0x00, 0x00, // pitch
+    0x00, 0x00, // dgram port
+    0x01, 0x00, // number of formats
+    0x00, // last block confirmed
+    0x06, 0x00, // version
+    0x00, // padding
+    0x01, 0x00, // format tag
+    0x02, 0x00, // channels
+    0x44, 0xac, 0x00, 0x00, // samples per sec
+    0x10, 0xb1, 0x02, 0x00, // avg bytes per sec
+    0x04, 0x00, // block align
+    0x10, 0x00, // bits per sample
+    0x00, 0x00, // extra data size
+];
+
+const TRAINING_BUFFER: [u8; 8] = [0x06, 0x00, 0x04, 0x00, 0x0b, 0x0a, 0x00, 0x00];
+
+const TRAINING_CONFIRM_BUFFER: [u8; 8] = [0x06, 0x00, 0x04, 0x00, 0x0b, 0x0a, 0x00, 0x00];
+
+const WAVE_INFO_BUFFER: [u8; 16] = [
+    0x02, 0x00, 0x10, 0x00, // header
+    0x34, 0x12, // time stamp
+    0x00, 0x00, // format no
+    0x05, // block no
+    0x00, 0x00, 0x00, // padding
+    0x01, 0x02, 0x03, 0x04, // initial data
+];
+
+const WAVE_BUFFER: [u8; 8] = [0x00, 0x00, 0x00, 0x00, 0x05, 0x06, 0x07, 0x08];
+
+const WAVE_CONFIRM_BUFFER: [u8; 8] = [0x05, 0x00, 0x04, 0x00, 0x34, 0x12, 0x05, 0x00];
+
+const QUALITY_MODE_BUFFER: [u8; 8] = [0x0c, 0x00, 0x04, 0x00, 0x02, 0x00, 0x00, 0x00];
+
+const VOLUME_BUFFER: [u8; 8] = [0x03, 0x00, 0x04, 0x00, 0xff, 0xff, 0x00, 0x80];
+
+const CLOSE_BUFFER: [u8; 4] = [0x01, 0x00, 0x00, 0x00];
+
+lazy_static! {
+    static ref PCM_FORMAT: AudioFormat = AudioFormat {
+        format_tag: WAVE_FORMAT_PCM,
+        channels: 2,
+        samples_per_sec: 44100,
+        avg_bytes_per_sec: 176_400,
+        block_align: 4,
+        bits_per_sample: 16,
+        extra_data: Vec::new(),
+    };
+    static ref SERVER_AUDIO_FORMATS: ServerPdu = ServerPdu::AudioFormats(AudioFormatsPdu {
+        flags: AudioFormatsFlags::empty(),
+        volume: 0,
+        pitch: 0,
+        dgram_port: 0,
+        last_block_confirmed: 0,
+        version: 6,
+        formats: vec![PCM_FORMAT.clone()],
+    });
+    static ref TRAINING: ServerPdu = ServerPdu::Training(TrainingPdu {
+        time_stamp: 0x0a0b,
+        pack_size: 0,
+        data: Vec::new(),
+    });
+    static ref TRAINING_CONFIRM: ClientPdu = ClientPdu::TrainingConfirm(TrainingConfirmPdu {
+        time_stamp: 0x0a0b,
+        pack_size: 0,
+    });
+    static ref WAVE_INFO: WaveInfoPdu = WaveInfoPdu {
+        time_stamp: 0x1234,
+        format_no: 0,
+        block_no: 5,
+        data: [0x01, 0x02, 0x03, 0x04],
+        wave_length: 8,
+    };
+    static ref WAVE: WavePdu = WavePdu {
+        data: vec![0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08],
+    };
+    static ref WAVE_CONFIRM: ClientPdu = ClientPdu::WaveConfirm(WaveConfirmPdu {
+        time_stamp: 0x1234,
+        confirmed_block_no: 5,
+    });
+    static ref QUALITY_MODE: ClientPdu = ClientPdu::QualityMode(QualityModePdu {
+        quality_mode: QualityMode::High,
+    });
+    static ref VOLUME: ServerPdu = ServerPdu::Volume(VolumePdu {
+        left: 0xffff,
+        right: 0x8000,
+    });
+}
+
+#[test]
+fn from_buffer_correctly_parses_server_audio_formats_pdu() {
+    assert_eq!(
+        *SERVER_AUDIO_FORMATS,
+        ServerPdu::from_buffer(SERVER_AUDIO_FORMATS_BUFFER.as_ref()).unwrap()
+    );
+}
+
+#[test]
+fn to_buffer_correctly_serializes_server_audio_formats_pdu() {
+    let mut buffer = Vec::new();
+    SERVER_AUDIO_FORMATS.to_buffer(&mut buffer).unwrap();
+
+    assert_eq!(SERVER_AUDIO_FORMATS_BUFFER.as_ref(), buffer.as_slice());
+}
+
+#[test]
+fn buffer_length_is_correct_for_server_audio_formats_pdu() {
+    assert_eq!(SERVER_AUDIO_FORMATS_BUFFER.len(), SERVER_AUDIO_FORMATS.buffer_length());
+}
+
+#[test]
+fn from_buffer_correctly_parses_training_pdu() {
+    assert_eq!(*TRAINING, ServerPdu::from_buffer(TRAINING_BUFFER.as_ref()).unwrap());
+}
+
+#[test]
+fn to_buffer_correctly_serializes_training_confirm_pdu() {
+    let mut buffer = Vec::new();
+    TRAINING_CONFIRM.to_buffer(&mut buffer).unwrap();
+
+    assert_eq!(TRAINING_CONFIRM_BUFFER.as_ref(), buffer.as_slice());
+}
+
+#[test]
+fn from_buffer_correctly_parses_wave_info_and_wave_pdus() {
+    let wave_info = match ServerPdu::from_buffer(WAVE_INFO_BUFFER.as_ref()).unwrap() {
+        ServerPdu::WaveInfo(wave_info) => wave_info,
+        pdu => panic!("Expected the WaveInfo PDU, got: {:?}", pdu),
+    };
+    assert_eq!(*WAVE_INFO, wave_info);
+
+    assert_eq!(*WAVE, WavePdu::from_buffer(WAVE_BUFFER.as_ref(), &wave_info).unwrap());
+}
+
+#[test]
+fn to_buffer_correctly_serializes_wave_info_and_wave_pdus() {
+    let mut buffer = Vec::new();
+    ServerPdu::WaveInfo(WAVE_INFO.clone()).to_buffer(&mut buffer).unwrap();
+    assert_eq!(WAVE_INFO_BUFFER.as_ref(), buffer.as_slice());
+
+    let mut buffer = Vec::new();
+    WAVE.to_buffer(&mut buffer).unwrap();
+    assert_eq!(WAVE_BUFFER.as_ref(), buffer.as_slice());
+}
+
+#[test]
+fn buffer_length_is_correct_for_wave_info_and_wave_pdus() {
+    assert_eq!(
+        WAVE_INFO_BUFFER.len(),
+        ServerPdu::WaveInfo(WAVE_INFO.clone()).buffer_length()
+    );
+    assert_eq!(WAVE_BUFFER.len(), WAVE.buffer_length());
+}
+
+#[test]
+fn from_buffer_wave_info_fails_on_too_small_body_size() {
+    let mut buffer = WAVE_INFO_BUFFER;
+    buffer[2] = 0x0b;
+
+    match ServerPdu::from_buffer(buffer.as_ref()) {
+        Err(RdpsndError::InvalidBodySize(0x0b)) => (),
+        res => panic!("Expected the invalid body size error, got: {:?}", res),
+    }
+}
+
+#[test]
+fn from_buffer_correctly_parses_wave_confirm_pdu() {
+    assert_eq!(
+        *WAVE_CONFIRM,
+        ClientPdu::from_buffer(WAVE_CONFIRM_BUFFER.as_ref()).unwrap()
+    );
+}
+
+#[test]
+fn to_buffer_correctly_serializes_wave_confirm_pdu() {
+    let mut buffer = Vec::new();
+    WAVE_CONFIRM.to_buffer(&mut buffer).unwrap();
+
+    assert_eq!(WAVE_CONFIRM_BUFFER.as_ref(), buffer.as_slice());
+}
+
+#[test]
+fn to_buffer_correctly_serializes_quality_mode_pdu() {
+    let mut buffer = Vec::new();
+    QUALITY_MODE.to_buffer(&mut buffer).unwrap();
+
+    assert_eq!(QUALITY_MODE_BUFFER.as_ref(), buffer.as_slice());
+    assert_eq!(QUALITY_MODE_BUFFER.len(), QUALITY_MODE.buffer_length());
+}
+
+#[test]
+fn from_buffer_correctly_parses_volume_pdu() {
+    assert_eq!(*VOLUME, ServerPdu::from_buffer(VOLUME_BUFFER.as_ref()).unwrap());
+}
+
+#[test]
+fn from_buffer_correctly_parses_close_pdu() {
+    assert_eq!(ServerPdu::Close, ServerPdu::from_buffer(CLOSE_BUFFER.as_ref()).unwrap());
+}
+
+#[test]
+fn from_buffer_server_pdu_fails_on_client_pdu_type() {
+    match ServerPdu::from_buffer(WAVE_CONFIRM_BUFFER.as_ref()) {
+        Err(RdpsndError::UnexpectedPduType(PduType::WaveConfirm)) => (),
+        res => panic!("Expected the unexpected PDU type error, got: {:?}", res),
+    }
+}
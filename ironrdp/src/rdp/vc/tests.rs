@@ -39,3 +39,127 @@ fn buffer_length_is_correct_for_channel_header() {
 
     assert_eq!(expected_buf_len, len);
 }
+
+#[test]
+fn static_channel_name_accepts_up_to_seven_characters() {
+    assert_eq!(StaticChannelName::CLIPRDR, StaticChannelName::new("cliprdr").unwrap());
+    assert_eq!(
+        Err(ChannelNameError::TooLong(
+            String::from("cliprdr2"),
+            STATIC_CHANNEL_NAME_MAX_LENGTH
+        )),
+        StaticChannelName::new("cliprdr2")
+    );
+}
+
+#[test]
+fn static_channel_name_rejects_empty_and_non_printable_ascii_names() {
+    assert_eq!(Err(ChannelNameError::Empty), StaticChannelName::new(""));
+    assert_eq!(
+        Err(ChannelNameError::InvalidCharacter(String::from("rdp\0dr"))),
+        StaticChannelName::new("rdp\0dr")
+    );
+    assert_eq!(
+        Err(ChannelNameError::InvalidCharacter(String::from("rdpé"))),
+        StaticChannelName::new("rdpé")
+    );
+}
+
+#[test]
+fn dvc_name_is_not_limited_in_length() {
+    let name = DvcName::new("Microsoft::Windows::RDS::Graphics").unwrap();
+
+    assert_eq!(DvcName::GRAPHICS_PIPELINE, name);
+    assert_eq!("Microsoft::Windows::RDS::Graphics", name.as_str());
+    assert_eq!(
+        Err(ChannelNameError::InvalidCharacter(String::from("Microsoft Graphics"))),
+        DvcName::new("Microsoft Graphics")
+    );
+}
+
+const CHANNEL_ID: ChannelId = ChannelId(1004);
+
+fn header(flags: ChannelControlFlags, total_length: u32) -> ChannelPduHeader {
+    ChannelPduHeader { total_length, flags }
+}
+
+#[test]
+fn reassembler_returns_message_once_last_chunk_is_received() {
+    let mut reassembler = ChannelReassembler::new(1024);
+
+    assert_eq!(
+        None,
+        reassembler
+            .process(CHANNEL_ID, &header(ChannelControlFlags::FLAG_FIRST, 6), b"abc")
+            .unwrap()
+    );
+    assert_eq!(
+        Some(b"abcdef".to_vec()),
+        reassembler
+            .process(CHANNEL_ID, &header(ChannelControlFlags::FLAG_LAST, 6), b"def")
+            .unwrap()
+    );
+}
+
+#[test]
+fn reassembler_decompresses_compressed_chunks() {
+    let mut reassembler = ChannelReassembler::new(1024);
+    // the 64K compression type
+    let flags = ChannelControlFlags::FLAG_FIRST
+        | ChannelControlFlags::FLAG_LAST
+        | ChannelControlFlags::PACKET_COMPRESSED
+        | ChannelControlFlags::PACKET_FLUSHED
+        | ChannelControlFlags::from_bits_truncate(0x0001_0000);
+    let header = header(flags, 9);
+    assert_eq!(
+        CompressionFlags::COMPRESSED | CompressionFlags::FLUSHED,
+        header.compression_flags()
+    );
+    assert_eq!(Some(CompressionType::K64), header.compression_type());
+
+    // literals `a`, `b` and `c`, followed by the copy of 6 bytes at offset 3
+    let data = [0x61, 0x62, 0x63, 0xf8, 0x74];
+    assert_eq!(
+        Some(b"abcabcabc".to_vec()),
+        reassembler.process(CHANNEL_ID, &header, &data).unwrap()
+    );
+}
+
+#[test]
+fn reassembler_fails_on_message_longer_than_total_length() {
+    let mut reassembler = ChannelReassembler::new(1024);
+    reassembler
+        .process(CHANNEL_ID, &header(ChannelControlFlags::FLAG_FIRST, 4), b"abc")
+        .unwrap();
+
+    assert!(matches!(
+        reassembler.process(CHANNEL_ID, &header(ChannelControlFlags::FLAG_LAST, 4), b"def"),
+        Err(ChannelError::InvalidChannelTotalDataLength)
+    ));
+}
+
+#[test]
+fn reassembler_fails_on_chunk_without_first_chunk() {
+    let mut reassembler = ChannelReassembler::new(1024);
+
+    assert!(matches!(
+        reassembler.process(CHANNEL_ID, &header(ChannelControlFlags::FLAG_LAST, 3), b"abc"),
+        Err(ChannelError::InvalidChannelPduHeader)
+    ));
+}
+
+#[test]
+fn reassembler_fails_on_message_larger_than_limit() {
+    let mut reassembler = ChannelReassembler::new(2);
+
+    assert!(matches!(
+        reassembler.process(CHANNEL_ID, &header(ChannelControlFlags::FLAG_FIRST, 3), b"ab"),
+        Err(ChannelError::LimitExceeded(_))
+    ));
+}
+
+#[test]
+#[allow(deprecated)]
+fn deprecated_drdynvc_channel_name_matches_static_channel_name() {
+    assert_eq!(StaticChannelName::DRDYNVC.as_str(), DRDYNVC_CHANNEL_NAME);
+}
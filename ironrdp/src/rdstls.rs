@@ -0,0 +1,277 @@
+#[cfg(test)]
+mod tests;
+
+use std::io;
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use thiserror::Error;
+
+use crate::{impl_from_error, utils, PduParsing};
+
+pub const RDSTLS_VERSION_1: u16 = 0x0001;
+
+const RDSTLS_HEADER_SIZE: usize = 6;
+const STRING_LENGTH_SIZE: usize = 2;
+const NULL_TERMINATOR_SIZE: usize = 2;
+
+const RDSTLS_TYPE_CAPABILITIES: u16 = 0x0001;
+const RDSTLS_TYPE_AUTHREQ: u16 = 0x0002;
+const RDSTLS_TYPE_AUTHRSP: u16 = 0x0004;
+
+const RDSTLS_DATA_CAPABILITIES: u16 = 0x0001;
+const RDSTLS_DATA_PASSWORD_CREDS: u16 = 0x0001;
+const RDSTLS_DATA_AUTORECONNECT_COOKIE: u16 = 0x0002;
+const RDSTLS_DATA_RESULT_CODE: u16 = 0x0001;
+
+/// The RDSTLS Capabilities PDU, sent by the server once the TLS handshake is complete.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RdstlsCapabilitiesPdu {
+    /// The versions of RDSTLS supported by the server, as a bit mask of the `RDSTLS_VERSION_*` values.
+    pub supported_versions: u16,
+}
+
+impl RdstlsCapabilitiesPdu {
+    pub const SIZE: usize = RDSTLS_HEADER_SIZE + 2;
+}
+
+impl PduParsing for RdstlsCapabilitiesPdu {
+    type Error = RdstlsError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        read_header(&mut stream, RDSTLS_TYPE_CAPABILITIES, &[RDSTLS_DATA_CAPABILITIES])?;
+        let supported_versions = stream.read_u16::<LittleEndian>()?;
+
+        Ok(Self { supported_versions })
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        write_header(&mut stream, RDSTLS_TYPE_CAPABILITIES, RDSTLS_DATA_CAPABILITIES)?;
+        stream.write_u16::<LittleEndian>(self.supported_versions)?;
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        Self::SIZE
+    }
+}
+
+/// The RDSTLS Authentication Request PDU, by which the client authenticates with the credentials
+/// given by the server it has been redirected from, or reconnects to its session.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RdstlsAuthenticationRequestPdu {
+    PasswordCredentials {
+        /// The redirection GUID of the Server Redirection PDU, passed as is.
+        redirection_guid: Vec<u8>,
+        username: String,
+        domain: String,
+        /// The password of the Server Redirection PDU, which is encrypted for the target server and passed as is.
+        password: Vec<u8>,
+    },
+    AutoReconnectCookie {
+        session_id: u32,
+        /// The ARC_SC_PRIVATE_PACKET of the session, as sent in the Save Session Info PDU.
+        auto_reconnect_cookie: Vec<u8>,
+    },
+}
+
+impl PduParsing for RdstlsAuthenticationRequestPdu {
+    type Error = RdstlsError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        let data_type = read_header(
+            &mut stream,
+            RDSTLS_TYPE_AUTHREQ,
+            &[RDSTLS_DATA_PASSWORD_CREDS, RDSTLS_DATA_AUTORECONNECT_COOKIE],
+        )?;
+
+        if data_type == RDSTLS_DATA_PASSWORD_CREDS {
+            Ok(Self::PasswordCredentials {
+                redirection_guid: read_data(&mut stream)?,
+                username: read_string(&mut stream)?,
+                domain: read_string(&mut stream)?,
+                password: read_data(&mut stream)?,
+            })
+        } else {
+            Ok(Self::AutoReconnectCookie {
+                session_id: stream.read_u32::<LittleEndian>()?,
+                auto_reconnect_cookie: read_data(&mut stream)?,
+            })
+        }
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        match self {
+            Self::PasswordCredentials {
+                redirection_guid,
+                username,
+                domain,
+                password,
+            } => {
+                write_header(&mut stream, RDSTLS_TYPE_AUTHREQ, RDSTLS_DATA_PASSWORD_CREDS)?;
+                write_data(&mut stream, redirection_guid)?;
+                write_string(&mut stream, username)?;
+                write_string(&mut stream, domain)?;
+                write_data(&mut stream, password)?;
+            }
+            Self::AutoReconnectCookie {
+                session_id,
+                auto_reconnect_cookie,
+            } => {
+                write_header(&mut stream, RDSTLS_TYPE_AUTHREQ, RDSTLS_DATA_AUTORECONNECT_COOKIE)?;
+                stream.write_u32::<LittleEndian>(*session_id)?;
+                write_data(&mut stream, auto_reconnect_cookie)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        RDSTLS_HEADER_SIZE
+            + match self {
+                Self::PasswordCredentials {
+                    redirection_guid,
+                    username,
+                    domain,
+                    password,
+                } => {
+                    STRING_LENGTH_SIZE * 4
+                        + redirection_guid.len()
+                        + string_length(username)
+                        + string_length(domain)
+                        + password.len()
+                }
+                Self::AutoReconnectCookie {
+                    auto_reconnect_cookie, ..
+                } => 4 + STRING_LENGTH_SIZE + auto_reconnect_cookie.len(),
+            }
+    }
+}
+
+/// The RDSTLS Authentication Response PDU, by which the server accepts or rejects the credentials.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RdstlsAuthenticationResponsePdu {
+    pub result_code: RdstlsResultCode,
+}
+
+impl RdstlsAuthenticationResponsePdu {
+    pub const SIZE: usize = RDSTLS_HEADER_SIZE + 4;
+}
+
+impl PduParsing for RdstlsAuthenticationResponsePdu {
+    type Error = RdstlsError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        read_header(&mut stream, RDSTLS_TYPE_AUTHRSP, &[RDSTLS_DATA_RESULT_CODE])?;
+        let result_code = RdstlsResultCode(stream.read_u32::<LittleEndian>()?);
+
+        Ok(Self { result_code })
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        write_header(&mut stream, RDSTLS_TYPE_AUTHRSP, RDSTLS_DATA_RESULT_CODE)?;
+        stream.write_u32::<LittleEndian>(self.result_code.0)?;
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        Self::SIZE
+    }
+}
+
+/// The result of the RDSTLS authentication, `RDSTLS_RESULT_SUCCESS` or one of the `RDSTLS_RESULT_*` error codes.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct RdstlsResultCode(pub u32);
+
+impl RdstlsResultCode {
+    pub const SUCCESS: Self = Self(0x0000_0000);
+    pub const ACCESS_DENIED: Self = Self(0x0000_0005);
+    pub const LOGON_FAILURE: Self = Self(0x0000_052E);
+    pub const INVALID_LOGON_HOURS: Self = Self(0x0000_0530);
+    pub const PASSWORD_EXPIRED: Self = Self(0x0000_0532);
+    pub const ACCOUNT_DISABLED: Self = Self(0x0000_0533);
+    pub const PASSWORD_MUST_CHANGE: Self = Self(0x0000_0773);
+    pub const ACCOUNT_LOCKED_OUT: Self = Self(0x0000_0775);
+}
+
+#[derive(Debug, Error)]
+pub enum RdstlsError {
+    #[error("IO error: {}", .0)]
+    IoError(#[source] io::Error),
+    #[error("Unexpected RDSTLS version: {}", .0)]
+    UnexpectedVersion(u16),
+    #[error("Unexpected RDSTLS PDU type: {}", .0)]
+    UnexpectedPduType(u16),
+    #[error("Unexpected RDSTLS data type: {}", .0)]
+    UnexpectedDataType(u16),
+    #[error("The field is too long to be encoded: {} bytes", .0)]
+    FieldTooLong(usize),
+}
+
+impl_from_error!(io::Error, RdstlsError, RdstlsError::IoError);
+
+/// Returns the data type, which is one of the expected ones.
+fn read_header(mut stream: impl io::Read, pdu_type: u16, data_types: &[u16]) -> Result<u16, RdstlsError> {
+    let version = stream.read_u16::<LittleEndian>()?;
+    if version != RDSTLS_VERSION_1 {
+        return Err(RdstlsError::UnexpectedVersion(version));
+    }
+
+    let received_pdu_type = stream.read_u16::<LittleEndian>()?;
+    if received_pdu_type != pdu_type {
+        return Err(RdstlsError::UnexpectedPduType(received_pdu_type));
+    }
+
+    let data_type = stream.read_u16::<LittleEndian>()?;
+    if !data_types.contains(&data_type) {
+        return Err(RdstlsError::UnexpectedDataType(data_type));
+    }
+
+    Ok(data_type)
+}
+
+fn write_header(mut stream: impl io::Write, pdu_type: u16, data_type: u16) -> Result<(), RdstlsError> {
+    stream.write_u16::<LittleEndian>(RDSTLS_VERSION_1)?;
+    stream.write_u16::<LittleEndian>(pdu_type)?;
+    stream.write_u16::<LittleEndian>(data_type)?;
+
+    Ok(())
+}
+
+fn read_data(mut stream: impl io::Read) -> Result<Vec<u8>, RdstlsError> {
+    let length = stream.read_u16::<LittleEndian>()?;
+    let mut data = vec![0; usize::from(length)];
+    stream.read_exact(&mut data)?;
+
+    Ok(data)
+}
+
+fn write_data(mut stream: impl io::Write, data: &[u8]) -> Result<(), RdstlsError> {
+    let length = u16::try_from(data.len()).map_err(|_| RdstlsError::FieldTooLong(data.len()))?;
+    stream.write_u16::<LittleEndian>(length)?;
+    stream.write_all(data)?;
+
+    Ok(())
+}
+
+/// Reads a null-terminated UTF-16 string, whose length includes the null terminator.
+fn read_string(stream: impl io::Read) -> Result<String, RdstlsError> {
+    let data = read_data(stream)?;
+
+    Ok(utils::bytes_to_utf16_string(&data).trim_end_matches('\0').into())
+}
+
+fn write_string(mut stream: impl io::Write, value: &str) -> Result<(), RdstlsError> {
+    let length = string_length(value);
+    let length = u16::try_from(length).map_err(|_| RdstlsError::FieldTooLong(length))?;
+    stream.write_u16::<LittleEndian>(length)?;
+    utils::write_string_with_null_terminator(&mut stream, value, utils::CharacterSet::Unicode)?;
+
+    Ok(())
+}
+
+fn string_length(value: &str) -> usize {
+    value.encode_utf16().count() * 2 + NULL_TERMINATOR_SIZE
+}
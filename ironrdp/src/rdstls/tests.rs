@@ -0,0 +1,148 @@
+use lazy_static::lazy_static;
+
+use super::*;
+
+const CAPABILITIES_BUFFER: [u8; 8] = [
+    0x01, 0x00, // version
+    0x01, 0x00, // PDU type: capabilities
+    0x01, 0x00, // data type: capabilities
+    0x01, 0x00, // supported versions
+];
+
+const AUTHENTICATION_REQUEST_PASSWORD_BUFFER: [u8; 34] = [
+    0x01, 0x00, // version
+    0x02, 0x00, // PDU type: authentication request
+    0x01, 0x00, // data type: password credentials
+    0x04, 0x00, // redirection GUID length
+    0x01, 0x02, 0x03, 0x04, // redirection GUID
+    0x06, 0x00, // user name length
+    0x75, 0x00, 0x73, 0x00, 0x00, 0x00, // "us"
+    0x04, 0x00, // domain length
+    0x64, 0x00, 0x00, 0x00, // "d"
+    0x06, 0x00, // password length
+    0xa1, 0xa2, 0xa3, 0xa4, 0xa5, 0xa6, // password
+];
+
+const AUTHENTICATION_REQUEST_COOKIE_BUFFER: [u8; 16] = [
+    0x01, 0x00, // version
+    0x02, 0x00, // PDU type: authentication request
+    0x02, 0x00, // data type: auto-reconnect cookie
+    0x2a, 0x00, 0x00, 0x00, // session ID
+    0x04, 0x00, // cookie length
+    0xc1, 0xc2, 0xc3, 0xc4, // cookie
+];
+
+const AUTHENTICATION_RESPONSE_BUFFER: [u8; 10] = [
+    0x01, 0x00, // version
+    0x04, 0x00, // PDU type: authentication response
+    0x01, 0x00, // data type: result code
+    0x2e, 0x05, 0x00, 0x00, // result code: logon failure
+];
+
+const CAPABILITIES: RdstlsCapabilitiesPdu = RdstlsCapabilitiesPdu {
+    supported_versions: RDSTLS_VERSION_1,
+};
+
+const AUTHENTICATION_RESPONSE: RdstlsAuthenticationResponsePdu = RdstlsAuthenticationResponsePdu {
+    result_code: RdstlsResultCode::LOGON_FAILURE,
+};
+
+lazy_static! {
+    static ref AUTHENTICATION_REQUEST_PASSWORD: RdstlsAuthenticationRequestPdu =
+        RdstlsAuthenticationRequestPdu::PasswordCredentials {
+            redirection_guid: vec![0x01, 0x02, 0x03, 0x04],
+            username: String::from("us"),
+            domain: String::from("d"),
+            password: vec![0xa1, 0xa2, 0xa3, 0xa4, 0xa5, 0xa6],
+        };
+    static ref AUTHENTICATION_REQUEST_COOKIE: RdstlsAuthenticationRequestPdu =
+        RdstlsAuthenticationRequestPdu::AutoReconnectCookie {
+            session_id: 42,
+            auto_reconnect_cookie: vec![0xc1, 0xc2, 0xc3, 0xc4],
+        };
+}
+
+#[test]
+fn from_buffer_correctly_parses_capabilities() {
+    assert_eq!(
+        CAPABILITIES,
+        RdstlsCapabilitiesPdu::from_buffer(CAPABILITIES_BUFFER.as_ref()).unwrap()
+    );
+}
+
+#[test]
+fn to_buffer_correctly_serializes_capabilities() {
+    let mut buffer = Vec::new();
+    CAPABILITIES.to_buffer(&mut buffer).unwrap();
+
+    assert_eq!(CAPABILITIES_BUFFER.as_ref(), buffer.as_slice());
+    assert_eq!(CAPABILITIES_BUFFER.len(), CAPABILITIES.buffer_length());
+}
+
+#[test]
+fn from_buffer_correctly_parses_password_authentication_request() {
+    assert_eq!(
+        *AUTHENTICATION_REQUEST_PASSWORD,
+        RdstlsAuthenticationRequestPdu::from_buffer(AUTHENTICATION_REQUEST_PASSWORD_BUFFER.as_ref()).unwrap()
+    );
+}
+
+#[test]
+fn to_buffer_correctly_serializes_password_authentication_request() {
+    let mut buffer = Vec::new();
+    AUTHENTICATION_REQUEST_PASSWORD.to_buffer(&mut buffer).unwrap();
+
+    assert_eq!(AUTHENTICATION_REQUEST_PASSWORD_BUFFER.as_ref(), buffer.as_slice());
+    assert_eq!(
+        AUTHENTICATION_REQUEST_PASSWORD_BUFFER.len(),
+        AUTHENTICATION_REQUEST_PASSWORD.buffer_length()
+    );
+}
+
+#[test]
+fn from_buffer_correctly_parses_cookie_authentication_request() {
+    assert_eq!(
+        *AUTHENTICATION_REQUEST_COOKIE,
+        RdstlsAuthenticationRequestPdu::from_buffer(AUTHENTICATION_REQUEST_COOKIE_BUFFER.as_ref()).unwrap()
+    );
+}
+
+#[test]
+fn to_buffer_correctly_serializes_cookie_authentication_request() {
+    let mut buffer = Vec::new();
+    AUTHENTICATION_REQUEST_COOKIE.to_buffer(&mut buffer).unwrap();
+
+    assert_eq!(AUTHENTICATION_REQUEST_COOKIE_BUFFER.as_ref(), buffer.as_slice());
+    assert_eq!(
+        AUTHENTICATION_REQUEST_COOKIE_BUFFER.len(),
+        AUTHENTICATION_REQUEST_COOKIE.buffer_length()
+    );
+}
+
+#[test]
+fn from_buffer_correctly_parses_authentication_response() {
+    assert_eq!(
+        AUTHENTICATION_RESPONSE,
+        RdstlsAuthenticationResponsePdu::from_buffer(AUTHENTICATION_RESPONSE_BUFFER.as_ref()).unwrap()
+    );
+}
+
+#[test]
+fn to_buffer_correctly_serializes_authentication_response() {
+    let mut buffer = Vec::new();
+    AUTHENTICATION_RESPONSE.to_buffer(&mut buffer).unwrap();
+
+    assert_eq!(AUTHENTICATION_RESPONSE_BUFFER.as_ref(), buffer.as_slice());
+    assert_eq!(
+        AUTHENTICATION_RESPONSE_BUFFER.len(),
+        AUTHENTICATION_RESPONSE.buffer_length()
+    );
+}
+
+#[test]
+fn from_buffer_returns_error_on_unexpected_pdu_type() {
+    assert!(matches!(
+        RdstlsCapabilitiesPdu::from_buffer(AUTHENTICATION_RESPONSE_BUFFER.as_ref()),
+        Err(RdstlsError::UnexpectedPduType(RDSTLS_TYPE_AUTHRSP))
+    ));
+}
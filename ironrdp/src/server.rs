@@ -0,0 +1,212 @@
+//! Building blocks for the server role.
+
+#[cfg(test)]
+mod test;
+
+use crate::consts::{CHANNEL_CHUNK_LENGTH, SERVER_CHANNEL_ID};
+use crate::rdp::capability_sets::{
+    Bitmap, BitmapCodecs, BitmapDrawingFlags, CmdFlags, Codec, CodecGuid, CodecProperty, FrameAcknowledge, General,
+    GeneralExtraFlags, Input, InputFlags, LargePointer, LargePointerSupportFlags, MajorPlatformType, MinorPlatformType,
+    MultifragmentUpdate, NsCodec, Order, OrderFlags, OrderSupportExFlags, Pointer, RemoteFxContainer, SurfaceCommands,
+    VirtualChannel, VirtualChannelFlags,
+};
+use crate::rdp::{CapabilitySet, DemandActive, ServerDemandActive};
+
+const SOURCE_DESCRIPTOR: &str = "RDP";
+
+const FONT_SUPPORT_FONT_LIST: u16 = 0x0001;
+const COLOR_TABLE_CACHE_SIZE: u16 = 6;
+const BITMAP_CACHE_HOST_SUPPORT_REV2: u8 = 0x01;
+
+const MULTIFRAGMENT_MAX_REQUEST_SIZE: u32 = 0x003f_0000;
+const POINTER_CACHE_SIZE: u16 = 25;
+const MAX_UNACKNOWLEDGED_FRAME_COUNT: u32 = 2;
+
+const REMOTEFX_SERVER_CONTAINER_SIZE: usize = 4;
+
+/// Capability sets advertised in the Demand Active PDU.
+///
+/// * `Minimal` - only the mandatory capability sets, the graphics are expected to be sent as
+/// uncompressed Fast-Path bitmap updates
+/// * `Windows10` - the capability sets advertised by a Windows 10 host, with the surface
+/// commands and the NSCodec and RemoteFX bitmap codecs
+/// * `GfxOnly` - the mandatory capability sets and the ones required to stream the graphics
+/// through the Graphics Pipeline dynamic virtual channel, without surface commands and bitmap codecs
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CapabilitiesPreset {
+    Minimal,
+    Windows10,
+    GfxOnly,
+}
+
+impl CapabilitiesPreset {
+    pub fn capability_sets(self, desktop_width: u16, desktop_height: u16) -> Vec<CapabilitySet> {
+        let mut capability_sets = vec![
+            create_share_capability_set(),
+            create_general_capability_set(),
+            create_bitmap_capability_set(desktop_width, desktop_height),
+            create_order_capability_set(),
+            create_pointer_capability_set(),
+            create_input_capability_set(),
+            create_virtual_channel_capability_set(),
+            create_font_capability_set(),
+        ];
+
+        match self {
+            Self::Minimal => (),
+            Self::Windows10 => capability_sets.extend([
+                create_color_cache_capability_set(),
+                create_bitmap_cache_host_support_capability_set(),
+                create_multi_fragment_update_capability_set(),
+                create_large_pointer_capability_set(),
+                create_surface_commands_capability_set(),
+                create_bitmap_codecs_capability_set(),
+                create_frame_acknowledge_capability_set(),
+            ]),
+            Self::GfxOnly => capability_sets.extend([
+                create_multi_fragment_update_capability_set(),
+                create_large_pointer_capability_set(),
+                create_frame_acknowledge_capability_set(),
+            ]),
+        }
+
+        capability_sets
+    }
+
+    pub fn server_demand_active(self, desktop_width: u16, desktop_height: u16) -> ServerDemandActive {
+        ServerDemandActive {
+            pdu: DemandActive {
+                source_descriptor: String::from(SOURCE_DESCRIPTOR),
+                capability_sets: self.capability_sets(desktop_width, desktop_height),
+            },
+        }
+    }
+}
+
+fn create_share_capability_set() -> CapabilitySet {
+    let mut buffer = SERVER_CHANNEL_ID.to_le_bytes().to_vec();
+    buffer.extend_from_slice(&[0; 2]); // padding
+
+    CapabilitySet::Share(buffer)
+}
+
+fn create_general_capability_set() -> CapabilitySet {
+    CapabilitySet::General(General {
+        major_platform_type: MajorPlatformType::Windows,
+        minor_platform_type: MinorPlatformType::WindowsNT,
+        extra_flags: GeneralExtraFlags::FASTPATH_OUTPUT_SUPPORTED
+            | GeneralExtraFlags::NO_BITMAP_COMPRESSION_HDR
+            | GeneralExtraFlags::LONG_CREDENTIALS_SUPPORTED
+            | GeneralExtraFlags::AUTORECONNECT_SUPPORTED
+            | GeneralExtraFlags::ENC_SALTED_CHECKSUM,
+        refresh_rect_support: true,
+        suppress_output_support: true,
+    })
+}
+
+fn create_bitmap_capability_set(desktop_width: u16, desktop_height: u16) -> CapabilitySet {
+    CapabilitySet::Bitmap(Bitmap {
+        pref_bits_per_pix: 32,
+        desktop_width,
+        desktop_height,
+        desktop_resize_flag: true,
+        drawing_flags: BitmapDrawingFlags::ALLOW_SKIP_ALPHA,
+    })
+}
+
+fn create_order_capability_set() -> CapabilitySet {
+    CapabilitySet::Order(Order::new(
+        OrderFlags::NEGOTIATE_ORDER_SUPPORT | OrderFlags::ZERO_BOUNDS_DELTAS_SUPPORT,
+        OrderSupportExFlags::empty(),
+        0,
+        0,
+    ))
+}
+
+fn create_pointer_capability_set() -> CapabilitySet {
+    CapabilitySet::Pointer(Pointer {
+        color_pointer_cache_size: POINTER_CACHE_SIZE,
+        pointer_cache_size: POINTER_CACHE_SIZE,
+    })
+}
+
+fn create_input_capability_set() -> CapabilitySet {
+    CapabilitySet::Input(Input {
+        input_flags: InputFlags::SCANCODES
+            | InputFlags::MOUSEX
+            | InputFlags::UNICODE
+            | InputFlags::FASTPATH_INPUT_2
+            | InputFlags::TS_MOUSE_HWHEEL,
+        keyboard_layout: 0,
+        keyboard_type: None,
+        keyboard_subtype: 0,
+        keyboard_function_key: 0,
+        keyboard_ime_filename: String::new(),
+    })
+}
+
+fn create_virtual_channel_capability_set() -> CapabilitySet {
+    CapabilitySet::VirtualChannel(VirtualChannel {
+        flags: VirtualChannelFlags::NO_COMPRESSION,
+        chunk_size: Some(CHANNEL_CHUNK_LENGTH as u32),
+    })
+}
+
+fn create_font_capability_set() -> CapabilitySet {
+    let mut buffer = FONT_SUPPORT_FONT_LIST.to_le_bytes().to_vec();
+    buffer.extend_from_slice(&[0; 2]); // padding
+
+    CapabilitySet::Font(buffer)
+}
+
+fn create_color_cache_capability_set() -> CapabilitySet {
+    let mut buffer = COLOR_TABLE_CACHE_SIZE.to_le_bytes().to_vec();
+    buffer.extend_from_slice(&[0; 2]); // padding
+
+    CapabilitySet::ColorCache(buffer)
+}
+
+fn create_bitmap_cache_host_support_capability_set() -> CapabilitySet {
+    CapabilitySet::BitmapCacheHostSupport(vec![BITMAP_CACHE_HOST_SUPPORT_REV2, 0, 0, 0])
+}
+
+fn create_multi_fragment_update_capability_set() -> CapabilitySet {
+    CapabilitySet::MultiFragmentUpdate(MultifragmentUpdate {
+        max_request_size: MULTIFRAGMENT_MAX_REQUEST_SIZE,
+    })
+}
+
+fn create_large_pointer_capability_set() -> CapabilitySet {
+    CapabilitySet::LargePointer(LargePointer {
+        flags: LargePointerSupportFlags::UP_TO_96X96_PIXELS | LargePointerSupportFlags::UP_TO_384X384_PIXELS,
+    })
+}
+
+fn create_surface_commands_capability_set() -> CapabilitySet {
+    CapabilitySet::SurfaceCommands(SurfaceCommands {
+        flags: CmdFlags::SET_SURFACE_BITS | CmdFlags::FRAME_MARKER | CmdFlags::STREAM_SURFACE_BITS,
+    })
+}
+
+fn create_bitmap_codecs_capability_set() -> CapabilitySet {
+    CapabilitySet::BitmapCodecs(BitmapCodecs(vec![
+        Codec {
+            id: CodecGuid::NsCodec.id(),
+            property: CodecProperty::NsCodec(NsCodec {
+                is_dynamic_fidelity_allowed: true,
+                is_subsampling_allowed: true,
+                color_loss_level: 3,
+            }),
+        },
+        Codec {
+            id: CodecGuid::RemoteFx.id(),
+            property: CodecProperty::RemoteFx(RemoteFxContainer::ServerContainer(REMOTEFX_SERVER_CONTAINER_SIZE)),
+        },
+    ]))
+}
+
+fn create_frame_acknowledge_capability_set() -> CapabilitySet {
+    CapabilitySet::FrameAcknowledge(FrameAcknowledge {
+        max_unacknowledged_frame_count: MAX_UNACKNOWLEDGED_FRAME_COUNT,
+    })
+}
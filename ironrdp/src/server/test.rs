@@ -0,0 +1,78 @@
+use super::*;
+use crate::PduParsing;
+
+const DESKTOP_WIDTH: u16 = 1920;
+const DESKTOP_HEIGHT: u16 = 1080;
+
+const PRESETS: [CapabilitiesPreset; 3] = [
+    CapabilitiesPreset::Minimal,
+    CapabilitiesPreset::Windows10,
+    CapabilitiesPreset::GfxOnly,
+];
+
+fn advertises(preset: CapabilitiesPreset, predicate: impl Fn(&CapabilitySet) -> bool) -> bool {
+    preset
+        .capability_sets(DESKTOP_WIDTH, DESKTOP_HEIGHT)
+        .iter()
+        .any(predicate)
+}
+
+#[test]
+fn server_demand_active_round_trips_for_every_preset() {
+    for preset in PRESETS {
+        let pdu = preset.server_demand_active(DESKTOP_WIDTH, DESKTOP_HEIGHT);
+
+        let mut buffer = Vec::new();
+        pdu.to_buffer(&mut buffer).unwrap();
+        assert_eq!(pdu.buffer_length(), buffer.len(), "{:?}", preset);
+
+        assert_eq!(
+            pdu,
+            ServerDemandActive::from_buffer(buffer.as_slice()).unwrap(),
+            "{:?}",
+            preset
+        );
+    }
+}
+
+#[test]
+fn every_preset_advertises_mandatory_capability_sets() {
+    for preset in PRESETS {
+        assert!(advertises(preset, |c| matches!(c, CapabilitySet::General(_))));
+        assert!(advertises(preset, |c| matches!(c, CapabilitySet::Order(_))));
+        assert!(advertises(preset, |c| matches!(c, CapabilitySet::Pointer(_))));
+        assert!(advertises(preset, |c| matches!(c, CapabilitySet::Input(_))));
+        assert!(advertises(preset, |c| matches!(c, CapabilitySet::VirtualChannel(_))));
+        assert!(advertises(preset, |c| matches!(c, CapabilitySet::Share(_))));
+        assert!(advertises(preset, |c| matches!(c, CapabilitySet::Font(_))));
+        assert!(advertises(preset, |c| matches!(
+            c,
+            CapabilitySet::Bitmap(Bitmap {
+                desktop_width: DESKTOP_WIDTH,
+                desktop_height: DESKTOP_HEIGHT,
+                ..
+            })
+        )));
+    }
+}
+
+#[test]
+fn gfx_only_preset_does_not_advertise_surface_commands_and_bitmap_codecs() {
+    let preset = CapabilitiesPreset::GfxOnly;
+
+    assert!(!advertises(preset, |c| matches!(c, CapabilitySet::SurfaceCommands(_))));
+    assert!(!advertises(preset, |c| matches!(c, CapabilitySet::BitmapCodecs(_))));
+    assert!(advertises(preset, |c| matches!(
+        c,
+        CapabilitySet::MultiFragmentUpdate(_)
+    )));
+}
+
+#[test]
+fn windows_10_preset_advertises_surface_commands_and_bitmap_codecs() {
+    let preset = CapabilitiesPreset::Windows10;
+
+    assert!(advertises(preset, |c| matches!(c, CapabilitySet::SurfaceCommands(_))));
+    assert!(advertises(preset, |c| matches!(c, CapabilitySet::BitmapCodecs(_))));
+    assert!(advertises(preset, |c| matches!(c, CapabilitySet::FrameAcknowledge(_))));
+}
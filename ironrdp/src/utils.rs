@@ -2,12 +2,16 @@ pub mod rc4;
 pub mod rsa;
 
 use std::cmp::{max, min};
-use std::{io, ops};
+use std::io::{self, Read};
+#[cfg(any(feature = "rfx", feature = "zgfx"))]
+use std::ops;
 
+#[cfg(any(feature = "rfx", feature = "zgfx"))]
 use bitvec::prelude::{BitSlice, Msb0};
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use num_derive::{FromPrimitive, ToPrimitive};
 use num_traits::ToPrimitive;
+use zeroize::Zeroizing;
 
 use crate::PduParsing;
 
@@ -46,11 +50,64 @@ macro_rules! impl_from_error {
     };
 }
 
+/// Generates a proptest for each PDU type, asserting that every generated PDU is decoded back
+/// to itself once encoded, and that its buffer length matches the size of the encoded PDU.
+///
+/// The PDUs are decoded with [`PduParsing::from_buffer`](crate::PduParsing::from_buffer),
+/// unless a decoding function taking the encoded buffer is given with `decode`.
+#[cfg(test)]
+#[macro_export]
+macro_rules! round_trip_proptest {
+    ($($test_name:ident: $pdu_type:ty = $strategy:expr $(, decode = $decode:expr)?;)+) => {
+        $(
+            #[test]
+            fn $test_name() {
+                #[allow(unused_imports)]
+                use $crate::PduParsing as _;
+
+                proptest::proptest!(|(pdu in $strategy)| {
+                    let mut buffer = Vec::with_capacity(pdu.buffer_length());
+                    pdu.to_buffer(&mut buffer).unwrap();
+                    proptest::prop_assert_eq!(pdu.buffer_length(), buffer.len());
+
+                    let decoded: $pdu_type =
+                        $crate::round_trip_proptest!(@decode $pdu_type, buffer.as_slice() $(, $decode)?).unwrap();
+                    proptest::prop_assert_eq!(pdu, decoded);
+                });
+            }
+        )+
+    };
+    (@decode $pdu_type:ty, $buffer:expr) => {
+        <$pdu_type as $crate::PduParsing>::from_buffer($buffer)
+    };
+    (@decode $pdu_type:ty, $buffer:expr, $decode:expr) => {
+        ($decode)($buffer)
+    };
+}
+
+/// Encodes the string to UTF-16 in a buffer allocated at once, for the secrets, such as the passwords,
+/// not to be copied by the reallocations of the buffer.
 pub fn string_to_utf16(value: &str) -> Vec<u8> {
-    value
-        .encode_utf16()
-        .flat_map(|i| i.to_le_bytes().to_vec())
-        .collect::<Vec<u8>>()
+    let mut utf16 = Vec::with_capacity(value.encode_utf16().count() * 2);
+    for code_unit in value.encode_utf16() {
+        utf16.extend_from_slice(&code_unit.to_le_bytes());
+    }
+
+    utf16
+}
+
+/// Encodes at most `max_length` UTF-16 code units of the string, without splitting a surrogate pair.
+pub fn string_to_truncated_utf16(value: &str, max_length: usize) -> Vec<u8> {
+    let mut length = 0;
+    let truncated = value
+        .chars()
+        .take_while(|c| {
+            length += c.len_utf16();
+            length <= max_length
+        })
+        .collect::<String>();
+
+    string_to_utf16(truncated.as_str())
 }
 
 pub fn bytes_to_utf16_string(mut value: &[u8]) -> String {
@@ -92,6 +149,22 @@ pub fn read_string(
     Ok(result.trim_end_matches('\0').into())
 }
 
+/// Reads `length` bytes, the buffer growing with the data actually read rather than being allocated
+/// up front for a length announced by the peer.
+pub fn read_vec(stream: impl io::Read, length: usize) -> io::Result<Vec<u8>> {
+    let mut buffer = Vec::new();
+    stream.take(length as u64).read_to_end(&mut buffer)?;
+
+    if buffer.len() < length {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            format!("expected {} bytes, got {}", length, buffer.len()),
+        ));
+    }
+
+    Ok(buffer)
+}
+
 pub fn write_string_with_null_terminator(
     mut stream: impl io::Write,
     value: &str,
@@ -99,7 +172,8 @@ pub fn write_string_with_null_terminator(
 ) -> io::Result<()> {
     match character_set {
         CharacterSet::Unicode => {
-            stream.write_all(string_to_utf16(value).as_ref())?;
+            // the string may be a secret, such as the password of the Client Info PDU
+            stream.write_all(Zeroizing::new(string_to_utf16(value)).as_ref())?;
             stream.write_u16::<LittleEndian>(0)
         }
         CharacterSet::Ansi => {
@@ -109,11 +183,13 @@ pub fn write_string_with_null_terminator(
     }
 }
 
+#[cfg(any(feature = "rfx", feature = "zgfx"))]
 pub struct Bits<'a> {
     bits_slice: &'a BitSlice<u8, Msb0>,
     remaining_bits_of_last_byte: usize,
 }
 
+#[cfg(any(feature = "rfx", feature = "zgfx"))]
 impl<'a> Bits<'a> {
     pub fn new(bits_slice: &'a BitSlice<u8, Msb0>) -> Self {
         Self {
@@ -129,11 +205,13 @@ impl<'a> Bits<'a> {
         value
     }
 
+    #[cfg(feature = "zgfx")]
     pub fn remaining_bits_of_last_byte(&self) -> usize {
         self.remaining_bits_of_last_byte
     }
 }
 
+#[cfg(any(feature = "rfx", feature = "zgfx"))]
 impl<'a> ops::Deref for Bits<'a> {
     type Target = BitSlice<u8, Msb0>;
 
@@ -142,15 +220,50 @@ impl<'a> ops::Deref for Bits<'a> {
     }
 }
 
+/// A rectangle of the virtual desktop, whose coordinates may exceed the 16-bit coordinates of most PDUs,
+/// e.g. with several monitors side by side.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Rectangle {
-    pub left: u16,
-    pub top: u16,
-    pub right: u16,
-    pub bottom: u16,
+    pub left: u32,
+    pub top: u32,
+    pub right: u32,
+    pub bottom: u32,
 }
 
 impl Rectangle {
+    /// Creates a rectangle from the 16-bit coordinates of a PDU.
+    pub fn from_u16(left: u16, top: u16, right: u16, bottom: u16) -> Self {
+        Self {
+            left: u32::from(left),
+            top: u32::from(top),
+            right: u32::from(right),
+            bottom: u32::from(bottom),
+        }
+    }
+
+    /// Returns the left, top, right and bottom coordinates for a PDU limited to 16 bits,
+    /// or `None` if any of them overflows.
+    pub fn to_u16(&self) -> Option<[u16; 4]> {
+        Some([
+            self.left.try_into().ok()?,
+            self.top.try_into().ok()?,
+            self.right.try_into().ok()?,
+            self.bottom.try_into().ok()?,
+        ])
+    }
+
+    /// Returns the part of the rectangle which can be sent in a PDU limited to 16 bits.
+    pub fn clamp_to_u16(&self) -> Self {
+        let max = u32::from(u16::MAX);
+
+        Self {
+            left: self.left.min(max),
+            top: self.top.min(max),
+            right: self.right.min(max),
+            bottom: self.bottom.min(max),
+        }
+    }
+
     pub fn empty() -> Self {
         Self {
             left: 0,
@@ -160,12 +273,14 @@ impl Rectangle {
         }
     }
 
-    pub fn width(&self) -> u16 {
-        self.right - self.left
+    /// The width, zero if the right bound is before the left one.
+    pub fn width(&self) -> u32 {
+        self.right.saturating_sub(self.left)
     }
 
-    pub fn height(&self) -> u16 {
-        self.bottom - self.top
+    /// The height, zero if the bottom bound is above the top one.
+    pub fn height(&self) -> u32 {
+        self.bottom.saturating_sub(self.top)
     }
 
     pub fn union_all(rectangles: &[Self]) -> Self {
@@ -211,19 +326,19 @@ impl PduParsing for Rectangle {
         let right = stream.read_u16::<LittleEndian>()?;
         let bottom = stream.read_u16::<LittleEndian>()?;
 
-        Ok(Self {
-            left,
-            top,
-            right,
-            bottom,
-        })
+        Ok(Self::from_u16(left, top, right, bottom))
     }
 
     fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
-        stream.write_u16::<LittleEndian>(self.left)?;
-        stream.write_u16::<LittleEndian>(self.top)?;
-        stream.write_u16::<LittleEndian>(self.right)?;
-        stream.write_u16::<LittleEndian>(self.bottom)?;
+        let coordinates = self.to_u16().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Rectangle coordinates do not fit in 16 bits",
+            )
+        })?;
+        for coordinate in coordinates {
+            stream.write_u16::<LittleEndian>(coordinate)?;
+        }
 
         Ok(())
     }
@@ -0,0 +1,169 @@
+//! Differential testing of the PDU encoding against the PDUs sent by other implementations (FreeRDP, mstsc).
+//!
+//! The tests run on the mstsc PDUs of `tests/fixtures`. The `IRONRDP_DIFFERENTIAL_FIXTURES` environment variable
+//! names another directory to run on instead, e.g. with captures holding host names and user names not to be
+//! checked in. Each fixture is named after the kind of the PDU it holds, e.g.
+//! `client-confirm-active.freerdp-2.9.hex`, and holds its bytes as hex, `#` starting a comment:
+//!
+//! * `connection-request`: X.224 Connection Request, starting with the TPKT header;
+//! * `mcs-connect-initial`: MCS Connect Initial, with the GCC Conference Create Request and the client GCC blocks;
+//! * `client-info`: Client Info PDU, starting with the basic security header;
+//! * `client-confirm-active`: Confirm Active PDU, starting with the originator ID.
+//!
+//! The PDU is decoded and encoded back by IronRDP, which has to produce the captured bytes exactly.
+//! On divergence, the fields decoded from both encodings are compared to point to the culprit.
+
+use std::fmt::Debug;
+use std::path::{Path, PathBuf};
+use std::{env, fs};
+
+use ironrdp::{ClientConfirmActive, ClientInfoPdu, ConnectInitial, PduParsing, Request};
+
+const FIXTURES_DIRECTORY: &str = "tests/fixtures";
+const FIXTURES_DIRECTORY_VARIABLE: &str = "IRONRDP_DIFFERENTIAL_FIXTURES";
+
+#[test]
+fn encoding_matches_captured_pdus() {
+    let directory = env::var_os(FIXTURES_DIRECTORY_VARIABLE)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| Path::new(env!("CARGO_MANIFEST_DIR")).join(FIXTURES_DIRECTORY));
+
+    let mut paths = fs::read_dir(&directory)
+        .expect("failed to read the fixtures directory")
+        .map(|entry| entry.expect("failed to read the fixtures directory").path())
+        .filter(|path| path.extension().and_then(|extension| extension.to_str()) == Some("hex"))
+        .collect::<Vec<_>>();
+    paths.sort();
+    assert!(!paths.is_empty(), "no fixture found in {:?}", directory);
+
+    let divergences = paths
+        .iter()
+        .filter_map(|path| {
+            check_fixture(path)
+                .err()
+                .map(|error| format!("{}: {}", path.display(), error))
+        })
+        .collect::<Vec<_>>();
+
+    assert!(
+        divergences.is_empty(),
+        "{} of {} fixtures diverge:\n\n{}",
+        divergences.len(),
+        paths.len(),
+        divergences.join("\n\n")
+    );
+}
+
+#[test]
+fn field_diff_reports_only_differing_fields() {
+    let diff = field_diff(
+        "Pdu {\n    flags: A,\n    length: 1,\n}",
+        "Pdu {\n    flags: A | B,\n    length: 1,\n}",
+    );
+
+    assert_eq!("  line 2: captured `flags: A`, encoded `flags: A | B`", diff);
+}
+
+#[test]
+fn hex_fixture_ignores_comments_and_whitespace() {
+    assert_eq!(
+        vec![0x03, 0x00, 0x00, 0x2c],
+        parse_hex("# TPKT header\n03 00\n002c # length\n").unwrap()
+    );
+}
+
+fn check_fixture(path: &Path) -> Result<(), String> {
+    let file_name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+    let kind = file_name.split('.').next().unwrap_or_default();
+    let captured = parse_hex(&fs::read_to_string(path).map_err(|e| e.to_string())?)?;
+
+    match kind {
+        "connection-request" => check_encoding::<Request>(&captured),
+        "mcs-connect-initial" => check_encoding::<ConnectInitial>(&captured),
+        "client-info" => check_encoding::<ClientInfoPdu>(&captured),
+        "client-confirm-active" => check_encoding::<ClientConfirmActive>(&captured),
+        _ => Err(format!("unknown PDU kind `{}`", kind)),
+    }
+}
+
+fn check_encoding<T>(captured: &[u8]) -> Result<(), String>
+where
+    T: PduParsing + Debug,
+    T::Error: Debug,
+{
+    let pdu = T::from_buffer(captured).map_err(|e| format!("failed to decode the captured PDU: {:?}", e))?;
+
+    let mut encoded = Vec::with_capacity(pdu.buffer_length());
+    pdu.to_buffer(&mut encoded)
+        .map_err(|e| format!("failed to encode the PDU: {:?}", e))?;
+
+    if encoded == captured {
+        return Ok(());
+    }
+
+    let mut report = match captured.iter().zip(&encoded).position(|(a, b)| a != b) {
+        Some(offset) => format!(
+            "first divergence at offset {:#x}: captured {:#04x}, encoded {:#04x}",
+            offset, captured[offset], encoded[offset]
+        ),
+        None => format!("captured {} bytes, encoded {} bytes", captured.len(), encoded.len()),
+    };
+
+    match T::from_buffer(encoded.as_slice()) {
+        Ok(decoded) => {
+            let diff = field_diff(&format!("{:#?}", pdu), &format!("{:#?}", decoded));
+            if diff.is_empty() {
+                report.push_str("\nthe fields are identical, the divergence is in the padding or the ordering");
+            } else {
+                report.push_str("\nthe fields differ once encoded:\n");
+                report.push_str(&diff);
+            }
+        }
+        Err(e) => report.push_str(&format!("\nfailed to decode the encoded PDU: {:?}", e)),
+    }
+
+    Err(report)
+}
+
+/// Compares the pretty-printed fields line by line.
+fn field_diff(captured: &str, encoded: &str) -> String {
+    let captured_lines = captured.lines().collect::<Vec<_>>();
+    let encoded_lines = encoded.lines().collect::<Vec<_>>();
+
+    (0..captured_lines.len().max(encoded_lines.len()))
+        .filter_map(|i| {
+            let captured_line = captured_lines.get(i).map_or("", |line| line.trim());
+            let encoded_line = encoded_lines.get(i).map_or("", |line| line.trim());
+
+            (captured_line != encoded_line).then(|| {
+                format!(
+                    "  line {}: captured `{}`, encoded `{}`",
+                    i + 1,
+                    captured_line.trim_end_matches(','),
+                    encoded_line.trim_end_matches(',')
+                )
+            })
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn parse_hex(fixture: &str) -> Result<Vec<u8>, String> {
+    let digits = fixture
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default())
+        .flat_map(|line| line.chars().filter(|c| !c.is_whitespace()))
+        .collect::<Vec<_>>();
+
+    if digits.len() % 2 != 0 {
+        return Err(String::from("odd number of hex digits"));
+    }
+
+    digits
+        .chunks(2)
+        .map(|pair| {
+            let byte = pair.iter().collect::<String>();
+            u8::from_str_radix(&byte, 16).map_err(|_| format!("invalid hex byte `{}`", byte))
+        })
+        .collect()
+}